@@ -0,0 +1,174 @@
+// Machine-readable batch results - `chonker8 batch --report report.json`
+//
+// One entry per input file with what happened to each page, so a CI job can
+// check a document set (everything extracted, no page below a quality bar, no
+// page that needed OCR, ...) without scraping the log. `version` changes only
+// when fields are removed or change meaning.
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::pdf_extraction::orientation::Correction;
+use crate::pdf_extraction::{ExtractionResult, QualityChecker};
+use crate::temp_files;
+
+pub const REPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PageReport {
+    /// 1-based
+    pub page: usize,
+    pub method: String,
+    pub quality: f32,
+    pub chars: usize,
+    pub duration_ms: u64,
+    /// The page this one repeats, when duplicates are collapsed
+    pub duplicate_of: Option<usize>,
+    /// Rotation and skew corrected before OCR
+    pub correction: Option<Correction>,
+}
+
+impl PageReport {
+    pub fn from_results(results: &[ExtractionResult], checker: &QualityChecker) -> Vec<Self> {
+        results
+            .iter()
+            .enumerate()
+            .map(|(index, result)| PageReport {
+                page: index + 1,
+                method: result.method.name().to_string(),
+                quality: checker.score(&result.text),
+                chars: result.text.chars().count(),
+                duration_ms: result.extraction_time_ms,
+                duplicate_of: None,
+                correction: result.correction,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub path: String,
+    /// "extracted" or "failed"
+    pub status: String,
+    /// Pages in the PDF, if it could be parsed
+    pub page_count: Option<usize>,
+    /// The engine that handled the most pages
+    pub method: Option<String>,
+    pub mean_quality: Option<f32>,
+    pub duration_ms: u64,
+    pub estimated_ms: u64,
+    pub error: Option<String>,
+    /// The page extraction stopped on, when it failed part way through
+    pub failed_page: Option<usize>,
+    pub pages: Vec<PageReport>,
+}
+
+impl FileReport {
+    pub fn extracted(path: &str, method: &str, pages: Vec<PageReport>, duration: Duration, estimate: Duration) -> Self {
+        let mean_quality = pages.iter().map(|p| p.quality).sum::<f32>() / pages.len().max(1) as f32;
+        Self {
+            path: path.to_string(),
+            status: "extracted".to_string(),
+            page_count: Some(pages.len()),
+            method: Some(method.to_string()),
+            mean_quality: Some(mean_quality),
+            duration_ms: duration.as_millis() as u64,
+            estimated_ms: estimate.as_millis() as u64,
+            error: None,
+            failed_page: None,
+            pages,
+        }
+    }
+
+    /// `pages_done` counts the pages extracted before the error; None if it never got that far
+    pub fn failed(path: &str, page_count: Option<usize>, error: &str, pages_done: Option<usize>, duration: Duration, estimate: Duration) -> Self {
+        Self {
+            path: path.to_string(),
+            status: "failed".to_string(),
+            page_count,
+            method: None,
+            mean_quality: None,
+            duration_ms: duration.as_millis() as u64,
+            estimated_ms: estimate.as_millis() as u64,
+            error: Some(error.to_string()),
+            failed_page: pages_done.map(|done| done + 1),
+            pages: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    pub version: u32,
+    pub engine: String,
+    /// RFC 3339, UTC
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub estimated_ms: u64,
+    pub files: usize,
+    pub extracted: usize,
+    pub failed: usize,
+    pub pages: usize,
+    pub results: Vec<FileReport>,
+}
+
+impl BatchReport {
+    pub fn new(engine: &str, estimate: Duration) -> Self {
+        Self {
+            version: REPORT_VERSION,
+            engine: engine.to_string(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            duration_ms: 0,
+            estimated_ms: estimate.as_millis() as u64,
+            files: 0,
+            extracted: 0,
+            failed: 0,
+            pages: 0,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, file: FileReport) {
+        self.files += 1;
+        match file.status.as_str() {
+            "extracted" => self.extracted += 1,
+            _ => self.failed += 1,
+        }
+        self.pages += file.pages.len();
+        self.results.push(file);
+    }
+
+    pub fn write(&mut self, path: &Path, elapsed: Duration) -> Result<()> {
+        self.duration_ms = elapsed.as_millis() as u64;
+        temp_files::write(path, serde_json::to_string_pretty(self)? + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_extraction::{ExtractionMethod, QualityConfig};
+
+    #[test]
+    fn test_report_counts_and_pages() {
+        let checker = QualityChecker::new(&QualityConfig::default());
+        let results = vec![
+            ExtractionResult::new("Invoice 42".to_string(), ExtractionMethod::PdfToText),
+            ExtractionResult::new(String::new(), ExtractionMethod::Tesseract),
+        ];
+        let pages = PageReport::from_results(&results, &checker);
+        assert_eq!((pages[1].page, pages[1].method.as_str(), pages[1].chars), (2, "tesseract", 0));
+
+        let mut report = BatchReport::new("auto", Duration::from_secs(3));
+        report.push(FileReport::extracted("a.pdf", "pdftotext", pages, Duration::from_millis(120), Duration::from_secs(1)));
+        report.push(FileReport::failed("b.pdf", Some(9), "tesseract not found", Some(4), Duration::ZERO, Duration::from_secs(2)));
+        assert_eq!((report.files, report.extracted, report.failed, report.pages), (2, 1, 1, 2));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["results"][1]["failed_page"], 5);
+        assert_eq!(json["results"][0]["pages"][0]["method"], "pdftotext");
+        assert!(json["results"][1]["pages"].as_array().unwrap().is_empty());
+    }
+}
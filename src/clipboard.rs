@@ -0,0 +1,73 @@
+// Clipboard - copies text with the platform's clipboard tool, or through the
+// terminal with OSC 52
+//
+// Locally, pbcopy (macOS), wl-copy (Wayland) or xclip/xsel (X11) set the
+// clipboard directly. Over ssh, or when none of them is installed, the OSC 52
+// escape sequence asks the terminal itself to do it; kitty, iTerm2, WezTerm,
+// foot and tmux (with set-clipboard on) support it.
+use anyhow::{anyhow, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Clipboard tools tried in order, with their arguments
+const TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+/// OSC 52 sequence that puts `text` on the system clipboard
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", BASE64.encode(text))
+}
+
+/// Copy `text` and return how it was done, for the status line
+pub fn copy(text: &str) -> Result<&'static str> {
+    // Over ssh a local tool would fill the remote machine's clipboard
+    if std::env::var_os("SSH_CONNECTION").is_none() {
+        for (tool, args) in TOOLS {
+            if copy_with(tool, args, text).is_ok() {
+                return Ok(tool);
+            }
+        }
+    }
+    let mut out = std::io::stdout().lock();
+    out.write_all(osc52(text).as_bytes())?;
+    out.flush()?;
+    Ok("OSC 52")
+}
+
+fn copy_with(tool: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child.stdin.take().ok_or_else(|| anyhow!("{} has no stdin", tool))?.write_all(text.as_bytes())?;
+    if !child.wait()?.success() {
+        return Err(anyhow!("{} failed", tool));
+    }
+    Ok(())
+}
+
+/// Grid rows as text, trailing blanks trimmed from every line and from the end
+pub fn grid_text(rows: &[Vec<char>]) -> String {
+    let lines: Vec<String> = rows.iter().map(|row| row.iter().collect::<String>().trim_end().to_string()).collect();
+    lines.join("\n").trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_and_grid_text() {
+        assert_eq!(osc52("Qty\n 10"), "\x1b]52;c;UXR5CiAxMA==\x07");
+
+        let rows: Vec<Vec<char>> = ["Total   ", "  42.00 ", "        "].iter().map(|l| l.chars().collect()).collect();
+        assert_eq!(grid_text(&rows), "Total\n  42.00");
+    }
+}
@@ -4,6 +4,9 @@ use lopdf::{Document, Object, Dictionary};
 use std::path::Path;
 use std::collections::BTreeMap;
 
+use crate::content_stream::{self, Operand};
+use crate::coords::{GridScale, PagePt};
+
 pub async fn extract_to_matrix(
     pdf_path: &Path,
     page_num: usize,
@@ -33,16 +36,14 @@ pub async fn extract_to_matrix(
     // Extract text with positions
     let char_positions = extract_text_with_positions(&document, page_dict)?;
     
-    // Map characters to grid positions
+    // Map characters to grid positions (PDF y grows upward, the grid's downward)
+    let scale = GridScale::fit(page_width, page_height, width, height);
     for (ch, x, y) in char_positions {
-        // Convert PDF coordinates to grid coordinates
-        // PDF coordinates are bottom-left origin, so flip y
-        let grid_x = ((x / page_width) * width as f32) as usize;
-        let grid_y = (((page_height - y) / page_height) * height as f32) as usize;
+        let cell = scale.to_cell(PagePt::from_pdf(x, y, page_height));
         
         // Clamp to grid bounds
-        if grid_x < width && grid_y < height {
-            grid[grid_y][grid_x] = ch;
+        if cell.col < width && cell.row < height {
+            grid[cell.row][cell.col] = ch;
         }
     }
     
@@ -94,58 +95,55 @@ fn extract_text_with_positions(document: &Document, page: &Dictionary) -> Result
     let contents = page.get(b"Contents")?;
     let content_data = get_content_data(document, contents)?;
     
-    // Parse content stream for text operations
+    // Start of the current line; text is shown from here
     let mut current_x = 0.0;
     let mut current_y = 0.0;
-    let mut text_matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0]; // Identity matrix
-    
-    // Simple content stream parser for text operations
-    let content_str = String::from_utf8_lossy(&content_data);
-    let lines: Vec<&str> = content_str.lines().collect();
+    let mut leading = 0.0;
     
-    for line in lines {
-        let line = line.trim();
-        
-        // Text positioning operators
-        if line.ends_with(" Td") {
-            // Text position
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 3 {
-                if let (Ok(tx), Ok(ty)) = (parts[0].parse::<f32>(), parts[1].parse::<f32>()) {
-                    current_x += tx;
-                    current_y += ty;
+    for op in content_stream::parse(&content_data) {
+        match op.operator.as_str() {
+            "BT" => {
+                current_x = 0.0;
+                current_y = 0.0;
+            }
+            "TL" => leading = op.number(0),
+            // Move to the next line, TD also setting the leading
+            "Td" | "TD" => {
+                current_x += op.number(0);
+                current_y += op.number(1);
+                if op.operator == "TD" {
+                    leading = -op.number(1);
                 }
             }
-        } else if line.ends_with(" Tm") {
             // Text matrix
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 7 {
-                for i in 0..6 {
-                    if let Ok(val) = parts[i].parse::<f32>() {
-                        text_matrix[i] = val;
-                    }
-                }
-                current_x = text_matrix[4];
-                current_y = text_matrix[5];
+            "Tm" => {
+                current_x = op.number(4);
+                current_y = op.number(5);
             }
-        } else if line.contains("Tj") {
-            // Show text string
-            if let Some(text) = extract_text_from_tj(line) {
-                // Add each character with current position
-                for (i, ch) in text.chars().enumerate() {
-                    // Simple character spacing approximation
-                    let char_x = current_x + (i as f32 * 6.0); // Approximate char width
-                    char_positions.push((ch, char_x, current_y));
+            "T*" => current_y -= leading,
+            // Show text, ' and " on the next line
+            "Tj" | "'" | "\"" => {
+                if op.operator != "Tj" {
+                    current_y -= leading;
+                }
+                if let Some(Operand::String(bytes)) = op.operands.last() {
+                    push_chars(&mut char_positions, &decode_pdf_string(bytes), current_x, current_y);
                 }
             }
-        } else if line.contains("TJ") {
             // Show text with individual glyph positioning
-            if let Some(text) = extract_text_from_tj_array(line) {
-                for (i, ch) in text.chars().enumerate() {
-                    let char_x = current_x + (i as f32 * 6.0);
-                    char_positions.push((ch, char_x, current_y));
+            "TJ" => {
+                if let Some(Operand::Array(items)) = op.operands.first() {
+                    let text: String = items
+                        .iter()
+                        .filter_map(|item| match item {
+                            Operand::String(bytes) => Some(decode_pdf_string(bytes)),
+                            _ => None,
+                        })
+                        .collect();
+                    push_chars(&mut char_positions, &text, current_x, current_y);
                 }
             }
+            _ => {}
         }
     }
     
@@ -162,6 +160,14 @@ fn extract_text_with_positions(document: &Document, page: &Dictionary) -> Result
     Ok(char_positions)
 }
 
+fn push_chars(char_positions: &mut Vec<(char, f32, f32)>, text: &str, x: f32, y: f32) {
+    for (i, ch) in text.chars().enumerate() {
+        // Simple character spacing approximation
+        let char_x = x + (i as f32 * 6.0); // Approximate char width
+        char_positions.push((ch, char_x, y));
+    }
+}
+
 // Get content data from content object
 fn get_content_data(document: &Document, contents: &Object) -> Result<Vec<u8>> {
     match contents {
@@ -184,81 +190,14 @@ fn get_content_data(document: &Document, contents: &Object) -> Result<Vec<u8>> {
     }
 }
 
-// Extract text from Tj operator
-fn extract_text_from_tj(line: &str) -> Option<String> {
-    // Find text between parentheses
-    if let Some(start) = line.find('(') {
-        if let Some(end) = line.rfind(')') {
-            if end > start {
-                let text = &line[start + 1..end];
-                // Basic PDF string decoding
-                return Some(decode_pdf_string(text));
-            }
+// Basic PDF string decoder - escapes are already resolved by the lexer; a
+// UTF-16 byte order mark means UTF-16BE, anything else is read as Latin-1
+fn decode_pdf_string(bytes: &[u8]) -> String {
+    match bytes {
+        [0xfe, 0xff, rest @ ..] => {
+            let units: Vec<u16> = rest.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+            String::from_utf16_lossy(&units)
         }
+        _ => bytes.iter().map(|&b| b as char).collect(),
     }
-    None
 }
-
-// Extract text from TJ array operator
-fn extract_text_from_tj_array(line: &str) -> Option<String> {
-    // Find text between brackets
-    if let Some(start) = line.find('[') {
-        if let Some(end) = line.rfind(']') {
-            if end > start {
-                let array_content = &line[start + 1..end];
-                let mut result = String::new();
-                
-                // Extract strings from array
-                let mut in_string = false;
-                let mut current_string = String::new();
-                
-                for ch in array_content.chars() {
-                    if ch == '(' {
-                        in_string = true;
-                        current_string.clear();
-                    } else if ch == ')' && in_string {
-                        in_string = false;
-                        result.push_str(&decode_pdf_string(&current_string));
-                    } else if in_string {
-                        current_string.push(ch);
-                    }
-                }
-                
-                if !result.is_empty() {
-                    return Some(result);
-                }
-            }
-        }
-    }
-    None
-}
-
-// Basic PDF string decoder
-fn decode_pdf_string(s: &str) -> String {
-    let mut result = String::new();
-    let mut chars = s.chars();
-    
-    while let Some(ch) = chars.next() {
-        if ch == '\\' {
-            // Handle escape sequences
-            if let Some(next) = chars.next() {
-                match next {
-                    'n' => result.push('\n'),
-                    'r' => result.push('\r'),
-                    't' => result.push('\t'),
-                    '\\' => result.push('\\'),
-                    '(' => result.push('('),
-                    ')' => result.push(')'),
-                    _ => {
-                        // Octal escape or just add the character
-                        result.push(next);
-                    }
-                }
-            }
-        } else {
-            result.push(ch);
-        }
-    }
-    
-    result
-}
\ No newline at end of file
@@ -0,0 +1,351 @@
+// PDF content-stream lexer - a page's drawing program as operators with their operands
+//
+// A content stream is postfix: operands (numbers, names, strings, arrays,
+// dictionaries) pile up until an operator consumes them. Nothing in the syntax
+// is line-based - whole pages are often one line, and strings may hold spaces,
+// brackets and newlines - so the stream is tokenized byte by byte
+// (PDF 32000-1 7.2-7.3, 8.9.7 for inline images).
+
+/// An operand, as written in the stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Number(f32),
+    Bool(bool),
+    Null,
+    /// Without the leading slash, #xx escapes decoded
+    Name(String),
+    /// Literal (...) or hex <...> string, escapes decoded
+    String(Vec<u8>),
+    Array(Vec<Operand>),
+    Dict(Vec<(String, Operand)>),
+}
+
+impl Operand {
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Operand::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// An operator and the operands before it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub operator: String,
+    pub operands: Vec<Operand>,
+}
+
+impl Operation {
+    /// Operand `i` as a number; 0 when missing or not a number
+    pub fn number(&self, i: usize) -> f32 {
+        self.operands.get(i).and_then(Operand::as_f32).unwrap_or(0.0)
+    }
+}
+
+/// Every operation in a content stream. Malformed input never fails: an
+/// unterminated token ends the stream, stray delimiters are skipped.
+pub fn parse(data: &[u8]) -> Vec<Operation> {
+    let mut lexer = Lexer { data, pos: 0 };
+    let mut operations = Vec::new();
+    let mut operands = Vec::new();
+    while let Some(token) = lexer.next_token() {
+        match token {
+            Token::Operand(operand) => operands.push(operand),
+            Token::Keyword(keyword) if keyword == "BI" => {
+                // Inline image: key/value pairs up to ID, then raw bytes up to EI
+                let mut dict = Vec::new();
+                let mut key = None;
+                while let Some(token) = lexer.next_token() {
+                    match token {
+                        Token::Keyword(k) if k == "ID" => break,
+                        Token::Operand(Operand::Name(name)) if key.is_none() => key = Some(name),
+                        Token::Operand(value) => {
+                            if let Some(key) = key.take() {
+                                dict.push((key, value));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                lexer.skip_inline_image();
+                operations.push(Operation { operator: "BI".to_string(), operands: vec![Operand::Dict(dict)] });
+                operands.clear();
+            }
+            Token::Keyword(operator) => operations.push(Operation { operator, operands: std::mem::take(&mut operands) }),
+            Token::ArrayEnd | Token::DictEnd => {}
+        }
+    }
+    operations
+}
+
+enum Token {
+    Operand(Operand),
+    Keyword(String),
+    ArrayEnd,
+    DictEnd,
+}
+
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(b, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+fn is_regular(b: u8) -> bool {
+    !is_whitespace(b) && !is_delimiter(b)
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+impl Lexer<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(b) = self.peek() {
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while self.peek().is_some_and(|b| b != b'\r' && b != b'\n') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn regular_run(&mut self) -> &[u8] {
+        let start = self.pos;
+        while self.peek().is_some_and(is_regular) {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        loop {
+            self.skip_whitespace_and_comments();
+            let b = self.peek()?;
+            let token = match b {
+                b'(' => {
+                    self.pos += 1;
+                    Token::Operand(Operand::String(self.literal_string()))
+                }
+                b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                    self.pos += 2;
+                    Token::Operand(self.dict())
+                }
+                b'<' => {
+                    self.pos += 1;
+                    Token::Operand(Operand::String(self.hex_string()))
+                }
+                b'>' if self.data.get(self.pos + 1) == Some(&b'>') => {
+                    self.pos += 2;
+                    Token::DictEnd
+                }
+                b'[' => {
+                    self.pos += 1;
+                    Token::Operand(self.array())
+                }
+                b']' => {
+                    self.pos += 1;
+                    Token::ArrayEnd
+                }
+                b'/' => {
+                    self.pos += 1;
+                    Token::Operand(Operand::Name(self.name()))
+                }
+                _ if is_regular(b) => {
+                    let word = String::from_utf8_lossy(self.regular_run()).into_owned();
+                    match word.as_str() {
+                        "true" => Token::Operand(Operand::Bool(true)),
+                        "false" => Token::Operand(Operand::Bool(false)),
+                        "null" => Token::Operand(Operand::Null),
+                        _ => match word.parse::<f32>() {
+                            Ok(n) if word.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '+' | '-' | '.')) => {
+                                Token::Operand(Operand::Number(n))
+                            }
+                            _ => Token::Keyword(word),
+                        },
+                    }
+                }
+                _ => {
+                    // A stray ) > { or }
+                    self.pos += 1;
+                    continue;
+                }
+            };
+            return Some(token);
+        }
+    }
+
+    /// After the opening paren; parens nest, backslash escapes
+    fn literal_string(&mut self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut depth = 0;
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    bytes.push(b);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    bytes.push(b);
+                }
+                b'\\' => {
+                    let Some(escaped) = self.peek() else { break };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => bytes.push(b'\n'),
+                        b'r' => bytes.push(b'\r'),
+                        b't' => bytes.push(b'\t'),
+                        b'b' => bytes.push(0x08),
+                        b'f' => bytes.push(0x0c),
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            bytes.push(value as u8);
+                        }
+                        // Line continuation
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => bytes.push(other),
+                    }
+                }
+                _ => bytes.push(b),
+            }
+        }
+        bytes
+    }
+
+    /// After the opening <; whitespace ignored, an odd final digit padded with 0
+    fn hex_string(&mut self) -> Vec<u8> {
+        let mut digits = Vec::new();
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            if b == b'>' {
+                break;
+            }
+            if let Some(d) = hex_value(b) {
+                digits.push(d);
+            }
+        }
+        digits.chunks(2).map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0)).collect()
+    }
+
+    fn name(&mut self) -> String {
+        let raw = self.regular_run().to_vec();
+        let mut bytes = Vec::with_capacity(raw.len());
+        let mut i = 0;
+        while i < raw.len() {
+            match (raw[i], raw.get(i + 1).and_then(|&b| hex_value(b)), raw.get(i + 2).and_then(|&b| hex_value(b))) {
+                (b'#', Some(high), Some(low)) => {
+                    bytes.push(high << 4 | low);
+                    i += 3;
+                }
+                (b, _, _) => {
+                    bytes.push(b);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    fn array(&mut self) -> Operand {
+        let mut items = Vec::new();
+        while let Some(token) = self.next_token() {
+            match token {
+                Token::ArrayEnd => break,
+                Token::Operand(operand) => items.push(operand),
+                // Operators don't belong in arrays; keep going as readers do
+                Token::Keyword(_) | Token::DictEnd => {}
+            }
+        }
+        Operand::Array(items)
+    }
+
+    fn dict(&mut self) -> Operand {
+        let mut entries = Vec::new();
+        let mut key = None;
+        while let Some(token) = self.next_token() {
+            match token {
+                Token::DictEnd => break,
+                Token::Operand(Operand::Name(name)) if key.is_none() => key = Some(name),
+                Token::Operand(value) => {
+                    if let Some(key) = key.take() {
+                        entries.push((key, value));
+                    }
+                }
+                Token::Keyword(_) | Token::ArrayEnd => {}
+            }
+        }
+        Operand::Dict(entries)
+    }
+
+    /// Past an inline image's data: one whitespace after ID, then up to an EI
+    /// standing alone between whitespace
+    fn skip_inline_image(&mut self) {
+        self.pos += 1;
+        while self.pos < self.data.len() {
+            let at_ei = self.data[self.pos..].starts_with(b"EI")
+                && self.pos > 0
+                && is_whitespace(self.data[self.pos - 1])
+                && self.data.get(self.pos + 2).is_none_or(|&b| is_whitespace(b) || is_delimiter(b));
+            if at_ei {
+                self.pos += 2;
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_line_stream_with_tricky_strings() {
+        let stream = b"BT/F1 12 Tf 72 700 Td(Total (net): 1\\0512)Tj[(A)-120<4243>]TJ ET \
+                       BI /W 2 /H 1 ID \x01EI\xff EI q 1 0 0 1 .5 -3 cm/Span<</MCID 3>>BDC % comment Tj\nQ";
+        let ops = parse(stream);
+        let names: Vec<&str> = ops.iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(names, vec!["BT", "Tf", "Td", "Tj", "TJ", "ET", "BI", "q", "cm", "BDC", "Q"]);
+        assert_eq!(ops[1].operands, vec![Operand::Name("F1".to_string()), Operand::Number(12.0)]);
+        assert_eq!(ops[3].operands, vec![Operand::String(b"Total (net): 1)2".to_vec())]);
+        assert_eq!(
+            ops[4].operands,
+            vec![Operand::Array(vec![Operand::String(b"A".to_vec()), Operand::Number(-120.0), Operand::String(b"BC".to_vec())])]
+        );
+        assert_eq!(ops[8].number(4), 0.5);
+        assert_eq!(ops[9].operands[1], Operand::Dict(vec![("MCID".to_string(), Operand::Number(3.0))]));
+    }
+}
@@ -0,0 +1,291 @@
+// Word-level convergence between OCR and native text
+//
+// Comparing which grid cells are blank rewards garbage placed in the right spots.
+// Instead, words are taken with their positions from both sources (pdftotext -bbox
+// for the native text layer, tesseract TSV for OCR), bucketed into horizontal
+// regions of the page, and scored per region by token F1 and word edit distance.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use crate::pdf_extraction::bbox;
+use crate::pdf_extraction::extractors::{render_page_png, OCR_DPI};
+use crate::temp_files::scratch_dir;
+
+/// Horizontal bands the page is split into for per-region scoring
+pub const REGION_BANDS: usize = 8;
+
+/// Grid for `compare --heatmap`. Coarse on purpose: OCR and the text layer place
+/// the same word a little differently, and small cells would split such pairs.
+pub const HEATMAP_ROWS: usize = 20;
+pub const HEATMAP_COLS: usize = 10;
+
+/// A normalized word with its center in page-relative coordinates (0.0-1.0)
+#[derive(Debug, Clone)]
+pub struct Word {
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct RegionScore {
+    pub band: usize,
+    pub native_words: usize,
+    pub ocr_words: usize,
+    pub token_f1: f32,
+    pub word_similarity: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct PageConvergence {
+    pub page: usize, // 0-based
+    pub native_words: usize,
+    pub ocr_words: usize,
+    /// Region scores averaged by word count
+    pub token_f1: f32,
+    pub word_similarity: f32,
+    pub regions: Vec<RegionScore>,
+}
+
+/// Compare OCR and native text for one page
+pub fn compare_page(pdf_path: &Path, page_index: usize) -> Result<PageConvergence> {
+    let native = native_words(pdf_path, page_index)?;
+    let ocr = ocr_words(pdf_path, page_index)?;
+    Ok(score_words(page_index, &native, &ocr))
+}
+
+/// Score two positioned word lists against each other
+pub fn score_words(page: usize, native: &[Word], ocr: &[Word]) -> PageConvergence {
+    let mut regions = Vec::new();
+    let (mut f1_sum, mut similarity_sum, mut weight_sum) = (0.0, 0.0, 0.0);
+
+    for band in 0..REGION_BANDS {
+        let native_tokens = tokens_in_band(native, band);
+        let ocr_tokens = tokens_in_band(ocr, band);
+        if native_tokens.is_empty() && ocr_tokens.is_empty() {
+            continue;
+        }
+
+        let region = RegionScore {
+            band,
+            native_words: native_tokens.len(),
+            ocr_words: ocr_tokens.len(),
+            token_f1: token_f1(&native_tokens, &ocr_tokens),
+            word_similarity: word_similarity(&native_tokens, &ocr_tokens),
+        };
+
+        let weight = native_tokens.len().max(ocr_tokens.len()) as f32;
+        f1_sum += region.token_f1 * weight;
+        similarity_sum += region.word_similarity * weight;
+        weight_sum += weight;
+        regions.push(region);
+    }
+
+    PageConvergence {
+        page,
+        native_words: native.len(),
+        ocr_words: ocr.len(),
+        token_f1: if weight_sum > 0.0 { f1_sum / weight_sum } else { 1.0 },
+        word_similarity: if weight_sum > 0.0 { similarity_sum / weight_sum } else { 1.0 },
+        regions,
+    }
+}
+
+/// Token F1 for each cell of a rows x cols grid over the page, top row first.
+/// None where neither source has any words.
+pub fn score_grid(native: &[Word], ocr: &[Word], rows: usize, cols: usize) -> Vec<Vec<Option<f32>>> {
+    let cell_of = |w: &Word| {
+        let row = ((w.y * rows as f32) as usize).min(rows - 1);
+        let col = ((w.x * cols as f32) as usize).min(cols - 1);
+        row * cols + col
+    };
+    let mut native_cells = vec![Vec::new(); rows * cols];
+    let mut ocr_cells = vec![Vec::new(); rows * cols];
+    for word in native {
+        native_cells[cell_of(word)].push(word.text.clone());
+    }
+    for word in ocr {
+        ocr_cells[cell_of(word)].push(word.text.clone());
+    }
+
+    (0..rows)
+        .map(|row| {
+            (0..cols)
+                .map(|col| {
+                    let (native, ocr) = (&native_cells[row * cols + col], &ocr_cells[row * cols + col]);
+                    if native.is_empty() && ocr.is_empty() {
+                        None
+                    } else {
+                        Some(token_f1(native, ocr))
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn tokens_in_band(words: &[Word], band: usize) -> Vec<String> {
+    words
+        .iter()
+        .filter(|w| ((w.y * REGION_BANDS as f32) as usize).min(REGION_BANDS - 1) == band)
+        .map(|w| w.text.clone())
+        .collect()
+}
+
+/// Bag-of-words F1 - order-insensitive, counts duplicates
+pub fn token_f1(expected: &[String], actual: &[String]) -> f32 {
+    if expected.is_empty() && actual.is_empty() {
+        return 1.0;
+    }
+    if expected.is_empty() || actual.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for token in expected {
+        *counts.entry(token.as_str()).or_insert(0) += 1;
+    }
+    let mut overlap = 0;
+    for token in actual {
+        if let Some(count) = counts.get_mut(token.as_str()) {
+            if *count > 0 {
+                *count -= 1;
+                overlap += 1;
+            }
+        }
+    }
+
+    let precision = overlap as f32 / actual.len() as f32;
+    let recall = overlap as f32 / expected.len() as f32;
+    if precision + recall == 0.0 {
+        0.0
+    } else {
+        2.0 * precision * recall / (precision + recall)
+    }
+}
+
+/// 1 - (word-level Levenshtein distance / longer sequence length)
+pub fn word_similarity(a: &[String], b: &[String]) -> f32 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f32 / longest as f32
+}
+
+fn levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, item_a) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, item_b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(item_a != item_b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Lowercase and strip surrounding punctuation so "Total:" matches "total"
+fn normalize_token(raw: &str) -> Option<String> {
+    let token = raw.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+    if token.is_empty() { None } else { Some(token) }
+}
+
+/// Native text layer words via `pdftotext -bbox`, normalized to page coordinates
+pub fn native_words(pdf_path: &Path, page_index: usize) -> Result<Vec<Word>> {
+    let page = bbox::page_words(pdf_path, page_index)?;
+    if page.width <= 0.0 || page.height <= 0.0 {
+        return Ok(Vec::new());
+    }
+
+    Ok(page
+        .words
+        .iter()
+        .filter_map(|w| {
+            Some(Word {
+                text: normalize_token(&w.text)?,
+                x: (w.x0 + w.x1) / 2.0 / page.width,
+                y: w.center_y() / page.height,
+            })
+        })
+        .collect())
+}
+
+/// OCR words via pdftoppm + `tesseract ... tsv`
+pub fn ocr_words(pdf_path: &Path, page_index: usize) -> Result<Vec<Word>> {
+    let temp_dir = scratch_dir()?;
+    let image_path = render_page_png(pdf_path, page_index, OCR_DPI, temp_dir.path())?;
+
+    let output = Command::new("tesseract")
+        .arg(&image_path)
+        .args(["stdout", "tsv"])
+        .output()
+        .map_err(|e| anyhow!("tesseract not available ({}) - install it to compare OCR text", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+
+    Ok(parse_tesseract_tsv(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_tesseract_tsv(tsv: &str) -> Vec<Word> {
+    // Columns: level page block par line word left top width height conf text
+    let rows: Vec<Vec<&str>> = tsv.lines().skip(1).map(|l| l.split('\t').collect()).collect();
+    let number = |row: &[&str], i: usize| row.get(i).and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+
+    // The level-1 row carries the page size in pixels
+    let Some(page) = rows.iter().find(|r| r.first() == Some(&"1")) else {
+        return Vec::new();
+    };
+    let (width, height) = (number(page, 8).max(1.0), number(page, 9).max(1.0));
+
+    rows.iter()
+        .filter(|r| r.first() == Some(&"5") && r.len() >= 12)
+        .filter_map(|r| {
+            let text = normalize_token(r[11])?;
+            Some(Word {
+                text,
+                x: (number(r, 6) + number(r, 8) / 2.0) / width,
+                y: (number(r, 7) + number(r, 9) / 2.0) / height,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str, y: f32) -> Vec<Word> {
+        text.split_whitespace()
+            .filter_map(normalize_token)
+            .map(|t| Word { text: t, x: 0.5, y })
+            .collect()
+    }
+
+    #[test]
+    fn test_garbage_in_right_place_scores_low() {
+        let native = words("Invoice total: $1,200.00 due March 3", 0.1);
+        let good_ocr = words("Invoice total $1,200.00 due March 3", 0.1);
+        let garbage = words("lnvo1ce tota1 $l,2OO.OO dve Marcb 8", 0.1);
+
+        let good = score_words(0, &native, &good_ocr);
+        let bad = score_words(0, &native, &garbage);
+        assert!(good.token_f1 > 0.99 && good.word_similarity > 0.99);
+        assert!(bad.token_f1 < 0.2, "garbage scored {}", bad.token_f1);
+
+        // Only the top-left cell has words, and there OCR is garbage
+        let native = vec![Word { text: "total".to_string(), x: 0.1, y: 0.1 }];
+        let ocr = vec![Word { text: "tota1".to_string(), x: 0.1, y: 0.1 }];
+        let grid = score_grid(&native, &ocr, 4, 2);
+        assert_eq!(grid[0][0], Some(0.0));
+        assert!(grid.iter().flatten().skip(1).all(|cell| cell.is_none()));
+    }
+}
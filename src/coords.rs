@@ -0,0 +1,225 @@
+// Coordinate spaces - page points, grid cells and screen cells as distinct types
+//
+// Three spaces meet in the viewer: positions on the PDF page in points, cells
+// of the extracted text grid, and cells of the terminal. Mixing them up (a
+// grid column used as a screen column, a bottom-up PDF y used as a top-down
+// one) is an easy bug, so each gets its own type and the only way between them
+// is through a GridScale or a Viewport. The rendered page image is a fourth,
+// reached from grid rows through a RowMapping.
+use std::ops::Range;
+
+/// A position on the page in PDF points, origin top-left (as pdftotext -bbox reports)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PagePt {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl PagePt {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    /// From PDF user space, where y grows upward from the bottom of the page
+    pub fn from_pdf(x: f32, y: f32, page_height: f32) -> Self {
+        Self { x, y: page_height - y }
+    }
+}
+
+/// A cell of the extracted text grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GridCell {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl GridCell {
+    pub fn new(col: usize, row: usize) -> Self {
+        Self { row, col }
+    }
+}
+
+/// A cell of the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenCell {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl ScreenCell {
+    pub fn new(x: u16, y: u16) -> Self {
+        Self { x, y }
+    }
+}
+
+/// Size of one grid cell in page points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridScale {
+    pub cell_width: f32,
+    pub cell_height: f32,
+}
+
+impl GridScale {
+    pub fn new(cell_width: f32, cell_height: f32) -> Self {
+        Self { cell_width, cell_height }
+    }
+
+    /// Scale that fits a page of the given size into a grid of `cols` x `rows`
+    pub fn fit(page_width: f32, page_height: f32, cols: usize, rows: usize) -> Self {
+        Self { cell_width: page_width / cols.max(1) as f32, cell_height: page_height / rows.max(1) as f32 }
+    }
+
+    /// The cell a point falls in; points left of or above the page land in the first column or row
+    pub fn to_cell(&self, pt: PagePt) -> GridCell {
+        GridCell {
+            col: (pt.x / self.cell_width).floor().max(0.0) as usize,
+            row: (pt.y / self.cell_height).floor().max(0.0) as usize,
+        }
+    }
+
+    /// The top-left corner of a cell
+    pub fn to_page(&self, cell: GridCell) -> PagePt {
+        PagePt { x: cell.col as f32 * self.cell_width, y: cell.row as f32 * self.cell_height }
+    }
+}
+
+/// Grid columns per inch of page width for auto-sized grids; 200 across US Letter
+pub const DEFAULT_COLS_PER_INCH: f32 = 23.5;
+/// Terminal cells are about twice as tall as they are wide
+const CELL_ASPECT: f32 = 2.0;
+
+/// Columns and rows of an extracted text grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridSize {
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl GridSize {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Self { cols, rows }
+    }
+
+    /// Grid for a page of the given size in points: `cols_per_inch` across, and as
+    /// many rows as keep each cell the shape of a terminal cell, so wide and
+    /// rotated pages aren't squeezed into a portrait grid
+    pub fn for_page(page_width: f32, page_height: f32, cols_per_inch: f32) -> Self {
+        let cols = (page_width / 72.0 * cols_per_inch).round().max(1.0);
+        let cell_height = page_width / cols * CELL_ASPECT;
+        let rows = (page_height / cell_height).round().max(1.0);
+        Self { cols: cols as usize, rows: rows as usize }
+    }
+}
+
+/// A window onto the grid: the screen rectangle it's drawn in and the grid cell
+/// shown in its top-left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub origin: ScreenCell,
+    pub scroll: GridCell,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Viewport {
+    /// Where a grid cell is drawn, if it is in view
+    pub fn to_screen(&self, cell: GridCell) -> Option<ScreenCell> {
+        let col = cell.col.checked_sub(self.scroll.col)?;
+        let row = cell.row.checked_sub(self.scroll.row)?;
+        if col >= self.width as usize || row >= self.height as usize {
+            return None;
+        }
+        Some(ScreenCell { x: self.origin.x + col as u16, y: self.origin.y + row as u16 })
+    }
+
+    /// The grid cell under a screen cell, if the screen cell is inside the viewport
+    pub fn to_grid(&self, screen: ScreenCell) -> Option<GridCell> {
+        let x = screen.x.checked_sub(self.origin.x)?;
+        let y = screen.y.checked_sub(self.origin.y)?;
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(GridCell { col: self.scroll.col + x as usize, row: self.scroll.row + y as usize })
+    }
+
+    /// The grid cell drawn at a row and column offset inside the viewport
+    pub fn grid_at(&self, dx: u16, dy: u16) -> GridCell {
+        GridCell { col: self.scroll.col + dx as usize, row: self.scroll.row + dy as usize }
+    }
+}
+
+/// Grid rows laid over the rendered page image: the grid covers the page top to
+/// bottom, so `rows` rows share the image's `image_height` pixel rows evenly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowMapping {
+    pub rows: usize,
+    pub image_height: u32,
+}
+
+impl RowMapping {
+    pub fn new(rows: usize, image_height: u32) -> Self {
+        Self { rows: rows.max(1), image_height }
+    }
+
+    /// First pixel row inside a grid row, so y_to_row gives the row back; rows
+    /// past the end land on the bottom edge
+    pub fn row_to_y(&self, row: usize) -> u32 {
+        (row.min(self.rows) as u64 * self.image_height as u64).div_ceil(self.rows as u64) as u32
+    }
+
+    /// The grid row a pixel row falls in
+    pub fn y_to_row(&self, y: u32) -> usize {
+        let row = y as u64 * self.rows as u64 / self.image_height.max(1) as u64;
+        (row as usize).min(self.rows - 1)
+    }
+
+    /// Pixel rows showing grid rows `first..first + count`, never empty
+    pub fn band(&self, first: usize, count: usize) -> Range<u32> {
+        let top = self.row_to_y(first).min(self.image_height.saturating_sub(1));
+        let bottom = self.row_to_y(first + count.max(1)).max(top + 1);
+        top..bottom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_between_spaces() {
+        let scale = GridScale::fit(612.0, 792.0, 102, 66);
+        let pt = PagePt::from_pdf(72.0, 720.0, 792.0);
+        assert_eq!(pt, PagePt::new(72.0, 72.0));
+        let cell = scale.to_cell(pt);
+        assert_eq!(cell, GridCell::new(12, 6));
+        assert_eq!(scale.to_page(cell), pt);
+
+        let view = Viewport { origin: ScreenCell::new(40, 3), scroll: GridCell::new(10, 5), width: 20, height: 10 };
+        let screen = view.to_screen(cell).unwrap();
+        assert_eq!(screen, ScreenCell::new(42, 4));
+        assert_eq!(view.to_grid(screen), Some(cell));
+        // Scrolled out of view, and outside the panel
+        assert_eq!(view.to_screen(GridCell::new(9, 6)), None);
+        assert_eq!(view.to_grid(ScreenCell::new(39, 4)), None);
+    }
+
+    #[test]
+    fn test_grid_size_follows_page_shape() {
+        assert_eq!(GridSize::for_page(612.0, 792.0, DEFAULT_COLS_PER_INCH), GridSize::new(200, 129));
+        // Landscape: more columns, fewer rows, same cell shape
+        assert_eq!(GridSize::for_page(792.0, 612.0, DEFAULT_COLS_PER_INCH), GridSize::new(259, 100));
+        assert_eq!(GridSize::for_page(0.0, 0.0, DEFAULT_COLS_PER_INCH), GridSize::new(1, 1));
+    }
+
+    #[test]
+    fn test_rows_map_to_image_bands() {
+        // 129 rows over a 1584 px render of a Letter page
+        let map = RowMapping::new(129, 1584);
+        assert_eq!(map.band(0, 129), 0..1584);
+        assert_eq!(map.band(40, 30), 492..860);
+        assert_eq!(map.y_to_row(492), 40);
+        assert_eq!(map.y_to_row(1583), 128);
+        // Scrolled past the end still shows the last pixel row
+        assert_eq!(map.band(129, 30), 1583..1584);
+    }
+}
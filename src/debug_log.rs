@@ -0,0 +1,147 @@
+// Debug logging for hot paths - tracing events, gated at compile time, sampled at run time
+//
+// The renderer used to eprintln on every frame, chunk and page, which dominates
+// runtime once stderr is a pipe. `debug_log!` is a tracing debug event, shown by
+// the CLI's -v. `debug_log_throttled!` is for per-frame and per-chunk messages: a
+// trace event (-vv) that lets one message per call site through per interval and
+// reports how many it swallowed in between. Release builds compile trace events
+// out altogether (tracing's release_max_level_debug), so those call sites cost
+// nothing there.
+//
+// While the TUI owns the screen nothing may write to stderr: init_tui() sends
+// its events to the log file the Debug screen shows, at the level CHONKER8_DEBUG
+// asks for.
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::FormatTime;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Environment variable with the TUI's log level: 1 or debug, 2 or trace
+pub const DEBUG_ENV: &str = "CHONKER8_DEBUG";
+
+/// Where the TUI logs; its Debug screen reads this file back
+pub const TUI_LOG: &str = "/tmp/chonker8_debug.log";
+
+/// How often a throttled call site may log
+pub const THROTTLE_INTERVAL: Duration = Duration::from_secs(1);
+
+static THROTTLE: Lazy<Mutex<Throttle>> = Lazy::new(|| Mutex::new(Throttle::new(THROTTLE_INTERVAL)));
+
+/// Whether a throttled message from `site` goes out now; Some carries the count suppressed since the last one
+pub fn sample(site: &'static str) -> Option<u64> {
+    THROTTLE.lock().map(|mut throttle| throttle.sample(site, Instant::now())).unwrap_or(Some(0))
+}
+
+/// The TUI's level for chonker8's own events from CHONKER8_DEBUG; info when unset
+pub fn tui_level(setting: Option<&str>) -> LevelFilter {
+    match setting.map(str::trim) {
+        None | Some("" | "0") => LevelFilter::INFO,
+        Some("2" | "trace") => LevelFilter::TRACE,
+        Some(_) => LevelFilter::DEBUG,
+    }
+}
+
+/// Local time as the Debug screen's other lines show it
+struct LocalTime;
+
+impl FormatTime for LocalTime {
+    fn format_time(&self, w: &mut Writer<'_>) -> fmt::Result {
+        write!(w, "[{}]", chrono::Local::now().format("%H:%M:%S%.3f"))
+    }
+}
+
+/// Install the TUI's logger, writing to TUI_LOG instead of the screen; call
+/// once, before the terminal is taken over. Without the file nothing is logged.
+pub fn init_tui() {
+    let Ok(file) = std::fs::OpenOptions::new().create(true).append(true).open(TUI_LOG) else { return };
+    let level = tui_level(std::env::var(DEBUG_ENV).ok().as_deref());
+    let format = tracing_subscriber::fmt::layer().with_timer(LocalTime).with_target(false).with_ansi(false).with_writer(Mutex::new(file));
+    let _ = tracing_subscriber::registry()
+        .with(format)
+        .with(Targets::new().with_target("chonker8", level).with_target("chonker8_hot", level).with_default(LevelFilter::WARN))
+        .try_init();
+}
+
+/// Per call site: when it last logged and how many messages it dropped since
+struct Throttle {
+    interval: Duration,
+    sites: HashMap<&'static str, (Instant, u64)>,
+}
+
+impl Throttle {
+    fn new(interval: Duration) -> Self {
+        Self { interval, sites: HashMap::new() }
+    }
+
+    fn sample(&mut self, site: &'static str, now: Instant) -> Option<u64> {
+        match self.sites.get_mut(site) {
+            Some((last, suppressed)) if now.duration_since(*last) < self.interval => {
+                *suppressed += 1;
+                None
+            }
+            Some((last, suppressed)) => {
+                *last = now;
+                Some(std::mem::take(suppressed))
+            }
+            None => {
+                self.sites.insert(site, (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+/// `debug_log!("TAG", "format", args..)` - a debug event reading `[TAG] message`
+#[macro_export]
+macro_rules! debug_log {
+    ($tag:literal, $($arg:tt)*) => {
+        ::tracing::debug!(concat!("[", $tag, "] {}"), format_args!($($arg)*))
+    };
+}
+
+/// Like `debug_log!`, but a trace event for messages repeated every frame or chunk:
+/// at most one per call site per interval, with a count of the ones skipped
+#[macro_export]
+macro_rules! debug_log_throttled {
+    ($tag:literal, $($arg:tt)*) => {
+        if ::tracing::enabled!(::tracing::Level::TRACE) {
+            match $crate::debug_log::sample(concat!(file!(), ":", line!())) {
+                Some(0) => ::tracing::trace!(concat!("[", $tag, "] {}"), format_args!($($arg)*)),
+                Some(skipped) => ::tracing::trace!(concat!("[", $tag, "] {} (+{} similar)"), format_args!($($arg)*), skipped),
+                None => {}
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_counts_what_it_drops() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(Duration::from_secs(1));
+        assert_eq!(throttle.sample("frame", start), Some(0));
+        assert_eq!(throttle.sample("frame", start + Duration::from_millis(10)), None);
+        assert_eq!(throttle.sample("frame", start + Duration::from_millis(20)), None);
+        // Another call site has its own budget
+        assert_eq!(throttle.sample("chunk", start + Duration::from_millis(30)), Some(0));
+        assert_eq!(throttle.sample("frame", start + Duration::from_secs(1)), Some(2));
+        assert_eq!(throttle.sample("frame", start + Duration::from_secs(3)), Some(0));
+    }
+
+    #[test]
+    fn test_tui_level_from_env() {
+        assert_eq!(tui_level(None), LevelFilter::INFO);
+        assert_eq!(tui_level(Some("0")), LevelFilter::INFO);
+        assert_eq!(tui_level(Some("1")), LevelFilter::DEBUG);
+        assert_eq!(tui_level(Some("trace")), LevelFilter::TRACE);
+    }
+}
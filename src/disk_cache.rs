@@ -0,0 +1,231 @@
+// On-disk caches - one directory per user and machine, capped in size
+//
+// Everything chonker8 can recompute goes under one cache directory: [cache] dir in
+// extraction.toml, else $CHONKER8_CACHE, else the platform cache dir
+// (~/.cache/chonker8 on Linux, ~/Library/Caches/chonker8 on macOS). Each cache is a
+// subdirectory of it. Reading an entry bumps its mtime and every write evicts the
+// least recently used files, across all of the caches, until the total is back
+// under [cache] max_size_mb. `chonker8 cache stats|prune|clear` shows, trims and
+// empties them.
+//
+// Models stay in the data directory (see pdf_extraction::models): they are
+// downloads rather than something chonker8 can rebuild, so they are never evicted.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+pub const CACHE_DIR_ENV: &str = "CHONKER8_CACHE";
+pub const DEFAULT_MAX_SIZE_MB: u64 = 1024;
+
+/// The [cache] table of extraction.toml
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CacheConfig {
+    /// Cache directory, overriding $CHONKER8_CACHE and the platform cache dir
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+    /// Total size of every cache together before the oldest entries go
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u64,
+}
+
+fn default_max_size_mb() -> u64 { DEFAULT_MAX_SIZE_MB }
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { dir: None, max_size_mb: DEFAULT_MAX_SIZE_MB }
+    }
+}
+
+static CONFIGURED: OnceLock<CacheConfig> = OnceLock::new();
+
+/// Use this config for the rest of the process; the first call wins
+pub fn configure(config: CacheConfig) {
+    let _ = CONFIGURED.set(config);
+}
+
+/// Where every cache lives
+pub fn cache_root() -> PathBuf {
+    if let Some(dir) = CONFIGURED.get().and_then(|config| config.dir.clone()) {
+        return dir;
+    }
+    if let Some(dir) = std::env::var_os(CACHE_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("chonker8")
+}
+
+fn max_bytes() -> u64 {
+    CONFIGURED.get().map_or(DEFAULT_MAX_SIZE_MB, |config| config.max_size_mb) * 1024 * 1024
+}
+
+/// One named cache: a directory of files keyed by name
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    /// What eviction counts and trims - the cache root, or `dir` itself when it was placed elsewhere
+    root: PathBuf,
+}
+
+impl DiskCache {
+    /// The `name` subdirectory of the cache root
+    pub fn new(name: &str) -> Self {
+        let root = cache_root();
+        Self { dir: root.join(name), root }
+    }
+
+    /// A cache kept in a directory of its own, evicted on its own
+    pub fn at(dir: PathBuf) -> Self {
+        Self { root: dir.clone(), dir }
+    }
+
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+
+    /// The entry's bytes, marking it used
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let path = self.path(key);
+        let bytes = fs::read(&path).ok()?;
+        // Best effort - an entry that can't be touched is just evicted sooner
+        if let Ok(file) = File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(bytes)
+    }
+
+    /// Store an entry, then trim the caches back under the size cap
+    pub fn put(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Written aside and renamed in, so a reader never sees half an entry
+        let path = self.path(key);
+        let partial = self.dir.join(format!(".{}.partial", key));
+        fs::write(&partial, bytes)?;
+        fs::rename(&partial, &path)?;
+        let removed = evict(&self.root, max_bytes())?;
+        if removed > 0 {
+            tracing::debug!("[CACHE] Evicted {} entries from {}", removed, self.root.display());
+        }
+        Ok(())
+    }
+}
+
+/// Files and bytes in one cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub name: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Every file under `dir` with its size and last use
+fn entries(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let mut found = Vec::new();
+    let Ok(read) = fs::read_dir(dir) else { return found };
+    for entry in read.flatten() {
+        let Ok(meta) = entry.metadata() else { continue };
+        if meta.is_dir() {
+            found.extend(entries(&entry.path()));
+        } else {
+            found.push((entry.path(), meta.len(), meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)));
+        }
+    }
+    found
+}
+
+/// Remove least recently used files under `root` until they total at most
+/// `max_bytes`. Returns how many were removed.
+pub fn evict(root: &Path, max_bytes: u64) -> Result<usize> {
+    let mut files = entries(root);
+    let mut total: u64 = files.iter().map(|(_, bytes, _)| bytes).sum();
+    files.sort_by_key(|(_, _, used)| *used);
+    let mut removed = 0;
+    for (path, bytes, _) in files {
+        if total <= max_bytes {
+            break;
+        }
+        fs::remove_file(&path)?;
+        total -= bytes;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Remove files under `root` unused for longer than `older_than`, then evict down
+/// to `max_bytes`. Returns the files and bytes removed.
+pub fn prune(root: &Path, max_bytes: u64, older_than: Option<Duration>) -> Result<(usize, u64)> {
+    let total = |files: &[(PathBuf, u64, SystemTime)]| files.iter().map(|(_, bytes, _)| bytes).sum::<u64>();
+    let before = entries(root);
+    if let Some(cutoff) = older_than.and_then(|age| SystemTime::now().checked_sub(age)) {
+        for (path, _, used) in &before {
+            if *used < cutoff {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+    evict(root, max_bytes)?;
+    let after = entries(root);
+    Ok((before.len().saturating_sub(after.len()), total(&before).saturating_sub(total(&after))))
+}
+
+/// Usage of each cache under `root`, by name
+pub fn stats(root: &Path) -> Result<Vec<CacheUsage>> {
+    let mut usage = Vec::new();
+    let Ok(read) = fs::read_dir(root) else { return Ok(usage) };
+    for entry in read.flatten() {
+        if !entry.path().is_dir() {
+            continue;
+        }
+        let files = entries(&entry.path());
+        usage.push(CacheUsage {
+            name: entry.file_name().to_string_lossy().to_string(),
+            files: files.len(),
+            bytes: files.iter().map(|(_, bytes, _)| bytes).sum(),
+        });
+    }
+    usage.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(usage)
+}
+
+/// Empty one cache, or all of them. Returns the bytes freed.
+pub fn clear(root: &Path, name: Option<&str>) -> Result<u64> {
+    let dir = name.map_or_else(|| root.to_path_buf(), |name| root.join(name));
+    let freed = entries(&dir).iter().map(|(_, bytes, _)| bytes).sum();
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_least_recently_used_evicted_first() {
+        let temp = TempDir::new().unwrap();
+        let cache = DiskCache::at(temp.path().join("cloud-ocr"));
+        for key in ["a", "b", "c"] {
+            fs::create_dir_all(&cache.dir).unwrap();
+            fs::write(cache.path(key), [0u8; 100]).unwrap();
+        }
+        // a used long ago, b longer, c just now
+        let ago = |secs| SystemTime::now() - Duration::from_secs(secs);
+        File::options().write(true).open(cache.path("a")).unwrap().set_modified(ago(60)).unwrap();
+        File::options().write(true).open(cache.path("b")).unwrap().set_modified(ago(120)).unwrap();
+        assert_eq!(cache.get("a").unwrap().len(), 100);
+
+        assert_eq!(evict(&cache.root, 200).unwrap(), 1);
+        assert!(!cache.path("b").exists());
+        assert!(cache.path("a").exists() && cache.path("c").exists());
+
+        let usage = stats(temp.path()).unwrap();
+        assert_eq!(usage, vec![CacheUsage { name: "cloud-ocr".to_string(), files: 2, bytes: 200 }]);
+        assert_eq!(clear(temp.path(), Some("cloud-ocr")).unwrap(), 200);
+        assert!(stats(temp.path()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+// Drop folder - notices PDFs that appear or change under a directory
+//
+// Copying a file in shows up as a burst of create/modify events, and a large file
+// can take a while to finish writing. A path is only handed out once it has gone
+// `settle` without a new event and its size has stopped changing, so `watch`
+// never extracts a half-copied PDF.
+use anyhow::Result;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use crate::ingest::is_pdf;
+
+pub struct DropFolder {
+    // Kept alive for as long as the folder is watched
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    pending: Pending,
+}
+
+impl DropFolder {
+    /// Start watching `dir` and everything below it
+    pub fn new(dir: &Path, settle: Duration) -> Result<Self> {
+        let (tx, events) = channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+        Ok(Self { _watcher: watcher, events, pending: Pending::new(settle) })
+    }
+
+    /// PDFs that changed and have since settled, in path order
+    pub fn ready(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        while let Ok(event) = self.events.try_recv() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!("[WATCH] Watcher error: {}", e);
+                    continue;
+                }
+            };
+            if !(event.kind.is_create() || event.kind.is_modify()) {
+                continue;
+            }
+            for path in event.paths.into_iter().filter(|p| is_pdf(p)) {
+                let size = file_size(&path);
+                self.pending.touch(path, now, size);
+            }
+        }
+        self.pending.ready(now, file_size)
+    }
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).ok().filter(|m| m.is_file()).map(|m| m.len())
+}
+
+/// Paths waiting to settle: when they last changed and how big they were then
+struct Pending {
+    settle: Duration,
+    paths: HashMap<PathBuf, (Instant, Option<u64>)>,
+}
+
+impl Pending {
+    fn new(settle: Duration) -> Self {
+        Self { settle, paths: HashMap::new() }
+    }
+
+    fn touch(&mut self, path: PathBuf, now: Instant, size: Option<u64>) {
+        self.paths.insert(path, (now, size));
+    }
+
+    /// Take the paths that have been quiet for `settle`. One that grew in the
+    /// meantime waits another round; one that disappeared is dropped.
+    fn ready(&mut self, now: Instant, size_of: impl Fn(&Path) -> Option<u64>) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        self.paths.retain(|path, (changed, size)| {
+            if now.duration_since(*changed) < self.settle {
+                return true;
+            }
+            match size_of(path) {
+                None => false,
+                Some(current) if Some(current) == *size => {
+                    ready.push(path.clone());
+                    false
+                }
+                current => {
+                    *changed = now;
+                    *size = current;
+                    true
+                }
+            }
+        });
+        ready.sort();
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_wait_until_quiet_and_stable() {
+        let settle = Duration::from_secs(2);
+        let start = Instant::now();
+        let mut pending = Pending::new(settle);
+        pending.touch(PathBuf::from("done.pdf"), start, Some(100));
+        pending.touch(PathBuf::from("copying.pdf"), start, Some(10));
+        pending.touch(PathBuf::from("gone.pdf"), start, Some(10));
+
+        let size_of = |path: &Path| match path.to_str() {
+            Some("done.pdf") => Some(100),
+            Some("copying.pdf") => Some(50),
+            _ => None,
+        };
+        assert!(pending.ready(start + Duration::from_secs(1), size_of).is_empty());
+        assert_eq!(pending.ready(start + settle, size_of), vec![PathBuf::from("done.pdf")]);
+        // Still growing at the last check, so it needs another quiet period
+        assert!(pending.ready(start + settle + Duration::from_secs(1), size_of).is_empty());
+        assert_eq!(pending.ready(start + settle * 2, size_of), vec![PathBuf::from("copying.pdf")]);
+        assert!(pending.paths.is_empty());
+    }
+}
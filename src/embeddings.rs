@@ -0,0 +1,140 @@
+// Sentence embeddings for semantic search - an ONNX sentence-transformer via ort
+//
+// Optional: when the model and its tokenizer.json are in the models directory,
+// every stored page gets a vector and `search --semantic` ranks pages by cosine
+// similarity to the query. Without them, semantic search falls back to keyword
+// search. Models exported with a pooled `sentence_embedding` output are used as
+// is; otherwise token states are mean-pooled over the attention mask. Vectors
+// are L2-normalized, so cosine similarity is a dot product.
+use anyhow::{anyhow, Result};
+use ort::session::{Session, SessionInputValue};
+use ort::value::Tensor;
+use std::path::{Path, PathBuf};
+use tokenizers::{Tokenizer, TruncationParams};
+
+use crate::pdf_extraction::models;
+
+/// Under the models directory (see pdf_extraction::models)
+pub const EMBEDDING_MODEL: &str = "sentence-embedding.onnx";
+pub const EMBEDDING_TOKENIZER: &str = "sentence-embedding-tokenizer.json";
+
+/// Tokens per page fed to the model; sentence-transformers are trained on short inputs
+const MAX_TOKENS: usize = 256;
+
+pub struct Embedder {
+    session: Session,
+    tokenizer: Tokenizer,
+    model_id: String,
+}
+
+impl std::fmt::Debug for Embedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Embedder").field("model", &self.model_id).finish()
+    }
+}
+
+impl Embedder {
+    /// The installed model, or None if there isn't one
+    pub fn load_default() -> Result<Option<Self>> {
+        let (model, tokenizer) = (models::locate(EMBEDDING_MODEL), models::locate(EMBEDDING_TOKENIZER));
+        if !model.exists() || !tokenizer.exists() {
+            return Ok(None);
+        }
+        Self::load(&model, &tokenizer).map(Some)
+    }
+
+    pub fn load(model: &Path, tokenizer: &Path) -> Result<Self> {
+        let _ = ort::init();
+        let session = Session::builder()?
+            .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
+            .with_intra_threads(4)?
+            .commit_from_file(model)?;
+        let mut tokenizer = Tokenizer::from_file(tokenizer).map_err(|e| anyhow!("Loading {}: {}", tokenizer.display(), e))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams { max_length: MAX_TOKENS, ..Default::default() }))
+            .map_err(|e| anyhow!("Tokenizer truncation: {}", e))?;
+        let model_id = PathBuf::from(model).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        Ok(Self { session, tokenizer, model_id })
+    }
+
+    /// Vectors from different models can't be compared; stored vectors are tagged with this
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    pub fn embed(&mut self, text: &str) -> Result<Vec<f32>> {
+        let encoding = self.tokenizer.encode(text, true).map_err(|e| anyhow!("Tokenizing: {}", e))?;
+        let ids: Vec<i64> = encoding.get_ids().iter().map(|&id| id as i64).collect();
+        let mask: Vec<i64> = encoding.get_attention_mask().iter().map(|&m| m as i64).collect();
+        let types: Vec<i64> = encoding.get_type_ids().iter().map(|&t| t as i64).collect();
+        let tokens = ids.len();
+
+        // BERT-style models take token_type_ids, most others don't
+        let mut inputs: Vec<(String, SessionInputValue)> = Vec::new();
+        for input in &self.session.inputs {
+            let values = match input.name.as_str() {
+                "input_ids" => ids.clone(),
+                "attention_mask" => mask.clone(),
+                "token_type_ids" => types.clone(),
+                other => return Err(anyhow!("Embedding model wants an unsupported input '{}'", other)),
+            };
+            inputs.push((input.name.clone(), Tensor::from_array(([1usize, tokens], values.into_boxed_slice()))?.into()));
+        }
+
+        let pooled_output = self.session.outputs.iter().position(|o| o.name == "sentence_embedding");
+        let outputs = self.session.run(inputs)?;
+        let (shape, data) = outputs[pooled_output.unwrap_or(0)].try_extract_tensor::<f32>()?;
+        let vector = match shape.len() {
+            2 => data.to_vec(),
+            3 => mean_pool(data, &mask, shape[2] as usize),
+            _ => return Err(anyhow!("Unexpected embedding output shape {:?}", shape)),
+        };
+        Ok(normalize(vector))
+    }
+}
+
+/// Average of the token vectors the attention mask keeps
+fn mean_pool(states: &[f32], mask: &[i64], dim: usize) -> Vec<f32> {
+    let mut sum = vec![0.0; dim];
+    let mut kept = 0.0;
+    for (token, &keep) in states.chunks_exact(dim).zip(mask) {
+        if keep != 0 {
+            sum.iter_mut().zip(token).for_each(|(s, v)| *s += v);
+            kept += 1.0;
+        }
+    }
+    if kept > 0.0 {
+        sum.iter_mut().for_each(|s| *s /= kept);
+    }
+    sum
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// Cosine similarity of two normalized vectors
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pooling_skips_padding() {
+        // Two real tokens and one padding token, dim 2
+        let states = [1.0, 0.0, 3.0, 4.0, 100.0, 100.0];
+        let pooled = mean_pool(&states, &[1, 1, 0], 2);
+        assert_eq!(pooled, vec![2.0, 2.0]);
+
+        let unit = normalize(pooled);
+        assert!((cosine(&unit, &unit) - 1.0).abs() < 1e-6);
+        assert!(cosine(&unit, &normalize(vec![1.0, -1.0])).abs() < 1e-6);
+    }
+}
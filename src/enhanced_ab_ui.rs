@@ -6,45 +6,17 @@ use anyhow::Result;
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
     execute,
-    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    style::{Print, ResetColor, SetBackgroundColor, SetForegroundColor},
     terminal::{self, Clear, ClearType},
 };
 use std::io::{stdout, Write};
 use std::path::PathBuf;
 use image::{DynamicImage, ImageBuffer, Rgba};
 use crate::kitty_protocol::KittyProtocol;
-
-// Dark theme colors optimized for PDF comparison
-pub struct DarkTheme {
-    pub bg_primary: Color,      // Main background
-    pub bg_secondary: Color,     // Panel backgrounds
-    pub border: Color,           // Dividers and borders
-    pub text_primary: Color,     // Main text
-    pub text_secondary: Color,   // Labels and headers
-    pub highlight: Color,        // Selected/active items
-    pub success: Color,          // Good extraction
-    pub warning: Color,          // Medium confidence
-    pub error: Color,            // Low confidence
-}
-
-impl Default for DarkTheme {
-    fn default() -> Self {
-        Self {
-            bg_primary: Color::Rgb { r: 15, g: 15, b: 20 },
-            bg_secondary: Color::Rgb { r: 25, g: 25, b: 35 },
-            border: Color::Rgb { r: 50, g: 50, b: 70 },
-            text_primary: Color::Rgb { r: 220, g: 220, b: 230 },
-            text_secondary: Color::Rgb { r: 150, g: 150, b: 170 },
-            highlight: Color::Rgb { r: 100, g: 150, b: 255 },
-            success: Color::Rgb { r: 100, g: 255, b: 150 },
-            warning: Color::Rgb { r: 255, g: 200, b: 100 },
-            error: Color::Rgb { r: 255, g: 100, b: 100 },
-        }
-    }
-}
+use crate::theme::{self, ChonkerTheme};
 
 pub struct EnhancedABComparison {
-    theme: DarkTheme,
+    theme: ChonkerTheme,
     pdf_image: Option<DynamicImage>,
     extracted_text: Vec<String>,
     extraction_layout: Vec<Vec<char>>, // Raw pdftotext layout
@@ -65,7 +37,7 @@ pub struct EnhancedABComparison {
 impl EnhancedABComparison {
     pub fn new() -> Self {
         Self {
-            theme: DarkTheme::default(),
+            theme: theme::current(),
             pdf_image: None,
             extracted_text: Vec::new(),
             extraction_layout: Vec::new(),
@@ -128,11 +100,12 @@ impl EnhancedABComparison {
     /// Render the perfect 50/50 split comparison view
     pub fn render_split_view(&mut self) -> Result<()> {
         let (width, height) = terminal::size()?;
+        self.theme = theme::current();
         
-        // Clear with dark background
+        // Clear with the theme's background
         execute!(
             stdout(),
-            SetBackgroundColor(self.theme.bg_primary),
+            SetBackgroundColor(self.theme.background),
             Clear(ClearType::All),
             MoveTo(0, 0),
             Hide
@@ -162,7 +135,7 @@ impl EnhancedABComparison {
         execute!(
             stdout(),
             MoveTo(x, y),
-            SetBackgroundColor(self.theme.bg_secondary),
+            SetBackgroundColor(self.theme.title_bg),
             SetForegroundColor(self.theme.text_secondary),
             Print(format!("┌─ 📄 PDF Page {}/{} ", self.current_page, self.total_pages)),
             Print("─".repeat((width as usize).saturating_sub(20))),
@@ -174,7 +147,7 @@ impl EnhancedABComparison {
             execute!(
                 stdout(),
                 MoveTo(x, y + row),
-                SetBackgroundColor(self.theme.bg_secondary),
+                SetBackgroundColor(self.theme.title_bg),
                 Print(" ".repeat(width as usize))
             )?;
         }
@@ -233,8 +206,8 @@ impl EnhancedABComparison {
         execute!(
             stdout(),
             MoveTo(x, y),
-            SetBackgroundColor(self.theme.bg_secondary),
-            SetForegroundColor(self.theme.highlight),
+            SetBackgroundColor(self.theme.title_bg),
+            SetForegroundColor(self.theme.accent),
             Print(&header),
             SetForegroundColor(self.theme.text_secondary),
             Print("─".repeat((width as usize).saturating_sub(header.len() + 1))),
@@ -263,7 +236,7 @@ impl EnhancedABComparison {
                 } else if self.has_vision_annotation(line_idx) {
                     self.theme.warning // Yellow for vision suggestions
                 } else {
-                    self.theme.text_primary // Normal text
+                    self.theme.text // Normal text
                 };
                 
                 execute!(
@@ -281,9 +254,11 @@ impl EnhancedABComparison {
                         if self.edit_mode && line_idx == self.cursor_row && col == self.cursor_col {
                             execute!(
                                 stdout(),
-                                SetBackgroundColor(self.theme.highlight),
+                                SetBackgroundColor(self.theme.cursor),
+                                SetForegroundColor(self.theme.on_highlight),
                                 Print(ch),
-                                SetBackgroundColor(self.theme.bg_secondary)
+                                SetForegroundColor(line_color),
+                                SetBackgroundColor(self.theme.title_bg)
                             )?;
                         } else {
                             execute!(stdout(), Print(ch))?;
@@ -302,7 +277,7 @@ impl EnhancedABComparison {
     }
     
     fn render_divider(&self, x: u16, height: u16) -> Result<()> {
-        execute!(stdout(), SetForegroundColor(self.theme.border))?;
+        execute!(stdout(), SetForegroundColor(self.theme.text_dim))?;
         
         for y in 0..height {
             execute!(
@@ -341,7 +316,7 @@ impl EnhancedABComparison {
         execute!(
             stdout(),
             MoveTo(0, height - 1),
-            SetBackgroundColor(self.theme.bg_secondary),
+            SetBackgroundColor(self.theme.title_bg),
             SetForegroundColor(self.theme.text_secondary),
             Print(format!("{:width$}", status, width = width as usize)),
             ResetColor
@@ -445,7 +420,6 @@ impl EnhancedABComparison {
             .collect::<Vec<_>>()
             .join("\n");
         
-        std::fs::write(path, content)?;
-        Ok(())
+        crate::temp_files::write(path, content)
     }
 }
\ No newline at end of file
@@ -0,0 +1,247 @@
+// Entity extraction - `chonker8 entities`
+//
+// Pulls structured values out of stored text with the same kind of regexes that
+// name files (naming.rs): dates, money amounts, email addresses, phone numbers,
+// IDs (invoice/order/account references, labelled customer or tax IDs, IBANs)
+// and street addresses. Each is stored with the text as written and a
+// normalized value, so "March 14, 2024" and "14.03.2024" are both 2024-03-14
+// and "$1,234.5" is "1234.50 USD". Where matches overlap the earlier kind in
+// that list wins - a date is never also read as a phone number.
+//
+// Search takes `type:<kind>` terms to keep only documents with that kind of
+// entity, e.g. `chonker8 search "type:amount overdue"`.
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::naming::{find_dates, REFERENCE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntityKind {
+    Date,
+    Amount,
+    Email,
+    Phone,
+    Id,
+    Address,
+}
+
+impl EntityKind {
+    pub const ALL: [EntityKind; 6] = [Self::Date, Self::Amount, Self::Email, Self::Phone, Self::Id, Self::Address];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Date => "date",
+            Self::Amount => "amount",
+            Self::Email => "email",
+            Self::Phone => "phone",
+            Self::Id => "id",
+            Self::Address => "address",
+        }
+    }
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EntityKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_lowercase();
+        Self::ALL.into_iter().find(|kind| kind.name() == name).ok_or_else(|| {
+            let known: Vec<&str> = Self::ALL.iter().map(|kind| kind.name()).collect();
+            anyhow!("Unknown entity type '{}' (known: {})", s, known.join(", "))
+        })
+    }
+}
+
+/// One entity found in a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub kind: EntityKind,
+    /// Normalized: YYYY-MM-DD, "1234.50 USD", lowercase email, +digits, ...
+    pub value: String,
+    /// As written in the document
+    pub text: String,
+    pub page: usize, // 1-based
+}
+
+const CURRENCY_CODES: &str = "USD|EUR|GBP|CHF|JPY|CAD|AUD";
+pub(crate) const NUMBER: &str = r"\d{1,3}(?:[,.']\d{3})+(?:[.,]\d{1,2})?|\d+(?:[.,]\d{1,2})?";
+
+static EMAIL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b").unwrap());
+static AMOUNT_BEFORE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"(?:([$€£¥])|\b({codes})\b)[ \t]?({number})\b", codes = CURRENCY_CODES, number = NUMBER)).unwrap()
+});
+static AMOUNT_AFTER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"\b({number})[ \t]?(?:([€£])|({codes})\b)", codes = CURRENCY_CODES, number = NUMBER)).unwrap()
+});
+static LABELLED_ID: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:vat|tax|ein|customer|client|policy|member|patient|employee|case)\s*(?:id|no\.?|number|#)\s*[:#]?\s*([A-Z0-9][A-Z0-9/-]{2,19})\b").unwrap()
+});
+static IBAN: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){3,7}(?: ?[A-Z0-9]{1,3})?\b").unwrap());
+static LABELLED_PHONE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:tel|telephone|phone|fax|mobile|cell)\b\.?(?:[ \t]*(?:no\.?|number))?[ \t]*[:#]?[ \t]*(\+?[\d(][\d ()./-]{5,}\d)").unwrap()
+});
+static INTERNATIONAL_PHONE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^|[^\w+])(\+\d[\d ()./-]{6,}\d)").unwrap());
+static LOCAL_PHONE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\(\d{3}\)[ \t]?\d{3}[-. \t]\d{4}\b|\b\d{3}[-.]\d{3}[-.]\d{4}\b)").unwrap());
+static STREET_ADDRESS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"\b\d{1,5}[A-Za-z]?[ \t]+(?:[A-Z][A-Za-z'.-]*[ \t]+){1,4}(?:Street|St|Avenue|Ave|Road|Rd|Boulevard|Blvd|Lane|Ln|Drive|Dr|Way|Court|Ct|Place|Pl|Square|Sq|Parkway|Pkwy|Terrace)\b\.?(?:,?[ \t]*(?:Suite|Ste|Apt|Unit)\.?[ \t]*#?\w+)?(?:,[ \t]*[A-Z][A-Za-z.]*(?:[ \t][A-Z][A-Za-z.]*)*,?[ \t]+[A-Z]{2}[ \t]+\d{5}(?:-\d{4})?)?",
+    )
+    .unwrap()
+});
+static STRASSE_ADDRESS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b[A-ZÄÖÜ][\w-]*(?:straße|strasse|str\.|weg|platz|allee|gasse|ring|damm)[ \t]+\d{1,4}[a-z]?\b(?:,?[ \t]+\d{5}[ \t]+[A-ZÄÖÜ][\w-]+)?").unwrap()
+});
+
+fn currency(symbol_or_code: &str) -> String {
+    match symbol_or_code {
+        "$" => "USD".to_string(),
+        "€" => "EUR".to_string(),
+        "£" => "GBP".to_string(),
+        "¥" => "JPY".to_string(),
+        code => code.to_uppercase(),
+    }
+}
+
+/// "1,234.5" or "1.234,50" as "1234.50": a separator followed by one or two
+/// digits at the end is the decimal point, every other one groups thousands
+pub(crate) fn decimal(number: &str) -> String {
+    let split = number.rfind([',', '.']).filter(|&at| (2..=3).contains(&(number.len() - at)));
+    let (whole, fraction) = match split {
+        Some(at) => (&number[..at], &number[at + 1..]),
+        None => (number, ""),
+    };
+    let whole: String = whole.chars().filter(char::is_ascii_digit).collect();
+    let whole = whole.trim_start_matches('0');
+    format!("{}.{:0<2}", if whole.is_empty() { "0" } else { whole }, fraction)
+}
+
+/// Digits only, with a leading + for an international number (written +49 or 0049)
+fn phone_number(text: &str) -> Option<String> {
+    let digits: String = text.chars().filter(char::is_ascii_digit).collect();
+    let (plus, digits) = match digits.strip_prefix("00") {
+        Some(rest) if !text.starts_with('+') => ("+", rest.to_string()),
+        _ => (if text.starts_with('+') { "+" } else { "" }, digits),
+    };
+    (7..=15).contains(&digits.len()).then(|| format!("{}{}", plus, digits))
+}
+
+fn group_span(captures: &Captures, group: usize) -> (Range<usize>, String) {
+    let found = captures.get(group).or_else(|| captures.get(0)).expect("group 0 always matches");
+    (found.range(), found.as_str().to_string())
+}
+
+/// Every candidate in one page's text as (span, kind, value, text as written), by kind in priority order
+fn candidates(text: &str) -> Vec<(Range<usize>, EntityKind, String, String)> {
+    let mut found = Vec::new();
+    for email in EMAIL.find_iter(text) {
+        found.push((email.range(), EntityKind::Email, email.as_str().to_lowercase(), email.as_str().to_string()));
+    }
+    for (span, date) in find_dates(text) {
+        found.push((span.clone(), EntityKind::Date, date.format("%Y-%m-%d").to_string(), text[span].to_string()));
+    }
+    for captures in AMOUNT_BEFORE.captures_iter(text) {
+        let unit = captures.get(1).or_else(|| captures.get(2)).map_or("", |m| m.as_str());
+        let value = format!("{} {}", decimal(&captures[3]), currency(unit));
+        found.push((captures.get(0).expect("whole match").range(), EntityKind::Amount, value, captures[0].to_string()));
+    }
+    for captures in AMOUNT_AFTER.captures_iter(text) {
+        let unit = captures.get(2).or_else(|| captures.get(3)).map_or("", |m| m.as_str());
+        let value = format!("{} {}", decimal(&captures[1]), currency(unit));
+        found.push((captures.get(0).expect("whole match").range(), EntityKind::Amount, value, captures[0].to_string()));
+    }
+    for captures in REFERENCE.captures_iter(text).chain(LABELLED_ID.captures_iter(text)) {
+        let value = captures[1].to_uppercase();
+        if value.chars().any(|c| c.is_ascii_digit()) {
+            found.push((captures.get(0).expect("whole match").range(), EntityKind::Id, value, captures[0].to_string()));
+        }
+    }
+    for iban in IBAN.find_iter(text) {
+        found.push((iban.range(), EntityKind::Id, iban.as_str().replace(' ', ""), iban.as_str().to_string()));
+    }
+    for pattern in [&*LABELLED_PHONE, &*INTERNATIONAL_PHONE, &*LOCAL_PHONE] {
+        for captures in pattern.captures_iter(text) {
+            let (span, written) = group_span(&captures, 1);
+            if let Some(value) = phone_number(&written) {
+                found.push((span, EntityKind::Phone, value, written));
+            }
+        }
+    }
+    for address in STREET_ADDRESS.find_iter(text).chain(STRASSE_ADDRESS.find_iter(text)) {
+        let value = address.as_str().split_whitespace().collect::<Vec<_>>().join(" ");
+        found.push((address.range(), EntityKind::Address, value, address.as_str().to_string()));
+    }
+    found
+}
+
+/// The entities in one page's text, in reading order
+pub fn page_entities(text: &str, page: usize) -> Vec<Entity> {
+    let mut claimed: Vec<(Range<usize>, Entity)> = Vec::new();
+    for (span, kind, value, written) in candidates(text) {
+        if claimed.iter().all(|(taken, _)| span.end <= taken.start || span.start >= taken.end) {
+            claimed.push((span, Entity { kind, value, text: written.trim().to_string(), page }));
+        }
+    }
+    claimed.sort_by_key(|(span, _)| span.start);
+    claimed.into_iter().map(|(_, entity)| entity).collect()
+}
+
+/// The entities in a stored document's content, page by page
+pub fn extract_entities(content: &str) -> Vec<Entity> {
+    content.split('\x0c').enumerate().flat_map(|(index, page)| page_entities(page, index + 1)).collect()
+}
+
+/// A search query with its `type:<kind>` term taken out; at most one is allowed
+pub fn take_type_filter(query: &str) -> Result<(String, Option<EntityKind>)> {
+    let mut kind = None;
+    let mut words = Vec::new();
+    for word in query.split_whitespace() {
+        match word.strip_prefix("type:") {
+            Some(name) if kind.is_some() => bail!("Only one type: filter per search, got another in type:{}", name),
+            Some(name) => kind = Some(name.parse::<EntityKind>()?),
+            None => words.push(word),
+        }
+    }
+    Ok((words.join(" "), kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entities_found_and_normalized() {
+        let page = "ACME Widgets, Inc.\n12 Main St, Springfield, IL 62701\nTel: +1 (555) 010-2030\nbilling@ACME.example\n\nInvoice No: 12345\nInvoice date: March 14, 2024\nCustomer ID: C-778\nTotal due: $1,234.5 by 14.04.2024\nBalance 99,90 EUR\n";
+        let found: Vec<(EntityKind, String)> = extract_entities(page).into_iter().map(|entity| (entity.kind, entity.value)).collect();
+        assert_eq!(
+            found.iter().map(|(kind, value)| (*kind, value.as_str())).collect::<Vec<_>>(),
+            vec![
+                (EntityKind::Address, "12 Main St, Springfield, IL 62701"),
+                (EntityKind::Phone, "+15550102030"),
+                (EntityKind::Email, "billing@acme.example"),
+                (EntityKind::Id, "12345"),
+                (EntityKind::Date, "2024-03-14"),
+                (EntityKind::Id, "C-778"),
+                (EntityKind::Amount, "1234.50 USD"),
+                (EntityKind::Date, "2024-04-14"),
+                (EntityKind::Amount, "99.90 EUR"),
+            ]
+        );
+
+        let second = extract_entities("Cover\x0cHauptstraße 5, 10115 Berlin");
+        assert_eq!((second[0].kind, second[0].page), (EntityKind::Address, 2));
+
+        assert_eq!(take_type_filter("type:Amount overdue").unwrap(), ("overdue".to_string(), Some(EntityKind::Amount)));
+        assert!(take_type_filter("type:colour").is_err());
+        assert!(take_type_filter("type:date type:email").is_err());
+    }
+}
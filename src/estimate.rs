@@ -0,0 +1,175 @@
+// Extraction cost model - predicts per-document extraction time from past runs
+//
+// A least-squares fit per engine over
+//     duration_ms = base + per_page * pages + per_image_page * pages * image_coverage
+// Engines with too little history fall back to the pooled fit, then to defaults.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::storage::RunRecord;
+
+/// Runs needed before an engine gets its own coefficients
+const MIN_RUNS_PER_ENGINE: usize = 8;
+
+/// Defaults before any history exists - roughly one pdftotext spawn per page
+const DEFAULT_COEFFICIENTS: Coefficients = Coefficients { base_ms: 20.0, per_page_ms: 45.0, per_image_page_ms: 30.0 };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coefficients {
+    pub base_ms: f64,
+    pub per_page_ms: f64,
+    pub per_image_page_ms: f64,
+}
+
+impl Coefficients {
+    fn predict_ms(&self, pages: usize, image_coverage: f32) -> f64 {
+        let pages = pages as f64;
+        let predicted = self.base_ms
+            + self.per_page_ms * pages
+            + self.per_image_page_ms * pages * image_coverage as f64;
+        predicted.max(0.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CostModel {
+    per_engine: HashMap<String, Coefficients>,
+    pooled: Coefficients,
+    pub samples: usize,
+}
+
+impl CostModel {
+    /// Fit from historical runs. Always succeeds - missing data means default coefficients.
+    pub fn fit(runs: &[RunRecord]) -> Self {
+        let pooled = fit_coefficients(runs).unwrap_or(DEFAULT_COEFFICIENTS);
+
+        let mut by_engine: HashMap<&str, Vec<RunRecord>> = HashMap::new();
+        for run in runs {
+            by_engine.entry(run.engine.as_str()).or_default().push(run.clone());
+        }
+
+        let per_engine = by_engine
+            .into_iter()
+            .filter(|(_, runs)| runs.len() >= MIN_RUNS_PER_ENGINE)
+            .filter_map(|(engine, runs)| fit_coefficients(&runs).map(|c| (engine.to_string(), c)))
+            .collect();
+
+        Self { per_engine, pooled, samples: runs.len() }
+    }
+
+    pub fn coefficients(&self, engine: &str) -> Coefficients {
+        self.per_engine.get(engine).copied().unwrap_or(self.pooled)
+    }
+
+    pub fn predict(&self, engine: &str, pages: usize, image_coverage: f32) -> Duration {
+        let ms = self.coefficients(engine).predict_ms(pages, image_coverage);
+        Duration::from_millis(ms.round() as u64)
+    }
+}
+
+/// Ordinary least squares via the 3x3 normal equations. None when the system is
+/// degenerate (e.g. every run had the same page count).
+fn fit_coefficients(runs: &[RunRecord]) -> Option<Coefficients> {
+    if runs.len() < 3 {
+        return None;
+    }
+
+    let mut xtx = [[0.0f64; 3]; 3];
+    let mut xty = [0.0f64; 3];
+    for run in runs {
+        let pages = run.page_count as f64;
+        let x = [1.0, pages, pages * run.image_coverage as f64];
+        for i in 0..3 {
+            for j in 0..3 {
+                xtx[i][j] += x[i] * x[j];
+            }
+            xty[i] += x[i] * run.duration_ms as f64;
+        }
+    }
+
+    // Image pages are often absent from history; a tiny ridge keeps that column solvable
+    for (i, row) in xtx.iter_mut().enumerate().skip(1) {
+        row[i] += 1e-6;
+    }
+
+    let [base_ms, per_page_ms, per_image_page_ms] = solve3(xtx, xty)?;
+    if !per_page_ms.is_finite() || per_page_ms < 0.0 {
+        return None;
+    }
+
+    Some(Coefficients { base_ms: base_ms.max(0.0), per_page_ms, per_image_page_ms: per_image_page_ms.max(0.0) })
+}
+
+/// Gaussian elimination with partial pivoting
+fn solve3(mut a: [[f64; 3]; 3], mut b: [f64; 3]) -> Option<[f64; 3]> {
+    for col in 0..3 {
+        let pivot = (col..3).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-9 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let pivot_row = a[col];
+        for row in col + 1..3 {
+            let factor = a[row][col] / pivot_row[col];
+            for (cell, pivot) in a[row].iter_mut().zip(pivot_row).skip(col) {
+                *cell -= factor * pivot;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = [0.0; 3];
+    for row in (0..3).rev() {
+        let sum: f64 = (row + 1..3).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Human-readable duration for plans and warnings, e.g. "1h 05m", "3m 12s", "850ms"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else if secs > 0 {
+        format!("{:.1}s", duration.as_secs_f32())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(pages: usize, image_coverage: f32, duration_ms: u64) -> RunRecord {
+        RunRecord {
+            path: String::new(),
+            engine: "pdftotext".to_string(),
+            page_count: pages,
+            image_coverage,
+            duration_ms,
+        }
+    }
+
+    #[test]
+    fn test_fit_recovers_linear_cost() {
+        // 100ms base + 50ms/page + 200ms per fully-image page
+        let runs: Vec<RunRecord> = [(1, 0.0), (4, 0.5), (10, 0.0), (20, 1.0), (7, 0.2), (3, 0.9), (12, 0.4), (2, 0.0)]
+            .iter()
+            .map(|&(pages, img)| run(pages, img, (100.0 + 50.0 * pages as f64 + 200.0 * pages as f64 * img as f64) as u64))
+            .collect();
+
+        let model = CostModel::fit(&runs);
+        let predicted = model.predict("pdftotext", 30, 0.5).as_millis() as i64;
+        assert!((predicted - 4600).abs() < 10, "predicted {}ms", predicted);
+
+        // Unknown engine uses the pooled fit
+        assert_eq!(model.predict("other", 30, 0.5), model.predict("pdftotext", 30, 0.5));
+    }
+}
@@ -0,0 +1,186 @@
+// Hot-reloadable extraction configuration for long-running modes
+//
+// Jobs take a snapshot of the config when they start, so an edit to extraction.toml
+// only affects jobs started after the reload - never a document halfway through.
+use anyhow::Result;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+
+use crate::disk_cache::CacheConfig;
+use crate::hyperlink::LinkConfig;
+use crate::pdf_extraction::cloud_ocr::CloudOcrConfig;
+use crate::pdf_extraction::models::ModelsConfig;
+use crate::pdf_extraction::QualityConfig;
+use crate::search_index::IndexConfig;
+use crate::translate::TranslateConfig;
+use crate::storage::{FederationConfig, HookConfig, MetadataConfig, RetentionConfig};
+
+pub const DEFAULT_CONFIG_PATH: &str = "extraction.toml";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExtractionConfig {
+    /// Engines in priority order; the first one that supports a page wins
+    #[serde(default = "default_engines")]
+    pub engines: Vec<String>,
+    /// Documents whose mean page quality falls below this are flagged for review
+    #[serde(default = "default_min_quality")]
+    pub min_quality: f32,
+    /// Expected document languages, recorded with each job
+    #[serde(default = "default_locales")]
+    pub locales: Vec<String>,
+    /// How often the daemon polls for new work when idle
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// Thresholds and weights for the text quality checks
+    #[serde(default)]
+    pub quality: QualityConfig,
+    /// Actions run on storage changes ([[hooks]] tables, see storage::hooks)
+    #[serde(default)]
+    pub hooks: Vec<HookConfig>,
+    /// Elasticsearch/OpenSearch cluster pages are pushed to ([index] table)
+    #[serde(default)]
+    pub index: Option<IndexConfig>,
+    /// Cloud OCR service for `--engine cloud` ([cloud_ocr] table)
+    #[serde(default)]
+    pub cloud_ocr: Option<CloudOcrConfig>,
+    /// Translation API for `translate` when no local model is installed ([translate] table)
+    #[serde(default)]
+    pub translate: Option<TranslateConfig>,
+    /// What `db maintain --enforce-retention` purges ([retention] table)
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Databases a bare `--db-set` searches together ([federation] table)
+    #[serde(default)]
+    pub federation: FederationConfig,
+    /// Models directory and where `models download` fetches from ([models] table)
+    #[serde(default)]
+    pub models: ModelsConfig,
+    /// Custom document metadata fields and their types ([metadata.fields.<name>] tables)
+    #[serde(default)]
+    pub metadata: MetadataConfig,
+    /// Where paths in search and list output link to in the terminal ([links] table)
+    #[serde(default)]
+    pub links: LinkConfig,
+    /// Cache directory and its size cap ([cache] table)
+    #[serde(default)]
+    pub cache: CacheConfig,
+}
+
+fn default_engines() -> Vec<String> { vec!["pdftotext".to_string()] }
+fn default_min_quality() -> f32 { 0.4 }
+fn default_locales() -> Vec<String> { vec!["en".to_string()] }
+fn default_poll_interval() -> u64 { 5 }
+
+impl Default for ExtractionConfig {
+    fn default() -> Self {
+        Self {
+            engines: default_engines(),
+            min_quality: default_min_quality(),
+            locales: default_locales(),
+            poll_interval_secs: default_poll_interval(),
+            quality: QualityConfig::default(),
+            hooks: Vec::new(),
+            index: None,
+            cloud_ocr: None,
+            translate: None,
+            retention: RetentionConfig::default(),
+            federation: FederationConfig::default(),
+            models: ModelsConfig::default(),
+            metadata: MetadataConfig::default(),
+            links: LinkConfig::default(),
+            cache: CacheConfig::default(),
+        }
+    }
+}
+
+/// A loaded config plus the version jobs are tagged with
+#[derive(Debug, Clone)]
+pub struct VersionedConfig {
+    pub config: ExtractionConfig,
+    /// First 12 hex digits of the file's SHA-256, or "default" when no file exists
+    pub version: String,
+}
+
+impl VersionedConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self { config: ExtractionConfig::default(), version: "default".to_string() });
+        }
+
+        let content = fs::read_to_string(path)?;
+        let config: ExtractionConfig = toml::from_str(&content)?;
+        let digest = format!("{:x}", Sha256::digest(content.as_bytes()));
+        Ok(Self { config, version: digest[..12].to_string() })
+    }
+}
+
+/// Watches the config file and swaps in new versions between jobs
+pub struct ConfigWatcher {
+    path: PathBuf,
+    current: Arc<VersionedConfig>,
+    // Kept alive for as long as the watcher is in use
+    _watcher: Option<RecommendedWatcher>,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> Result<Self> {
+        let current = Arc::new(VersionedConfig::load(path)?);
+        let (tx, events) = channel();
+
+        // Watch the directory rather than the file so editors that save by
+        // rename-over (vim, most IDEs) keep triggering reloads
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let watcher = match notify::recommended_watcher(tx) {
+            Ok(mut watcher) => match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(()) => Some(watcher),
+                Err(e) => {
+                    tracing::warn!("[CONFIG] Not watching {:?} for changes: {}", dir, e);
+                    None
+                }
+            },
+            Err(e) => {
+                tracing::warn!("[CONFIG] File watching unavailable: {}", e);
+                None
+            }
+        };
+
+        Ok(Self { path: path.to_path_buf(), current, _watcher: watcher, events })
+    }
+
+    /// Latest config. Reloads first if the file changed; a broken file keeps the
+    /// previous version so a typo never stops running jobs.
+    pub fn current(&mut self) -> Arc<VersionedConfig> {
+        let file_name = self.path.file_name();
+        let mut changed = false;
+        while let Ok(Ok(event)) = self.events.try_recv() {
+            if event.paths.iter().any(|p| p.file_name() == file_name)
+                && (event.kind.is_modify() || event.kind.is_create() || event.kind.is_remove())
+            {
+                changed = true;
+            }
+        }
+
+        if changed {
+            match VersionedConfig::load(&self.path) {
+                Ok(loaded) if loaded.version != self.current.version => {
+                    tracing::info!("🔄 [CONFIG] {} reloaded: version {} -> {}",
+                        self.path.display(), self.current.version, loaded.version);
+                    self.current = Arc::new(loaded);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("⚠️  [CONFIG] Ignoring invalid {}: {}", self.path.display(), e),
+            }
+        }
+
+        Arc::clone(&self.current)
+    }
+}
@@ -12,7 +12,7 @@ use std::path::PathBuf;
 use std::process::Command;
 use std::sync::Arc;
 
-use crate::theme::ChonkerTheme;
+use crate::theme;
 
 /// Use nucleo to pick a PDF file with interactive fuzzy finding
 pub fn pick_pdf_file() -> Result<Option<PathBuf>> {
@@ -69,6 +69,8 @@ fn run_fuzzy_picker(files: &[String]) -> Result<Option<PathBuf>> {
     let mut scroll_offset = 0usize;
     
     loop {
+        let theme = theme::current();
+
         // Clear screen
         execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
         
@@ -80,8 +82,8 @@ fn run_fuzzy_picker(files: &[String]) -> Result<Option<PathBuf>> {
         execute!(
             stdout,
             MoveTo(0, 0),
-            SetBackgroundColor(ChonkerTheme::accent_load_file()),
-            SetForegroundColor(ChonkerTheme::text_header()),
+            SetBackgroundColor(theme.accent_load_file),
+            SetForegroundColor(theme.text_header),
             SetAttribute(Attribute::Bold),
             Print(format!("  {:<width$}", header_text, width = (term_width - 2) as usize)),
             ResetColor,
@@ -92,7 +94,7 @@ fn run_fuzzy_picker(files: &[String]) -> Result<Option<PathBuf>> {
         execute!(
             stdout,
             MoveTo(0, 1),
-            SetBackgroundColor(ChonkerTheme::accent_load_file()),
+            SetBackgroundColor(theme.accent_load_file),
             Print(" ".repeat(term_width as usize)),
             ResetColor,
             Print("\n")
@@ -102,11 +104,11 @@ fn run_fuzzy_picker(files: &[String]) -> Result<Option<PathBuf>> {
         execute!(
             stdout,
             MoveTo(0, 3),
-            SetForegroundColor(ChonkerTheme::accent_text()),
+            SetForegroundColor(theme.accent_text),
             Print("  🔍 Search: "),
-            SetForegroundColor(ChonkerTheme::text_primary()),
+            SetForegroundColor(theme.text),
             Print(&query),
-            SetForegroundColor(ChonkerTheme::text_dim()),
+            SetForegroundColor(theme.text_dim),
             Print("_"),
             ResetColor,
             Print("\n\n")
@@ -182,9 +184,9 @@ fn run_fuzzy_picker(files: &[String]) -> Result<Option<PathBuf>> {
             if actual_index == selected_index {
                 execute!(
                     stdout,
-                    SetForegroundColor(ChonkerTheme::success()),
+                    SetForegroundColor(theme.success),
                     Print("  ▶ "),
-                    SetForegroundColor(ChonkerTheme::text_primary()),
+                    SetForegroundColor(theme.text),
                     Print(&final_display),
                     ResetColor
                 )?;
@@ -192,7 +194,7 @@ fn run_fuzzy_picker(files: &[String]) -> Result<Option<PathBuf>> {
                 execute!(
                     stdout,
                     Print("    "),
-                    SetForegroundColor(ChonkerTheme::text_secondary()),
+                    SetForegroundColor(theme.text_secondary),
                     Print(&final_display),
                     ResetColor
                 )?;
@@ -224,7 +226,7 @@ fn run_fuzzy_picker(files: &[String]) -> Result<Option<PathBuf>> {
             stdout,
             MoveTo(0, help_line),
             Clear(ClearType::CurrentLine),
-            SetForegroundColor(ChonkerTheme::text_dim()),
+            SetForegroundColor(theme.text_dim),
             Print(&scroll_indicator),
             ResetColor
         )?;
@@ -233,7 +235,7 @@ fn run_fuzzy_picker(files: &[String]) -> Result<Option<PathBuf>> {
             stdout,
             MoveTo(0, help_line + 1),
             Clear(ClearType::CurrentLine),
-            SetForegroundColor(ChonkerTheme::text_dim()),
+            SetForegroundColor(theme.text_dim),
             Print("  🔥 HOT-RELOAD FILE PICKER  •  ↑/↓ Navigate  •  Enter Select  •  Esc Back  •  Type to search"),
             ResetColor
         )?;
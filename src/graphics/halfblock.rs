@@ -0,0 +1,71 @@
+// Halfblock mosaic - '▀' with the top pixel as foreground and the bottom pixel as
+// background, two pixels per cell. Works in any truecolor terminal.
+use anyhow::Result;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use std::fmt::Write as _;
+use std::io::Write;
+
+use super::{CellArea, GraphicsBackend, Protocol};
+use crate::kitty_protocol::KittyProtocol;
+
+/// Each cell is one pixel wide and two tall, so a cell is square in image space
+const HALFBLOCK_CELL: (f32, f32) = (1.0, 2.0);
+
+#[derive(Default)]
+pub struct HalfblockBackend {
+    /// Rendered rows for the current image and the area they were sized for
+    cached: Option<(CellArea, Vec<String>)>,
+}
+
+/// ANSI-colored rows of '▀' for an image resized to exactly `cols` x `rows` cells
+pub fn mosaic(image: &DynamicImage, cols: u16, rows: u16) -> Vec<String> {
+    let pixels = image
+        .resize_exact(cols as u32, rows as u32 * 2, FilterType::Triangle)
+        .to_rgb8();
+
+    (0..rows as u32)
+        .map(|row| {
+            let mut line = String::new();
+            for col in 0..cols as u32 {
+                let top = pixels.get_pixel(col, row * 2);
+                let bottom = pixels.get_pixel(col, row * 2 + 1);
+                let _ = write!(
+                    line,
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                    top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                );
+            }
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect()
+}
+
+impl GraphicsBackend for HalfblockBackend {
+    fn protocol(&self) -> Protocol {
+        Protocol::Halfblock
+    }
+
+    fn show(&mut self, image: &DynamicImage, area: CellArea, changed: bool) -> Result<()> {
+        let stale = changed || self.cached.as_ref().map(|(cached, _)| *cached != area).unwrap_or(true);
+        if stale {
+            let (cols, rows) = KittyProtocol::fit_cells(image.width(), image.height(), area.cols, area.rows, HALFBLOCK_CELL);
+            self.cached = Some((area, mosaic(image, cols, rows)));
+        }
+
+        if let Some((_, lines)) = &self.cached {
+            let mut out = std::io::stdout().lock();
+            for (i, line) in lines.iter().enumerate() {
+                write!(out, "\x1b[{};{}H{}", area.y + 1 + i as u16, area.x + 1, line)?;
+            }
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.cached = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+// iTerm2 inline images (OSC 1337) - iTerm2 and WezTerm
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+
+use super::{write_at, CellArea, GraphicsBackend, Protocol};
+
+pub struct Iterm2Backend {
+    passthrough: bool,
+    /// Encoded sequence for the current image and the area it was sized for
+    cached: Option<(CellArea, Vec<u8>)>,
+}
+
+impl Iterm2Backend {
+    pub fn new(passthrough: bool) -> Self {
+        Self { passthrough, cached: None }
+    }
+}
+
+impl GraphicsBackend for Iterm2Backend {
+    fn protocol(&self) -> Protocol {
+        Protocol::Iterm2
+    }
+
+    fn show(&mut self, image: &DynamicImage, area: CellArea, changed: bool) -> Result<()> {
+        // Inline images are part of the cell grid and vanish with every screen clear,
+        // so the sequence is rebuilt only when the image or area changes and re-sent
+        // every frame
+        let stale = changed || self.cached.as_ref().map(|(cached, _)| *cached != area).unwrap_or(true);
+        if stale {
+            let (fitted, cols, rows) = super::fit_pixels(image, area);
+            let mut png = Vec::new();
+            fitted.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+            let sequence = format!(
+                "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+                png.len(), cols, rows, BASE64.encode(&png)
+            );
+            self.cached = Some((area, sequence.into_bytes()));
+        }
+
+        if let Some((_, sequence)) = &self.cached {
+            write_at(area.x, area.y, sequence, self.passthrough)?;
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.cached = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+// Kitty graphics - transmit once per image, re-place every frame
+use anyhow::Result;
+use image::DynamicImage;
+
+use super::{CellArea, GraphicsBackend, Protocol};
+use crate::kitty_protocol::KittyProtocol;
+
+/// Placement id for the one image this backend shows
+const PLACEMENT_ID: u32 = 1;
+
+pub struct KittyBackend {
+    protocol: KittyProtocol,
+    image_id: Option<u32>,
+    placed: Option<(CellArea, u16, u16)>,
+}
+
+impl KittyBackend {
+    pub fn new(passthrough: bool) -> Self {
+        // Detection already happened in graphics::detect
+        let mut protocol = KittyProtocol::new();
+        protocol.force_enable();
+        protocol.set_passthrough(passthrough);
+        Self { protocol, image_id: None, placed: None }
+    }
+}
+
+impl GraphicsBackend for KittyBackend {
+    fn protocol(&self) -> Protocol {
+        Protocol::Kitty
+    }
+
+    fn show(&mut self, image: &DynamicImage, area: CellArea, changed: bool) -> Result<()> {
+        // New image: free the old one in the terminal and send the new one once
+        if changed || self.image_id.is_none() {
+            if let Some(old_id) = self.image_id.take() {
+                self.protocol.delete_image(old_id)?;
+            }
+            self.image_id = Some(self.protocol.transmit(image)?);
+        }
+
+        // Placed every frame under the same placement id, so the image stays anchored
+        // to the area and follows it on resize without being re-sent
+        let (cols, rows) = KittyProtocol::fit_cells(
+            image.width(),
+            image.height(),
+            area.cols,
+            area.rows,
+            KittyProtocol::cell_size(),
+        );
+        if self.placed != Some((area, cols, rows)) {
+            crate::debug_log!("KITTY", "Placing image at ({}, {}) as {}x{} cells", area.x, area.y, cols, rows);
+            self.placed = Some((area, cols, rows));
+        }
+        if let Some(image_id) = self.image_id {
+            self.protocol.place(image_id, PLACEMENT_ID, area.x, area.y, cols, rows)?;
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        if let Some(image_id) = self.image_id.take() {
+            self.protocol.delete_image(image_id)?;
+        }
+        self.placed = None;
+        Ok(())
+    }
+}
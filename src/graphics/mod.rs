@@ -0,0 +1,168 @@
+// Terminal graphics - picks the best image protocol the terminal speaks
+//
+// Kitty graphics where available, then iTerm2 inline images (iTerm2, WezTerm),
+// then Sixel (foot, mlterm, xterm -ti vt340), and finally a halfblock mosaic that
+// works in any truecolor terminal. Inside tmux the escape sequences are wrapped in
+// DCS passthrough (needs `set -g allow-passthrough on`). Set CHONKER_GRAPHICS to
+// kitty, iterm2, sixel or halfblock to override detection.
+
+mod halfblock;
+mod iterm2;
+mod kitty;
+mod sixel;
+
+use anyhow::Result;
+use image::DynamicImage;
+
+pub use halfblock::HalfblockBackend;
+pub use iterm2::Iterm2Backend;
+pub use kitty::KittyBackend;
+pub use sixel::SixelBackend;
+
+/// Environment variable that forces a protocol
+pub const GRAPHICS_ENV: &str = "CHONKER_GRAPHICS";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Iterm2,
+    Sixel,
+    Halfblock,
+}
+
+impl Protocol {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::Kitty => "kitty",
+            Protocol::Iterm2 => "iterm2",
+            Protocol::Sixel => "sixel",
+            Protocol::Halfblock => "halfblock",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "kitty" => Some(Protocol::Kitty),
+            "iterm2" | "iterm" => Some(Protocol::Iterm2),
+            "sixel" => Some(Protocol::Sixel),
+            "halfblock" | "blocks" => Some(Protocol::Halfblock),
+            _ => None,
+        }
+    }
+}
+
+/// A rectangle of terminal cells, 0-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellArea {
+    pub x: u16,
+    pub y: u16,
+    pub cols: u16,
+    pub rows: u16,
+}
+
+pub trait GraphicsBackend {
+    fn protocol(&self) -> Protocol;
+
+    /// Draw `image` fitted into `area`. Called on every frame after the screen is
+    /// cleared; `changed` is true when the image differs from the previous call.
+    fn show(&mut self, image: &DynamicImage, area: CellArea, changed: bool) -> Result<()>;
+
+    /// Remove anything the backend keeps on screen or in the terminal
+    fn clear(&mut self) -> Result<()>;
+}
+
+/// Pick a protocol from the environment
+pub fn detect() -> Protocol {
+    detect_from(|key| std::env::var(key).ok())
+}
+
+fn detect_from(env: impl Fn(&str) -> Option<String>) -> Protocol {
+    if let Some(forced) = env(GRAPHICS_ENV).as_deref().and_then(Protocol::from_name) {
+        return forced;
+    }
+
+    let term = env("TERM").unwrap_or_default().to_lowercase();
+    // tmux replaces TERM_PROGRAM; LC_TERMINAL and the terminal's own variables survive
+    let program = env("TERM_PROGRAM").unwrap_or_default().to_lowercase();
+    let lc_terminal = env("LC_TERMINAL").unwrap_or_default().to_lowercase();
+
+    if env("KITTY_WINDOW_ID").is_some() || term.contains("kitty") || program == "ghostty" {
+        Protocol::Kitty
+    } else if program == "iterm.app" || lc_terminal == "iterm2" || program == "wezterm" || env("WEZTERM_EXECUTABLE").is_some() {
+        Protocol::Iterm2
+    } else if term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") || program == "mlterm" {
+        Protocol::Sixel
+    } else {
+        Protocol::Halfblock
+    }
+}
+
+/// Create the backend for a protocol
+pub fn backend(protocol: Protocol) -> Box<dyn GraphicsBackend> {
+    let passthrough = std::env::var("TMUX").is_ok();
+    match protocol {
+        Protocol::Kitty => Box::new(KittyBackend::new(passthrough)),
+        Protocol::Iterm2 => Box::new(Iterm2Backend::new(passthrough)),
+        Protocol::Sixel => Box::new(SixelBackend::new(passthrough)),
+        Protocol::Halfblock => Box::new(HalfblockBackend::default()),
+    }
+}
+
+/// Wrap an escape sequence for tmux passthrough: DCS tmux; with every ESC doubled
+pub(crate) fn tmux_wrap(sequence: &[u8]) -> Vec<u8> {
+    let mut wrapped = Vec::with_capacity(sequence.len() + 16);
+    wrapped.extend_from_slice(b"\x1bPtmux;");
+    for &byte in sequence {
+        if byte == 0x1b {
+            wrapped.push(0x1b);
+        }
+        wrapped.push(byte);
+    }
+    wrapped.extend_from_slice(b"\x1b\\");
+    wrapped
+}
+
+/// Write a graphics sequence at a cell, wrapped for tmux when needed
+pub(crate) fn write_at(x: u16, y: u16, sequence: &[u8], passthrough: bool) -> Result<()> {
+    use std::io::Write;
+    let mut out = std::io::stdout().lock();
+    write!(out, "\x1b[{};{}H", y + 1, x + 1)?;
+    if passthrough {
+        out.write_all(&tmux_wrap(sequence))?;
+    } else {
+        out.write_all(sequence)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Resize an image to fill exactly `cols` x `rows` cells at the terminal's cell size
+pub(crate) fn fit_pixels(image: &DynamicImage, area: CellArea) -> (DynamicImage, u16, u16) {
+    let cell = crate::kitty_protocol::KittyProtocol::cell_size();
+    let (cols, rows) = crate::kitty_protocol::KittyProtocol::fit_cells(image.width(), image.height(), area.cols, area.rows, cell);
+    let width = (cols as f32 * cell.0).round().max(1.0) as u32;
+    let height = (rows as f32 * cell.1).round().max(1.0) as u32;
+    (image.resize_exact(width, height, image::imageops::FilterType::Triangle), cols, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn detect_with(vars: &[(&str, &str)]) -> Protocol {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        detect_from(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_detection_order_and_override() {
+        assert_eq!(detect_with(&[("TERM", "xterm-kitty")]), Protocol::Kitty);
+        assert_eq!(detect_with(&[("TERM", "tmux-256color"), ("WEZTERM_EXECUTABLE", "/usr/bin/wezterm")]), Protocol::Iterm2);
+        assert_eq!(detect_with(&[("TERM", "foot")]), Protocol::Sixel);
+        assert_eq!(detect_with(&[("TERM", "xterm-256color")]), Protocol::Halfblock);
+        assert_eq!(detect_with(&[("TERM", "xterm-kitty"), (GRAPHICS_ENV, "halfblock")]), Protocol::Halfblock);
+
+        assert_eq!(tmux_wrap(b"\x1b_Ga=d\x1b\\"), b"\x1bPtmux;\x1b\x1b_Ga=d\x1b\x1b\\\x1b\\".to_vec());
+    }
+}
@@ -0,0 +1,128 @@
+// Sixel graphics - foot, mlterm, xterm with sixel enabled
+//
+// Colors are quantized to a 6x6x6 cube; only the entries the image uses are sent.
+// Each band of six pixel rows is written once per color present, with runs of
+// identical columns run-length encoded.
+use anyhow::Result;
+use image::{DynamicImage, RgbImage};
+use std::fmt::Write as _;
+
+use super::{write_at, CellArea, GraphicsBackend, Protocol};
+
+const LEVELS: usize = 6;
+const PALETTE_SIZE: usize = LEVELS * LEVELS * LEVELS;
+
+pub struct SixelBackend {
+    passthrough: bool,
+    /// Encoded sequence for the current image and the area it was sized for
+    cached: Option<(CellArea, Vec<u8>)>,
+}
+
+impl SixelBackend {
+    pub fn new(passthrough: bool) -> Self {
+        Self { passthrough, cached: None }
+    }
+}
+
+fn palette_index(pixel: &image::Rgb<u8>) -> usize {
+    let level = |c: u8| (c as usize * (LEVELS - 1) + 127) / 255;
+    level(pixel[0]) * LEVELS * LEVELS + level(pixel[1]) * LEVELS + level(pixel[2])
+}
+
+/// Encode an image as a complete Sixel DCS sequence
+pub fn encode(image: &RgbImage) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let mut out = String::new();
+    // P2=1: pixels left at 0 keep the background instead of being painted
+    let _ = write!(out, "\x1bP0;1q\"1;1;{};{}", width, height);
+
+    let mut used = [false; PALETTE_SIZE];
+    for pixel in image.pixels() {
+        used[palette_index(pixel)] = true;
+    }
+    for (index, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        let (r, g, b) = (index / (LEVELS * LEVELS), index / LEVELS % LEVELS, index % LEVELS);
+        let percent = |level: usize| level * 100 / (LEVELS - 1);
+        let _ = write!(out, "#{};2;{};{};{}", index, percent(r), percent(g), percent(b));
+    }
+
+    let mut bands: Vec<Option<Vec<u8>>> = vec![None; PALETTE_SIZE];
+    for band_top in (0..height).step_by(6) {
+        for slot in bands.iter_mut() {
+            *slot = None;
+        }
+        for dy in 0..(height - band_top).min(6) {
+            for x in 0..width {
+                let color = palette_index(image.get_pixel(x, band_top + dy));
+                let bits = bands[color].get_or_insert_with(|| vec![0; width as usize]);
+                bits[x as usize] |= 1 << dy;
+            }
+        }
+
+        let mut first = true;
+        for (color, bits) in bands.iter().enumerate() {
+            let Some(bits) = bits else { continue };
+            if !first {
+                out.push('$'); // back to the start of the band for the next color
+            }
+            first = false;
+            let _ = write!(out, "#{}", color);
+
+            let mut x = 0;
+            while x < bits.len() {
+                let run = bits[x..].iter().take_while(|b| **b == bits[x]).count();
+                let sixel = (63 + bits[x]) as char;
+                if run > 3 {
+                    let _ = write!(out, "!{}{}", run, sixel);
+                } else {
+                    out.extend(std::iter::repeat_n(sixel, run));
+                }
+                x += run;
+            }
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out.into_bytes()
+}
+
+impl GraphicsBackend for SixelBackend {
+    fn protocol(&self) -> Protocol {
+        Protocol::Sixel
+    }
+
+    fn show(&mut self, image: &DynamicImage, area: CellArea, changed: bool) -> Result<()> {
+        // Sixel output is part of the cell grid and vanishes with every screen clear,
+        // so the encoding is cached and re-sent each frame
+        let stale = changed || self.cached.as_ref().map(|(cached, _)| *cached != area).unwrap_or(true);
+        if stale {
+            let (fitted, _, _) = super::fit_pixels(image, area);
+            self.cached = Some((area, encode(&fitted.to_rgb8())));
+        }
+
+        if let Some((_, sequence)) = &self.cached {
+            write_at(area.x, area.y, sequence, self.passthrough)?;
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.cached = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_solid_band() {
+        let red = RgbImage::from_pixel(5, 6, image::Rgb([255, 0, 0]));
+        let encoded = String::from_utf8(encode(&red)).unwrap();
+        // Only the pure red cube entry is defined, and the full band is one run
+        assert!(encoded.contains("#180;2;100;0;0#180!5~-"), "{}", encoded);
+        assert!(encoded.ends_with("\x1b\\"));
+    }
+}
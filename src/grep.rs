@@ -0,0 +1,78 @@
+// Regex search within one document's pages, with grep-style context
+//
+// Positions are 1-based page, line and column, counted in characters so they
+// match what the viewer shows. Matches never span lines, like `replace-all`.
+use regex::Regex;
+
+/// One match, with the lines around it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrepMatch {
+    pub page: usize,
+    pub line: usize,
+    pub column: usize,
+    /// The matched text
+    pub text: String,
+    /// Named capture groups that took part in the match
+    pub groups: Vec<(String, String)>,
+    pub before: Vec<String>,
+    pub line_text: String,
+    pub after: Vec<String>,
+}
+
+/// Every match on every page (form-feed separated), in reading order, with
+/// `context` lines either side
+pub fn grep_pages(content: &str, pattern: &Regex, context: usize) -> Vec<GrepMatch> {
+    let names: Vec<&str> = pattern.capture_names().flatten().collect();
+    let mut matches = Vec::new();
+    for (page_index, page) in content.split('\x0c').enumerate() {
+        let lines: Vec<&str> = page.lines().collect();
+        for (line_index, line) in lines.iter().enumerate() {
+            for captures in pattern.captures_iter(line) {
+                let Some(whole) = captures.get(0) else { continue };
+                if whole.as_str().is_empty() {
+                    continue;
+                }
+                let groups = names
+                    .iter()
+                    .filter_map(|name| captures.name(name).map(|m| (name.to_string(), m.as_str().to_string())))
+                    .collect();
+                let context_line = |l: &&str| l.trim_end().to_string();
+                matches.push(GrepMatch {
+                    page: page_index + 1,
+                    line: line_index + 1,
+                    column: line[..whole.start()].chars().count() + 1,
+                    text: whole.as_str().to_string(),
+                    groups,
+                    before: lines[line_index.saturating_sub(context)..line_index].iter().map(context_line).collect(),
+                    line_text: line.trim_end().to_string(),
+                    after: lines[line_index + 1..(line_index + 1 + context).min(lines.len())].iter().map(context_line).collect(),
+                });
+            }
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_context_and_named_groups() {
+        let content = "Invoice\nTotal:  €42.00\nThank you\x0cCredit note\nTotal:  €-5.00";
+        let pattern = Regex::new(r"(?i)total:\s+€(?P<amount>-?[\d.]+)").unwrap();
+        let matches = grep_pages(content, &pattern, 1);
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!((matches[0].page, matches[0].line, matches[0].column), (1, 2, 1));
+        assert_eq!(matches[0].before, vec!["Invoice"]);
+        assert_eq!(matches[0].after, vec!["Thank you"]);
+        assert_eq!(matches[1].page, 2);
+        assert_eq!(matches[1].groups, vec![("amount".to_string(), "-5.00".to_string())]);
+        assert!(matches[1].after.is_empty());
+
+        // Columns count characters, not bytes
+        let euro = grep_pages("€€ x", &Regex::new("x").unwrap(), 0);
+        assert_eq!(euro[0].column, 4);
+    }
+}
@@ -0,0 +1,248 @@
+// Health endpoints and systemd notification for supervised long-running modes
+//
+// /healthz answers as long as the process and its work loop are alive (liveness).
+// /readyz runs the readiness checks - database reachable, external tools on PATH,
+// model files readable - and reports 503 while draining for shutdown. Probes only
+// get each check's name and outcome; the details, which name files on this
+// machine, are for `chonker8 selftest`. The listener is on loopback unless told
+// otherwise, and every connection gets its own thread so a stalled probe can't
+// hold up the next one.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::pdf_extraction::models::{self, MODELS};
+
+/// Work loop is considered stuck when it hasn't checked in for this long
+const HEARTBEAT_TIMEOUT_SECS: i64 = 600;
+
+/// Readiness checks are cached so a busy probe can't hammer the database
+const READINESS_CACHE: Duration = Duration::from_secs(5);
+
+/// External tools the extraction backends shell out to; tesseract is optional
+const REQUIRED_TOOLS: &[(&str, &str)] = &[("pdftotext", "-v"), ("pdftoppm", "-v")];
+const OPTIONAL_TOOLS: &[(&str, &str)] = &[("tesseract", "--version")];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub required: bool,
+    /// Local diagnostics, may name paths - never sent to probes
+    #[serde(skip_serializing)]
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Report {
+    status: &'static str,
+    checks: Vec<Check>,
+}
+
+/// Shared state between the work loop and the health server
+pub struct HealthState {
+    db_path: PathBuf,
+    draining: AtomicBool,
+    heartbeat: AtomicI64,
+    cached: Mutex<Option<(Instant, Vec<Check>)>>,
+}
+
+impl HealthState {
+    pub fn new(db_path: &Path) -> Arc<Self> {
+        Arc::new(Self {
+            db_path: db_path.to_path_buf(),
+            draining: AtomicBool::new(false),
+            heartbeat: AtomicI64::new(chrono::Utc::now().timestamp()),
+            cached: Mutex::new(None),
+        })
+    }
+
+    /// Called by the work loop on every iteration; also pets the systemd watchdog
+    pub fn heartbeat(&self) {
+        self.heartbeat.store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+        let _ = sd_notify::notify(false, &[sd_notify::NotifyState::Watchdog]);
+    }
+
+    /// Stop reporting ready so load balancers route work elsewhere while draining
+    pub fn set_draining(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+        let _ = sd_notify::notify(false, &[sd_notify::NotifyState::Stopping]);
+    }
+
+    pub fn is_alive(&self) -> bool {
+        chrono::Utc::now().timestamp() - self.heartbeat.load(Ordering::Relaxed) < HEARTBEAT_TIMEOUT_SECS
+    }
+
+    pub fn readiness(&self) -> Vec<Check> {
+        let mut cached = self.cached.lock().unwrap();
+        if let Some((at, checks)) = cached.as_ref() {
+            if at.elapsed() < READINESS_CACHE {
+                return checks.clone();
+            }
+        }
+        let checks = run_checks(&self.db_path);
+        *cached = Some((Instant::now(), checks.clone()));
+        checks
+    }
+
+    pub fn is_ready(&self) -> bool {
+        !self.draining.load(Ordering::Relaxed) && self.readiness().iter().all(|c| c.ok || !c.required)
+    }
+}
+
+/// Tell systemd startup is complete (no-op when not run under systemd)
+pub fn notify_ready(status: &str) {
+    let _ = sd_notify::notify(false, &[sd_notify::NotifyState::Ready, sd_notify::NotifyState::Status(status)]);
+}
+
+pub fn run_checks(db_path: &Path) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let db = rusqlite::Connection::open(db_path)
+        .and_then(|conn| conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0)));
+    checks.push(Check {
+        name: "database".to_string(),
+        ok: db.is_ok(),
+        required: true,
+        detail: match db {
+            Ok(_) => db_path.display().to_string(),
+            Err(e) => e.to_string(),
+        },
+    });
+
+    for (tools, required) in [(REQUIRED_TOOLS, true), (OPTIONAL_TOOLS, false)] {
+        for (tool, version_flag) in tools {
+            let found = Command::new(tool)
+                .arg(version_flag)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok();
+            checks.push(Check {
+                name: tool.to_string(),
+                ok: found,
+                required,
+                detail: if found { "found".to_string() } else { "not on PATH".to_string() },
+            });
+        }
+    }
+
+    // Models are optional - none of the default engines need them - but a present,
+    // unreadable model file means a broken deployment
+    let present: Vec<PathBuf> = MODELS
+        .iter()
+        .flat_map(|model| model.files.iter().map(|file| models::locate(file)))
+        .filter(|path| path.exists())
+        .collect();
+    let unreadable: Vec<String> =
+        present.iter().filter(|path| std::fs::File::open(path).is_err()).map(|path| path.display().to_string()).collect();
+    checks.push(Check {
+        name: "models".to_string(),
+        ok: unreadable.is_empty(),
+        required: false,
+        detail: if !unreadable.is_empty() {
+            format!("unreadable: {}", unreadable.join(", "))
+        } else if present.is_empty() {
+            "none installed".to_string()
+        } else {
+            present.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")
+        },
+    });
+
+    checks
+}
+
+/// Serve /healthz and /readyz on a background thread
+pub fn spawn_server(bind: IpAddr, port: u16, state: Arc<HealthState>) -> Result<()> {
+    let listener = TcpListener::bind((bind, port))?;
+    tracing::info!("💓 [HEALTH] Listening on {}:{} (/healthz, /readyz)", bind, port);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                if let Err(e) = handle_connection(stream, &state) {
+                    tracing::warn!("[HEALTH] Request failed: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, state: &HealthState) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (code, body) = respond(path, state)?;
+
+    let reason = match code {
+        200 => "OK",
+        404 => "Not Found",
+        _ => "Service Unavailable",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code, reason, body.len(), body
+    )?;
+    Ok(())
+}
+
+/// Status code and JSON body for a probe path
+fn respond(path: &str, state: &HealthState) -> Result<(u16, String)> {
+    Ok(match path {
+        "/healthz" => {
+            let alive = state.is_alive();
+            let report = Report { status: if alive { "ok" } else { "stalled" }, checks: Vec::new() };
+            (if alive { 200 } else { 503 }, serde_json::to_string(&report)?)
+        }
+        "/readyz" => {
+            let ready = state.is_ready();
+            let status = if state.draining.load(Ordering::Relaxed) {
+                "draining"
+            } else if ready {
+                "ready"
+            } else {
+                "not ready"
+            };
+            let report = Report { status, checks: state.readiness() };
+            (if ready { 200 } else { 503 }, serde_json::to_string(&report)?)
+        }
+        _ => (404, r#"{"status":"not found"}"#.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_without_paths_and_draining() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("health.db");
+        let state = HealthState::new(&db);
+        assert!(state.readiness().iter().any(|check| check.name == "database" && check.ok));
+
+        let (code, body) = respond("/readyz", &state).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(code == 200, report["status"] == "ready");
+        assert!(report["checks"].as_array().unwrap().iter().all(|check| check.get("detail").is_none()));
+        assert!(!body.contains(&dir.path().display().to_string()));
+
+        state.set_draining();
+        let (code, body) = respond("/readyz", &state).unwrap();
+        assert_eq!(code, 503);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["status"], "draining");
+        // Draining isn't dying: liveness still answers
+        assert_eq!(respond("/healthz", &state).unwrap().0, 200);
+    }
+}
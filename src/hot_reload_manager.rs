@@ -170,7 +170,7 @@ impl HotReloadManager {
             if let Ok(mut file) = std::fs::OpenOptions::new()
                 .create(true)
                 .append(true)
-                .open("/tmp/chonker8_debug.log")
+                .open(chonker8::debug_log::TUI_LOG)
             {
                 use std::io::Write;
                 let _ = writeln!(file, "[{}] [BUILD] Starting build for {}...", 
@@ -206,7 +206,7 @@ impl HotReloadManager {
                 if let Ok(mut file) = std::fs::OpenOptions::new()
                     .create(true)
                     .append(true)
-                    .open("/tmp/chonker8_debug.log")
+                    .open(chonker8::debug_log::TUI_LOG)
                 {
                     use std::io::Write;
                     // Log stdout first (usually contains compilation progress and warnings)
@@ -0,0 +1,167 @@
+// Clickable paths in terminal output - OSC 8 hyperlinks
+//
+// Terminals that support OSC 8 (iTerm2, kitty, WezTerm, GNOME Terminal, Windows
+// Terminal, ...) show the wrapped text as a link; others print the text alone.
+// Links go to the file itself (file:// URL, #page=N for a page) or, with a URL
+// template configured, to a `chonker8 serve` instance:
+//
+//     [links]
+//     document_url = "http://localhost:8080/documents/{id}"
+//     page_url = "http://localhost:8080/documents/{id}/pages/{page}"
+//
+// Templates take {id}, {path} (URL-encoded) and {page}. Output that isn't a
+// terminal gets no escapes, so pipes and scripts see plain text.
+use serde::{Deserialize, Serialize};
+use std::io::IsTerminal;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMode {
+    /// When stdout is a terminal that isn't `TERM=dumb`
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The [links] table of extraction.toml
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LinkConfig {
+    #[serde(default)]
+    pub mode: LinkMode,
+    /// Where a document links to; file:// when unset
+    pub document_url: Option<String>,
+    /// Where a page of a document links to; document_url, then file://, when unset
+    pub page_url: Option<String>,
+}
+
+/// What a link points at
+#[derive(Debug, Clone, Copy)]
+pub struct LinkTarget<'a> {
+    pub path: &'a str,
+    pub id: Option<i64>,
+    /// 1-based
+    pub page: Option<usize>,
+}
+
+impl<'a> LinkTarget<'a> {
+    pub fn document(path: &'a str, id: Option<i64>) -> Self {
+        Self { path, id, page: None }
+    }
+
+    pub fn page(path: &'a str, id: Option<i64>, page: usize) -> Self {
+        Self { path, id, page: Some(page) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Linker {
+    config: LinkConfig,
+    enabled: bool,
+}
+
+impl Linker {
+    /// Links on or off for stdout as it is now
+    pub fn for_stdout(config: &LinkConfig) -> Self {
+        let terminal = std::io::stdout().is_terminal() && std::env::var("TERM").map_or(true, |term| term != "dumb");
+        Self::new(config, terminal)
+    }
+
+    pub fn new(config: &LinkConfig, terminal: bool) -> Self {
+        let enabled = match config.mode {
+            LinkMode::Auto => terminal,
+            LinkMode::Always => true,
+            LinkMode::Never => false,
+        };
+        Self { config: config.clone(), enabled }
+    }
+
+    /// A linker that never links, for plain output
+    pub fn disabled() -> Self {
+        Self::new(&LinkConfig::default(), false)
+    }
+
+    /// `text` wrapped in an OSC 8 link to `target`, or `text` unchanged when links are off
+    pub fn link(&self, target: LinkTarget, text: &str) -> String {
+        if !self.enabled || text.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", self.url(target), text)
+    }
+
+    /// The URL for `target` when links are on, for output formats that carry links themselves
+    pub fn target(&self, target: LinkTarget) -> Option<String> {
+        self.enabled.then(|| self.url(target))
+    }
+
+    pub fn url(&self, target: LinkTarget) -> String {
+        let templates = [target.page.and(self.config.page_url.as_deref()), self.config.document_url.as_deref()];
+        for template in templates.into_iter().flatten() {
+            // A template that needs an id can't be filled without one
+            if target.id.is_none() && template.contains("{id}") {
+                continue;
+            }
+            return template
+                .replace("{id}", &target.id.map(|id| id.to_string()).unwrap_or_default())
+                .replace("{page}", &target.page.unwrap_or(1).to_string())
+                .replace("{path}", &percent_encode(target.path, false));
+        }
+        file_url(target.path, target.page)
+    }
+}
+
+/// file:// URL for a path, relative paths taken from the current directory
+pub fn file_url(path: &str, page: Option<usize>) -> String {
+    let absolute = std::fs::canonicalize(path)
+        .or_else(|_| std::env::current_dir().map(|dir| dir.join(path)))
+        .unwrap_or_else(|_| Path::new(path).to_path_buf());
+    let mut url = format!("file://{}", percent_encode(&absolute.to_string_lossy(), true));
+    if let Some(page) = page {
+        // Understood by most PDF viewers and by browsers' built-in ones
+        url.push_str(&format!("#page={}", page));
+    }
+    url
+}
+
+/// Percent-encode everything but unreserved characters, and `/` when `keep_slashes`
+fn percent_encode(text: &str, keep_slashes: bool) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if keep_slashes => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_use_templates_and_fall_back_to_files() {
+        let config = LinkConfig {
+            mode: LinkMode::Auto,
+            document_url: Some("http://localhost:8080/documents/{id}".to_string()),
+            page_url: Some("http://localhost:8080/documents/{id}/pages/{page}".to_string()),
+        };
+        let linker = Linker::new(&config, true);
+        assert_eq!(linker.url(LinkTarget::page("a.pdf", Some(7), 3)), "http://localhost:8080/documents/7/pages/3");
+        assert_eq!(linker.url(LinkTarget::document("a.pdf", Some(7))), "http://localhost:8080/documents/7");
+        assert_eq!(
+            linker.link(LinkTarget::document("a.pdf", Some(7)), "a.pdf"),
+            "\x1b]8;;http://localhost:8080/documents/7\x1b\\a.pdf\x1b]8;;\x1b\\"
+        );
+
+        // No id to fill the template with
+        let url = linker.url(LinkTarget::page("/scans/Q3 report.pdf", None, 2));
+        assert_eq!(url, "file:///scans/Q3%20report.pdf#page=2");
+
+        assert_eq!(Linker::new(&config, false).link(LinkTarget::document("a.pdf", None), "a.pdf"), "a.pdf");
+        let always = LinkConfig { mode: LinkMode::Always, ..LinkConfig::default() };
+        assert!(Linker::new(&always, false).link(LinkTarget::document("a.pdf", None), "a.pdf").starts_with("\x1b]8;;file://"));
+    }
+}
@@ -0,0 +1,164 @@
+// Corpus ingest - fast parallel pre-scan that hashes and fingerprints PDFs
+//
+// The scan registers every file in the database without running text extraction,
+// so large corpora can be prioritized (by size, page count, scanned vs native)
+// before the expensive extraction pass.
+
+use anyhow::Result;
+use lopdf::Document;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use crate::pdf_extraction::DocumentAnalyzer;
+use crate::storage::FileRecord;
+
+/// Recursively collect every .pdf file under a directory
+pub fn find_pdfs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut pdfs = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        let entries = match std::fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("[INGEST] Skipping {:?}: {}", current, e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let file_type = match entry.file_type() {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() && is_pdf(&path) {
+                pdfs.push(path);
+            }
+        }
+    }
+
+    pdfs.sort();
+    Ok(pdfs)
+}
+
+pub(crate) fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false)
+}
+
+/// Size and mtime (unix seconds) straight from the filesystem - cheap, no file read
+pub fn file_stat(path: &Path) -> Result<(u64, i64)> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata.modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    Ok((metadata.len(), modified))
+}
+
+/// Stream the file through SHA-256 without loading it fully into memory
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hash and fingerprint a single PDF. Unparseable PDFs are still registered (status "failed")
+/// so they show up in the corpus instead of silently disappearing.
+pub fn scan_file(path: &Path) -> Result<FileRecord> {
+    let (size_bytes, modified_at) = file_stat(path)?;
+    let sha256 = sha256_file(path)?;
+
+    let mut record = FileRecord {
+        path: path.to_string_lossy().to_string(),
+        sha256,
+        size_bytes,
+        modified_at,
+        page_count: None,
+        pdf_version: None,
+        text_coverage: 0.0,
+        image_coverage: 0.0,
+        status: "scanned".to_string(),
+    };
+
+    match Document::load(path) {
+        Ok(document) => {
+            record.page_count = Some(document.get_pages().len());
+            record.pdf_version = Some(document.version.clone());
+        }
+        Err(e) => {
+            tracing::warn!("[INGEST] Failed to parse {:?}: {}", path, e);
+            record.status = "failed".to_string();
+            return Ok(record);
+        }
+    }
+
+    // First-page fingerprint is enough to tell scanned from born-digital documents
+    if record.page_count.unwrap_or(0) > 0 {
+        if let Ok(fingerprint) = DocumentAnalyzer::new()?.analyze_page(path, 0) {
+            record.text_coverage = fingerprint.text_coverage;
+            record.image_coverage = fingerprint.image_coverage;
+        }
+    }
+
+    Ok(record)
+}
+
+/// Scan files across `jobs` worker threads. `on_progress` is called with the number of
+/// files finished so far after each one completes.
+pub fn scan_files<F>(paths: &[PathBuf], jobs: usize, on_progress: F) -> Vec<FileRecord>
+where
+    F: Fn(usize) + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(paths.len()));
+    let jobs = jobs.max(1).min(paths.len().max(1));
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(index) else { break };
+
+                match scan_file(path) {
+                    Ok(record) => results.lock().unwrap().push(record),
+                    Err(e) => tracing::warn!("[INGEST] Failed to scan {:?}: {}", path, e),
+                }
+
+                on_progress(done.fetch_add(1, Ordering::Relaxed) + 1);
+            });
+        }
+    });
+
+    let mut records = results.into_inner().unwrap();
+    records.sort_by(|a, b| a.path.cmp(&b.path));
+    records
+}
+
+/// Default worker count - one per available core
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
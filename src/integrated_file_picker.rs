@@ -1,4 +1,15 @@
 // Integrated file picker that runs within the TUI screen
+//
+// PDFs under every search root in ui.toml ([picker] roots) are indexed on a
+// background thread and handed to the matcher as they are found, so a large
+// tree fills in while the picker is already usable. Matching is fuzzy and
+// path-aware: letters from a file name score above the same letters scattered
+// through its directories.
+//
+// Before anything is typed, pinned files and recently opened or extracted ones
+// are listed above the search results, so the usual documents are an Enter or
+// two away. The renderer keeps both lists in the database; the picker only
+// tracks them for the session.
 use anyhow::Result;
 use crossterm::{
     cursor::MoveTo,
@@ -8,14 +19,31 @@ use crossterm::{
 };
 use nucleo::{Config, Nucleo, Utf32String};
 use std::io::{stdout, Write};
-use std::path::PathBuf;
-use std::process::Command;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use crate::theme::ChonkerTheme;
+use std::thread;
+use crate::theme;
+
+/// How many recent files are listed
+pub const RECENT_LIMIT: usize = 8;
+
+/// Why a file is listed above the search results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortcut {
+    Pinned,
+    Recent,
+}
 
 pub struct IntegratedFilePicker {
     nucleo: Nucleo<Arc<str>>,
-    files: Vec<String>,
+    /// Set while the roots are still being walked
+    indexing: Arc<AtomicBool>,
+    /// Canonical paths, both lists; files that have gone are dropped
+    pinned: Vec<String>,
+    recent: Vec<String>,
+    shortcuts: Vec<(String, Shortcut)>,
     query: String,
     selected_index: usize,
     scroll_offset: usize,
@@ -23,39 +51,90 @@ pub struct IntegratedFilePicker {
 }
 
 impl IntegratedFilePicker {
-    pub fn new() -> Result<Self> {
-        let files = find_pdf_files()?;
-        
-        let mut nucleo = Nucleo::<Arc<str>>::new(
-            Config::DEFAULT,
+    /// Start indexing `roots`; files turn up in the list as they are found
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let nucleo = Nucleo::<Arc<str>>::new(
+            Config::DEFAULT.match_paths(),
             Arc::new(|| {}),
             None,
             1,
         );
 
-        // Add all files as items
         let injector = nucleo.injector();
-        for file in &files {
-            let file_arc: Arc<str> = Arc::from(file.as_str());
-            let _ = injector.push(file_arc.clone(), |data, cols: &mut [Utf32String]| {
-                cols[0] = data.as_ref().into();
+        let indexing = Arc::new(AtomicBool::new(true));
+        let running = indexing.clone();
+        thread::spawn(move || {
+            index_roots(&roots, |file| {
+                let file: Arc<str> = Arc::from(file.to_string_lossy().as_ref());
+                let _ = injector.push(file, |data, cols: &mut [Utf32String]| {
+                    cols[0] = data.as_ref().into();
+                });
             });
-        }
+            running.store(false, Ordering::Relaxed);
+        });
 
-        Ok(Self {
+        Self {
             nucleo,
-            files,
+            indexing,
+            pinned: Vec::new(),
+            recent: Vec::new(),
+            shortcuts: Vec::new(),
             query: String::new(),
             selected_index: 0,
             scroll_offset: 0,
             initialized: true,
-        })
+        }
+    }
+
+    /// Let the matcher catch up with new files and the query. True when the list changed.
+    pub fn poll(&mut self) -> bool {
+        self.nucleo.tick(10).changed
+    }
+
+    /// Pinned and recent files from storage, recent newest first
+    pub fn set_shortcuts(&mut self, pinned: &[String], recent: &[String]) {
+        self.pinned = pinned.iter().filter_map(|path| canonical(path)).collect();
+        self.recent = recent.iter().filter_map(|path| canonical(path)).collect();
+        self.shortcuts = shortcut_list(&self.pinned, &self.recent);
+    }
+
+    /// Move an opened file to the top of the recent list
+    pub fn record_opened(&mut self, path: &str) {
+        let Some(path) = canonical(path) else { return };
+        self.recent.retain(|recent| *recent != path);
+        self.recent.insert(0, path);
+        self.shortcuts = shortcut_list(&self.pinned, &self.recent);
+    }
+
+    /// Pin the selected file, or unpin it. Returns its canonical path and whether it is now pinned.
+    pub fn toggle_pin(&mut self) -> Option<(String, bool)> {
+        let path = canonical(&self.get_selected_file()?.to_string_lossy())?;
+        let pinned = !self.pinned.contains(&path);
+        if pinned {
+            self.pinned.push(path.clone());
+            self.pinned.sort();
+        } else {
+            self.pinned.retain(|pin| *pin != path);
+        }
+        self.shortcuts = shortcut_list(&self.pinned, &self.recent);
+        self.selected_index = self.selected_index.min(self.entries().len().saturating_sub(1));
+        Some((path, pinned))
+    }
+
+    /// What is listed, in order: shortcuts (while the query is empty), then matches
+    fn entries(&self) -> Vec<(String, Option<Shortcut>)> {
+        let shortcuts = self.shortcuts.iter().filter(|_| self.query.is_empty()).map(|(path, kind)| (path.clone(), Some(*kind)));
+        let snapshot = self.nucleo.snapshot();
+        let matches = snapshot.matched_items(..).map(|item| (item.data.to_string(), None));
+        shortcuts.chain(matches).collect()
     }
 
     pub fn render(&mut self, width: u16, height: u16) -> Result<()> {
         if !self.initialized {
             return Ok(());
         }
+        let theme = theme::current();
+        self.nucleo.tick(10);
 
         // Clear the screen area
         execute!(
@@ -69,11 +148,11 @@ impl IntegratedFilePicker {
         execute!(
             stdout(),
             MoveTo(0, 0),
-            SetForegroundColor(ChonkerTheme::accent_load_file()),
+            SetForegroundColor(theme.accent_load_file),
             Print(format!("  {:<width$}", header_text, width = (width - 2) as usize)),
             ResetColor,
             MoveTo(0, 1),
-            SetForegroundColor(ChonkerTheme::accent_load_file()),
+            SetForegroundColor(theme.accent_load_file),
             Print(" ".repeat(width as usize)),
             ResetColor,
             Print("\n")
@@ -83,22 +162,22 @@ impl IntegratedFilePicker {
         execute!(
             stdout(),
             MoveTo(0, 3),
-            SetForegroundColor(ChonkerTheme::accent_text()),
+            SetForegroundColor(theme.accent_text),
             Print("  🔍 Search: "),
-            SetForegroundColor(ChonkerTheme::text_primary()),
+            SetForegroundColor(theme.text),
             Print(&self.query),
-            SetForegroundColor(ChonkerTheme::text_dim()),
+            SetForegroundColor(theme.text_dim),
             Print("_"),
             ResetColor,
             Print("\n\n")
         )?;
 
         // Get filtered results
-        let snapshot = self.nucleo.snapshot();
-        let all_matches = snapshot.matched_items(..).collect::<Vec<_>>();
+        let all_matches = self.entries();
+        let home = dirs::home_dir().map(|home| home.to_string_lossy().to_string());
 
         // Calculate display parameters
-        let max_path_width = (width as usize).saturating_sub(5);
+        let max_path_width = (width as usize).saturating_sub(7);
         let max_display_items = (height as usize).saturating_sub(9).min(15);
 
         // Update scroll offset to keep selected item visible
@@ -116,21 +195,20 @@ impl IntegratedFilePicker {
             .collect::<Vec<_>>();
 
         // Draw matches
-        for (display_i, item) in visible_matches.iter().enumerate() {
+        for (display_i, (path, shortcut)) in visible_matches.iter().enumerate() {
             let actual_index = self.scroll_offset + display_i;
-            let path = item.data.as_ref();
-
-            // Strip common prefixes for cleaner display
-            let clean_path = if path.starts_with("/Users/jack/Downloads/") {
-                &path[22..] // Length of "/Users/jack/Downloads/"
-            } else if path.starts_with("/Users/jack/Desktop/") {
-                &path[20..] // Length of "/Users/jack/Desktop/"
-            } else if path.starts_with("/Users/jack/Documents/") {
-                &path[22..] // Length of "/Users/jack/Documents/"
-            } else {
-                path
+            let path = path.as_str();
+            let marker = match shortcut {
+                Some(Shortcut::Pinned) => "★ ",
+                Some(Shortcut::Recent) => "↺ ",
+                None => "  ",
             };
 
+            // Paths under the home directory are shown from ~
+            let home_relative = home.as_deref().and_then(|home| path.strip_prefix(home)).filter(|rest| rest.starts_with('/'));
+            let clean_path = home_relative.map(|rest| format!("~{}", rest)).unwrap_or_else(|| path.to_string());
+            let clean_path = clean_path.as_str();
+
             let line_pos = 6 + display_i as u16;
 
             // Move to the correct line and clear it
@@ -167,9 +245,11 @@ impl IntegratedFilePicker {
             if actual_index == self.selected_index {
                 execute!(
                     stdout(),
-                    SetForegroundColor(ChonkerTheme::success()),
+                    SetForegroundColor(theme.success),
                     Print("  ▶ "),
-                    SetForegroundColor(ChonkerTheme::text_primary()),
+                    SetForegroundColor(theme.accent_text),
+                    Print(marker),
+                    SetForegroundColor(theme.text),
                     Print(&final_display),
                     ResetColor
                 )?;
@@ -177,7 +257,9 @@ impl IntegratedFilePicker {
                 execute!(
                     stdout(),
                     Print("    "),
-                    SetForegroundColor(ChonkerTheme::text_secondary()),
+                    SetForegroundColor(theme.accent_text),
+                    Print(marker),
+                    SetForegroundColor(theme.text_secondary),
                     Print(&final_display),
                     ResetColor
                 )?;
@@ -204,12 +286,22 @@ impl IntegratedFilePicker {
         } else {
             format!("  {} files", all_matches.len())
         };
+        let scroll_indicator = if self.query.is_empty() && !self.shortcuts.is_empty() {
+            format!("{}  (★ pinned, ↺ recent)", scroll_indicator)
+        } else {
+            scroll_indicator
+        };
+        let scroll_indicator = if self.indexing.load(Ordering::Relaxed) {
+            format!("{}  - indexing...", scroll_indicator)
+        } else {
+            scroll_indicator
+        };
 
         execute!(
             stdout(),
             MoveTo(0, help_line),
             Clear(ClearType::CurrentLine),
-            SetForegroundColor(ChonkerTheme::text_dim()),
+            SetForegroundColor(theme.text_dim),
             Print(&scroll_indicator),
             ResetColor
         )?;
@@ -218,16 +310,13 @@ impl IntegratedFilePicker {
             stdout(),
             MoveTo(0, help_line + 1),
             Clear(ClearType::CurrentLine),
-            SetForegroundColor(ChonkerTheme::text_dim()),
-            Print("  🔥 INTEGRATED FILE PICKER  •  Tab: Next Screen  •  Esc: Exit"),
+            SetForegroundColor(theme.text_dim),
+            Print("  🔥 INTEGRATED FILE PICKER  •  Ctrl+P: Pin/Unpin  •  Tab: Next Screen  •  Esc: Exit"),
             ResetColor
         )?;
 
         stdout().flush()?;
 
-        // Let nucleo process
-        self.nucleo.tick(10);
-
         Ok(())
     }
 
@@ -273,83 +362,81 @@ impl IntegratedFilePicker {
     }
 
     pub fn handle_down(&mut self) -> Result<()> {
-        let snapshot = self.nucleo.snapshot();
-        let all_matches = snapshot.matched_items(..).collect::<Vec<_>>();
-        
-        if self.selected_index < all_matches.len().saturating_sub(1) {
+        if self.selected_index < self.entries().len().saturating_sub(1) {
             self.selected_index += 1;
         }
         Ok(())
     }
 
     pub fn get_selected_file(&self) -> Option<PathBuf> {
-        let snapshot = self.nucleo.snapshot();
-        let all_matches = snapshot.matched_items(..).collect::<Vec<_>>();
-        
-        if self.selected_index < all_matches.len() {
-            let selected = all_matches[self.selected_index].data.as_ref();
-            Some(PathBuf::from(selected))
-        } else {
-            None
-        }
+        self.entries().into_iter().nth(self.selected_index).map(|(path, _)| PathBuf::from(path))
     }
 }
 
-/// Find all PDF files in current directory and subdirectories
-fn find_pdf_files() -> Result<Vec<String>> {
-    let search_dirs = [
-        "/Users/jack/Downloads",
-        "/Users/jack/Desktop", 
-        "/Users/jack/Documents",
-        "."
-    ];
-    
-    let mut all_files = Vec::new();
-    
-    for search_dir in &search_dirs {
-        let files = find_pdfs_in_dir(search_dir)?;
-        all_files.extend(files);
-    }
-    
-    // Remove duplicates while preserving order
-    all_files.sort();
-    all_files.dedup();
-    
-    Ok(all_files)
+fn canonical(path: &str) -> Option<String> {
+    Some(Path::new(path).canonicalize().ok()?.to_string_lossy().to_string())
 }
 
-fn find_pdfs_in_dir(search_dir: &str) -> Result<Vec<String>> {
-    // Try using fd first (faster), fallback to find
-    let output = if command_exists("fd") {
-        Command::new("fd")
-            .args(&["-e", "pdf", "-t", "f", ".", search_dir])
-            .output()
-    } else {
-        // Fallback to find command
-        Command::new("find")
-            .args(&[search_dir, "-name", "*.pdf", "-type", "f"])
-            .output()
-    };
-    
-    match output {
-        Ok(output) if output.status.success() => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let files: Vec<String> = stdout
-                .lines()
-                .map(|s| s.to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-            Ok(files)
+/// Pinned files, then the newest recent ones that aren't pinned
+fn shortcut_list(pinned: &[String], recent: &[String]) -> Vec<(String, Shortcut)> {
+    let recent = recent.iter().filter(|path| !pinned.contains(path)).take(RECENT_LIMIT);
+    pinned.iter().map(|path| (path.clone(), Shortcut::Pinned)).chain(recent.map(|path| (path.clone(), Shortcut::Recent))).collect()
+}
+
+/// Every PDF under `roots`, in root order, passed to `found` as it turns up.
+/// Hidden files and directories are skipped, and so is anything already walked
+/// through another root or a symlink.
+fn index_roots(roots: &[PathBuf], mut found: impl FnMut(PathBuf)) {
+    let mut walked = HashSet::new();
+    for root in roots {
+        let mut pending = vec![root.clone()];
+        while let Some(dir) = pending.pop() {
+            let Ok(canonical) = dir.canonicalize() else { continue };
+            if !walked.insert(canonical) {
+                continue;
+            }
+            let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+            let mut entries: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            entries.sort();
+            for path in entries {
+                if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')) {
+                    continue;
+                }
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("pdf")) {
+                    found(path);
+                }
+            }
         }
-        _ => Ok(Vec::new()) // Return empty vec on error
     }
 }
 
-/// Check if a command exists
-fn command_exists(cmd: &str) -> bool {
-    Command::new("which")
-        .arg(cmd)
-        .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_files_listed_before_recent_ones() {
+        let pinned = vec!["/docs/a.pdf".to_string()];
+        let recent: Vec<String> = (0..RECENT_LIMIT + 2).map(|i| format!("/docs/{}.pdf", i)).chain(["/docs/a.pdf".to_string()]).collect();
+        let shortcuts = shortcut_list(&pinned, &recent);
+        assert_eq!(shortcuts[0], ("/docs/a.pdf".to_string(), Shortcut::Pinned));
+        assert_eq!(shortcuts[1], ("/docs/0.pdf".to_string(), Shortcut::Recent));
+        assert_eq!(shortcuts.len(), 1 + RECENT_LIMIT);
+    }
+
+    #[test]
+    fn test_overlapping_roots_indexed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let reports = dir.path().join("reports");
+        std::fs::create_dir_all(reports.join(".cache")).unwrap();
+        for file in ["a.pdf", "reports/B.PDF", "reports/notes.txt", "reports/.cache/c.pdf"] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        let mut files = Vec::new();
+        index_roots(&[reports.clone(), dir.path().to_path_buf(), dir.path().join("missing")], |file| files.push(file));
+        assert_eq!(files, vec![reports.join("B.PDF"), dir.path().join("a.pdf")]);
+    }
+}
@@ -0,0 +1,316 @@
+// Invoice profile - `--profile invoice` on export and batch
+//
+// Writes an invoice or receipt as one normalized JSON record for accounting
+// pipelines instead of as pages: the vendor, invoice number, dates, line items
+// and totals, checked against schemas/invoice.schema.json before anything is
+// written. The keyword classifier's document type goes along with it, so a
+// pipeline can drop what isn't an invoice.
+//
+// The header fields come from the same readers that name files (naming.rs) and
+// find entities (entities.rs). Line items come from tables: those detected on
+// stored word boxes where there are any, else blocks of lines laid out in
+// columns, and only tables whose headers name an amount or price column. Totals
+// are the amounts on lines labelled subtotal, tax/VAT and total/amount due.
+// Money is a decimal string with two places, so no float rounding gets in.
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+
+use crate::entities::{decimal, page_entities, Entity, EntityKind, NUMBER};
+use crate::json_schema;
+use crate::naming::NameParts;
+use crate::output_format::{DocumentOutput, OutputFormatter, Records};
+use crate::pdf_extraction::bbox::PageWords;
+use crate::scheduler::classify_text;
+use crate::tables::detect_tables;
+
+pub const PROFILE: &str = "invoice";
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// schemas/invoice.schema.json, which every record is checked against
+pub static SCHEMA: Lazy<Value> =
+    Lazy::new(|| serde_json::from_str(include_str!("../schemas/invoice.schema.json")).expect("bundled invoice schema is valid JSON"));
+
+static MONEY: Lazy<Regex> = Lazy::new(|| Regex::new(&format!(r"(?:{})", NUMBER)).unwrap());
+static COLUMN_GAP: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s{2,}").unwrap());
+static SUBTOTAL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bsub-?\s?total\b|\bnet\s+(?:total|amount)\b").unwrap());
+static TAX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b(?:sales\s+)?(?:tax|vat|gst|hst|mwst)\b").unwrap());
+static TOTAL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\btotal\b|\b(?:amount|balance)\s+due\b").unwrap());
+static DUE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b(?:due|payable)\b").unwrap());
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Vendor {
+    pub name: Option<String>,
+    pub address: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub tax_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LineItem {
+    pub description: String,
+    pub quantity: Option<f64>,
+    pub unit_price: Option<String>,
+    pub amount: Option<String>,
+    pub page: usize, // 1-based
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Totals {
+    pub subtotal: Option<String>,
+    pub tax: Option<String>,
+    pub total: Option<String>,
+}
+
+/// One invoice or receipt as the profile writes it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Invoice {
+    pub profile: &'static str,
+    pub schema_version: u32,
+    pub source: String,
+    pub document_type: Option<&'static str>,
+    pub vendor: Vendor,
+    pub invoice_number: Option<String>,
+    pub date: Option<String>,
+    pub due_date: Option<String>,
+    pub currency: Option<String>,
+    pub line_items: Vec<LineItem>,
+    pub totals: Totals,
+}
+
+/// The first decimal in `text` as money, "1,200.5" giving "1200.50"
+fn money(text: &str) -> Option<String> {
+    MONEY.find(text).map(|number| decimal(number.as_str()))
+}
+
+/// The last decimal on a labelled line, i.e. the one after the label
+fn labelled_money(line: &str) -> Option<String> {
+    MONEY.find_iter(line).last().map(|number| decimal(number.as_str()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Column {
+    Description,
+    Quantity,
+    UnitPrice,
+    Amount,
+}
+
+fn column(header: &str) -> Option<Column> {
+    let header = header.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|word| header.contains(word));
+    if has(&["qty", "quantity", "units", "hours", "hrs", "pcs"]) {
+        Some(Column::Quantity)
+    } else if has(&["unit", "price", "rate", "each"]) {
+        Some(Column::UnitPrice)
+    } else if has(&["amount", "total", "sum"]) {
+        Some(Column::Amount)
+    } else if has(&["description", "item", "product", "service", "details", "article"]) {
+        Some(Column::Description)
+    } else {
+        None
+    }
+}
+
+/// Blocks of 2+ lines split into cells by runs of spaces, the first line the header
+fn text_tables(text: &str) -> Vec<(Vec<String>, Vec<Vec<String>>)> {
+    let mut tables = Vec::new();
+    let mut block: Vec<Vec<String>> = Vec::new();
+    for line in text.lines().chain([""]) {
+        let cells: Vec<String> = COLUMN_GAP.split(line.trim()).filter(|cell| !cell.is_empty()).map(str::to_string).collect();
+        if cells.len() >= 2 {
+            block.push(cells);
+            continue;
+        }
+        if block.len() >= 2 {
+            let headers = block.remove(0);
+            tables.push((headers, std::mem::take(&mut block)));
+        }
+        block.clear();
+    }
+    tables
+}
+
+/// Line items from one table, if its headers say it lists them
+fn table_items(headers: &[String], rows: &[Vec<String>], page: usize) -> Vec<LineItem> {
+    let mut columns: Vec<Option<Column>> = headers.iter().map(|header| column(header)).collect();
+    // A price with no quantity or amount beside it (receipts) is what the line costs
+    if !columns.iter().any(|column| matches!(column, Some(Column::Amount | Column::Quantity))) {
+        for column in columns.iter_mut().filter(|column| **column == Some(Column::UnitPrice)) {
+            *column = Some(Column::Amount);
+        }
+    }
+    let priced = columns.iter().any(|column| matches!(column, Some(Column::Amount | Column::UnitPrice)));
+    let description = columns.iter().position(|column| *column == Some(Column::Description)).or_else(|| columns.iter().position(Option::is_none));
+    let Some(description) = description.filter(|_| priced) else { return Vec::new() };
+
+    let mut items = Vec::new();
+    for row in rows {
+        // A short row keeps its first cell and is missing cells after it, not
+        // the prices on the right
+        let shift = headers.len().saturating_sub(row.len());
+        let cell = |index: usize| -> Option<&str> {
+            let at = if index == 0 { 0 } else { index.checked_sub(shift).filter(|&at| at > 0)? };
+            row.get(at).map(String::as_str)
+        };
+        let value = |wanted: Column| columns.iter().position(|column| *column == Some(wanted)).and_then(cell);
+        let text = cell(description).unwrap_or_default().trim().to_string();
+        if text.is_empty() || SUBTOTAL.is_match(&text) || TAX.is_match(&text) || TOTAL.is_match(&text) {
+            continue;
+        }
+        let item = LineItem {
+            description: text,
+            quantity: value(Column::Quantity).and_then(money).and_then(|quantity| quantity.parse().ok()),
+            unit_price: value(Column::UnitPrice).and_then(money),
+            amount: value(Column::Amount).and_then(money),
+            page,
+        };
+        if item.quantity.is_some() || item.unit_price.is_some() || item.amount.is_some() {
+            items.push(item);
+        }
+    }
+    items
+}
+
+/// Read an invoice out of a document's pages
+pub fn read_invoice(document: &DocumentOutput) -> Invoice {
+    let text: String = document.pages.iter().map(|page| page.text.as_str()).collect::<Vec<_>>().join("\x0c");
+    let parts = NameParts::detect(&text);
+    let mut entities: Vec<Entity> = Vec::new();
+    let mut due_date = None;
+    let mut totals = Totals::default();
+    let mut currency = None;
+    let mut line_items = Vec::new();
+
+    for page in &document.pages {
+        for line in page.text.lines() {
+            let found = page_entities(line, page.number);
+            let amount = found.iter().rev().find(|entity| entity.kind == EntityKind::Amount);
+            let is_id = found.iter().any(|entity| entity.kind == EntityKind::Id);
+            if due_date.is_none() && DUE.is_match(line) {
+                due_date = found.iter().find(|entity| entity.kind == EntityKind::Date).map(|date| date.value.clone());
+            }
+            let slot = if SUBTOTAL.is_match(line) {
+                Some(&mut totals.subtotal)
+            } else if TOTAL.is_match(line) {
+                Some(&mut totals.total)
+            } else if TAX.is_match(line) && !is_id {
+                Some(&mut totals.tax)
+            } else {
+                None
+            };
+            let labelled = found.iter().filter(|entity| entity.kind == EntityKind::Date).fold(line.to_string(), |line, date| line.replace(&date.text, ""));
+            if let (Some(slot), Some(value)) = (slot, labelled_money(&labelled)) {
+                *slot = Some(value);
+                if let Some(amount) = amount {
+                    currency = amount.value.split_once(' ').map(|(_, code)| code.to_string());
+                }
+            }
+            entities.extend(found);
+        }
+
+        let tables = if page.words.is_empty() {
+            text_tables(&page.text)
+        } else {
+            let (width, height) = page.size.unwrap_or_default();
+            let words = PageWords { width, height, words: page.words.clone() };
+            detect_tables(page.number, &words).into_iter().map(|table| (table.headers, table.rows)).collect()
+        };
+        for (headers, rows) in tables {
+            line_items.extend(table_items(&headers, &rows, page.number));
+        }
+    }
+
+    let first = |kind: EntityKind| entities.iter().find(|entity| entity.kind == kind).map(|entity| entity.value.clone());
+    let currency = currency.or_else(|| first(EntityKind::Amount).and_then(|amount| amount.split_once(' ').map(|(_, code)| code.to_string())));
+    let tax_id = entities
+        .iter()
+        .find(|entity| entity.kind == EntityKind::Id && ["vat", "tax", "ein"].iter().any(|label| entity.text.to_lowercase().starts_with(label)))
+        .map(|entity| entity.value.clone());
+
+    Invoice {
+        profile: PROFILE,
+        schema_version: SCHEMA_VERSION,
+        source: document.path.clone(),
+        document_type: classify_text(&text),
+        vendor: Vendor { name: parts.party, address: first(EntityKind::Address), email: first(EntityKind::Email), phone: first(EntityKind::Phone), tax_id },
+        invoice_number: parts.reference,
+        date: parts.date.map(|date| date.format("%Y-%m-%d").to_string()),
+        due_date,
+        currency,
+        line_items,
+        totals,
+    }
+}
+
+/// The profile as an output format: one schema-checked JSON record per document
+pub struct InvoiceFormatter;
+
+impl OutputFormatter for InvoiceFormatter {
+    fn name(&self) -> &'static str {
+        PROFILE
+    }
+
+    fn extension(&self) -> &'static str {
+        "invoice.json"
+    }
+
+    fn write_records(&self, _out: &mut dyn Write, _records: &Records) -> Result<()> {
+        bail!("The invoice profile writes documents, not result lists")
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        let invoice = read_invoice(document);
+        if !matches!(invoice.document_type, Some("invoice" | "receipt")) {
+            tracing::warn!("[INVOICE] {} doesn't read as an invoice or receipt ({})", document.path, invoice.document_type.unwrap_or("unclassified"));
+        }
+        json_schema::validate(&SCHEMA, &serde_json::to_value(&invoice)?)?;
+        writeln!(out, "{}", serde_json::to_string_pretty(&invoice)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invoice_read_from_columns_and_labels() {
+        let page = "ACME Widgets, Inc.\n12 Main St, Springfield, IL 62701\nbilling@acme.example\nVAT No: GB123456789\n\n\
+                    INVOICE\nInvoice No: 12345\nInvoice date: March 14, 2024\nBill to: Globex\nDue date: 2024-04-13\n\n\
+                    Description        Qty    Unit Price      Amount\n\
+                    Widget, large        4         2.50       10.00\n\
+                    Setup service        1     1,200.00    1,200.00\n\
+                    Subtotal                               1,210.00\n\n\
+                    VAT 20%                                  242.00\n\
+                    Total due                             $1,452.00\n";
+        let document = DocumentOutput::from_content("acme.pdf", page);
+        let invoice = read_invoice(&document);
+        assert_eq!(invoice.document_type, Some("invoice"));
+        assert_eq!(invoice.vendor.name.as_deref(), Some("ACME Widgets"));
+        assert_eq!(invoice.vendor.address.as_deref(), Some("12 Main St, Springfield, IL 62701"));
+        assert_eq!(invoice.vendor.tax_id.as_deref(), Some("GB123456789"));
+        assert_eq!(invoice.invoice_number.as_deref(), Some("12345"));
+        assert_eq!((invoice.date.as_deref(), invoice.due_date.as_deref()), (Some("2024-03-14"), Some("2024-04-13")));
+        assert_eq!(invoice.currency.as_deref(), Some("USD"));
+        assert_eq!(
+            invoice.line_items,
+            vec![
+                LineItem { description: "Widget, large".to_string(), quantity: Some(4.0), unit_price: Some("2.50".to_string()), amount: Some("10.00".to_string()), page: 1 },
+                LineItem { description: "Setup service".to_string(), quantity: Some(1.0), unit_price: Some("1200.00".to_string()), amount: Some("1200.00".to_string()), page: 1 },
+            ]
+        );
+        assert_eq!(
+            invoice.totals,
+            Totals { subtotal: Some("1210.00".to_string()), tax: Some("242.00".to_string()), total: Some("1452.00".to_string()) }
+        );
+
+        let mut written = Vec::new();
+        InvoiceFormatter.write_document(&mut written, &document).unwrap();
+        assert!(json_schema::violations(&SCHEMA, &serde_json::from_slice(&written).unwrap()).is_empty());
+    }
+}
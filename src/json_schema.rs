@@ -0,0 +1,166 @@
+// JSON Schema checks for the structured output profiles
+//
+// Covers the part of JSON Schema the bundled schemas (schemas/*.schema.json)
+// use: type (one or a list), properties, required, additionalProperties: true
+// or false, items, enum, pattern and minimum. Annotations ($schema, $id,
+// $comment, title, description, default, examples) are ignored. Any other
+// keyword is reported as unsupported rather than skipped, so a schema can't
+// ask for more than is actually checked. Every violation is reported with the
+// JSON pointer it was found at, not just the first.
+use anyhow::{bail, Result};
+use regex::Regex;
+use serde_json::Value;
+
+/// Keywords check() enforces; properties, items and additionalProperties are handled apart
+const ASSERTIONS: &[&str] = &["type", "enum", "pattern", "minimum", "required"];
+const ANNOTATIONS: &[&str] = &["$schema", "$id", "$comment", "title", "description", "default", "examples"];
+
+/// Report every keyword in the schema, nested ones included, that check() doesn't understand
+fn unsupported(schema: &Value, at: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        errors.push(format!("schema {}: expected an object, got {}", at_or_root(at), schema));
+        return;
+    };
+    for (keyword, value) in schema {
+        match keyword.as_str() {
+            "properties" => {
+                for (name, property) in value.as_object().into_iter().flatten() {
+                    unsupported(property, &format!("{}/properties/{}", at, name), errors);
+                }
+            }
+            "items" => unsupported(value, &format!("{}/items", at), errors),
+            "additionalProperties" if value.is_boolean() => {}
+            keyword if ASSERTIONS.contains(&keyword) || ANNOTATIONS.contains(&keyword) => {}
+            _ => errors.push(format!("schema {}: unsupported keyword \"{}\"", at_or_root(at), keyword)),
+        }
+    }
+}
+
+fn type_matches(name: &str, value: &Value) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+fn check(schema: &Value, value: &Value, at: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else { return };
+
+    if let Some(types) = schema.get("type") {
+        let names: Vec<&str> = match types {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !names.iter().any(|name| type_matches(name, value)) {
+            errors.push(format!("{}: expected {}, got {}", at_or_root(at), names.join(" or "), value));
+            return;
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            errors.push(format!("{}: {} is not one of {}", at_or_root(at), value, Value::Array(allowed.clone())));
+        }
+    }
+    if let (Some(pattern), Some(text)) = (schema.get("pattern").and_then(Value::as_str), value.as_str()) {
+        match Regex::new(pattern) {
+            Ok(pattern) if !pattern.is_match(text) => errors.push(format!("{}: \"{}\" doesn't match {}", at_or_root(at), text, pattern)),
+            Ok(_) => {}
+            Err(e) => errors.push(format!("{}: bad pattern in schema: {}", at_or_root(at), e)),
+        }
+    }
+    if let (Some(minimum), Some(number)) = (schema.get("minimum").and_then(Value::as_f64), value.as_f64()) {
+        if number < minimum {
+            errors.push(format!("{}: {} is below the minimum {}", at_or_root(at), number, minimum));
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                errors.push(format!("{}: missing required \"{}\"", at_or_root(at), name));
+            }
+        }
+        for (name, item) in object {
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property) => check(property, item, &format!("{}/{}", at, name), errors),
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    errors.push(format!("{}: unexpected \"{}\"", at_or_root(at), name));
+                }
+                None => {}
+            }
+        }
+    }
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in array.iter().enumerate() {
+            check(items, item, &format!("{}/{}", at, index), errors);
+        }
+    }
+}
+
+fn at_or_root(at: &str) -> &str {
+    if at.is_empty() { "/" } else { at }
+}
+
+/// Every way `value` breaks `schema`, empty when it conforms
+pub fn violations(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    unsupported(schema, "", &mut errors);
+    if errors.is_empty() {
+        check(schema, value, "", &mut errors);
+    }
+    errors
+}
+
+/// Fail with every violation when `value` doesn't conform to `schema`
+pub fn validate(schema: &Value, value: &Value) -> Result<()> {
+    let errors = violations(schema, value);
+    if !errors.is_empty() {
+        bail!("Output doesn't match its schema:\n  {}", errors.join("\n  "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_every_violation_reported_with_its_pointer() {
+        let schema = json!({
+            "type": "object",
+            "required": ["total", "items"],
+            "additionalProperties": false,
+            "properties": {
+                "total": { "type": ["string", "null"], "pattern": "^\\d+\\.\\d{2}$" },
+                "kind": { "enum": ["invoice", "receipt"] },
+                "items": { "type": "array", "items": { "type": "object", "properties": { "quantity": { "type": "number", "minimum": 0 } } } }
+            }
+        });
+        assert!(violations(&schema, &json!({ "total": "12.50", "items": [{ "quantity": 2 }] })).is_empty());
+        assert!(violations(&schema, &json!({ "total": null, "kind": "receipt", "items": [] })).is_empty());
+        assert_eq!(
+            violations(&schema, &json!({ "total": "12.5", "kind": "memo", "items": [{ "quantity": -1 }, "x"], "extra": 1 })),
+            vec![
+                "/: unexpected \"extra\"",
+                "/items/0/quantity: -1 is below the minimum 0",
+                "/items/1: expected object, got \"x\"",
+                "/kind: \"memo\" is not one of [\"invoice\",\"receipt\"]",
+                "/total: \"12.5\" doesn't match ^\\d+\\.\\d{2}$",
+            ]
+        );
+        assert!(validate(&schema, &json!({ "items": [] })).unwrap_err().to_string().contains("missing required \"total\""));
+
+        // Keywords outside the subset are refused, not silently passed
+        let unchecked = json!({ "type": "object", "properties": { "total": { "type": "string", "maxLength": 3 } } });
+        assert_eq!(violations(&unchecked, &json!({ "total": "1234" })), vec!["schema /properties/total: unsupported keyword \"maxLength\""]);
+    }
+}
@@ -1,15 +1,19 @@
 // Kitty Graphics Protocol implementation for terminal image display
 use anyhow::{Result, bail};
 use image::{DynamicImage, ImageFormat};
-use std::io::Cursor;
+use std::io::{Cursor, Write};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
+/// Cell size in pixels when the terminal doesn't report its pixel dimensions
+const DEFAULT_CELL_SIZE: (f32, f32) = (8.0, 16.0);
+
 /// Kitty graphics protocol handler
 pub struct KittyProtocol {
     supported: bool,
     next_image_id: u32,
     active_images: Vec<u32>,
     chunk_size: usize,
+    passthrough: bool,
 }
 
 impl KittyProtocol {
@@ -20,6 +24,7 @@ impl KittyProtocol {
             next_image_id: 1,
             active_images: Vec::new(),
             chunk_size: 4096, // 4KB chunks for safe transmission
+            passthrough: false,
         }
     }
     
@@ -50,6 +55,20 @@ impl KittyProtocol {
         self.supported
     }
     
+    /// Wrap the graphics commands from transmit/place/delete_image in tmux passthrough
+    pub fn set_passthrough(&mut self, passthrough: bool) {
+        self.passthrough = passthrough;
+    }
+    
+    fn emit(&self, out: &mut impl Write, command: &[u8]) -> Result<()> {
+        if self.passthrough {
+            out.write_all(&crate::graphics::tmux_wrap(command))?;
+        } else {
+            out.write_all(command)?;
+        }
+        Ok(())
+    }
+    
     /// Force enable Kitty support (for testing)
     pub fn force_enable(&mut self) {
         self.supported = true;
@@ -64,23 +83,23 @@ impl KittyProtocol {
         width: Option<u32>,
         height: Option<u32>,
     ) -> Result<u32> {
-        eprintln!("[KITTY] display_image called at ({}, {}) supported={}", x, y, self.supported);
+        crate::debug_log!("KITTY", "display_image called at ({}, {}) supported={}", x, y, self.supported);
         if !self.supported {
             bail!("Kitty graphics protocol not supported");
         }
         
         // Move cursor to the position first
-        eprintln!("[KITTY] Moving cursor to ({}, {})", y + 1, x + 1);
+        crate::debug_log!("KITTY", "Moving cursor to ({}, {})", y + 1, x + 1);
         print!("\x1b[{};{}H", y + 1, x + 1);  // Terminal positions are 1-based
         use std::io::{self, Write};
         let _ = io::stdout().flush();
         
         // Convert image to PNG format
-        eprintln!("[KITTY] Converting image to PNG format");
+        crate::debug_log!("KITTY", "Converting image to PNG format");
         let mut png_data = Vec::new();
         let mut cursor = Cursor::new(&mut png_data);
         image.write_to(&mut cursor, ImageFormat::Png)?;
-        eprintln!("[KITTY] PNG data size: {} bytes", png_data.len());
+        crate::debug_log!("KITTY", "PNG data size: {} bytes", png_data.len());
         
         // Get dimensions - use smaller size for testing
         let (img_width, img_height) = (image.width(), image.height());
@@ -88,13 +107,13 @@ impl KittyProtocol {
         let scale = 0.2; // Scale to 20% for testing
         let display_width = ((img_width as f32 * scale) as u32).max(100);
         let display_height = ((img_height as f32 * scale) as u32).max(100);
-        eprintln!("[KITTY] Image dimensions: {}x{}, display: {}x{}", 
+        crate::debug_log!("KITTY", "Image dimensions: {}x{}, display: {}x{}", 
                  img_width, img_height, display_width, display_height);
         
         // Transmit and display image at current cursor position
-        eprintln!("[KITTY] Starting image transmission...");
+        crate::debug_log!("KITTY", "Starting image transmission...");
         let image_id = self.transmit_image_data(&png_data, display_width, display_height)?;
-        eprintln!("[KITTY] Image transmitted successfully with ID: {}", image_id);
+        crate::debug_log!("KITTY", "Image transmitted successfully with ID: {}", image_id);
         
         self.active_images.push(image_id);
         Ok(image_id)
@@ -109,13 +128,13 @@ impl KittyProtocol {
     ) -> Result<u32> {
         let image_id = self.next_image_id;
         self.next_image_id += 1;
-        eprintln!("[KITTY] transmit_image_data: ID={}, size={}x{}, data_len={}", 
+        crate::debug_log!("KITTY", "transmit_image_data: ID={}, size={}x{}, data_len={}", 
                  image_id, width, height, data.len());
         
         // Split data into chunks
         let chunks: Vec<&[u8]> = data.chunks(self.chunk_size).collect();
         let total_chunks = chunks.len();
-        eprintln!("[KITTY] Splitting into {} chunks of max {} bytes", total_chunks, self.chunk_size);
+        crate::debug_log!("KITTY", "Splitting into {} chunks of max {} bytes", total_chunks, self.chunk_size);
         
         for (i, chunk) in chunks.iter().enumerate() {
             let is_first = i == 0;
@@ -123,7 +142,7 @@ impl KittyProtocol {
             
             // Encode chunk to base64
             let encoded = BASE64.encode(chunk);
-            eprintln!("[KITTY] Chunk {}/{}: {} bytes -> {} base64 chars", 
+            crate::debug_log_throttled!("KITTY", "Chunk {}/{}: {} bytes -> {} base64 chars", 
                      i+1, total_chunks, chunk.len(), encoded.len());
             
             // Build control data
@@ -141,7 +160,7 @@ impl KittyProtocol {
                     height,
                     if is_last { 0 } else { 1 }
                 ));
-                eprintln!("[KITTY] First chunk control: {}", control);
+                crate::debug_log!("KITTY", "First chunk control: {}", control);
             } else {
                 // Continuation chunks
                 control.push_str(&format!(
@@ -150,7 +169,7 @@ impl KittyProtocol {
                     if is_last { 0 } else { 1 }
                 ));
                 if is_last {
-                    eprintln!("[KITTY] Last chunk control: {}", control);
+                    crate::debug_log!("KITTY", "Last chunk control: {}", control);
                 }
             }
             
@@ -160,7 +179,7 @@ impl KittyProtocol {
             let _ = io::stdout().flush();
         }
         
-        eprintln!("[KITTY] All {} chunks transmitted", total_chunks);
+        crate::debug_log!("KITTY", "All {} chunks transmitted", total_chunks);
         Ok(image_id)
     }
     
@@ -174,6 +193,103 @@ impl KittyProtocol {
         Ok(())
     }
     
+    /// Transmit an image without displaying it. Show it with `place()`; the data
+    /// stays in the terminal until `delete_image()`, so re-placing is cheap.
+    pub fn transmit(&mut self, image: &DynamicImage) -> Result<u32> {
+        if !self.supported {
+            bail!("Kitty graphics protocol not supported");
+        }
+
+        let mut png_data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png)?;
+        let encoded = BASE64.encode(&png_data);
+
+        let image_id = self.next_image_id;
+        self.next_image_id += 1;
+
+        // Chunk the base64 text, not the raw bytes, so every chunk decodes on its own
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(self.chunk_size).collect();
+        let mut out = std::io::stdout().lock();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = if i + 1 < chunks.len() { 1 } else { 0 };
+            let mut command = if i == 0 {
+                // a=t: transmit only, q=2: no replies cluttering the input stream
+                format!("\x1b_Ga=t,f=100,q=2,i={},m={};", image_id, more).into_bytes()
+            } else {
+                format!("\x1b_Gm={};", more).into_bytes()
+            };
+            command.extend_from_slice(chunk);
+            command.extend_from_slice(b"\x1b\\");
+            self.emit(&mut out, &command)?;
+        }
+        out.flush()?;
+
+        crate::debug_log!("KITTY", "Transmitted image {} ({} bytes PNG, {} chunks)", image_id, png_data.len(), chunks.len());
+        self.active_images.push(image_id);
+        Ok(image_id)
+    }
+
+    /// Show a transmitted image scaled to exactly `cols` x `rows` cells with its
+    /// top-left cell at (col, row). Placing again with the same placement id moves
+    /// or resizes the existing placement instead of stacking another copy.
+    pub fn place(&self, image_id: u32, placement_id: u32, col: u16, row: u16, cols: u16, rows: u16) -> Result<()> {
+        if !self.supported {
+            bail!("Kitty graphics protocol not supported");
+        }
+
+        let mut out = std::io::stdout().lock();
+        write!(out, "\x1b[{};{}H", row + 1, col + 1)?;
+        // C=1 leaves the cursor alone so text drawn afterwards isn't shifted
+        let command = format!("\x1b_Ga=p,q=2,i={},p={},c={},r={},C=1\x1b\\", image_id, placement_id, cols, rows);
+        self.emit(&mut out, command.as_bytes())?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Delete an image with all its placements and free its data in the terminal
+    pub fn delete_image(&mut self, image_id: u32) -> Result<()> {
+        if !self.supported {
+            return Ok(());
+        }
+
+        // d=I (uppercase) also frees the image data, not just the placements
+        let mut out = std::io::stdout().lock();
+        self.emit(&mut out, format!("\x1b_Ga=d,d=I,q=2,i={}\x1b\\", image_id).as_bytes())?;
+        out.flush()?;
+
+        self.active_images.retain(|&id| id != image_id);
+        Ok(())
+    }
+
+    /// Terminal cell size in pixels, falling back to a typical 1:2 cell when the
+    /// terminal doesn't report pixel dimensions
+    pub fn cell_size() -> (f32, f32) {
+        match crossterm::terminal::window_size() {
+            Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+                size.width as f32 / size.columns as f32,
+                size.height as f32 / size.rows as f32,
+            ),
+            _ => DEFAULT_CELL_SIZE,
+        }
+    }
+
+    /// Largest cell area that fits in `max_cols` x `max_rows` while keeping the
+    /// image's aspect ratio, given the cell size in pixels
+    pub fn fit_cells(image_width: u32, image_height: u32, max_cols: u16, max_rows: u16, cell: (f32, f32)) -> (u16, u16) {
+        if image_width == 0 || image_height == 0 || max_cols == 0 || max_rows == 0 {
+            return (0, 0);
+        }
+
+        // Image size measured in cells at 1:1 pixel scale
+        let natural_cols = image_width as f32 / cell.0;
+        let natural_rows = image_height as f32 / cell.1;
+        let scale = (max_cols as f32 / natural_cols).min(max_rows as f32 / natural_rows);
+
+        let cols = ((natural_cols * scale).round() as u16).clamp(1, max_cols);
+        let rows = ((natural_rows * scale).round() as u16).clamp(1, max_rows);
+        (cols, rows)
+    }
+
     /// Clear a specific image
     pub fn clear_image(&mut self, image_id: u32) -> Result<()> {
         if !self.supported {
@@ -240,4 +356,18 @@ impl Drop for KittyProtocol {
         // Clean up images when dropping
         let _ = self.clear_all_images();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_cells_keeps_aspect_ratio() {
+        // 800x1000 page with 8x16 cells is 100x62.5 cells at natural size
+        assert_eq!(KittyProtocol::fit_cells(800, 1000, 60, 40, (8.0, 16.0)), (60, 38));
+
+        // A narrower panel after a resize shrinks both dimensions
+        assert_eq!(KittyProtocol::fit_cells(800, 1000, 30, 40, (8.0, 16.0)), (30, 19));
+    }
+}
@@ -6,7 +6,48 @@ pub mod pdf_renderer;
 pub mod system_pdf_renderer;
 pub mod viuer_display;
 pub mod content_extractor;
+pub mod content_stream;
 pub mod ascii_display;
 pub mod kitty_protocol;
 pub mod kitty_simple;
-pub mod enhanced_ab_ui;
\ No newline at end of file
+pub mod enhanced_ab_ui;
+pub mod storage;
+pub mod ingest;
+pub mod views;
+pub mod scheduler;
+pub mod estimate;
+pub mod extraction_config;
+pub mod convergence;
+pub mod overlay_gif;
+pub mod shutdown;
+pub mod health;
+pub mod hyperlink;
+pub mod tables;
+pub mod server;
+pub mod graphics;
+pub mod render_cache;
+pub mod sql_console;
+pub mod search_index;
+pub mod drop_folder;
+pub mod embeddings;
+pub mod debug_log;
+pub mod clipboard;
+pub mod coords;
+pub mod translate;
+pub mod grep;
+pub mod speech;
+pub mod progress;
+pub mod batch_report;
+pub mod naming;
+pub mod organize;
+pub mod output_format;
+pub mod disk_cache;
+pub mod page_images;
+pub mod temp_files;
+pub mod self_update;
+pub mod selftest;
+pub mod reextract;
+pub mod reflow;
+pub mod entities;
+pub mod json_schema;
+pub mod invoice;
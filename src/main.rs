@@ -0,0 +1,3778 @@
+// Chonker8 CLI - corpus ingest and extraction
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chonker8::convergence;
+use chonker8::disk_cache::{self, CacheConfig};
+use chonker8::entities::{extract_entities, take_type_filter, EntityKind};
+use chonker8::drop_folder::DropFolder;
+use chonker8::embeddings::{Embedder, EMBEDDING_MODEL};
+use chonker8::grep::{grep_pages, GrepMatch};
+use chonker8::estimate::{format_duration, CostModel};
+use chonker8::extraction_config::{ConfigWatcher, ExtractionConfig, VersionedConfig, DEFAULT_CONFIG_PATH};
+use chonker8::health::{self, HealthState};
+use chonker8::hyperlink::{LinkTarget, Linker};
+use chonker8::ingest;
+use chonker8::invoice::{self, InvoiceFormatter};
+use chonker8::naming;
+use chonker8::organize::{self, Manifest, Move};
+use chonker8::output_format::{Cell, DocumentOutput, FormatterRegistry, OutputFormatter, Records};
+use chonker8::overlay_gif::{self, EngineLayer};
+use chonker8::pdf_extraction::annotations::{read_annotations, Annotation};
+use chonker8::pdf_extraction::forms::read_form_fields;
+use chonker8::pdf_extraction::bbox;
+use chonker8::pdf_extraction::cloud_ocr::{CloudOcrExtractor, CLOUD_ENGINE};
+use chonker8::pdf_extraction::dedup::{self, PageSignature};
+use chonker8::pdf_extraction::document_analyzer::page_dimensions;
+use chonker8::pdf_extraction::handwriting;
+use chonker8::pdf_extraction::hybrid::{self, Provenance};
+use chonker8::pdf_extraction::trocr::TrOcr;
+use chonker8::pdf_extraction::language::{language_code, language_name};
+use chonker8::pdf_extraction::models::{self, FileStatus, MODELS};
+use chonker8::pdf_extraction::sidecar::{self, SidecarFormat};
+use chonker8::pdf_extraction::text_layer::{align_corrections, remove_text_layer, TextLayer};
+use chonker8::pdf_extraction::extractors::{combine_pages, engine_version, looks_scanned, AUTO_ENGINE};
+use chonker8::pdf_extraction::{DocumentAnalyzer, ExtractionMethod, ExtractionResult, ExtractorRegistry, QualityChecker};
+use chonker8::progress;
+use chonker8::batch_report::{BatchReport, FileReport, PageReport};
+use chonker8::reextract;
+use chonker8::reflow;
+use chonker8::scheduler::{self, Predicate, Scheduler};
+use chonker8::self_update;
+use chonker8::selftest::{self, Outcome};
+use chonker8::search_index::{self, IndexConfig, PushSummary, SearchIndexer};
+use chonker8::server::{self, ServerConfig};
+use chonker8::shutdown::Shutdown;
+use chonker8::speech::{self, TtsEngine};
+use chonker8::sql_console::SqlConsole;
+use chonker8::storage::query::{self as list_query, Filter, SortKey};
+use chonker8::storage::sql::param_value;
+use chonker8::storage::{is_remote, latest_schema_version, open_store, pending_migrations, set_metadata_schema, spawn_index_worker, spawn_webhook_worker, ArchiveCounts, DocumentMetadata, DocumentSummary, DocumentUsage, DuckDBStorage, Federation, FileRecord, ListQuery, PageEvents, PageExtraction, PageInfo, PageRequest, RunRecord, StoredDocument, DEFAULT_DB_PATH};
+use chonker8::tables;
+use chonker8::temp_files;
+use chonker8::translate::{self, Translator};
+use chonker8::views::text_editor::{diff_lines, diff_stats, grid_lines, replace_in_grid, replay, revert, DiffLine, EditEvent, EditHistory};
+use tracing::{debug, error, info, warn};
+
+/// How many past runs the cost model is fitted on
+const COST_MODEL_HISTORY: usize = 5000;
+
+/// Largest upload `serve` accepts unless --max-upload-mb says otherwise
+const DEFAULT_MAX_UPLOAD_MB: u64 = 1024;
+/// Queued pages `index drain` reindexes per round
+const INDEX_DRAIN_BATCH: usize = 50;
+/// Stored documents `reextract --all` and `entities extract --all` read per query while listing them
+const DOCUMENT_LIST_BATCH: usize = 500;
+
+#[derive(Parser, Debug)]
+#[command(name = "chonker8")]
+#[command(version = "8.8.0")]
+#[command(about = "PDF extraction tool", long_about = None)]
+struct Cli {
+    /// SQLite database holding the extracted corpus, or the URL of a `chonker8 serve`
+    /// sharing one (search, list, stats, grep and speak only)
+    #[arg(long, global = true, default_value = DEFAULT_DB_PATH)]
+    db: PathBuf,
+
+    /// Search, list or count across several databases at once, e.g. 2023.db,2024.db;
+    /// given bare, the databases under [federation] in extraction.toml
+    #[arg(long, global = true, value_delimiter = ',', num_args = 0..=1, conflicts_with = "db")]
+    db_set: Option<Vec<PathBuf>>,
+
+    /// Extraction backend: auto, pdftotext, tesseract, lopdf, hybrid, handwriting, trocr or cloud (needs [cloud_ocr] in extraction.toml)
+    #[arg(long, global = true, default_value = AUTO_ENGINE)]
+    engine: String,
+
+    /// Print only the command's output, and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// More diagnostics on stderr: -v for debug, -vv for trace
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Keep intermediate images (page renders, OCR inputs) in this directory
+    #[arg(long, global = true, value_name = "DIR")]
+    debug_artifacts: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+fn list_columns_help() -> String {
+    let fields: Vec<String> = list_query::fields().map(|f| format!("  {:<8} {}", f.name, f.help)).collect();
+    format!("Comma-separated fields to show:\n{}", fields.join("\n"))
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Register every PDF under a directory, then extract and store its text
+    Ingest {
+        /// Directory to scan recursively for PDFs
+        dir: PathBuf,
+
+        /// Only hash, fingerprint and register files - skip text extraction
+        #[arg(long)]
+        scan_only: bool,
+
+        /// Worker threads for the scan (defaults to the number of cores)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Extraction priority, highest first: recent[=7d], small, class=<name>, name=<keyword>
+        #[arg(long = "priority", value_name = "PREDICATE")]
+        priorities: Vec<Predicate>,
+
+        /// Store pages that repeat the page before them as a note pointing at it
+        #[arg(long)]
+        collapse_duplicates: bool,
+    },
+
+    /// Extract a set of PDFs (files or directories) into the database
+    Batch {
+        /// PDF files or directories to extract
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Print the estimated time per document without extracting anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Warn when the estimated run exceeds this budget (e.g. 45m, 2h)
+        #[arg(long, value_parser = parse_duration)]
+        max_duration: Option<Duration>,
+
+        /// Write per-file and per-page results (engine, quality, timings, errors) as JSON
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+
+        /// Store pages that repeat the page before them as a note pointing at it
+        #[arg(long)]
+        collapse_duplicates: bool,
+
+        /// Also write each extracted document into this directory, in --format
+        #[arg(long, value_name = "DIR")]
+        out: Option<PathBuf>,
+
+        /// Format for --out: text, grid, json, jsonl, markdown, html, hocr, csv or tsv
+        #[arg(long, value_parser = parse_format, default_value = "text", requires = "out")]
+        format: String,
+
+        /// Write --out as a structured record instead of pages: invoice gives vendor,
+        /// number, dates, line items and totals as JSON checked against its schema
+        #[arg(long, value_parser = [invoice::PROFILE], requires = "out", conflicts_with = "format")]
+        profile: Option<String>,
+    },
+
+    /// Extract stored documents again (with --engine, e.g. after an upgrade), keeping
+    /// each new page only where it scores higher than the stored one
+    Reextract {
+        /// Document id or path (repeatable)
+        #[arg(long = "doc", value_name = "ID", required_unless_present = "all", conflicts_with = "all")]
+        docs: Vec<String>,
+
+        /// Every stored document
+        #[arg(long)]
+        all: bool,
+
+        /// Take every new page, better or not
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Find dates, amounts, emails, phone numbers, IDs and addresses in stored
+    /// documents, or list what was found; search with type:amount and the like
+    Entities {
+        #[command(subcommand)]
+        action: EntitiesAction,
+    },
+
+    /// List extracted documents, oldest first unless --sort says otherwise
+    List {
+        /// Sort by a field, optionally :desc (e.g. size, pages:desc, date, quality)
+        #[arg(short, long)]
+        sort: Option<SortKey>,
+
+        /// Only documents matching, e.g. 'pages>100 AND tag=contract' (ops: = != > >= < <= ~)
+        #[arg(short, long)]
+        filter: Option<Filter>,
+
+        /// Comma-separated fields to show (see --columns help for the list)
+        #[arg(short, long, value_delimiter = ',', default_value = "id,chars,date,path",
+              long_help = list_columns_help())]
+        columns: Vec<String>,
+
+        /// Output format: text, grid, json, jsonl, markdown, html, csv or tsv
+        #[arg(long, value_parser = parse_format, default_value = "text")]
+        format: String,
+
+        /// Documents per page
+        #[arg(short, long, default_value_t = 50)]
+        limit: usize,
+
+        /// Page number (1-based); deep pages get slower, prefer --after
+        #[arg(short, long, default_value_t = 1, conflicts_with = "after")]
+        page: usize,
+
+        /// Continue after the cursor printed at the end of the previous page
+        #[arg(long)]
+        after: Option<String>,
+    },
+
+    /// Search extracted text, best matches first
+    Search {
+        /// Text to look for (case-insensitive); a type:amount term (or date, email,
+        /// phone, id, address) keeps documents with that kind of entity
+        query: String,
+
+        /// Results per page
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+
+        /// Page number (1-based); deep pages get slower, prefer --after
+        #[arg(short, long, default_value_t = 1, conflicts_with = "after")]
+        page: usize,
+
+        /// Continue after the cursor printed at the end of the previous page
+        #[arg(long)]
+        after: Option<String>,
+
+        /// Search stored annotations (comments, highlighted text, authors) instead of documents
+        #[arg(long)]
+        annotations: bool,
+
+        /// Only documents with pages in this language (en, deu, German, ...)
+        #[arg(long, value_parser = parse_language, conflicts_with = "annotations")]
+        language: Option<&'static str>,
+
+        /// Rank pages by meaning with the sentence-embedding model under models/;
+        /// falls back to keyword search when the model or stored vectors are missing
+        #[arg(long, conflicts_with_all = ["annotations", "language", "page", "after"])]
+        semantic: bool,
+
+        /// Print hits as a table in this format (text, grid, json, jsonl, markdown, html, csv, tsv)
+        /// instead of path and snippet lines
+        #[arg(long, value_parser = parse_format)]
+        format: Option<String>,
+    },
+
+    /// Document count, stored text size and the languages detected across pages
+    Stats {
+        /// Also break storage down per document and forecast the database's growth
+        #[arg(long)]
+        usage: bool,
+
+        /// Documents in the breakdown, largest first (0 for all)
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+
+        /// Days of ingest the growth rate is measured over
+        #[arg(long, default_value_t = 30)]
+        window_days: i64,
+    },
+
+    /// Query the database with SQL: an interactive console, or one statement and exit
+    #[command(alias = "query")]
+    Sql {
+        /// Run this statement and exit instead of starting the console
+        statement: Option<String>,
+
+        /// Bind a parameter: --param min=100 for :min, or a bare value for the next ?,
+        /// e.g. "... WHERE path LIKE ?" --param "%invoice%"
+        #[arg(long = "param", value_name = "[NAME=]VALUE", value_parser = parse_param)]
+        params: Vec<(Option<String>, String)>,
+
+        /// Print the statement's rows as CSV instead of a table
+        #[arg(long, requires = "statement")]
+        csv: bool,
+    },
+
+    /// Fingerprint every page of a PDF and estimate its extraction time
+    Analyze {
+        /// PDF to analyze
+        pdf: PathBuf,
+    },
+
+    /// Compare OCR text against the native text layer, word by word
+    Compare {
+        /// PDF to compare
+        pdf: PathBuf,
+
+        /// Only compare this page (1-based)
+        #[arg(short, long)]
+        page: Option<usize>,
+
+        /// Show per-region scores for every page
+        #[arg(long)]
+        stats: bool,
+
+        /// Draw a color-coded grid per page showing where OCR and the text layer disagree
+        #[arg(long)]
+        heatmap: bool,
+
+        /// Write a GIF per page into this directory, cycling through each engine's
+        /// word boxes and text (text layer, tesseract, imported sidecar)
+        #[arg(long, value_name = "DIR")]
+        gif: Option<PathBuf>,
+
+        /// Language confidence needed for full marks in the OCR quality score (default from config, 0.7)
+        #[arg(long)]
+        min_lang_confidence: Option<f64>,
+
+        /// Dictionary hit rate needed for full marks in the OCR quality score (default from config, 0.3)
+        #[arg(long)]
+        min_dictionary_rate: Option<f32>,
+
+        /// Flag OCR text scoring below this as gibberish (default from config, 0.4)
+        #[arg(long)]
+        gibberish_below: Option<f32>,
+    },
+
+    /// Write every table detected in a PDF as CSV, with cell coordinates as JSON
+    ExtractTables {
+        /// PDF to scan for tables
+        pdf: PathBuf,
+
+        /// Directory for the CSV files
+        #[arg(short, long, default_value = "tables")]
+        out: PathBuf,
+
+        /// Only this page (1-based)
+        #[arg(short, long)]
+        page: Option<usize>,
+
+        /// Print the tables with cell coordinates as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Merge native text and OCR word by word and print the merged page text
+    Hybrid {
+        /// PDF to extract
+        pdf: PathBuf,
+
+        /// Only this page (1-based)
+        #[arg(short, long)]
+        page: Option<usize>,
+
+        /// Print every word with its box and source (native, ocr or handwriting) as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Also read handwritten fields with the TrOCR model in models/trocr-handwritten
+        #[arg(long)]
+        handwriting: bool,
+
+        /// Drop handwriting read with less confidence than this (0-1)
+        #[arg(long, default_value_t = handwriting::DEFAULT_MIN_CONFIDENCE)]
+        min_confidence: f32,
+    },
+
+    /// List the highlights, comments and sticky notes in a PDF
+    Annotations {
+        /// PDF to read
+        pdf: PathBuf,
+
+        /// Print them as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Also store them in the database, replacing any stored earlier, for `search --annotations`
+        #[arg(long)]
+        store: bool,
+    },
+
+    /// List the fillable form fields in a PDF with their values
+    Forms {
+        /// PDF to read
+        pdf: PathBuf,
+
+        /// Print them as JSON, with types, options and positions
+        #[arg(long)]
+        json: bool,
+
+        /// Also store them in the database, replacing any stored earlier
+        #[arg(long)]
+        store: bool,
+    },
+
+    /// Write a copy of a PDF whose invisible text layer carries the corrected text
+    /// stored for it, placed on the boxes of the words it corrects
+    ApplyCorrections {
+        /// The PDF, as stored in the database
+        doc: PathBuf,
+
+        /// Output PDF
+        #[arg(short, long)]
+        out: PathBuf,
+
+        /// Take the corrected text from this file (pages separated by form feeds)
+        /// instead of the database
+        #[arg(long, value_name = "FILE")]
+        text: Option<PathBuf>,
+    },
+
+    /// Store results from another OCR tool for a PDF instead of extracting it again,
+    /// or given a .tar.zst, restore a database archive (as `db import`)
+    #[command(group(clap::ArgGroup::new("sidecar").args(["text", "hocr", "alto"])))]
+    Import {
+        /// The PDF the results belong to, or an archive from `export`
+        pdf: PathBuf,
+
+        /// Plain text, pages separated by form feeds
+        #[arg(long, value_name = "FILE")]
+        text: Option<PathBuf>,
+
+        /// hOCR (Tesseract, ABBYY converters); word boxes are kept
+        #[arg(long, value_name = "FILE")]
+        hocr: Option<PathBuf>,
+
+        /// ALTO XML; word boxes are kept
+        #[arg(long, value_name = "FILE")]
+        alto: Option<PathBuf>,
+    },
+
+    /// Write a copy of a scanned PDF with an invisible OCR text layer, so it can be
+    /// searched and selected in any viewer
+    OcrOverlay {
+        /// Scanned PDF
+        pdf: PathBuf,
+
+        /// Output PDF (default: <name>.searchable.pdf next to the input)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+
+        /// OCR every page, including ones that already have a text layer
+        #[arg(long)]
+        all_pages: bool,
+    },
+
+    /// Keep extracting files registered by `ingest --scan-only` as they arrive.
+    /// Edits to the extraction config apply to the next job without a restart.
+    Daemon {
+        /// Extraction config (engines, thresholds, locales), reloaded on change
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+
+        /// Serve /healthz and /readyz on this port for supervisors and probes
+        #[arg(long)]
+        health_port: Option<u16>,
+
+        /// Address the health endpoints listen on
+        #[arg(long, default_value = "127.0.0.1", requires = "health_port")]
+        health_bind: IpAddr,
+    },
+
+    /// Watch a drop folder: every PDF that lands in it (or changes) is extracted
+    /// and stored, one summary line per file. Runs until Ctrl-C.
+    Watch {
+        dir: PathBuf,
+
+        /// Seconds a file must go unchanged before it is picked up
+        #[arg(long, default_value_t = 2)]
+        settle_secs: u64,
+    },
+
+    /// Run the HTTP server. POST a PDF to /extract to store it, or to /extract/stream
+    /// (chunked uploads welcome) to get pages back as Server-Sent Events while the
+    /// upload is still arriving. /search and /documents read the database.
+    /// Set CHONKER8_TOKEN to require it as a bearer token.
+    Serve {
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to listen on; anything beyond loopback needs CHONKER8_TOKEN
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: IpAddr,
+
+        /// Reject uploads larger than this
+        #[arg(long, default_value_t = DEFAULT_MAX_UPLOAD_MB)]
+        max_upload_mb: u64,
+
+        /// Uploads extracted concurrently; more get 503 with Retry-After
+        #[arg(long, default_value_t = 4)]
+        max_concurrent: usize,
+    },
+
+    /// Edit history shared with the viewer's text editor: log, current text, undo, redo
+    Edits {
+        #[command(subcommand)]
+        action: EditsAction,
+    },
+
+    /// Snapshots and branches of the edit history: name a point to come back to,
+    /// or try corrections on a branch and merge or discard them later
+    Versions {
+        #[command(subcommand)]
+        action: VersionsAction,
+    },
+
+    /// Line-level changes between two versions of a page: base (the text its edit
+    /// log starts from), stored (the latest extraction), latest (as edited), an
+    /// edit log seq number to see the page as it was after that event, or a snapshot name
+    Diff {
+        pdf: PathBuf,
+        #[arg(short, long, default_value_t = 1)]
+        page: usize,
+        #[arg(long, default_value = "base")]
+        from: String,
+        #[arg(long, default_value = "latest")]
+        to: String,
+    },
+
+    /// Regex search within one PDF, with page, line and column of every match. Uses
+    /// the stored extraction when the PDF is in the database, else extracts it.
+    Grep {
+        pdf: PathBuf,
+        /// Regular expression (Rust regex syntax); named groups are printed per match
+        pattern: String,
+        #[arg(short, long)]
+        ignore_case: bool,
+        /// Lines of context around each match
+        #[arg(short = 'C', long, default_value_t = 2)]
+        context: usize,
+        /// Extract the PDF again even if it is stored
+        #[arg(long)]
+        fresh: bool,
+    },
+
+    /// Suggest descriptive names (date_party_type_number.pdf) for stored documents with
+    /// scanner-style names like scan0001.pdf; a dry run unless --apply
+    Rename {
+        /// Only these documents (default: every stored one with a generated-looking name)
+        docs: Vec<PathBuf>,
+
+        /// Consider every stored document, however it is named
+        #[arg(long, conflicts_with = "docs")]
+        all: bool,
+
+        /// Rename the files and move their database records along
+        #[arg(long)]
+        apply: bool,
+    },
+
+    /// Sort a folder of PDFs into folders by detected type, date or issuer
+    Organize {
+        /// Folder to organize, searched recursively
+        #[arg(required_unless_present = "undo")]
+        dir: Option<PathBuf>,
+
+        /// Folder levels, top down: type, year, month, party
+        #[arg(long, default_value = "type/year")]
+        by: String,
+
+        /// Build the tree here instead of inside DIR
+        #[arg(long)]
+        into: Option<PathBuf>,
+
+        /// Link the files into the tree and leave the originals where they are
+        #[arg(long)]
+        symlink: bool,
+
+        /// Move (or link) the files; without it the plan is only printed
+        #[arg(long)]
+        apply: bool,
+
+        /// Put everything back as recorded in a manifest from an earlier run
+        #[arg(long, value_name = "MANIFEST", conflicts_with_all = ["dir", "into", "symlink", "apply"])]
+        undo: Option<PathBuf>,
+    },
+
+    /// Read a document aloud into an audio file (format from the extension, via
+    /// ffmpeg), with a chapter marker at every detected heading
+    Speak {
+        doc: PathBuf,
+        /// Pages to read, e.g. 1-5 or 1,3,7-9; all by default
+        #[arg(long)]
+        pages: Option<String>,
+        #[arg(short, long)]
+        out: PathBuf,
+        /// Extract the PDF again even if it is stored
+        #[arg(long)]
+        fresh: bool,
+    },
+
+    /// Translate a stored document's pages into another language. The translation is
+    /// stored next to the original; Ctrl+T in the viewer shows it.
+    Translate {
+        doc: PathBuf,
+        /// Target language code, e.g. en
+        #[arg(long)]
+        to: String,
+        /// Translate pages again even if they already have a translation
+        #[arg(long)]
+        force: bool,
+        /// Also write the original and the translation side by side to this file
+        #[arg(long)]
+        side_by_side: Option<PathBuf>,
+        /// Column width of each side
+        #[arg(long, default_value_t = 60)]
+        width: usize,
+    },
+
+    /// Find and replace across every stored page. Applied as one operation in the
+    /// edit log, so it can be previewed first and rolled back as a whole.
+    ReplaceAll {
+        /// Text to find (a regex with --regex); matches never span lines
+        #[arg(long, required_unless_present = "rollback")]
+        pattern: Option<String>,
+
+        /// Replacement text; with --regex, $1 etc. refer to capture groups
+        #[arg(long = "with", required_unless_present = "rollback")]
+        replacement: Option<String>,
+
+        #[arg(long)]
+        regex: bool,
+
+        /// Show every line that would change without applying anything
+        #[arg(long)]
+        preview: bool,
+
+        /// Undo an earlier replace-all by its operation id
+        #[arg(long, conflicts_with_all = ["pattern", "replacement", "preview"])]
+        rollback: Option<i64>,
+    },
+
+    /// Push stored pages to Elasticsearch/OpenSearch (configured under [index]), or
+    /// catch up on pages queued by edits
+    Index {
+        #[command(subcommand)]
+        action: IndexAction,
+    },
+
+    /// Add or remove document tags, or list them
+    Tag {
+        #[arg(required = true)]
+        docs: Vec<PathBuf>,
+
+        /// Tag to add (repeatable), e.g. ephemeral for the retention rules
+        #[arg(long)]
+        add: Vec<String>,
+
+        /// Tag to remove (repeatable)
+        #[arg(long)]
+        remove: Vec<String>,
+    },
+
+    /// Write stored documents out as text, markdown, html, hOCR, JSON, ... - with word
+    /// boxes where they were stored. Given one .tar.zst instead, write the whole
+    /// database to a portable archive (as `db export`).
+    Export {
+        #[arg(required = true)]
+        docs: Vec<PathBuf>,
+
+        /// text, grid, json, jsonl, markdown, html, hocr, csv or tsv
+        #[arg(short, long, value_parser = parse_format, default_value = "text")]
+        format: String,
+
+        /// Write <name>.<ext> per document into this directory instead of to stdout
+        #[arg(short, long, value_name = "DIR")]
+        out: Option<PathBuf>,
+
+        /// With --format text: join each paragraph's lines, rejoining hyphenated
+        /// words, and wrap them at word boundaries to WIDTH (80 if not given)
+        #[arg(long, value_name = "WIDTH", num_args = 0..=1, default_missing_value = "80")]
+        reflow: Option<usize>,
+
+        /// With --format text: mend words hyphenated across lines and join lines
+        /// that carry on in lowercase, keeping the other line breaks
+        #[arg(long)]
+        dehyphenate: bool,
+
+        /// Write a structured record instead of pages: invoice gives vendor, number,
+        /// dates, line items and totals as JSON checked against schemas/invoice.schema.json
+        #[arg(long, value_parser = [invoice::PROFILE], conflicts_with_all = ["format", "reflow", "dehyphenate"])]
+        profile: Option<String>,
+    },
+
+    /// Show or set a document's metadata: title, author, doc_date, classification,
+    /// language, and the custom fields declared under [metadata.fields]
+    Meta {
+        pdf: PathBuf,
+
+        /// Set a field (repeatable), e.g. --set doc_date=2024-03-01; checked against its type
+        #[arg(long = "set", value_parser = parse_field)]
+        set: Vec<(String, String)>,
+
+        /// Clear a field (repeatable)
+        #[arg(long)]
+        unset: Vec<String>,
+
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// How a stored page was extracted: method, quality score, time taken, engine
+    /// version, language and OCR/native convergence
+    Info {
+        pdf: PathBuf,
+        #[arg(short, long, default_value_t = 1)]
+        page: usize,
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Database upkeep
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+
+    /// Install, check and remove the ONNX models (TrOCR, LayoutLM, embeddings, ...)
+    Models {
+        #[command(subcommand)]
+        action: ModelsAction,
+    },
+
+    /// Show, trim or empty the on-disk caches ([cache] in extraction.toml sets where and how big)
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Update to the latest release, verified against its signed checksums
+    SelfUpdate {
+        /// Only say whether a newer release is out
+        #[arg(long)]
+        check: bool,
+
+        /// Install the latest release even if it isn't newer (reinstall or downgrade)
+        #[arg(long)]
+        force: bool,
+
+        /// Trust the checksum alone when this build has no release key
+        #[arg(long)]
+        allow_unsigned: bool,
+    },
+
+    /// Check this installation: tools, models, rendering, every engine, storage and search
+    Selftest {
+        /// Also run the cloud OCR engine from extraction.toml (billed per page)
+        #[arg(long)]
+        cloud: bool,
+
+        /// Print the steps as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum EntitiesAction {
+    /// Read entities from stored documents, replacing what was found before
+    Extract {
+        /// Document id or path (repeatable)
+        #[arg(long = "doc", value_name = "ID", required_unless_present = "all", conflicts_with = "all")]
+        docs: Vec<String>,
+
+        /// Every stored document
+        #[arg(long)]
+        all: bool,
+    },
+    /// Entities found so far, by document and page
+    List {
+        /// Only this document (id or path)
+        #[arg(long, value_name = "ID")]
+        doc: Option<String>,
+
+        /// Only this kind (date, amount, email, phone, id, address)
+        #[arg(long = "type", value_name = "TYPE")]
+        kind: Option<EntityKind>,
+
+        /// Print as a table in this format (text, grid, json, jsonl, markdown, html, csv, tsv)
+        #[arg(long, value_parser = parse_format)]
+        format: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Files and size of each cache, against the size cap
+    Stats,
+    /// Drop least recently used entries until the caches fit the size cap
+    Prune {
+        /// Trim to this instead of [cache] max_size_mb
+        #[arg(long)]
+        max_size_mb: Option<u64>,
+
+        /// Also drop entries unused for this long, e.g. 30d
+        #[arg(long, value_parser = parse_duration)]
+        older_than: Option<Duration>,
+    },
+    /// Delete one cache's entries, or every cache's
+    Clear {
+        /// e.g. cloud-ocr; all caches if omitted
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ModelsAction {
+    /// Every known model, whether it is installed and where
+    List,
+    /// Fetch models from the URLs under [models.sources] in extraction.toml
+    Download {
+        /// Model names, e.g. layoutlm trocr-printed
+        #[arg(required = true)]
+        names: Vec<String>,
+
+        /// Download files that are already installed again
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check installed files against their configured SHA-256 (all models if none named)
+    Verify {
+        names: Vec<String>,
+    },
+    /// Delete models from the models directory
+    Remove {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbAction {
+    /// Reclaim space and refresh query statistics, optionally purging what the
+    /// [retention] rules select first
+    Maintain {
+        /// Purge documents older than max_age_years or carrying the ephemeral tag
+        #[arg(long)]
+        enforce_retention: bool,
+
+        /// List what would be purged without deleting anything
+        #[arg(long, requires = "enforce_retention")]
+        dry_run: bool,
+
+        /// Print the purge report as JSON
+        #[arg(long, requires = "enforce_retention")]
+        json: bool,
+    },
+    /// Write the documents and everything stored about them, edit history included,
+    /// to a portable archive (zstd-compressed tar of JSON), e.g. to back up or move
+    /// machines. Same as `chonker8 export <file>.tar.zst`.
+    Export {
+        /// Archive to write, e.g. corpus.tar.zst
+        archive: PathBuf,
+    },
+    /// Restore an archive from `export`; documents in it replace those at the same
+    /// path. Same as `chonker8 import <file>.tar.zst`.
+    Import {
+        archive: PathBuf,
+    },
+    /// Upgrade the database's schema for this release (opening it does the same)
+    Migrate {
+        /// List the migrations that would run without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum VersionsAction {
+    /// Branches (* marks the checked-out one) and snapshots
+    List,
+    /// Name the checked-out branch as it is now, e.g. pre-cleanup
+    Snapshot {
+        name: String,
+        #[arg(long)]
+        note: Option<String>,
+    },
+    /// Switch to a branch, start one with -b, or put every edited page back as it
+    /// was at a snapshot
+    Checkout {
+        name: String,
+        /// Fork a new branch from the checked-out one and switch to it
+        #[arg(short = 'b', long = "branch")]
+        new_branch: bool,
+    },
+    /// Bring a branch's edits into the branch it was forked from
+    Merge { name: String },
+    /// Close a branch without merging; its edits stay in the log but no longer show
+    Discard { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum EditsAction {
+    /// Show a page's edit log, or every edited page's
+    Log {
+        pdf: PathBuf,
+        /// Only this page (1-based)
+        #[arg(short, long)]
+        page: Option<usize>,
+    },
+    /// Print a page's text as its edits leave it
+    Show {
+        pdf: PathBuf,
+        #[arg(short, long, default_value_t = 1)]
+        page: usize,
+    },
+    /// Undo the latest edit step on a page
+    Undo {
+        pdf: PathBuf,
+        #[arg(short, long, default_value_t = 1)]
+        page: usize,
+    },
+    /// Redo the latest undone step on a page
+    Redo {
+        pdf: PathBuf,
+        #[arg(short, long, default_value_t = 1)]
+        page: usize,
+    },
+    /// Show edits refused because someone else had changed the page first
+    Conflicts {
+        /// Only this document's
+        pdf: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum IndexAction {
+    /// Install the index template and bulk-index every stored page
+    Push {
+        /// Cluster URL, overriding the config, e.g. http://localhost:9200
+        #[arg(long)]
+        endpoint: Option<String>,
+
+        /// Index name, overriding the config
+        #[arg(long)]
+        index: Option<String>,
+
+        /// Pages per bulk request, overriding the config
+        #[arg(long)]
+        batch_size: Option<usize>,
+    },
+    /// Catch up now on pages queued by edits: their search text, embeddings and
+    /// index entries (the TUI and the server do this in the background)
+    Drain,
+}
+
+fn parse_language(input: &str) -> std::result::Result<&'static str, String> {
+    language_code(input).ok_or_else(|| format!("Unknown language '{}' - use an ISO code (en, deu) or a name", input))
+}
+
+fn parse_format(name: &str) -> std::result::Result<String, String> {
+    let registry = FormatterRegistry::default();
+    registry.get(name).map(|f| f.name().to_string()).map_err(|e| e.to_string())
+}
+
+/// NAME=VALUE, or a bare VALUE for the next positional parameter. Only a
+/// parameter-like name counts, so --param "a b=c" is a value.
+fn parse_param(s: &str) -> Result<(Option<String>, String)> {
+    let named = s.split_once('=').and_then(|(name, value)| {
+        let name = name.trim_start_matches([':', '@', '$', '?']);
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        valid.then(|| (Some(name.to_string()), value.to_string()))
+    });
+    Ok(named.unwrap_or_else(|| (None, s.to_string())))
+}
+
+fn parse_field(s: &str) -> Result<(String, String)> {
+    let (name, value) = s.split_once('=').ok_or_else(|| anyhow::anyhow!("Expected NAME=VALUE, got '{}'", s))?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+fn parse_duration(s: &str) -> Result<Duration> {
+    Ok(Duration::from_secs(scheduler::parse_window(s)?.max(0) as u64))
+}
+
+fn main() -> Result<()> {
+    let config = VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config;
+    // Custom metadata fields have to be known before --filter and --sort are parsed
+    set_metadata_schema(&config.metadata)?;
+    let cli = Cli::parse();
+    progress::init(cli.quiet, cli.verbose);
+
+    let registry = extractor_registry(&config);
+    if cli.engine == CLOUD_ENGINE {
+        let cloud = config.cloud_ocr.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("The cloud engine needs a [cloud_ocr] table in {}", DEFAULT_CONFIG_PATH)
+        })?;
+        let cap = cloud.max_pages_per_run.map(|max| format!(", at most {} pages", max)).unwrap_or_default();
+        warn!("⚠️  [CLOUD] Pages are sent to {} at ≈ ${:.4} each{} ({} requests/min); cached responses are free",
+            cloud.provider.name(), cloud.cost_per_page(), cap, cloud.requests_per_minute);
+    }
+    if cli.engine != AUTO_ENGINE && registry.get(&cli.engine).is_none() {
+        anyhow::bail!("Unknown engine '{}' (available: {}, {})", cli.engine, AUTO_ENGINE, registry.names().join(", "));
+    }
+    if let Some(dir) = &config.models.dir {
+        models::set_models_dir(dir.clone());
+    }
+    disk_cache::configure(config.cache.clone());
+    if let Some(dir) = &cli.debug_artifacts {
+        temp_files::set_debug_artifacts(dir.clone());
+    }
+    // Scratch space a killed run couldn't remove
+    let swept = temp_files::sweep_stale();
+    if swept > 0 {
+        debug!("🧹 Removed {} stale temporary files", swept);
+    }
+    let engine = cli.engine.as_str();
+    let db_set = cli.db_set.map(|set| if set.is_empty() { config.federation.databases.clone() } else { set });
+    if db_set.is_some() && !matches!(cli.command, Commands::List { .. } | Commands::Search { .. } | Commands::Stats { .. }) {
+        anyhow::bail!("--db-set only applies to search, list and stats");
+    }
+    if is_remote(&cli.db) && !matches!(cli.command,
+        Commands::List { .. } | Commands::Search { .. } | Commands::Stats { .. } | Commands::Grep { .. } | Commands::Speak { .. })
+    {
+        anyhow::bail!("{} is a server; only search, list, stats, grep and speak work against one \
+            (the viewer, edits, tags and versions need a local file) - pass a local --db", cli.db.display());
+    }
+
+    let linker = Linker::for_stdout(&config.links);
+
+    match cli.command {
+        Commands::Ingest { dir, scan_only, jobs, priorities, collapse_duplicates } => {
+            let jobs = jobs.unwrap_or_else(ingest::default_jobs);
+            cmd_ingest(&cli.db, &dir, scan_only, jobs, priorities, collapse_duplicates, engine)
+        }
+        Commands::Batch { inputs, dry_run, max_duration, report, collapse_duplicates, out, format, profile } => {
+            let registry = FormatterRegistry::default();
+            let formatter: &dyn OutputFormatter = match profile {
+                Some(_) => &InvoiceFormatter,
+                None => registry.get(&format)?,
+            };
+            let export = out.as_deref().map(|dir| (dir, formatter));
+            let outputs = BatchOutputs { report: report.as_deref(), export };
+            cmd_batch(&cli.db, &inputs, dry_run, max_duration, &outputs, collapse_duplicates, engine)
+        }
+        Commands::Reextract { docs, all, force } => cmd_reextract(&cli.db, &docs, all, force, engine),
+        Commands::Entities { action } => cmd_entities(&cli.db, action, &linker),
+        Commands::List { sort, filter, columns, format, limit, page, after } => {
+            let (query, request) = (ListQuery { filter, sort }, PageRequest { limit, page, after });
+            match &db_set {
+                Some(db_set) => cmd_list_federated(db_set, &query, &columns, &format, &request, &linker),
+                None => cmd_list(&cli.db, &query, &columns, &format, &request, &linker),
+            }
+        }
+        Commands::Search { query, limit, page, after, annotations, language, semantic, format } => {
+            let request = PageRequest { limit, page, after };
+            let format = format.as_deref();
+            if (semantic || annotations) && take_type_filter(&query)?.1.is_some() {
+                anyhow::bail!("type: filters apply to keyword search, not --semantic or --annotations");
+            }
+            if let Some(db_set) = &db_set {
+                if semantic || annotations {
+                    anyhow::bail!("--semantic and --annotations search one database at a time, not a --db-set");
+                }
+                cmd_search_federated(db_set, &query, language, &request, format, &linker)
+            } else if (semantic || annotations) && is_remote(&cli.db) {
+                anyhow::bail!("--semantic and --annotations search a local database, not a server")
+            } else if semantic {
+                cmd_search_semantic(&cli.db, &query, &request, format, &linker)
+            } else if annotations {
+                cmd_search_annotations(&cli.db, &query, &request, format, &linker)
+            } else {
+                cmd_search(&cli.db, &query, language, &request, format, &linker)
+            }
+        }
+        Commands::Stats { usage, top, window_days } => {
+            if usage && (db_set.is_some() || is_remote(&cli.db)) {
+                anyhow::bail!("--usage needs a local --db");
+            }
+            let stats = match &db_set {
+                Some(db_set) => Federation::open(db_set)?.get_stats()?,
+                None => open_store(&cli.db)?.get_stats()?,
+            };
+            println!("{}", stats);
+            if usage {
+                print_storage_usage(&DuckDBStorage::new(Some(&cli.db))?, top, window_days)?;
+            }
+            Ok(())
+        }
+        Commands::Sql { statement, params, csv } => cmd_sql(&cli.db, statement.as_deref(), params, csv),
+        Commands::Analyze { pdf } => cmd_analyze(&cli.db, &pdf, engine),
+        Commands::Compare { pdf, page, stats, heatmap, gif, min_lang_confidence, min_dictionary_rate, gibberish_below } => {
+            let mut quality = VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config.quality;
+            quality.min_language_confidence = min_lang_confidence.unwrap_or(quality.min_language_confidence);
+            quality.min_dictionary_hit_rate = min_dictionary_rate.unwrap_or(quality.min_dictionary_hit_rate);
+            quality.gibberish_below = gibberish_below.unwrap_or(quality.gibberish_below);
+            cmd_compare(&cli.db, &pdf, page, stats, heatmap, gif.as_deref(), &QualityChecker::new(&quality))
+        }
+        Commands::ExtractTables { pdf, out, page, json } => cmd_extract_tables(&pdf, &out, page, json),
+        Commands::Hybrid { pdf, page, json, handwriting, min_confidence } => cmd_hybrid(&pdf, page, json, handwriting.then_some(min_confidence)),
+        Commands::Annotations { pdf, json, store } => cmd_annotations(&cli.db, &pdf, json, store),
+        Commands::Forms { pdf, json, store } => cmd_forms(&cli.db, &pdf, json, store),
+        Commands::OcrOverlay { pdf, out, all_pages } => {
+            let out = out.unwrap_or_else(|| pdf.with_extension("searchable.pdf"));
+            cmd_ocr_overlay(&cli.db, &pdf, &out, all_pages)
+        }
+        Commands::ApplyCorrections { doc, out, text } => cmd_apply_corrections(&cli.db, &doc, &out, text.as_deref()),
+        Commands::Import { pdf, text, hocr, alto } => {
+            let (format, sidecar) = match (text, hocr, alto) {
+                (Some(file), _, _) => (SidecarFormat::Text, file),
+                (_, Some(file), _) => (SidecarFormat::Hocr, file),
+                (_, _, Some(file)) => (SidecarFormat::Alto, file),
+                _ if is_archive(&pdf) => return cmd_db_import(&cli.db, &pdf),
+                _ => anyhow::bail!("Give the results to import with --text, --hocr or --alto"),
+            };
+            cmd_import(&cli.db, &pdf, format, &sidecar)
+        }
+        Commands::Daemon { config, health_port, health_bind } => {
+            cmd_daemon(&cli.db, &config, engine, health_port.map(|port| (health_bind, port)))
+        }
+        Commands::Watch { dir, settle_secs } => cmd_watch(&cli.db, &dir, engine, Duration::from_secs(settle_secs)),
+        Commands::Serve { port, bind, max_upload_mb, max_concurrent } => cmd_serve(&cli.db, ServerConfig {
+            bind,
+            port,
+            engine: engine.to_string(),
+            max_upload_bytes: max_upload_mb * 1024 * 1024,
+            max_concurrent,
+            token: std::env::var(server::TOKEN_ENV).ok().filter(|token| !token.is_empty()),
+        }),
+        Commands::Edits { action } => cmd_edits(&cli.db, action),
+        Commands::Versions { action } => cmd_versions(&cli.db, action),
+        Commands::Diff { pdf, page, from, to } => cmd_diff(&cli.db, &pdf, page, &from, &to),
+        Commands::Grep { pdf, pattern, ignore_case, context, fresh } => {
+            cmd_grep(&cli.db, &pdf, &pattern, ignore_case, context, fresh, engine)
+        }
+        Commands::Rename { docs, all, apply } => cmd_rename(&cli.db, &docs, all, apply),
+        Commands::Organize { dir, by, into, symlink, apply, undo } => match (undo, dir) {
+            (Some(manifest), _) => cmd_organize_undo(&cli.db, &manifest),
+            (None, Some(dir)) => cmd_organize(&cli.db, &dir, &by, into.as_deref(), symlink, apply, engine),
+            (None, None) => unreachable!("clap requires DIR without --undo"),
+        },
+        Commands::Speak { doc, pages, out, fresh } => cmd_speak(&cli.db, &doc, pages.as_deref(), &out, fresh, engine),
+        Commands::Translate { doc, to, force, side_by_side, width } => {
+            cmd_translate(&cli.db, &doc, &to, force, side_by_side.as_deref(), width)
+        }
+        Commands::ReplaceAll { pattern, replacement, regex, preview, rollback } => match (rollback, pattern, replacement) {
+            (Some(id), _, _) => cmd_replace_rollback(&cli.db, id),
+            (None, Some(pattern), Some(replacement)) => cmd_replace_all(&cli.db, &pattern, &replacement, regex, preview),
+            _ => unreachable!("clap requires --pattern and --with without --rollback"),
+        },
+        Commands::Index { action: IndexAction::Push { endpoint, index, batch_size } } => {
+            let configured = VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config.index;
+            let mut config = match (configured, endpoint.as_deref()) {
+                (Some(config), _) => config,
+                (None, Some(endpoint)) => IndexConfig::new(endpoint),
+                (None, None) => anyhow::bail!("No search cluster configured - add an [index] table to {} or pass --endpoint", DEFAULT_CONFIG_PATH),
+            };
+            config.endpoint = endpoint.unwrap_or(config.endpoint);
+            config.index = index.unwrap_or(config.index);
+            config.batch_size = batch_size.unwrap_or(config.batch_size);
+            cmd_index_push(&cli.db, config)
+        }
+        Commands::Index { action: IndexAction::Drain } => cmd_index_drain(&cli.db),
+        Commands::Tag { docs, add, remove } => cmd_tag(&cli.db, &docs, &add, &remove),
+        Commands::Export { docs, out, reflow, dehyphenate, profile, .. } if docs.len() == 1 && is_archive(&docs[0]) => {
+            if out.is_some() || reflow.is_some() || dehyphenate || profile.is_some() {
+                anyhow::bail!("{} is an archive of the whole database - --out, --reflow, --dehyphenate and --profile are for documents", docs[0].display());
+            }
+            cmd_db_export(&cli.db, &docs[0])
+        }
+        Commands::Export { docs, format, out, reflow, dehyphenate, profile } => {
+            cmd_export(&cli.db, &docs, &format, out.as_deref(), reflow, dehyphenate, profile.as_deref())
+        }
+        Commands::Meta { pdf, set, unset, json } => cmd_meta(&cli.db, &pdf, &set, &unset, json),
+        Commands::Info { pdf, page, json } => cmd_info(&cli.db, &pdf, page, json),
+        Commands::Db { action: DbAction::Maintain { enforce_retention, dry_run, json } } => {
+            cmd_db_maintain(&cli.db, enforce_retention, dry_run, json)
+        }
+        Commands::Db { action: DbAction::Export { archive } } => cmd_db_export(&cli.db, &archive),
+        Commands::Db { action: DbAction::Import { archive } } => cmd_db_import(&cli.db, &archive),
+        Commands::Db { action: DbAction::Migrate { dry_run } } => cmd_db_migrate(&cli.db, dry_run),
+        Commands::Models { action } => cmd_models(action, &config.models),
+        Commands::Cache { action } => cmd_cache(action, &config.cache),
+        Commands::SelfUpdate { check, force, allow_unsigned } => cmd_self_update(check, force, allow_unsigned),
+        Commands::Selftest { cloud, json } => {
+            let registry = if cloud { extractor_registry(&config) } else { ExtractorRegistry::default() };
+            cmd_selftest(&registry, json)
+        }
+    }
+}
+
+fn cmd_index_drain(db: &Path) -> Result<()> {
+    let mut storage = open_storage(db)?;
+    let pending = storage.pending_index_count()?;
+    let bar = progress::bar(pending as usize, "pages");
+    let mut done = 0;
+    loop {
+        let batch = storage.process_index_queue(INDEX_DRAIN_BATCH)?;
+        if batch == 0 {
+            break;
+        }
+        done += batch;
+        bar.set_position(done as u64);
+    }
+    bar.finish_and_clear();
+    info!("✅ {} queued pages brought up to date", done);
+    Ok(())
+}
+
+/// Open the database for writing, with the storage hooks from the extraction config
+fn open_storage(db: &Path) -> Result<DuckDBStorage> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let config = VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config;
+    storage.set_hooks(config.hooks);
+    storage.set_indexer(config.index.filter(|index| index.on_store).map(SearchIndexer::new));
+    storage.set_embedder(load_embedder());
+    Ok(storage)
+}
+
+/// The sentence-embedding model, if installed; a broken one only loses semantic search
+fn load_embedder() -> Option<Embedder> {
+    Embedder::load_default().unwrap_or_else(|e| {
+        warn!("⚠️  Embedding model not loaded: {}", e);
+        None
+    })
+}
+
+fn cmd_ingest(
+    db: &Path,
+    dir: &Path,
+    scan_only: bool,
+    jobs: usize,
+    priorities: Vec<Predicate>,
+    collapse_duplicates: bool,
+    engine: &str,
+) -> Result<()> {
+    let registry = default_registry()?;
+    let min_quality = VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config.min_quality;
+    let mut storage = open_storage(db)?;
+    start_webhook_delivery(db);
+    let start = Instant::now();
+
+    let pdfs = ingest::find_pdfs(dir)?;
+    let found = pdfs.len();
+    info!("📂 Found {} PDFs under {}", found, dir.display());
+
+    // Skip files whose size and mtime match what we already registered
+    let known = storage.known_files()?;
+    let changed: Vec<PathBuf> = pdfs
+        .into_iter()
+        .filter(|path| {
+            let key = path.to_string_lossy().to_string();
+            match (known.get(&key), ingest::file_stat(path)) {
+                (Some(previous), Ok(current)) => *previous != current,
+                _ => true,
+            }
+        })
+        .collect();
+
+    info!("🔍 Scanning {} new or changed files with {} workers ({} unchanged)",
+        changed.len(), jobs, found - changed.len());
+
+    let bar = progress::bar(changed.len(), "files scanned");
+    let records = ingest::scan_files(&changed, jobs, |done| bar.set_position(done as u64));
+    bar.finish_and_clear();
+
+    storage.upsert_files(&records)?;
+
+    let failed = records.iter().filter(|r| r.status == "failed").count();
+    let pages: usize = records.iter().filter_map(|r| r.page_count).sum();
+    info!("✅ Registered {} files ({} pages, {} unreadable) in {:.1}s",
+        records.len(), pages, failed, start.elapsed().as_secs_f32());
+
+    if scan_only {
+        return Ok(());
+    }
+
+    // Extraction pass over everything that has been scanned but not yet extracted,
+    // highest priority first. Results feed back into the scheduler as they arrive.
+    let pending = storage.files_with_status("scanned")?;
+    if priorities.is_empty() {
+        info!("📄 Extracting {} files", pending.len());
+    } else {
+        info!("📄 Extracting {} files by priority: {:?}", pending.len(), priorities);
+    }
+    let bar = progress::bar(pending.len(), "files");
+    let mut scheduler = Scheduler::new(priorities, pending);
+
+    while let Some(file) = scheduler.next_file() {
+        let started = Instant::now();
+        let reuse = reusable_pages(&storage, engine, &file, min_quality)?;
+        let reused: Vec<bool> = reuse.iter().map(Option::is_some).collect();
+        match extract_with_bar(&registry, engine, &file, reuse, &mut 0) {
+            Ok(results) => {
+                record_page_extractions(&mut storage, &file.path, &results, &reused)?;
+                let corrections = page_corrections(&results);
+                let (content, method, duplicates) = document_content(results, collapse_duplicates);
+                record_run(&mut storage, &file, &method, started.elapsed())?;
+                let classification = scheduler.record_result(&file, &content);
+                let metadata = serde_json::json!({
+                    "sha256": file.sha256,
+                    "pages": file.page_count,
+                    "size_bytes": file.size_bytes,
+                    "classification": classification,
+                    "duplicate_pages": duplicate_pairs(&duplicates),
+                    "suggested_name": naming::suggest_name(&content),
+                    "corrections": corrections,
+                });
+                storage.store_document(&file.path, &content, Some(&metadata.to_string()))?;
+                storage.set_file_status(&file.path, "extracted")?;
+                match classification {
+                    Some(class) => debug!("   ✓ {} ({})", file.path, class),
+                    None => debug!("   ✓ {}", file.path),
+                }
+            }
+            Err(e) => {
+                storage.set_file_status(&file.path, "failed")?;
+                error!("   ✗ {}: {}", file.path, e);
+            }
+        }
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+
+    Ok(())
+}
+
+/// A document's stored text and majority engine. With `collapse`, pages repeating
+/// the page before them are replaced by a note; which ones is returned either way
+/// (all None without it).
+fn document_content(results: Vec<ExtractionResult>, collapse: bool) -> (String, ExtractionMethod, Vec<Option<usize>>) {
+    if !collapse {
+        let duplicates = vec![None; results.len()];
+        let (content, method) = combine_pages(results);
+        return (content, method, duplicates);
+    }
+    let signatures: Vec<PageSignature> = results.iter().map(|r| PageSignature::new(&r.text, None)).collect();
+    let duplicates = dedup::duplicate_pages(&signatures);
+    let (content, method) = combine_pages(results);
+    let pages: Vec<&str> = content.split('\x0c').collect();
+    (dedup::collapse_pages(&pages, &duplicates), method, duplicates)
+}
+
+/// [page, original] pairs (1-based) for document metadata
+/// Pages turned or straightened before OCR, for document metadata
+fn page_corrections(results: &[ExtractionResult]) -> Vec<serde_json::Value> {
+    results
+        .iter()
+        .enumerate()
+        .filter_map(|(index, result)| {
+            let c = result.correction?;
+            Some(serde_json::json!({ "page": index + 1, "pdf_rotate": c.pdf_rotate, "rotated": c.rotated, "deskewed": c.deskewed }))
+        })
+        .collect()
+}
+
+fn duplicate_pairs(duplicates: &[Option<usize>]) -> Vec<[usize; 2]> {
+    duplicates
+        .iter()
+        .enumerate()
+        .filter_map(|(index, duplicate)| duplicate.map(|original| [index + 1, original + 1]))
+        .collect()
+}
+
+/// Extract a registered file page by page with a bar over its pages under the file
+/// bar, keeping the results in `reuse`. `pages_done` is left at the number of pages
+/// that came out before any error.
+fn extract_with_bar(
+    registry: &ExtractorRegistry,
+    engine: &str,
+    file: &FileRecord,
+    reuse: Vec<Option<ExtractionResult>>,
+    pages_done: &mut usize,
+) -> Result<Vec<ExtractionResult>> {
+    let pages = file.page_count.unwrap_or(0);
+    let bar = progress::bar(pages, "pages");
+    bar.set_message(file.path.rsplit('/').next().unwrap_or(&file.path).to_string());
+    let results = registry.extract_pages_reusing(engine, Path::new(&file.path), pages, reuse, |done| {
+        *pages_done = done;
+        bar.set_position(done as u64);
+    });
+    bar.finish_and_clear();
+    results
+}
+
+/// In auto mode, the stored text of pages that needn't be extracted again - the
+/// file is the one extracted before and the page's record doesn't call for it -
+/// by page index. Empty when nothing can be kept.
+fn reusable_pages(storage: &DuckDBStorage, engine: &str, file: &FileRecord, min_quality: f32) -> Result<Vec<Option<ExtractionResult>>> {
+    if engine != AUTO_ENGINE {
+        return Ok(Vec::new());
+    }
+    let Some(document) = storage.document_by_path(&file.path)? else {
+        return Ok(Vec::new());
+    };
+    let metadata: serde_json::Value = document.metadata.as_deref().and_then(|m| serde_json::from_str(m).ok()).unwrap_or_default();
+    // Collapsed duplicates were stored as a note rather than their text
+    let collapsed = metadata["duplicate_pages"].as_array().is_some_and(|pairs| !pairs.is_empty());
+    if metadata["sha256"].as_str() != Some(file.sha256.as_str()) || collapsed {
+        return Ok(Vec::new());
+    }
+    let records = storage.page_extractions(&file.path)?;
+    let reuse = document
+        .content
+        .split('\x0c')
+        .enumerate()
+        .map(|(index, text)| {
+            let record = records.iter().find(|record| record.page == index + 1)?;
+            if record.needs_reextraction(min_quality) {
+                return None;
+            }
+            let mut result = ExtractionResult::new(text.to_string(), ExtractionMethod::from_name(&record.method)?);
+            result.quality_score = record.quality;
+            result.extraction_time_ms = record.duration_ms;
+            Some(result)
+        })
+        .collect::<Vec<_>>();
+    let kept = reuse.iter().flatten().count();
+    if kept > 0 {
+        debug!("   ↺ {}: keeping {} of {} pages from the last extraction", file.path, kept, reuse.len());
+    }
+    Ok(reuse)
+}
+
+/// Record how each page came out; pages marked `reused` keep their earlier record
+fn record_page_extractions(storage: &mut DuckDBStorage, path: &str, results: &[ExtractionResult], reused: &[bool]) -> Result<()> {
+    let records: Vec<PageExtraction> = results
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !reused.get(*index).copied().unwrap_or(false))
+        .map(|(index, result)| PageExtraction {
+            page: index + 1,
+            method: result.method.name().to_string(),
+            quality: result.quality_score,
+            duration_ms: result.extraction_time_ms,
+            engine_version: engine_version(&result.method),
+        })
+        .collect();
+    storage.store_page_extractions(path, &records)
+}
+
+/// Where a batch writes besides the database
+struct BatchOutputs<'a> {
+    /// Per-file and per-page results as JSON
+    report: Option<&'a Path>,
+    /// Each extracted document, in a format, into a directory
+    export: Option<(&'a Path, &'a dyn OutputFormatter)>,
+}
+
+fn cmd_batch(
+    db: &Path,
+    inputs: &[PathBuf],
+    dry_run: bool,
+    max_duration: Option<Duration>,
+    outputs: &BatchOutputs,
+    collapse_duplicates: bool,
+    engine: &str,
+) -> Result<()> {
+    let mut storage = open_storage(db)?;
+    let registry = default_registry()?;
+    let config = VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config;
+    let checker = QualityChecker::new(&config.quality);
+
+    let mut pdfs = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            pdfs.extend(ingest::find_pdfs(input)?);
+        } else {
+            pdfs.push(input.clone());
+        }
+    }
+
+    let files = ingest::scan_files(&pdfs, ingest::default_jobs(), |_| {});
+    // In auto mode the engine varies per page, so the pooled model is the best guess
+    let model = CostModel::fit(&storage.recent_runs(COST_MODEL_HISTORY)?);
+
+    let estimates: Vec<Duration> = files
+        .iter()
+        .map(|f| model.predict(engine, f.page_count.unwrap_or(0), f.image_coverage))
+        .collect();
+    let total: Duration = estimates.iter().sum();
+
+    if dry_run {
+        println!("{:<60} {:>6} {:>6} {:>10}", "FILE", "PAGES", "IMG%", "ESTIMATE");
+        for (file, estimate) in files.iter().zip(&estimates) {
+            println!("{:<60} {:>6} {:>5.0}% {:>10}",
+                file.path,
+                file.page_count.map(|p| p.to_string()).unwrap_or_else(|| "-".to_string()),
+                file.image_coverage * 100.0,
+                format_duration(*estimate));
+        }
+    }
+
+    info!("⏱️  Estimated {} for {} files ({}, model from {} past runs)",
+        format_duration(total), files.len(), engine, model.samples);
+
+    if let Some(budget) = max_duration {
+        if total > budget {
+            warn!("⚠️  Estimated run time {} exceeds --max-duration {}",
+                format_duration(total), format_duration(budget));
+        }
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+    if let Some((dir, _)) = outputs.export {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let start = Instant::now();
+    let mut report = BatchReport::new(engine, total);
+    let bar = progress::bar(files.len(), "files");
+    for (mut file, estimate) in files.into_iter().zip(estimates) {
+        bar.inc(1);
+        if file.status == "failed" {
+            storage.upsert_file(&file)?;
+            error!("   ✗ {}: unreadable PDF", file.path);
+            report.push(FileReport::failed(&file.path, None, "unreadable PDF", None, Duration::ZERO, estimate));
+            continue;
+        }
+
+        let started = Instant::now();
+        let mut pages_done = 0;
+        let reuse = reusable_pages(&storage, engine, &file, config.min_quality)?;
+        let reused: Vec<bool> = reuse.iter().map(Option::is_some).collect();
+        match extract_with_bar(&registry, engine, &file, reuse, &mut pages_done) {
+            Ok(results) => {
+                let elapsed = started.elapsed();
+                record_page_extractions(&mut storage, &file.path, &results, &reused)?;
+                let mut pages = PageReport::from_results(&results, &checker);
+                let corrections = page_corrections(&results);
+                let (content, method, duplicates) = document_content(results, collapse_duplicates);
+                for (page, duplicate) in pages.iter_mut().zip(&duplicates) {
+                    page.duplicate_of = duplicate.map(|original| original + 1);
+                }
+                let metadata = serde_json::json!({
+                    "sha256": file.sha256,
+                    "pages": file.page_count,
+                    "size_bytes": file.size_bytes,
+                    "duplicate_pages": duplicate_pairs(&duplicates),
+                    "suggested_name": naming::suggest_name(&content),
+                    "corrections": corrections,
+                });
+                storage.store_document(&file.path, &content, Some(&metadata.to_string()))?;
+                record_run(&mut storage, &file, &method, elapsed)?;
+                if let Some((dir, formatter)) = outputs.export {
+                    let document = document_output(&storage, &file.path, &content)?;
+                    let written = export_document(formatter, &document, dir)?;
+                    debug!("   → {}", written.display());
+                }
+                file.status = "extracted".to_string();
+                debug!("   ✓ {} ({} / est. {})", file.path, format_duration(elapsed), format_duration(estimate));
+                report.push(FileReport::extracted(&file.path, method.name(), pages, elapsed, estimate));
+            }
+            Err(e) => {
+                file.status = "failed".to_string();
+                error!("   ✗ {}: {}", file.path, e);
+                report.push(FileReport::failed(&file.path, file.page_count, &e.to_string(), Some(pages_done), started.elapsed(), estimate));
+            }
+        }
+        storage.upsert_file(&file)?;
+    }
+    bar.finish_and_clear();
+
+    info!("✅ Batch finished in {} (estimated {}): {} extracted, {} failed",
+        format_duration(start.elapsed()), format_duration(total), report.extracted, report.failed);
+    if let Some(path) = outputs.report {
+        report.write(path, start.elapsed())?;
+        info!("📋 Report written to {}", path.display());
+    }
+    Ok(())
+}
+
+fn cmd_list(db: &Path, query: &ListQuery, columns: &[String], format: &str, request: &PageRequest, linker: &Linker) -> Result<()> {
+    let fields = columns.iter().map(|name| list_query::field(name)).collect::<Result<Vec<_>>>()?;
+    let page = open_store(db)?.list_documents(query, request)?;
+    let docs: Vec<(Option<&str>, &DocumentSummary)> = page.items.iter().map(|doc| (None, doc)).collect();
+    print_records(format, &document_records(&fields, &docs, linker))?;
+    print_next_page(page.items.len(), page.next_cursor.as_deref());
+    Ok(())
+}
+
+/// `list --db-set`: the same listing with the database each document is in first
+fn cmd_list_federated(
+    db_set: &[PathBuf],
+    query: &ListQuery,
+    columns: &[String],
+    format: &str,
+    request: &PageRequest,
+    linker: &Linker,
+) -> Result<()> {
+    let fields = columns.iter().map(|name| list_query::field(name)).collect::<Result<Vec<_>>>()?;
+    let page = Federation::open(db_set)?.list_documents(query, request)?;
+    let docs: Vec<(Option<&str>, &DocumentSummary)> = page.items.iter().map(|hit| (Some(hit.source.as_str()), &hit.item)).collect();
+    print_records(format, &document_records(&fields, &docs, linker))?;
+    print_next_page(page.items.len(), page.next_cursor.as_deref());
+    Ok(())
+}
+
+/// Listed documents as rows; a source database, when given, is the first column.
+/// Ids and paths link to the document.
+fn document_records(fields: &[&list_query::Field], docs: &[(Option<&str>, &DocumentSummary)], linker: &Linker) -> Records {
+    let federated = docs.iter().any(|(source, _)| source.is_some());
+    let columns: Vec<&str> = federated.then_some("db").into_iter().chain(fields.iter().map(|f| f.name)).collect();
+    let mut records = Records::new(&columns);
+    for (source, doc) in docs {
+        let path = doc.text("path");
+        let link = linker.target(LinkTarget::document(&path, Some(doc.id())));
+        let row = source
+            .map(Cell::new)
+            .into_iter()
+            .chain(fields.iter().map(|f| match f.name {
+                "id" | "path" => Cell::linked(doc.json(f.name), link.clone()),
+                _ => Cell::new(doc.json(f.name)),
+            }))
+            .collect();
+        records.push(row);
+    }
+    records
+}
+
+fn print_records(format: &str, records: &Records) -> Result<()> {
+    let registry = FormatterRegistry::default();
+    registry.get(format)?.write_records(&mut std::io::stdout().lock(), records)
+}
+
+fn cmd_search(db: &Path, query: &str, language: Option<&str>, request: &PageRequest, format: Option<&str>, linker: &Linker) -> Result<()> {
+    let page = open_store(db)?.search_page(query, language, request)?;
+
+    if let Some(format) = format {
+        let mut records = Records::new(&["id", "path", "score", "snippet"]);
+        for result in &page.items {
+            let link = linker.target(LinkTarget::document(&result.path, Some(result.id)));
+            records.push(vec![
+                Cell::linked(result.id, link.clone()),
+                Cell::linked(result.path.as_str(), link),
+                Cell::new(result.score),
+                Cell::new(result.snippet.split_whitespace().collect::<Vec<_>>().join(" ")),
+            ]);
+        }
+        print_records(format, &records)?;
+    } else {
+        for result in &page.items {
+            println!("{} ({} matches)", linker.link(LinkTarget::document(&result.path, Some(result.id)), &result.path), result.score);
+            println!("    {}", result.snippet.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+    }
+    print_next_page(page.items.len(), page.next_cursor.as_deref());
+    Ok(())
+}
+
+/// `search --db-set`: hits from every database, best first, each with the database it is in
+fn cmd_search_federated(
+    db_set: &[PathBuf],
+    query: &str,
+    language: Option<&str>,
+    request: &PageRequest,
+    format: Option<&str>,
+    linker: &Linker,
+) -> Result<()> {
+    let page = Federation::open(db_set)?.search_page(query, language, request)?;
+
+    if let Some(format) = format {
+        let mut records = Records::new(&["db", "id", "path", "score", "snippet"]);
+        for hit in &page.items {
+            let link = linker.target(LinkTarget::document(&hit.item.path, Some(hit.item.id)));
+            records.push(vec![
+                Cell::new(hit.source.as_str()),
+                Cell::linked(hit.item.id, link.clone()),
+                Cell::linked(hit.item.path.as_str(), link),
+                Cell::new(hit.item.score),
+                Cell::new(hit.item.snippet.split_whitespace().collect::<Vec<_>>().join(" ")),
+            ]);
+        }
+        print_records(format, &records)?;
+    } else {
+        for hit in &page.items {
+            let path = linker.link(LinkTarget::document(&hit.item.path, Some(hit.item.id)), &hit.item.path);
+            println!("[{}] {} ({} matches)", hit.source, path, hit.item.score);
+            println!("    {}", hit.item.snippet.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+    }
+    print_next_page(page.items.len(), page.next_cursor.as_deref());
+    Ok(())
+}
+
+fn cmd_search_semantic(db: &Path, query: &str, request: &PageRequest, format: Option<&str>, linker: &Linker) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    storage.set_embedder(load_embedder());
+    let Some(embedder) = storage.embedder_mut() else {
+        warn!("⚠️  No embedding model at {}, using keyword search", models::locate(EMBEDDING_MODEL).display());
+        return cmd_search(db, query, None, request, format, linker);
+    };
+    let model = embedder.model_id().to_string();
+    let vector = embedder.embed(query)?;
+    if storage.embedded_page_count(&model)? == 0 {
+        warn!("⚠️  No pages embedded with {} yet (re-extract to embed them), using keyword search", model);
+        return cmd_search(db, query, None, request, format, linker);
+    }
+
+    let hits = storage.semantic_search(&vector, &model, request.limit)?;
+    if let Some(format) = format {
+        let mut records = Records::new(&["path", "page", "score", "snippet"]);
+        for hit in &hits {
+            records.push(vec![
+                Cell::linked(hit.path.as_str(), linker.target(LinkTarget::document(&hit.path, None))),
+                Cell::linked(hit.page, linker.target(LinkTarget::page(&hit.path, None, hit.page))),
+                Cell::new(hit.score),
+                Cell::new(hit.snippet.as_str()),
+            ]);
+        }
+        print_records(format, &records)?;
+    } else {
+        for hit in &hits {
+            let page = linker.link(LinkTarget::page(&hit.path, None, hit.page), &format!("p{}", hit.page));
+            println!("{} {} ({:.3})", linker.link(LinkTarget::document(&hit.path, None), &hit.path), page, hit.score);
+            println!("    {}", hit.snippet);
+        }
+    }
+    info!("-- {} shown", hits.len());
+    Ok(())
+}
+
+fn cmd_search_annotations(db: &Path, query: &str, request: &PageRequest, format: Option<&str>, linker: &Linker) -> Result<()> {
+    let storage = DuckDBStorage::new(Some(db))?;
+    let page = storage.search_annotations(query, request)?;
+
+    if let Some(format) = format {
+        let mut records = Records::new(&["path", "page", "annotation"]);
+        for hit in &page.items {
+            let number = hit.annotation.page;
+            records.push(vec![
+                Cell::linked(hit.path.as_str(), linker.target(LinkTarget::document(&hit.path, None))),
+                Cell::linked(number, linker.target(LinkTarget::page(&hit.path, None, number))),
+                Cell::new(describe_annotation(&hit.annotation)),
+            ]);
+        }
+        print_records(format, &records)?;
+    } else {
+        for hit in &page.items {
+            let page = linker.link(LinkTarget::page(&hit.path, None, hit.annotation.page), &format!("p{}", hit.annotation.page));
+            println!("{} {} {}", linker.link(LinkTarget::document(&hit.path, None), &hit.path), page, describe_annotation(&hit.annotation));
+        }
+    }
+    print_next_page(page.items.len(), page.next_cursor.as_deref());
+    Ok(())
+}
+
+/// Kind, author, comment and quoted text on one line
+fn describe_annotation(annotation: &Annotation) -> String {
+    let mut line = annotation.kind.clone();
+    if let Some(author) = &annotation.author {
+        line.push_str(&format!(" by {}", author));
+    }
+    if let Some(quoted) = &annotation.quoted {
+        line.push_str(&format!(" \"{}\"", quoted));
+    }
+    if let Some(contents) = &annotation.contents {
+        line.push_str(&format!(" - {}", contents.split_whitespace().collect::<Vec<_>>().join(" ")));
+    }
+    line
+}
+
+/// Footer for paged output - the cursor goes to stderr so stdout stays pipeable
+fn print_next_page(shown: usize, next_cursor: Option<&str>) {
+    match next_cursor {
+        Some(cursor) => info!("-- {} shown, more with --after {}", shown, cursor),
+        None => info!("-- {} shown, end of results", shown),
+    }
+}
+
+fn cmd_sql(db: &Path, statement: Option<&str>, params: Vec<(Option<String>, String)>, csv: bool) -> Result<()> {
+    // Bare values bind ?1, ?2, ... in the order given
+    let mut position = 0;
+    let params = params
+        .into_iter()
+        .map(|(name, value)| {
+            let name = name.unwrap_or_else(|| {
+                position += 1;
+                position.to_string()
+            });
+            (name, param_value(&value))
+        })
+        .collect();
+    let mut console = SqlConsole::new(DuckDBStorage::new(Some(db))?, params);
+    match statement {
+        Some(sql) => console.run_once(sql, csv),
+        None => console.run(),
+    }
+}
+
+fn print_storage_usage(storage: &DuckDBStorage, top: usize, window_days: i64) -> Result<()> {
+    let usage = storage.storage_usage(top)?;
+    println!("\nStorage by document (row data; indexes and free pages not included):");
+    println!("  {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}  path", "total", "text", "grids", "embed", "versions", "pdf");
+    for doc in &usage {
+        println!(
+            "  {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}  {}",
+            human_bytes(doc.total()),
+            human_bytes(doc.text),
+            human_bytes(doc.grids),
+            human_bytes(doc.embeddings),
+            human_bytes(doc.versions),
+            doc.pdf.map_or("-".to_string(), human_bytes),
+            doc.path
+        );
+    }
+    let all = storage.storage_usage(0)?;
+    let sum = |part: fn(&DocumentUsage) -> i64| all.iter().map(part).sum::<i64>();
+    println!(
+        "  {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}  all {} documents",
+        human_bytes(sum(DocumentUsage::total)),
+        human_bytes(sum(|d| d.text)),
+        human_bytes(sum(|d| d.grids)),
+        human_bytes(sum(|d| d.embeddings)),
+        human_bytes(sum(|d| d.versions)),
+        human_bytes(sum(|d| d.pdf.unwrap_or(0))),
+        all.len()
+    );
+
+    let forecast = storage.storage_forecast(window_days)?;
+    println!("\nDatabase file: {}", human_bytes(forecast.db_bytes));
+    println!(
+        "Ingest rate: {} documents in the last {} days ({:.1}/day, ~{} each)",
+        forecast.recent_documents,
+        forecast.window_days,
+        forecast.documents_per_day(),
+        human_bytes(forecast.bytes_per_document)
+    );
+    for days in [30, 90, 365] {
+        println!("  in {:>3} days: {}", days, human_bytes(forecast.projected(days)));
+    }
+    Ok(())
+}
+
+/// 1536 -> "1.5 KB"
+fn human_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value.abs() >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn cmd_edits(db: &Path, action: EditsAction) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let undo = matches!(action, EditsAction::Undo { .. });
+    match action {
+        EditsAction::Log { pdf, page } => {
+            let path = pdf.to_string_lossy();
+            let pages = match page {
+                Some(page) => vec![page],
+                None => storage.edited_pages(&path)?,
+            };
+            for page in pages {
+                println!("📄 {} page {}", path, page);
+                for logged in storage.edit_log(&path, page)? {
+                    println!("  {:>6}  {}  {:<3}  {}", logged.seq, logged.created_at, logged.source, describe_edit(&logged.event));
+                }
+            }
+        }
+        EditsAction::Show { pdf, page } => {
+            let (grid, _) = page_edits(&storage, &pdf, page)?;
+            for row in grid {
+                println!("{}", row.iter().collect::<String>().trim_end());
+            }
+        }
+        EditsAction::Undo { pdf, page } | EditsAction::Redo { pdf, page } => {
+            let path = pdf.to_string_lossy();
+            let version = storage.edit_version(&path, page)?;
+            let (_, history) = page_edits(&storage, &pdf, page)?;
+            let (possible, event, verb) = if undo {
+                (history.can_undo(), EditEvent::Undo, "undo")
+            } else {
+                (history.can_redo(), EditEvent::Redo, "redo")
+            };
+            if !possible {
+                anyhow::bail!("Nothing to {} on page {} of {}", verb, page, pdf.display());
+            }
+            storage.append_edit_events_at(&path, page, "cli", version, &[event])?;
+            info!("✅ Page {}: {} done", page, verb);
+        }
+        EditsAction::Conflicts { pdf } => {
+            let path = pdf.map(|pdf| pdf.to_string_lossy().to_string());
+            let conflicts = storage.edit_conflicts(path.as_deref())?;
+            if conflicts.is_empty() {
+                println!("No edit conflicts");
+            }
+            for conflict in conflicts {
+                let state = match (&conflict.resolved_at, &conflict.resolution) {
+                    (Some(at), Some(resolution)) => format!("resolved {} ({})", at, resolution),
+                    _ => "unresolved".to_string(),
+                };
+                println!(
+                    "#{:<4} {}  {} page {} [{}]  {} event(s) from {} made at v{}, page was at v{}  {}",
+                    conflict.id,
+                    conflict.created_at,
+                    conflict.path,
+                    conflict.page,
+                    conflict.branch,
+                    conflict.events,
+                    conflict.source,
+                    conflict.expected,
+                    conflict.actual,
+                    state
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_versions(db: &Path, action: VersionsAction) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    match action {
+        VersionsAction::List => {
+            let head = storage.current_branch()?;
+            println!("Branches:");
+            for branch in storage.branches()? {
+                let state = match (&branch.merged_at, &branch.discarded_at) {
+                    (Some(at), _) => format!("merged {}", at),
+                    (_, Some(at)) => format!("discarded {}", at),
+                    _ => "open".to_string(),
+                };
+                let fork = branch.parent.as_ref().map(|parent| format!("from {} at seq {}", parent, branch.base_seq)).unwrap_or_default();
+                let marker = if branch.name == head { "*" } else { " " };
+                println!("{} {:<24} {:>6} events  {:<28} {}", marker, branch.name, branch.events, state, fork);
+            }
+            println!("Snapshots:");
+            for snapshot in storage.snapshots()? {
+                println!("  {:<24} {} at seq {}  {}  {}", snapshot.name, snapshot.branch, snapshot.seq, snapshot.created_at,
+                    snapshot.note.as_deref().unwrap_or(""));
+            }
+        }
+        VersionsAction::Snapshot { name, note } => {
+            let snapshot = storage.create_snapshot(&name, note.as_deref())?;
+            info!("📸 Snapshot {} of {} at seq {}", snapshot.name, snapshot.branch, snapshot.seq);
+        }
+        VersionsAction::Checkout { name, new_branch: true } => {
+            let branch = storage.create_branch(&name)?;
+            info!("🌿 On new branch {} (from {} at seq {})", branch.name, branch.parent.unwrap_or_default(), branch.base_seq);
+        }
+        VersionsAction::Checkout { name, new_branch: false } => {
+            if storage.snapshot(&name)?.is_some() {
+                let pages = storage.restore_snapshot(&name)?;
+                info!("⏪ {} pages on {} restored to snapshot {}", pages, storage.current_branch()?, name);
+            } else {
+                storage.switch_branch(&name)?;
+                info!("🌿 On branch {}", name);
+            }
+        }
+        VersionsAction::Merge { name } => {
+            let pages = storage.merge_branch(&name)?;
+            info!("✅ Merged {} ({} pages); now on {}", name, pages, storage.current_branch()?);
+        }
+        VersionsAction::Discard { name } => {
+            storage.discard_branch(&name)?;
+            info!("🗑️  Discarded {}; now on {}", name, storage.current_branch()?);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_replace_all(db: &Path, pattern: &str, replacement: &str, is_regex: bool, preview: bool) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let matcher = regex::Regex::new(&if is_regex { pattern.to_string() } else { regex::escape(pattern) })?;
+    let replacement = if is_regex { replacement.to_string() } else { replacement.replace('$', "$$") };
+
+    let mut pages: Vec<PageEvents> = Vec::new();
+    let (mut lines, mut documents) = (0, 0);
+    let mut after_id = 0;
+    loop {
+        let batch = storage.documents_after(after_id, 100)?;
+        let Some(last) = batch.last() else { break };
+        after_id = last.id;
+
+        for doc in batch {
+            let touched = pages.len();
+            for (index, text) in doc.content.split('\x0c').enumerate() {
+                let page = index + 1;
+                // Pages edited before carry on from their log; others start from the stored text
+                let log = storage.edit_log(&doc.path, page)?;
+                let (grid, mut events) = if log.is_empty() {
+                    let grid: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+                    let base = EditEvent::base(&grid);
+                    (grid, vec![base])
+                } else {
+                    let events: Vec<EditEvent> = log.into_iter().map(|logged| logged.event).collect();
+                    (replay(&events).0, Vec::new())
+                };
+
+                let Some((command, changes)) = replace_in_grid(&grid, &matcher, &replacement) else { continue };
+                for change in &changes {
+                    println!("{} p.{} l.{}", doc.path, page, change.row + 1);
+                    println!("  - {}", change.before.trim_end());
+                    println!("  + {}", change.after.trim_end());
+                }
+                lines += changes.len();
+                events.push(EditEvent::Edit { command, merged: false });
+                pages.push((doc.path.clone(), page, events));
+            }
+            if pages.len() > touched {
+                documents += 1;
+            }
+        }
+    }
+
+    if pages.is_empty() {
+        info!("No matches for '{}'", pattern);
+        return Ok(());
+    }
+    if preview {
+        info!("🔍 {} lines on {} pages in {} documents would change (run without --preview to apply)",
+            lines, pages.len(), documents);
+        return Ok(());
+    }
+
+    let summary = format!("'{}' -> '{}' ({} lines, {} pages)", pattern, replacement, lines, pages.len());
+    let operation = storage.apply_edit_operation("replace-all", &summary, &pages)?;
+    info!("✅ Replaced {} lines on {} pages in {} documents as operation #{}", lines, pages.len(), documents, operation.id);
+    info!("   Undo it with: chonker8 replace-all --rollback {}", operation.id);
+    Ok(())
+}
+
+/// Revert every page an operation changed, or nothing if any of them was edited
+/// again in the meantime
+fn cmd_replace_rollback(db: &Path, id: i64) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let operation = storage.edit_operation(id)?;
+    if let Some(when) = &operation.rolled_back_at {
+        anyhow::bail!("Operation #{} was already rolled back at {}", id, when);
+    }
+
+    let mut reverts: Vec<PageEvents> = Vec::new();
+    let mut conflicts = Vec::new();
+    for (path, page, events) in storage.edit_operation_events(&operation)? {
+        let (grid, _) = page_edits(&storage, Path::new(&path), page)?;
+        for event in events {
+            let EditEvent::Edit { command, .. } = event else { continue };
+            match revert(&grid, &command) {
+                Ok(command) => reverts.push((path.clone(), page, vec![EditEvent::Edit { command, merged: false }])),
+                Err(rows) => {
+                    let rows: Vec<String> = rows.iter().map(|row| (row + 1).to_string()).collect();
+                    conflicts.push(format!("{} p.{} l.{}", path, page, rows.join(",")));
+                }
+            }
+        }
+    }
+
+    if !conflicts.is_empty() {
+        anyhow::bail!("Lines changed by operation #{} were edited since - undo those edits first:\n  {}",
+            id, conflicts.join("\n  "));
+    }
+    storage.roll_back_edit_operation(&operation, &reverts)?;
+    info!("✅ Rolled back operation #{}: {}", id, operation.summary);
+    Ok(())
+}
+
+/// A page's grid and undo/redo state, replayed from its edit log
+fn page_edits(storage: &DuckDBStorage, pdf: &Path, page: usize) -> Result<(Vec<Vec<char>>, EditHistory)> {
+    let log = storage.edit_log(&pdf.to_string_lossy(), page)?;
+    if log.is_empty() {
+        anyhow::bail!("Page {} of {} has no edits", page, pdf.display());
+    }
+    let events: Vec<EditEvent> = log.into_iter().map(|logged| logged.event).collect();
+    Ok(replay(&events))
+}
+
+fn cmd_diff(db: &Path, pdf: &Path, page: usize, from: &str, to: &str) -> Result<()> {
+    use crossterm::style::Stylize;
+
+    let storage = DuckDBStorage::new(Some(db))?;
+    let old = page_version(&storage, pdf, page, from)?;
+    let new = page_version(&storage, pdf, page, to)?;
+    let diff = diff_lines(&grid_lines(&old), &grid_lines(&new));
+    let (added, removed) = diff_stats(&diff);
+
+    println!("📄 {} page {}: {} → {} ({} added, {} removed)", pdf.display(), page, from, to, added, removed);
+    for line in &diff {
+        let text = format!("{} {}", line.marker(), line.text());
+        match line {
+            DiffLine::Same(_) => println!("{}", text.dark_grey()),
+            DiffLine::Added(_) => println!("{}", text.green()),
+            DiffLine::Removed(_) => println!("{}", text.red()),
+        }
+    }
+    Ok(())
+}
+
+fn cmd_rename(db: &Path, docs: &[PathBuf], all: bool, apply: bool) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let mut candidates = Vec::new();
+    if docs.is_empty() {
+        let mut last_id = 0;
+        loop {
+            let batch = storage.documents_after(last_id, 500)?;
+            let Some(last) = batch.last() else { break };
+            last_id = last.id;
+            candidates.extend(batch.into_iter().filter(|doc| {
+                all || naming::looks_generated(&Path::new(&doc.path).file_stem().unwrap_or_default().to_string_lossy())
+            }));
+        }
+    } else {
+        for doc in docs {
+            let path = doc.to_string_lossy();
+            candidates.push(storage.document_by_path(&path)?.ok_or_else(|| anyhow::anyhow!("{} is not in the database", path))?);
+        }
+    }
+
+    // Two scans of the same invoice get the same name; later ones are numbered
+    let mut planned: Vec<(String, PathBuf)> = Vec::new();
+    for doc in candidates {
+        let old = Path::new(&doc.path);
+        let Some(name) = naming::suggest_name(&doc.content) else {
+            println!("{}  (nothing to name it by)", doc.path);
+            continue;
+        };
+        let target = unique_target(&old.with_file_name(name), |p| p != old && (p.exists() || planned.iter().any(|(_, t)| t == p)));
+        if target.file_name() == old.file_name() {
+            continue;
+        }
+        println!("{} → {}", doc.path, target.display());
+        planned.push((doc.path, target));
+    }
+
+    if !apply {
+        info!("🔍 {} files would be renamed (run with --apply to rename them)", planned.len());
+        return Ok(());
+    }
+    let mut renamed = 0;
+    for (old, new) in &planned {
+        if !Path::new(old).exists() {
+            error!("   ✗ {}: file not found", old);
+            continue;
+        }
+        std::fs::rename(old, new)?;
+        // The file moves first; if the database refuses, put it back
+        if let Err(e) = storage.rename_path(old, &new.to_string_lossy()) {
+            std::fs::rename(new, old)?;
+            error!("   ✗ {}: {}", old, e);
+            continue;
+        }
+        renamed += 1;
+    }
+    info!("✅ Renamed {} of {} files", renamed, planned.len());
+    Ok(())
+}
+
+fn cmd_tag(db: &Path, docs: &[PathBuf], add: &[String], remove: &[String]) -> Result<()> {
+    let storage = DuckDBStorage::new(Some(db))?;
+    for doc in docs {
+        let path = doc.to_string_lossy();
+        for tag in add {
+            storage.add_tag(&path, tag)?;
+        }
+        for tag in remove {
+            if !storage.remove_tag(&path, tag)? {
+                warn!("⚠️  {} wasn't tagged {}", path, tag);
+            }
+        }
+        println!("{}: {}", path, storage.tags(&path)?.join(", "));
+    }
+    Ok(())
+}
+
+fn cmd_export(
+    db: &Path,
+    docs: &[PathBuf],
+    format: &str,
+    out: Option<&Path>,
+    reflow: Option<usize>,
+    dehyphenate: bool,
+    profile: Option<&str>,
+) -> Result<()> {
+    let storage = DuckDBStorage::new(Some(db))?;
+    let registry = FormatterRegistry::default();
+    let formatter: &dyn OutputFormatter = match profile {
+        Some(_) => &InvoiceFormatter,
+        None => registry.get(format)?,
+    };
+    if (reflow.is_some() || dehyphenate) && formatter.name() != "text" {
+        anyhow::bail!("--reflow and --dehyphenate only apply to --format text");
+    }
+    if let Some(dir) = out {
+        std::fs::create_dir_all(dir)?;
+    }
+    for doc in docs {
+        let path = doc.to_string_lossy();
+        let stored = storage.document_by_path(&path)?.ok_or_else(|| anyhow::anyhow!("{} isn't stored - extract it first", path))?;
+        let mut document = document_output(&storage, &path, &stored.content)?;
+        for page in &mut document.pages {
+            if dehyphenate {
+                page.text = reflow::dehyphenate(&page.text);
+            }
+            if let Some(width) = reflow {
+                page.text = reflow::reflow(&page.text, width).join("\n");
+            }
+        }
+        match out {
+            Some(dir) => info!("📄 {}", export_document(formatter, &document, dir)?.display()),
+            None => formatter.write_document(&mut std::io::stdout().lock(), &document)?,
+        }
+    }
+    Ok(())
+}
+
+/// A stored document's pages with their stored word boxes, and page sizes when
+/// the PDF is still where it was extracted from
+fn document_output(storage: &DuckDBStorage, path: &str, content: &str) -> Result<DocumentOutput> {
+    let mut document = DocumentOutput::from_content(path, content);
+    let pdf = lopdf::Document::load(path).ok();
+    for (index, page) in document.pages.iter_mut().enumerate() {
+        page.words = storage.page_words(path, index)?;
+        page.size = pdf.as_ref().and_then(|pdf| page_dimensions(pdf, index).ok());
+    }
+    Ok(document)
+}
+
+/// Write `<name>.<ext>` into `dir`, returning where it went
+fn export_document(formatter: &dyn OutputFormatter, document: &DocumentOutput, dir: &Path) -> Result<PathBuf> {
+    let stem = Path::new(&document.path).file_stem().map_or("document".into(), |stem| stem.to_string_lossy());
+    let target = dir.join(format!("{}.{}", stem, formatter.extension()));
+    temp_files::write_with(&target, |out| formatter.write_document(out, document))?;
+    Ok(target)
+}
+
+fn cmd_meta(db: &Path, pdf: &Path, set: &[(String, String)], unset: &[String], json: bool) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let path = pdf.to_string_lossy();
+    let meta = if set.is_empty() && unset.is_empty() {
+        storage.document_metadata(&path)?.ok_or_else(|| anyhow::anyhow!("{} isn't stored - extract it first", path))?
+    } else {
+        storage.set_metadata_fields(&path, set, unset)?
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "core": meta.core, "fields": meta.fields }))?);
+    } else {
+        print_metadata(&path, &meta);
+    }
+    Ok(())
+}
+
+fn print_metadata(path: &str, meta: &DocumentMetadata) {
+    println!("📄 {}", path);
+    let core = &meta.core;
+    let language = core.language.as_deref().map(|code| format!("{} ({})", code, language_name(code)));
+    for (name, value) in [
+        ("title", core.title.clone()),
+        ("author", core.author.clone()),
+        ("doc_date", core.doc_date.clone()),
+        ("classification", core.classification.clone()),
+        ("language", language),
+    ] {
+        println!("  {:<16} {}", name, value.unwrap_or_else(|| "-".to_string()));
+    }
+    for (name, value) in &meta.fields {
+        let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+        println!("  {:<16} {}", name, value);
+    }
+}
+
+fn cmd_info(db: &Path, pdf: &Path, page: usize, json: bool) -> Result<()> {
+    let storage = DuckDBStorage::new(Some(db))?;
+    let path = pdf.to_string_lossy();
+    let info = storage
+        .page_info(&path, page)?
+        .ok_or_else(|| anyhow::anyhow!("{} page {} isn't stored - extract it first", path, page))?;
+    let min_quality = VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config.min_quality;
+    let reextract = info.extraction.as_ref().is_none_or(|extraction| extraction.needs_reextraction(min_quality));
+    if json {
+        println!("{}", serde_json::to_string_pretty(&page_info_json(&path, &info, reextract))?);
+    } else {
+        print_page_info(&path, &info, reextract);
+    }
+    Ok(())
+}
+
+fn page_info_json(path: &str, info: &PageInfo, reextract: bool) -> serde_json::Value {
+    let extraction = info.extraction.as_ref();
+    serde_json::json!({
+        "path": path,
+        "page": info.page,
+        "method": extraction.map(|e| &e.method),
+        "quality": extraction.map(|e| e.quality),
+        "duration_ms": extraction.map(|e| e.duration_ms),
+        "engine_version": extraction.map(|e| &e.engine_version),
+        "extracted_at": info.extracted_at,
+        "language": info.language.as_ref().map(|l| &l.lang),
+        "language_confidence": info.language.as_ref().map(|l| l.confidence),
+        "convergence": info.convergence,
+        "needs_reextraction": reextract,
+    })
+}
+
+fn print_page_info(path: &str, info: &PageInfo, reextract: bool) {
+    println!("📄 {}, page {}", path, info.page);
+    let extraction = info.extraction.as_ref();
+    let language = info.language.as_ref().map(|l| format!("{} ({}, {:.2})", l.lang, language_name(&l.lang), l.confidence));
+    for (name, value) in [
+        ("method", extraction.map(|e| e.method.clone())),
+        ("quality", extraction.map(|e| format!("{:.2}", e.quality))),
+        ("time", extraction.map(|e| format_duration(Duration::from_millis(e.duration_ms)))),
+        ("engine version", extraction.map(|e| e.engine_version.clone())),
+        ("extracted at", info.extracted_at.clone()),
+        ("language", language),
+        ("convergence", info.convergence.map(|f1| format!("{:.2} token F1", f1))),
+    ] {
+        println!("  {:<16} {}", name, value.unwrap_or_else(|| "-".to_string()));
+    }
+    println!("  {:<16} {}", "re-extraction", if reextract { "needed" } else { "not needed" });
+}
+
+fn cmd_db_maintain(db: &Path, enforce_retention: bool, dry_run: bool, json: bool) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    if enforce_retention {
+        let rules = VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config.retention;
+        let candidates = storage.retention_candidates(&rules)?;
+        let mut report = Vec::new();
+        for candidate in candidates {
+            let rows = if dry_run { None } else { Some(storage.purge_path(&candidate.path, &candidate.reason)?) };
+            if !json {
+                let rows = rows.map_or_else(|| "would be purged".to_string(), |rows| format!("{} rows purged", rows));
+                println!("{}  ({}; {})", candidate.path, candidate.reason, rows);
+            }
+            report.push(serde_json::json!({ "path": candidate.path, "reason": candidate.reason, "rows": rows }));
+        }
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        if dry_run {
+            info!("🔍 {} documents would be purged (run without --dry-run to purge them)", report.len());
+            return Ok(());
+        }
+        info!("🗑️  Purged {} documents; each is recorded in the retention_audit table", report.len());
+    }
+    storage.vacuum()?;
+    info!("✅ {} vacuumed and optimized", db.display());
+    Ok(())
+}
+
+fn archive_summary(counts: &ArchiveCounts) -> String {
+    let tables: Vec<String> = counts.iter().filter(|(_, rows)| **rows > 0).map(|(table, rows)| format!("{} {}", rows, table)).collect();
+    if tables.is_empty() { "no rows".to_string() } else { tables.join(", ") }
+}
+
+/// A database archive rather than a document, by its name: export and import
+/// take either
+fn is_archive(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(".tar.zst")
+}
+
+fn cmd_db_export(db: &Path, archive: &Path) -> Result<()> {
+    let storage = DuckDBStorage::new(Some(db))?;
+    let mut counts = ArchiveCounts::new();
+    temp_files::write_with(archive, |out| {
+        counts = storage.export_archive(out)?;
+        Ok(())
+    })?;
+    let size = std::fs::metadata(archive)?.len();
+    info!("📦 Exported {} to {} ({})", archive_summary(&counts), archive.display(), human_bytes(size as i64));
+    Ok(())
+}
+
+fn cmd_db_import(db: &Path, archive: &Path) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let file = std::fs::File::open(archive).map_err(|e| anyhow::anyhow!("Can't open {}: {}", archive.display(), e))?;
+    let counts = storage.import_archive(std::io::BufReader::new(file))?;
+    info!("📥 Imported {} into {}", archive_summary(&counts), db.display());
+    info!("🔎 Run `chonker8 index drain` to make imported edits searchable");
+    Ok(())
+}
+
+fn cmd_db_migrate(db: &Path, dry_run: bool) -> Result<()> {
+    let (version, pending) = pending_migrations(db)?;
+    info!("🗄️  {} is at schema version {} (this release: {})", db.display(), version, latest_schema_version());
+    for migration in &pending {
+        println!("  {:>3}  {}", migration.version, migration.name);
+    }
+    if pending.is_empty() {
+        info!("✅ Up to date");
+    } else if dry_run {
+        info!("🔍 {} migrations would run (run without --dry-run to apply them)", pending.len());
+    } else {
+        DuckDBStorage::new(Some(db))?;
+        info!("✅ Applied {} migrations", pending.len());
+    }
+    Ok(())
+}
+
+fn cmd_cache(action: CacheAction, config: &CacheConfig) -> Result<()> {
+    let root = disk_cache::cache_root();
+    match action {
+        CacheAction::Stats => {
+            info!("🗄️  Cache directory: {}", root.display());
+            let usage = disk_cache::stats(&root)?;
+            for cache in &usage {
+                println!("{:<20} {:>8} files {:>10}", cache.name, cache.files, human_bytes(cache.bytes as i64));
+            }
+            let total: u64 = usage.iter().map(|cache| cache.bytes).sum();
+            println!("{:<20} {:>14} {:>10} of {}", "total", "", human_bytes(total as i64), human_bytes((config.max_size_mb * 1024 * 1024) as i64));
+        }
+        CacheAction::Prune { max_size_mb, older_than } => {
+            let max_size_mb = max_size_mb.unwrap_or(config.max_size_mb);
+            let (files, freed) = disk_cache::prune(&root, max_size_mb * 1024 * 1024, older_than)?;
+            info!("✂️  Pruned {} files ({} freed); the caches are within {}", files, human_bytes(freed as i64), human_bytes((max_size_mb * 1024 * 1024) as i64));
+        }
+        CacheAction::Clear { name } => {
+            if let Some(name) = &name {
+                if !root.join(name).is_dir() {
+                    anyhow::bail!("No cache named '{}' under {} (`chonker8 cache stats` lists them)", name, root.display());
+                }
+            }
+            let freed = disk_cache::clear(&root, name.as_deref())?;
+            info!("🗑️  Cleared {} ({} freed)", name.as_deref().unwrap_or("every cache"), human_bytes(freed as i64));
+        }
+    }
+    Ok(())
+}
+
+fn cmd_self_update(check: bool, force: bool, allow_unsigned: bool) -> Result<()> {
+    info!("🔎 Checking for a newer release than {}", self_update::CURRENT_VERSION);
+    let release = self_update::latest_release()?;
+    if !release.is_newer() && (check || !force) {
+        println!("chonker8 {} is the latest release", self_update::CURRENT_VERSION);
+        return Ok(());
+    }
+    if check {
+        println!("chonker8 {} is available (this is {}) - `chonker8 self-update` installs it", release.version, self_update::CURRENT_VERSION);
+        return Ok(());
+    }
+    let exe = self_update::install(&release, allow_unsigned, force)?;
+    info!("✅ Updated {} to {}", exe.display(), release.version);
+    Ok(())
+}
+
+fn cmd_selftest(registry: &ExtractorRegistry, json: bool) -> Result<()> {
+    info!("🩺 Running a sample page through chonker8 {}", self_update::CURRENT_VERSION);
+    let steps = selftest::run(registry)?;
+    let mut report = Vec::new();
+    for step in &steps {
+        let outcome = match step.outcome {
+            Outcome::Pass => "PASS",
+            Outcome::Fail => "FAIL",
+            Outcome::Skip => "SKIP",
+        };
+        let elapsed = step.elapsed.map(|elapsed| elapsed.as_millis() as u64);
+        if !json {
+            let elapsed = elapsed.map(|ms| format!("{} ms", ms)).unwrap_or_default();
+            println!("{}  {:<20} {:>8}  {}", outcome, step.subsystem, elapsed, step.detail);
+        }
+        report.push(serde_json::json!({ "subsystem": step.subsystem, "outcome": outcome.to_lowercase(), "elapsed_ms": elapsed, "detail": step.detail }));
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+    let failed = steps.iter().filter(|step| step.outcome == Outcome::Fail).count();
+    let skipped = steps.iter().filter(|step| step.outcome == Outcome::Skip).count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} checks failed", failed, steps.len());
+    }
+    info!("✅ All checks passed ({} skipped)", skipped);
+    Ok(())
+}
+
+fn cmd_models(action: ModelsAction, config: &models::ModelsConfig) -> Result<()> {
+    let selected = |names: &[String]| -> Result<Vec<&'static models::Model>> {
+        if names.is_empty() {
+            return Ok(MODELS.iter().collect());
+        }
+        names.iter().map(|name| models::model(name)).collect()
+    };
+    match action {
+        ModelsAction::List => {
+            info!("📦 Models directory: {}", models::models_dir().display());
+            for model in MODELS {
+                let installed = model.files.iter().filter(|file| models::locate(file).is_file()).count();
+                let state = match installed {
+                    0 => "missing".to_string(),
+                    n if n == model.files.len() => "installed".to_string(),
+                    n => format!("partial ({}/{} files)", n, model.files.len()),
+                };
+                let location = models::locate(model.files[0]);
+                let location = location.parent().filter(|_| model.files[0].contains('/')).unwrap_or(&location);
+                println!("{:<20} {:<22} {:<44} {}", model.name, state, model.used_by, location.display());
+            }
+        }
+        ModelsAction::Download { names, force } => {
+            let mut files: Vec<&str> = Vec::new();
+            for model in selected(&names)? {
+                for file in model.files {
+                    if !force && models::locate(file).is_file() {
+                        info!("✅ {} already installed (--force to fetch it again)", file);
+                    } else {
+                        files.push(file);
+                    }
+                }
+            }
+            // Rather than fail halfway through a model
+            let unsourced: Vec<&str> = files.iter().copied().filter(|file| !config.sources.contains_key(*file)).collect();
+            if !unsourced.is_empty() {
+                anyhow::bail!("No download URL for {} - add a [models.sources.\"<file>\"] table with url and sha256 to {} for each",
+                    unsourced.join(", "), DEFAULT_CONFIG_PATH);
+            }
+            for file in files {
+                info!("⬇️  Downloading {}", file);
+                let bytes = models::download_file(file, config)?;
+                info!("✅ {} ({} bytes)", file, bytes);
+            }
+        }
+        ModelsAction::Verify { names } => {
+            let mut failed = 0;
+            for model in selected(&names)? {
+                for file in model.files {
+                    let status = models::check_file(file, config)?;
+                    let verdict = match &status {
+                        FileStatus::Missing => "missing".to_string(),
+                        FileStatus::Unverified => "present, no sha256 configured".to_string(),
+                        FileStatus::Verified => "ok".to_string(),
+                        FileStatus::Mismatch { actual } => format!("CHECKSUM MISMATCH (is {})", actual),
+                    };
+                    failed += matches!(status, FileStatus::Mismatch { .. }) as usize;
+                    println!("{:<50} {}", file, verdict);
+                }
+            }
+            if failed > 0 {
+                anyhow::bail!("{} model files do not match their checksums - `chonker8 models download --force` replaces them", failed);
+            }
+        }
+        ModelsAction::Remove { names } => {
+            for model in selected(&names)? {
+                let removed = models::remove_model(model)?;
+                info!("🗑️  {}: {} files removed from {}", model.name, removed, models::models_dir().display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pages read to place a document that hasn't been extracted yet
+const ORGANIZE_PAGES: usize = 2;
+
+fn cmd_organize(db: &Path, dir: &Path, by: &str, into: Option<&Path>, symlink: bool, apply: bool, engine: &str) -> Result<()> {
+    let by = organize::parse_by(by)?;
+    let root = into.unwrap_or(dir);
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let registry = default_registry()?;
+    let pdfs = ingest::find_pdfs(dir)?;
+
+    let mut planned: Vec<Move> = Vec::new();
+    let bar = progress::bar(pdfs.len(), "files");
+    for pdf in &pdfs {
+        bar.inc(1);
+        let text = match storage.document_by_path(&pdf.to_string_lossy())? {
+            Some(doc) => doc.content,
+            None => {
+                let extracted = lopdf::Document::load(pdf)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|document| registry.extract_document(engine, pdf, document.get_pages().len().min(ORGANIZE_PAGES)));
+                match extracted {
+                    Ok((text, _)) => text,
+                    Err(e) => {
+                        error!("   ✗ {}: {}", pdf.display(), e);
+                        continue;
+                    }
+                }
+            }
+        };
+        let target = root.join(organize::folder_for(&naming::NameParts::detect(&text), &by)).join(pdf.file_name().unwrap_or_default());
+        // Already in place, from an earlier run
+        if target == *pdf {
+            continue;
+        }
+        let target = unique_target(&target, |p| p.exists() || planned.iter().any(|step| step.to == p));
+        planned.push(Move { from: pdf.clone(), to: target });
+    }
+    bar.finish_and_clear();
+
+    for step in &planned {
+        println!("{} → {}", step.from.display(), step.to.display());
+    }
+    let verb = if symlink { "linked" } else { "moved" };
+    if !apply {
+        info!("🔍 {} files would be {} (run with --apply to do it)", planned.len(), verb);
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(root)?;
+    let manifest_path = root.join(format!(".chonker8-organize-{}.json", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    let mut manifest = Manifest::new(root, symlink);
+    let known = storage.known_files()?;
+    for step in planned {
+        if let Err(e) = organize::place(&step.from, &step.to, symlink) {
+            error!("   ✗ {}: {}", step.from.display(), e);
+            continue;
+        }
+        // A moved file takes its database records along; if the database refuses, put it back
+        let old = step.from.to_string_lossy().to_string();
+        if !symlink && (known.contains_key(&old) || storage.document_by_path(&old)?.is_some()) {
+            if let Err(e) = storage.rename_path(&old, &step.to.to_string_lossy()) {
+                organize::unplace(&step, false, root)?;
+                error!("   ✗ {}: {}", old, e);
+                continue;
+            }
+        }
+        manifest.moves.push(step);
+        manifest.save(&manifest_path)?;
+    }
+    let done = if symlink { "Linked" } else { "Moved" };
+    info!("✅ {} {} files into {}", done, manifest.moves.len(), root.display());
+    info!("   Undo with: chonker8 organize --undo {}", manifest_path.display());
+    Ok(())
+}
+
+fn cmd_organize_undo(db: &Path, manifest_path: &Path) -> Result<()> {
+    let manifest = Manifest::load(manifest_path)?;
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let known = storage.known_files()?;
+    let mut undone = 0;
+    for step in manifest.moves.iter().rev() {
+        if let Err(e) = organize::unplace(step, manifest.symlinks, &manifest.root) {
+            error!("   ✗ {}: {}", step.to.display(), e);
+            continue;
+        }
+        let new = step.to.to_string_lossy().to_string();
+        if !manifest.symlinks && (known.contains_key(&new) || storage.document_by_path(&new)?.is_some()) {
+            storage.rename_path(&new, &step.from.to_string_lossy())?;
+        }
+        undone += 1;
+    }
+    info!("↩️  Put back {} of {} files", undone, manifest.moves.len());
+    // Keep the manifest while anything is left to undo
+    if undone == manifest.moves.len() {
+        std::fs::remove_file(manifest_path)?;
+    }
+    Ok(())
+}
+
+/// `path`, or `name-2.pdf`, `name-3.pdf`, ... beside it if `taken`
+fn unique_target(path: &Path, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let mut candidate = path.to_path_buf();
+    let mut n = 2;
+    while taken(&candidate) {
+        candidate = path.with_file_name(format!("{}-{}.pdf", stem, n));
+        n += 1;
+    }
+    candidate
+}
+
+fn cmd_grep(db: &Path, pdf: &Path, pattern: &str, ignore_case: bool, context: usize, fresh: bool, engine: &str) -> Result<()> {
+    use crossterm::style::Stylize;
+
+    let pattern = regex::RegexBuilder::new(pattern).case_insensitive(ignore_case).build()?;
+    let content = document_text(db, pdf, fresh, engine)?;
+
+    let matches = grep_pages(&content, &pattern, context);
+    let mut previous: Option<&GrepMatch> = None;
+    for m in &matches {
+        // Matches sharing a line print it once; separate groups like grep does
+        if previous.is_some_and(|p| (p.page, p.line) == (m.page, m.line)) {
+            print_groups(m);
+            continue;
+        }
+        if previous.is_some() && context > 0 {
+            println!("{}", "--".dark_grey());
+        }
+        for (offset, line) in m.before.iter().enumerate() {
+            println!("{} {}", format!("{}:{}-", m.page, m.line - m.before.len() + offset).dark_grey(), line);
+        }
+        let start = m.line_text.char_indices().nth(m.column - 1).map_or(m.line_text.len(), |(i, _)| i);
+        let end = (start + m.text.len()).min(m.line_text.len());
+        println!("{} {}{}{}", format!("{}:{}:{}:", m.page, m.line, m.column).cyan(),
+            &m.line_text[..start], m.line_text[start..end].to_string().red().bold(), &m.line_text[end..]);
+        print_groups(m);
+        for (offset, line) in m.after.iter().enumerate() {
+            println!("{} {}", format!("{}:{}-", m.page, m.line + 1 + offset).dark_grey(), line);
+        }
+        previous = Some(m);
+    }
+
+    let mut pages: Vec<usize> = matches.iter().map(|m| m.page).collect();
+    pages.dedup();
+    info!("🔎 {} matches on {} pages", matches.len(), pages.len());
+    Ok(())
+}
+
+/// A PDF's text, form-feed separated: the stored extraction if there is one, else extracted now
+fn document_text(db: &Path, pdf: &Path, fresh: bool, engine: &str) -> Result<String> {
+    let stored = if fresh { None } else { open_store(db)?.document_by_path(&pdf.to_string_lossy())? };
+    match stored {
+        Some(doc) => {
+            info!("📚 Using the stored extraction from {} (--fresh to extract again)", db.display());
+            Ok(doc.content)
+        }
+        None => {
+            let pages = lopdf::Document::load(pdf)?.get_pages().len();
+            info!("⚙️  Extracting {} pages of {}", pages, pdf.display());
+            let bar = progress::bar(pages, "pages");
+            let (content, _) = default_registry()?.extract_document_with_progress(engine, pdf, pages, |done| bar.set_position(done as u64))?;
+            bar.finish_and_clear();
+            Ok(content)
+        }
+    }
+}
+
+fn cmd_speak(db: &Path, pdf: &Path, pages: Option<&str>, out: &Path, fresh: bool, engine: &str) -> Result<()> {
+    let content = document_text(db, pdf, fresh, engine)?;
+    let texts: Vec<&str> = content.split('\x0c').collect();
+    let selected = match pages {
+        Some(spec) => speech::parse_pages(spec, texts.len())?,
+        None => (1..=texts.len()).collect(),
+    };
+    let pages: Vec<(usize, &str)> = selected.into_iter().map(|page| (page, texts[page - 1])).collect();
+    let chapters = speech::chapters(&pages);
+    if chapters.is_empty() {
+        anyhow::bail!("No text to read on the selected pages of {}", pdf.display());
+    }
+
+    let tts = TtsEngine::detect()?;
+    info!("🔊 Reading {} chapters with {}", chapters.len(), tts.name());
+    let work = temp_files::scratch_dir()?;
+    let (mut segments, mut markers, mut elapsed) = (Vec::new(), Vec::new(), 0u64);
+    let bar = progress::bar(chapters.len(), "chapters");
+    for (index, chapter) in chapters.iter().enumerate() {
+        bar.set_message(chapter.title.clone());
+        let segment = tts.synthesize(&chapter.paragraphs.join("\n\n"), work.path(), &format!("chapter-{:03}", index))?;
+        let length = speech::duration_ms(&segment)?;
+        debug!("  [{}/{}] p.{} {}", index + 1, chapters.len(), chapter.first_page, chapter.title);
+        markers.push((chapter.title.clone(), elapsed, elapsed + length));
+        segments.push(segment);
+        elapsed += length;
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+    speech::join_segments(&segments, &speech::ffmetadata(&markers), work.path(), out)?;
+    info!("✅ {} ({}, {} chapters)", out.display(), format_duration(Duration::from_millis(elapsed)), markers.len());
+    Ok(())
+}
+
+fn print_groups(m: &GrepMatch) {
+    for (name, value) in &m.groups {
+        println!("      {} = {}", name, value);
+    }
+}
+
+fn cmd_translate(db: &Path, doc: &Path, to: &str, force: bool, side_by_side: Option<&Path>, width: usize) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    let path = doc.to_string_lossy();
+    let stored = storage
+        .document_by_path(&path)?
+        .ok_or_else(|| anyhow::anyhow!("{} is not in the database - ingest it first", doc.display()))?;
+    let config = VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config;
+
+    // Loaded on first use, so an already translated document needs no model
+    let mut translator: Option<Translator> = None;
+    let mut pages = Vec::new();
+    let bar = progress::bar(stored.content.split('\x0c').count(), "pages");
+    for (index, text) in stored.content.split('\x0c').enumerate() {
+        bar.inc(1);
+        let page = index + 1;
+        let existing = if force { None } else { storage.translation(&path, page, to)? };
+        let translated = match existing {
+            Some(translated) => translated,
+            None if text.trim().is_empty() => String::new(),
+            None => {
+                let translator = match &mut translator {
+                    Some(translator) => translator,
+                    None => translator.insert(Translator::load(to, config.translate.as_ref())?),
+                };
+                let start = Instant::now();
+                let translated = translator.translate_page(text)?;
+                storage.store_translation(&path, page, to, &translated, &translator.engine())?;
+                debug!("  🌐 page {} translated in {:.1}s", page, start.elapsed().as_secs_f32());
+                translated
+            }
+        };
+        pages.push((text, translated));
+    }
+    bar.finish_and_clear();
+    match &translator {
+        Some(translator) => info!("✅ {} → {} with {}", doc.display(), to, translator.engine()),
+        None => info!("✅ {} was already translated to {} (--force to redo)", doc.display(), to),
+    }
+
+    if let Some(out) = side_by_side {
+        let sections: Vec<String> = pages
+            .iter()
+            .enumerate()
+            .map(|(index, (original, translated))| {
+                format!("=== Page {} ===\n{}", index + 1, translate::side_by_side(original, translated, width))
+            })
+            .collect();
+        temp_files::write(out, sections.join("\n\n") + "\n")?;
+        info!("📄 Side by side: {}", out.display());
+    }
+    Ok(())
+}
+
+/// A version of a page's (1-based) grid, named as `chonker8 diff` takes them
+fn page_version(storage: &DuckDBStorage, pdf: &Path, page: usize, version: &str) -> Result<Vec<Vec<char>>> {
+    let path = pdf.to_string_lossy();
+    let stored = || -> Result<Vec<Vec<char>>> {
+        let doc = storage
+            .document_by_path(&path)?
+            .ok_or_else(|| anyhow::anyhow!("{} is not in the database", pdf.display()))?;
+        let text = doc
+            .content
+            .split('\x0c')
+            .nth(page - 1)
+            .ok_or_else(|| anyhow::anyhow!("{} has no page {}", pdf.display(), page))?;
+        Ok(text.lines().map(|line| line.chars().collect()).collect())
+    };
+
+    let log = storage.edit_log(&path, page)?;
+    // A page that was never edited is the same in every version
+    if log.is_empty() || version == "stored" {
+        return stored();
+    }
+    if let Some(snapshot) = storage.snapshot(version)? {
+        let then = storage.snapshot_log(&snapshot, &path, page)?;
+        // A page not edited yet at the snapshot was its base
+        let then = if then.is_empty() { log.into_iter().take(1).collect() } else { then };
+        let events: Vec<EditEvent> = then.into_iter().map(|logged| logged.event).collect();
+        return Ok(replay(&events).0);
+    }
+    let upto = match version {
+        "base" => 1,
+        "latest" => log.len(),
+        seq => {
+            let seq: i64 = seq.parse().map_err(|_| {
+                anyhow::anyhow!("Unknown version '{}' - use base, stored, latest, an edit seq number or a snapshot name", seq)
+            })?;
+            let upto = log.iter().take_while(|logged| logged.seq <= seq).count();
+            if upto == 0 {
+                anyhow::bail!("Page {} has no edits up to seq {} (see `chonker8 edits log`)", page, seq);
+            }
+            upto
+        }
+    };
+    let events: Vec<EditEvent> = log.into_iter().take(upto).map(|logged| logged.event).collect();
+    Ok(replay(&events).0)
+}
+
+fn describe_edit(event: &EditEvent) -> String {
+    match event {
+        EditEvent::Base { rows } => format!("extracted text, {} rows", rows.len()),
+        EditEvent::Edit { command, merged } => {
+            let typed: String = command.edits.iter().map(|edit| edit.after).collect();
+            let at = command.edits.first().map(|e| format!("row {} col {}", e.row + 1, e.col + 1)).unwrap_or_default();
+            format!("{}{} cells at {}: {:?}", if *merged { "+ " } else { "" }, command.edits.len(), at, typed)
+        }
+        EditEvent::Undo => "undo".to_string(),
+        EditEvent::Redo => "redo".to_string(),
+    }
+}
+
+fn cmd_index_push(db: &Path, config: IndexConfig) -> Result<()> {
+    let storage = DuckDBStorage::new(Some(db))?;
+    let batch_size = config.batch_size.max(1);
+    let indexer = SearchIndexer::new(config);
+    let start = Instant::now();
+
+    indexer.ensure_template()?;
+    info!("🔎 Pushing pages to {} index '{}'", indexer.config().endpoint, indexer.config().index);
+
+    let (mut documents, mut total) = (0, PushSummary::default());
+    let mut last_id = 0;
+    loop {
+        // Roughly one bulk request of pages per batch of documents is plenty
+        let batch = storage.documents_after(last_id, batch_size)?;
+        let Some(last) = batch.last() else { break };
+        last_id = last.id;
+        documents += batch.len();
+
+        let pages: Vec<_> = batch
+            .iter()
+            .flat_map(|doc| search_index::page_docs(&doc.path, &doc.content, doc.metadata.as_deref()))
+            .collect();
+        let summary = indexer.push(&pages)?;
+        total.indexed += summary.indexed;
+        total.failed += summary.failed;
+        total.retries += summary.retries;
+        info!("  {} documents, {} pages indexed", documents, total.indexed);
+    }
+
+    info!(
+        "✅ {} pages from {} documents indexed in {:.1}s ({} failed, {} retries)",
+        total.indexed,
+        documents,
+        start.elapsed().as_secs_f64(),
+        total.failed,
+        total.retries
+    );
+    if total.failed > 0 {
+        anyhow::bail!("{} pages could not be indexed", total.failed);
+    }
+    Ok(())
+}
+
+fn cmd_analyze(db: &Path, pdf: &Path, engine: &str) -> Result<()> {
+    let storage = DuckDBStorage::new(Some(db))?;
+    let registry = default_registry()?;
+    let pages = DocumentAnalyzer::new()?.analyze_document(pdf)?;
+
+    // Engine auto mode would pick for each page
+    let engines: Vec<&str> = pages
+        .iter()
+        .map(|page| match engine {
+            AUTO_ENGINE => registry.candidates(page).first().map(|e| e.name()).unwrap_or("-"),
+            forced => forced,
+        })
+        .collect();
+
+    // Repeated pages, by text layer and rendered thumbnail
+    let text_layer = registry.get(ExtractionMethod::PdfToText.name());
+    let mut signatures = Vec::with_capacity(pages.len());
+    for index in 0..pages.len() {
+        let text = match text_layer {
+            Some(extractor) => extractor.extract(pdf, index).map(|r| r.text).unwrap_or_default(),
+            None => String::new(),
+        };
+        signatures.push(PageSignature::new(&text, dedup::page_image_hash(pdf, index).ok()));
+    }
+    let duplicates = dedup::duplicate_pages(&signatures);
+
+    println!("📄 {} - {} pages", pdf.display(), pages.len());
+    println!("{:>5} {:>6} {:>6} {:>8} {:>7} {:>8} {:>10} {:>5}", "PAGE", "TEXT%", "IMG%", "CHARS", "TABLES", "QUALITY", "ENGINE", "DUP");
+    for (index, page) in pages.iter().enumerate() {
+        println!("{:>5} {:>5.0}% {:>5.0}% {:>8} {:>7} {:>8.2} {:>10} {:>5}",
+            index + 1,
+            page.text_coverage * 100.0,
+            page.image_coverage * 100.0,
+            page.char_count,
+            if page.has_tables { "yes" } else { "" },
+            page.text_quality,
+            engines[index],
+            duplicates[index].map(|original| format!("p{}", original + 1)).unwrap_or_default());
+    }
+    let repeated = duplicates.iter().flatten().count();
+    if repeated > 0 {
+        println!("🔁 {} pages repeat the page before them (--collapse-duplicates stores them as a note)", repeated);
+    }
+
+    // Estimate page by page so mixed scanned/native documents are costed per engine
+    let model = CostModel::fit(&storage.recent_runs(COST_MODEL_HISTORY)?);
+    let estimate: Duration = pages
+        .iter()
+        .zip(&engines)
+        .map(|(page, engine)| model.predict(engine, 1, page.image_coverage))
+        .sum();
+    println!("⏱️  Estimated extraction time: {} (model from {} past runs)",
+        format_duration(estimate), model.samples);
+
+    Ok(())
+}
+
+fn cmd_compare(db: &Path, pdf: &Path, page: Option<usize>, stats: bool, heatmap: bool, gif: Option<&Path>, checker: &QualityChecker) -> Result<()> {
+    let mut storage = open_storage(db)?;
+    let total_pages = lopdf::Document::load(pdf)?.get_pages().len();
+    let pages: Vec<usize> = match page {
+        Some(p) if p >= 1 && p <= total_pages => vec![p - 1],
+        Some(p) => anyhow::bail!("Page {} out of range (1-{})", p, total_pages),
+        None => (0..total_pages).collect(),
+    };
+
+    let path = pdf.to_string_lossy().to_string();
+    println!("{:>5} {:>8} {:>8} {:>7} {:>7}", "PAGE", "TOKEN_F1", "WORD_SIM", "NATIVE", "OCR");
+
+    let (mut f1_sum, mut similarity_sum) = (0.0, 0.0);
+    for &page_index in &pages {
+        let native = convergence::native_words(pdf, page_index)?;
+        let ocr = convergence::ocr_words(pdf, page_index)?;
+        let score = convergence::score_words(page_index, &native, &ocr);
+        storage.store_convergence(&path, &score)?;
+        f1_sum += score.token_f1;
+        similarity_sum += score.word_similarity;
+
+        println!("{:>5} {:>8.3} {:>8.3} {:>7} {:>7}",
+            page_index + 1, score.token_f1, score.word_similarity, score.native_words, score.ocr_words);
+
+        if stats {
+            for region in &score.regions {
+                println!("      band {}/{}: f1 {:.3}  sim {:.3}  ({} native / {} ocr words)",
+                    region.band + 1, convergence::REGION_BANDS,
+                    region.token_f1, region.word_similarity, region.native_words, region.ocr_words);
+            }
+
+            let ocr_text: Vec<&str> = ocr.iter().map(|w| w.text.as_str()).collect();
+            let report = checker.assess(&ocr_text.join(" "));
+            let checks: Vec<String> = report
+                .checks
+                .iter()
+                .map(|c| match c.score {
+                    Some(score) => format!("{} {:.2}", c.name, score),
+                    None => format!("{} -", c.name),
+                })
+                .collect();
+            println!("      ocr quality {:.3}{} ({})",
+                report.combined, if report.gibberish { " ⚠️ gibberish" } else { "" }, checks.join(", "));
+        }
+
+        if heatmap {
+            print_heatmap(&convergence::score_grid(&native, &ocr, convergence::HEATMAP_ROWS, convergence::HEATMAP_COLS));
+        }
+
+        if let Some(dir) = gif {
+            let out = write_overlay_gif(&storage, pdf, page_index, dir)?;
+            info!("🎞️  {}", out.display());
+        }
+    }
+
+    if pages.len() > 1 {
+        let n = pages.len() as f32;
+        println!("📊 Mean token F1 {:.3}, word similarity {:.3} over {} pages", f1_sum / n, similarity_sum / n, pages.len());
+    }
+    Ok(())
+}
+
+/// One GIF for a page (0-based), a frame for each engine that has words on it
+fn write_overlay_gif(storage: &DuckDBStorage, pdf: &Path, page_index: usize, dir: &Path) -> Result<PathBuf> {
+    let mut readings = vec![
+        ("TEXT LAYER", bbox::page_words(pdf, page_index)?.words),
+        ("TESSERACT", bbox::ocr_page_words(pdf, page_index)?.words),
+        ("IMPORTED", storage.page_words(&pdf.to_string_lossy(), page_index)?),
+    ];
+    readings.retain(|(_, words)| !words.is_empty());
+    let layers: Vec<EngineLayer> = readings
+        .into_iter()
+        .zip(overlay_gif::LAYER_COLORS)
+        .map(|((name, words), color)| EngineLayer { name: name.to_string(), words, color })
+        .collect();
+    std::fs::create_dir_all(dir)?;
+    let stem = pdf.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "page".to_string());
+    let out = dir.join(format!("{}-page-{}.gif", stem, page_index + 1));
+    overlay_gif::page_overlay_gif(pdf, page_index, &layers, &out)?;
+    Ok(out)
+}
+
+/// One colored cell per grid square with the token F1 as a percentage; the grid is
+/// laid out like the page, so red cells point at the areas to correct by hand
+fn print_heatmap(grid: &[Vec<Option<f32>>]) {
+    use crossterm::style::{Color, Stylize};
+
+    let cell = |score: Option<f32>| match score {
+        None => "  · ".to_string().dark_grey(),
+        Some(f1) => {
+            let background = if f1 >= 0.9 {
+                Color::DarkGreen
+            } else if f1 >= 0.7 {
+                Color::DarkYellow
+            } else if f1 >= 0.4 {
+                Color::Rgb { r: 200, g: 90, b: 0 }
+            } else {
+                Color::DarkRed
+            };
+            format!("{:>3} ", (f1 * 100.0).round() as u32).white().on(background)
+        }
+    };
+
+    println!("      ┌{}┐", "─".repeat(grid.first().map_or(0, |r| r.len()) * 4));
+    for row in grid {
+        print!("      │");
+        for score in row {
+            print!("{}", cell(*score));
+        }
+        println!("│");
+    }
+    println!("      └{}┘", "─".repeat(grid.first().map_or(0, |r| r.len()) * 4));
+    println!("      token F1 %: {} ≥90  {} ≥70  {} ≥40  {} <40  {} no text",
+        "  ".on(Color::DarkGreen), "  ".on(Color::DarkYellow),
+        "  ".on(Color::Rgb { r: 200, g: 90, b: 0 }), "  ".on(Color::DarkRed), " ·".dark_grey());
+}
+
+fn cmd_extract_tables(pdf: &Path, out: &Path, page: Option<usize>, json: bool) -> Result<()> {
+    let total_pages = lopdf::Document::load(pdf)?.get_pages().len();
+    let pages: Vec<usize> = match page {
+        Some(p) if p >= 1 && p <= total_pages => vec![p - 1],
+        Some(p) => anyhow::bail!("Page {} out of range (1-{})", p, total_pages),
+        None => (0..total_pages).collect(),
+    };
+
+    std::fs::create_dir_all(out)?;
+    let stem = pdf.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let mut all_tables = Vec::new();
+
+    for page_index in pages {
+        let words = bbox::page_words(pdf, page_index)?;
+        for table in tables::detect_tables(page_index + 1, &words) {
+            let csv_path = out.join(format!("{}_p{}_t{}.csv", stem, table.page, table.index + 1));
+            temp_files::write(&csv_path, tables::to_csv(&table))?;
+            // Progress goes to stderr so --json output stays parseable
+            info!("📊 Page {} table {}: {} columns x {} rows -> {}",
+                table.page, table.index + 1, table.headers.len(), table.rows.len(), csv_path.display());
+            all_tables.push(table);
+        }
+    }
+
+    if json {
+        let output = serde_json::json!({
+            "path": pdf.to_string_lossy(),
+            "tables": all_tables,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if all_tables.is_empty() {
+        info!("No tables found in {}", pdf.display());
+    }
+    Ok(())
+}
+
+/// `handwriting` is the minimum confidence for handwritten words, when reading them at all
+fn cmd_hybrid(pdf: &Path, page: Option<usize>, json: bool, handwriting: Option<f32>) -> Result<()> {
+    let total_pages = lopdf::Document::load(pdf)?.get_pages().len();
+    let pages: Vec<usize> = match page {
+        Some(p) if p >= 1 && p <= total_pages => vec![p - 1],
+        Some(p) => anyhow::bail!("Page {} out of range (1-{})", p, total_pages),
+        None => (0..total_pages).collect(),
+    };
+
+    let model_dir = models::locate(handwriting::HANDWRITING_MODEL_DIR);
+    let mut model = match handwriting {
+        Some(_) if !TrOcr::available(&model_dir) => anyhow::bail!("No handwriting model in {} (`chonker8 models download trocr-handwritten`, or export microsoft/trocr-base-handwritten with optimum-cli)", model_dir.display()),
+        Some(_) => Some(TrOcr::load(&model_dir)?),
+        None => None,
+    };
+
+    let mut json_pages = Vec::new();
+    for page_index in pages {
+        let merged = match (&mut model, handwriting) {
+            (Some(model), Some(min_confidence)) => handwriting::handwriting_page(pdf, page_index, model, min_confidence)?,
+            _ => hybrid::hybrid_page(pdf, page_index)?,
+        };
+        // Progress goes to stderr so the text and --json output stay clean
+        info!("🔀 Page {}: {} native words, {} from OCR, {} handwritten",
+            page_index + 1, merged.count(Provenance::Native), merged.count(Provenance::Ocr), merged.count(Provenance::Handwriting));
+        if json {
+            let words: Vec<_> = merged.words.iter().map(|w| serde_json::json!({
+                "text": w.word.text,
+                "bbox": [w.word.x0, w.word.y0, w.word.x1, w.word.y1],
+                "source": w.source,
+                "confidence": w.confidence,
+            })).collect();
+            json_pages.push(serde_json::json!({ "page": page_index + 1, "width": merged.width, "height": merged.height, "words": words }));
+        } else {
+            println!("{}\x0c", merged.grid());
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "path": pdf.to_string_lossy(), "pages": json_pages }))?);
+    }
+    Ok(())
+}
+
+fn cmd_annotations(db: &Path, pdf: &Path, json: bool, store: bool) -> Result<()> {
+    let annotations = read_annotations(pdf)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&annotations)?);
+    } else {
+        for annotation in &annotations {
+            let color = annotation.color.as_deref().unwrap_or("-");
+            println!("p{:<4} {:<8} {}", annotation.page, color, describe_annotation(annotation));
+        }
+        info!("📝 {} annotations in {}", annotations.len(), pdf.display());
+    }
+
+    if store {
+        let mut storage = open_storage(db)?;
+        storage.replace_annotations(&pdf.to_string_lossy(), &annotations)?;
+        info!("💾 Stored {} annotations", annotations.len());
+    }
+    Ok(())
+}
+
+fn cmd_forms(db: &Path, pdf: &Path, json: bool, store: bool) -> Result<()> {
+    let fields = read_form_fields(pdf)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&fields)?);
+    } else {
+        for field in &fields {
+            println!("{} = {}", field.name, field.value.as_deref().unwrap_or(""));
+        }
+        let filled = fields.iter().filter(|f| f.value.as_deref().is_some_and(|v| v != "Off")).count();
+        info!("📋 {} form fields in {}, {} filled", fields.len(), pdf.display(), filled);
+    }
+
+    if store {
+        let mut storage = open_storage(db)?;
+        storage.replace_form_fields(&pdf.to_string_lossy(), &fields)?;
+        info!("💾 Stored {} form fields", fields.len());
+    }
+    Ok(())
+}
+
+/// Pages with word boxes already stored (an imported hOCR/ALTO result) use those;
+/// other scanned pages are OCR'd. Pages with a text layer are left alone.
+fn cmd_ocr_overlay(db: &Path, pdf: &Path, out: &Path, all_pages: bool) -> Result<()> {
+    let storage = DuckDBStorage::new(Some(db))?;
+    let path = pdf.to_string_lossy();
+    let mut document = lopdf::Document::load(pdf)?;
+    let analyzer = DocumentAnalyzer::new()?;
+    let layer = TextLayer::new(&mut document);
+    let page_count = document.get_pages().len();
+    let (mut pages_written, mut words_written) = (0, 0);
+
+    let bar = progress::bar(page_count, "pages");
+    for index in 0..page_count {
+        bar.inc(1);
+        // Re-running replaces the layer rather than stacking a second one; a page
+        // that had one is a scan whatever its text now says
+        let replaced = remove_text_layer(&mut document, index)?;
+        let stored = storage.page_words(&path, index)?;
+        let words = if !stored.is_empty() {
+            stored
+        } else {
+            let fingerprint = analyzer.analyze_loaded_page(&document, index)?;
+            if !all_pages && !replaced && !looks_scanned(&fingerprint) {
+                debug!("   - page {} already has text", index + 1);
+                continue;
+            }
+            bbox::ocr_page_words(pdf, index)?.words
+        };
+
+        let written = layer.add_words(&mut document, index, &words)?;
+        debug!("   ✓ page {}: {} words", index + 1, written);
+        if written > 0 {
+            pages_written += 1;
+            words_written += written;
+        }
+    }
+
+    bar.finish_and_clear();
+
+    temp_files::write_with(out, |mut file| Ok(document.save_to(&mut file)?))?;
+    info!("✅ {} words on {} of {} pages written to {}", words_written, pages_written, page_count, out.display());
+    Ok(())
+}
+
+/// Any layer an earlier ocr-overlay or apply-corrections run added is replaced.
+/// Word boxes come from the same places ocr-overlay takes them; pages that keep a
+/// text layer of their own are left as they are.
+fn cmd_apply_corrections(db: &Path, pdf: &Path, out: &Path, text: Option<&Path>) -> Result<()> {
+    let storage = DuckDBStorage::new(Some(db))?;
+    let path = pdf.to_string_lossy();
+    let corrected = match text {
+        Some(file) => std::fs::read_to_string(file)?,
+        None => {
+            let stored = storage.document_by_path(&path)?;
+            stored.ok_or_else(|| anyhow::anyhow!("{} is not in {} - extract it first or pass --text", path, db.display()))?.content
+        }
+    };
+    let pages: Vec<&str> = corrected.split('\x0c').collect();
+
+    let mut document = lopdf::Document::load(pdf)?;
+    let page_count = document.get_pages().len();
+    if pages.len() < page_count {
+        warn!("⚠️  Corrected text has {} pages, {} has {}", pages.len(), pdf.display(), page_count);
+    }
+    let analyzer = DocumentAnalyzer::new()?;
+    let layer = TextLayer::new(&mut document);
+    let (mut pages_written, mut words_written) = (0, 0);
+
+    let bar = progress::bar(page_count, "pages");
+    for (index, page_text) in pages.iter().enumerate().take(page_count) {
+        bar.inc(1);
+        let replaced = remove_text_layer(&mut document, index)?;
+        let mut positioned = storage.page_words(&path, index)?;
+        if positioned.is_empty() {
+            let fingerprint = analyzer.analyze_loaded_page(&document, index)?;
+            if !replaced && !looks_scanned(&fingerprint) {
+                debug!("   - page {} keeps its own text layer", index + 1);
+                continue;
+            }
+            positioned = bbox::ocr_page_words(pdf, index)?.words;
+        }
+
+        let words = align_corrections(&positioned, page_text);
+        let written = layer.add_words(&mut document, index, &words)?;
+        debug!("   ✓ page {}: {} words{}", index + 1, written, if replaced { " (layer replaced)" } else { "" });
+        if written > 0 {
+            pages_written += 1;
+            words_written += written;
+        }
+    }
+
+    bar.finish_and_clear();
+
+    temp_files::write_with(out, |mut file| Ok(document.save_to(&mut file)?))?;
+    info!("✅ {} corrected words on {} of {} pages written to {}", words_written, pages_written, page_count, out.display());
+    Ok(())
+}
+
+fn cmd_import(db: &Path, pdf: &Path, format: SidecarFormat, sidecar: &Path) -> Result<()> {
+    let mut storage = open_storage(db)?;
+    let file = ingest::scan_file(pdf)?;
+    let page_count = file.page_count.ok_or_else(|| anyhow::anyhow!("{} could not be parsed as a PDF", pdf.display()))?;
+    storage.upsert_file(&file)?;
+
+    let pages = sidecar::parse_sidecar(format, &std::fs::read_to_string(sidecar)?)?;
+    if pages.len() != page_count {
+        warn!("⚠️  {} has {} pages but {} has {}", sidecar.display(), pages.len(), pdf.display(), page_count);
+    }
+
+    // Boxes are in the OCR'd image's coordinates; map them onto each PDF page
+    let mut positioned = 0;
+    if pages.iter().any(|page| page.words.is_some()) {
+        let document = lopdf::Document::load(pdf)?;
+        for (index, page) in pages.iter().enumerate().take(page_count) {
+            let (width, height) = page_dimensions(&document, index)?;
+            if let Some(words) = page.words_on_page(width, height) {
+                storage.replace_page_words(&file.path, index, &words)?;
+                positioned += words.len();
+            }
+        }
+    }
+
+    let content = pages.iter().map(|page| page.text.as_str()).collect::<Vec<_>>().join("\x0c");
+    let metadata = serde_json::json!({
+        "sha256": file.sha256,
+        "pages": file.page_count,
+        "size_bytes": file.size_bytes,
+        "provenance": "imported",
+        "engine": format!("import:{}", format.name()),
+        "source_file": sidecar.to_string_lossy(),
+    });
+    storage.store_document(&file.path, &content, Some(&metadata.to_string()))?;
+    storage.set_file_status(&file.path, "extracted")?;
+
+    info!(
+        "✅ Imported {} pages of {} from {} ({} chars, {} positioned words)",
+        pages.len(),
+        format.name(),
+        sidecar.display(),
+        content.chars().count(),
+        positioned
+    );
+    Ok(())
+}
+
+fn cmd_daemon(db: &Path, config_path: &Path, engine: &str, health_addr: Option<(IpAddr, u16)>) -> Result<()> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    start_webhook_delivery(db);
+    let mut watcher = ConfigWatcher::new(config_path)?;
+    let shutdown = Shutdown::install()?;
+    let health = HealthState::new(db);
+    if let Some((bind, port)) = health_addr {
+        health::spawn_server(bind, port, Arc::clone(&health))?;
+    }
+    let started = Instant::now();
+    let mut active_version = String::new();
+    let mut registry = default_registry()?;
+    let mut job_id = 0u64;
+    let mut failed = 0usize;
+
+    info!("🚀 [DAEMON] Extracting scanned files from {} (config: {})", db.display(), config_path.display());
+    health::notify_ready("Waiting for scanned files");
+
+    'outer: while !shutdown.requested() {
+        health.heartbeat();
+        let pending = storage.files_with_status("scanned")?;
+        if pending.is_empty() {
+            let idle = watcher.current().config.poll_interval_secs.max(1);
+            shutdown.sleep(Duration::from_secs(idle));
+            continue;
+        }
+
+        for file in pending {
+            // Stop taking new work; anything not started stays "scanned" for next time
+            if shutdown.requested() {
+                break 'outer;
+            }
+
+            // Snapshot per job - a reload mid-document never mixes settings
+            let snapshot = watcher.current();
+            if snapshot.version != active_version {
+                registry = engine_registry(&snapshot);
+                storage.set_hooks(snapshot.config.hooks.clone());
+                active_version = snapshot.version.clone();
+            }
+
+            health.heartbeat();
+            job_id += 1;
+            info!("[DAEMON] job {} {} (config {})", job_id, file.path, snapshot.version);
+            if !run_extraction_job(&mut storage, &registry, engine, &file, &snapshot)? {
+                failed += 1;
+            }
+        }
+    }
+
+    info!("🛑 [DAEMON] Shutdown requested - draining");
+    health.set_draining();
+    storage.flush()?;
+    let remaining = storage.files_with_status("scanned")?.len();
+    info!("✅ [DAEMON] Stopped after {}: {} jobs ({} failed), {} files left queued",
+        format_duration(started.elapsed()), job_id, failed, remaining);
+    Ok(())
+}
+
+fn cmd_watch(db: &Path, dir: &Path, engine: &str, settle: Duration) -> Result<()> {
+    let mut storage = open_storage(db)?;
+    start_webhook_delivery(db);
+    let registry = default_registry()?;
+    let shutdown = Shutdown::install()?;
+    // Watch before the catch-up pass so nothing dropped in meanwhile is missed
+    let mut folder = DropFolder::new(dir, settle)?;
+    let (mut stored, mut failed) = (0usize, 0usize);
+
+    info!("👀 [WATCH] Watching {} (Ctrl-C to stop)", dir.display());
+    let known = storage.known_files()?;
+    for path in ingest::find_pdfs(dir)? {
+        if shutdown.requested() {
+            break;
+        }
+        // Same size and mtime as when it was last seen - nothing to do
+        let stat = known.get(path.to_string_lossy().as_ref());
+        if stat.is_some() && stat == ingest::file_stat(&path).ok().as_ref() {
+            continue;
+        }
+        match watch_ingest(&mut storage, &registry, engine, &path)? {
+            Some(true) => stored += 1,
+            Some(false) => failed += 1,
+            None => {}
+        }
+    }
+
+    while !shutdown.sleep(Duration::from_millis(500)) {
+        for path in folder.ready() {
+            match watch_ingest(&mut storage, &registry, engine, &path)? {
+                Some(true) => stored += 1,
+                Some(false) => failed += 1,
+                None => {}
+            }
+        }
+    }
+
+    storage.flush()?;
+    info!("🛑 [WATCH] Stopped: {} stored, {} failed", stored, failed);
+    Ok(())
+}
+
+/// Paths of the stored documents named by id or path, or of every one with `all`
+fn stored_paths(storage: &DuckDBStorage, docs: &[String], all: bool) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    if all {
+        let mut after = 0;
+        loop {
+            let batch = storage.documents_after(after, DOCUMENT_LIST_BATCH)?;
+            let Some(last) = batch.last() else { break };
+            after = last.id;
+            paths.extend(batch.into_iter().map(|doc| doc.path));
+        }
+    } else {
+        for doc in docs {
+            paths.push(stored_document(storage, doc)?.path);
+        }
+    }
+    Ok(paths)
+}
+
+/// A stored document by id or path
+fn stored_document(storage: &DuckDBStorage, doc: &str) -> Result<StoredDocument> {
+    let stored = match doc.parse::<i64>() {
+        Ok(id) => storage.document_by_id(id)?,
+        Err(_) => storage.document_by_path(doc)?,
+    };
+    stored.ok_or_else(|| anyhow::anyhow!("No stored document {} - extract it first", doc))
+}
+
+fn cmd_entities(db: &Path, action: EntitiesAction, linker: &Linker) -> Result<()> {
+    let mut storage = open_storage(db)?;
+    match action {
+        EntitiesAction::Extract { docs, all } => {
+            let paths = stored_paths(&storage, &docs, all)?;
+            let mut found = 0;
+            for path in &paths {
+                let Some(doc) = storage.document_by_path(path)? else { continue };
+                let entities = extract_entities(&doc.content);
+                storage.replace_entities(doc.id, &entities)?;
+                debug!("   {}: {} entities", path, entities.len());
+                found += entities.len();
+            }
+            info!("🔎 {} entities found in {} documents", found, paths.len());
+        }
+        EntitiesAction::List { doc, kind, format } => {
+            let document_id = doc.as_deref().map(|doc| stored_document(&storage, doc)).transpose()?.map(|doc| doc.id);
+            let entities = storage.entities(document_id, kind)?;
+            if let Some(format) = format {
+                let mut records = Records::new(&["path", "page", "type", "value", "text"]);
+                for entity in &entities {
+                    let link = linker.target(LinkTarget::document(&entity.path, Some(entity.document_id)));
+                    records.push(vec![
+                        Cell::linked(entity.path.as_str(), link),
+                        Cell::new(entity.page.map(|page| page as i64)),
+                        Cell::new(entity.kind.as_str()),
+                        Cell::new(entity.value.as_str()),
+                        Cell::new(entity.text.as_deref().unwrap_or("")),
+                    ]);
+                }
+                print_records(&format, &records)?;
+            } else {
+                for entity in &entities {
+                    let page = entity.page.map(|page| format!(" p{}", page)).unwrap_or_default();
+                    let written = entity.text.as_deref().filter(|text| *text != entity.value).map(|text| format!("  ({})", text)).unwrap_or_default();
+                    println!("{}{}  {:<8} {}{}", linker.link(LinkTarget::document(&entity.path, Some(entity.document_id)), &entity.path), page, entity.kind, entity.value, written);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_reextract(db: &Path, docs: &[String], all: bool, force: bool, engine: &str) -> Result<()> {
+    let registry = default_registry()?;
+    let checker = QualityChecker::new(&VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config.quality);
+    let mut storage = open_storage(db)?;
+
+    let paths = stored_paths(&storage, docs, all)?;
+
+    info!("🔁 Re-extracting {} documents with {}{}", paths.len(), engine, if force { ", replacing every page" } else { "" });
+    let (mut changed, mut pages_replaced, mut failed) = (0, 0, 0);
+    let bar = progress::bar(paths.len(), "documents");
+    for path in &paths {
+        bar.inc(1);
+        match reextract_document(&mut storage, &registry, &checker, engine, path, force) {
+            Ok((replaced, pages)) if replaced > 0 => {
+                changed += 1;
+                pages_replaced += replaced;
+                info!("   ✓ {}: {} of {} pages replaced", path, replaced, pages);
+            }
+            Ok(_) => debug!("   = {}: nothing scored higher, stored text kept", path),
+            Err(e) => {
+                failed += 1;
+                error!("   ✗ {}: {}", path, e);
+            }
+        }
+    }
+    bar.finish_and_clear();
+    info!("✅ {} of {} documents updated ({} pages replaced), {} failed", changed, paths.len(), pages_replaced, failed);
+    Ok(())
+}
+
+/// Extract one stored document again and store the pages chosen over the stored
+/// ones. Returns how many were replaced, and of how many.
+fn reextract_document(
+    storage: &mut DuckDBStorage,
+    registry: &ExtractorRegistry,
+    checker: &QualityChecker,
+    engine: &str,
+    path: &str,
+    force: bool,
+) -> Result<(usize, usize)> {
+    let stored = storage.document_by_path(path)?.ok_or_else(|| anyhow::anyhow!("not stored"))?;
+    let mut file = ingest::scan_file(Path::new(path))?;
+    if file.status == "failed" {
+        anyhow::bail!("unreadable PDF");
+    }
+    let started = Instant::now();
+    let results = extract_with_bar(registry, engine, &file, Vec::new(), &mut 0)?;
+    let (_, method) = combine_pages(results.clone());
+    record_run(storage, &file, &method, started.elapsed())?;
+
+    let choices = reextract::choose_pages(&stored.content, &results, checker, force);
+    let replaced = choices.iter().filter(|choice| choice.replace).count();
+    if replaced == 0 {
+        return Ok((0, results.len()));
+    }
+    let kept: Vec<bool> = choices.iter().map(|choice| !choice.replace).collect();
+    record_page_extractions(storage, path, &results, &kept)?;
+    let content = reextract::merge_pages(&stored.content, &results, &choices);
+    let mut metadata: serde_json::Value = stored.metadata.as_deref().and_then(|m| serde_json::from_str(m).ok()).unwrap_or_default();
+    if !metadata.is_object() {
+        metadata = serde_json::json!({});
+    }
+    metadata["sha256"] = serde_json::json!(file.sha256);
+    metadata["pages"] = serde_json::json!(file.page_count);
+    metadata["size_bytes"] = serde_json::json!(file.size_bytes);
+    storage.store_document(path, &content, Some(&metadata.to_string()))?;
+    file.status = "extracted".to_string();
+    storage.upsert_file(&file)?;
+    Ok((replaced, results.len()))
+}
+
+/// Extract and store one dropped PDF. Returns None when its content matches what
+/// is already stored, otherwise whether extraction succeeded.
+fn watch_ingest(storage: &mut DuckDBStorage, registry: &ExtractorRegistry, engine: &str, path: &Path) -> Result<Option<bool>> {
+    let mut file = match ingest::scan_file(path) {
+        Ok(file) => file,
+        Err(e) => {
+            error!("   ✗ {}: {}", path.display(), e);
+            return Ok(Some(false));
+        }
+    };
+    if file.status == "failed" {
+        storage.upsert_file(&file)?;
+        error!("   ✗ {}: unreadable PDF", file.path);
+        return Ok(Some(false));
+    }
+
+    let stored_sha = storage
+        .document_by_path(&file.path)?
+        .and_then(|doc| doc.metadata)
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok())
+        .and_then(|m| m.get("sha256").and_then(|s| s.as_str()).map(str::to_string));
+    if stored_sha.as_deref() == Some(file.sha256.as_str()) {
+        // Touched but not changed - keep the new mtime so the next start skips it
+        file.status = "extracted".to_string();
+        storage.upsert_file(&file)?;
+        return Ok(None);
+    }
+
+    let started = Instant::now();
+    let pages = file.page_count.unwrap_or(0);
+    let result = match registry.extract_pages(engine, path, pages, |_| {}) {
+        Ok(results) => {
+            let elapsed = started.elapsed();
+            record_page_extractions(storage, &file.path, &results, &[])?;
+            let (content, method) = combine_pages(results);
+            let metadata = serde_json::json!({
+                "sha256": file.sha256,
+                "pages": file.page_count,
+                "size_bytes": file.size_bytes,
+                "engine": method.name(),
+            });
+            storage.store_document(&file.path, &content, Some(&metadata.to_string()))?;
+            record_run(storage, &file, &method, elapsed)?;
+            file.status = "extracted".to_string();
+            info!("   ✓ {} - {} pages, {} chars, {} in {}",
+                file.path, pages, content.chars().count(), method.name(), format_duration(elapsed));
+            true
+        }
+        Err(e) => {
+            file.status = "failed".to_string();
+            error!("   ✗ {}: {}", file.path, e);
+            false
+        }
+    };
+    storage.upsert_file(&file)?;
+    Ok(Some(result))
+}
+
+fn cmd_serve(db: &Path, config: ServerConfig) -> Result<()> {
+    let storage = open_storage(db)?;
+    let worker_db = db.to_path_buf();
+    spawn_index_worker(move || open_storage(&worker_db));
+    start_webhook_delivery(db);
+    let shutdown = Shutdown::install()?;
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    runtime.block_on(server::serve(config, storage, shutdown))?;
+    info!("✅ [SERVER] Stopped");
+    Ok(())
+}
+
+/// POST queued webhook events in the background while a long-running command works
+fn start_webhook_delivery(db: &Path) {
+    let db = db.to_path_buf();
+    spawn_webhook_worker(move || DuckDBStorage::new(Some(&db)));
+}
+
+/// Registry ordered by the config's engine priorities, warning about unknown engines
+fn engine_registry(snapshot: &VersionedConfig) -> ExtractorRegistry {
+    let (mut registry, unknown) = ExtractorRegistry::with_priority(&snapshot.config.engines);
+    for engine in unknown {
+        warn!("⚠️  [CONFIG] Unknown engine '{}' in config {} - available: {}",
+            engine, snapshot.version, registry.names().join(", "));
+    }
+    add_cloud_engine(&mut registry, &snapshot.config);
+    registry
+}
+
+/// The default registry, plus the cloud engine if extraction.toml configures one
+fn default_registry() -> Result<ExtractorRegistry> {
+    Ok(extractor_registry(&VersionedConfig::load(Path::new(DEFAULT_CONFIG_PATH))?.config))
+}
+
+fn extractor_registry(config: &ExtractionConfig) -> ExtractorRegistry {
+    let mut registry = ExtractorRegistry::default();
+    add_cloud_engine(&mut registry, config);
+    registry
+}
+
+/// Cloud OCR is never chosen in auto mode, so it always goes last
+fn add_cloud_engine(registry: &mut ExtractorRegistry, config: &ExtractionConfig) {
+    if let Some(cloud) = &config.cloud_ocr {
+        registry.register(Box::new(CloudOcrExtractor::new(cloud.clone())));
+    }
+}
+
+/// Extract one registered file under a config snapshot and store the result.
+/// Returns false if extraction failed.
+fn run_extraction_job(
+    storage: &mut DuckDBStorage,
+    registry: &ExtractorRegistry,
+    engine: &str,
+    file: &FileRecord,
+    snapshot: &VersionedConfig,
+) -> Result<bool> {
+    let started = Instant::now();
+    match registry.extract_pages(engine, Path::new(&file.path), file.page_count.unwrap_or(0), |_| {}) {
+        Ok(results) => {
+            record_page_extractions(storage, &file.path, &results, &[])?;
+            let (content, method) = combine_pages(results);
+            record_run(storage, file, &method, started.elapsed())?;
+
+            let pages: Vec<&str> = content.split('\x0c').collect();
+            let checker = QualityChecker::new(&snapshot.config.quality);
+            let mean_quality = pages.iter().map(|p| checker.score(p)).sum::<f32>() / pages.len().max(1) as f32;
+            let needs_review = mean_quality < snapshot.config.min_quality;
+
+            let metadata = serde_json::json!({
+                "sha256": file.sha256,
+                "pages": file.page_count,
+                "size_bytes": file.size_bytes,
+                "engine": method.name(),
+                "config_version": snapshot.version,
+                "locales": snapshot.config.locales,
+                "mean_quality": mean_quality,
+                "needs_review": needs_review,
+            });
+            storage.store_document(&file.path, &content, Some(&metadata.to_string()))?;
+            storage.set_file_status(&file.path, "extracted")?;
+
+            if needs_review {
+                warn!("   ⚠️  {} quality {:.2} below {:.2}", file.path, mean_quality, snapshot.config.min_quality);
+            } else {
+                info!("   ✓ {} ({})", file.path, format_duration(started.elapsed()));
+            }
+        }
+        Err(e) => {
+            storage.set_file_status(&file.path, "failed")?;
+            error!("   ✗ {}: {}", file.path, e);
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Record how long a document took so future estimates improve
+fn record_run(storage: &mut DuckDBStorage, file: &FileRecord, method: &ExtractionMethod, elapsed: Duration) -> Result<()> {
+    storage.record_run(&RunRecord {
+        path: file.path.clone(),
+        engine: method.name().to_string(),
+        page_count: file.page_count.unwrap_or(0),
+        image_coverage: file.image_coverage,
+        duration_ms: elapsed.as_millis() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["chonker8", "meta", "x.pdf", "--set", "title=foo", "--set", "doc_date=2024-03-01"]).unwrap();
+        let Commands::Meta { set, .. } = cli.command else { panic!("expected meta") };
+        assert_eq!(set, [("title".to_string(), "foo".to_string()), ("doc_date".to_string(), "2024-03-01".to_string())]);
+        assert!(Cli::try_parse_from(["chonker8", "meta", "x.pdf", "--set", "title"]).is_err());
+
+        let cli = Cli::try_parse_from(["chonker8", "sql", "select ?1, :n", "--param", "a b=c", "--param", ":n=1"]).unwrap();
+        let Commands::Sql { params, .. } = cli.command else { panic!("expected sql") };
+        assert_eq!(params, [(None, "a b=c".to_string()), (Some("n".to_string()), "1".to_string())]);
+
+        // Archives go through export and import without the document options
+        let cli = Cli::try_parse_from(["chonker8", "import", "corpus.tar.zst"]).unwrap();
+        let Commands::Import { pdf, text: None, hocr: None, alto: None } = cli.command else { panic!("expected import") };
+        assert!(is_archive(&pdf));
+        assert!(Cli::try_parse_from(["chonker8", "export", "corpus.tar.zst"]).is_ok());
+    }
+}
@@ -2,6 +2,10 @@
 mod ui_config;
 mod ui_renderer;
 mod pdf_extraction;
+mod content_stream;
+mod disk_cache;
+mod page_images;
+mod temp_files;
 mod config;
 mod hot_reload_manager;
 mod build_system;
@@ -11,7 +15,7 @@ use anyhow::Result;
 use clap::Parser;
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
-    event::{self, Event, KeyCode, KeyEvent, MouseEvent, MouseEventKind, EnableMouseCapture, DisableMouseCapture},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind, EnableMouseCapture, DisableMouseCapture},
     execute,
     terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -23,7 +27,7 @@ use std::{
     time::Duration,
 };
 use ui_config::UIConfig;
-use ui_renderer::{UIRenderer, Screen};
+use ui_renderer::{ConflictChoice, Pane, UIRenderer, Screen, SPLIT_STEP};
 use hot_reload_manager::HotReloadManager;
 use std::process::{Command, Stdio};
 // use chonker8::integrated_file_picker::IntegratedFilePicker; // Unused import
@@ -39,6 +43,14 @@ struct Args {
     /// Test Kitty graphics protocol detection
     #[arg(long)]
     test_kitty: bool,
+    
+    /// Size the text grid to each page's shape (MediaBox and /Rotate) instead of a fixed 200x100
+    #[arg(long)]
+    auto_grid: bool,
+    
+    /// Grid columns per inch of page width with --auto-grid
+    #[arg(long, default_value_t = chonker8::coords::DEFAULT_COLS_PER_INCH, requires = "auto_grid")]
+    cols_per_inch: f32,
 }
 
 struct App {
@@ -209,6 +221,20 @@ impl App {
                 }
             }
             
+            // Pick up a page that finished loading in the background
+            if self.renderer.poll_page_load() {
+                self.needs_redraw = true;
+            }
+            if self.renderer.poll_thumbnails() {
+                self.needs_redraw = true;
+            }
+            if self.renderer.poll_update_check() {
+                self.needs_redraw = true;
+            }
+            if self.renderer.poll_file_index() {
+                self.needs_redraw = true;
+            }
+            
             // Render if needed
             if self.needs_redraw {
                 // Pass the file picker reference to the renderer for file picker screen
@@ -302,31 +328,83 @@ impl App {
             }
         }
         
+        // Diff screen - scroll through the changes, or settle an edit conflict
+        if *self.renderer.current_screen() == Screen::Diff {
+            if self.renderer.has_conflict() {
+                let choice = match key.code {
+                    KeyCode::Char('o') => Some(ConflictChoice::Mine),
+                    KeyCode::Char('t') => Some(ConflictChoice::Theirs),
+                    KeyCode::Char('m') => Some(ConflictChoice::Merged),
+                    _ => None,
+                };
+                if let Some(choice) = choice {
+                    self.renderer.resolve_conflict(choice);
+                    self.needs_redraw = true;
+                    return Ok(());
+                }
+            }
+            let lines = match key.code {
+                KeyCode::Up => -1,
+                KeyCode::Down => 1,
+                KeyCode::PageUp => -10,
+                KeyCode::PageDown => 10,
+                _ => 0,
+            };
+            if lines != 0 {
+                self.renderer.scroll_diff(lines);
+                self.needs_redraw = true;
+                return Ok(());
+            }
+        }
+        
+        // Page overview - pick a page from the thumbnails
+        if *self.renderer.current_screen() == Screen::Overview && self.renderer.handle_overview_input(key) {
+            self.needs_redraw = true;
+            return Ok(());
+        }
+        
         // Check if we're on the PDF viewer screen and handle scrolling
         let screen = self.renderer.current_screen();
         if *screen == Screen::PdfViewer {
             match key.code {
-                KeyCode::Up => {
-                    self.renderer.scroll_up();
+                KeyCode::PageUp => {
+                    self.renderer.prev_page();
                     self.needs_redraw = true;
                     return Ok(());
                 }
-                KeyCode::Down => {
-                    self.renderer.scroll_down();
+                KeyCode::PageDown => {
+                    self.renderer.next_page();
                     self.needs_redraw = true;
                     return Ok(());
                 }
-                KeyCode::PageUp => {
-                    self.renderer.prev_page();
+                KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.renderer.open_overview();
                     self.needs_redraw = true;
                     return Ok(());
                 }
-                KeyCode::PageDown => {
-                    self.renderer.next_page();
+                KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.renderer.toggle_sync_scroll();
                     self.needs_redraw = true;
                     return Ok(());
                 }
-                _ => {}
+                // Ctrl+Left/Right move the split, Ctrl+Up/Down give one pane the whole width
+                KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    match key.code {
+                        KeyCode::Left => self.renderer.adjust_split(-SPLIT_STEP),
+                        KeyCode::Right => self.renderer.adjust_split(SPLIT_STEP),
+                        KeyCode::Up => self.renderer.toggle_maximized(Pane::Pdf),
+                        _ => self.renderer.toggle_maximized(Pane::Text),
+                    }
+                    self.needs_redraw = true;
+                    return Ok(());
+                }
+                _ => {
+                    // Cursor movement, typing and Ctrl+Z/Ctrl+Y go to the text editor
+                    if self.renderer.handle_editor_input(key) {
+                        self.needs_redraw = true;
+                        return Ok(());
+                    }
+                }
             }
         }
         
@@ -408,9 +486,15 @@ impl App {
             }
         }
         
-        // Handle mouse wheel scrolling on PDF viewer screen
+        // Clicks and drags on the text panel place the cursor and select
+        if self.renderer.handle_editor_mouse(mouse) {
+            self.needs_redraw = true;
+            return Ok(());
+        }
+        
+        // Handle mouse wheel scrolling on PDF viewer and diff screens
         let screen = self.renderer.current_screen();
-        if *screen == Screen::PdfViewer {
+        if *screen == Screen::PdfViewer || *screen == Screen::Diff {
             match mouse.kind {
                 MouseEventKind::ScrollUp => {
                     self.renderer.scroll_up();
@@ -425,13 +509,13 @@ impl App {
                     // Ignore mouse movement
                 }
                 MouseEventKind::Down(_) => {
-                    // Ignore mouse button presses
+                    // Ignore presses outside the text panel
                 }
                 MouseEventKind::Up(_) => {
                     // Ignore mouse button releases
                 }
                 MouseEventKind::Drag(_) => {
-                    // Ignore mouse drag
+                    // Ignore drags outside the text panel
                 }
                 _ => {
                     // Ignore any other mouse events
@@ -524,6 +608,8 @@ fn main() -> Result<()> {
     
     // Parse command line arguments using clap
     let args = Args::parse();
+    // Logging goes to the Debug screen's file while the TUI has the terminal
+    chonker8::debug_log::init_tui();
     
     // Handle test mode
     if args.test_kitty {
@@ -535,12 +621,15 @@ fn main() -> Result<()> {
             capture_warning!("❌ Kitty graphics protocol not detected");
             capture_warning!("  Run this in a Kitty terminal for graphics support");
         }
+        let protocol = chonker8::graphics::detect();
+        capture_info!("PDF panel will use {} graphics (override with {})", protocol.name(), chonker8::graphics::GRAPHICS_ENV);
         return Ok(());
     }
     
     
     // Create app
     let mut app = App::new()?;
+    app.renderer.set_auto_grid(args.auto_grid.then_some(args.cols_per_inch));
     
     // Load PDF if provided, or use default test PDF
     if let Some(pdf_path) = args.pdf_file {
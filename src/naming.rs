@@ -0,0 +1,172 @@
+// Descriptive file names from document content - 2024-03-14_ACME_Invoice_12345.pdf
+//
+// Everything is read from the first page or two, where letterheads, dates and
+// reference numbers sit: the document date (one next to a "date" label wins over
+// the first date seen), the issuing party (a line naming a company), the class
+// from the scheduler's keyword classifier, and the invoice/order/account number.
+// A name needs a date or a party plus one more part, else there is no suggestion.
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::ops::Range;
+
+use crate::scheduler::classify_text;
+
+/// Characters of text looked at - the first page or so
+const HEAD_CHARS: usize = 4000;
+/// Longest party name or reference kept, in characters
+const MAX_PARTY_CHARS: usize = 24;
+
+static ISO_DATE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(\d{4})[-/.](\d{1,2})[-/.](\d{1,2})\b").unwrap());
+static NUMERIC_DATE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(\d{1,2})[/.](\d{1,2})[/.](\d{4})\b").unwrap());
+static WORD_DATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:(\d{1,2})(?:st|nd|rd|th)?\s+([a-z]{3,9})\.?,?\s+(\d{4})|([a-z]{3,9})\.?\s+(\d{1,2})(?:st|nd|rd|th)?,?\s+(\d{4}))\b").unwrap()
+});
+static DATE_LABEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b(?:invoice |statement |issue |document )?dated?\b[:\s]*$").unwrap());
+pub(crate) static REFERENCE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:invoice|inv|receipt|order|statement|account|contract|ref(?:erence)?)\s*(?:no\.?|number|num|#)\s*[:#]?\s*([A-Z0-9][A-Z0-9-]{2,19})\b").unwrap()
+});
+static COMPANY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b((?:[A-Z][\w&'-]*\s+){0,3}[A-Z][\w&'-]*),?\s+(?:Inc|LLC|Ltd|Limited|GmbH|AG|Corp|Corporation|Co|Company|plc|S\.?A|B\.?V)\b\.?").unwrap()
+});
+
+/// The parts a suggested name is built from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NameParts {
+    pub date: Option<NaiveDate>,
+    pub party: Option<String>,
+    pub kind: Option<String>,
+    pub reference: Option<String>,
+}
+
+impl NameParts {
+    pub fn detect(text: &str) -> Self {
+        let head: String = text.chars().take(HEAD_CHARS).collect();
+        Self {
+            date: document_date(&head),
+            party: party(&head),
+            kind: classify_text(&head).map(capitalize),
+            reference: REFERENCE.captures(&head).map(|c| c[1].to_uppercase()),
+        }
+    }
+
+    /// `2024-03-14_ACME_Invoice_12345.pdf`, or None when too little was found
+    pub fn file_name(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            self.date.map(|d| d.format("%Y-%m-%d").to_string()),
+            self.party.as_deref().map(sanitize),
+            self.kind.clone(),
+            self.reference.as_deref().map(sanitize),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect();
+        let anchored = self.date.is_some() || self.party.is_some();
+        (anchored && parts.len() >= 2).then(|| format!("{}.pdf", parts.join("_")))
+    }
+}
+
+/// Suggested file name for a document's text
+pub fn suggest_name(text: &str) -> Option<String> {
+    NameParts::detect(text).file_name()
+}
+
+/// Names scanners, phones and mail gateways make up: scan0001, IMG_2231, Document (3), 20240314_101112
+pub fn looks_generated(stem: &str) -> bool {
+    static GENERATED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?i)^(?:scan|scanned|img|image|doc|document|file|untitled|attachment|fax|pdf|page|print|copy|new)?[\s_-]*(?:\d[\d\s_().-]*)?(?:\(\d+\))?$").unwrap()
+    });
+    let stem = stem.trim();
+    stem.is_empty() || GENERATED.is_match(stem)
+}
+
+/// Every plausible date in `text`, with where it was written, in text order
+pub(crate) fn find_dates(text: &str) -> Vec<(Range<usize>, NaiveDate)> {
+    let mut found: Vec<(Range<usize>, NaiveDate)> = Vec::new();
+    for captures in ISO_DATE.captures_iter(text) {
+        let date = ymd(&captures[1], &captures[2], &captures[3]);
+        found.extend(date.map(|d| (captures.get(0).map_or(0..0, |m| m.range()), d)));
+    }
+    for captures in NUMERIC_DATE.captures_iter(text) {
+        // Month first unless that can't be a month (14/03/2024)
+        let (a, b) = (&captures[1], &captures[2]);
+        let date = ymd(&captures[3], a, b).or_else(|| ymd(&captures[3], b, a));
+        found.extend(date.map(|d| (captures.get(0).map_or(0..0, |m| m.range()), d)));
+    }
+    for captures in WORD_DATE.captures_iter(text) {
+        let (day, month, year) = match captures.get(1) {
+            Some(day) => (day.as_str(), &captures[2], &captures[3]),
+            None => (&captures[5], &captures[4], &captures[6]),
+        };
+        let date = month_number(month).and_then(|m| ymd(year, &m.to_string(), day));
+        found.extend(date.map(|d| (captures.get(0).map_or(0..0, |m| m.range()), d)));
+    }
+    found.sort_by_key(|(span, _)| span.start);
+    found
+}
+
+/// A date next to a "date" label, else the first plausible date
+fn document_date(text: &str) -> Option<NaiveDate> {
+    let found = find_dates(text);
+    let labelled = found.iter().find(|(span, _)| {
+        let line_start = text[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        DATE_LABEL.is_match(&text[line_start..span.start])
+    });
+    labelled.or(found.first()).map(|(_, date)| *date)
+}
+
+fn ymd(year: &str, month: &str, day: &str) -> Option<NaiveDate> {
+    let year: i32 = year.parse().ok()?;
+    if !(1950..=2100).contains(&year) {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year, month.parse().ok()?, day.parse().ok()?)
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let name = name.to_lowercase();
+    MONTHS.iter().position(|m| name.starts_with(m)).map(|i| i as u32 + 1)
+}
+
+/// The first company named, without its legal form
+fn party(text: &str) -> Option<String> {
+    COMPANY.captures(text).map(|c| c[1].split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// Letters and digits only, words joined by dashes
+pub(crate) fn sanitize(part: &str) -> String {
+    let words: Vec<&str> = part.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+    let joined = words.join("-");
+    joined.chars().take(MAX_PARTY_CHARS).collect::<String>().trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invoice_name_and_generated_stems() {
+        let text = "ACME Widgets, Inc.\n12 Main St\n\nINVOICE\nInvoice No: 12345\nShipped 01/02/2024\nInvoice date: March 14, 2024\nBill to: Globex\nAmount due: $100\n";
+        let parts = NameParts::detect(text);
+        assert_eq!(parts.date, NaiveDate::from_ymd_opt(2024, 3, 14));
+        assert_eq!(parts.party.as_deref(), Some("ACME Widgets"));
+        assert_eq!(parts.file_name().as_deref(), Some("2024-03-14_ACME-Widgets_Invoice_12345.pdf"));
+
+        // Day first when the first number can't be a month; no anchor, no name
+        assert_eq!(document_date("Received 14.03.2024"), NaiveDate::from_ymd_opt(2024, 3, 14));
+        assert_eq!(suggest_name("invoice amount due 12 apples"), None);
+
+        assert!(looks_generated("scan0001"));
+        assert!(looks_generated("IMG_2231"));
+        assert!(looks_generated("Document (3)"));
+        assert!(looks_generated("20240314_101112"));
+        assert!(!looks_generated("lease-renewal"));
+    }
+}
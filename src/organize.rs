@@ -0,0 +1,173 @@
+// Sorting a folder of PDFs into a tree by content - `chonker8 organize <dir> --by type/year`
+//
+// A file's folder is built from the same detection as suggested file names
+// (naming::NameParts): document class, date and issuing party, in the order the
+// --by spec lists them. What can't be detected goes into a catch-all folder at
+// that level rather than being left behind. Every move or symlink is recorded in
+// a manifest, written after each one so an interrupted run can still be undone.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::naming::{sanitize, NameParts};
+use crate::temp_files;
+
+pub const MANIFEST_VERSION: u32 = 1;
+/// Folder for files whose class, date or party wasn't found
+const UNSORTED: &str = "Unsorted";
+const UNDATED: &str = "Undated";
+
+/// One level of the folder tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FolderKey {
+    Type,
+    Year,
+    Month,
+    Party,
+}
+
+/// `type/year`, `party/year/month`, ... - levels from the top down
+pub fn parse_by(spec: &str) -> Result<Vec<FolderKey>> {
+    let keys = spec
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.trim().to_lowercase().as_str() {
+            "type" | "class" => Ok(FolderKey::Type),
+            "year" => Ok(FolderKey::Year),
+            "month" => Ok(FolderKey::Month),
+            "party" | "sender" => Ok(FolderKey::Party),
+            other => bail!("Unknown folder level '{}' (use type, year, month or party)", other),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if keys.is_empty() {
+        bail!("--by needs at least one of type, year, month or party");
+    }
+    Ok(keys)
+}
+
+/// Folder (relative to the destination) for a document with these parts
+pub fn folder_for(parts: &NameParts, by: &[FolderKey]) -> PathBuf {
+    by.iter()
+        .map(|key| match key {
+            FolderKey::Type => parts.kind.clone().unwrap_or_else(|| UNSORTED.to_string()),
+            FolderKey::Year => parts.date.map_or_else(|| UNDATED.to_string(), |d| d.format("%Y").to_string()),
+            FolderKey::Month => parts.date.map_or_else(|| UNDATED.to_string(), |d| d.format("%Y-%m").to_string()),
+            FolderKey::Party => parts.party.as_deref().map(sanitize).filter(|p| !p.is_empty()).unwrap_or_else(|| UNSORTED.to_string()),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Move {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// What an organize run did, for `organize --undo`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// RFC 3339, UTC
+    pub created_at: String,
+    /// The folder the tree was built in
+    pub root: PathBuf,
+    /// Links were made and the originals left in place
+    pub symlinks: bool,
+    pub moves: Vec<Move>,
+}
+
+impl Manifest {
+    pub fn new(root: &Path, symlinks: bool) -> Self {
+        Self { version: MANIFEST_VERSION, created_at: chrono::Utc::now().to_rfc3339(), root: root.to_path_buf(), symlinks, moves: Vec::new() }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let manifest: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if manifest.version != MANIFEST_VERSION {
+            bail!("{} is a version {} manifest, this build reads version {}", path.display(), manifest.version, MANIFEST_VERSION);
+        }
+        Ok(manifest)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        temp_files::write(path, serde_json::to_string_pretty(self)? + "\n")
+    }
+}
+
+/// Move `from` to `to`, or link `to` to it, creating folders as needed
+pub fn place(from: &Path, to: &Path, symlink: bool) -> Result<()> {
+    if to.exists() {
+        bail!("{} already exists", to.display());
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if symlink {
+        link(&from.canonicalize()?, to)
+    } else {
+        std::fs::rename(from, to)?;
+        Ok(())
+    }
+}
+
+/// Reverse one `place`, then drop the folders under `root` it leaves empty
+pub fn unplace(step: &Move, symlink: bool, root: &Path) -> Result<()> {
+    if symlink {
+        if !step.to.is_symlink() {
+            bail!("{} is no longer a link", step.to.display());
+        }
+        std::fs::remove_file(&step.to)?;
+    } else {
+        if step.from.exists() {
+            bail!("{} exists again", step.from.display());
+        }
+        if let Some(parent) = step.from.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&step.to, &step.from)?;
+    }
+    // remove_dir only succeeds on empty folders, which is the point
+    for dir in step.to.ancestors().skip(1).take_while(|dir| *dir != root && dir.starts_with(root)) {
+        if std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn link(original: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(original, link)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn link(_original: &Path, _link: &Path) -> Result<()> {
+    bail!("--symlink is only supported on Unix")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_folders_and_undo() {
+        let by = parse_by("type/year").unwrap();
+        let parts = NameParts { date: NaiveDate::from_ymd_opt(2024, 3, 14), kind: Some("Invoice".to_string()), ..Default::default() };
+        assert_eq!(folder_for(&parts, &by), PathBuf::from("Invoice/2024"));
+        assert_eq!(folder_for(&NameParts::default(), &parse_by("party/month").unwrap()), PathBuf::from("Unsorted/Undated"));
+        assert!(parse_by("type/colour").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("scan0001.pdf");
+        std::fs::write(&from, b"%PDF-1.4").unwrap();
+        let step = Move { from: from.clone(), to: dir.path().join("Invoice/2024/scan0001.pdf") };
+        place(&step.from, &step.to, false).unwrap();
+        assert!(!from.exists() && step.to.exists());
+        unplace(&step, false, dir.path()).unwrap();
+        assert!(from.exists());
+        // The folders made for it are gone again
+        assert!(!dir.path().join("Invoice").exists());
+    }
+}
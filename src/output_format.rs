@@ -0,0 +1,606 @@
+// Output writers - every format the CLI prints or exports in, behind one trait
+//
+// Two shapes of output go through a formatter: result lists (search hits, listed
+// documents) as columns and rows, and document content as pages. A format
+// implements both, or refuses the one that makes no sense for it (hOCR has no
+// notion of a result list). Commands look formats up by name in the registry,
+// so a format registered once works for list, search, export and batch alike.
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Value};
+use std::io::Write;
+
+use crate::pdf_extraction::bbox::BoxWord;
+
+/// One cell of a result list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub value: Value,
+    /// Where the value points, for formats that can link
+    pub link: Option<String>,
+}
+
+impl Cell {
+    pub fn new(value: impl Into<Value>) -> Self {
+        Self { value: value.into(), link: None }
+    }
+
+    pub fn linked(value: impl Into<Value>, link: Option<String>) -> Self {
+        Self { value: value.into(), link }
+    }
+
+    /// Display text; empty for null
+    pub fn text(&self) -> String {
+        match &self.value {
+            Value::Null => String::new(),
+            Value::String(text) => text.clone(),
+            Value::Number(n) if n.is_f64() => format!("{:.2}", n.as_f64().unwrap_or_default()),
+            other => other.to_string(),
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        self.value.is_number()
+    }
+}
+
+/// A result list: named columns, rows in the same order
+#[derive(Debug, Clone, Default)]
+pub struct Records {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Records {
+    pub fn new(columns: &[&str]) -> Self {
+        Self { columns: columns.iter().map(|c| c.to_string()).collect(), rows: Vec::new() }
+    }
+
+    pub fn push(&mut self, row: Vec<Cell>) {
+        self.rows.push(row);
+    }
+
+    fn objects(&self) -> impl Iterator<Item = Value> + '_ {
+        self.rows.iter().map(|row| {
+            Value::Object(self.columns.iter().cloned().zip(row.iter().map(|cell| cell.value.clone())).collect())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PageOutput {
+    /// 1-based
+    pub number: usize,
+    pub text: String,
+    /// Word boxes in PDF points from the top-left, when known
+    pub words: Vec<BoxWord>,
+    /// Page width and height in points, when known
+    pub size: Option<(f32, f32)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DocumentOutput {
+    pub path: String,
+    pub pages: Vec<PageOutput>,
+}
+
+impl DocumentOutput {
+    /// Pages from stored content, split at form feeds
+    pub fn from_content(path: &str, content: &str) -> Self {
+        let pages = content
+            .split('\x0c')
+            .enumerate()
+            .map(|(i, text)| PageOutput { number: i + 1, text: text.to_string(), ..PageOutput::default() })
+            .collect();
+        Self { path: path.to_string(), pages }
+    }
+}
+
+pub trait OutputFormatter: Send + Sync {
+    /// Name used by --format
+    fn name(&self) -> &'static str;
+
+    /// Other names --format accepts
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// File extension for exported documents, without the dot
+    fn extension(&self) -> &'static str;
+
+    fn write_records(&self, out: &mut dyn Write, records: &Records) -> Result<()>;
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()>;
+}
+
+/// Formats by name; the built-in ones unless more are registered
+pub struct FormatterRegistry {
+    formatters: Vec<Box<dyn OutputFormatter>>,
+}
+
+impl Default for FormatterRegistry {
+    fn default() -> Self {
+        Self {
+            formatters: vec![
+                Box::new(TextFormatter),
+                Box::new(GridFormatter),
+                Box::new(JsonFormatter),
+                Box::new(JsonLinesFormatter),
+                Box::new(MarkdownFormatter),
+                Box::new(HtmlFormatter),
+                Box::new(HocrFormatter),
+                Box::new(CsvFormatter),
+                Box::new(TsvFormatter),
+            ],
+        }
+    }
+}
+
+impl FormatterRegistry {
+    pub fn register(&mut self, formatter: Box<dyn OutputFormatter>) {
+        self.formatters.push(formatter);
+    }
+
+    pub fn get(&self, name: &str) -> Result<&dyn OutputFormatter> {
+        let name = name.trim().to_lowercase();
+        self.formatters
+            .iter()
+            .find(|f| f.name() == name || f.aliases().contains(&name.as_str()))
+            .map(|f| f.as_ref())
+            .ok_or_else(|| anyhow!("Unknown format '{}' (available: {})", name, self.names().join(", ")))
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.formatters.iter().map(|f| f.name()).collect()
+    }
+}
+
+/// `text` as an OSC 8 terminal link when there is one
+fn osc8(text: &str, link: Option<&str>) -> String {
+    match link {
+        Some(url) if !text.is_empty() => format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text),
+        _ => text.to_string(),
+    }
+}
+
+/// Column widths by display text, headers included
+fn widths(records: &Records) -> Vec<usize> {
+    records
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| records.rows.iter().map(|row| row[i].text().chars().count()).chain([name.len()]).max().unwrap_or(0))
+        .collect()
+}
+
+/// A cell padded to `width`, numbers to the right; padding stays outside any link
+fn padded(cell: &Cell, width: usize) -> String {
+    let text = cell.text();
+    let padding = " ".repeat(width.saturating_sub(text.chars().count()));
+    let text = osc8(&text, cell.link.as_deref());
+    if cell.is_number() {
+        format!("{}{}", padding, text)
+    } else {
+        format!("{}{}", text, padding)
+    }
+}
+
+/// Aligned columns for reading in a terminal; documents as plain text, pages
+/// separated by form feeds like pdftotext
+pub struct TextFormatter;
+
+impl OutputFormatter for TextFormatter {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["table"]
+    }
+
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn write_records(&self, out: &mut dyn Write, records: &Records) -> Result<()> {
+        let widths = widths(records);
+        let header: Vec<String> = records.columns.iter().zip(&widths).map(|(name, w)| format!("{:<w$}", name.to_uppercase(), w = *w)).collect();
+        writeln!(out, "{}", header.join("  ").trim_end())?;
+        for row in &records.rows {
+            let line: Vec<String> = row.iter().zip(&widths).map(|(cell, w)| padded(cell, *w)).collect();
+            writeln!(out, "{}", line.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        let pages: Vec<&str> = document.pages.iter().map(|page| page.text.as_str()).collect();
+        writeln!(out, "{}", pages.join("\x0c"))?;
+        Ok(())
+    }
+}
+
+/// Box-drawn tables; documents as one framed block per page
+pub struct GridFormatter;
+
+impl OutputFormatter for GridFormatter {
+    fn name(&self) -> &'static str {
+        "grid"
+    }
+
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn write_records(&self, out: &mut dyn Write, records: &Records) -> Result<()> {
+        let widths = widths(records);
+        let rule = |left: &str, middle: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+            format!("{}{}{}", left, segments.join(middle), right)
+        };
+        writeln!(out, "{}", rule("┌", "┬", "┐"))?;
+        let header: Vec<String> = records.columns.iter().zip(&widths).map(|(name, w)| format!(" {:<w$} ", name.to_uppercase(), w = *w)).collect();
+        writeln!(out, "│{}│", header.join("│"))?;
+        writeln!(out, "{}", rule("├", "┼", "┤"))?;
+        for row in &records.rows {
+            let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, w)| format!(" {} ", padded(cell, *w))).collect();
+            writeln!(out, "│{}│", cells.join("│"))?;
+        }
+        writeln!(out, "{}", rule("└", "┴", "┘"))?;
+        Ok(())
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        for page in &document.pages {
+            let title = format!(" {} - page {} ", document.path, page.number);
+            let width = page.text.lines().map(|line| line.chars().count()).chain([title.chars().count()]).max().unwrap_or(0);
+            writeln!(out, "┌{}{}┐", title, "─".repeat(width - title.chars().count() + 2))?;
+            for line in page.text.lines() {
+                writeln!(out, "│ {:<w$} │", line, w = width)?;
+            }
+            writeln!(out, "└{}┘", "─".repeat(width + 2))?;
+        }
+        Ok(())
+    }
+}
+
+/// One pretty-printed JSON array or document object
+pub struct JsonFormatter;
+
+fn page_json(document: &DocumentOutput, page: &PageOutput) -> Value {
+    let mut object = json!({ "path": document.path, "page": page.number, "text": page.text });
+    if !page.words.is_empty() {
+        object["words"] = json!(page
+            .words
+            .iter()
+            .map(|w| json!({ "text": w.text, "x0": w.x0, "y0": w.y0, "x1": w.x1, "y1": w.y1 }))
+            .collect::<Vec<_>>());
+    }
+    object
+}
+
+impl OutputFormatter for JsonFormatter {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn write_records(&self, out: &mut dyn Write, records: &Records) -> Result<()> {
+        writeln!(out, "{}", serde_json::to_string_pretty(&records.objects().collect::<Vec<_>>())?)?;
+        Ok(())
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        let pages: Vec<Value> = document.pages.iter().map(|page| page_json(document, page)).collect();
+        writeln!(out, "{}", serde_json::to_string_pretty(&json!({ "path": document.path, "pages": pages }))?)?;
+        Ok(())
+    }
+}
+
+/// One JSON object per line - a row, or a page - for streaming into other tools
+pub struct JsonLinesFormatter;
+
+impl OutputFormatter for JsonLinesFormatter {
+    fn name(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn extension(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn write_records(&self, out: &mut dyn Write, records: &Records) -> Result<()> {
+        for object in records.objects() {
+            writeln!(out, "{}", object)?;
+        }
+        Ok(())
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        for page in &document.pages {
+            writeln!(out, "{}", page_json(document, page))?;
+        }
+        Ok(())
+    }
+}
+
+/// Pipe tables; documents with a heading per page
+pub struct MarkdownFormatter;
+
+fn markdown_cell(cell: &Cell) -> String {
+    let text = cell.text().replace('|', "\\|").replace('\n', " ");
+    match &cell.link {
+        Some(url) if !text.is_empty() => format!("[{}]({})", text, url),
+        _ => text,
+    }
+}
+
+impl OutputFormatter for MarkdownFormatter {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["md"]
+    }
+
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn write_records(&self, out: &mut dyn Write, records: &Records) -> Result<()> {
+        writeln!(out, "| {} |", records.columns.join(" | "))?;
+        let rule: Vec<&str> = records.columns.iter().map(|_| "---").collect();
+        writeln!(out, "| {} |", rule.join(" | "))?;
+        for row in &records.rows {
+            let cells: Vec<String> = row.iter().map(markdown_cell).collect();
+            writeln!(out, "| {} |", cells.join(" | "))?;
+        }
+        Ok(())
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        writeln!(out, "# {}", document.path)?;
+        for page in &document.pages {
+            writeln!(out, "\n## Page {}\n", page.number)?;
+            writeln!(out, "{}", page.text.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// A standalone HTML page with a table, or the document's pages as preformatted text
+pub struct HtmlFormatter;
+
+impl OutputFormatter for HtmlFormatter {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn write_records(&self, out: &mut dyn Write, records: &Records) -> Result<()> {
+        writeln!(out, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"></head><body>\n<table>")?;
+        let header: Vec<String> = records.columns.iter().map(|c| format!("<th>{}</th>", escape_html(c))).collect();
+        writeln!(out, "<tr>{}</tr>", header.concat())?;
+        for row in &records.rows {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| {
+                    let text = escape_html(&cell.text());
+                    match &cell.link {
+                        Some(url) => format!("<td><a href=\"{}\">{}</a></td>", escape_html(url), text),
+                        None => format!("<td>{}</td>", text),
+                    }
+                })
+                .collect();
+            writeln!(out, "<tr>{}</tr>", cells.concat())?;
+        }
+        writeln!(out, "</table>\n</body></html>")?;
+        Ok(())
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        let title = escape_html(&document.path);
+        writeln!(out, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title></head><body>", title)?;
+        writeln!(out, "<h1>{}</h1>", title)?;
+        for page in &document.pages {
+            writeln!(out, "<section id=\"page-{n}\"><h2>Page {n}</h2>\n<pre>{}</pre></section>", escape_html(&page.text), n = page.number)?;
+        }
+        writeln!(out, "</body></html>")?;
+        Ok(())
+    }
+}
+
+/// hOCR: words with their boxes, readable by the import command and other OCR
+/// tooling. Pages without word boxes get their lines, unpositioned.
+pub struct HocrFormatter;
+
+fn bbox(x0: f32, y0: f32, x1: f32, y1: f32) -> String {
+    format!("bbox {} {} {} {}", x0.round() as i64, y0.round() as i64, x1.round() as i64, y1.round() as i64)
+}
+
+impl OutputFormatter for HocrFormatter {
+    fn name(&self) -> &'static str {
+        "hocr"
+    }
+
+    fn extension(&self) -> &'static str {
+        "hocr"
+    }
+
+    fn write_records(&self, _out: &mut dyn Write, _records: &Records) -> Result<()> {
+        bail!("hocr formats document pages, not result lists")
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        writeln!(out, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>", escape_html(&document.path))?;
+        writeln!(out, "<meta name=\"ocr-system\" content=\"chonker8\"><meta name=\"ocr-capabilities\" content=\"ocr_page ocr_line ocrx_word\"></head><body>")?;
+        for page in &document.pages {
+            // Without a known size, the page is as large as its words reach
+            let (width, height) = page.size.unwrap_or_else(|| {
+                page.words.iter().fold((0.0, 0.0), |(w, h), word| (f32::max(w, word.x1), f32::max(h, word.y1)))
+            });
+            writeln!(out, "<div class=\"ocr_page\" id=\"page_{n}\" title=\"{}; ppageno {}\">", bbox(0.0, 0.0, width, height), page.number - 1, n = page.number)?;
+            if page.words.is_empty() {
+                for (i, line) in page.text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+                    writeln!(out, "<span class=\"ocr_line\" id=\"line_{}_{}\">{}</span>", page.number, i + 1, escape_html(line.trim()))?;
+                }
+            } else {
+                // A word starts a new line when its middle is outside the current word's height
+                let mut lines: Vec<Vec<&BoxWord>> = Vec::new();
+                for word in &page.words {
+                    match lines.last_mut() {
+                        Some(line) if (word.center_y() - line[line.len() - 1].center_y()).abs() < line[line.len() - 1].height() / 2.0 => {
+                            line.push(word)
+                        }
+                        _ => lines.push(vec![word]),
+                    }
+                }
+                let mut id = 0;
+                for (i, line) in lines.iter().enumerate() {
+                    let (x0, y0, x1, y1) = line.iter().fold((f32::MAX, f32::MAX, 0.0, 0.0), |(x0, y0, x1, y1), w| {
+                        (x0.min(w.x0), y0.min(w.y0), f32::max(x1, w.x1), f32::max(y1, w.y1))
+                    });
+                    writeln!(out, "<span class=\"ocr_line\" id=\"line_{}_{}\" title=\"{}\">", page.number, i + 1, bbox(x0, y0, x1, y1))?;
+                    for word in line {
+                        id += 1;
+                        let title = bbox(word.x0, word.y0, word.x1, word.y1);
+                        writeln!(out, "<span class=\"ocrx_word\" id=\"word_{}_{}\" title=\"{}\">{}</span>", page.number, id, title, escape_html(&word.text))?;
+                    }
+                    writeln!(out, "</span>")?;
+                }
+            }
+            writeln!(out, "</div>")?;
+        }
+        writeln!(out, "</body></html>")?;
+        Ok(())
+    }
+}
+
+/// RFC 4180: quoted when a field holds a comma, quote or line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+pub struct CsvFormatter;
+
+impl OutputFormatter for CsvFormatter {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn write_records(&self, out: &mut dyn Write, records: &Records) -> Result<()> {
+        let header: Vec<String> = records.columns.iter().map(|c| csv_field(c)).collect();
+        writeln!(out, "{}", header.join(","))?;
+        for row in &records.rows {
+            let fields: Vec<String> = row.iter().map(|cell| csv_field(&cell.text())).collect();
+            writeln!(out, "{}", fields.join(","))?;
+        }
+        Ok(())
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        writeln!(out, "path,page,text")?;
+        for page in &document.pages {
+            writeln!(out, "{},{},{}", csv_field(&document.path), page.number, csv_field(&page.text))?;
+        }
+        Ok(())
+    }
+}
+
+/// Tab-separated, tabs and line breaks inside values turned into spaces
+pub struct TsvFormatter;
+
+fn tsv_field(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+impl OutputFormatter for TsvFormatter {
+    fn name(&self) -> &'static str {
+        "tsv"
+    }
+
+    fn extension(&self) -> &'static str {
+        "tsv"
+    }
+
+    fn write_records(&self, out: &mut dyn Write, records: &Records) -> Result<()> {
+        writeln!(out, "{}", records.columns.join("\t"))?;
+        for row in &records.rows {
+            let fields: Vec<String> = row.iter().map(|cell| tsv_field(&cell.text())).collect();
+            writeln!(out, "{}", fields.join("\t"))?;
+        }
+        Ok(())
+    }
+
+    fn write_document(&self, out: &mut dyn Write, document: &DocumentOutput) -> Result<()> {
+        writeln!(out, "path\tpage\ttext")?;
+        for page in &document.pages {
+            writeln!(out, "{}\t{}\t{}", tsv_field(&document.path), page.number, tsv_field(&page.text))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_extraction::sidecar::{parse_sidecar, SidecarFormat};
+
+    fn render(format: &str, write: impl Fn(&dyn OutputFormatter, &mut Vec<u8>) -> Result<()>) -> String {
+        let registry = FormatterRegistry::default();
+        let mut out = Vec::new();
+        write(registry.get(format).unwrap(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_every_format_renders_lists_and_documents() {
+        let mut records = Records::new(&["id", "path"]);
+        records.push(vec![Cell::new(7), Cell::new("a, \"b\".pdf")]);
+        let document = DocumentOutput::from_content("a.pdf", "Total 10\x0cPage <2>");
+
+        assert_eq!(render("table", |f, out| f.write_records(out, &records)), "ID  PATH\n 7  a, \"b\".pdf\n");
+        assert_eq!(render("csv", |f, out| f.write_records(out, &records)), "id,path\n7,\"a, \"\"b\"\".pdf\"\n");
+        assert_eq!(render("jsonl", |f, out| f.write_records(out, &records)), "{\"id\":7,\"path\":\"a, \\\"b\\\".pdf\"}\n");
+        assert!(render("html", |f, out| f.write_document(out, &document)).contains("<pre>Page &lt;2&gt;</pre>"));
+        assert!(render("md", |f, out| f.write_document(out, &document)).contains("## Page 2\n\nPage <2>"));
+        assert_eq!(render("jsonl", |f, out| f.write_document(out, &document)).lines().count(), 2);
+
+        // hOCR goes back in through import
+        let mut boxed = document.clone();
+        let word = |text: &str, x0: f32, y0: f32| BoxWord { text: text.to_string(), x0, y0, x1: x0 + 40.0, y1: y0 + 10.0 };
+        boxed.pages[0].words = vec![word("Total", 10.0, 10.0), word("10", 60.0, 11.0), word("due", 10.0, 30.0)];
+        boxed.pages[0].size = Some((600.0, 800.0));
+        let hocr = render("hocr", |f, out| f.write_document(out, &boxed));
+        let pages = parse_sidecar(SidecarFormat::Hocr, &hocr).unwrap();
+        assert_eq!(pages[0].text, "Total 10\ndue");
+
+        let registry = FormatterRegistry::default();
+        let mut out = Vec::new();
+        assert!(registry.get("hocr").unwrap().write_records(&mut out, &records).is_err());
+        assert!(registry.get("pdf").is_err());
+        for name in registry.names() {
+            let mut out = Vec::new();
+            registry.get(name).unwrap().write_document(&mut out, &document).unwrap();
+            assert!(!out.is_empty(), "{} wrote nothing", name);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+// Animated overlay export - one GIF per page cycling through each engine's words
+//
+// Each frame is the rendered page with one engine's word boxes drawn on it and
+// the words it read written inside them, so places where engines disagree show
+// up as boxes that jump or text that changes between frames. Text is drawn
+// with a built-in 5x7 bitmap font (letters upper-cased, anything it lacks as ?),
+// scaled to the box height - legible enough for a slide, no font files needed.
+use anyhow::{anyhow, Result};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, Rgba, RgbaImage};
+use std::path::Path;
+
+use crate::pdf_extraction::bbox::BoxWord;
+use crate::pdf_extraction::extractors::render_page_png;
+use crate::temp_files::{self, scratch_dir};
+
+/// Resolution pages are rendered at; GIFs are for viewing, not OCR
+pub const GIF_DPI: u32 = 100;
+/// How long each frame shows
+pub const FRAME_DELAY_MS: u32 = 1500;
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// Glyph plus one pixel of spacing on each axis
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+/// Caption text scale
+const CAPTION_SCALE: u32 = 2;
+
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const CAPTION_BACKGROUND: Rgba<u8> = Rgba([30, 30, 30, 255]);
+
+/// One engine's reading of a page
+#[derive(Debug, Clone)]
+pub struct EngineLayer {
+    pub name: String,
+    /// Boxes in PDF points, origin top-left
+    pub words: Vec<BoxWord>,
+    pub color: Rgba<u8>,
+}
+
+/// Colors for successive layers
+pub const LAYER_COLORS: [Rgba<u8>; 4] =
+    [Rgba([0, 140, 60, 255]), Rgba([215, 100, 0, 255]), Rgba([30, 90, 200, 255]), Rgba([170, 30, 140, 255])];
+
+/// Rows of a glyph, bit 4 the leftmost pixel
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ' ' => [0; 7],
+        '.' => [0, 0, 0, 0, 0, 0x0C, 0x0C],
+        ',' => [0, 0, 0, 0, 0x0C, 0x04, 0x08],
+        ':' => [0, 0x0C, 0x0C, 0, 0x0C, 0x0C, 0],
+        '-' => [0, 0, 0, 0x1F, 0, 0, 0],
+        '/' => [0x01, 0x01, 0x02, 0x04, 0x08, 0x10, 0x10],
+        '$' => [0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+fn put(image: &mut RgbaImage, x: u32, y: u32, color: Rgba<u8>) {
+    if x < image.width() && y < image.height() {
+        image.put_pixel(x, y, color);
+    }
+}
+
+/// Mix `color` over a pixel, `alpha` 0-1
+fn blend(image: &mut RgbaImage, x: u32, y: u32, color: Rgba<u8>, alpha: f32) {
+    if x < image.width() && y < image.height() {
+        let pixel = image.get_pixel_mut(x, y);
+        for channel in 0..3 {
+            pixel[channel] = (pixel[channel] as f32 * (1.0 - alpha) + color[channel] as f32 * alpha).round() as u8;
+        }
+    }
+}
+
+/// `text` with its top-left at (x, y), each font pixel `scale` pixels square
+pub(crate) fn draw_text(image: &mut RgbaImage, x: u32, y: u32, text: &str, scale: u32, color: Rgba<u8>) {
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i as u32 * CELL_WIDTH * scale;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            put(image, left + col * scale + dx, y + row as u32 * scale + dy, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The page with one layer drawn on it; `scale` is image pixels per PDF point
+pub fn render_frame(page: &RgbaImage, scale: f32, layer: &EngineLayer, caption: &str) -> RgbaImage {
+    let mut frame = page.clone();
+    for word in &layer.words {
+        let (x0, y0) = ((word.x0 * scale).max(0.0) as u32, (word.y0 * scale).max(0.0) as u32);
+        let (x1, y1) = ((word.x1 * scale).max(0.0) as u32, (word.y1 * scale).max(0.0) as u32);
+        if x1 <= x0 || y1 <= y0 {
+            continue;
+        }
+        // Wash out what's underneath so the engine's text reads against it
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                if x == x0 || x == x1 || y == y0 || y == y1 {
+                    put(&mut frame, x, y, layer.color);
+                } else {
+                    blend(&mut frame, x, y, WHITE, 0.8);
+                }
+            }
+        }
+        let chars = word.text.chars().count() as u32;
+        let fit_height = (y1 - y0).saturating_sub(1) / CELL_HEIGHT;
+        let fit_width = (x1 - x0).saturating_sub(1) / (CELL_WIDTH * chars.max(1));
+        let text_scale = fit_height.min(fit_width);
+        if text_scale > 0 {
+            let top = y0 + 1 + ((y1 - y0).saturating_sub(1) - CELL_HEIGHT * text_scale) / 2;
+            draw_text(&mut frame, x0 + 2, top, &word.text, text_scale, layer.color);
+        }
+    }
+    let bar = CELL_HEIGHT * CAPTION_SCALE + 8;
+    for y in 0..bar.min(frame.height()) {
+        for x in 0..frame.width() {
+            frame.put_pixel(x, y, CAPTION_BACKGROUND);
+        }
+    }
+    let swatch = CELL_HEIGHT * CAPTION_SCALE;
+    for y in 4..4 + swatch {
+        for x in 4..4 + swatch {
+            put(&mut frame, x, y, layer.color);
+        }
+    }
+    draw_text(&mut frame, 8 + swatch, 4 + CAPTION_SCALE, caption, CAPTION_SCALE, WHITE);
+    frame
+}
+
+/// Frames in order, looping forever
+pub fn write_gif(path: &Path, frames: Vec<RgbaImage>, delay_ms: u32) -> Result<()> {
+    temp_files::write_with(path, |out| {
+        let mut encoder = GifEncoder::new_with_speed(out, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+        let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+        encoder.encode_frames(frames.into_iter().map(|frame| Frame::from_parts(frame, 0, 0, delay)))?;
+        Ok(())
+    })
+}
+
+/// Render a page (0-based) and write a GIF with one frame per layer
+pub fn page_overlay_gif(pdf_path: &Path, page_index: usize, layers: &[EngineLayer], out: &Path) -> Result<()> {
+    if layers.is_empty() {
+        return Err(anyhow!("No engine has words for page {}", page_index + 1));
+    }
+    let temp_dir = scratch_dir()?;
+    let png = render_page_png(pdf_path, page_index, GIF_DPI, temp_dir.path())?;
+    let page = image::open(&png)?.to_rgba8();
+    let scale = GIF_DPI as f32 / 72.0;
+    let frames = layers
+        .iter()
+        .map(|layer| {
+            let caption = format!("PAGE {}  {}  {} WORDS", page_index + 1, layer.name, layer.words.len());
+            render_frame(&page, scale, layer, &caption)
+        })
+        .collect();
+    write_gif(out, frames, FRAME_DELAY_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+    use std::fs::File;
+    use std::io::BufReader;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_frames_show_each_layer_and_loop_as_a_gif() {
+        let page = RgbaImage::from_pixel(200, 120, WHITE);
+        let word = |text: &str, x0: f32| BoxWord { text: text.to_string(), x0, y0: 40.0, x1: x0 + 80.0, y1: 60.0 };
+        let layers = [
+            EngineLayer { name: "TEXT LAYER".to_string(), words: vec![word("Total", 10.0)], color: LAYER_COLORS[0] },
+            EngineLayer { name: "TESSERACT".to_string(), words: vec![word("Tota1", 12.0)], color: LAYER_COLORS[1] },
+        ];
+        let frames: Vec<RgbaImage> = layers.iter().map(|layer| render_frame(&page, 1.0, layer, &layer.name)).collect();
+        assert_eq!(*frames[0].get_pixel(10, 40), LAYER_COLORS[0]);
+        assert_eq!(*frames[1].get_pixel(12, 40), LAYER_COLORS[1]);
+        // Text inside the box, in the layer's color
+        let inked = |frame: &RgbaImage, color| (11..90).flat_map(|x| (41..60).map(move |y| (x, y))).any(|(x, y)| *frame.get_pixel(x, y) == color);
+        assert!(inked(&frames[0], LAYER_COLORS[0]));
+        assert_eq!(*frames[0].get_pixel(0, 0), CAPTION_BACKGROUND);
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("page-1.gif");
+        write_gif(&path, frames, 500).unwrap();
+        let decoded = GifDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap().into_frames().collect_frames().unwrap();
+        assert_eq!(decoded.len(), 2);
+    }
+}
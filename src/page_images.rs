@@ -0,0 +1,102 @@
+// Rendered page images - one content-addressed store for every renderer
+//
+// OCR, page hashing, convergence scoring, overlays and the viewer all render
+// pages with pdftoppm, and each used to render the same page again every time.
+// Renders now go in the page-images disk cache, keyed by the SHA-256 of the
+// PDF's bytes, the page and the render setting (a DPI, or a size for the
+// viewer): a page is rendered once per setting whatever asks for it, and a PDF
+// that is moved or renamed keeps its renders. The cache is evicted with the
+// others under [cache] max_size_mb; `chonker8 cache prune` trims it sooner.
+//
+// A PDF is hashed once per process until its size or mtime changes. The cache
+// is only ever a shortcut - if it can't be read or written the page is rendered
+// as before.
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::disk_cache::DiskCache;
+
+pub const CACHE_NAME: &str = "page-images";
+
+/// A file's size and mtime when it was hashed, and its digest
+type Hashed = (u64, SystemTime, String);
+
+/// The SHA-256 of a PDF's bytes, remembered while its size and mtime stay the same
+pub fn pdf_digest(pdf: &Path) -> Result<String> {
+    static DIGESTS: Lazy<Mutex<HashMap<PathBuf, Hashed>>> = Lazy::new(Default::default);
+    let meta = std::fs::metadata(pdf)?;
+    let stamp = (meta.len(), meta.modified()?);
+    let mut digests = DIGESTS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((len, modified, digest)) = digests.get(pdf) {
+        if (*len, *modified) == stamp {
+            return Ok(digest.clone());
+        }
+    }
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(pdf)?, &mut hasher)?;
+    let digest = format!("{:x}", hasher.finalize());
+    digests.insert(pdf.to_path_buf(), (stamp.0, stamp.1, digest.clone()));
+    Ok(digest)
+}
+
+/// Cache key for one page (0-based) of the PDF with `digest`, rendered with `setting`
+pub fn image_key(digest: &str, page_index: usize, setting: &str) -> String {
+    let key = Sha256::digest(format!("{}\0{}\0{}", digest, page_index, setting));
+    format!("{:x}.png", key)
+}
+
+fn cached_in(cache: &DiskCache, pdf: &Path, page_index: usize, setting: &str, render: impl FnOnce() -> Result<Vec<u8>>) -> Result<Vec<u8>> {
+    let key = match pdf_digest(pdf) {
+        Ok(digest) => image_key(&digest, page_index, setting),
+        Err(_) => return render(),
+    };
+    if let Some(png) = cache.get(&key) {
+        return Ok(png);
+    }
+    let png = render()?;
+    if let Err(e) = cache.put(&key, &png) {
+        tracing::debug!("[CACHE] Page image not cached: {}", e);
+    }
+    Ok(png)
+}
+
+/// A page's PNG (page 0-based) as rendered with `setting`, e.g. "300dpi", from the
+/// cache or from `render` on a miss
+pub fn cached_png(pdf: &Path, page_index: usize, setting: &str, render: impl FnOnce() -> Result<Vec<u8>>) -> Result<Vec<u8>> {
+    cached_in(&DiskCache::new(CACHE_NAME), pdf, page_index, setting, render)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_page_rendered_once_per_setting_wherever_the_pdf_is() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = DiskCache::at(temp.path().join(CACHE_NAME));
+        let (a, b) = (temp.path().join("a.pdf"), temp.path().join("moved.pdf"));
+        std::fs::write(&a, b"%PDF-1.5 same bytes").unwrap();
+        std::fs::copy(&a, &b).unwrap();
+
+        let renders = Cell::new(0);
+        let render = || {
+            renders.set(renders.get() + 1);
+            Ok(vec![renders.get() as u8])
+        };
+        assert_eq!(cached_in(&cache, &a, 0, "300dpi", render).unwrap(), vec![1]);
+        assert_eq!(cached_in(&cache, &b, 0, "300dpi", render).unwrap(), vec![1]);
+        assert_eq!(cached_in(&cache, &a, 0, "150dpi", render).unwrap(), vec![2]);
+        assert_eq!(cached_in(&cache, &a, 1, "300dpi", render).unwrap(), vec![3]);
+        assert_eq!(renders.get(), 3);
+
+        // A missing PDF can't be hashed, so it is rendered without the cache
+        assert_eq!(cached_in(&cache, &temp.path().join("gone.pdf"), 0, "300dpi", render).unwrap(), vec![4]);
+    }
+}
@@ -0,0 +1,173 @@
+// Highlights, comments and sticky notes read from a PDF's /Annots
+//
+// Markup annotations (highlight, underline, strike-out, squiggly) only store the
+// quads they cover, not the text. The quoted text is recovered by taking the
+// words from the native text layer whose centres fall inside one of the quads.
+use anyhow::Result;
+use lopdf::{Dictionary, Document, Object};
+use serde::Serialize;
+use std::path::Path;
+
+use super::bbox::{self, BoxWord};
+
+/// Annotation types that are part of the page furniture rather than reader comments
+const SKIPPED_KINDS: &[&str] = &["Link", "Popup", "Widget", "PrinterMark", "TrapNet", "Watermark"];
+
+/// Types whose QuadPoints mark a span of text
+const TEXT_MARKUP_KINDS: &[&str] = &["Highlight", "Underline", "StrikeOut", "Squiggly"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Annotation {
+    pub page: usize, // 1-based
+    /// PDF subtype: Highlight, Text (sticky note), FreeText, Underline, ...
+    pub kind: String,
+    /// #rrggbb, when the annotation has a colour
+    pub color: Option<String>,
+    /// x0, y0, x1, y1 in PDF points, origin bottom-left as in the file
+    pub rect: [f32; 4],
+    pub contents: Option<String>,
+    pub author: Option<String>,
+    /// Raw PDF date, e.g. D:20240131093000Z
+    pub modified: Option<String>,
+    /// Text under a highlight or other text markup
+    pub quoted: Option<String>,
+}
+
+/// Every reader annotation in the PDF, page by page
+pub fn read_annotations(pdf_path: &Path) -> Result<Vec<Annotation>> {
+    let document = Document::load(pdf_path)?;
+    let mut annotations = Vec::new();
+
+    for (page_number, page_id) in document.get_pages() {
+        let page = document.get_dictionary(page_id)?;
+        let Some(annots) = page.get(b"Annots").ok().and_then(|a| resolve(&document, a).as_array().ok()) else {
+            continue;
+        };
+        // Text layer words, fetched once per page and only if a markup needs them
+        let mut words: Option<(f32, Vec<BoxWord>)> = None;
+
+        for annot in annots {
+            let Ok(dict) = resolve(&document, annot).as_dict() else { continue };
+            let kind = dict.get(b"Subtype").and_then(Object::as_name_str).unwrap_or("Unknown").to_string();
+            if SKIPPED_KINDS.contains(&kind.as_str()) {
+                continue;
+            }
+
+            let quads = numbers(&document, dict, b"QuadPoints");
+            let quoted = if TEXT_MARKUP_KINDS.contains(&kind.as_str()) && quads.len() >= 8 {
+                let (height, page_words) = words.get_or_insert_with(|| match bbox::page_words(pdf_path, page_number as usize - 1) {
+                    Ok(page) => (page.height, page.words),
+                    Err(e) => {
+                        tracing::warn!("[ANNOTATIONS] No text layer words for page {}: {}", page_number, e);
+                        (0.0, Vec::new())
+                    }
+                });
+                words_in_quads(page_words, &quads, *height)
+            } else {
+                None
+            };
+
+            let rect = numbers(&document, dict, b"Rect");
+            annotations.push(Annotation {
+                page: page_number as usize,
+                kind,
+                color: color_hex(&numbers(&document, dict, b"C")),
+                rect: if rect.len() == 4 { [rect[0], rect[1], rect[2], rect[3]] } else { [0.0; 4] },
+                contents: text_string(&document, dict, b"Contents"),
+                author: text_string(&document, dict, b"T"),
+                modified: text_string(&document, dict, b"M"),
+                quoted,
+            });
+        }
+    }
+    Ok(annotations)
+}
+
+pub(super) fn resolve<'a>(document: &'a Document, object: &'a Object) -> &'a Object {
+    match object {
+        Object::Reference(id) => document.get_object(*id).unwrap_or(object),
+        _ => object,
+    }
+}
+
+pub(super) fn numbers(document: &Document, dict: &Dictionary, key: &[u8]) -> Vec<f32> {
+    let Some(Ok(items)) = dict.get(key).ok().map(|o| resolve(document, o).as_array()) else {
+        return Vec::new();
+    };
+    items.iter().filter_map(|n| resolve(document, n).as_float().ok()).collect()
+}
+
+pub(super) fn text_string(document: &Document, dict: &Dictionary, key: &[u8]) -> Option<String> {
+    let Object::String(bytes, _) = resolve(document, dict.get(key).ok()?) else { return None };
+    let text = decode_text_string(bytes);
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// PDF text strings are UTF-16BE with a byte order mark, UTF-8 with one (PDF 2.0),
+/// or PDFDocEncoding, which matches Latin-1 for everything readers write in practice
+pub fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+        return String::from_utf16_lossy(&units);
+    }
+    if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(utf8).to_string();
+    }
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Gray (1 component), RGB (3) or CMYK (4) as #rrggbb; no components means transparent
+fn color_hex(components: &[f32]) -> Option<String> {
+    let (r, g, b) = match components {
+        [gray] => (*gray, *gray, *gray),
+        [r, g, b] => (*r, *g, *b),
+        [c, m, y, k] => ((1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)),
+        _ => return None,
+    };
+    let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Some(format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b)))
+}
+
+/// Words (top-left origin) whose centres fall inside any of the quads (bottom-left
+/// origin, 8 numbers each), joined in reading order
+fn words_in_quads(words: &[BoxWord], quads: &[f32], page_height: f32) -> Option<String> {
+    let boxes: Vec<[f32; 4]> = quads
+        .chunks_exact(8)
+        .map(|q| {
+            let xs = [q[0], q[2], q[4], q[6]];
+            let ys = [q[1], q[3], q[5], q[7]];
+            let min = |v: [f32; 4]| v.iter().cloned().fold(f32::MAX, f32::min);
+            let max = |v: [f32; 4]| v.iter().cloned().fold(f32::MIN, f32::max);
+            [min(xs), page_height - max(ys), max(xs), page_height - min(ys)]
+        })
+        .collect();
+
+    let quoted: Vec<&str> = words
+        .iter()
+        .filter(|w| {
+            let (x, y) = ((w.x0 + w.x1) / 2.0, w.center_y());
+            boxes.iter().any(|b| x >= b[0] && x <= b[2] && y >= b[1] && y <= b[3])
+        })
+        .map(|w| w.text.as_str())
+        .collect();
+    (!quoted.is_empty()).then(|| quoted.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoted_text_and_strings() {
+        let word = |text: &str, x0: f32, y0: f32| BoxWord { text: text.to_string(), x0, y0, x1: x0 + 40.0, y1: y0 + 10.0 };
+        let words = vec![word("before", 10.0, 100.0), word("marked", 60.0, 100.0), word("text", 110.0, 100.0), word("below", 60.0, 200.0)];
+        // Quad over x 55-155, y 98-112 from the top of a 792pt page
+        let quad = [55.0, 694.0, 155.0, 694.0, 55.0, 680.0, 155.0, 680.0];
+        assert_eq!(words_in_quads(&words, &quad, 792.0), Some("marked text".to_string()));
+
+        assert_eq!(decode_text_string(&[0xFE, 0xFF, 0x00, 0x48, 0x00, 0xE9]), "Hé");
+        assert_eq!(decode_text_string(b"plain"), "plain");
+        assert_eq!(color_hex(&[1.0, 1.0, 0.0]), Some("#ffff00".to_string()));
+        assert_eq!(color_hex(&[]), None);
+    }
+}
@@ -0,0 +1,105 @@
+// Positioned words from the native text layer via `pdftotext -bbox`, or from
+// tesseract's hOCR output for scanned pages
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::path::Path;
+use std::process::Command;
+
+use crate::temp_files::scratch_dir;
+use super::extractors::{render_page_png, OCR_DPI};
+use super::sidecar::{parse_sidecar, SidecarFormat};
+
+/// A word and its bounding box in PDF points, origin top-left
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxWord {
+    pub text: String,
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+}
+
+impl BoxWord {
+    pub fn center_y(&self) -> f32 {
+        (self.y0 + self.y1) / 2.0
+    }
+
+    pub fn height(&self) -> f32 {
+        self.y1 - self.y0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PageWords {
+    pub width: f32,
+    pub height: f32,
+    pub words: Vec<BoxWord>,
+}
+
+/// Words on one page (0-based index) with their boxes
+pub fn page_words(pdf_path: &Path, page_index: usize) -> Result<PageWords> {
+    let page = (page_index + 1).to_string();
+    let output = Command::new("pdftotext")
+        .args(["-f", &page, "-l", &page, "-bbox"])
+        .arg(pdf_path)
+        .arg("-")
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("pdftotext -bbox failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    parse_bbox_html(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Words tesseract reads on one page (0-based), scaled from the rendered image
+/// to PDF points like `page_words`
+pub fn ocr_page_words(pdf_path: &Path, page_index: usize) -> Result<PageWords> {
+    let temp_dir = scratch_dir()?;
+    let image_path = render_page_png(pdf_path, page_index, OCR_DPI, temp_dir.path())?;
+    let output = Command::new("tesseract")
+        .arg(&image_path)
+        .args(["stdout", "hocr"])
+        .output()
+        .map_err(|e| anyhow!("tesseract not available: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let pages = parse_sidecar(SidecarFormat::Hocr, &String::from_utf8_lossy(&output.stdout))?;
+    let page = pages.first().and_then(|p| p.words.as_ref()).ok_or_else(|| anyhow!("tesseract returned no page"))?;
+    let points = 72.0 / OCR_DPI as f32;
+    let (width, height) = (page.width * points, page.height * points);
+    let words = pages[0].words_on_page(width, height).unwrap_or_default();
+    Ok(PageWords { width, height, words })
+}
+
+pub fn parse_bbox_html(html: &str) -> Result<PageWords> {
+    let page_re = Regex::new(r#"<page width="([\d.]+)" height="([\d.]+)">"#)?;
+    let word_re = Regex::new(
+        r#"<word xMin="([\d.]+)" yMin="([\d.]+)" xMax="([\d.]+)" yMax="([\d.]+)">([^<]*)</word>"#,
+    )?;
+
+    let Some(page) = page_re.captures(html) else {
+        return Ok(PageWords { width: 0.0, height: 0.0, words: Vec::new() });
+    };
+
+    let mut words = Vec::new();
+    for caps in word_re.captures_iter(html) {
+        words.push(BoxWord {
+            text: unescape_html(&caps[5]),
+            x0: caps[1].parse()?,
+            y0: caps[2].parse()?,
+            x1: caps[3].parse()?,
+            y1: caps[4].parse()?,
+        });
+    }
+
+    Ok(PageWords { width: page[1].parse()?, height: page[2].parse()?, words })
+}
+
+pub(crate) fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,468 @@
+// Cloud OCR backend - Azure Read, Google Cloud Vision or AWS Textract
+//
+// Optional and never picked by auto mode: every page costs money, so it runs only
+// when a run asks for it with `--engine cloud` and a [cloud_ocr] table is in
+// extraction.toml. Pages are rendered like for tesseract and sent to the
+// provider one at a time, no faster than `requests_per_minute`. Responses are
+// cached on disk by image hash, so re-running a document is free. Whatever the
+// provider, words come back as BoxWords in PDF points and are laid out as text
+// the same way hybrid extraction lays out its words.
+use anyhow::{anyhow, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::disk_cache::DiskCache;
+use crate::temp_files::scratch_dir;
+
+use super::bbox::BoxWord;
+use super::document_analyzer::PageFingerprint;
+use super::extraction_router::{ExtractionMethod, ExtractionResult};
+use super::extractors::{missing_tools, render_page_png, Extractor, OCR_DPI};
+use super::hybrid::layout_words;
+
+pub const CLOUD_ENGINE: &str = "cloud";
+
+/// Responses' cache under the cache directory
+const CACHE_NAME: &str = "cloud-ocr";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_ATTEMPTS: u32 = 4;
+/// How often an Azure Read operation is polled for its result
+const AZURE_POLL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloudProvider {
+    Azure,
+    Google,
+    Textract,
+}
+
+impl CloudProvider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CloudProvider::Azure => "azure",
+            CloudProvider::Google => "google",
+            CloudProvider::Textract => "textract",
+        }
+    }
+
+    /// List price per page in USD, for cost warnings when the config doesn't say
+    fn list_price(&self) -> f64 {
+        match self {
+            CloudProvider::Azure => 0.001,
+            CloudProvider::Google => 0.0015,
+            CloudProvider::Textract => 0.0015,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CloudOcrConfig {
+    pub provider: CloudProvider,
+    /// Azure: the resource endpoint, e.g. https://<name>.cognitiveservices.azure.com
+    pub endpoint: Option<String>,
+    /// Textract: AWS region, e.g. us-east-1 (credentials come from AWS_ACCESS_KEY_ID etc.)
+    pub region: Option<String>,
+    /// Azure and Google: the key itself, or "env:NAME" to read it from an environment variable
+    pub api_key: Option<String>,
+    #[serde(default = "default_requests_per_minute")]
+    pub requests_per_minute: u32,
+    /// USD per page for cost warnings; the provider's list price if unset
+    pub cost_per_page: Option<f64>,
+    /// Refuse to send more pages than this in one run
+    pub max_pages_per_run: Option<usize>,
+    /// Where responses are cached; the cloud-ocr cache under the cache directory if unset
+    pub cache_dir: Option<PathBuf>,
+}
+
+fn default_requests_per_minute() -> u32 { 10 }
+
+impl CloudOcrConfig {
+    pub fn cost_per_page(&self) -> f64 {
+        self.cost_per_page.unwrap_or_else(|| self.provider.list_price())
+    }
+
+    fn cache(&self) -> DiskCache {
+        self.cache_dir.clone().map_or_else(|| DiskCache::new(CACHE_NAME), DiskCache::at)
+    }
+
+    fn api_key(&self) -> Result<String> {
+        let key = self.api_key.as_deref().ok_or_else(|| anyhow!("[cloud_ocr] api_key is required for {}", self.provider.name()))?;
+        match key.strip_prefix("env:") {
+            Some(var) => std::env::var(var).map_err(|_| anyhow!("[cloud_ocr] environment variable {} is not set", var)),
+            None => Ok(key.to_string()),
+        }
+    }
+}
+
+pub struct CloudOcrExtractor {
+    config: CloudOcrConfig,
+    agent: ureq::Agent,
+    /// Earliest time the next request may go out
+    next_slot: Mutex<Instant>,
+    /// Pages sent (not served from the cache) this run
+    billed: AtomicUsize,
+}
+
+impl CloudOcrExtractor {
+    pub fn new(config: CloudOcrConfig) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+        Self { config, agent, next_slot: Mutex::new(Instant::now()), billed: AtomicUsize::new(0) }
+    }
+
+    pub fn config(&self) -> &CloudOcrConfig {
+        &self.config
+    }
+
+    /// Pages billed so far this run
+    pub fn billed_pages(&self) -> usize {
+        self.billed.load(Ordering::Relaxed)
+    }
+
+    /// Block until the rate limit allows another request
+    fn wait_turn(&self) {
+        let interval = Duration::from_secs(60) / self.config.requests_per_minute.max(1);
+        let wait = {
+            let mut next = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let slot = (*next).max(now);
+            *next = slot + interval;
+            slot - now
+        };
+        std::thread::sleep(wait);
+    }
+
+    /// The provider's response for a rendered page, from the cache when possible
+    fn response(&self, png: &[u8]) -> Result<Value> {
+        let digest = format!("{:x}", Sha256::digest(png));
+        let cache = self.config.cache();
+        let cache_key = format!("{}-{}.json", self.config.provider.name(), &digest[..32]);
+        if let Some(value) = cache.get(&cache_key).and_then(|cached| serde_json::from_slice::<Value>(&cached).ok()) {
+            return Ok(value);
+        }
+
+        let billed = self.billed.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(limit) = self.config.max_pages_per_run {
+            if billed > limit {
+                return Err(anyhow!("Cloud OCR page limit reached ({} per run, max_pages_per_run)", limit));
+            }
+        }
+
+        let response = self.send(png)?;
+        tracing::info!("[CLOUD] {} pages billed this run, ≈ ${:.3}", billed, billed as f64 * self.config.cost_per_page());
+        cache.put(&cache_key, serde_json::to_string(&response)?.as_bytes())?;
+        Ok(response)
+    }
+
+    /// One request, retried when the provider throttles or has a transient failure
+    fn send(&self, png: &[u8]) -> Result<Value> {
+        let mut attempt = 0;
+        loop {
+            self.wait_turn();
+            let result = match self.config.provider {
+                CloudProvider::Azure => self.send_azure(png),
+                CloudProvider::Google => self.send_google(png),
+                CloudProvider::Textract => self.send_textract(png),
+            };
+            attempt += 1;
+            let error = match result {
+                Ok(value) => return Ok(value),
+                Err(e) => e.downcast::<ureq::Error>(),
+            };
+            match error {
+                Ok(ureq::Error::Status(status, response)) if (status == 429 || status >= 500) && attempt < MAX_ATTEMPTS => {
+                    let retry_after = response.header("Retry-After").and_then(|s| s.parse().ok()).unwrap_or(1u64 << attempt);
+                    tracing::warn!("[CLOUD] {} returned {}, retrying in {}s", self.config.provider.name(), status, retry_after);
+                    std::thread::sleep(Duration::from_secs(retry_after));
+                }
+                Ok(ureq::Error::Status(status, response)) => {
+                    let body = response.into_string().unwrap_or_default();
+                    return Err(anyhow!("{} returned {}: {}", self.config.provider.name(), status, body.trim()));
+                }
+                Ok(e) => return Err(anyhow!("{} request failed: {}", self.config.provider.name(), e)),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn send_azure(&self, png: &[u8]) -> Result<Value> {
+        let endpoint = self.config.endpoint.as_deref().unwrap_or_default().trim_end_matches('/');
+        let key = self.config.api_key()?;
+        let submitted = self
+            .agent
+            .post(&format!("{}/vision/v3.2/read/analyze", endpoint))
+            .set("Ocp-Apim-Subscription-Key", &key)
+            .set("Content-Type", "application/octet-stream")
+            .send_bytes(png)?;
+        let operation = submitted
+            .header("Operation-Location")
+            .ok_or_else(|| anyhow!("Azure returned no Operation-Location"))?
+            .to_string();
+        // Polls count against the rate limit too, and give up once a request's worth of time has passed
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        let mut status = String::from("notStarted");
+        while Instant::now() < deadline {
+            std::thread::sleep(AZURE_POLL);
+            self.wait_turn();
+            let result = json_body(self.agent.get(&operation).set("Ocp-Apim-Subscription-Key", &key).call()?)?;
+            match result["status"].as_str() {
+                Some("succeeded") => return Ok(result),
+                Some("failed") => return Err(anyhow!("Azure Read operation failed")),
+                other => status = other.unwrap_or("missing").to_string(),
+            }
+        }
+        Err(anyhow!("Azure Read operation still {} after {}s", status, REQUEST_TIMEOUT.as_secs()))
+    }
+
+    fn send_google(&self, png: &[u8]) -> Result<Value> {
+        let key = self.config.api_key()?;
+        let body = json!({ "requests": [{
+            "image": { "content": BASE64.encode(png) },
+            "features": [{ "type": "DOCUMENT_TEXT_DETECTION" }],
+        }]});
+        json_body(
+            self.agent
+                .post("https://vision.googleapis.com/v1/images:annotate")
+                .query("key", &key)
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string())?,
+        )
+    }
+
+    fn send_textract(&self, png: &[u8]) -> Result<Value> {
+        let region = self.config.region.as_deref().unwrap_or("us-east-1");
+        let access_key = std::env::var("AWS_ACCESS_KEY_ID").map_err(|_| anyhow!("AWS_ACCESS_KEY_ID is not set"))?;
+        let secret_key = std::env::var("AWS_SECRET_ACCESS_KEY").map_err(|_| anyhow!("AWS_SECRET_ACCESS_KEY is not set"))?;
+        let session_token = std::env::var("AWS_SESSION_TOKEN").ok();
+
+        let host = format!("textract.{}.amazonaws.com", region);
+        let body = json!({ "Document": { "Bytes": BASE64.encode(png) } }).to_string();
+        let now = chrono::Utc::now();
+        let signed = sigv4(&SigV4Request {
+            host: &host,
+            region,
+            target: "Textract.DetectDocumentText",
+            body: &body,
+            amz_date: &now.format("%Y%m%dT%H%M%SZ").to_string(),
+            access_key: &access_key,
+            secret_key: &secret_key,
+            session_token: session_token.as_deref(),
+        });
+
+        let mut request = self
+            .agent
+            .post(&format!("https://{}/", host))
+            .set("Content-Type", "application/x-amz-json-1.1")
+            .set("X-Amz-Target", "Textract.DetectDocumentText")
+            .set("X-Amz-Date", &now.format("%Y%m%dT%H%M%SZ").to_string())
+            .set("Authorization", &signed);
+        if let Some(token) = &session_token {
+            request = request.set("X-Amz-Security-Token", token);
+        }
+        json_body(request.send_string(&body)?)
+    }
+}
+
+fn json_body(response: ureq::Response) -> Result<Value> {
+    let body = response.into_string()?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+impl Extractor for CloudOcrExtractor {
+    fn name(&self) -> &'static str {
+        CLOUD_ENGINE
+    }
+
+    /// Never chosen automatically - it costs money per page
+    fn supports(&self, _fingerprint: &PageFingerprint) -> bool {
+        false
+    }
+
+    fn extract(&self, pdf_path: &Path, page_index: usize) -> Result<ExtractionResult> {
+        let start = Instant::now();
+        let temp_dir = scratch_dir()?;
+        let image_path = render_page_png(pdf_path, page_index, OCR_DPI, temp_dir.path())?;
+        let png = std::fs::read(&image_path)?;
+        let (pixel_width, pixel_height) = image::image_dimensions(&image_path)?;
+
+        let response = self.response(&png)?;
+        let points = 72.0 / OCR_DPI as f32;
+        let page = PageSize { width: pixel_width as f32 * points, height: pixel_height as f32 * points, points_per_pixel: points };
+        let words = parse_response(self.config.provider, &response, page)?;
+
+        let mut result = ExtractionResult::new(layout_words(&words), ExtractionMethod::Cloud);
+        result.extraction_time_ms = start.elapsed().as_millis() as u64;
+        Ok(result)
+    }
+
+    fn missing(&self) -> Option<String> {
+        missing_tools(&["pdftoppm"])
+    }
+}
+
+/// The rendered page: its size in points and the scale of the image sent
+#[derive(Debug, Clone, Copy)]
+struct PageSize {
+    width: f32,
+    height: f32,
+    points_per_pixel: f32,
+}
+
+/// Words from a provider's response, in PDF points
+fn parse_response(provider: CloudProvider, response: &Value, page: PageSize) -> Result<Vec<BoxWord>> {
+    let number = |v: &Value| v.as_f64().unwrap_or(0.0) as f32;
+    // A box from corner points in pixels
+    let from_points = |text: &str, xs: Vec<f32>, ys: Vec<f32>| BoxWord {
+        text: text.to_string(),
+        x0: xs.iter().copied().fold(f32::MAX, f32::min) * page.points_per_pixel,
+        y0: ys.iter().copied().fold(f32::MAX, f32::min) * page.points_per_pixel,
+        x1: xs.iter().copied().fold(0.0, f32::max) * page.points_per_pixel,
+        y1: ys.iter().copied().fold(0.0, f32::max) * page.points_per_pixel,
+    };
+
+    let words = match provider {
+        CloudProvider::Azure => response["analyzeResult"]["readResults"]
+            .as_array()
+            .and_then(|pages| pages.first())
+            .and_then(|p| p["lines"].as_array())
+            .ok_or_else(|| anyhow!("Azure response has no readResults"))?
+            .iter()
+            .flat_map(|line| line["words"].as_array().cloned().unwrap_or_default())
+            .map(|word| {
+                let corners: Vec<f32> = word["boundingBox"].as_array().map(|b| b.iter().map(number).collect()).unwrap_or_default();
+                let xs = corners.iter().step_by(2).copied().collect();
+                let ys = corners.iter().skip(1).step_by(2).copied().collect();
+                from_points(word["text"].as_str().unwrap_or_default(), xs, ys)
+            })
+            .collect(),
+        // The first annotation is the whole page's text; the rest are words
+        CloudProvider::Google => response["responses"][0]["textAnnotations"]
+            .as_array()
+            .map(|annotations| {
+                annotations
+                    .iter()
+                    .skip(1)
+                    .map(|a| {
+                        let vertices = a["boundingPoly"]["vertices"].as_array().cloned().unwrap_or_default();
+                        let xs = vertices.iter().map(|v| number(&v["x"])).collect();
+                        let ys = vertices.iter().map(|v| number(&v["y"])).collect();
+                        from_points(a["description"].as_str().unwrap_or_default(), xs, ys)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        // Boxes are fractions of the page
+        CloudProvider::Textract => response["Blocks"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Textract response has no Blocks"))?
+            .iter()
+            .filter(|block| block["BlockType"] == "WORD")
+            .map(|block| {
+                let bbox = &block["Geometry"]["BoundingBox"];
+                let (left, top) = (number(&bbox["Left"]) * page.width, number(&bbox["Top"]) * page.height);
+                BoxWord {
+                    text: block["Text"].as_str().unwrap_or_default().to_string(),
+                    x0: left,
+                    y0: top,
+                    x1: left + number(&bbox["Width"]) * page.width,
+                    y1: top + number(&bbox["Height"]) * page.height,
+                }
+            })
+            .collect(),
+    };
+    Ok(words)
+}
+
+struct SigV4Request<'a> {
+    host: &'a str,
+    region: &'a str,
+    target: &'a str,
+    body: &'a str,
+    amz_date: &'a str,
+    access_key: &'a str,
+    secret_key: &'a str,
+    session_token: Option<&'a str>,
+}
+
+/// Authorization header for an AWS JSON API call (Signature Version 4)
+fn sigv4(request: &SigV4Request) -> String {
+    let date = &request.amz_date[..8];
+    let mut headers = vec![
+        ("content-type", "application/x-amz-json-1.1"),
+        ("host", request.host),
+        ("x-amz-date", request.amz_date),
+    ];
+    if let Some(token) = request.session_token {
+        headers.push(("x-amz-security-token", token));
+    }
+    headers.push(("x-amz-target", request.target));
+
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "POST\n/\n\n{}\n{}\n{:x}",
+        canonical_headers, signed_headers, Sha256::digest(request.body.as_bytes())
+    );
+    let scope = format!("{}/{}/textract/aws4_request", date, request.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+        request.amz_date, scope, Sha256::digest(canonical_request.as_bytes())
+    );
+
+    let key = [date, request.region, "textract", "aws4_request"]
+        .iter()
+        .fold(format!("AWS4{}", request.secret_key).into_bytes(), |key, part| hmac_sha256(&key, part.as_bytes()).to_vec());
+    let signature: String = hmac_sha256(&key, string_to_sign.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        request.access_key, scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.iter().map(|b| b ^ byte).collect::<Vec<u8>>();
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_responses_normalize_to_points() {
+        // 300 dpi render of a 612 x 792 pt page
+        let page = PageSize { width: 612.0, height: 792.0, points_per_pixel: 72.0 / 300.0 };
+
+        let google = json!({ "responses": [{ "textAnnotations": [
+            { "description": "Total due" },
+            { "description": "Total", "boundingPoly": { "vertices": [{ "x": 300, "y": 600 }, { "x": 500, "y": 600 }, { "x": 500, "y": 650 }, { "y": 650, "x": 300 }] } },
+        ]}]});
+        let words = parse_response(CloudProvider::Google, &google, page).unwrap();
+        assert_eq!(words, vec![BoxWord { text: "Total".to_string(), x0: 72.0, y0: 144.0, x1: 120.0, y1: 156.0 }]);
+
+        let textract = json!({ "Blocks": [
+            { "BlockType": "LINE", "Text": "Total due" },
+            { "BlockType": "WORD", "Text": "due", "Geometry": { "BoundingBox": { "Left": 0.25, "Top": 0.5, "Width": 0.05, "Height": 0.01 } } },
+        ]});
+        let words = parse_response(CloudProvider::Textract, &textract, page).unwrap();
+        assert_eq!((words.len(), words[0].x0, words[0].y0), (1, 153.0, 396.0));
+
+        // RFC 4231 test case 2
+        let mac: String = hmac_sha256(b"Jefe", b"what do ya want for nothing?").iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(mac, "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+}
@@ -0,0 +1,148 @@
+// Duplicate pages within a document - repeated fax cover sheets, pages scanned twice
+//
+// Each page is compared with the one before it. Text is compared as sets of
+// three-word shingles; when the page images are available too, a difference hash
+// of a thumbnail decides, with a looser text check so OCR noise on two scans of
+// the same sheet doesn't hide the match. Blank pages are never duplicates - they
+// are their own problem. A run of repeats all point at its first page.
+use anyhow::Result;
+use image::imageops::FilterType;
+use image::GrayImage;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use crate::temp_files::scratch_dir;
+use super::extractors::render_page_png;
+
+/// Share of shingles two pages must have in common on text alone
+pub const TEXT_SIMILARITY: f32 = 0.9;
+/// ... and when their images already match
+const LOOSE_TEXT_SIMILARITY: f32 = 0.6;
+/// Differing bits (of 128) allowed between two image hashes
+pub const IMAGE_DISTANCE: u32 = 6;
+/// Thumbnails this coarse still separate different pages of a form
+const HASH_DPI: u32 = 24;
+
+#[derive(Debug, Clone, Default)]
+pub struct PageSignature {
+    shingles: HashSet<u64>,
+    image: Option<u128>,
+}
+
+impl PageSignature {
+    pub fn new(text: &str, image: Option<u128>) -> Self {
+        let words: Vec<String> = text
+            .split_whitespace()
+            .map(|w| w.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>())
+            .filter(|w| !w.is_empty())
+            .collect();
+        let width = words.len().clamp(1, 3);
+        let shingles = words
+            .windows(width)
+            .map(|window| {
+                let mut hasher = DefaultHasher::new();
+                window.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect();
+        Self { shingles, image }
+    }
+
+    fn is_blank(&self) -> bool {
+        self.shingles.is_empty() && self.image.is_none_or(|hash| hash == 0)
+    }
+
+    fn text_similarity(&self, other: &Self) -> f32 {
+        let shared = self.shingles.intersection(&other.shingles).count();
+        let total = self.shingles.union(&other.shingles).count();
+        if total == 0 { 1.0 } else { shared as f32 / total as f32 }
+    }
+
+    pub fn matches(&self, other: &Self) -> bool {
+        if self.is_blank() || other.is_blank() {
+            return false;
+        }
+        match (self.image, other.image) {
+            (Some(a), Some(b)) => (a ^ b).count_ones() <= IMAGE_DISTANCE && self.text_similarity(other) >= LOOSE_TEXT_SIMILARITY,
+            _ => !self.shingles.is_empty() && self.text_similarity(other) >= TEXT_SIMILARITY,
+        }
+    }
+}
+
+/// For each page, the (0-based) page it repeats, if it repeats the one before it
+pub fn duplicate_pages(signatures: &[PageSignature]) -> Vec<Option<usize>> {
+    let mut duplicates: Vec<Option<usize>> = Vec::with_capacity(signatures.len());
+    for (index, signature) in signatures.iter().enumerate() {
+        let original = match index.checked_sub(1) {
+            Some(previous) if signatures[previous].matches(signature) => Some(duplicates[previous].unwrap_or(previous)),
+            _ => None,
+        };
+        duplicates.push(original);
+    }
+    duplicates
+}
+
+/// Pages joined with form feeds, repeats replaced by a note pointing at the original.
+/// Page numbering is kept, so word boxes and edits still line up.
+pub fn collapse_pages(pages: &[&str], duplicates: &[Option<usize>]) -> String {
+    pages
+        .iter()
+        .zip(duplicates)
+        .map(|(text, duplicate)| match duplicate {
+            Some(original) => format!("[Duplicate of page {}]", original + 1),
+            None => text.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\x0c")
+}
+
+/// 128-bit difference hash: a 17x8 thumbnail, one bit per horizontally adjacent pair
+pub fn image_hash(page: &GrayImage) -> u128 {
+    let small = image::imageops::resize(page, 17, 8, FilterType::Triangle);
+    let mut hash = 0u128;
+    for y in 0..8 {
+        for x in 0..16 {
+            hash <<= 1;
+            // Ties hash to 0, so a blank page hashes to exactly 0
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Image hash of a rendered page (0-based)
+pub fn page_image_hash(pdf_path: &Path, page_index: usize) -> Result<u128> {
+    let dir = scratch_dir()?;
+    let png = render_page_png(pdf_path, page_index, HASH_DPI, dir.path())?;
+    Ok(image_hash(&image::open(png)?.to_luma8()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn test_consecutive_repeats_point_at_first() {
+        let cover = "FAX COVER SHEET To: Accounts From: Legal Pages: 12 Please call on receipt";
+        let pages = [cover, cover, "FAX COVER SHEET To: Accounts From: Legal Pages: 12 Please call on receipt.", "Invoice 42 Total due 100", "", ""];
+        let signatures: Vec<PageSignature> = pages.iter().map(|text| PageSignature::new(text, None)).collect();
+        let duplicates = duplicate_pages(&signatures);
+        // Blank pages stay, however many there are
+        assert_eq!(duplicates, vec![None, Some(0), Some(0), None, None, None]);
+        assert_eq!(collapse_pages(&pages[2..4], &[Some(0), None]), "[Duplicate of page 1]\x0cInvoice 42 Total due 100");
+
+        // Same layout, unrelated text: the image alone doesn't make a duplicate
+        let mut form = GrayImage::from_pixel(200, 100, Luma([255]));
+        (20..180).for_each(|x| form.put_pixel(x, 50, Luma([0])));
+        let hash = image_hash(&form);
+        assert_ne!(hash, 0);
+        assert_eq!(image_hash(&GrayImage::from_pixel(200, 100, Luma([255]))), 0);
+        assert!(PageSignature::new("Claim 1 approved", Some(hash)).matches(&PageSignature::new("Claim 1 approved", Some(hash))));
+        assert!(!PageSignature::new("Claim 1 approved", Some(hash)).matches(&PageSignature::new("Unrelated note here", Some(hash))));
+    }
+}
@@ -3,6 +3,8 @@ use lopdf::{Document, Object, Dictionary};
 use std::path::Path;
 use std::time::Instant;
 
+use crate::content_stream::{self, Operand};
+
 /// Page content fingerprint for routing decisions
 #[derive(Debug, Clone)]
 pub struct PageFingerprint {
@@ -37,11 +39,15 @@ impl DocumentAnalyzer {
     
     /// Analyze a single page and generate fingerprint
     pub fn analyze_page(&self, pdf_path: &Path, page_index: usize) -> Result<PageFingerprint> {
-        let start = Instant::now();
-        let mut fingerprint = PageFingerprint::new();
-        
         // Load PDF with lopdf
         let document = Document::load(pdf_path)?;
+        self.analyze_loaded_page(&document, page_index)
+    }
+    
+    /// Analyze a page of an already-loaded document (avoids re-parsing per page)
+    pub fn analyze_loaded_page(&self, document: &Document, page_index: usize) -> Result<PageFingerprint> {
+        let start = Instant::now();
+        let mut fingerprint = PageFingerprint::new();
         
         // Get the page
         let pages = document.get_pages();
@@ -53,11 +59,11 @@ impl DocumentAnalyzer {
             .as_dict()?;
         
         // Get page dimensions
-        let (page_width, page_height) = get_page_dimensions(&document, page_dict)?;
+        let (page_width, page_height) = get_page_dimensions(document, page_dict)?;
         let page_area = page_width * page_height;
         
         // Extract and analyze text
-        let text = extract_page_text(&document, page_dict)?;
+        let text = extract_page_text(document, page_dict)?;
         fingerprint.char_count = text.chars().count();
         
         // Calculate text coverage (simplified - assumes avg char size)
@@ -72,7 +78,7 @@ impl DocumentAnalyzer {
         fingerprint.has_tables = detect_tables(&text);
         
         // Analyze images in content stream
-        fingerprint.image_coverage = analyze_images(&document, page_dict, page_area)?;
+        fingerprint.image_coverage = analyze_images(document, page_dict, page_width, page_height)?;
         
         fingerprint.extraction_time_ms = start.elapsed().as_millis() as u64;
         
@@ -86,13 +92,48 @@ impl DocumentAnalyzer {
         
         let mut fingerprints = Vec::new();
         for i in 0..page_count {
-            fingerprints.push(self.analyze_page(pdf_path, i)?);
+            fingerprints.push(self.analyze_loaded_page(&document, i)?);
         }
         
         Ok(fingerprints)
     }
 }
 
+/// Width and height in points of one page (0-based index)
+pub fn page_dimensions(document: &Document, page_index: usize) -> Result<(f32, f32)> {
+    let page_id = document
+        .get_pages()
+        .get(&((page_index + 1) as u32))
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("Page {} not found", page_index + 1))?;
+    get_page_dimensions(document, document.get_object(page_id)?.as_dict()?)
+}
+
+/// Width and height in points of one page as it is displayed, swapped when the
+/// page is turned a quarter by /Rotate
+pub fn displayed_page_dimensions(document: &Document, page_index: usize) -> Result<(f32, f32)> {
+    let (width, height) = page_dimensions(document, page_index)?;
+    Ok(if page_rotation(document, page_index)?.rem_euclid(180) == 90 { (height, width) } else { (width, height) })
+}
+
+/// Clockwise degrees viewers turn a page (0-based index) by - its /Rotate, set on
+/// the page or inherited from the page tree
+pub fn page_rotation(document: &Document, page_index: usize) -> Result<i64> {
+    let page_id = document
+        .get_pages()
+        .get(&((page_index + 1) as u32))
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("Page {} not found", page_index + 1))?;
+    let mut node = document.get_dictionary(page_id).ok();
+    while let Some(dict) = node {
+        if let Ok(angle) = dict.get(b"Rotate").and_then(Object::as_i64) {
+            return Ok(angle);
+        }
+        node = dict.get(b"Parent").and_then(Object::as_reference).and_then(|id| document.get_dictionary(id)).ok();
+    }
+    Ok(0)
+}
+
 // Get page dimensions from MediaBox
 fn get_page_dimensions(document: &Document, page: &Dictionary) -> Result<(f32, f32)> {
     if let Ok(media_box) = page.get(b"MediaBox") {
@@ -196,8 +237,9 @@ fn get_content_data(document: &Document, contents: &Object) -> Result<Vec<u8>> {
             let obj = document.get_object(*r)?;
             get_content_data(document, obj)
         }
+        // Unfiltered streams have nothing to decompress
         Object::Stream(stream) => {
-            Ok(stream.decompressed_content()?)
+            Ok(stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()))
         }
         Object::Array(arr) => {
             let mut data = Vec::new();
@@ -211,75 +253,159 @@ fn get_content_data(document: &Document, contents: &Object) -> Result<Vec<u8>> {
     }
 }
 
-// Analyze images in page content
-fn analyze_images(document: &Document, page: &Dictionary, page_area: f32) -> Result<f32> {
-    let mut image_area = 0.0;
-    
-    // Check for XObject resources (images)
-    if let Ok(resources) = page.get(b"Resources") {
-        let res_dict = match resources {
-            Object::Reference(id) => {
-                if let Ok(Object::Dictionary(dict)) = document.get_object(*id) {
-                    dict
-                } else {
-                    return Ok(0.0);
-                }
+/// Where an image XObject lands on a page - the image's unit square taken
+/// through the transformation matrix in force at its Do operator
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImagePlacement {
+    /// Resource name, without the slash
+    pub name: String,
+    /// Corners of the unit square (0,0) (1,0) (1,1) (0,1) in page space; a
+    /// rotated or skewed image gives a quad that isn't axis-aligned
+    pub quad: [(f32, f32); 4],
+    /// Image size in samples
+    pub pixels: (u32, u32),
+    /// Has a soft mask (/SMask), i.e. transparent parts
+    pub soft_mask: bool,
+}
+
+impl ImagePlacement {
+    /// Axis-aligned bounds (x0, y0, x1, y1)
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        self.quad.iter().fold((f32::MAX, f32::MAX, f32::MIN, f32::MIN), |(x0, y0, x1, y1), &(x, y)| {
+            (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+        })
+    }
+}
+
+/// Transformation matrix [a b c d e f]
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+/// Forms nested deeper than this are not followed (and cycles end here)
+const MAX_FORM_DEPTH: usize = 8;
+
+/// `m` applied first, then `ctm` - what `m cm` does to the CTM
+fn concat(m: &Matrix, ctm: &Matrix) -> Matrix {
+    [
+        m[0] * ctm[0] + m[1] * ctm[2],
+        m[0] * ctm[1] + m[1] * ctm[3],
+        m[2] * ctm[0] + m[3] * ctm[2],
+        m[2] * ctm[1] + m[3] * ctm[3],
+        m[4] * ctm[0] + m[5] * ctm[2] + ctm[4],
+        m[4] * ctm[1] + m[5] * ctm[3] + ctm[5],
+    ]
+}
+
+fn transform(m: &Matrix, (x, y): (f32, f32)) -> (f32, f32) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+fn resolve<'a>(document: &'a Document, object: &'a Object) -> &'a Object {
+    match object {
+        Object::Reference(id) => document.get_object(*id).unwrap_or(object),
+        _ => object,
+    }
+}
+
+/// The page's /Resources, inherited from the page tree when not set on the page
+fn page_resources<'a>(document: &'a Document, page: &'a Dictionary) -> Option<&'a Dictionary> {
+    let mut node = Some(page);
+    while let Some(dict) = node {
+        if let Ok(resources) = dict.get(b"Resources") {
+            return resolve(document, resources).as_dict().ok();
+        }
+        node = dict.get(b"Parent").and_then(Object::as_reference).and_then(|id| document.get_dictionary(id)).ok();
+    }
+    None
+}
+
+/// Every image a page draws, where it draws it, forms followed
+pub fn image_placements(document: &Document, page_index: usize) -> Result<Vec<ImagePlacement>> {
+    let page_id = document
+        .get_pages()
+        .get(&((page_index + 1) as u32))
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("Page {} not found", page_index + 1))?;
+    page_image_placements(document, document.get_dictionary(page_id)?)
+}
+
+fn page_image_placements(document: &Document, page: &Dictionary) -> Result<Vec<ImagePlacement>> {
+    let content = match page.get(b"Contents") {
+        Ok(contents) => get_content_data(document, contents)?,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut placements = Vec::new();
+    collect_placements(document, &content, page_resources(document, page), IDENTITY, 0, &mut placements);
+    Ok(placements)
+}
+
+fn collect_placements(
+    document: &Document,
+    content: &[u8],
+    resources: Option<&Dictionary>,
+    base: Matrix,
+    depth: usize,
+    placements: &mut Vec<ImagePlacement>,
+) {
+    let xobjects = resources.and_then(|r| r.get(b"XObject").ok()).and_then(|x| resolve(document, x).as_dict().ok());
+    let mut ctm = base;
+    let mut saved = Vec::new();
+    for op in content_stream::parse(content) {
+        match op.operator.as_str() {
+            "q" => saved.push(ctm),
+            "Q" => ctm = saved.pop().unwrap_or(base),
+            "cm" if op.operands.len() == 6 => {
+                let m = [op.number(0), op.number(1), op.number(2), op.number(3), op.number(4), op.number(5)];
+                ctm = concat(&m, &ctm);
             }
-            Object::Dictionary(dict) => dict,
-            _ => return Ok(0.0),
-        };
-        if let Ok(xobject) = res_dict.get(b"XObject") {
-            let xobj_dict = match xobject {
-                Object::Reference(id) => {
-                    if let Ok(Object::Dictionary(dict)) = document.get_object(*id) {
-                        dict
-                    } else {
-                        return Ok(0.0);
-                    }
-                }
-                Object::Dictionary(dict) => dict,
-                _ => return Ok(0.0),
-            };
-            for (_name, obj_ref) in xobj_dict.iter() {
-                let stream = match obj_ref {
-                    Object::Reference(id) => {
-                        if let Ok(Object::Stream(s)) = document.get_object(*id) {
-                            s
-                        } else {
-                            continue;
-                        }
-                    }
-                    Object::Stream(s) => s,
-                    _ => continue,
+            "Do" => {
+                let Some(Operand::Name(name)) = op.operands.first() else { continue };
+                let Some(Object::Stream(stream)) = xobjects.and_then(|x| x.get(name.as_bytes()).ok()).map(|x| resolve(document, x)) else {
+                    continue;
                 };
-                {
-                    let stream_dict = &stream.dict;
-                    // Check if it's an image
-                    if let Ok(subtype) = stream_dict.get(b"Subtype") {
-                        let is_image = match subtype {
-                            Object::Reference(id) => {
-                                if let Ok(Object::Name(name)) = document.get_object(*id) {
-                                    name == b"Image"
-                                } else {
-                                    false
-                                }
+                match stream.dict.get(b"Subtype").and_then(Object::as_name).ok() {
+                    Some(b"Image") => {
+                        let size = |key: &[u8]| get_number(document, &stream.dict, key).unwrap_or(0.0) as u32;
+                        placements.push(ImagePlacement {
+                            name: name.clone(),
+                            quad: [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|corner| transform(&ctm, corner)),
+                            pixels: (size(b"Width"), size(b"Height")),
+                            soft_mask: stream.dict.has(b"SMask"),
+                        });
+                    }
+                    Some(b"Form") if depth < MAX_FORM_DEPTH => {
+                        let matrix = stream.dict.get(b"Matrix").and_then(Object::as_array).ok().filter(|m| m.len() == 6);
+                        let matrix = matrix.map_or(IDENTITY, |m| {
+                            let mut out = IDENTITY;
+                            for (slot, value) in out.iter_mut().zip(m) {
+                                *slot = resolve(document, value).as_float().unwrap_or(0.0);
                             }
-                            Object::Name(name) => name == b"Image",
-                            _ => false,
-                        };
-                        if is_image {
-                            // Get image dimensions
-                            let width = get_number(&document, &stream_dict, b"Width").unwrap_or(100.0);
-                            let height = get_number(&document, &stream_dict, b"Height").unwrap_or(100.0);
-                            image_area += width * height;
-                        }
+                            out
+                        });
+                        // A form without its own resources uses the ones it is drawn with
+                        let own = stream.dict.get(b"Resources").ok().and_then(|r| resolve(document, r).as_dict().ok());
+                        let data = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+                        collect_placements(document, &data, own.or(resources), concat(&matrix, &ctm), depth + 1, placements);
                     }
+                    _ => {}
                 }
             }
+            _ => {}
         }
     }
-    
-    Ok((image_area / page_area).min(1.0))
+}
+
+// Share of the page covered by images, from where they are actually drawn
+fn analyze_images(document: &Document, page: &Dictionary, page_width: f32, page_height: f32) -> Result<f32> {
+    let mut image_area = 0.0;
+    for placement in page_image_placements(document, page)? {
+        let (x0, y0, x1, y1) = placement.bounds();
+        // Only the part on the page counts
+        let width = (x1.min(page_width) - x0.max(0.0)).max(0.0);
+        let height = (y1.min(page_height) - y0.max(0.0)).max(0.0);
+        image_area += width * height;
+    }
+    Ok((image_area / (page_width * page_height)).min(1.0))
 }
 
 // Helper to get numeric value from dictionary
@@ -354,4 +480,60 @@ fn detect_tables(text: &str) -> bool {
     }
     
     consecutive_similar >= 2
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    #[test]
+    fn test_images_are_placed_by_the_ctm_at_do() {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let mask = document.add_object(Stream::new(dictionary! { "Subtype" => "Image", "Width" => 800, "Height" => 600 }, vec![0; 4]));
+        let image = document.add_object(Stream::new(
+            dictionary! { "Type" => "XObject", "Subtype" => "Image", "Width" => 800, "Height" => 600, "SMask" => mask },
+            vec![0; 4],
+        ));
+        // A form that draws the image at half size, moved by its own matrix
+        let form = document.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "Matrix" => vec![1.into(), 0.into(), 0.into(), 1.into(), 10.into(), 0.into()],
+            },
+            b"q 0.5 0 0 0.5 0 0 cm /Im1 Do Q".to_vec(),
+        ));
+        let content = document.add_object(Stream::new(
+            Dictionary::new(),
+            b"q 200 0 0 100 50 600 cm /Im1 Do Q q 1 0 0 1 100 100 cm 200 0 0 100 0 0 cm /Fm1 Do Q".to_vec(),
+        ));
+        let resources = dictionary! { "XObject" => dictionary! { "Im1" => image, "Fm1" => form } };
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 600.into(), 800.into()],
+            "Contents" => content,
+        });
+        // Resources inherited from the page tree
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+            "Resources" => resources,
+        }));
+        let catalog = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog);
+
+        let placements = image_placements(&document, 0).unwrap();
+        assert_eq!(placements.len(), 2);
+        assert_eq!(placements[0].bounds(), (50.0, 600.0, 250.0, 700.0));
+        assert_eq!((placements[0].pixels, placements[0].soft_mask), ((800, 600), true));
+        // Form matrix, then the outer cms: 10 points across in form space is 2000 on the page
+        assert_eq!(placements[1].bounds(), (2100.0, 100.0, 2200.0, 150.0));
+
+        let page = document.get_dictionary(page_id).unwrap();
+        let coverage = analyze_images(&document, page, 600.0, 800.0).unwrap();
+        assert!((coverage - 200.0 * 100.0 / (600.0 * 800.0)).abs() < 1e-6);
+    }
+}
@@ -1,9 +1,23 @@
 use anyhow::Result;
-use image::{DynamicImage, GenericImageView};
-use ort::{session::Session, value::Value, inputs};
+use image::DynamicImage;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 
+use super::layoutlm::{spans, LayoutLm, LayoutWord, Span, LAYOUTLM_MODEL_DIR};
+use super::models;
+use super::trocr::{TrOcr, PRINTED_MODEL_DIR};
+
+/// Page images come without a resolution; assume they span a letter-size page
+const ASSUMED_PAGE_WIDTH_INCHES: f32 = 8.5;
+/// Below this mean label confidence, LayoutLM's reading is replaced by the layout heuristics
+const MIN_LABEL_CONFIDENCE: f32 = 0.6;
+/// Confidence given to sections and tables the heuristics find
+const HEURISTIC_CONFIDENCE: f32 = 0.5;
+/// A line on its own this much taller than the page's median line is a header
+const HEADER_HEIGHT_RATIO: f32 = 1.3;
+/// Consecutive rows of two or more cells needed for a table
+const MIN_TABLE_ROWS: usize = 3;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractedText {
     pub text: String,
@@ -18,18 +32,26 @@ pub struct DocumentSection {
     pub confidence: f32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableData {
+    /// Cell text, row by row
+    pub rows: Vec<Vec<String>>,
+    pub bbox: Option<[f32; 4]>,
+    pub confidence: f32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessedDocument {
     pub extracted_text: Vec<ExtractedText>,
     pub sections: Vec<DocumentSection>,
+    pub tables: Vec<TableData>,
     pub metadata: HashMap<String, String>,
     pub processing_time_ms: u64,
 }
 
 pub struct DocumentProcessor {
-    trocr_encoder: Option<Session>,
-    trocr_decoder: Option<Session>,
-    layoutlm: Option<Session>,
+    trocr: Option<TrOcr>,
+    layoutlm: Option<LayoutLm>,
     initialized: bool,
 }
 
@@ -38,8 +60,7 @@ impl DocumentProcessor {
         let _ = ort::init();
         
         let mut processor = Self {
-            trocr_encoder: None,
-            trocr_decoder: None,
+            trocr: None,
             layoutlm: None,
             initialized: false,
         };
@@ -53,36 +74,18 @@ impl DocumentProcessor {
             return Ok(());
         }
         
-        // Load TrOCR models
-        if std::path::Path::new("models/trocr_encoder.onnx").exists() {
-            self.trocr_encoder = Some(
-                Session::builder()?
-                    .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
-                    .with_intra_threads(4)?
-                    .commit_from_file("models/trocr_encoder.onnx")?
-            );
-            println!("✅ TrOCR Encoder loaded");
-        }
-        
-        if std::path::Path::new("models/trocr.onnx").exists() {
-            self.trocr_decoder = Some(
-                Session::builder()?
-                    .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
-                    .with_intra_threads(4)?
-                    .commit_from_file("models/trocr.onnx")?
-            );
-            println!("✅ TrOCR Decoder loaded");
+        // Load TrOCR (encoder, decoder and tokenizer in one directory)
+        let trocr_dir = models::locate(PRINTED_MODEL_DIR);
+        if TrOcr::available(&trocr_dir) {
+            self.trocr = Some(TrOcr::load(&trocr_dir)?);
+            tracing::info!("✅ TrOCR loaded from {}", trocr_dir.display());
         }
         
-        // Load LayoutLM
-        if std::path::Path::new("models/layoutlm.onnx").exists() {
-            self.layoutlm = Some(
-                Session::builder()?
-                    .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
-                    .with_intra_threads(4)?
-                    .commit_from_file("models/layoutlm.onnx")?
-            );
-            println!("✅ LayoutLMv3 loaded");
+        // Load LayoutLM (model, tokenizer and label names in one directory)
+        let layoutlm_dir = models::locate(LAYOUTLM_MODEL_DIR);
+        if LayoutLm::available(&layoutlm_dir) {
+            self.layoutlm = Some(LayoutLm::load(&layoutlm_dir)?);
+            tracing::info!("✅ LayoutLM loaded from {}", layoutlm_dir.display());
         }
         
         self.initialized = true;
@@ -93,118 +96,247 @@ impl DocumentProcessor {
         let start = std::time::Instant::now();
         
         // Extract text with TrOCR
-        let extracted_text = if self.trocr_encoder.is_some() {
+        let extracted_text = if self.trocr.is_some() {
             self.extract_text_trocr(image).await?
         } else {
             vec![]
         };
         
-        // Analyze structure with LayoutLM
-        let sections = if self.layoutlm.is_some() {
-            self.analyze_structure_layoutlm(image, &extracted_text).await?
-        } else {
-            vec![]
-        };
+        // Analyze structure with LayoutLM, or the layout heuristics
+        let (sections, tables, structure) = self.analyze_structure(image, &extracted_text).await?;
         
         // Create metadata
         let mut metadata = HashMap::new();
         metadata.insert("width".to_string(), image.width().to_string());
         metadata.insert("height".to_string(), image.height().to_string());
-        metadata.insert("has_trocr".to_string(), self.trocr_encoder.is_some().to_string());
+        metadata.insert("has_trocr".to_string(), self.trocr.is_some().to_string());
         metadata.insert("has_layoutlm".to_string(), self.layoutlm.is_some().to_string());
+        metadata.insert("structure".to_string(), structure.to_string());
         
         Ok(ProcessedDocument {
             extracted_text,
             sections,
+            tables,
             metadata,
             processing_time_ms: start.elapsed().as_millis() as u64,
         })
     }
     
     async fn extract_text_trocr(&mut self, image: &DynamicImage) -> Result<Vec<ExtractedText>> {
-        let encoder = self.trocr_encoder.as_mut()
-            .ok_or_else(|| anyhow::anyhow!("TrOCR encoder not loaded"))?;
+        let trocr = self.trocr.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("TrOCR not loaded"))?;
         
-        // Resize to 384x384
-        let img = image.resize_exact(384, 384, image::imageops::FilterType::Lanczos3);
-        
-        // Convert to CHW format
-        let mut pixels = Vec::with_capacity(3 * 384 * 384);
-        for c in 0..3 {
-            for y in 0..384 {
-                for x in 0..384 {
-                    let pixel = img.get_pixel(x, y);
-                    let value = pixel[c] as f32 / 255.0;
-                    pixels.push(value);
-                }
-            }
-        }
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        let dpi = ((width / ASSUMED_PAGE_WIDTH_INCHES) as u32).max(1);
         
-        // Run encoder
-        let input = Value::from_array(([1_usize, 3, 384, 384], pixels.into_boxed_slice()))?;
-        let encoder_outputs = encoder.run(inputs![input])?;
-        
-        // TODO: Run decoder for actual text generation
-        // For now, return placeholder
-        Ok(vec![
-            ExtractedText {
-                text: "Document text extracted by TrOCR".to_string(),
-                confidence: 0.95,
-                bbox: Some([0.1, 0.1, 0.9, 0.2]),
-            }
-        ])
+        // One entry per line, boxes as fractions of the image
+        let lines = trocr.read_lines(image, dpi)?;
+        Ok(lines
+            .into_iter()
+            .map(|line| ExtractedText {
+                text: line.text,
+                confidence: line.confidence,
+                bbox: Some([
+                    line.region.x0 as f32 / width,
+                    line.region.y0 as f32 / height,
+                    line.region.x1 as f32 / width,
+                    line.region.y1 as f32 / height,
+                ]),
+            })
+            .collect())
     }
     
-    async fn analyze_structure_layoutlm(
+    /// Sections and tables, and which of "layoutlm" or "heuristic" found them
+    async fn analyze_structure(
         &mut self, 
         image: &DynamicImage,
         text: &[ExtractedText]
-    ) -> Result<Vec<DocumentSection>> {
-        let layoutlm = self.layoutlm.as_mut()
-            .ok_or_else(|| anyhow::anyhow!("LayoutLM not loaded"))?;
-        
-        // Resize to 224x224
-        let img = image.resize_exact(224, 224, image::imageops::FilterType::Lanczos3);
-        
-        // Convert to CHW format with normalization
-        let mut pixels = Vec::with_capacity(3 * 224 * 224);
-        for c in 0..3 {
-            for y in 0..224 {
-                for x in 0..224 {
-                    let pixel = img.get_pixel(x, y);
-                    let value = (pixel[c] as f32 / 255.0 - 0.5) / 0.5;
-                    pixels.push(value);
+    ) -> Result<(Vec<DocumentSection>, Vec<TableData>, &'static str)> {
+        if let Some(layoutlm) = self.layoutlm.as_mut() {
+            let words = split_words(text);
+            if !words.is_empty() {
+                let labels = layoutlm.label_words(image, &words)?;
+                let mean = labels.iter().map(|l| l.confidence).sum::<f32>() / labels.len() as f32;
+                if mean >= MIN_LABEL_CONFIDENCE {
+                    let (sections, tables) = labelled_structure(&words, &spans(&labels));
+                    return Ok((sections, tables, "layoutlm"));
                 }
+                tracing::debug!("[LAYOUTLM] Mean label confidence {:.2}, using layout heuristics", mean);
             }
         }
-        
-        // Prepare inputs
-        let seq_len = 512;
-        let input_ids = Value::from_array(([1_usize, seq_len], vec![101i64; seq_len].into_boxed_slice()))?;
-        let bbox = Value::from_array(([1_usize, seq_len, 4], vec![0i64; seq_len * 4].into_boxed_slice()))?;
-        let attention_mask = Value::from_array(([1_usize, seq_len], vec![1i64; seq_len].into_boxed_slice()))?;
-        let pixel_values = Value::from_array(([1_usize, 3, 224, 224], pixels.into_boxed_slice()))?;
-        
-        // Run LayoutLM
-        let _outputs = layoutlm.run(inputs![input_ids, bbox, attention_mask, pixel_values])?;
-        
-        // TODO: Analyze hidden states for structure
-        // For now, return placeholder
-        Ok(vec![
-            DocumentSection {
-                section_type: "header".to_string(),
-                content: text.to_vec(),
-                confidence: 0.92,
-            }
-        ])
+        let (sections, tables) = heuristic_structure(text);
+        Ok((sections, tables, "heuristic"))
     }
     
     pub fn get_status(&self) -> HashMap<String, bool> {
         let mut status = HashMap::new();
         status.insert("initialized".to_string(), self.initialized);
-        status.insert("trocr_encoder".to_string(), self.trocr_encoder.is_some());
-        status.insert("trocr_decoder".to_string(), self.trocr_decoder.is_some());
+        // Encoder and decoder load together now; both keys kept for existing callers
+        status.insert("trocr_encoder".to_string(), self.trocr.is_some());
+        status.insert("trocr_decoder".to_string(), self.trocr.is_some());
         status.insert("layoutlm".to_string(), self.layoutlm.is_some());
         status
     }
 }
+
+/// Lines split into words, each given a share of the line's box in proportion
+/// to its position in the text. Lines without a box are skipped.
+fn split_words(lines: &[ExtractedText]) -> Vec<LayoutWord> {
+    let mut words = Vec::new();
+    for line in lines {
+        let Some([x0, y0, x1, y1]) = line.bbox else { continue };
+        let char_width = (x1 - x0) / line.text.chars().count().max(1) as f32;
+        let mut column = 0;
+        for piece in line.text.split(' ') {
+            let length = piece.chars().count();
+            if !piece.is_empty() {
+                let left = x0 + column as f32 * char_width;
+                words.push(LayoutWord { text: piece.to_string(), bbox: [left, y0, left + length as f32 * char_width, y1] });
+            }
+            column += length + 1;
+        }
+    }
+    words
+}
+
+/// LayoutLM spans as sections, with TABLE spans laid out as tables
+fn labelled_structure(words: &[LayoutWord], spans: &[Span]) -> (Vec<DocumentSection>, Vec<TableData>) {
+    let mut sections = Vec::new();
+    let mut tables = Vec::new();
+    for span in spans {
+        let entries: Vec<ExtractedText> = words[span.start..span.end]
+            .iter()
+            .map(|w| ExtractedText { text: w.text.clone(), confidence: span.confidence, bbox: Some(w.bbox) })
+            .collect();
+        let bbox = union(entries.iter());
+        if span.kind.eq_ignore_ascii_case("table") {
+            let rows = rows(&entries).iter().map(|row| cells(row)).collect();
+            tables.push(TableData { rows, bbox, confidence: span.confidence });
+        } else {
+            let text = entries.iter().map(|e| e.text.as_str()).collect::<Vec<_>>().join(" ");
+            sections.push(DocumentSection {
+                section_type: span.kind.to_lowercase(),
+                content: vec![ExtractedText { text, confidence: span.confidence, bbox }],
+                confidence: span.confidence,
+            });
+        }
+    }
+    (sections, tables)
+}
+
+/// Structure from layout alone: runs of multi-cell rows are tables, a tall line
+/// on its own is a header, and lines less than a line apart form paragraphs
+fn heuristic_structure(lines: &[ExtractedText]) -> (Vec<DocumentSection>, Vec<TableData>) {
+    let mut heights: Vec<f32> = lines.iter().filter_map(|l| l.bbox).map(|b| b[3] - b[1]).collect();
+    heights.sort_by(f32::total_cmp);
+    let median = heights.get(heights.len() / 2).copied().unwrap_or(0.0);
+
+    let rows = rows(lines);
+    let mut sections: Vec<DocumentSection> = Vec::new();
+    let mut tables = Vec::new();
+    let mut index = 0;
+    while index < rows.len() {
+        let run = rows[index..].iter().take_while(|row| cells(row).len() >= 2).count();
+        if run >= MIN_TABLE_ROWS {
+            let table = &rows[index..index + run];
+            tables.push(TableData {
+                rows: table.iter().map(|row| cells(row)).collect(),
+                bbox: union(table.iter().flatten().copied()),
+                confidence: HEURISTIC_CONFIDENCE,
+            });
+            index += run;
+            continue;
+        }
+
+        let row = &rows[index];
+        let [_, top, _, bottom] = union(row.iter().copied()).unwrap_or_default();
+        let kind = if row.len() == 1 && bottom - top >= median * HEADER_HEIGHT_RATIO { "header" } else { "paragraph" };
+        let content = row.iter().map(|&entry| entry.clone());
+        match sections.last_mut() {
+            Some(last) if kind == "paragraph" && last.section_type == kind
+                && top - union(last.content.iter()).map_or(f32::MIN, |b| b[3]) < median => last.content.extend(content),
+            _ => sections.push(DocumentSection { section_type: kind.to_string(), content: content.collect(), confidence: HEURISTIC_CONFIDENCE }),
+        }
+        index += 1;
+    }
+    (sections, tables)
+}
+
+/// Entries with a box grouped into rows top to bottom (vertical centres within
+/// half a row's height), each row left to right
+fn rows(entries: &[ExtractedText]) -> Vec<Vec<&ExtractedText>> {
+    let mut boxed: Vec<(&ExtractedText, [f32; 4])> = entries.iter().filter_map(|e| e.bbox.map(|b| (e, b))).collect();
+    boxed.sort_by(|a, b| (a.1[1] + a.1[3]).total_cmp(&(b.1[1] + b.1[3])));
+    let mut rows: Vec<Vec<(&ExtractedText, [f32; 4])>> = Vec::new();
+    for (entry, bbox) in boxed {
+        let centre = (bbox[1] + bbox[3]) / 2.0;
+        match rows.last_mut() {
+            Some(row) if (centre - (row[0].1[1] + row[0].1[3]) / 2.0).abs() < (row[0].1[3] - row[0].1[1]) / 2.0 => row.push((entry, bbox)),
+            _ => rows.push(vec![(entry, bbox)]),
+        }
+    }
+    rows.into_iter()
+        .map(|mut row| {
+            row.sort_by(|a, b| a.1[0].total_cmp(&b.1[0]));
+            row.into_iter().map(|(entry, _)| entry).collect()
+        })
+        .collect()
+}
+
+/// A row's text split into cells wherever entries are more than a row height apart
+fn cells(row: &[&ExtractedText]) -> Vec<String> {
+    let mut cells: Vec<String> = Vec::new();
+    let mut previous: Option<[f32; 4]> = None;
+    for entry in row {
+        let Some(bbox) = entry.bbox else { continue };
+        match previous {
+            Some(p) if bbox[0] - p[2] <= bbox[3] - bbox[1] => {
+                if let Some(cell) = cells.last_mut() {
+                    cell.push(' ');
+                    cell.push_str(&entry.text);
+                }
+            }
+            _ => cells.push(entry.text.clone()),
+        }
+        previous = Some(bbox);
+    }
+    cells
+}
+
+/// Smallest box around every entry that has one
+fn union<'a>(entries: impl Iterator<Item = &'a ExtractedText>) -> Option<[f32; 4]> {
+    entries.filter_map(|e| e.bbox).reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, x0: f32, y0: f32, x1: f32, y1: f32) -> ExtractedText {
+        ExtractedText { text: text.to_string(), confidence: 0.9, bbox: Some([x0, y0, x1, y1]) }
+    }
+
+    #[test]
+    fn test_heuristics_find_header_paragraph_and_table() {
+        let lines = vec![
+            line("Quarterly Report", 0.1, 0.05, 0.6, 0.09),
+            line("Sales rose in every region", 0.1, 0.12, 0.8, 0.14),
+            line("except the north.", 0.1, 0.145, 0.4, 0.165),
+            line("Region", 0.1, 0.30, 0.2, 0.32),
+            line("Total", 0.5, 0.30, 0.6, 0.32),
+            line("East", 0.1, 0.33, 0.2, 0.35),
+            line("120", 0.5, 0.33, 0.6, 0.35),
+            line("West", 0.1, 0.36, 0.2, 0.38),
+            line("95", 0.5, 0.36, 0.6, 0.38),
+        ];
+        let (sections, tables) = heuristic_structure(&lines);
+        let kinds: Vec<(&str, usize)> = sections.iter().map(|s| (s.section_type.as_str(), s.content.len())).collect();
+        assert_eq!(kinds, vec![("header", 1), ("paragraph", 2)]);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].rows, vec![vec!["Region", "Total"], vec!["East", "120"], vec!["West", "95"]]);
+
+        let words = split_words(&lines[1..2]);
+        assert_eq!(words.len(), 5);
+        assert_eq!(words[0].text, "Sales");
+    }
+}
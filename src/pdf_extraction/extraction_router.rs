@@ -1,21 +1,55 @@
-// Simplified PDF text extraction using pdftotext
+// PDF text extraction routing
 //
-// This module has been simplified to use only the pdftotext utility for all PDF text extraction.
-// Previous implementations using OCR (TrOCR) and LayoutLM have been removed.
-// 
-// The pdftotext utility is called with the -layout flag to preserve formatting:
-// pdftotext -f [page] -l [page] -layout [pdf_path] -
-//
-// This provides reliable text extraction that works well for most PDFs.
+// The actual backends live in extractors.rs behind the Extractor trait. The router
+// keeps the original entry points and walks the default registry in priority order:
+// pdftotext for pages with a text layer, tesseract for scanned pages, lopdf last.
 
 use anyhow::Result;
 use std::path::Path;
 use super::document_analyzer::PageFingerprint;
+use super::extractors::ExtractorRegistry;
+use super::orientation::Correction;
+use super::quality::QualityChecker;
+use once_cell::sync::Lazy;
 
-/// Extraction method enum - now only contains PdfToText
+/// Extraction method enum - one variant per registered extractor backend
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExtractionMethod {
-    PdfToText,  // Only pdftotext for all extraction
+    PdfToText,  // pdftotext -layout, the default for pages with a text layer
+    Tesseract,  // OCR of the rendered page for scanned pages
+    Lopdf,      // Pure-Rust content stream text, last-resort fallback
+    Hybrid,     // Native text and OCR merged word by word
+    Cloud,      // Azure, Google or Textract OCR, opt-in per run
+    Handwriting, // Hybrid plus TrOCR on handwritten fields
+    TrOcr,      // TrOCR on every line of the rendered page
+}
+
+impl ExtractionMethod {
+    /// Engine name as recorded in run history
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExtractionMethod::PdfToText => "pdftotext",
+            ExtractionMethod::Tesseract => "tesseract",
+            ExtractionMethod::Lopdf => "lopdf",
+            ExtractionMethod::Hybrid => "hybrid",
+            ExtractionMethod::Cloud => "cloud",
+            ExtractionMethod::Handwriting => "handwriting",
+            ExtractionMethod::TrOcr => "trocr",
+        }
+    }
+    
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pdftotext" => Some(ExtractionMethod::PdfToText),
+            "tesseract" => Some(ExtractionMethod::Tesseract),
+            "lopdf" => Some(ExtractionMethod::Lopdf),
+            "hybrid" => Some(ExtractionMethod::Hybrid),
+            "cloud" => Some(ExtractionMethod::Cloud),
+            "handwriting" => Some(ExtractionMethod::Handwriting),
+            "trocr" => Some(ExtractionMethod::TrOcr),
+            _ => None,
+        }
+    }
 }
 
 /// Extraction result with quality metrics
@@ -25,6 +59,8 @@ pub struct ExtractionResult {
     pub method: ExtractionMethod,
     pub quality_score: f32,
     pub extraction_time_ms: u64,
+    /// Rotation and skew corrected before OCR, when there was any
+    pub correction: Option<Correction>,
 }
 
 impl ExtractionResult {
@@ -35,174 +71,59 @@ impl ExtractionResult {
             method,
             quality_score,
             extraction_time_ms: 0,
+            correction: None,
         }
     }
 }
 
-/// Router for determining extraction strategy - simplified
+/// Router for determining extraction strategy - walks the extractor registry
 pub struct ExtractionRouter;
 
 impl ExtractionRouter {
-    /// Determine extraction strategy - always returns PdfToText
-    pub fn determine_strategy(_fingerprint: &PageFingerprint) -> ExtractionMethod {
-        ExtractionMethod::PdfToText
+    /// First backend in the default registry that supports the page
+    pub fn determine_strategy(fingerprint: &PageFingerprint) -> ExtractionMethod {
+        ExtractorRegistry::default()
+            .candidates(fingerprint)
+            .first()
+            .and_then(|e| ExtractionMethod::from_name(e.name()))
+            .unwrap_or(ExtractionMethod::PdfToText)
     }
     
-    /// Get fallback chain - not needed anymore since we only have one method
-    pub fn get_fallback_chain(_primary: &ExtractionMethod) -> Vec<ExtractionMethod> {
-        vec![]
+    /// Backends tried after the primary one, in priority order
+    pub fn get_fallback_chain(primary: &ExtractionMethod) -> Vec<ExtractionMethod> {
+        ExtractorRegistry::default()
+            .names()
+            .into_iter()
+            .filter_map(ExtractionMethod::from_name)
+            .filter(|method| method != primary)
+            .collect()
     }
     
-    /// Execute extraction with pdftotext (synchronous version for UI)
+    /// Extract with the default registry in auto mode (synchronous version for UI)
     pub fn extract_with_fallback_sync(
         pdf_path: &Path,
         page_index: usize,
-        _fingerprint: &PageFingerprint,
+        fingerprint: &PageFingerprint,
     ) -> Result<ExtractionResult> {
-        Self::execute_extraction_sync(pdf_path, page_index, &ExtractionMethod::PdfToText)
+        ExtractorRegistry::default().extract_auto(pdf_path, page_index, fingerprint)
     }
     
-    /// Execute extraction with pdftotext (async version)
+    /// Async wrapper - the backends shell out, so this simply runs the sync path
     pub async fn extract_with_fallback(
         pdf_path: &Path,
         page_index: usize,
-        _fingerprint: &PageFingerprint,
-    ) -> Result<ExtractionResult> {
-        Self::execute_extraction(pdf_path, page_index, &ExtractionMethod::PdfToText).await
-    }
-    
-    /// Execute extraction with pdftotext (synchronous)
-    fn execute_extraction_sync(
-        pdf_path: &Path,
-        page_index: usize,
-        _method: &ExtractionMethod,
+        fingerprint: &PageFingerprint,
     ) -> Result<ExtractionResult> {
-        use std::time::Instant;
-        use std::process::Command;
-        let start = Instant::now();
-        
-        // Always use pdftotext command
-        let output = Command::new("pdftotext")
-            .args(&[
-                "-f", &(page_index + 1).to_string(),
-                "-l", &(page_index + 1).to_string(),
-                "-layout",
-                pdf_path.to_str().unwrap(),
-                "-"
-            ])
-            .output()?;
-            
-        let text = if output.status.success() {
-            String::from_utf8_lossy(&output.stdout).to_string()
-        } else {
-            anyhow::bail!("pdftotext failed");
-        };
-        
-        let mut result = ExtractionResult::new(text, ExtractionMethod::PdfToText);
-        result.extraction_time_ms = start.elapsed().as_millis() as u64;
-        
-        Ok(result)
-    }
-    
-    /// Execute extraction with pdftotext (async)
-    async fn execute_extraction(
-        pdf_path: &Path,
-        page_index: usize,
-        _method: &ExtractionMethod,
-    ) -> Result<ExtractionResult> {
-        use std::time::Instant;
-        use std::process::Command;
-        let start = Instant::now();
-        
-        // Always use pdftotext command
-        let output = Command::new("pdftotext")
-            .args(&[
-                "-f", &(page_index + 1).to_string(),
-                "-l", &(page_index + 1).to_string(),
-                "-layout",
-                pdf_path.to_str().unwrap(),
-                "-"
-            ])
-            .output()?;
-            
-        let text = if output.status.success() {
-            String::from_utf8_lossy(&output.stdout).to_string()
-        } else {
-            anyhow::bail!("pdftotext failed");
-        };
-        
-        let mut result = ExtractionResult::new(text, ExtractionMethod::PdfToText);
-        result.extraction_time_ms = start.elapsed().as_millis() as u64;
-        
-        Ok(result)
+        Self::extract_with_fallback_sync(pdf_path, page_index, fingerprint)
     }
 }
 
-/// Calculate quality score for extracted text
+/// Quality score for extracted text with the default QualityChecker thresholds
 pub fn calculate_quality_score(text: &str) -> f32 {
-    if text.is_empty() {
-        return 0.0;
-    }
-    
-    let checks = [
-        text.len() > 10,                          // Has content
-        text.contains(". "),                      // Has sentences
-        !is_mostly_gibberish(text),              // Not gibberish
-        has_dictionary_words(text),              // Has real words
-        has_reasonable_whitespace(text),         // Proper formatting
-    ];
-    
-    let passed = checks.iter().filter(|&&x| x).count() as f32;
-    passed / checks.len() as f32
+    static CHECKER: Lazy<QualityChecker> = Lazy::new(QualityChecker::default);
+    CHECKER.score(text)
 }
 
-/// Check if text is mostly gibberish
-fn is_mostly_gibberish(text: &str) -> bool {
-    if text.is_empty() {
-        return true;
-    }
-    
-    // Check vowel ratio
-    let vowel_count = text.chars()
-        .filter(|c| "aeiouAEIOU".contains(*c))
-        .count();
-    let vowel_ratio = vowel_count as f32 / text.len() as f32;
-    
-    vowel_ratio < 0.1 || vowel_ratio > 0.6
-}
-
-/// Check if text has dictionary words
-fn has_dictionary_words(text: &str) -> bool {
-    let words: Vec<&str> = text.split_whitespace().collect();
-    if words.is_empty() {
-        return false;
-    }
-    
-    // Simple check: words should be mostly alphabetic and reasonable length
-    let valid_words = words.iter()
-        .filter(|w| w.len() >= 2 && w.len() <= 20)
-        .filter(|w| {
-            let alpha_ratio = w.chars().filter(|c| c.is_alphabetic()).count() as f32 / w.len() as f32;
-            alpha_ratio > 0.7
-        })
-        .count();
-    
-    valid_words as f32 / words.len() as f32 > 0.5
-}
-
-/// Check if text has reasonable whitespace
-fn has_reasonable_whitespace(text: &str) -> bool {
-    if text.is_empty() {
-        return false;
-    }
-    
-    let whitespace_count = text.chars().filter(|c| c.is_whitespace()).count();
-    let whitespace_ratio = whitespace_count as f32 / text.len() as f32;
-    
-    whitespace_ratio > 0.05 && whitespace_ratio < 0.5
-}
-
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -216,9 +137,11 @@ mod tests {
     
     #[test]
     fn test_strategy_selection() {
-        let fingerprint = PageFingerprint::new();
+        let mut fingerprint = PageFingerprint::new();
         
-        // Always returns PdfToText now
+        // Pages with a text layer go to pdftotext, image-only pages to OCR
         assert_eq!(ExtractionRouter::determine_strategy(&fingerprint), ExtractionMethod::PdfToText);
+        fingerprint.image_coverage = 0.9;
+        assert_eq!(ExtractionRouter::determine_strategy(&fingerprint), ExtractionMethod::Tesseract);
     }
 }
\ No newline at end of file
@@ -0,0 +1,407 @@
+// Pluggable extraction backends
+//
+// Every backend implements Extractor. The registry holds them in priority order;
+// auto mode walks it, skipping backends that don't support the page fingerprint
+// and falling through to the next one on errors or empty output.
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::page_images;
+use crate::temp_files::{self, scratch_dir};
+use super::document_analyzer::{page_rotation, DocumentAnalyzer, PageFingerprint};
+use super::extraction_router::{ExtractionMethod, ExtractionResult};
+use super::hybrid::HybridExtractor;
+use super::handwriting::HandwritingExtractor;
+use super::trocr::TrOcrExtractor;
+use super::orientation::correct_page_png;
+use super::multilingual::{reread_by_language, DEFAULT_MODEL};
+
+/// Pages with at least this much image area and almost no text layer are treated as scanned
+const SCANNED_IMAGE_COVERAGE: f32 = 0.5;
+const SCANNED_MAX_CHARS: usize = 50;
+
+/// Resolution pages are rendered at for OCR
+pub const OCR_DPI: u32 = 300;
+
+/// Engine name that walks the registry per page instead of forcing a backend
+pub const AUTO_ENGINE: &str = "auto";
+
+pub trait Extractor: Send + Sync {
+    /// Engine name used by --engine, extraction.toml and run history
+    fn name(&self) -> &'static str;
+
+    /// Whether this backend is a sensible choice for the page
+    fn supports(&self, fingerprint: &PageFingerprint) -> bool;
+
+    fn extract(&self, pdf_path: &Path, page_index: usize) -> Result<ExtractionResult>;
+
+    /// What this backend needs that isn't installed (a tool, a model), or None if it can run
+    fn missing(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Which of `tools` aren't on PATH, as a `missing()` answer
+pub fn missing_tools(tools: &[&str]) -> Option<String> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let absent: Vec<&str> = tools
+        .iter()
+        .copied()
+        .filter(|tool| !std::env::split_paths(&path).any(|dir| dir.join(format!("{}{}", tool, std::env::consts::EXE_SUFFIX)).is_file()))
+        .collect();
+    (!absent.is_empty()).then(|| format!("{} not on PATH", absent.join(", ")))
+}
+
+/// Mostly image and hardly any text - a scan, or a page whose text layer is missing
+pub fn looks_scanned(fingerprint: &PageFingerprint) -> bool {
+    fingerprint.image_coverage >= SCANNED_IMAGE_COVERAGE && fingerprint.char_count < SCANNED_MAX_CHARS
+}
+
+fn timed(method: ExtractionMethod, start: Instant, text: String) -> ExtractionResult {
+    let mut result = ExtractionResult::new(text, method);
+    result.extraction_time_ms = start.elapsed().as_millis() as u64;
+    result
+}
+
+/// pdftotext -layout - fast and layout-preserving for born-digital pages
+pub struct PdfToTextExtractor;
+
+impl Extractor for PdfToTextExtractor {
+    fn name(&self) -> &'static str {
+        "pdftotext"
+    }
+
+    fn supports(&self, fingerprint: &PageFingerprint) -> bool {
+        !looks_scanned(fingerprint)
+    }
+
+    fn extract(&self, pdf_path: &Path, page_index: usize) -> Result<ExtractionResult> {
+        let start = Instant::now();
+        let page = (page_index + 1).to_string();
+        let output = Command::new("pdftotext")
+            .args(["-f", &page, "-l", &page, "-layout"])
+            .arg(pdf_path)
+            .arg("-")
+            .output()?;
+
+        if !output.status.success() {
+            return Err(anyhow!("pdftotext failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        let text = String::from_utf8_lossy(&output.stdout).to_string();
+        Ok(timed(ExtractionMethod::PdfToText, start, text))
+    }
+
+    fn missing(&self) -> Option<String> {
+        missing_tools(&["pdftotext"])
+    }
+}
+
+/// Tesseract OCR on the page rendered by pdftoppm - for scanned pages
+pub struct TesseractExtractor;
+
+impl Extractor for TesseractExtractor {
+    fn name(&self) -> &'static str {
+        "tesseract"
+    }
+
+    fn supports(&self, fingerprint: &PageFingerprint) -> bool {
+        looks_scanned(fingerprint)
+    }
+
+    fn extract(&self, pdf_path: &Path, page_index: usize) -> Result<ExtractionResult> {
+        let start = Instant::now();
+        let temp_dir = scratch_dir()?;
+        let image_path = render_page_png(pdf_path, page_index, OCR_DPI, temp_dir.path())?;
+        // The renderer applies /Rotate; a scan turned inside the page is fixed here
+        let pdf_rotate = lopdf::Document::load(pdf_path).ok().and_then(|d| page_rotation(&d, page_index).ok()).unwrap_or(0);
+        let correction = correct_page_png(&image_path, pdf_rotate)?;
+        if correction.rotated != 0 || correction.deskewed != 0.0 {
+            tracing::debug!("[OCR] Page {}: turned {}°, deskewed {:.2}°", page_index + 1, correction.rotated, correction.deskewed);
+        }
+        let stem = pdf_path.file_stem().unwrap_or_default().to_string_lossy();
+        if let Some(copy) = temp_files::debug_artifact(&format!("ocr-{}-p{}.png", stem, page_index + 1)) {
+            let _ = std::fs::copy(&image_path, copy);
+        }
+
+        let output = Command::new("tesseract")
+            .arg(&image_path)
+            .args(["stdout", "-c", "preserve_interword_spaces=1"])
+            .output()
+            .map_err(|e| anyhow!("tesseract not available: {}", e))?;
+
+        if !output.status.success() {
+            return Err(anyhow!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+        // Blocks in another language get a second read with their own model
+        match reread_by_language(&image_path, DEFAULT_MODEL) {
+            Ok(Some(reread)) => text = reread,
+            Ok(None) => {}
+            Err(e) => tracing::debug!("[OCR] Page {}: no per-language re-read: {}", page_index + 1, e),
+        }
+        let mut result = timed(ExtractionMethod::Tesseract, start, text);
+        result.correction = (!correction.is_none()).then_some(correction);
+        Ok(result)
+    }
+
+    fn missing(&self) -> Option<String> {
+        missing_tools(&["pdftoppm", "tesseract"])
+    }
+}
+
+/// lopdf's own text extraction - no external tools, so it always works as a last resort
+pub struct LopdfExtractor;
+
+impl Extractor for LopdfExtractor {
+    fn name(&self) -> &'static str {
+        "lopdf"
+    }
+
+    fn supports(&self, fingerprint: &PageFingerprint) -> bool {
+        !looks_scanned(fingerprint)
+    }
+
+    fn extract(&self, pdf_path: &Path, page_index: usize) -> Result<ExtractionResult> {
+        let start = Instant::now();
+        let document = lopdf::Document::load(pdf_path)?;
+        let text = document.extract_text(&[(page_index + 1) as u32])?;
+        Ok(timed(ExtractionMethod::Lopdf, start, text))
+    }
+}
+
+/// Render one page to PNG with pdftoppm, returning the image path inside `dir`.
+/// Renders come from the page image cache when the page was rendered at `dpi` before.
+pub fn render_page_png(pdf_path: &Path, page_index: usize, dpi: u32, dir: &Path) -> Result<PathBuf> {
+    let prefix = dir.join(format!("page-{}", page_index + 1));
+    let png_path = prefix.with_extension("png");
+    let page = (page_index + 1).to_string();
+    let png = page_images::cached_png(pdf_path, page_index, &format!("{}dpi", dpi), || {
+        let output = Command::new("pdftoppm")
+            .args(["-png", "-singlefile", "-r", &dpi.to_string(), "-f", &page, "-l", &page])
+            .arg(pdf_path)
+            .arg(&prefix)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(anyhow!("pdftoppm failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(std::fs::read(&png_path)?)
+    })?;
+    if !png_path.exists() {
+        std::fs::write(&png_path, png)?;
+    }
+    Ok(png_path)
+}
+
+/// Extractors in priority order
+pub struct ExtractorRegistry {
+    extractors: Vec<Box<dyn Extractor>>,
+}
+
+impl Default for ExtractorRegistry {
+    fn default() -> Self {
+        Self {
+            extractors: vec![
+                Box::new(PdfToTextExtractor),
+                Box::new(TesseractExtractor),
+                Box::new(LopdfExtractor),
+                Box::new(HybridExtractor),
+                Box::new(HandwritingExtractor::default()),
+                Box::new(TrOcrExtractor::default()),
+            ],
+        }
+    }
+}
+
+impl ExtractorRegistry {
+    /// Default registry reordered so the named engines come first, in the given order.
+    /// Unknown names are returned so callers can warn about them.
+    pub fn with_priority(names: &[String]) -> (Self, Vec<String>) {
+        let mut remaining = Self::default().extractors;
+        let mut ordered = Vec::with_capacity(remaining.len());
+        let mut unknown = Vec::new();
+
+        for name in names {
+            match remaining.iter().position(|e| e.name() == name) {
+                Some(index) => ordered.push(remaining.remove(index)),
+                None => unknown.push(name.clone()),
+            }
+        }
+        ordered.extend(remaining);
+
+        (Self { extractors: ordered }, unknown)
+    }
+
+    pub fn register(&mut self, extractor: Box<dyn Extractor>) {
+        self.extractors.push(extractor);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.extractors.iter().map(|e| e.name()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Extractor> {
+        self.extractors.iter().find(|e| e.name() == name).map(|e| e.as_ref())
+    }
+
+    /// Backends that support the page, in priority order
+    pub fn candidates(&self, fingerprint: &PageFingerprint) -> Vec<&dyn Extractor> {
+        self.extractors
+            .iter()
+            .filter(|e| e.supports(fingerprint))
+            .map(|e| e.as_ref())
+            .collect()
+    }
+
+    /// Auto mode - first supporting backend that produces non-empty text
+    pub fn extract_auto(&self, pdf_path: &Path, page_index: usize, fingerprint: &PageFingerprint) -> Result<ExtractionResult> {
+        let mut last_error = None;
+        let mut empty_result = None;
+
+        for extractor in self.candidates(fingerprint) {
+            match extractor.extract(pdf_path, page_index) {
+                Ok(result) if !result.text.trim().is_empty() => return Ok(result),
+                Ok(result) => {
+                    empty_result.get_or_insert(result);
+                }
+                Err(e) => {
+                    tracing::debug!("[EXTRACT] {} failed on page {}: {}", extractor.name(), page_index + 1, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        // A blank page legitimately has no text - only fail if nothing ran successfully
+        match (empty_result, last_error) {
+            (Some(result), _) => Ok(result),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(anyhow!("No extractor supports page {}", page_index + 1)),
+        }
+    }
+
+    /// Forced mode (--engine) - the named backend, no fallback
+    pub fn extract_with(&self, name: &str, pdf_path: &Path, page_index: usize) -> Result<ExtractionResult> {
+        let extractor = self.get(name).ok_or_else(|| {
+            anyhow!("Unknown engine '{}' (available: {})", name, self.names().join(", "))
+        })?;
+        extractor.extract(pdf_path, page_index)
+    }
+
+    /// Extract every page, separated by form feeds. `engine` is either "auto" (walk the
+    /// registry per page fingerprint) or the name of a backend to force. Returns the
+    /// method that handled the most pages.
+    pub fn extract_document(&self, engine: &str, pdf_path: &Path, page_count: usize) -> Result<(String, ExtractionMethod)> {
+        self.extract_document_with_progress(engine, pdf_path, page_count, |_| {})
+    }
+
+    /// `extract_document`, calling `progress` with the number of pages done after each one
+    pub fn extract_document_with_progress(
+        &self,
+        engine: &str,
+        pdf_path: &Path,
+        page_count: usize,
+        progress: impl FnMut(usize),
+    ) -> Result<(String, ExtractionMethod)> {
+        Ok(combine_pages(self.extract_pages(engine, pdf_path, page_count, progress)?))
+    }
+
+    /// Every page's result in order, each timed, calling `progress` with the number
+    /// of pages done after each one
+    pub fn extract_pages(
+        &self,
+        engine: &str,
+        pdf_path: &Path,
+        page_count: usize,
+        progress: impl FnMut(usize),
+    ) -> Result<Vec<ExtractionResult>> {
+        self.extract_pages_reusing(engine, pdf_path, page_count, Vec::new(), progress)
+    }
+
+    /// As extract_pages, but a page with a result in `reuse` (by page index) keeps
+    /// it instead of being extracted again
+    pub fn extract_pages_reusing(
+        &self,
+        engine: &str,
+        pdf_path: &Path,
+        page_count: usize,
+        mut reuse: Vec<Option<ExtractionResult>>,
+        mut progress: impl FnMut(usize),
+    ) -> Result<Vec<ExtractionResult>> {
+        let mut results = Vec::with_capacity(page_count);
+        let analyzer = match engine {
+            AUTO_ENGINE => Some((lopdf::Document::load(pdf_path)?, DocumentAnalyzer::new()?)),
+            _ => None,
+        };
+
+        for page_index in 0..page_count {
+            if let Some(result) = reuse.get_mut(page_index).and_then(Option::take) {
+                results.push(result);
+                progress(results.len());
+                continue;
+            }
+            let started = Instant::now();
+            let mut result = match &analyzer {
+                Some((document, analyzer)) => {
+                    let fingerprint = analyzer
+                        .analyze_loaded_page(document, page_index)
+                        .unwrap_or_else(|_| PageFingerprint::new());
+                    self.extract_auto(pdf_path, page_index, &fingerprint)?
+                }
+                None => self.extract_with(engine, pdf_path, page_index)?,
+            };
+            result.extraction_time_ms = started.elapsed().as_millis() as u64;
+            results.push(result);
+            progress(results.len());
+        }
+        Ok(results)
+    }
+}
+
+/// Page results joined with form feeds, and the method that handled the most pages
+pub fn combine_pages(results: Vec<ExtractionResult>) -> (String, ExtractionMethod) {
+    let method = results
+        .iter()
+        .map(|r| &r.method)
+        .max_by_key(|m| results.iter().filter(|other| other.method == **m).count())
+        .cloned()
+        .unwrap_or(ExtractionMethod::PdfToText);
+    let pages: Vec<String> = results.into_iter().map(|r| r.text).collect();
+    (pages.join("\x0c"), method)
+}
+
+/// The external tools a method's text comes from, besides chonker8 itself
+fn method_tools(method: &ExtractionMethod) -> &'static [&'static str] {
+    match method {
+        ExtractionMethod::PdfToText => &["pdftotext"],
+        ExtractionMethod::Tesseract => &["tesseract"],
+        ExtractionMethod::Hybrid | ExtractionMethod::Handwriting => &["pdftotext", "tesseract"],
+        ExtractionMethod::Lopdf | ExtractionMethod::Cloud | ExtractionMethod::TrOcr => &[],
+    }
+}
+
+/// "pdftotext 22.02.0" from the tool's -v banner (poppler prints it to stderr), or
+/// None if the tool isn't installed
+fn tool_version(tool: &str) -> Option<String> {
+    let output = Command::new(tool).arg("-v").output().ok()?;
+    let banner = String::from_utf8_lossy(&[output.stdout, output.stderr].concat()).into_owned();
+    let version = banner.lines().next()?.split_whitespace().find(|word| word.starts_with(|c: char| c.is_ascii_digit()))?;
+    Some(format!("{} {}", tool, version))
+}
+
+/// What a method's text depends on: this chonker8 and the tools it ran, e.g.
+/// "chonker8 8.8.0, pdftotext 22.02.0". Each tool is asked once per process.
+pub fn engine_version(method: &ExtractionMethod) -> String {
+    static TOOL_VERSIONS: Lazy<Mutex<HashMap<&'static str, Option<String>>>> = Lazy::new(Default::default);
+    let mut versions = TOOL_VERSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut parts = vec![format!("chonker8 {}", env!("CARGO_PKG_VERSION"))];
+    for tool in method_tools(method) {
+        parts.extend(versions.entry(tool).or_insert_with(|| tool_version(tool)).clone());
+    }
+    parts.join(", ")
+}
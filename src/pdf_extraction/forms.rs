@@ -0,0 +1,292 @@
+// Fillable form fields (AcroForm) read from a PDF's catalog
+//
+// Fields form a tree under /AcroForm /Fields: a field's full name joins the /T of
+// every ancestor with dots, and its type, flags and value may be set on any of
+// them. A terminal field owns one or more widget annotations - the boxes on the
+// page - either as kids without a /T or merged into its own dictionary; the first
+// widget gives the field its page and position.
+use anyhow::Result;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::annotations::{decode_text_string, numbers, resolve, text_string};
+
+/// Field flags (/Ff) bits, counted from 1 as in the spec
+const READ_ONLY: i64 = 1;
+const REQUIRED: i64 = 1 << 1;
+const RADIO: i64 = 1 << 15;
+const PUSHBUTTON: i64 = 1 << 16;
+const COMBO: i64 = 1 << 17;
+
+/// Deeper field trees than this are treated as a reference cycle
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FormField {
+    /// Fully qualified name, e.g. applicant.address.city
+    pub name: String,
+    /// text, checkbox, radio, pushbutton, combo, list, signature or unknown
+    pub kind: String,
+    /// Text entered, option chosen or button state ("Off" when unchecked)
+    pub value: Option<String>,
+    /// Choices of a combo or list box, on-states of a checkbox or radio group
+    pub options: Vec<String>,
+    /// 1-based, when a widget of the field could be placed on a page
+    pub page: Option<usize>,
+    /// x0, y0, x1, y1 in PDF points, origin bottom-left as in the file
+    pub rect: Option<[f32; 4]>,
+    pub read_only: bool,
+    pub required: bool,
+}
+
+/// What a field picks up from its ancestors
+#[derive(Default, Clone)]
+struct Inherited {
+    name: String,
+    field_type: Option<String>,
+    flags: i64,
+    value: Option<Object>,
+}
+
+/// Every terminal form field, in the order of the field tree; empty when the PDF has no form
+pub fn read_form_fields(pdf_path: &Path) -> Result<Vec<FormField>> {
+    let document = Document::load(pdf_path)?;
+    form_fields(&document)
+}
+
+fn form_fields(document: &Document) -> Result<Vec<FormField>> {
+    let Some(fields) = document
+        .catalog()?
+        .get(b"AcroForm")
+        .ok()
+        .and_then(|form| resolve(document, form).as_dict().ok())
+        .and_then(|form| form.get(b"Fields").ok())
+        .and_then(|fields| resolve(document, fields).as_array().ok())
+    else {
+        return Ok(Vec::new());
+    };
+
+    // Widgets are found through their page's /Annots; /P on the widget is optional
+    let mut widget_pages: HashMap<ObjectId, usize> = HashMap::new();
+    for (page_number, page_id) in document.get_pages() {
+        let annots = document.get_dictionary(page_id)?.get(b"Annots").ok().and_then(|a| resolve(document, a).as_array().ok());
+        for annot in annots.into_iter().flatten() {
+            if let Object::Reference(id) = annot {
+                widget_pages.insert(*id, page_number as usize);
+            }
+        }
+    }
+    let page_numbers: HashMap<ObjectId, usize> = document.get_pages().into_iter().map(|(n, id)| (id, n as usize)).collect();
+
+    let mut walker = Walker { document, widget_pages, page_numbers, fields: Vec::new() };
+    for field in fields {
+        walker.walk(field, &Inherited::default(), 0);
+    }
+    Ok(walker.fields)
+}
+
+struct Walker<'a> {
+    document: &'a Document,
+    widget_pages: HashMap<ObjectId, usize>,
+    page_numbers: HashMap<ObjectId, usize>,
+    fields: Vec<FormField>,
+}
+
+impl Walker<'_> {
+    fn walk(&mut self, field: &Object, parent: &Inherited, depth: usize) {
+        let document = self.document;
+        let Ok(dict) = resolve(document, field).as_dict() else { return };
+        if depth > MAX_DEPTH {
+            return;
+        }
+
+        let mut inherited = parent.clone();
+        if let Some(partial) = text_string(document, dict, b"T") {
+            inherited.name = if parent.name.is_empty() { partial } else { format!("{}.{}", parent.name, partial) };
+        }
+        if let Ok(field_type) = dict.get(b"FT").and_then(Object::as_name_str) {
+            inherited.field_type = Some(field_type.to_string());
+        }
+        if let Ok(flags) = dict.get(b"Ff").and_then(|f| resolve(document, f).as_i64()) {
+            inherited.flags = flags;
+        }
+        if let Ok(value) = dict.get(b"V") {
+            inherited.value = Some(resolve(document, value).clone());
+        }
+
+        // Kids with a /T are child fields; kids without one are this field's widgets
+        let kids: Vec<&Object> = dict
+            .get(b"Kids")
+            .ok()
+            .and_then(|k| resolve(document, k).as_array().ok())
+            .map(|kids| kids.iter().collect())
+            .unwrap_or_default();
+        let (children, widgets): (Vec<&Object>, Vec<&Object>) =
+            kids.into_iter().partition(|kid| resolve(document, kid).as_dict().is_ok_and(|k| k.has(b"T")));
+        if !children.is_empty() {
+            for child in children {
+                self.walk(child, &inherited, depth + 1);
+            }
+            return;
+        }
+
+        let widgets = if widgets.is_empty() { vec![field] } else { widgets };
+        self.fields.push(self.terminal(dict, &inherited, &widgets));
+    }
+
+    fn terminal(&self, dict: &Dictionary, inherited: &Inherited, widgets: &[&Object]) -> FormField {
+        let document = self.document;
+        let flags = inherited.flags;
+        let kind = match inherited.field_type.as_deref() {
+            Some("Tx") => "text",
+            Some("Btn") if flags & PUSHBUTTON != 0 => "pushbutton",
+            Some("Btn") if flags & RADIO != 0 => "radio",
+            Some("Btn") => "checkbox",
+            Some("Ch") if flags & COMBO != 0 => "combo",
+            Some("Ch") => "list",
+            Some("Sig") => "signature",
+            _ => "unknown",
+        };
+
+        let options = if kind == "combo" || kind == "list" {
+            choice_options(document, dict)
+        } else if kind == "checkbox" || kind == "radio" {
+            let mut states: Vec<String> = Vec::new();
+            for widget in widgets {
+                for state in on_states(document, widget) {
+                    if !states.contains(&state) {
+                        states.push(state);
+                    }
+                }
+            }
+            states
+        } else {
+            Vec::new()
+        };
+
+        let (page, rect) = widgets
+            .iter()
+            .find_map(|widget| {
+                let widget_dict = resolve(document, widget).as_dict().ok()?;
+                let rect = numbers(document, widget_dict, b"Rect");
+                let page = match widget {
+                    Object::Reference(id) => self.widget_pages.get(id).copied(),
+                    _ => None,
+                }
+                .or_else(|| widget_dict.get(b"P").ok()?.as_reference().ok().and_then(|id| self.page_numbers.get(&id).copied()));
+                (rect.len() == 4).then(|| (page, Some([rect[0], rect[1], rect[2], rect[3]])))
+            })
+            .unwrap_or((None, None));
+
+        FormField {
+            name: inherited.name.clone(),
+            kind: kind.to_string(),
+            value: inherited.value.as_ref().and_then(|v| value_text(document, v)),
+            options,
+            page,
+            rect,
+            read_only: flags & READ_ONLY != 0,
+            required: flags & REQUIRED != 0,
+        }
+    }
+}
+
+/// A field value as text: strings decoded, names as written, multiple selections joined
+fn value_text(document: &Document, value: &Object) -> Option<String> {
+    let text = match value {
+        Object::String(bytes, _) => decode_text_string(bytes),
+        Object::Name(name) => String::from_utf8_lossy(name).to_string(),
+        Object::Array(items) => {
+            let items: Vec<String> = items.iter().filter_map(|item| value_text(document, resolve(document, item))).collect();
+            items.join(", ")
+        }
+        _ => return None,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// /Opt entries are a string each, or an [export value, display text] pair
+fn choice_options(document: &Document, dict: &Dictionary) -> Vec<String> {
+    let Some(Ok(options)) = dict.get(b"Opt").ok().map(|o| resolve(document, o).as_array()) else {
+        return Vec::new();
+    };
+    options
+        .iter()
+        .filter_map(|option| match resolve(document, option) {
+            Object::Array(pair) => pair.last().and_then(|display| value_text(document, resolve(document, display))),
+            other => value_text(document, other),
+        })
+        .collect()
+}
+
+/// Appearance states of a button widget other than Off - what checking it sets the value to
+fn on_states(document: &Document, widget: &Object) -> Vec<String> {
+    let normal = resolve(document, widget)
+        .as_dict()
+        .ok()
+        .and_then(|w| w.get(b"AP").ok())
+        .and_then(|ap| resolve(document, ap).as_dict().ok())
+        .and_then(|ap| ap.get(b"N").ok())
+        .and_then(|n| resolve(document, n).as_dict().ok());
+    normal
+        .map(|states| {
+            states
+                .iter()
+                .map(|(state, _)| String::from_utf8_lossy(state).to_string())
+                .filter(|state| state != "Off")
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, StringFormat};
+
+    #[test]
+    fn test_field_tree_names_kinds_and_pages() {
+        let mut document = Document::with_version("1.7");
+        let pages_id = document.new_object_id();
+        let page_id = document.new_object_id();
+
+        let name = |text: &str| Object::String(text.as_bytes().to_vec(), StringFormat::Literal);
+        let city = document.add_object(dictionary! {
+            "T" => name("city"), "FT" => "Tx", "V" => name("Lisbon"),
+            "Type" => "Annot", "Subtype" => "Widget", "Rect" => vec![100.into(), 700.into(), 300.into(), 720.into()],
+        });
+        let address = document.add_object(dictionary! { "T" => name("address"), "Kids" => vec![city.into()] });
+        let on = document.add_object(dictionary! {});
+        let agree = document.add_object(dictionary! {
+            "T" => name("agree"), "FT" => "Btn", "Ff" => 2, "V" => "Yes",
+            "Subtype" => "Widget", "P" => page_id, "Rect" => vec![50.into(), 50.into(), 60.into(), 60.into()],
+            "AP" => dictionary! { "N" => dictionary! { "Yes" => on, "Off" => on } },
+        });
+        let colour = document.add_object(dictionary! {
+            "T" => name("colour"), "FT" => "Ch", "Ff" => COMBO,
+            "Opt" => vec![name("Red"), Object::Array(vec![name("b"), name("Blue")])],
+        });
+
+        document.objects.insert(page_id, Object::Dictionary(dictionary! {
+            "Type" => "Page", "Parent" => pages_id, "Annots" => vec![city.into()],
+        }));
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages", "Kids" => vec![page_id.into()], "Count" => 1,
+        }));
+        let form = dictionary! { "Fields" => vec![address.into(), agree.into(), colour.into()] };
+        let catalog = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id, "AcroForm" => form });
+        document.trailer.set("Root", catalog);
+
+        let fields = form_fields(&document).unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!((fields[0].name.as_str(), fields[0].kind.as_str(), fields[0].value.as_deref()), ("address.city", "text", Some("Lisbon")));
+        assert_eq!((fields[0].page, fields[0].rect), (Some(1), Some([100.0, 700.0, 300.0, 720.0])));
+        // Page from /P when the widget isn't in the page's /Annots
+        assert_eq!((fields[1].kind.as_str(), fields[1].page, fields[1].required), ("checkbox", Some(1), true));
+        assert_eq!((fields[1].value.as_deref(), fields[1].options.clone()), (Some("Yes"), vec!["Yes".to_string()]));
+        assert_eq!((fields[2].kind.as_str(), fields[2].options.clone()), ("combo", vec!["Red".to_string(), "Blue".to_string()]));
+        assert_eq!((fields[2].value.clone(), fields[2].rect), (None, None));
+    }
+}
@@ -0,0 +1,305 @@
+// Handwriting - ink that neither the text layer nor tesseract claims, read by a
+// TrOCR model trained on handwriting
+//
+// Filled-in forms extract with blanks where the handwriting is: pdftotext has no
+// text there and tesseract, trained on print, drops it. This pass renders the
+// page, blanks out every printed word found by hybrid extraction, erases form
+// rules and box borders, and groups the ink that is left into line-sized
+// regions. Each region is cropped and read with the handwriting model
+// (models/trocr-handwritten, see trocr.rs). Its words are marked as handwriting
+// with the model's confidence, which runs lower than for print even when the
+// reading is right - hence a lower default threshold.
+use anyhow::{anyhow, Result};
+use image::GrayImage;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::temp_files::scratch_dir;
+use super::bbox::BoxWord;
+use super::document_analyzer::PageFingerprint;
+use super::extraction_router::{ExtractionMethod, ExtractionResult};
+use super::extractors::{missing_tools, render_page_png, Extractor, OCR_DPI};
+use super::hybrid::{hybrid_page, in_reading_order, HybridPage, MergedWord, Provenance};
+use super::models;
+use super::trocr::TrOcr;
+
+/// Under the models directory (see models.rs)
+pub const HANDWRITING_MODEL_DIR: &str = "trocr-handwritten";
+pub const HANDWRITING_ENGINE: &str = "handwriting";
+
+/// Readings below this are dropped. Printed OCR is trusted far less at this
+/// level, but handwriting models rarely score much above it.
+pub const DEFAULT_MIN_CONFIDENCE: f32 = 0.3;
+
+/// Darker than this counts as ink
+const INK_LEVEL: u8 = 160;
+/// Pixels of margin kept around printed words when blanking them
+const PRINTED_MARGIN: u32 = 3;
+
+/// A rectangle of the rendered page, in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl Region {
+    fn width(&self) -> u32 {
+        self.x1 - self.x0
+    }
+
+    fn height(&self) -> u32 {
+        self.y1 - self.y0
+    }
+}
+
+/// Sizes in pixels at the render resolution
+struct Scale {
+    /// Straight runs longer than this are rules or borders, not writing
+    rule: u32,
+    /// Rows closer than this belong to the same line of writing
+    line_gap: u32,
+    /// Ink closer than this across belongs to the same field
+    word_gap: u32,
+    min_height: u32,
+    max_height: u32,
+    min_width: u32,
+}
+
+impl Scale {
+    fn at(dpi: u32) -> Self {
+        let inch = |fraction: f32| (dpi as f32 * fraction) as u32;
+        Self {
+            rule: inch(0.5),
+            line_gap: inch(0.03),
+            word_gap: inch(0.3),
+            min_height: inch(0.05),
+            max_height: inch(0.8),
+            min_width: inch(0.1),
+        }
+    }
+}
+
+/// Line-sized regions of ink outside the printed words (given in pixels)
+pub fn handwriting_regions(page: &GrayImage, printed: &[Region], dpi: u32) -> Vec<Region> {
+    let (width, height) = page.dimensions();
+    let scale = Scale::at(dpi);
+    let mut ink: Vec<bool> = page.pixels().map(|p| p[0] < INK_LEVEL).collect();
+    let at = |x: u32, y: u32| (y * width + x) as usize;
+
+    for r in printed {
+        for y in r.y0.saturating_sub(PRINTED_MARGIN)..(r.y1 + PRINTED_MARGIN).min(height) {
+            for x in r.x0.saturating_sub(PRINTED_MARGIN)..(r.x1 + PRINTED_MARGIN).min(width) {
+                ink[at(x, y)] = false;
+            }
+        }
+    }
+    erase_rules(&mut ink, width, height, scale.rule);
+
+    let mut regions = Vec::new();
+    let row_ink: Vec<u32> = (0..height).map(|y| (0..width).filter(|&x| ink[at(x, y)]).count() as u32).collect();
+    for (top, bottom) in runs(&row_ink, 2, scale.line_gap) {
+        let band = bottom - top;
+        if band < scale.min_height || band > scale.max_height {
+            continue;
+        }
+        let col_ink: Vec<u32> = (0..width).map(|x| (top..bottom).filter(|&y| ink[at(x, y)]).count() as u32).collect();
+        for (left, right) in runs(&col_ink, 1, scale.word_gap) {
+            if right - left < scale.min_width {
+                continue;
+            }
+            // Tighten to the rows this field actually has ink in
+            let rows: Vec<u32> = (top..bottom).filter(|&y| (left..right).any(|x| ink[at(x, y)])).collect();
+            let (Some(&y0), Some(&y1)) = (rows.first(), rows.last()) else { continue };
+            let region = Region { x0: left, y0, x1: right, y1: y1 + 1 };
+            let area = (region.width() * region.height()) as f32;
+            let filled = (y0..=y1).flat_map(|y| (left..right).map(move |x| (x, y))).filter(|&(x, y)| ink[at(x, y)]).count() as f32;
+            // Strokes cover a small part of their box; solid marks are logos or redactions
+            if region.height() >= scale.min_height && (0.02..0.5).contains(&(filled / area)) {
+                regions.push(region);
+            }
+        }
+    }
+    regions
+}
+
+/// Clear horizontal and vertical runs of ink longer than `rule`
+fn erase_rules(ink: &mut [bool], width: u32, height: u32, rule: u32) {
+    let mut erase = vec![false; ink.len()];
+    let lines = [(width, height, true), (height, width, false)];
+    for (length, count, horizontal) in lines {
+        for line in 0..count {
+            let index = |i: u32| if horizontal { (line * width + i) as usize } else { (i * width + line) as usize };
+            let mut start = 0;
+            for i in 0..=length {
+                if i < length && ink[index(i)] {
+                    continue;
+                }
+                if i - start > rule {
+                    (start..i).for_each(|j| erase[index(j)] = true);
+                }
+                start = i + 1;
+            }
+        }
+    }
+    ink.iter_mut().zip(erase).for_each(|(cell, erased)| *cell &= !erased);
+}
+
+/// Spans of indices whose value reaches `min`, joining spans less than `gap` apart
+fn runs(values: &[u32], min: u32, gap: u32) -> Vec<(u32, u32)> {
+    let mut spans: Vec<(u32, u32)> = Vec::new();
+    for (i, &value) in values.iter().enumerate() {
+        if value < min {
+            continue;
+        }
+        let i = i as u32;
+        match spans.last_mut() {
+            Some((_, end)) if i <= *end + gap => *end = i + 1,
+            _ => spans.push((i, i + 1)),
+        }
+    }
+    spans
+}
+
+/// A recognized line split into words, each given a share of the region's width
+/// in proportion to its position in the text
+fn region_words(text: &str, region: Region, points: f32, confidence: f32) -> Vec<MergedWord> {
+    let char_width = region.width() as f32 / text.chars().count().max(1) as f32;
+    let mut words = Vec::new();
+    let mut column = 0;
+    for piece in text.split(' ') {
+        let length = piece.chars().count();
+        if !piece.is_empty() {
+            let x0 = region.x0 as f32 + column as f32 * char_width;
+            words.push(MergedWord {
+                word: BoxWord {
+                    text: piece.to_string(),
+                    x0: x0 * points,
+                    y0: region.y0 as f32 * points,
+                    x1: (x0 + length as f32 * char_width) * points,
+                    y1: region.y1 as f32 * points,
+                },
+                source: Provenance::Handwriting,
+                confidence: Some(confidence),
+            });
+        }
+        column += length + 1;
+    }
+    words
+}
+
+/// Hybrid extraction of a page (0-based) plus the handwriting around it
+pub fn handwriting_page(pdf_path: &Path, page_index: usize, model: &mut TrOcr, min_confidence: f32) -> Result<HybridPage> {
+    let mut page = hybrid_page(pdf_path, page_index)?;
+    let temp_dir = scratch_dir()?;
+    let image = image::open(render_page_png(pdf_path, page_index, OCR_DPI, temp_dir.path())?)?;
+    let points = 72.0 / OCR_DPI as f32;
+
+    let printed: Vec<Region> = page
+        .words
+        .iter()
+        .map(|w| Region {
+            x0: (w.word.x0 / points) as u32,
+            y0: (w.word.y0 / points) as u32,
+            x1: (w.word.x1 / points).ceil() as u32,
+            y1: (w.word.y1 / points).ceil() as u32,
+        })
+        .collect();
+
+    let mut words = std::mem::take(&mut page.words);
+    for region in handwriting_regions(&image.to_luma8(), &printed, OCR_DPI) {
+        let crop = image.crop_imm(region.x0, region.y0, region.width(), region.height());
+        let (text, confidence) = model.recognize(&crop)?;
+        if text.is_empty() || confidence < min_confidence {
+            tracing::debug!("[HANDWRITING] Page {}: dropped {:?} at {:.0},{:.0} (confidence {:.2})",
+                page_index + 1, text, region.x0 as f32 * points, region.y0 as f32 * points, confidence);
+            continue;
+        }
+        words.extend(region_words(&text, region, points, confidence));
+    }
+    page.words = in_reading_order(words);
+    Ok(page)
+}
+
+/// Hybrid extraction plus handwriting recognition. Forced only (--engine handwriting):
+/// it runs pdftotext, tesseract and a TrOCR pass per field.
+pub struct HandwritingExtractor {
+    model_dir: PathBuf,
+    model: Mutex<Option<TrOcr>>,
+}
+
+impl Default for HandwritingExtractor {
+    fn default() -> Self {
+        Self { model_dir: models::locate(HANDWRITING_MODEL_DIR), model: Mutex::new(None) }
+    }
+}
+
+impl Extractor for HandwritingExtractor {
+    fn name(&self) -> &'static str {
+        HANDWRITING_ENGINE
+    }
+
+    fn supports(&self, _fingerprint: &PageFingerprint) -> bool {
+        false
+    }
+
+    fn extract(&self, pdf_path: &Path, page_index: usize) -> Result<ExtractionResult> {
+        let start = Instant::now();
+        let mut model = self.model.lock().map_err(|_| anyhow!("Handwriting model lock poisoned"))?;
+        if model.is_none() {
+            if !TrOcr::available(&self.model_dir) {
+                return Err(anyhow!("No handwriting model in {}", self.model_dir.display()));
+            }
+            *model = Some(TrOcr::load(&self.model_dir)?);
+        }
+        let model = model.as_mut().ok_or_else(|| anyhow!("Handwriting model not loaded"))?;
+        let page = handwriting_page(pdf_path, page_index, model, DEFAULT_MIN_CONFIDENCE)?;
+        let mut result = ExtractionResult::new(page.grid(), ExtractionMethod::Handwriting);
+        result.extraction_time_ms = start.elapsed().as_millis() as u64;
+        Ok(result)
+    }
+
+    fn missing(&self) -> Option<String> {
+        if !TrOcr::available(&self.model_dir) {
+            return Some(format!("no handwriting model in {}", self.model_dir.display()));
+        }
+        missing_tools(&["pdftoppm"])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn test_regions_skip_print_and_rules() {
+        let mut page = GrayImage::from_pixel(600, 300, Luma([255]));
+        let mut ink = |x0: u32, y0: u32, x1: u32, y1: u32| {
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    page.put_pixel(x, y, Luma([0]));
+                }
+            }
+        };
+        // A printed label, the form line next to it, and a scrawl sitting on the line
+        ink(20, 100, 80, 120);
+        ink(100, 130, 580, 132);
+        for x in (110..300).step_by(6) {
+            let y = 105 + (x % 18);
+            ink(x, y, x + 3, y + 10);
+        }
+        let printed = [Region { x0: 20, y0: 100, x1: 80, y1: 120 }];
+
+        let regions = handwriting_regions(&page, &printed, 100);
+        assert_eq!(regions.len(), 1);
+        assert_eq!((regions[0].x0, regions[0].x1), (110, 299));
+
+        let words = region_words("Jane Doe", regions[0], 1.0, 0.4);
+        assert_eq!(words.len(), 2);
+        assert_eq!((words[1].word.text.as_str(), words[1].source), ("Doe", Provenance::Handwriting));
+    }
+}
@@ -0,0 +1,197 @@
+// Hybrid extraction - native text and OCR merged word by word
+//
+// Pages that mix a text layer with scanned or pasted-in images lose text either
+// way: pdftotext can't read the images and OCR misreads the native text. Here
+// both run, their words are aligned by position, and every native word is kept.
+// An OCR word survives only where no native word overlaps it, which in practice
+// is inside the images. The merged words are laid out as a text grid, and each
+// keeps a note of where it came from.
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Instant;
+
+use super::bbox::{self, BoxWord};
+use super::document_analyzer::PageFingerprint;
+use super::extraction_router::{ExtractionMethod, ExtractionResult};
+use super::extractors::{missing_tools, Extractor};
+
+/// Fraction of an OCR word's box a native word must cover for the two to count as the same text
+const SAME_WORD_OVERLAP: f32 = 0.3;
+
+/// Character width in points when a page has no words to measure
+const DEFAULT_CHAR_WIDTH: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provenance {
+    Native,
+    Ocr,
+    /// Recognized by the handwriting model (see handwriting.rs)
+    Handwriting,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedWord {
+    pub word: BoxWord,
+    pub source: Provenance,
+    /// Recognition confidence, where the source reports one
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HybridPage {
+    pub width: f32,
+    pub height: f32,
+    /// In reading order
+    pub words: Vec<MergedWord>,
+}
+
+impl HybridPage {
+    pub fn count(&self, source: Provenance) -> usize {
+        self.words.iter().filter(|w| w.source == source).count()
+    }
+
+    pub fn grid(&self) -> String {
+        layout_grid(&self.words)
+    }
+}
+
+/// Native and OCR words of one page (0-based), merged
+pub fn hybrid_page(pdf_path: &Path, page_index: usize) -> Result<HybridPage> {
+    let native = bbox::page_words(pdf_path, page_index)?;
+    let ocr = bbox::ocr_page_words(pdf_path, page_index)?;
+    let (width, height) = if native.width > 0.0 { (native.width, native.height) } else { (ocr.width, ocr.height) };
+    Ok(HybridPage { width, height, words: merge_words(&native.words, &ocr.words) })
+}
+
+fn area(word: &BoxWord) -> f32 {
+    (word.x1 - word.x0).max(0.0) * (word.y1 - word.y0).max(0.0)
+}
+
+fn overlap(a: &BoxWord, b: &BoxWord) -> f32 {
+    let width = a.x1.min(b.x1) - a.x0.max(b.x0);
+    let height = a.y1.min(b.y1) - a.y0.max(b.y0);
+    width.max(0.0) * height.max(0.0)
+}
+
+/// Every native word, plus the OCR words no native word covers, in reading order
+pub fn merge_words(native: &[BoxWord], ocr: &[BoxWord]) -> Vec<MergedWord> {
+    let mut merged: Vec<MergedWord> = native
+        .iter()
+        .filter(|w| !w.text.trim().is_empty())
+        .map(|w| MergedWord { word: w.clone(), source: Provenance::Native, confidence: None })
+        .collect();
+    for word in ocr.iter().filter(|w| !w.text.trim().is_empty()) {
+        let covered: f32 = native.iter().map(|n| overlap(word, n)).sum();
+        if covered <= area(word) * SAME_WORD_OVERLAP {
+            merged.push(MergedWord { word: word.clone(), source: Provenance::Ocr, confidence: None });
+        }
+    }
+
+    in_reading_order(merged)
+}
+
+/// Words sorted top to bottom by line, each line left to right
+pub fn in_reading_order(words: Vec<MergedWord>) -> Vec<MergedWord> {
+    group_lines(words, |w| &w.word).into_iter().flatten().collect()
+}
+
+/// Words grouped into lines top to bottom, each line left to right
+fn group_lines<T>(mut words: Vec<T>, word: impl Fn(&T) -> &BoxWord) -> Vec<Vec<T>> {
+    words.sort_by(|a, b| word(a).center_y().total_cmp(&word(b).center_y()).then(word(a).x0.total_cmp(&word(b).x0)));
+    let mut lines: Vec<Vec<T>> = Vec::new();
+    for item in words {
+        match lines.last_mut() {
+            Some(line) if (word(&line[0]).center_y() - word(&item).center_y()).abs() < word(&line[0]).height().max(1.0) * 0.5 => {
+                line.push(item)
+            }
+            _ => lines.push(vec![item]),
+        }
+    }
+    for line in &mut lines {
+        line.sort_by(|a, b| word(a).x0.total_cmp(&word(b).x0));
+    }
+    lines
+}
+
+/// Lay merged words out on a character grid (see `layout_words`)
+pub fn layout_grid(words: &[MergedWord]) -> String {
+    let words: Vec<BoxWord> = words.iter().map(|w| w.word.clone()).collect();
+    layout_words(&words)
+}
+
+/// Lay positioned words out on a character grid, one row per line, columns from
+/// their x position at the page's typical character width
+pub fn layout_words(words: &[BoxWord]) -> String {
+    let mut widths: Vec<f32> = words
+        .iter()
+        .map(|w| (w.x1 - w.x0) / w.text.chars().count().max(1) as f32)
+        .filter(|w| *w > 0.0)
+        .collect();
+    widths.sort_by(f32::total_cmp);
+    let char_width = widths.get(widths.len() / 2).copied().unwrap_or(DEFAULT_CHAR_WIDTH);
+
+    let mut rows = Vec::new();
+    for line in group_lines(words.iter().filter(|w| !w.text.trim().is_empty()).collect(), |w| *w) {
+        let mut row = String::new();
+        let mut used = 0;
+        for word in line {
+            let col = (word.x0 / char_width).round().max(0.0) as usize;
+            // Never let a word run into the previous one
+            let col = if used == 0 { col } else { col.max(used + 1) };
+            row.extend(std::iter::repeat_n(' ', col - used));
+            row.push_str(&word.text);
+            used = col + word.text.chars().count();
+        }
+        rows.push(row);
+    }
+    rows.join("\n")
+}
+
+/// Native text and OCR merged per word - for pages mixing a text layer with images.
+/// Runs both pdftotext and tesseract, so it isn't in the auto path unless configured first.
+pub struct HybridExtractor;
+
+impl Extractor for HybridExtractor {
+    fn name(&self) -> &'static str {
+        "hybrid"
+    }
+
+    fn supports(&self, fingerprint: &PageFingerprint) -> bool {
+        fingerprint.image_coverage > 0.0
+    }
+
+    fn extract(&self, pdf_path: &Path, page_index: usize) -> Result<ExtractionResult> {
+        let start = Instant::now();
+        let page = hybrid_page(pdf_path, page_index)?;
+        let mut result = ExtractionResult::new(page.grid(), ExtractionMethod::Hybrid);
+        result.extraction_time_ms = start.elapsed().as_millis() as u64;
+        Ok(result)
+    }
+
+    fn missing(&self) -> Option<String> {
+        missing_tools(&["pdftotext", "pdftoppm", "tesseract"])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, x0: f32, y0: f32) -> BoxWord {
+        BoxWord { text: text.to_string(), x0, y0, x1: x0 + 6.0 * text.len() as f32, y1: y0 + 10.0 }
+    }
+
+    #[test]
+    fn test_native_wins_and_ocr_fills_images() {
+        let native = vec![word("Invoice", 12.0, 10.0), word("Total:", 12.0, 40.0)];
+        // OCR misreads the native heading, but also reads the stamp pasted in as an image
+        let ocr = vec![word("lnvoice", 13.0, 11.0), word("PAID", 120.0, 41.0), word("Total:", 12.0, 40.0)];
+
+        let merged = merge_words(&native, &ocr);
+        let summary: Vec<(&str, Provenance)> = merged.iter().map(|w| (w.word.text.as_str(), w.source)).collect();
+        assert_eq!(summary, vec![("Invoice", Provenance::Native), ("Total:", Provenance::Native), ("PAID", Provenance::Ocr)]);
+        assert_eq!(layout_grid(&merged), "  Invoice\n  Total:            PAID");
+    }
+}
@@ -0,0 +1,169 @@
+// Language detection per page and per block - whatlang over the extracted text
+//
+// Codes are ISO 639-3 as whatlang reports them (eng, deu, cmn). Pages with too
+// little text to tell are left undetected rather than guessed. A document's
+// language is the one covering the most characters across its pages (see the
+// `language` field in storage::query).
+//
+// Pages of bilingual documents are also split into blocks - runs of lines between
+// blank lines - each with its own script and language, so a German clause under
+// an English one is found as German and OCR can read it with the German model.
+use whatlang::Lang;
+
+/// Letters a page needs before its language is worth detecting
+const MIN_LETTERS: usize = 40;
+/// Letters a block needs; blocks are short, and their script is told regardless
+const MIN_BLOCK_LETTERS: usize = 20;
+
+/// ISO 639-1 codes people type, for the languages whatlang knows
+const TWO_LETTER: &[(&str, &str)] = &[
+    ("ar", "ara"), ("cs", "ces"), ("da", "dan"), ("de", "deu"), ("el", "ell"), ("en", "eng"),
+    ("es", "spa"), ("fa", "pes"), ("fi", "fin"), ("fr", "fra"), ("he", "heb"), ("hi", "hin"),
+    ("hu", "hun"), ("id", "ind"), ("it", "ita"), ("ja", "jpn"), ("ko", "kor"), ("nb", "nob"),
+    ("nl", "nld"), ("no", "nob"), ("pl", "pol"), ("pt", "por"), ("ro", "ron"), ("ru", "rus"),
+    ("sv", "swe"), ("th", "tha"), ("tr", "tur"), ("uk", "ukr"), ("vi", "vie"), ("zh", "cmn"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLanguage {
+    pub page: usize, // 1-based
+    pub lang: String,
+    pub confidence: f64,
+    /// Characters on the page, to weigh pages when picking the document's language
+    pub chars: usize,
+}
+
+/// Detect the language of every page of a stored document (pages separated by form feeds)
+pub fn detect_page_languages(content: &str) -> Vec<PageLanguage> {
+    content
+        .split('\x0c')
+        .enumerate()
+        .filter(|(_, text)| text.chars().filter(|c| c.is_alphabetic()).count() >= MIN_LETTERS)
+        .filter_map(|(index, text)| {
+            let info = whatlang::detect(text)?;
+            Some(PageLanguage {
+                page: index + 1,
+                lang: info.lang().code().to_string(),
+                confidence: info.confidence(),
+                chars: text.chars().count(),
+            })
+        })
+        .collect()
+}
+
+/// A paragraph-like run of lines on a page, with what it is written in
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextBlock {
+    /// 0-based, in page order
+    pub index: usize,
+    /// 0-based line of the page the block starts on
+    pub first_line: usize,
+    pub lines: usize,
+    /// Writing system as whatlang names it (Latin, Cyrillic, Mandarin, ...)
+    pub script: Option<String>,
+    /// None when the block is too short to tell
+    pub lang: Option<String>,
+    pub confidence: f64,
+    pub chars: usize,
+}
+
+/// Split a page's text into blocks at blank lines and detect each one
+pub fn segment_blocks(page_text: &str) -> Vec<TextBlock> {
+    let lines: Vec<&str> = page_text.lines().collect();
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        if lines[start].trim().is_empty() {
+            start += 1;
+            continue;
+        }
+        let end = (start..lines.len()).find(|&i| lines[i].trim().is_empty()).unwrap_or(lines.len());
+        let text = lines[start..end].join("\n");
+        let info = block_language(&text);
+        blocks.push(TextBlock {
+            index: blocks.len(),
+            first_line: start,
+            lines: end - start,
+            script: whatlang::detect_script(&text).map(|script| script.name().to_string()),
+            lang: info.as_ref().map(|info| info.lang().code().to_string()),
+            confidence: info.map_or(0.0, |info| info.confidence()),
+            chars: text.chars().count(),
+        });
+        start = end;
+    }
+    blocks
+}
+
+/// Language of a short run of text, when it has enough letters to tell
+pub fn block_language(text: &str) -> Option<whatlang::Info> {
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_BLOCK_LETTERS {
+        return None;
+    }
+    whatlang::detect(text)
+}
+
+/// Tesseract's traineddata name for a language, for the languages it ships models for
+pub fn tesseract_model(code: &str) -> Option<&'static str> {
+    let model = match code {
+        "eng" => "eng", "deu" => "deu", "fra" => "fra", "spa" => "spa", "ita" => "ita", "por" => "por",
+        "nld" => "nld", "dan" => "dan", "swe" => "swe", "nob" => "nor", "fin" => "fin", "pol" => "pol",
+        "ces" => "ces", "slk" => "slk", "hun" => "hun", "ron" => "ron", "tur" => "tur", "ell" => "ell",
+        "rus" => "rus", "ukr" => "ukr", "bul" => "bul", "srp" => "srp", "hrv" => "hrv", "ara" => "ara",
+        "heb" => "heb", "pes" => "fas", "hin" => "hin", "ben" => "ben", "tha" => "tha", "vie" => "vie",
+        "ind" => "ind", "jpn" => "jpn", "kor" => "kor", "cmn" => "chi_sim",
+        _ => return None,
+    };
+    Some(model)
+}
+
+/// ISO 639-3 code for what a user typed: a 639-3 or 639-1 code, or the
+/// language's name in English or in itself
+pub fn language_code(input: &str) -> Option<&'static str> {
+    let input = input.trim().to_lowercase();
+    if let Some((_, code)) = TWO_LETTER.iter().find(|(short, _)| *short == input) {
+        return Some(code);
+    }
+    Lang::all()
+        .iter()
+        .find(|lang| lang.code() == input || lang.eng_name().to_lowercase() == input || lang.name().to_lowercase() == input)
+        .map(|lang| lang.code())
+}
+
+/// English name for a stored code, or the code itself if whatlang doesn't know it
+pub fn language_name(code: &str) -> String {
+    Lang::from_code(code).map(|lang| lang.eng_name().to_string()).unwrap_or_else(|| code.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_languages_and_codes() {
+        let english = "The invoice is due at the end of the month and payment should be made by bank transfer.";
+        let german = "Die Rechnung ist am Ende des Monats fällig und die Zahlung erfolgt per Überweisung auf unser Konto.";
+        let content = format!("{}\x0cshort\x0c{}\x0c{} {}", english, german, german, german);
+        let pages = detect_page_languages(&content);
+
+        assert_eq!(pages.iter().map(|p| (p.page, p.lang.as_str())).collect::<Vec<_>>(), vec![(1, "eng"), (3, "deu"), (4, "deu")]);
+        assert_eq!(language_code("DE"), Some("deu"));
+        assert_eq!(language_code("german"), Some("deu"));
+        assert_eq!(language_code("eng"), Some("eng"));
+        assert_eq!(language_code("klingon"), None);
+        assert_eq!(language_name("fra"), "French");
+    }
+
+    #[test]
+    fn test_blocks_of_a_bilingual_page() {
+        let page = "AGREEMENT\n\nThe tenant shall pay the rent on the first day of each month.\n\n\
+                    Der Mieter zahlt die Miete am ersten Tag eines jeden Monats.\nDie Kaution beträgt drei Monatsmieten.\n\n\
+                    Арендатор оплачивает аренду в первый день каждого месяца.";
+        let blocks = segment_blocks(page);
+        let found: Vec<(usize, usize, Option<&str>)> = blocks.iter().map(|b| (b.first_line, b.lines, b.lang.as_deref())).collect();
+        assert_eq!(found, vec![(0, 1, None), (2, 1, Some("eng")), (4, 2, Some("deu")), (7, 1, Some("rus"))]);
+        assert_eq!(blocks[0].script.as_deref(), Some("Latin"));
+        assert_eq!(blocks[3].script.as_deref(), Some("Cyrillic"));
+        assert_eq!(tesseract_model("cmn"), Some("chi_sim"));
+        assert_eq!(tesseract_model("xyz"), None);
+    }
+}
@@ -0,0 +1,208 @@
+// LayoutLM token classification - labelling the words of a page (header,
+// question, answer, table, ...) from their text, their position and the image
+//
+// A model directory holds model.onnx, tokenizer.json and config.json (with
+// id2label), as `optimum-cli export onnx --task token-classification` writes
+// them. Labels are BIO tags - B-HEADER starts a header, I-HEADER continues it,
+// O is anything else. Each word takes the label of its first sub-token, and
+// runs of words of one kind become spans.
+use anyhow::{anyhow, Result};
+use image::DynamicImage;
+use ort::session::{Session, SessionInputValue};
+use ort::value::Tensor;
+use std::path::Path;
+use tokenizers::Tokenizer;
+
+use super::trocr::top_log_probs;
+
+/// Under the models directory (see models.rs)
+pub const LAYOUTLM_MODEL_DIR: &str = "layoutlm";
+/// Span kind for words tagged O
+pub const OTHER: &str = "OTHER";
+
+/// LayoutLMv3's image resolution
+const IMAGE_SIZE: u32 = 224;
+/// Sequence length the model was trained with, [CLS] and [SEP] included
+const MAX_TOKENS: usize = 512;
+/// Boxes are given to the model on a 0-1000 grid
+const BOX_SCALE: f32 = 1000.0;
+
+/// A word and its box as fractions of the page (x0, y0, x1, y1)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutWord {
+    pub text: String,
+    pub bbox: [f32; 4],
+}
+
+/// The label a word was given and its probability
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordLabel {
+    pub label: String,
+    pub confidence: f32,
+}
+
+/// Words `start..end` all of one kind
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub kind: String,
+    pub start: usize,
+    pub end: usize,
+    /// Mean of the words' label probabilities
+    pub confidence: f32,
+}
+
+pub struct LayoutLm {
+    session: Session,
+    tokenizer: Tokenizer,
+    labels: Vec<String>,
+    cls: u32,
+    sep: u32,
+    /// Byte-level BPE vocabularies (LayoutLMv3) mark word starts with a leading space
+    prefix_space: bool,
+}
+
+impl LayoutLm {
+    /// Whether a model directory looks complete
+    pub fn available(dir: &Path) -> bool {
+        ["model.onnx", "tokenizer.json", "config.json"].iter().all(|file| dir.join(file).exists())
+    }
+
+    pub fn load(dir: &Path) -> Result<Self> {
+        let _ = ort::init();
+        let session = Session::builder()?
+            .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
+            .with_intra_threads(4)?
+            .commit_from_file(dir.join("model.onnx"))?;
+        let tokenizer_path = dir.join("tokenizer.json");
+        let tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|e| anyhow!("Loading {}: {}", tokenizer_path.display(), e))?;
+
+        let config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("config.json"))?)?;
+        let id2label = config["id2label"].as_object().ok_or_else(|| anyhow!("{}/config.json has no id2label", dir.display()))?;
+        let mut labels = vec![String::new(); id2label.len()];
+        for (id, label) in id2label {
+            let index: usize = id.parse().map_err(|_| anyhow!("Label id '{}' is not a number", id))?;
+            let slot = labels.get_mut(index).ok_or_else(|| anyhow!("Label ids in {}/config.json are not 0..n", dir.display()))?;
+            *slot = label.as_str().unwrap_or_default().to_string();
+        }
+
+        let (cls, sep, prefix_space) = match (tokenizer.token_to_id("<s>"), tokenizer.token_to_id("</s>")) {
+            (Some(cls), Some(sep)) => (cls, sep, true),
+            _ => (
+                tokenizer.token_to_id("[CLS]").ok_or_else(|| anyhow!("Tokenizer has neither <s> nor [CLS]"))?,
+                tokenizer.token_to_id("[SEP]").ok_or_else(|| anyhow!("Tokenizer has neither </s> nor [SEP]"))?,
+                false,
+            ),
+        };
+        Ok(Self { session, tokenizer, labels, cls, sep, prefix_space })
+    }
+
+    /// A label per word. Words past the model's 512 tokens are labelled O with
+    /// no confidence.
+    pub fn label_words(&mut self, image: &DynamicImage, words: &[LayoutWord]) -> Result<Vec<WordLabel>> {
+        let mut ids: Vec<i64> = vec![self.cls as i64];
+        let mut boxes: Vec<i64> = vec![0; 4];
+        // Index of each word's first sub-token
+        let mut first_token: Vec<Option<usize>> = Vec::with_capacity(words.len());
+        for (index, word) in words.iter().enumerate() {
+            let text = if self.prefix_space && index > 0 { format!(" {}", word.text) } else { word.text.clone() };
+            let encoding = self.tokenizer.encode(text, false).map_err(|e| anyhow!("Tokenizing: {}", e))?;
+            let pieces = encoding.get_ids();
+            if pieces.is_empty() || ids.len() + pieces.len() >= MAX_TOKENS {
+                first_token.push(None);
+                continue;
+            }
+            first_token.push(Some(ids.len()));
+            let scaled = word.bbox.map(|v| (v.clamp(0.0, 1.0) * BOX_SCALE) as i64);
+            for &piece in pieces {
+                ids.push(piece as i64);
+                boxes.extend(scaled);
+            }
+        }
+        ids.push(self.sep as i64);
+        boxes.extend([0; 4]);
+        let tokens = ids.len();
+
+        let mut inputs: Vec<(String, SessionInputValue)> = Vec::new();
+        for input in &self.session.inputs {
+            let value: SessionInputValue = match input.name.as_str() {
+                "input_ids" => Tensor::from_array(([1usize, tokens], ids.clone().into_boxed_slice()))?.into(),
+                "attention_mask" => Tensor::from_array(([1usize, tokens], vec![1i64; tokens].into_boxed_slice()))?.into(),
+                "token_type_ids" => Tensor::from_array(([1usize, tokens], vec![0i64; tokens].into_boxed_slice()))?.into(),
+                "bbox" => Tensor::from_array(([1usize, tokens, 4], boxes.clone().into_boxed_slice()))?.into(),
+                "pixel_values" => {
+                    let size = IMAGE_SIZE as usize;
+                    Tensor::from_array(([1usize, 3, size, size], pixel_values(image).into_boxed_slice()))?.into()
+                }
+                other => return Err(anyhow!("LayoutLM model wants an unsupported input '{}'", other)),
+            };
+            inputs.push((input.name.clone(), value));
+        }
+
+        let outputs = self.session.run(inputs)?;
+        let (shape, logits) = outputs[0].try_extract_tensor::<f32>()?;
+        let classes = shape[2] as usize;
+        Ok(first_token
+            .iter()
+            .map(|token| match token {
+                Some(token) => {
+                    let (label, log_prob) = top_log_probs(&logits[token * classes..(token + 1) * classes], 1)[0];
+                    WordLabel { label: self.labels.get(label).cloned().unwrap_or_else(|| "O".to_string()), confidence: log_prob.exp() }
+                }
+                None => WordLabel { label: "O".to_string(), confidence: 0.0 },
+            })
+            .collect())
+    }
+}
+
+/// Runs of words of one kind. B- always starts a span; I- continues one of the
+/// same kind and starts one otherwise (models do emit I- without a B-); O words
+/// form OTHER spans.
+pub fn spans(labels: &[WordLabel]) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    for (index, word) in labels.iter().enumerate() {
+        let (begins, kind) = match word.label.split_once('-') {
+            Some(("B", kind)) => (true, kind),
+            Some((_, kind)) => (false, kind),
+            None if word.label == "O" => (false, OTHER),
+            None => (false, word.label.as_str()),
+        };
+        match spans.last_mut() {
+            Some(span) if !begins && span.kind == kind => {
+                span.confidence = (span.confidence * (span.end - span.start) as f32 + word.confidence) / (span.end - span.start + 1) as f32;
+                span.end = index + 1;
+            }
+            _ => spans.push(Span { kind: kind.to_string(), start: index, end: index + 1, confidence: word.confidence }),
+        }
+    }
+    spans
+}
+
+/// RGB at 224x224 in CHW order, normalized to [-1, 1] like LayoutLMv3's processor
+fn pixel_values(image: &DynamicImage) -> Vec<f32> {
+    let rgb = image.resize_exact(IMAGE_SIZE, IMAGE_SIZE, image::imageops::FilterType::Triangle).to_rgb8();
+    let size = (IMAGE_SIZE * IMAGE_SIZE) as usize;
+    let mut pixels = vec![0.0; 3 * size];
+    for (i, pixel) in rgb.pixels().enumerate() {
+        for c in 0..3 {
+            pixels[c * size + i] = pixel[c] as f32 / 127.5 - 1.0;
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bio_tags_become_spans() {
+        let labels: Vec<WordLabel> = [("B-HEADER", 0.9), ("I-HEADER", 0.7), ("B-QUESTION", 0.8), ("I-ANSWER", 0.6), ("I-ANSWER", 0.8), ("O", 0.5)]
+            .iter()
+            .map(|&(label, confidence)| WordLabel { label: label.to_string(), confidence })
+            .collect();
+        let spans = spans(&labels);
+        let kinds: Vec<(&str, usize, usize)> = spans.iter().map(|s| (s.kind.as_str(), s.start, s.end)).collect();
+        assert_eq!(kinds, vec![("HEADER", 0, 2), ("QUESTION", 2, 3), ("ANSWER", 3, 5), (OTHER, 5, 6)]);
+        assert!((spans[0].confidence - 0.8).abs() < 1e-6);
+    }
+}
@@ -4,7 +4,8 @@
 // All extraction methods have been unified to use pdftotext with layout preservation.
 //
 // Main components:
-// - extraction_router: Handles PDF text extraction using pdftotext
+// - extraction_router: Picks a backend per page and falls back on failure
+// - extractors: Extractor trait and registry (pdftotext, tesseract, lopdf, hybrid, handwriting, trocr)
 // - document_analyzer: Analyzes PDF pages (still available for metrics)
 
 // Active modules - Pure Rust implementation
@@ -12,14 +13,33 @@ pub mod pdftotext_extraction;  // Text extraction using pdftotext
 pub mod lopdf_helper;         // Pure Rust PDF parsing
 pub mod document_processor;   // Document processing
 pub mod ui_api;               // UI API integration
+pub mod bbox;                 // Word bounding boxes from pdftotext -bbox
+pub mod annotations;          // Highlights, comments and sticky notes
+pub mod forms;                // Fillable AcroForm fields and their values
+pub mod sidecar;              // Text, hOCR and ALTO results from other OCR tools
+pub mod text_layer;           // Invisible text written back into PDFs
 
-// Active extraction system - uses pdftotext exclusively
+// Active extraction system - pluggable backends walked by the router
 pub mod document_analyzer;
 pub mod extraction_router;
+pub mod extractors;
+pub mod hybrid;               // Native text and OCR merged word by word
+pub mod trocr;                // TrOCR line recognition over ONNX
+pub mod layoutlm;             // LayoutLM word labels decoded into sections
+pub mod handwriting;          // Handwritten fields the other engines leave blank
+pub mod cloud_ocr;            // Azure/Google/Textract OCR, only with --engine cloud
+pub mod quality;              // QualityChecker - combined text quality score
+pub mod dedup;                // Pages that repeat the one before them
+pub mod orientation;          // Sideways, upside-down and skewed scans straightened before OCR
+pub mod language;             // Language per page, per block and per document
+pub mod multilingual;         // Blocks in other languages re-read with their own OCR model
+pub mod models;               // Where ONNX models live; `chonker8 models` fetches and checks them
 
 // Main exports for PDF extraction
 pub use document_analyzer::{DocumentAnalyzer, PageFingerprint};
 pub use extraction_router::{ExtractionRouter, ExtractionMethod, ExtractionResult};
+pub use extractors::{Extractor, ExtractorRegistry};
+pub use quality::{QualityChecker, QualityConfig};
 
 // Note: The following exports are kept for compatibility but are not used:
 // - All ML-based extraction methods (OCR, LayoutLM, TrOCR)
@@ -0,0 +1,233 @@
+// ONNX models - where they are looked for, and `chonker8 models` to fetch and check them
+//
+// Models live under one directory: [models] dir in extraction.toml, else
+// $CHONKER8_MODELS, else the platform data dir (~/.local/share/chonker8/models on
+// Linux). A file missing there is still found under ./models, where older
+// setups put them by hand. Download URLs and SHA-256 checksums come from
+// [models.sources], keyed by the file's path under the models directory:
+//
+//   [models.sources."layoutlm/model.onnx"]
+//   url = "https://example.org/layoutlmv3-base/model.onnx"
+//   sha256 = "9f2c..."
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+pub const MODELS_DIR_ENV: &str = "CHONKER8_MODELS";
+/// Where models were expected before there was a models directory
+pub const LEGACY_MODELS_DIR: &str = "models";
+
+/// Downloads are large; this bounds a stalled connection, not the whole transfer
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+static CONFIGURED_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// [models] in extraction.toml
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ModelsConfig {
+    /// Models directory, overriding $CHONKER8_MODELS and the data dir
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+    /// Where each file is downloaded from, by its path under the models directory
+    #[serde(default)]
+    pub sources: BTreeMap<String, ModelSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ModelSource {
+    pub url: String,
+    /// Hex SHA-256 the download must match; without one a file is fetched unverified
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// A model chonker8 knows how to use
+#[derive(Debug)]
+pub struct Model {
+    pub name: &'static str,
+    pub used_by: &'static str,
+    /// Paths under the models directory
+    pub files: &'static [&'static str],
+}
+
+pub const MODELS: &[Model] = &[
+    Model {
+        name: "trocr-printed",
+        used_by: "--engine trocr, document processor OCR",
+        files: &["trocr-printed/encoder_model.onnx", "trocr-printed/decoder_model.onnx", "trocr-printed/tokenizer.json", "trocr-printed/config.json"],
+    },
+    Model {
+        name: "trocr-handwritten",
+        used_by: "--engine handwriting, hybrid --handwriting",
+        files: &[
+            "trocr-handwritten/encoder_model.onnx",
+            "trocr-handwritten/decoder_model.onnx",
+            "trocr-handwritten/tokenizer.json",
+            "trocr-handwritten/config.json",
+        ],
+    },
+    Model {
+        name: "layoutlm",
+        used_by: "document processor sections and tables",
+        files: &["layoutlm/model.onnx", "layoutlm/tokenizer.json", "layoutlm/config.json"],
+    },
+    Model {
+        name: "sentence-embedding",
+        used_by: "search --semantic",
+        files: &["sentence-embedding.onnx", "sentence-embedding-tokenizer.json"],
+    },
+    Model { name: "piper", used_by: "speak", files: &["piper.onnx", "piper.onnx.json"] },
+];
+
+/// How an installed file compares with its configured checksum
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileStatus {
+    Missing,
+    /// Present, but no checksum is configured to compare with
+    Unverified,
+    Verified,
+    Mismatch { actual: String },
+}
+
+/// Use this models directory for the rest of the process; the first call wins
+pub fn set_models_dir(dir: PathBuf) {
+    let _ = CONFIGURED_DIR.set(dir);
+}
+
+/// Where downloads go and models are looked for first
+pub fn models_dir() -> PathBuf {
+    if let Some(dir) = CONFIGURED_DIR.get() {
+        return dir.clone();
+    }
+    if let Some(dir) = std::env::var_os(MODELS_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    dirs::data_dir().map(|dir| dir.join("chonker8").join("models")).unwrap_or_else(|| PathBuf::from(LEGACY_MODELS_DIR))
+}
+
+/// A model file or directory: under the models directory, or under ./models if
+/// only there. When it is in neither, the models directory path (where
+/// `models download` would put it).
+pub fn locate(relative: &str) -> PathBuf {
+    let primary = models_dir().join(relative);
+    let legacy = Path::new(LEGACY_MODELS_DIR).join(relative);
+    if !primary.exists() && legacy.exists() {
+        legacy
+    } else {
+        primary
+    }
+}
+
+pub fn model(name: &str) -> Result<&'static Model> {
+    MODELS.iter().find(|m| m.name == name).ok_or_else(|| {
+        anyhow!("Unknown model '{}' (known: {})", name, MODELS.iter().map(|m| m.name).collect::<Vec<_>>().join(", "))
+    })
+}
+
+/// Compare an installed file with the checksum configured for it
+pub fn check_file(relative: &str, config: &ModelsConfig) -> Result<FileStatus> {
+    let path = locate(relative);
+    if !path.is_file() {
+        return Ok(FileStatus::Missing);
+    }
+    let Some(expected) = config.sources.get(relative).and_then(|s| s.sha256.as_deref()) else {
+        return Ok(FileStatus::Unverified);
+    };
+    let actual = sha256_of(File::open(&path)?)?;
+    Ok(if actual.eq_ignore_ascii_case(expected) { FileStatus::Verified } else { FileStatus::Mismatch { actual } })
+}
+
+/// Fetch one file into the models directory. The download is written next to
+/// its destination and only moved into place once its checksum matches, so an
+/// interrupted or corrupt transfer never leaves a half-written model behind.
+/// Returns the bytes written.
+pub fn download_file(relative: &str, config: &ModelsConfig) -> Result<u64> {
+    let source = config.sources.get(relative).ok_or_else(|| {
+        anyhow!("No download URL for {} - add [models.sources.\"{}\"] with url and sha256 to extraction.toml", relative, relative)
+    })?;
+    let destination = models_dir().join(relative);
+    let parent = destination.parent().ok_or_else(|| anyhow!("Bad model path {}", relative))?;
+    fs::create_dir_all(parent)?;
+
+    let agent = ureq::AgentBuilder::new().timeout_read(READ_TIMEOUT).build();
+    let response = agent.get(&source.url).call().map_err(|e| anyhow!("Downloading {} failed: {}", source.url, e))?;
+    let mut reader = response.into_reader();
+    let mut spool = tempfile::NamedTempFile::new_in(parent)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    let mut written = 0u64;
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        spool.write_all(&buffer[..n])?;
+        written += n as u64;
+    }
+
+    let actual = format!("{:x}", hasher.finalize());
+    match source.sha256.as_deref() {
+        Some(expected) if !actual.eq_ignore_ascii_case(expected) => {
+            bail!("{} from {} has SHA-256 {}, expected {} - not installed", relative, source.url, actual, expected)
+        }
+        Some(_) => {}
+        None => tracing::warn!("[MODELS] No sha256 configured for {}, installed unverified (it is {})", relative, actual),
+    }
+    spool.persist(&destination)?;
+    Ok(written)
+}
+
+/// Delete a model's files from the models directory (never from ./models) and
+/// its directory once empty. Returns the files removed.
+pub fn remove_model(model: &Model) -> Result<usize> {
+    let root = models_dir();
+    let mut removed = 0;
+    for file in model.files {
+        let path = root.join(file);
+        if path.is_file() {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+        if let Some(parent) = path.parent().filter(|parent| *parent != root) {
+            // Fails harmlessly while the directory still holds other files
+            let _ = fs::remove_dir(parent);
+        }
+    }
+    Ok(removed)
+}
+
+fn sha256_of(mut reader: impl Read) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums_compare_case_insensitively() {
+        let dir = tempfile::tempdir().unwrap();
+        set_models_dir(dir.path().to_path_buf());
+        fs::create_dir_all(dir.path().join("layoutlm")).unwrap();
+        fs::write(dir.path().join("layoutlm/config.json"), b"{}").unwrap();
+
+        // SHA-256 of "{}"
+        let digest = "44136FA355B3678A1146AD16F7E8649E94FB4FC21FE77E8310C060F61CAAFF8A";
+        let mut config = ModelsConfig::default();
+        assert_eq!(check_file("layoutlm/config.json", &config).unwrap(), FileStatus::Unverified);
+        config.sources.insert("layoutlm/config.json".to_string(), ModelSource { url: String::new(), sha256: Some(digest.to_string()) });
+        assert_eq!(check_file("layoutlm/config.json", &config).unwrap(), FileStatus::Verified);
+        config.sources.insert("layoutlm/config.json".to_string(), ModelSource { url: String::new(), sha256: Some("00".to_string()) });
+        assert!(matches!(check_file("layoutlm/config.json", &config).unwrap(), FileStatus::Mismatch { .. }));
+        assert_eq!(check_file("layoutlm/model.onnx", &config).unwrap(), FileStatus::Missing);
+    }
+}
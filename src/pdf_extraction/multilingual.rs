@@ -0,0 +1,160 @@
+// Multilingual OCR - each block of a page read with its own language's model
+//
+// Tesseract reads a page with one model, which mangles the German half of a
+// bilingual contract read as English. After the first read, the page's blocks
+// (tesseract's own, from its TSV output) go through language detection; blocks
+// in another language whose model is installed are cropped out and read again
+// with that model. A page that needed this is rebuilt from its blocks with a
+// blank line between them, so storage tags the same blocks with their languages.
+use anyhow::{anyhow, Result};
+use image::GenericImageView;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::temp_files::scratch_dir;
+use super::language::{block_language, tesseract_model};
+
+/// What tesseract reads with when not told otherwise
+pub const DEFAULT_MODEL: &str = "eng";
+/// Pixels kept around a block when it is cropped out for its second read
+const CROP_MARGIN: u32 = 8;
+
+/// Tesseract's installed traineddata, asked once per process; empty when tesseract is missing
+pub fn installed_models() -> &'static [String] {
+    static INSTALLED: OnceLock<Vec<String>> = OnceLock::new();
+    INSTALLED.get_or_init(|| {
+        let Ok(output) = Command::new("tesseract").arg("--list-langs").output() else {
+            return Vec::new();
+        };
+        // First line is "List of available languages in ...:"
+        String::from_utf8_lossy(&output.stdout).lines().skip(1).map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect()
+    })
+}
+
+/// One of tesseract's blocks: where it is on the image and its lines of text
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrBlock {
+    /// left, top, width, height in image pixels
+    pub bbox: (u32, u32, u32, u32),
+    pub lines: Vec<String>,
+}
+
+impl OcrBlock {
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+/// Blocks from `tesseract ... tsv` output, in reading order
+pub fn parse_tsv_blocks(tsv: &str) -> Vec<OcrBlock> {
+    let mut blocks: Vec<(u32, OcrBlock)> = Vec::new();
+    let mut current_line = None;
+    // level page block par line word left top width height conf text
+    for row in tsv.lines().skip(1) {
+        let fields: Vec<&str> = row.split('\t').collect();
+        if fields.len() < 11 {
+            continue;
+        }
+        let number = |i: usize| fields[i].trim().parse::<u32>().unwrap_or(0);
+        let (level, block) = (number(0), number(2));
+        match level {
+            2 => blocks.push((block, OcrBlock { bbox: (number(6), number(7), number(8), number(9)), lines: Vec::new() })),
+            5 => {
+                let word = fields.get(11).map_or("", |text| text.trim());
+                let Some((_, target)) = blocks.iter_mut().rev().find(|(number, _)| *number == block) else { continue };
+                if word.is_empty() {
+                    continue;
+                }
+                let line = (block, number(3), number(4));
+                match target.lines.last_mut() {
+                    Some(last) if current_line == Some(line) => {
+                        last.push(' ');
+                        last.push_str(word);
+                    }
+                    _ => target.lines.push(word.to_string()),
+                }
+                current_line = Some(line);
+            }
+            _ => {}
+        }
+    }
+    blocks.into_iter().map(|(_, block)| block).filter(|block| !block.lines.is_empty()).collect()
+}
+
+fn tesseract(image: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("tesseract")
+        .arg(image)
+        .arg("stdout")
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("tesseract not available: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Re-read the blocks of a page image that aren't in `first_model`'s language.
+/// Returns the rebuilt page text, or None when every block was fine as read.
+pub fn reread_by_language(image_path: &Path, first_model: &str) -> Result<Option<String>> {
+    let installed = installed_models();
+    if installed.iter().filter(|model| model.as_str() != "osd").count() < 2 {
+        return Ok(None);
+    }
+
+    let mut blocks = parse_tsv_blocks(&tesseract(image_path, &["-l", first_model, "tsv"])?);
+    let image = image::open(image_path)?;
+    let temp_dir = scratch_dir()?;
+    let mut reread = false;
+    for (index, block) in blocks.iter_mut().enumerate() {
+        let Some(info) = block_language(&block.text()) else { continue };
+        let Some(model) = tesseract_model(info.lang().code()) else { continue };
+        if model == first_model || !installed.iter().any(|installed| installed == model) {
+            continue;
+        }
+
+        let (left, top, width, height) = block.bbox;
+        let (x, y) = (left.saturating_sub(CROP_MARGIN), top.saturating_sub(CROP_MARGIN));
+        let width = (width + 2 * CROP_MARGIN).min(image.width().saturating_sub(x));
+        let height = (height + 2 * CROP_MARGIN).min(image.height().saturating_sub(y));
+        let crop_path = temp_dir.path().join(format!("block-{}.png", index));
+        image.view(x, y, width, height).to_image().save(&crop_path)?;
+
+        // --psm 6: the crop is one uniform block of text
+        let text = tesseract(&crop_path, &["-l", model, "--psm", "6"])?;
+        let lines: Vec<String> = text.lines().map(|line| line.trim_end().to_string()).filter(|line| !line.trim().is_empty()).collect();
+        if !lines.is_empty() {
+            tracing::debug!("[OCR] Block {} re-read as {} ({} lines)", index + 1, model, lines.len());
+            block.lines = lines;
+            reread = true;
+        }
+    }
+
+    Ok(reread.then(|| blocks.iter().map(OcrBlock::text).collect::<Vec<_>>().join("\n\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tsv_rows_grouped_into_blocks_and_lines() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   1\t1\t0\t0\t0\t0\t0\t0\t2480\t3508\t-1\t\n\
+                   2\t1\t1\t0\t0\t0\t100\t120\t900\t80\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t100\t120\t200\t30\t96\tThe\n\
+                   5\t1\t1\t1\t1\t2\t320\t120\t200\t30\t95\ttenant\n\
+                   5\t1\t1\t1\t2\t1\t100\t160\t200\t30\t94\tpays\n\
+                   2\t1\t2\t0\t0\t0\t100\t400\t900\t40\t-1\t\n\
+                   5\t1\t2\t1\t1\t1\t100\t400\t200\t30\t90\tDer\n\
+                   5\t1\t2\t1\t1\t2\t320\t400\t200\t30\t91\tMieter\n\
+                   2\t1\t3\t0\t0\t0\t100\t600\t10\t10\t-1\t\n\
+                   5\t1\t3\t1\t1\t1\t100\t600\t10\t10\t-1\t \n";
+        let blocks = parse_tsv_blocks(tsv);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].bbox, (100, 120, 900, 80));
+        assert_eq!(blocks[0].text(), "The tenant\npays");
+        assert_eq!(blocks[1].text(), "Der Mieter");
+    }
+}
@@ -0,0 +1,239 @@
+// Page orientation and skew - scans fed in sideways, upside down or a few degrees off
+//
+// The PDF's own /Rotate is applied by pdftoppm when the page is rendered, so it is
+// only recorded. What the image analysis catches is the scanned picture itself
+// being turned. Text lines show up as sharp bands in the row ink profile; a
+// sideways page has them in the column profile instead. Upside down is told
+// apart by which side of each line's x-height band holds more ink: ascenders and
+// capitals above it outnumber descenders below in an upright line. Skew is the
+// small angle whose sheared row profile has the sharpest bands.
+use anyhow::Result;
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma};
+use serde::Serialize;
+use std::path::Path;
+
+/// Largest skew looked for, either way
+pub const MAX_SKEW_DEGREES: f32 = 5.0;
+const SKEW_STEP_DEGREES: f32 = 0.25;
+/// Skew below this is left alone - resampling would blur more than it straightens
+const MIN_SKEW_DEGREES: f32 = 0.3;
+/// Analysis runs on a copy no wider than this
+const ANALYSIS_WIDTH: u32 = 800;
+/// Darker than this is ink
+const INK: u8 = 128;
+/// Too little ink to judge (a blank or nearly blank page)
+const MIN_INK_PIXELS: usize = 200;
+/// How much sharper the column profile must be before a page counts as sideways
+const SIDEWAYS_MARGIN: f64 = 1.5;
+
+/// What was done to a page before OCR
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Correction {
+    /// /Rotate in the PDF, applied by the renderer
+    pub pdf_rotate: i64,
+    /// Clockwise quarter turns applied to the rendered image: 0, 90, 180 or 270
+    pub rotated: u16,
+    /// Skew that was straightened, in degrees; positive when lines fell to the right
+    pub deskewed: f32,
+}
+
+impl Correction {
+    pub fn is_none(&self) -> bool {
+        self.pdf_rotate.rem_euclid(360) == 0 && self.rotated == 0 && self.deskewed == 0.0
+    }
+}
+
+/// Clockwise degrees (0, 90, 180, 270) that turn the page upright
+pub fn detect_rotation(image: &GrayImage) -> u16 {
+    let ink = ink_points(&analysis_copy(image));
+    if ink.len() < MIN_INK_PIXELS {
+        return 0;
+    }
+    // Profiles are compared at their best shear, or a skewed page smears its lines
+    let (_, rows) = best_shear(&ink);
+    let transposed: Vec<(f32, f32)> = ink.iter().map(|&(x, y)| (y, x)).collect();
+    let (_, cols) = best_shear(&transposed);
+    if sharpness(&cols) > sharpness(&rows) * SIDEWAYS_MARGIN {
+        // Turned a quarter clockwise, the columns become rows in the same order
+        if upside_down(&cols) { 270 } else { 90 }
+    } else if upside_down(&rows) {
+        180
+    } else {
+        0
+    }
+}
+
+/// Skew of the text lines in degrees, positive when they fall to the right; 0 when unclear
+pub fn detect_skew(image: &GrayImage) -> f32 {
+    let ink = ink_points(&analysis_copy(image));
+    if ink.len() < MIN_INK_PIXELS {
+        return 0.0;
+    }
+    best_shear(&ink).0
+}
+
+/// The angle whose sheared row profile has the sharpest bands, and that profile
+fn best_shear(ink: &[(f32, f32)]) -> (f32, Vec<u32>) {
+    let (max_x, max_y) = ink.iter().fold((0.0f32, 0.0f32), |(mx, my), &(x, y)| (mx.max(x), my.max(y)));
+    let len = (max_x + max_y) as usize * 2 + 2;
+    let steps = (MAX_SKEW_DEGREES / SKEW_STEP_DEGREES) as i32;
+    let mut best: Option<(f32, f64, Vec<u32>)> = None;
+    // Outwards from 0, so ties keep the smaller angle and a flat page reports no skew
+    for step in std::iter::once(0).chain((1..=steps).flat_map(|s| [s, -s])) {
+        let angle = step as f32 * SKEW_STEP_DEGREES;
+        let slope = angle.to_radians().tan();
+        let mut bins = vec![0u32; len];
+        for (x, y) in ink {
+            let row = (y - x * slope + max_x).round();
+            if row >= 0.0 && (row as usize) < len {
+                bins[row as usize] += 1;
+            }
+        }
+        let score: f64 = bins.iter().map(|&n| (n as f64) * (n as f64)).sum();
+        if best.as_ref().is_none_or(|(_, top, _)| score > top * 1.0001) {
+            best = Some((angle, score, bins));
+        }
+    }
+    best.map(|(angle, _, bins)| (angle, bins)).unwrap_or_default()
+}
+
+/// Turn clockwise by a multiple of 90 degrees
+pub fn rotate_quarters(image: &GrayImage, degrees: u16) -> GrayImage {
+    match degrees % 360 {
+        90 => imageops::rotate90(image),
+        180 => imageops::rotate180(image),
+        270 => imageops::rotate270(image),
+        _ => image.clone(),
+    }
+}
+
+/// Straighten lines that fall `degrees` to the right, filling the corners with white
+pub fn deskew(image: &GrayImage, degrees: f32) -> GrayImage {
+    let (width, height) = image.dimensions();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    GrayImage::from_fn(width, height, |x, y| {
+        let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+        let sx = (cx + dx * cos - dy * sin).round();
+        let sy = (cy + dx * sin + dy * cos).round();
+        if sx >= 0.0 && sy >= 0.0 && (sx as u32) < width && (sy as u32) < height {
+            *image.get_pixel(sx as u32, sy as u32)
+        } else {
+            Luma([255])
+        }
+    })
+}
+
+/// Turn upright and straighten a page image, returning what was applied
+pub fn correct_image(image: &GrayImage) -> (GrayImage, Correction) {
+    let rotated = detect_rotation(image);
+    let upright = rotate_quarters(image, rotated);
+    let skew = detect_skew(&upright);
+    let deskewed = if skew.abs() >= MIN_SKEW_DEGREES { skew } else { 0.0 };
+    let corrected = if deskewed != 0.0 { deskew(&upright, deskewed) } else { upright };
+    (corrected, Correction { pdf_rotate: 0, rotated, deskewed })
+}
+
+/// Correct a rendered page in place; `pdf_rotate` is the page's /Rotate, recorded as is
+pub fn correct_page_png(png: &Path, pdf_rotate: i64) -> Result<Correction> {
+    let image = image::open(png)?.to_luma8();
+    let (corrected, mut correction) = correct_image(&image);
+    if correction.rotated != 0 || correction.deskewed != 0.0 {
+        corrected.save(png)?;
+    }
+    correction.pdf_rotate = pdf_rotate;
+    Ok(correction)
+}
+
+fn analysis_copy(image: &GrayImage) -> GrayImage {
+    if image.width() <= ANALYSIS_WIDTH {
+        return image.clone();
+    }
+    let height = (image.height() as u64 * ANALYSIS_WIDTH as u64 / image.width() as u64).max(1) as u32;
+    imageops::resize(image, ANALYSIS_WIDTH, height, FilterType::Triangle)
+}
+
+fn ink_points(image: &GrayImage) -> Vec<(f32, f32)> {
+    image.enumerate_pixels().filter(|(_, _, p)| p[0] < INK).map(|(x, y, _)| (x as f32, y as f32)).collect()
+}
+
+/// How abruptly a profile changes - high for bands with gaps between them
+fn sharpness(profile: &[u32]) -> f64 {
+    let total: f64 = profile.iter().map(|&n| n as f64).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    let jumps: f64 = profile.windows(2).map(|w| (w[1] as f64 - w[0] as f64).powi(2)).sum();
+    jumps / total
+}
+
+/// More ink below the lines' x-height bands than above them, in a row profile
+fn upside_down(rows: &[u32]) -> bool {
+    let floor = rows.iter().copied().max().unwrap_or(0) / 50;
+    let (mut above, mut below) = (0u64, 0u64);
+
+    let mut start = None;
+    for (index, &ink) in rows.iter().chain(std::iter::once(&0)).enumerate() {
+        match (start, ink > floor) {
+            (None, true) => start = Some(index),
+            (Some(top), false) => {
+                let line = &rows[top..index];
+                let peak = line.iter().copied().max().unwrap_or(0);
+                // The x-height band: rows at least half as inked as the densest
+                let band: Vec<usize> = (0..line.len()).filter(|&i| line[i] * 2 >= peak).collect();
+                if let (Some(&first), Some(&last)) = (band.first(), band.last()) {
+                    above += line[..first].iter().map(|&n| n as u64).sum::<u64>();
+                    below += line[last + 1..].iter().map(|&n| n as u64).sum::<u64>();
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    below > above
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines of "text": a solid x-height band with ascender stubs above it, one descender below
+    fn page(skew_degrees: f32) -> GrayImage {
+        let slope = skew_degrees.to_radians().tan();
+        let mut image = GrayImage::from_pixel(400, 520, Luma([255]));
+        for line in 0..12 {
+            let base = 40 + line * 36;
+            for x in 40..360u32 {
+                let shift = (x as f32 * slope).round() as i32;
+                let mut ink = |y: i32| image.put_pixel(x, (y + shift) as u32, Luma([0]));
+                (base..base + 10).for_each(&mut ink);
+                if x % 9 < 2 {
+                    (base - 6..base).for_each(&mut ink);
+                }
+                if x % 45 < 2 {
+                    (base + 10..base + 14).for_each(ink);
+                }
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_rotation_and_skew_detected_and_undone() {
+        let upright = page(0.0);
+        assert_eq!(detect_rotation(&upright), 0);
+        assert_eq!(detect_rotation(&imageops::rotate90(&upright)), 270);
+        assert_eq!(detect_rotation(&imageops::rotate180(&upright)), 180);
+        assert_eq!(detect_rotation(&imageops::rotate270(&upright)), 90);
+        assert_eq!(detect_skew(&upright), 0.0);
+
+        let skewed = page(2.0);
+        assert!((detect_skew(&skewed) - 2.0).abs() <= SKEW_STEP_DEGREES);
+        let (straightened, correction) = correct_image(&skewed);
+        assert_eq!(correction.rotated, 0);
+        assert!(detect_skew(&straightened).abs() < MIN_SKEW_DEGREES);
+
+        assert_eq!(correct_image(&GrayImage::from_pixel(50, 50, Luma([255]))).1, Correction::default());
+    }
+}
@@ -0,0 +1,327 @@
+// Extracted-text quality assessment
+//
+// QualityChecker runs a set of checks over a page's text, each scoring 0.0-1.0,
+// and combines them into a weighted mean. A check can abstain (None) when it has
+// nothing to say, e.g. entropy on a few words, and then carries no weight.
+// Thresholds come from the [quality] section of the extraction config; extra
+// checks can be plugged in with QualityChecker::with_check.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Common English words - enough to tell prose from OCR noise, not a spell checker
+const COMMON_WORDS: &str = "the of and to a in is it you that he was for on are with as i his they be at one
+have this from or had by not word but what some we can out other were all there when up use your how said
+an each she which do their time if will way about many then them write would like so these her long make
+thing see him two has look more day could go come did number sound no most people my over know water than
+call first who may down side been now find any new work part take get place made live where after back
+little only round man year came show every good me give our under name very through just form sentence great
+think say help low line differ turn cause much mean before move right boy old too same tell does set three
+want air well also play small end put home read hand port large spell add even land here must big high such
+follow act why ask men change went light kind off need house picture try us again animal point mother world
+near build self earth father head stand own page should country found answer school grow study still learn
+plant cover food sun four between state keep eye never last let thought city tree cross farm hard start
+might story saw far sea draw left late run while press close night real life few north open seem together
+next white children begin got walk example ease paper group always music those both mark often letter until
+mile river car feet care second book carry took science eat room friend began idea fish mountain stop once
+base hear horse cut sure watch color face wood main enough plain girl usual young ready above ever red list
+though feel talk bird soon body dog family direct pose leave song measure door product black short numeral
+class wind question happen complete ship area half rock order fire south problem piece told knew pass since
+top whole king space heard best hour better true during hundred five remember step early hold west ground
+interest reach fast verb sing listen six table travel less morning ten simple several vowel toward war lay
+against pattern slow center love person money serve appear road map rain rule govern pull cold notice voice
+unit power town fine certain fly fall lead cry dark machine note wait plan figure star box noun field rest
+correct able pound done beauty drive stood contain front teach week final gave green quick develop ocean
+warm free minute strong special mind behind clear tail produce fact street inch multiply nothing course stay
+wheel full force blue object decide surface deep moon island foot system busy test record boat common gold
+possible plane stead dry wonder laugh thousand ago ran check game shape equate hot miss brought heat snow
+tire bring yes distant fill east paint language among total date amount due invoice account payment price
+report section agreement contract party shall service services company information data value table figure";
+
+static DICTIONARY: Lazy<HashSet<&'static str>> = Lazy::new(|| COMMON_WORDS.split_whitespace().collect());
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QualityConfig {
+    /// whatlang confidence at or above which the language check fully passes
+    #[serde(default = "default_min_language_confidence")]
+    pub min_language_confidence: f64,
+    /// Share of words found in the dictionary at or above which that check fully passes
+    #[serde(default = "default_min_dictionary_hit_rate")]
+    pub min_dictionary_hit_rate: f32,
+    /// Character bigram conditional entropy (bits) expected of real text; outside this
+    /// range the score falls off linearly, reaching 0 one bit away
+    #[serde(default = "default_min_entropy")]
+    pub min_entropy: f32,
+    #[serde(default = "default_max_entropy")]
+    pub max_entropy: f32,
+    /// Combined score below which text is treated as gibberish
+    #[serde(default = "default_gibberish_below")]
+    pub gibberish_below: f32,
+    /// A single check scoring below this marks the text as gibberish on its own -
+    /// OCR noise often still looks like a language and has sane entropy
+    #[serde(default = "default_fail_below")]
+    pub fail_below: f32,
+    /// Weight per check name; checks not listed weigh 1.0
+    #[serde(default = "default_weights")]
+    pub weights: HashMap<String, f32>,
+}
+
+fn default_min_language_confidence() -> f64 { 0.7 }
+fn default_min_dictionary_hit_rate() -> f32 { 0.3 }
+fn default_min_entropy() -> f32 { 2.2 }
+fn default_max_entropy() -> f32 { 3.9 }
+fn default_gibberish_below() -> f32 { 0.4 }
+fn default_fail_below() -> f32 { 0.25 }
+fn default_weights() -> HashMap<String, f32> { HashMap::from([("dictionary".to_string(), 2.0)]) }
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            min_language_confidence: default_min_language_confidence(),
+            min_dictionary_hit_rate: default_min_dictionary_hit_rate(),
+            min_entropy: default_min_entropy(),
+            max_entropy: default_max_entropy(),
+            gibberish_below: default_gibberish_below(),
+            fail_below: default_fail_below(),
+            weights: default_weights(),
+        }
+    }
+}
+
+/// One quality signal over a page of text
+pub trait QualityCheck: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// 0.0 (garbage) to 1.0 (clean), or None when the check doesn't apply
+    fn score(&self, text: &str) -> Option<f32>;
+}
+
+/// How confidently whatlang recognizes a language at all
+pub struct LanguageConfidence {
+    pub min_confidence: f64,
+}
+
+impl QualityCheck for LanguageConfidence {
+    fn name(&self) -> &'static str { "language" }
+
+    fn score(&self, text: &str) -> Option<f32> {
+        let confidence = whatlang::detect(text).map(|info| info.confidence()).unwrap_or(0.0);
+        Some((confidence / self.min_confidence.max(f64::EPSILON)).min(1.0) as f32)
+    }
+}
+
+/// Share of words that are common English words. Abstains when whatlang is
+/// sure the text is in another language.
+pub struct DictionaryHitRate {
+    pub min_hit_rate: f32,
+}
+
+impl QualityCheck for DictionaryHitRate {
+    fn name(&self) -> &'static str { "dictionary" }
+
+    fn score(&self, text: &str) -> Option<f32> {
+        if let Some(info) = whatlang::detect(text) {
+            if info.is_reliable() && info.lang() != whatlang::Lang::Eng {
+                return None;
+            }
+        }
+        let words: Vec<String> = text
+            .split_whitespace()
+            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+            .filter(|w| !w.is_empty() && !w.chars().all(|c| c.is_numeric()))
+            .collect();
+        if words.is_empty() {
+            return Some(0.0);
+        }
+        let hits = words.iter().filter(|w| DICTIONARY.contains(w.as_str())).count();
+        let rate = hits as f32 / words.len() as f32;
+        Some((rate / self.min_hit_rate.max(f32::EPSILON)).min(1.0))
+    }
+}
+
+/// Conditional entropy of the next character given the previous one. Real text sits
+/// in a middle band; random strings score high and repeated junk low.
+pub struct NgramEntropy {
+    pub min_bits: f32,
+    pub max_bits: f32,
+}
+
+/// Bigrams needed before the entropy estimate means anything
+const MIN_ENTROPY_BIGRAMS: usize = 200;
+
+impl QualityCheck for NgramEntropy {
+    fn name(&self) -> &'static str { "entropy" }
+
+    fn score(&self, text: &str) -> Option<f32> {
+        let bits = conditional_entropy(text)?;
+        let distance = if bits < self.min_bits {
+            self.min_bits - bits
+        } else if bits > self.max_bits {
+            bits - self.max_bits
+        } else {
+            0.0
+        };
+        Some((1.0 - distance).max(0.0))
+    }
+}
+
+/// H(next | previous) in bits over lowercase letters and single spaces
+fn conditional_entropy(text: &str) -> Option<f32> {
+    let mut chars: Vec<char> = Vec::new();
+    for c in text.chars().flat_map(|c| c.to_lowercase()) {
+        let c = if c.is_alphabetic() { c } else { ' ' };
+        if c == ' ' && chars.last() == Some(&' ') {
+            continue;
+        }
+        chars.push(c);
+    }
+    if chars.len() <= MIN_ENTROPY_BIGRAMS {
+        return None;
+    }
+
+    let mut bigrams: HashMap<(char, char), usize> = HashMap::new();
+    let mut firsts: HashMap<char, usize> = HashMap::new();
+    for pair in chars.windows(2) {
+        *bigrams.entry((pair[0], pair[1])).or_insert(0) += 1;
+        *firsts.entry(pair[0]).or_insert(0) += 1;
+    }
+    let total = (chars.len() - 1) as f32;
+    let entropy = |counts: &mut dyn Iterator<Item = usize>| {
+        counts.map(|n| n as f32 / total).map(|p| -p * p.log2()).sum::<f32>()
+    };
+    Some(entropy(&mut bigrams.values().copied()) - entropy(&mut firsts.values().copied()))
+}
+
+/// The original layout heuristics: length, sentences, vowel ratio, word shape, whitespace
+pub struct TextStructure;
+
+impl QualityCheck for TextStructure {
+    fn name(&self) -> &'static str { "structure" }
+
+    fn score(&self, text: &str) -> Option<f32> {
+        let len = text.len().max(1) as f32;
+        let vowel_ratio = text.chars().filter(|c| "aeiouAEIOU".contains(*c)).count() as f32 / len;
+        let whitespace_ratio = text.chars().filter(|c| c.is_whitespace()).count() as f32 / len;
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let word_like = words
+            .iter()
+            .filter(|w| w.len() >= 2 && w.len() <= 20)
+            .filter(|w| w.chars().filter(|c| c.is_alphabetic()).count() as f32 / w.len() as f32 > 0.7)
+            .count();
+
+        let checks = [
+            text.len() > 10,
+            text.contains(". "),
+            (0.1..=0.6).contains(&vowel_ratio),
+            !words.is_empty() && word_like as f32 / words.len() as f32 > 0.5,
+            whitespace_ratio > 0.05 && whitespace_ratio < 0.5,
+        ];
+        Some(checks.iter().filter(|&&passed| passed).count() as f32 / checks.len() as f32)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckScore {
+    pub name: &'static str,
+    pub score: Option<f32>,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityReport {
+    pub checks: Vec<CheckScore>,
+    /// Weighted mean of the checks that applied
+    pub combined: f32,
+    pub gibberish: bool,
+}
+
+pub struct QualityChecker {
+    checks: Vec<(Box<dyn QualityCheck>, f32)>,
+    gibberish_below: f32,
+    fail_below: f32,
+}
+
+impl Default for QualityChecker {
+    fn default() -> Self {
+        Self::new(&QualityConfig::default())
+    }
+}
+
+impl QualityChecker {
+    /// The built-in checks with the config's thresholds and weights
+    pub fn new(config: &QualityConfig) -> Self {
+        let mut checker = Self {
+            checks: Vec::new(),
+            gibberish_below: config.gibberish_below,
+            fail_below: config.fail_below,
+        };
+        let builtin: Vec<Box<dyn QualityCheck>> = vec![
+            Box::new(LanguageConfidence { min_confidence: config.min_language_confidence }),
+            Box::new(DictionaryHitRate { min_hit_rate: config.min_dictionary_hit_rate }),
+            Box::new(NgramEntropy { min_bits: config.min_entropy, max_bits: config.max_entropy }),
+            Box::new(TextStructure),
+        ];
+        for check in builtin {
+            let weight = config.weights.get(check.name()).copied().unwrap_or(1.0);
+            checker.checks.push((check, weight));
+        }
+        checker
+    }
+
+    /// Add a custom check alongside the built-in ones
+    pub fn with_check(mut self, check: Box<dyn QualityCheck>, weight: f32) -> Self {
+        self.checks.push((check, weight));
+        self
+    }
+
+    pub fn assess(&self, text: &str) -> QualityReport {
+        if text.trim().is_empty() {
+            let checks = self.checks.iter().map(|(c, w)| CheckScore { name: c.name(), score: Some(0.0), weight: *w }).collect();
+            return QualityReport { checks, combined: 0.0, gibberish: true };
+        }
+
+        let checks: Vec<CheckScore> = self
+            .checks
+            .iter()
+            .map(|(check, weight)| CheckScore { name: check.name(), score: check.score(text), weight: *weight })
+            .collect();
+        let (sum, weights) = checks
+            .iter()
+            .filter_map(|c| c.score.map(|s| (s * c.weight, c.weight)))
+            .fold((0.0, 0.0), |(sum, weights), (s, w)| (sum + s, weights + w));
+        let combined = if weights > 0.0 { sum / weights } else { 0.0 };
+        let failed = checks.iter().any(|c| c.score.is_some_and(|s| s < self.fail_below));
+        QualityReport { checks, combined, gibberish: failed || combined < self.gibberish_below }
+    }
+
+    pub fn score(&self, text: &str) -> f32 {
+        self.assess(text).combined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checks_separate_prose_from_noise() {
+        let checker = QualityChecker::default();
+        let prose = "The contract shall remain in force for a period of two years from the date of signing. \
+            Either party may end the agreement with written notice to the other party. Payment is due within \
+            thirty days of the invoice date, and any amount not paid by then will be charged interest at the \
+            rate set out in the schedule. The parties agree to keep all information about this agreement private.";
+        // Typical output of OCR on a noisy scan
+        let noise = "Tbe c0ntrcat sba1l rernain ln f0rce f0r a perlod 0f tw0 yeers fr0rn tbe dale 0f siguing. \
+            Eltber parly rnay eud tbe agreernent wltb wrltten n0tlce t0 tbe 0tber parly. Payrnent ls dve wltbin \
+            tbirty dcys 0f tbe lnv0ice dale, aud auy arn0unt u0t pald by tbeu wlll be cbarged lnterest al tbe \
+            rale sel 0ut ln tbe scbedu1e.";
+
+        let clean = checker.assess(prose);
+        let garbage = checker.assess(noise);
+        assert!(clean.combined > 0.8, "{:?}", clean);
+        assert!(garbage.gibberish && garbage.combined < clean.combined, "{:?}", garbage);
+        assert!(!clean.gibberish);
+        assert!(clean.checks.iter().all(|c| c.score.is_some()), "every check applies to a long page");
+    }
+}
@@ -0,0 +1,295 @@
+// Results from other OCR tools - plain text, hOCR and ALTO sidecar files
+//
+// hOCR (Tesseract, ABBYY via converters) and ALTO (ABBYY, library digitisation
+// pipelines) carry a box per word in the coordinates of the image that was
+// OCR'd. Those are scaled onto the PDF page so imported words line up with the
+// ones chonker8 extracts itself. Plain text has no boxes; pages are separated by
+// form feeds, as in pdftotext output.
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+use super::bbox::{unescape_html, BoxWord, PageWords};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidecarFormat {
+    Text,
+    Hocr,
+    Alto,
+}
+
+impl SidecarFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SidecarFormat::Text => "text",
+            SidecarFormat::Hocr => "hocr",
+            SidecarFormat::Alto => "alto",
+        }
+    }
+}
+
+/// One page of an imported result
+#[derive(Debug, Clone)]
+pub struct ImportedPage {
+    pub text: String,
+    /// Word boxes in the source's own units, when the format has them
+    pub words: Option<PageWords>,
+}
+
+impl ImportedPage {
+    /// Word boxes scaled onto a PDF page of the given size in points
+    pub fn words_on_page(&self, pdf_width: f32, pdf_height: f32) -> Option<Vec<BoxWord>> {
+        let words = self.words.as_ref()?;
+        if words.width <= 0.0 || words.height <= 0.0 {
+            return None;
+        }
+        let (sx, sy) = (pdf_width / words.width, pdf_height / words.height);
+        Some(
+            words
+                .words
+                .iter()
+                .map(|w| BoxWord { text: w.text.clone(), x0: w.x0 * sx, y0: w.y0 * sy, x1: w.x1 * sx, y1: w.y1 * sy })
+                .collect(),
+        )
+    }
+}
+
+pub fn parse_sidecar(format: SidecarFormat, contents: &str) -> Result<Vec<ImportedPage>> {
+    match format {
+        SidecarFormat::Text => Ok(contents
+            .trim_end_matches('\x0c')
+            .split('\x0c')
+            .map(|text| ImportedPage { text: text.to_string(), words: None })
+            .collect()),
+        SidecarFormat::Hocr => parse_hocr(contents),
+        SidecarFormat::Alto => parse_alto(contents),
+    }
+}
+
+/// Builds pages line by line while walking the markup
+#[derive(Default)]
+struct PageBuilder {
+    pages: Vec<ImportedPage>,
+    lines: Vec<Vec<String>>,
+    words: Vec<BoxWord>,
+    size: (f32, f32),
+}
+
+impl PageBuilder {
+    fn start_page(&mut self, width: f32, height: f32) {
+        self.finish_page();
+        self.size = (width, height);
+    }
+
+    fn start_line(&mut self) {
+        if !matches!(self.lines.last(), Some(line) if line.is_empty()) {
+            self.lines.push(Vec::new());
+        }
+    }
+
+    fn add_word(&mut self, word: BoxWord) {
+        if word.text.trim().is_empty() {
+            return;
+        }
+        if self.lines.is_empty() {
+            self.lines.push(Vec::new());
+        }
+        if let Some(line) = self.lines.last_mut() {
+            line.push(word.text.clone());
+        }
+        self.words.push(word);
+    }
+
+    fn finish_page(&mut self) {
+        if self.lines.is_empty() && self.size == (0.0, 0.0) {
+            return;
+        }
+        let lines: Vec<String> = self.lines.drain(..).filter(|l| !l.is_empty()).map(|l| l.join(" ")).collect();
+        let (width, height) = std::mem::take(&mut self.size);
+        self.pages.push(ImportedPage {
+            text: lines.join("\n"),
+            words: Some(PageWords { width, height, words: std::mem::take(&mut self.words) }),
+        });
+    }
+
+    fn finish(mut self) -> Vec<ImportedPage> {
+        self.finish_page();
+        self.pages
+    }
+}
+
+/// Opening tags, closing tags and text between them - enough for hOCR and ALTO,
+/// which never rely on anything fancier
+struct Markup {
+    token: Regex,
+    attr: Regex,
+}
+
+enum Token<'a> {
+    Open { name: &'a str, attrs: &'a str, empty: bool },
+    Close,
+    Text(&'a str),
+}
+
+impl Markup {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            token: Regex::new(r"(?s)<!--.*?-->|<[?!][^>]*>|<(/?)([\w:.-]+)([^>]*?)(/?)>|([^<]+)")?,
+            attr: Regex::new(r#"([\w:.-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#)?,
+        })
+    }
+
+    fn tokens<'a>(&'a self, xml: &'a str) -> impl Iterator<Item = Token<'a>> + 'a {
+        self.token.captures_iter(xml).filter_map(|caps| {
+            if let Some(text) = caps.get(5) {
+                return Some(Token::Text(text.as_str()));
+            }
+            let name = caps.get(2)?.as_str();
+            if &caps[1] == "/" {
+                return Some(Token::Close);
+            }
+            Some(Token::Open { name, attrs: caps.get(3).map_or("", |m| m.as_str()), empty: &caps[4] == "/" })
+        })
+    }
+
+    fn attr(&self, attrs: &str, name: &str) -> Option<String> {
+        self.attr
+            .captures_iter(attrs)
+            .find(|caps| caps[1].eq_ignore_ascii_case(name))
+            .map(|caps| unescape_html(caps.get(2).or(caps.get(3)).map_or("", |m| m.as_str())))
+    }
+}
+
+/// `bbox x0 y0 x1 y1` from an hOCR title attribute
+fn hocr_bbox(title: &str) -> Option<[f32; 4]> {
+    let rest = title.split(';').map(str::trim).find_map(|prop| prop.strip_prefix("bbox "))?;
+    let numbers: Vec<f32> = rest.split_whitespace().filter_map(|n| n.parse().ok()).collect();
+    (numbers.len() == 4).then(|| [numbers[0], numbers[1], numbers[2], numbers[3]])
+}
+
+fn parse_hocr(html: &str) -> Result<Vec<ImportedPage>> {
+    let markup = Markup::new()?;
+    let mut builder = PageBuilder::default();
+    // Open word: its box, the text so far, and how many tags deep we are inside it
+    let mut word: Option<([f32; 4], String, usize)> = None;
+
+    for token in markup.tokens(html) {
+        match token {
+            Token::Open { name, attrs, empty } => {
+                if let Some((_, _, depth)) = word.as_mut() {
+                    if !empty {
+                        *depth += 1;
+                    }
+                    continue;
+                }
+                let class = markup.attr(attrs, "class").unwrap_or_default();
+                let title = markup.attr(attrs, "title").unwrap_or_default();
+                let classes: Vec<&str> = class.split_whitespace().collect();
+                if classes.contains(&"ocr_page") {
+                    let [x0, y0, x1, y1] = hocr_bbox(&title).unwrap_or_default();
+                    builder.start_page(x1 - x0, y1 - y0);
+                } else if classes.iter().any(|c| matches!(*c, "ocr_line" | "ocrx_line" | "ocr_caption" | "ocr_header" | "ocr_textfloat")) {
+                    builder.start_line();
+                } else if classes.contains(&"ocrx_word") && !empty {
+                    if let Some(bbox) = hocr_bbox(&title) {
+                        word = Some((bbox, String::new(), 1));
+                    }
+                } else if name.eq_ignore_ascii_case("br") {
+                    builder.start_line();
+                }
+            }
+            Token::Close => {
+                if let Some((bbox, text, depth)) = word.as_mut() {
+                    *depth -= 1;
+                    if *depth == 0 {
+                        let [x0, y0, x1, y1] = *bbox;
+                        let text = unescape_html(text.trim());
+                        builder.add_word(BoxWord { text, x0, y0, x1, y1 });
+                        word = None;
+                    }
+                }
+            }
+            Token::Text(text) => {
+                if let Some((_, buffer, _)) = word.as_mut() {
+                    buffer.push_str(text);
+                }
+            }
+        }
+    }
+
+    let pages = builder.finish();
+    if pages.is_empty() {
+        return Err(anyhow!("No ocr_page elements found - is this an hOCR file?"));
+    }
+    Ok(pages)
+}
+
+fn parse_alto(xml: &str) -> Result<Vec<ImportedPage>> {
+    let markup = Markup::new()?;
+    let mut builder = PageBuilder::default();
+    let number = |attrs: &str, name: &str| markup.attr(attrs, name).and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+
+    for token in markup.tokens(xml) {
+        let Token::Open { name, attrs, .. } = token else { continue };
+        // Namespaced documents use alto:String etc.
+        match name.rsplit(':').next().unwrap_or(name) {
+            "Page" => builder.start_page(number(attrs, "WIDTH"), number(attrs, "HEIGHT")),
+            "TextLine" => builder.start_line(),
+            "String" => {
+                let (x, y) = (number(attrs, "HPOS"), number(attrs, "VPOS"));
+                builder.add_word(BoxWord {
+                    text: markup.attr(attrs, "CONTENT").unwrap_or_default(),
+                    x0: x,
+                    y0: y,
+                    x1: x + number(attrs, "WIDTH"),
+                    y1: y + number(attrs, "HEIGHT"),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let pages = builder.finish();
+    if pages.is_empty() {
+        return Err(anyhow!("No Page elements found - is this an ALTO file?"));
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hocr_and_alto_words_scale_onto_the_page() {
+        let hocr = r#"<div class='ocr_page' title='image "p1.png"; bbox 0 0 1000 2000; ppageno 0'>
+            <span class='ocr_line' title='bbox 100 200 600 250'>
+              <span class='ocrx_word' title='bbox 100 200 300 250; x_wconf 96'><strong>Total</strong></span>
+              <span class='ocrx_word' title='bbox 350 200 600 250; x_wconf 91'>&amp;more</span>
+            </span>
+            <span class='ocr_line' title='bbox 100 300 300 350'>
+              <span class='ocrx_word' title='bbox 100 300 300 350'>due</span>
+            </span>
+        </div>"#;
+        let pages = parse_sidecar(SidecarFormat::Hocr, hocr).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].text, "Total &more\ndue");
+        let words = pages[0].words_on_page(500.0, 1000.0).unwrap();
+        assert_eq!(words[0], BoxWord { text: "Total".to_string(), x0: 50.0, y0: 100.0, x1: 150.0, y1: 125.0 });
+
+        let alto = r#"<?xml version="1.0"?><alto><Layout>
+            <Page ID="p1" WIDTH="2000" HEIGHT="2000"><PrintSpace><TextBlock>
+              <TextLine><String CONTENT="Invoice" HPOS="200" VPOS="100" WIDTH="400" HEIGHT="50"/><SP/><String HPOS="650" VPOS="100" WIDTH="100" HEIGHT="50" CONTENT="42"/></TextLine>
+            </TextBlock></PrintSpace></Page>
+            <Page ID="p2" WIDTH="2000" HEIGHT="2000"/>
+        </Layout></alto>"#;
+        let pages = parse_sidecar(SidecarFormat::Alto, alto).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].text, "Invoice 42");
+        let words = pages[0].words_on_page(1000.0, 1000.0).unwrap();
+        assert_eq!((words[1].x0, words[1].x1), (325.0, 375.0));
+        assert_eq!(pages[1].text, "");
+
+        let text = parse_sidecar(SidecarFormat::Text, "one\x0ctwo\x0c").unwrap();
+        assert_eq!(text.iter().map(|p| p.text.as_str()).collect::<Vec<_>>(), vec!["one", "two"]);
+    }
+}
@@ -0,0 +1,286 @@
+// Invisible text layer - positioned words written into a PDF so viewers can search
+// and select text on scanned pages
+//
+// Words are drawn in text render mode 3 (neither filled nor stroked) with Courier,
+// whose glyphs are all 0.6 em wide, so horizontal scaling can stretch each word to
+// exactly the width of its box. The page's own content is wrapped in q/Q first so
+// whatever graphics state it leaves behind can't shift the layer. Streams added
+// here are tagged with TEXT_LAYER_KEY so a later run can find and replace them.
+use anyhow::{anyhow, Result};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+
+use super::bbox::BoxWord;
+use super::document_analyzer::page_dimensions;
+
+/// Resource name of the layer's font
+const FONT_NAME: &[u8] = b"ChonkerOCR";
+/// Marks content streams added by chonker8
+pub const TEXT_LAYER_KEY: &str = "ChonkerTextLayer";
+/// Courier advance width as a fraction of the font size
+const COURIER_ADVANCE: f32 = 0.6;
+/// Baseline sits this far above the bottom of a word box, as a fraction of its height
+const DESCENT: f32 = 0.2;
+
+pub struct TextLayer {
+    font: ObjectId,
+}
+
+impl TextLayer {
+    /// Add the layer's font to the document; one TextLayer serves every page
+    pub fn new(document: &mut Document) -> Self {
+        let font = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        Self { font }
+    }
+
+    /// Write words (PDF points, origin top-left) onto one page (0-based) as invisible
+    /// text. Returns how many words were written.
+    pub fn add_words(&self, document: &mut Document, page_index: usize, words: &[BoxWord]) -> Result<usize> {
+        let page_id = *document
+            .get_pages()
+            .get(&((page_index + 1) as u32))
+            .ok_or_else(|| anyhow!("Page {} not found", page_index + 1))?;
+        let (_, page_height) = page_dimensions(document, page_index)?;
+
+        let mut operations = vec![Operation::new("Q", vec![]), Operation::new("BT", vec![]), Operation::new("Tr", vec![3.into()])];
+        let mut written = 0;
+        for word in words {
+            let text = win_ansi(word.text.trim());
+            let (width, height) = (word.x1 - word.x0, word.height());
+            if text.is_empty() || width <= 0.0 || height <= 0.0 {
+                continue;
+            }
+            let scale = 100.0 * width / (COURIER_ADVANCE * height * text.len() as f32);
+            operations.extend([
+                Operation::new("Tf", vec![Object::Name(FONT_NAME.to_vec()), height.into()]),
+                Operation::new("Tz", vec![scale.into()]),
+                Operation::new(
+                    "Tm",
+                    vec![1.into(), 0.into(), 0.into(), 1.into(), word.x0.into(), (page_height - word.y1 + DESCENT * height).into()],
+                ),
+                Operation::new("Tj", vec![Object::String(text, StringFormat::Literal)]),
+            ]);
+            written += 1;
+        }
+        operations.push(Operation::new("ET", vec![]));
+
+        if written == 0 {
+            return Ok(0);
+        }
+        self.add_font_resource(document, page_id)?;
+        let layer = Content { operations }.encode()?;
+        let open = document.add_object(tagged_stream(b"q\n".to_vec()));
+        let close = document.add_object(tagged_stream(layer));
+
+        let page = document.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        let mut contents = match page.get(b"Contents") {
+            Ok(Object::Reference(id)) => vec![Object::Reference(*id)],
+            Ok(Object::Array(items)) => items.clone(),
+            _ => Vec::new(),
+        };
+        contents.insert(0, Object::Reference(open));
+        contents.push(Object::Reference(close));
+        page.set("Contents", contents);
+        Ok(written)
+    }
+
+    /// Give the page its own Resources with the layer font added, so resources
+    /// shared with other pages (or inherited from the page tree) stay untouched
+    fn add_font_resource(&self, document: &mut Document, page_id: ObjectId) -> Result<()> {
+        let (direct, inherited) = document.get_page_resources(page_id);
+        let mut resources = match direct {
+            Some(dict) => dict.clone(),
+            None => inherited.first().and_then(|id| document.get_dictionary(*id).ok()).cloned().unwrap_or_default(),
+        };
+        let mut fonts = match resources.get(b"Font") {
+            Ok(Object::Reference(id)) => document.get_dictionary(*id).cloned().unwrap_or_default(),
+            Ok(Object::Dictionary(dict)) => dict.clone(),
+            _ => Dictionary::new(),
+        };
+        fonts.set(FONT_NAME, Object::Reference(self.font));
+        resources.set("Font", fonts);
+
+        let page = document.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        page.set("Resources", resources);
+        Ok(())
+    }
+}
+
+/// Drop the layer streams an earlier run added to one page (0-based). Returns
+/// whether there was a layer to remove.
+pub fn remove_text_layer(document: &mut Document, page_index: usize) -> Result<bool> {
+    let page_id = *document
+        .get_pages()
+        .get(&((page_index + 1) as u32))
+        .ok_or_else(|| anyhow!("Page {} not found", page_index + 1))?;
+    let Ok(Object::Array(contents)) = document.get_dictionary(page_id)?.get(b"Contents") else {
+        // A single stream is always the page's own content
+        return Ok(false);
+    };
+
+    let kept: Vec<Object> = contents
+        .iter()
+        .filter(|item| {
+            let tagged = item
+                .as_reference()
+                .and_then(|id| document.get_object(id))
+                .and_then(Object::as_stream)
+                .map(|stream| stream.dict.has(TEXT_LAYER_KEY.as_bytes()));
+            !tagged.unwrap_or(false)
+        })
+        .cloned()
+        .collect();
+    if kept.len() == contents.len() {
+        return Ok(false);
+    }
+    let page = document.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+    page.set("Contents", kept);
+    Ok(true)
+}
+
+/// Put corrected text onto the boxes of the words it corrects. The two word
+/// sequences are aligned by edit distance: matched and substituted words take the
+/// box of the word they line up with, removed words drop out, and inserted words
+/// share the box of the word before them (or after, at the start of the page).
+/// Replacing a word with a similar one (a fixed OCR error) costs less than with an
+/// unrelated one, so insertions don't pull later words onto the wrong boxes.
+pub fn align_corrections(positioned: &[BoxWord], corrected: &str) -> Vec<BoxWord> {
+    let old: Vec<Vec<char>> = positioned.iter().map(|w| w.text.to_lowercase().chars().collect()).collect();
+    let new_words: Vec<&str> = corrected.split_whitespace().collect();
+    let new: Vec<Vec<char>> = new_words.iter().map(|w| w.to_lowercase().chars().collect()).collect();
+    let (n, m) = (old.len(), new.len());
+    let substitution = |i: usize, j: usize| -> u32 {
+        if old[i] == new[j] {
+            0
+        } else if 2 * char_distance(&old[i], &new[j]) <= old[i].len().max(new[j].len()) {
+            1
+        } else {
+            2
+        }
+    };
+
+    // cost[i][j]: edits to turn old[i..] into new[j..]
+    let mut cost = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..=n).rev() {
+        for j in (0..=m).rev() {
+            cost[i][j] = match (i == n, j == m) {
+                (true, true) => 0,
+                (true, false) => cost[i][j + 1] + 1,
+                (false, true) => cost[i + 1][j] + 1,
+                (false, false) => {
+                    let substitute = cost[i + 1][j + 1] + substitution(i, j);
+                    substitute.min(cost[i + 1][j] + 1).min(cost[i][j + 1] + 1)
+                }
+            };
+        }
+    }
+
+    let mut aligned: Vec<BoxWord> = Vec::with_capacity(m);
+    let mut leading: Vec<&str> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while j < m {
+        if i < n && cost[i][j] == cost[i + 1][j + 1] + substitution(i, j) {
+            let mut text = std::mem::take(&mut leading);
+            text.push(new_words[j]);
+            aligned.push(BoxWord { text: text.join(" "), ..positioned[i].clone() });
+            i += 1;
+            j += 1;
+        } else if i < n && cost[i][j] == cost[i + 1][j] + 1 {
+            i += 1;
+        } else {
+            match aligned.last_mut() {
+                Some(previous) => {
+                    previous.text.push(' ');
+                    previous.text.push_str(new_words[j]);
+                }
+                None => leading.push(new_words[j]),
+            }
+            j += 1;
+        }
+    }
+    aligned
+}
+
+fn char_distance(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (diagonal + usize::from(ca != cb)).min(above + 1).min(row[j] + 1);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+fn tagged_stream(content: Vec<u8>) -> Stream {
+    Stream::new(dictionary! { TEXT_LAYER_KEY => true }, content)
+}
+
+/// Latin-1 characters map straight onto WinAnsi; anything else becomes '?' so the
+/// word keeps its length and stays selectable
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars().map(|c| if (c as u32) < 256 && !c.is_control() { c as u8 } else { b'?' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corrections_keep_their_boxes() {
+        let word = |text: &str, x0: f32| BoxWord { text: text.to_string(), x0, y0: 0.0, x1: x0 + 10.0, y1: 10.0 };
+        let ocr = vec![word("Tota1", 0.0), word("arnount", 20.0), word("~", 40.0), word("due", 60.0)];
+        let aligned = align_corrections(&ocr, "Total amount now due");
+        let placed: Vec<(&str, f32)> = aligned.iter().map(|w| (w.text.as_str(), w.x0)).collect();
+        assert_eq!(placed, vec![("Total", 0.0), ("amount", 20.0), ("now", 40.0), ("due", 60.0)]);
+
+        let aligned = align_corrections(&ocr[..2], "The Total amount");
+        assert_eq!(aligned[0].text, "The Total");
+        assert_eq!(align_corrections(&ocr, "").len(), 0);
+    }
+
+    #[test]
+    fn test_layer_words_are_stretched_to_their_boxes() {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let content = document.add_object(Stream::new(Dictionary::new(), b"0 0 m".to_vec()));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 600.into(), 800.into()],
+            "Contents" => content,
+        });
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }));
+        let catalog = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog);
+
+        let layer = TextLayer::new(&mut document);
+        let words = vec![
+            BoxWord { text: "Total".to_string(), x0: 100.0, y0: 90.0, x1: 160.0, y1: 110.0 },
+            BoxWord { text: " ".to_string(), x0: 0.0, y0: 0.0, x1: 1.0, y1: 1.0 },
+        ];
+        assert_eq!(layer.add_words(&mut document, 0, &words).unwrap(), 1);
+
+        let contents = document.get_page_contents(page_id);
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1], content);
+        let added = String::from_utf8(document.get_page_content(page_id).unwrap()).unwrap();
+        assert!(added.starts_with("q\n0 0 m"));
+        // 60pt wide / (0.6 * 20pt * 5 chars) = 100%; baseline 800 - 110 + 4
+        assert!(added.contains("/ChonkerOCR 20 Tf"), "{}", added);
+        assert!(added.contains("100 Tz") && added.contains("1 0 0 1 100 694 Tm") && added.contains("(Total) Tj"), "{}", added);
+        assert!(document.get_page_fonts(page_id).contains_key(FONT_NAME));
+    }
+}
@@ -0,0 +1,330 @@
+// TrOCR text-line recognition - ViT encoder and text decoder exported to ONNX
+//
+// A model directory holds encoder_model.onnx, decoder_model.onnx (no KV cache),
+// tokenizer.json and the model's config.json, as `optimum-cli export onnx`
+// writes them for a VisionEncoderDecoder model. TrOCR reads one line of text
+// per image, so callers crop lines or fields first - `read_lines` does that for
+// a whole page. Decoding is a beam search: the few most likely partial lines are
+// extended together, one batched decoder run per token, and the finished line
+// with the best per-token log-probability wins.
+use anyhow::{anyhow, Result};
+use image::DynamicImage;
+use ort::session::{Session, SessionInputValue};
+use ort::value::Tensor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use tokenizers::Tokenizer;
+
+use crate::temp_files::scratch_dir;
+use super::bbox::BoxWord;
+use super::document_analyzer::PageFingerprint;
+use super::extraction_router::{ExtractionMethod, ExtractionResult};
+use super::extractors::{missing_tools, render_page_png, Extractor, OCR_DPI};
+use super::handwriting::{handwriting_regions, Region};
+use super::hybrid::layout_words;
+use super::models;
+
+/// Model for printed text, as used by `--engine trocr` and the document processor
+/// Under the models directory (see models.rs)
+pub const PRINTED_MODEL_DIR: &str = "trocr-printed";
+pub const TROCR_ENGINE: &str = "trocr";
+/// Hypotheses kept per step; 1 is greedy decoding
+pub const DEFAULT_BEAMS: usize = 4;
+
+/// TrOCR's input resolution
+const IMAGE_SIZE: u32 = 384;
+/// Tokens generated per line at most
+const MAX_TOKENS: usize = 64;
+
+pub struct TrOcr {
+    encoder: Session,
+    decoder: Session,
+    tokenizer: Tokenizer,
+    decoder_start: i64,
+    eos: i64,
+    beams: usize,
+}
+
+/// One line of a page and what the model read there
+#[derive(Debug, Clone)]
+pub struct RecognizedLine {
+    pub text: String,
+    pub confidence: f32,
+    /// Where the line is, in pixels of the page image
+    pub region: Region,
+}
+
+impl TrOcr {
+    /// Whether a model directory looks complete
+    pub fn available(dir: &Path) -> bool {
+        ["encoder_model.onnx", "decoder_model.onnx", "tokenizer.json", "config.json"]
+            .iter()
+            .all(|file| dir.join(file).exists())
+    }
+
+    pub fn load(dir: &Path) -> Result<Self> {
+        let _ = ort::init();
+        let session = |file: &str| -> Result<Session> {
+            Ok(Session::builder()?
+                .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
+                .with_intra_threads(4)?
+                .commit_from_file(dir.join(file))?)
+        };
+        let tokenizer_path = dir.join("tokenizer.json");
+        let tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|e| anyhow!("Loading {}: {}", tokenizer_path.display(), e))?;
+
+        // Token ids sit at the top level or under "decoder" depending on the export
+        let config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("config.json"))?)?;
+        let token = |key: &str| config[key].as_i64().or_else(|| config["decoder"][key].as_i64());
+        let eos = token("eos_token_id").ok_or_else(|| anyhow!("{}/config.json has no eos_token_id", dir.display()))?;
+        Ok(Self {
+            encoder: session("encoder_model.onnx")?,
+            decoder: session("decoder_model.onnx")?,
+            tokenizer,
+            decoder_start: token("decoder_start_token_id").unwrap_or(eos),
+            eos,
+            beams: DEFAULT_BEAMS,
+        })
+    }
+
+    /// Beam width for `recognize`
+    pub fn with_beams(mut self, beams: usize) -> Self {
+        self.beams = beams.max(1);
+        self
+    }
+
+    /// The text on a line image and the model's confidence in it (geometric mean
+    /// of the chosen tokens' probabilities)
+    pub fn recognize(&mut self, image: &DynamicImage) -> Result<(String, f32)> {
+        let pixels = pixel_values(image);
+        let size = IMAGE_SIZE as usize;
+        let encoded = self.encoder.run(vec![(
+            "pixel_values".to_string(),
+            SessionInputValue::from(Tensor::from_array(([1usize, 3, size, size], pixels.into_boxed_slice()))?),
+        )])?;
+        let (shape, states) = encoded[0].try_extract_tensor::<f32>()?;
+        let (patches, hidden) = (shape[1] as usize, shape[2] as usize);
+        let states = states.to_vec();
+        drop(encoded);
+
+        let decoder = &mut self.decoder;
+        let (tokens, log_prob) = beam_search(self.decoder_start, self.eos, self.beams, MAX_TOKENS, |sequences| {
+            let (count, length) = (sequences.len(), sequences[0].len());
+            let ids: Vec<i64> = sequences.concat();
+            let repeated: Vec<f32> = (0..count).flat_map(|_| states.iter().copied()).collect();
+            let outputs = decoder.run(vec![
+                ("input_ids".to_string(), SessionInputValue::from(Tensor::from_array(([count, length], ids.into_boxed_slice()))?)),
+                ("encoder_hidden_states".to_string(), SessionInputValue::from(Tensor::from_array(([count, patches, hidden], repeated.into_boxed_slice()))?)),
+            ])?;
+            let (shape, logits) = outputs[0].try_extract_tensor::<f32>()?;
+            let vocab = shape[2] as usize;
+            // Only the last position of each sequence predicts the next token
+            Ok((0..count).map(|b| logits[(b * length + length - 1) * vocab..(b * length + length) * vocab].to_vec()).collect())
+        })?;
+
+        let confidence = if tokens.is_empty() { 0.0 } else { log_prob.exp() };
+        let ids: Vec<u32> = tokens.iter().map(|&id| id as u32).collect();
+        let text = self.tokenizer.decode(&ids, true).map_err(|e| anyhow!("Decoding: {}", e))?;
+        Ok((text.trim().to_string(), confidence))
+    }
+
+    /// Every line of text on a page image rendered at `dpi`, top to bottom
+    pub fn read_lines(&mut self, page: &DynamicImage, dpi: u32) -> Result<Vec<RecognizedLine>> {
+        let mut lines = Vec::new();
+        // With nothing marked as printed, the handwriting finder returns every line of ink
+        for region in handwriting_regions(&page.to_luma8(), &[], dpi) {
+            let crop = page.crop_imm(region.x0, region.y0, region.x1 - region.x0, region.y1 - region.y0);
+            let (text, confidence) = self.recognize(&crop)?;
+            if !text.is_empty() {
+                lines.push(RecognizedLine { text, confidence, region });
+            }
+        }
+        Ok(lines)
+    }
+}
+
+/// Beam search over a decoder. `step` is given the live sequences (all the same
+/// length) and returns each one's logits for the next token. Returns the best
+/// sequence without the start token, and its mean log-probability per token
+/// (end token included when it finished).
+fn beam_search(
+    start: i64,
+    eos: i64,
+    width: usize,
+    max_tokens: usize,
+    mut step: impl FnMut(&[Vec<i64>]) -> Result<Vec<Vec<f32>>>,
+) -> Result<(Vec<i64>, f32)> {
+    let width = width.max(1);
+    let mut live: Vec<(Vec<i64>, f32)> = vec![(vec![start], 0.0)];
+    let mut finished: Vec<(Vec<i64>, f32)> = Vec::new();
+
+    for _ in 0..max_tokens {
+        let sequences: Vec<Vec<i64>> = live.iter().map(|(tokens, _)| tokens.clone()).collect();
+        let logits = step(&sequences)?;
+        let mut candidates: Vec<(usize, i64, f32)> = Vec::new();
+        for (beam, row) in logits.iter().enumerate() {
+            for (token, log_prob) in top_log_probs(row, width) {
+                candidates.push((beam, token as i64, live[beam].1 + log_prob));
+            }
+        }
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        let mut next = Vec::with_capacity(width);
+        for (beam, token, score) in candidates {
+            if next.len() == width {
+                break;
+            }
+            if token == eos {
+                let tokens = live[beam].0[1..].to_vec();
+                let mean = score / (tokens.len() + 1) as f32;
+                finished.push((tokens, mean));
+            } else {
+                let mut tokens = live[beam].0.clone();
+                tokens.push(token);
+                next.push((tokens, score));
+            }
+        }
+        live = next;
+        if live.is_empty() || finished.len() >= width {
+            break;
+        }
+    }
+
+    // Lines that ran out of tokens still count, scored the same way
+    finished.extend(live.into_iter().map(|(tokens, score)| {
+        let tokens = tokens[1..].to_vec();
+        let mean = score / tokens.len().max(1) as f32;
+        (tokens, mean)
+    }));
+    finished
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .ok_or_else(|| anyhow!("Beam search produced nothing"))
+}
+
+/// RGB at 384x384 in CHW order, normalized to [-1, 1] like TrOCR's processor
+fn pixel_values(image: &DynamicImage) -> Vec<f32> {
+    let rgb = image.resize_exact(IMAGE_SIZE, IMAGE_SIZE, image::imageops::FilterType::Triangle).to_rgb8();
+    let size = (IMAGE_SIZE * IMAGE_SIZE) as usize;
+    let mut pixels = vec![0.0; 3 * size];
+    for (i, pixel) in rgb.pixels().enumerate() {
+        for c in 0..3 {
+            pixels[c * size + i] = pixel[c] as f32 / 127.5 - 1.0;
+        }
+    }
+    pixels
+}
+
+/// The `k` most likely tokens and their log-probabilities, most likely first
+pub(super) fn top_log_probs(logits: &[f32], k: usize) -> Vec<(usize, f32)> {
+    let max = logits.iter().copied().fold(f32::MIN, f32::max);
+    let log_total = logits.iter().map(|v| (v - max).exp()).sum::<f32>().ln() + max;
+    let mut ranked: Vec<(usize, f32)> = logits.iter().map(|&v| v - log_total).enumerate().collect();
+    let k = k.min(ranked.len());
+    if k < ranked.len() {
+        ranked.select_nth_unstable_by(k, |a, b| b.1.total_cmp(&a.1));
+        ranked.truncate(k);
+    }
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+}
+
+/// TrOCR over every line of the page. Forced only (--engine trocr): a decoder
+/// run per token per line is far slower than tesseract.
+pub struct TrOcrExtractor {
+    model_dir: PathBuf,
+    model: Mutex<Option<TrOcr>>,
+}
+
+impl Default for TrOcrExtractor {
+    fn default() -> Self {
+        Self { model_dir: models::locate(PRINTED_MODEL_DIR), model: Mutex::new(None) }
+    }
+}
+
+impl Extractor for TrOcrExtractor {
+    fn name(&self) -> &'static str {
+        TROCR_ENGINE
+    }
+
+    fn supports(&self, _fingerprint: &PageFingerprint) -> bool {
+        false
+    }
+
+    fn extract(&self, pdf_path: &Path, page_index: usize) -> Result<ExtractionResult> {
+        let start = Instant::now();
+        let mut model = self.model.lock().map_err(|_| anyhow!("TrOCR model lock poisoned"))?;
+        if model.is_none() {
+            if !TrOcr::available(&self.model_dir) {
+                return Err(anyhow!("No TrOCR model in {}", self.model_dir.display()));
+            }
+            *model = Some(TrOcr::load(&self.model_dir)?);
+        }
+        let model = model.as_mut().ok_or_else(|| anyhow!("TrOCR model not loaded"))?;
+
+        let temp_dir = scratch_dir()?;
+        let image = image::open(render_page_png(pdf_path, page_index, OCR_DPI, temp_dir.path())?)?;
+        let points = 72.0 / OCR_DPI as f32;
+        let lines = model.read_lines(&image, OCR_DPI)?;
+        let words: Vec<BoxWord> = lines
+            .iter()
+            .map(|line| BoxWord {
+                text: line.text.clone(),
+                x0: line.region.x0 as f32 * points,
+                y0: line.region.y0 as f32 * points,
+                x1: line.region.x1 as f32 * points,
+                y1: line.region.y1 as f32 * points,
+            })
+            .collect();
+        if !lines.is_empty() {
+            let mean = lines.iter().map(|l| l.confidence).sum::<f32>() / lines.len() as f32;
+            tracing::debug!("[TROCR] Page {}: {} lines, mean confidence {:.2}", page_index + 1, lines.len(), mean);
+        }
+
+        let mut result = ExtractionResult::new(layout_words(&words), ExtractionMethod::TrOcr);
+        result.extraction_time_ms = start.elapsed().as_millis() as u64;
+        Ok(result)
+    }
+
+    fn missing(&self) -> Option<String> {
+        if !TrOcr::available(&self.model_dir) {
+            return Some(format!("no TrOCR model in {}", self.model_dir.display()));
+        }
+        missing_tools(&["pdftoppm"])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beam_search_beats_greedy() {
+        let top = top_log_probs(&[0.0, 2.0_f32.ln(), 0.0], 1);
+        assert_eq!(top[0].0, 1);
+        assert!((top[0].1.exp() - 0.5).abs() < 1e-6);
+
+        // Token 1 looks best first, but only token 2 leads to a confident ending
+        let (eos, start) = (0, 9);
+        let model = |sequences: &[Vec<i64>]| -> Result<Vec<Vec<f32>>> {
+            Ok(sequences
+                .iter()
+                .map(|tokens| match tokens.last() {
+                    Some(&9) => vec![f32::MIN, 0.6f32.ln(), 0.4f32.ln()],
+                    Some(&1) => vec![0.3f32.ln(), 0.4f32.ln(), 0.3f32.ln()],
+                    _ => vec![0.0, f32::MIN, f32::MIN],
+                })
+                .collect())
+        };
+        assert_eq!(beam_search(start, eos, 1, 2, model).unwrap().0, vec![1, 1]);
+        let (tokens, log_prob) = beam_search(start, eos, 2, 8, model).unwrap();
+        assert_eq!(tokens, vec![2]);
+        assert!((log_prob - 0.4f32.ln() / 2.0).abs() < 1e-5);
+
+        let black = DynamicImage::new_rgb8(10, 4);
+        let pixels = pixel_values(&black);
+        assert_eq!(pixels.len(), 3 * 384 * 384);
+        assert!(pixels.iter().all(|&p| p == -1.0));
+    }
+}
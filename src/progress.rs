@@ -0,0 +1,89 @@
+// Progress bars and log levels for the CLI
+//
+// Stdout carries only a command's output - text, tables, JSON - so it can be piped.
+// Everything else goes to stderr through tracing: status lines at info, shown by
+// default; diagnostics at debug (-v) and trace (-vv); --quiet leaves errors only.
+// Progress bars draw on stderr too, only when it is a terminal, and log lines are
+// printed above them rather than through them.
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use once_cell::sync::Lazy;
+use std::io::Write;
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Every bar shown at once, so log lines can be written around all of them
+static BARS: Lazy<MultiProgress> = Lazy::new(MultiProgress::new);
+
+/// Levels for chonker8's own messages and for its dependencies' (ort, tokenizers, ...)
+pub fn levels(quiet: bool, verbose: u8) -> (LevelFilter, LevelFilter) {
+    match (quiet, verbose) {
+        (true, _) => (LevelFilter::ERROR, LevelFilter::ERROR),
+        (false, 0) => (LevelFilter::INFO, LevelFilter::WARN),
+        (false, 1) => (LevelFilter::DEBUG, LevelFilter::WARN),
+        (false, _) => (LevelFilter::TRACE, LevelFilter::DEBUG),
+    }
+}
+
+/// Install the stderr logger; call once, first thing in main
+pub fn init(quiet: bool, verbose: u8) {
+    let (own, deps) = levels(quiet, verbose);
+    if quiet {
+        BARS.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    // Plain status lines by default; levels and modules once diagnostics are asked for
+    let format = tracing_subscriber::fmt::layer()
+        .without_time()
+        .with_level(verbose > 0)
+        .with_target(verbose > 0)
+        .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stderr()))
+        .with_writer(|| AboveBars(Vec::new()));
+    let _ = tracing_subscriber::registry()
+        .with(format)
+        .with(Targets::new().with_target("chonker8", own).with_default(deps))
+        .try_init();
+}
+
+/// A bar counting `len` items of `unit` (files, pages); finish it with `finish_and_clear`
+pub fn bar(len: usize, unit: &str) -> ProgressBar {
+    let style = ProgressStyle::with_template(&format!("{{spinner}} [{{bar:30}}] {{pos}}/{{len}} {} {{msg}} ({{eta}})", unit))
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ");
+    BARS.add(ProgressBar::new(len as u64).with_style(style))
+}
+
+/// One formatted log line, written out with the bars cleared for a moment
+struct AboveBars(Vec<u8>);
+
+impl Write for AboveBars {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for AboveBars {
+    fn drop(&mut self) {
+        BARS.suspend(|| {
+            let _ = std::io::stderr().write_all(&self.0);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_and_verbose_levels() {
+        assert_eq!(levels(false, 0), (LevelFilter::INFO, LevelFilter::WARN));
+        assert_eq!(levels(false, 1).0, LevelFilter::DEBUG);
+        assert_eq!(levels(false, 2), (LevelFilter::TRACE, LevelFilter::DEBUG));
+        // --quiet wins over -v
+        assert_eq!(levels(true, 2), (LevelFilter::ERROR, LevelFilter::ERROR));
+    }
+}
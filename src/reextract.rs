@@ -0,0 +1,86 @@
+// Re-extraction - `chonker8 reextract`
+//
+// Runs stored documents through extraction again, e.g. after an engine upgrade,
+// and merges the result page by page: a fresh page replaces the stored one only
+// when it scores higher on the configured quality checks, so a run with a worse
+// engine can't overwrite good text. --force takes every fresh page. When the PDF
+// no longer has the stored number of pages the pages can't be paired, and the
+// documents' mean scores decide for all of them at once.
+use crate::pdf_extraction::{ExtractionResult, QualityChecker};
+
+/// How one fresh page compared with the stored one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageChoice {
+    /// None when the stored document had no such page
+    pub old_quality: Option<f32>,
+    pub new_quality: f32,
+    pub replace: bool,
+}
+
+fn mean(scores: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = scores.fold((0.0, 0), |(sum, count), score| (sum + score, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f32 }
+}
+
+/// Which of `fresh` (one result per page) to keep over the stored content's pages
+pub fn choose_pages(stored: &str, fresh: &[ExtractionResult], checker: &QualityChecker, force: bool) -> Vec<PageChoice> {
+    let old: Vec<f32> = stored.split('\x0c').map(|page| checker.score(page)).collect();
+    let new: Vec<f32> = fresh.iter().map(|result| checker.score(&result.text)).collect();
+    if old.len() != new.len() {
+        let replace = force || mean(new.iter().copied()) > mean(old.iter().copied());
+        return new
+            .iter()
+            .enumerate()
+            .map(|(index, &new_quality)| PageChoice { old_quality: old.get(index).copied(), new_quality, replace })
+            .collect();
+    }
+    old.iter()
+        .zip(&new)
+        .map(|(&old_quality, &new_quality)| PageChoice { old_quality: Some(old_quality), new_quality, replace: force || new_quality > old_quality })
+        .collect()
+}
+
+/// The stored content with the chosen fresh pages in place
+pub fn merge_pages(stored: &str, fresh: &[ExtractionResult], choices: &[PageChoice]) -> String {
+    let old: Vec<&str> = stored.split('\x0c').collect();
+    if old.len() != fresh.len() {
+        if choices.first().is_some_and(|choice| choice.replace) {
+            return fresh.iter().map(|result| result.text.as_str()).collect::<Vec<_>>().join("\x0c");
+        }
+        return stored.to_string();
+    }
+    old.iter()
+        .zip(fresh)
+        .zip(choices)
+        .map(|((old, result), choice)| if choice.replace { result.text.as_str() } else { old })
+        .collect::<Vec<_>>()
+        .join("\x0c")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_extraction::ExtractionMethod;
+
+    #[test]
+    fn test_only_better_pages_replace_stored_ones() {
+        let checker = QualityChecker::default();
+        let good = "The tenant shall pay the rent on the first day of each month.";
+        let garbled = "xvqpz kljfd qwerty";
+        let fresh = |pages: &[&str]| pages.iter().map(|text| ExtractionResult::new(text.to_string(), ExtractionMethod::Tesseract)).collect::<Vec<_>>();
+
+        let stored = format!("{}\x0c{}", good, garbled);
+        let results = fresh(&[garbled, good]);
+        let choices = choose_pages(&stored, &results, &checker, false);
+        assert_eq!(choices.iter().map(|choice| choice.replace).collect::<Vec<_>>(), vec![false, true]);
+        assert_eq!(merge_pages(&stored, &results, &choices), format!("{}\x0c{}", good, good));
+
+        let forced = choose_pages(&stored, &results, &checker, true);
+        assert_eq!(merge_pages(&stored, &results, &forced), format!("{}\x0c{}", garbled, good));
+
+        // A page fewer than stored: all or nothing, on the means
+        let shorter = fresh(&[garbled]);
+        let choices = choose_pages(&stored, &shorter, &checker, false);
+        assert_eq!(merge_pages(&stored, &shorter, &choices), stored);
+    }
+}
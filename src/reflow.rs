@@ -0,0 +1,173 @@
+// Text reflow - paragraphs re-wrapped to a width at word boundaries
+//
+// Extracted text keeps the PDF's line breaks, which only fit the page's own
+// column. Reflow joins the lines of each paragraph (a blank line ends one) and
+// wraps them again to the width at hand, splitting a word only when it is wider
+// than a whole line. A word the PDF hyphenated across a line break - "extrac-"
+// then "tion" - is joined back up; before a capital or a digit the hyphen is
+// part of the word and stays ("Rhein-" then "Main" gives "Rhein-Main"). Lines
+// laid out in columns, like table rows, are kept as they are rather than run
+// together, and only broken where they don't fit.
+//
+// Used by the text panel in wrap mode and by `chonker8 export --reflow`.
+// `export --dehyphenate` does the joining without the wrapping: hyphenated
+// words are mended and a line running on in lowercase is joined to the one
+// before it, while every other line break stays where the PDF had it.
+
+/// A run of this many spaces inside a line means it is laid out in columns
+const COLUMN_GAP: &str = "   ";
+
+fn is_columnar(line: &str) -> bool {
+    line.trim().contains(COLUMN_GAP)
+}
+
+/// One paragraph's lines as a single line, with words hyphenated across the
+/// breaks joined back together
+pub fn join_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> String {
+    let mut joined = String::new();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if joined.ends_with('\u{ad}') {
+            // A soft hyphen only ever marks a break
+            joined.pop();
+        } else if ends_hyphenated(&joined) {
+            if line.starts_with(char::is_lowercase) {
+                joined.pop();
+            }
+        } else if !joined.is_empty() {
+            joined.push(' ');
+        }
+        joined.push_str(line);
+    }
+    joined
+}
+
+/// Break `text` into lines of at most `width` characters at spaces; a word
+/// longer than `width` is the only thing ever cut
+fn wrap_words(text: &str, width: usize, lines: &mut Vec<String>) {
+    let width = width.max(1);
+    let mut line = String::new();
+    let mut line_len = 0;
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        if line_len > 0 && line_len + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+            line_len = 0;
+        }
+        while word.len() > width {
+            let rest = word.split_off(width);
+            lines.push(word.into_iter().collect());
+            word = rest;
+        }
+        if line_len > 0 {
+            line.push(' ');
+            line_len += 1;
+        }
+        line_len += word.len();
+        line.extend(word);
+    }
+    if line_len > 0 {
+        lines.push(line);
+    }
+}
+
+/// A line kept as laid out, broken at the last space that fits when it is too wide
+fn wrap_columnar(line: &str, width: usize, lines: &mut Vec<String>) {
+    let width = width.max(1);
+    let mut rest: Vec<char> = line.trim_end().chars().collect();
+    while rest.len() > width {
+        let cut = rest[..=width].iter().rposition(|c| *c == ' ').filter(|&cut| cut > 0).unwrap_or(width);
+        let tail = rest.split_off(cut);
+        lines.push(rest.into_iter().collect::<String>().trim_end().to_string());
+        rest = tail.into_iter().skip_while(|c| *c == ' ').collect();
+    }
+    lines.push(rest.into_iter().collect());
+}
+
+fn ends_hyphenated(line: &str) -> bool {
+    line.ends_with('\u{ad}') || (line.ends_with('-') && line.chars().rev().nth(1).is_some_and(char::is_alphabetic))
+}
+
+/// `text` with hyphenated words rejoined and lines that carry on in lowercase
+/// joined to the line before; other lines, and indentation, stay as they are
+pub fn dehyphenate(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let current = line.trim();
+        let joins = lines.last().is_some_and(|previous| {
+            let previous = previous.trim_end();
+            !previous.trim().is_empty()
+                && !current.is_empty()
+                && !is_columnar(previous)
+                && !is_columnar(line)
+                && (ends_hyphenated(previous) || current.starts_with(char::is_lowercase))
+        });
+        match lines.last_mut() {
+            Some(previous) if joins => {
+                let indent_len = previous.len() - previous.trim_start().len();
+                let indent = previous[..indent_len].to_string();
+                *previous = indent + &join_lines([previous.as_str(), current]);
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// `text` reflowed to `width` columns: paragraphs joined and wrapped, blank
+/// lines and columnar lines kept
+pub fn reflow(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() || is_columnar(line) {
+            wrap_words(&join_lines(paragraph.drain(..)), width, &mut lines);
+            if line.trim().is_empty() {
+                lines.push(String::new());
+            } else {
+                wrap_columnar(line, width, &mut lines);
+            }
+        } else {
+            paragraph.push(line);
+        }
+    }
+    wrap_words(&join_lines(paragraph), width, &mut lines);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paragraphs_rejoined_and_wrapped_at_words() {
+        let page = "The tenant shall pay the rent with-\nout deduction to the Rhein-\nMain office.\n\nItem        Qty    Price\nWidget        2    10.00\n";
+        assert_eq!(
+            reflow(page, 24),
+            vec![
+                "The tenant shall pay the",
+                "rent without deduction",
+                "to the Rhein-Main",
+                "office.",
+                "",
+                "Item        Qty    Price",
+                "Widget        2    10.00",
+            ]
+        );
+        assert_eq!(join_lines(["extrac\u{ad}", "tion of well -", "formed pages"]), "extraction of well - formed pages");
+        assert_eq!(reflow("Antidisestablishmentarianism", 10), vec!["Antidisest", "ablishment", "arianism"]);
+        assert_eq!(reflow("Item      Qty      Price", 14), vec!["Item      Qty", "Price"]);
+    }
+
+    #[test]
+    fn test_dehyphenate_keeps_other_line_breaks() {
+        let page = "  Terms of informa-\ntion sharing\nare set out below.\nPayment\nDue within 14 days to Rhein-\nMain GmbH.\nTotal  -   12.00\nnet";
+        assert_eq!(
+            dehyphenate(page),
+            "  Terms of information sharing are set out below.\nPayment\nDue within 14 days to Rhein-Main GmbH.\nTotal  -   12.00\nnet"
+        );
+    }
+}
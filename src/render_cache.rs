@@ -0,0 +1,179 @@
+// Rendered page cache - LRU over (pdf, page, resolution, dark mode)
+//
+// Rendering a page shells out to pdftoppm and then runs the dark-mode filter, which
+// is slow enough to notice on every PageUp/PageDown. Renders are kept in a small LRU
+// and the pages either side of the current one are rendered on a background thread,
+// so paging through a document usually hits a finished image. A request for a page
+// that is still being prerendered waits for that render instead of starting another.
+
+use anyhow::Result;
+use image::DynamicImage;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Pages kept by default - at 800x1000 RGBA that is about 3 MB each
+pub const DEFAULT_CAPACITY: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RenderKey {
+    pub path: PathBuf,
+    pub page: usize, // 0-based
+    pub width: u32,
+    pub height: u32,
+    pub dark_mode: bool,
+}
+
+type RenderFn = dyn Fn(&RenderKey) -> Result<DynamicImage> + Send + Sync;
+
+#[derive(Default)]
+struct Entries {
+    images: HashMap<RenderKey, (Arc<DynamicImage>, u64)>,
+    in_flight: HashSet<RenderKey>,
+    clock: u64,
+}
+
+struct Shared {
+    capacity: usize,
+    entries: Mutex<Entries>,
+    rendered: Condvar,
+    render: Box<RenderFn>,
+}
+
+/// Cheap to clone; clones share the same cache
+#[derive(Clone)]
+pub struct RenderCache {
+    shared: Arc<Shared>,
+}
+
+impl RenderCache {
+    pub fn new(capacity: usize, render: impl Fn(&RenderKey) -> Result<DynamicImage> + Send + Sync + 'static) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                capacity: capacity.max(1),
+                entries: Mutex::new(Entries::default()),
+                rendered: Condvar::new(),
+                render: Box::new(render),
+            }),
+        }
+    }
+
+    /// The rendered page, from the cache when possible
+    pub fn get(&self, key: &RenderKey) -> Result<Arc<DynamicImage>> {
+        let mut entries = self.lock();
+        loop {
+            entries.clock += 1;
+            let now = entries.clock;
+            if let Some((image, used)) = entries.images.get_mut(key) {
+                *used = now;
+                return Ok(image.clone());
+            }
+            if !entries.in_flight.contains(key) {
+                break;
+            }
+            entries = self.shared.rendered.wait(entries).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        entries.in_flight.insert(key.clone());
+        drop(entries);
+
+        let result = (self.shared.render)(key);
+        self.finish(key, result)
+    }
+
+    /// Render these pages on a background thread, skipping ones already cached or underway
+    pub fn prerender(&self, keys: Vec<RenderKey>) {
+        let queued: Vec<RenderKey> = {
+            let mut entries = self.lock();
+            keys.into_iter()
+                .filter(|key| !entries.images.contains_key(key) && entries.in_flight.insert(key.clone()))
+                .collect()
+        };
+        if queued.is_empty() {
+            return;
+        }
+
+        let cache = self.clone();
+        std::thread::spawn(move || {
+            for key in queued {
+                let result = (cache.shared.render)(&key);
+                if let Err(e) = cache.finish(&key, result) {
+                    tracing::warn!("[RENDER_CACHE] Prerender of page {} failed: {}", key.page + 1, e);
+                }
+            }
+        });
+    }
+
+    pub fn contains(&self, key: &RenderKey) -> bool {
+        self.lock().images.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Store a finished render, evict the least recently used page if over capacity,
+    /// and wake anyone waiting on it. Failures are not cached.
+    fn finish(&self, key: &RenderKey, result: Result<DynamicImage>) -> Result<Arc<DynamicImage>> {
+        let mut entries = self.lock();
+        entries.in_flight.remove(key);
+        let outcome = result.map(|image| {
+            let image = Arc::new(image);
+            entries.clock += 1;
+            let now = entries.clock;
+            entries.images.insert(key.clone(), (image.clone(), now));
+            while entries.images.len() > self.shared.capacity {
+                let oldest = entries.images.iter().min_by_key(|(_, (_, used))| *used).map(|(k, _)| k.clone());
+                match oldest {
+                    Some(oldest) => entries.images.remove(&oldest),
+                    None => break,
+                };
+            }
+            image
+        });
+        drop(entries);
+        self.shared.rendered.notify_all();
+        outcome
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        // A panicking render never holds the lock, so poisoning can't leave it inconsistent
+        self.shared.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn key(page: usize) -> RenderKey {
+        RenderKey { path: PathBuf::from("doc.pdf"), page, width: 8, height: 10, dark_mode: true }
+    }
+
+    #[test]
+    fn test_lru_eviction_and_prerender() {
+        let renders = Arc::new(AtomicUsize::new(0));
+        let counter = renders.clone();
+        let cache = RenderCache::new(2, move |key| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(DynamicImage::new_rgba8(key.width, key.height))
+        });
+
+        cache.get(&key(0)).unwrap();
+        cache.get(&key(1)).unwrap();
+        cache.get(&key(0)).unwrap(); // hit, and now most recent
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+
+        cache.get(&key(2)).unwrap(); // evicts page 1
+        assert!(cache.contains(&key(0)) && !cache.contains(&key(1)));
+
+        cache.prerender(vec![key(3)]);
+        cache.get(&key(3)).unwrap(); // waits for the background render, no second render
+        assert_eq!(renders.load(Ordering::SeqCst), 4);
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -0,0 +1,243 @@
+// Extraction scheduler - orders scanned files so the most valuable ones are extracted first
+//
+// Priorities are a list of predicates compared in order (the first predicate dominates,
+// later ones break ties). Classification is only known after a file is extracted, so
+// each result is fed back and pending files in the same directory are re-ranked.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::storage::FileRecord;
+
+/// One scheduling rule, parsed from `--priority`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    /// `recent` or `recent=7d` - modified within the window
+    RecentlyModified { within_secs: i64 },
+    /// `small` - fewer bytes first
+    SmallFirst,
+    /// `class=invoice` - documents classified (or likely to be) as this class
+    Classification(String),
+    /// `name=keyword` - keyword in the file name, case-insensitive
+    FilenameKeyword(String),
+}
+
+impl FromStr for Predicate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (key, value) = match s.split_once('=') {
+            Some((k, v)) => (k.trim(), Some(v.trim())),
+            None => (s.trim(), None),
+        };
+
+        match (key, value) {
+            ("recent", None) => Ok(Predicate::RecentlyModified { within_secs: 7 * 86400 }),
+            ("recent", Some(window)) => Ok(Predicate::RecentlyModified { within_secs: parse_window(window)? }),
+            ("small", None) => Ok(Predicate::SmallFirst),
+            ("class", Some(class)) if !class.is_empty() => Ok(Predicate::Classification(class.to_lowercase())),
+            ("name", Some(keyword)) if !keyword.is_empty() => Ok(Predicate::FilenameKeyword(keyword.to_lowercase())),
+            _ => Err(anyhow!(
+                "Unknown priority '{}' (expected recent[=7d], small, class=<name> or name=<keyword>)", s
+            )),
+        }
+    }
+}
+
+/// Parse "30m", "24h", "7d" or plain seconds
+pub fn parse_window(window: &str) -> Result<i64> {
+    let (number, unit) = window.split_at(window.trim_end_matches(char::is_alphabetic).len());
+    let value: i64 = number.parse().map_err(|_| anyhow!("Invalid time window '{}'", window))?;
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => return Err(anyhow!("Invalid time unit in '{}'", window)),
+    };
+    Ok(value * multiplier)
+}
+
+// Keyword sets for the cheap text classifier; a class needs at least two hits
+const CLASS_KEYWORDS: &[(&str, &[&str])] = &[
+    ("invoice", &["invoice", "amount due", "bill to", "due date", "invoice number", "remit"]),
+    ("receipt", &["receipt", "subtotal", "change due", "cashier", "thank you for your purchase"]),
+    ("contract", &["agreement", "hereinafter", "witnesseth", "governing law", "the parties"]),
+    ("statement", &["statement period", "opening balance", "closing balance", "account number"]),
+];
+
+/// Guess a document class from extracted text
+pub fn classify_text(text: &str) -> Option<&'static str> {
+    let lower = text.to_lowercase();
+    CLASS_KEYWORDS
+        .iter()
+        .map(|(class, keywords)| (*class, keywords.iter().filter(|k| lower.contains(*k)).count()))
+        .filter(|(_, hits)| *hits >= 2)
+        .max_by_key(|(_, hits)| *hits)
+        .map(|(class, _)| class)
+}
+
+/// Priority queue over scanned files
+pub struct Scheduler {
+    predicates: Vec<Predicate>,
+    // Sorted so the next file is last; keys are only recomputed when feedback changes them
+    pending: Vec<Pending>,
+    now: i64,
+    // Classes seen so far per directory - documents tend to be filed together
+    directory_classes: HashMap<PathBuf, HashMap<String, usize>>,
+}
+
+/// A pending file with its lowercased name and its rank, one entry per predicate
+struct Pending {
+    key: Vec<u64>,
+    name: String,
+    file: FileRecord,
+}
+
+impl Scheduler {
+    pub fn new(predicates: Vec<Predicate>, files: Vec<FileRecord>) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self::with_now(predicates, files, now)
+    }
+
+    pub fn with_now(predicates: Vec<Predicate>, files: Vec<FileRecord>, now: i64) -> Self {
+        let mut scheduler = Self {
+            predicates,
+            pending: Vec::with_capacity(files.len()),
+            now,
+            directory_classes: HashMap::new(),
+        };
+        for file in files {
+            let name = Path::new(&file.path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let key = scheduler.key(&file, &name);
+            scheduler.pending.push(Pending { key, name, file });
+        }
+        scheduler.sort();
+        scheduler
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Remove and return the highest-priority pending file
+    pub fn next_file(&mut self) -> Option<FileRecord> {
+        self.pending.pop().map(|pending| pending.file)
+    }
+
+    /// Feed back an extraction result. Returns the detected class, if any.
+    pub fn record_result(&mut self, file: &FileRecord, text: &str) -> Option<&'static str> {
+        let class = classify_text(text)?;
+        if let Some(dir) = Path::new(&file.path).parent() {
+            let seen = self.directory_classes
+                .entry(dir.to_path_buf())
+                .or_default()
+                .entry(class.to_string())
+                .or_insert(0);
+            *seen += 1;
+            // Only the first sighting of a class in a directory can change a rank
+            let ranked = self.predicates.iter().any(|p| matches!(p, Predicate::Classification(c) if c == class));
+            if *seen == 1 && ranked {
+                self.rerank(dir);
+            }
+        }
+        Some(class)
+    }
+
+    /// Recompute the keys of the pending files in `dir` and restore the order
+    fn rerank(&mut self, dir: &Path) {
+        let mut pending = std::mem::take(&mut self.pending);
+        for entry in pending.iter_mut().filter(|entry| Path::new(&entry.file.path).parent() == Some(dir)) {
+            entry.key = self.key(&entry.file, &entry.name);
+        }
+        self.pending = pending;
+        self.sort();
+    }
+
+    // Ascending by key, so the best file ends up last; ties go to the earlier path
+    fn sort(&mut self) {
+        self.pending.sort_by(|a, b| a.key.cmp(&b.key).then_with(|| b.file.path.cmp(&a.file.path)));
+    }
+
+    // Rank a file predicate by predicate; a higher key goes first
+    fn key(&self, file: &FileRecord, name: &str) -> Vec<u64> {
+        self.predicates
+            .iter()
+            .map(|predicate| match predicate {
+                Predicate::SmallFirst => u64::MAX - file.size_bytes,
+                _ => self.matches(predicate, file, name) as u64,
+            })
+            .collect()
+    }
+
+    fn matches(&self, predicate: &Predicate, file: &FileRecord, name: &str) -> bool {
+        match predicate {
+            Predicate::RecentlyModified { within_secs } => self.now - file.modified_at <= *within_secs,
+            Predicate::SmallFirst => false,
+            Predicate::FilenameKeyword(keyword) => name.contains(keyword.as_str()),
+            Predicate::Classification(class) => {
+                let in_directory = Path::new(&file.path)
+                    .parent()
+                    .and_then(|dir| self.directory_classes.get(dir))
+                    .and_then(|classes| classes.get(class))
+                    .is_some();
+                name.contains(class.as_str()) || in_directory
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size_bytes: u64, modified_at: i64) -> FileRecord {
+        FileRecord {
+            path: path.to_string(),
+            sha256: String::new(),
+            size_bytes,
+            modified_at,
+            page_count: Some(1),
+            pdf_version: None,
+            text_coverage: 0.0,
+            image_coverage: 0.0,
+            status: "scanned".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_predicates_in_order_and_feedback_reorders() {
+        let predicates = vec![
+            "class=invoice".parse().unwrap(),
+            "recent=1d".parse().unwrap(),
+            "small".parse().unwrap(),
+        ];
+        let files = vec![
+            file("/a/big.pdf", 900, 0),
+            file("/a/small.pdf", 100, 0),
+            file("/b/fresh.pdf", 500, 1_000_000),
+            file("/b/other.pdf", 50, 0),
+        ];
+        let mut scheduler = Scheduler::with_now(predicates, files, 1_000_000);
+
+        let first = scheduler.next_file().unwrap();
+        assert_eq!(first.path, "/b/fresh.pdf");
+
+        // An invoice turns up in /a - its siblings now outrank /b/other.pdf despite its size
+        scheduler.record_result(&file("/a/x.pdf", 1, 0), "INVOICE\nBill To: ACME\nAmount Due: $10");
+        assert_eq!(scheduler.next_file().unwrap().path, "/a/small.pdf");
+        assert_eq!(scheduler.next_file().unwrap().path, "/a/big.pdf");
+        assert_eq!(scheduler.next_file().unwrap().path, "/b/other.pdf");
+        assert!(scheduler.is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+// Elasticsearch / OpenSearch indexing - page text and metadata pushed over the bulk API
+//
+// One search document per page, id "<path>#<page>", so re-pushing a document
+// overwrites its pages instead of duplicating them. An index template is installed
+// before the first push so `text` is analyzed and the metadata fields are keywords.
+// Bulk items rejected with 429 or a 5xx are retried with exponential backoff; any
+// other rejection is reported and skipped.
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IndexConfig {
+    /// Base URL, e.g. http://localhost:9200
+    pub endpoint: String,
+    #[serde(default = "default_index")]
+    pub index: String,
+    /// Sent as `Authorization: ApiKey ...`; takes precedence over username/password
+    pub api_key: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Push every document as it is stored, not just on `chonker8 index push`
+    #[serde(default)]
+    pub on_store: bool,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_index() -> String { "chonker8-pages".to_string() }
+fn default_batch_size() -> usize { 500 }
+fn default_max_retries() -> u32 { 5 }
+
+impl IndexConfig {
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            index: default_index(),
+            api_key: None,
+            username: None,
+            password: None,
+            on_store: false,
+            batch_size: default_batch_size(),
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
+/// One page as it is indexed
+#[derive(Debug, Clone, Serialize)]
+pub struct PageDoc {
+    #[serde(skip)]
+    pub id: String,
+    pub path: String,
+    pub page: usize, // 1-based
+    pub text: String,
+    pub engine: Option<String>,
+    pub quality: Option<f64>,
+    pub classification: Option<String>,
+    pub sha256: Option<String>,
+    pub indexed_at: String,
+}
+
+/// Split a stored document (pages separated by form feeds) into page docs
+pub fn page_docs(path: &str, content: &str, metadata: Option<&str>) -> Vec<PageDoc> {
+    let meta: serde_json::Value = metadata.and_then(|m| serde_json::from_str(m).ok()).unwrap_or_default();
+    let text_field = |name: &str| meta.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    let indexed_at = chrono::Utc::now().to_rfc3339();
+
+    content
+        .split('\x0c')
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(i, text)| PageDoc {
+            id: format!("{}#{}", path, i + 1),
+            path: path.to_string(),
+            page: i + 1,
+            text: text.to_string(),
+            engine: text_field("engine"),
+            quality: meta.get("mean_quality").and_then(|v| v.as_f64()),
+            classification: text_field("classification"),
+            sha256: text_field("sha256"),
+            indexed_at: indexed_at.clone(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PushSummary {
+    pub indexed: usize,
+    pub failed: usize,
+    pub retries: u32,
+}
+
+pub struct SearchIndexer {
+    config: IndexConfig,
+    agent: ureq::Agent,
+}
+
+impl std::fmt::Debug for SearchIndexer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SearchIndexer").field("endpoint", &self.config.endpoint).field("index", &self.config.index).finish()
+    }
+}
+
+impl SearchIndexer {
+    pub fn new(config: IndexConfig) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+        Self { config, agent }
+    }
+
+    pub fn config(&self) -> &IndexConfig {
+        &self.config
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.config.endpoint.trim_end_matches('/'), path)
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self.agent.request(method, &self.url(path));
+        if let Some(key) = &self.config.api_key {
+            request.set("Authorization", &format!("ApiKey {}", key))
+        } else if let Some(user) = &self.config.username {
+            use base64::Engine;
+            let credentials = format!("{}:{}", user, self.config.password.as_deref().unwrap_or(""));
+            let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+            request.set("Authorization", &format!("Basic {}", encoded))
+        } else {
+            request
+        }
+    }
+
+    /// Install (or update) the index template; works on Elasticsearch 7.8+ and OpenSearch
+    pub fn ensure_template(&self) -> Result<()> {
+        let template = json!({
+            "index_patterns": [self.config.index],
+            "template": {
+                "mappings": {
+                    "properties": {
+                        "path": { "type": "keyword" },
+                        "page": { "type": "integer" },
+                        "text": { "type": "text" },
+                        "engine": { "type": "keyword" },
+                        "quality": { "type": "float" },
+                        "classification": { "type": "keyword" },
+                        "sha256": { "type": "keyword" },
+                        "indexed_at": { "type": "date" }
+                    }
+                }
+            }
+        });
+        self.request("PUT", &format!("_index_template/{}", self.config.index))
+            .set("Content-Type", "application/json")
+            .send_string(&template.to_string())
+            .map_err(|e| anyhow!("Installing the index template failed: {}", describe(e)))?;
+        Ok(())
+    }
+
+    /// Bulk-index pages in batches, retrying throttled and server-side failures
+    pub fn push(&self, docs: &[PageDoc]) -> Result<PushSummary> {
+        let mut summary = PushSummary::default();
+        for batch in docs.chunks(self.config.batch_size.max(1)) {
+            let mut pending: Vec<&PageDoc> = batch.iter().collect();
+            let mut backoff = FIRST_BACKOFF;
+            let mut attempt = 0;
+
+            while !pending.is_empty() {
+                let response = self
+                    .request("POST", "_bulk")
+                    .set("Content-Type", "application/x-ndjson")
+                    .send_string(&bulk_body(&self.config.index, &pending));
+
+                let retry: Vec<usize> = match response {
+                    Ok(response) => {
+                        let body: serde_json::Value = serde_json::from_str(&response.into_string()?)?;
+                        let outcome = bulk_outcome(&body, pending.len());
+                        summary.indexed += outcome.indexed;
+                        summary.failed += outcome.rejected.len();
+                        for (i, reason) in outcome.rejected {
+                            tracing::warn!("[INDEX] {} rejected: {}", pending[i].id, reason);
+                        }
+                        outcome.retry
+                    }
+                    // The whole request was throttled or the cluster is struggling
+                    Err(ureq::Error::Status(status, _)) if status == 429 || status >= 500 => (0..pending.len()).collect(),
+                    Err(e) => return Err(anyhow!("Bulk request failed: {}", describe(e))),
+                };
+
+                if retry.is_empty() {
+                    break;
+                }
+                attempt += 1;
+                if attempt > self.config.max_retries {
+                    tracing::warn!("[INDEX] Giving up on {} pages after {} retries", retry.len(), self.config.max_retries);
+                    summary.failed += retry.len();
+                    break;
+                }
+                summary.retries += 1;
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                pending = retry.into_iter().map(|i| pending[i]).collect();
+            }
+        }
+        Ok(summary)
+    }
+}
+
+fn describe(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(status, response) => {
+            format!("HTTP {}: {}", status, response.into_string().unwrap_or_default())
+        }
+        other => other.to_string(),
+    }
+}
+
+fn bulk_body(index: &str, docs: &[&PageDoc]) -> String {
+    let mut body = String::new();
+    for doc in docs {
+        body.push_str(&json!({ "index": { "_index": index, "_id": doc.id } }).to_string());
+        body.push('\n');
+        body.push_str(&serde_json::to_string(doc).unwrap_or_default());
+        body.push('\n');
+    }
+    body
+}
+
+#[derive(Debug, Default)]
+struct BulkOutcome {
+    indexed: usize,
+    /// Positions to send again
+    retry: Vec<usize>,
+    /// Positions that failed for good, with the reason
+    rejected: Vec<(usize, String)>,
+}
+
+/// Sort the items of a bulk response into indexed, retryable and rejected
+fn bulk_outcome(body: &serde_json::Value, sent: usize) -> BulkOutcome {
+    let mut outcome = BulkOutcome::default();
+    let items = body.get("items").and_then(|i| i.as_array()).cloned().unwrap_or_default();
+    for (i, item) in items.iter().enumerate().take(sent) {
+        let result = item.get("index").unwrap_or(item);
+        let status = result.get("status").and_then(|s| s.as_u64()).unwrap_or(0);
+        match status {
+            200..=299 => outcome.indexed += 1,
+            429 | 500..=599 => outcome.retry.push(i),
+            _ => outcome.rejected.push((i, result.get("error").map(|e| e.to_string()).unwrap_or_default())),
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_and_bulk_outcome() {
+        let docs = page_docs("a.pdf", "first page\x0c\x0cthird page", Some(r#"{"engine": "tesseract", "mean_quality": 0.5}"#));
+        assert_eq!(docs.iter().map(|d| d.id.as_str()).collect::<Vec<_>>(), vec!["a.pdf#1", "a.pdf#3"]);
+        assert_eq!(docs[1].engine.as_deref(), Some("tesseract"));
+
+        let body = bulk_body("pages", &docs.iter().collect::<Vec<_>>());
+        assert_eq!(body.lines().count(), 4);
+        assert!(body.lines().next().unwrap().contains(r#""_id":"a.pdf#1""#));
+
+        let response = json!({ "errors": true, "items": [
+            { "index": { "status": 201 } },
+            { "index": { "status": 429 } },
+            { "index": { "status": 400, "error": { "type": "mapper_parsing_exception" } } },
+        ]});
+        let outcome = bulk_outcome(&response, 3);
+        assert_eq!((outcome.indexed, outcome.retry, outcome.rejected.len()), (1, vec![1], 1));
+    }
+}
@@ -0,0 +1,297 @@
+// Self-update - `chonker8 self-update` and the TUI's new-version notice
+//
+// Releases come from a feed in GitHub's release format: the latest release of
+// jackgrauer/chonker8, or whatever $CHONKER8_RELEASE_FEED points at. A release
+// is tagged like v8.9.0 and carries one binary per platform, named
+// chonker8-<arch>-<os> (x86_64-linux, aarch64-macos, ...), plus release.json -
+// a manifest of the version, the tag and each binary's SHA-256 - and
+// release.json.sig, a base64 Ed25519 signature of it.
+//
+// Release builds embed the signing key's public half (base64, from
+// CHONKER8_RELEASE_KEY at build time). A binary is only installed when its
+// checksum is listed in a manifest that key signed. The feed itself is not
+// signed, so the version comes from the manifest: it has to name the release
+// the feed offered, and be newer than this build unless --force says to
+// reinstall or downgrade - an old signed release served under a new tag is
+// refused. Builds without a key can only update with --allow-unsigned,
+// checked against the checksum alone. The new binary is written beside the
+// running one and renamed over it, so an interrupted update leaves the old
+// one in place.
+use anyhow::{anyhow, bail, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::disk_cache::DiskCache;
+use crate::temp_files::Partial;
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const FEED_ENV: &str = "CHONKER8_RELEASE_FEED";
+pub const DEFAULT_FEED: &str = "https://api.github.com/repos/jackgrauer/chonker8/releases/latest";
+pub const MANIFEST_ASSET: &str = "release.json";
+pub const SIGNATURE_ASSET: &str = "release.json.sig";
+
+/// Public half of the release signing key, when this build was given one
+const RELEASE_KEY: Option<&str> = option_env!("CHONKER8_RELEASE_KEY");
+/// Asking the feed; binaries are large, so this bounds a stalled read rather than the transfer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long the TUI trusts its last look at the feed
+const CHECK_EVERY: Duration = Duration::from_secs(24 * 60 * 60);
+const CACHE_NAME: &str = "updates";
+const CACHE_KEY: &str = "latest.json";
+
+/// major.minor.patch; anything after a '-' (pre-release) is ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u64, pub u64, pub u64);
+
+impl Version {
+    /// "8.9.0" or "v8.9.0"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().trim_start_matches('v');
+        let core = text.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+        let version = Version(parts.next()??, parts.next().unwrap_or(Some(0))?, parts.next().unwrap_or(Some(0))?);
+        parts.next().is_none().then_some(version)
+    }
+
+    pub fn current() -> Self {
+        Self::parse(CURRENT_VERSION).expect("CARGO_PKG_VERSION is a version")
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: Version,
+    pub tag: String,
+    pub assets: Vec<Asset>,
+}
+
+impl Release {
+    pub fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+
+    pub fn is_newer(&self) -> bool {
+        self.version > Version::current()
+    }
+}
+
+/// The binary's asset name on this machine, e.g. chonker8-x86_64-linux
+pub fn platform_asset() -> String {
+    format!("chonker8-{}-{}{}", std::env::consts::ARCH, std::env::consts::OS, std::env::consts::EXE_SUFFIX)
+}
+
+fn feed_url() -> String {
+    std::env::var(FEED_ENV).ok().filter(|url| !url.is_empty()).unwrap_or_else(|| DEFAULT_FEED.to_string())
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout_connect(REQUEST_TIMEOUT).timeout_read(REQUEST_TIMEOUT).build()
+}
+
+fn fetch(agent: &ureq::Agent, url: &str) -> Result<Vec<u8>> {
+    let response = agent
+        .get(url)
+        .set("User-Agent", &format!("chonker8/{}", CURRENT_VERSION))
+        .call()
+        .map_err(|e| anyhow!("Fetching {} failed: {}", url, e))?;
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A release from the feed's JSON
+pub fn parse_release(feed: &Value) -> Result<Release> {
+    let tag = feed["tag_name"].as_str().ok_or_else(|| anyhow!("Release feed has no tag_name"))?.to_string();
+    let version = Version::parse(&tag).ok_or_else(|| anyhow!("Release tag '{}' isn't a version", tag))?;
+    let assets = feed["assets"]
+        .as_array()
+        .map(|assets| {
+            assets
+                .iter()
+                .filter_map(|asset| {
+                    Some(Asset { name: asset["name"].as_str()?.to_string(), url: asset["browser_download_url"].as_str()?.to_string() })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Release { version, tag, assets })
+}
+
+/// What the feed currently offers
+pub fn latest_release() -> Result<Release> {
+    let feed = fetch(&agent(), &feed_url())?;
+    parse_release(&serde_json::from_slice::<Value>(&feed)?)
+}
+
+/// What a release's signed manifest vouches for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub version: Version,
+    pub tag: String,
+    /// SHA-256 (lowercase hex) by asset name
+    pub files: HashMap<String, String>,
+}
+
+/// release.json: {"version": "8.9.0", "tag": "v8.9.0", "files": {"chonker8-x86_64-linux": "<sha256>", ...}}
+pub fn parse_manifest(bytes: &[u8]) -> Result<Manifest> {
+    let manifest: Value = serde_json::from_slice(bytes).map_err(|e| anyhow!("{} isn't JSON: {}", MANIFEST_ASSET, e))?;
+    let version = manifest["version"].as_str().and_then(Version::parse).ok_or_else(|| anyhow!("{} has no version", MANIFEST_ASSET))?;
+    let tag = manifest["tag"].as_str().ok_or_else(|| anyhow!("{} has no tag", MANIFEST_ASSET))?.to_string();
+    let files = manifest["files"]
+        .as_object()
+        .ok_or_else(|| anyhow!("{} lists no files", MANIFEST_ASSET))?
+        .iter()
+        .filter_map(|(name, hash)| Some((name.clone(), hash.as_str()?.to_lowercase())))
+        .collect();
+    Ok(Manifest { version, tag, files })
+}
+
+/// Refuse a manifest for another release than the feed offered, or - unless
+/// `force` - one that isn't newer than `current`
+pub fn check_manifest(manifest: &Manifest, release: &Release, current: Version, force: bool) -> Result<()> {
+    if manifest.tag != release.tag || manifest.version != release.version {
+        bail!("{} is for {} ({}), but the feed offered {} - not installed", MANIFEST_ASSET, manifest.tag, manifest.version, release.tag);
+    }
+    if manifest.version <= current && !force {
+        bail!("Release {} isn't newer than this chonker8 {} - rerun with --force to install it anyway", manifest.version, current);
+    }
+    Ok(())
+}
+
+/// Check an Ed25519 signature, key and signature both base64
+pub fn verify_signature(public_key: &str, message: &[u8], signature: &str) -> Result<()> {
+    let key = BASE64.decode(public_key.trim()).map_err(|e| anyhow!("Release key isn't base64: {}", e))?;
+    let signature = BASE64.decode(signature.trim()).map_err(|e| anyhow!("{} isn't base64: {}", SIGNATURE_ASSET, e))?;
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, key)
+        .verify(message, &signature)
+        .map_err(|_| anyhow!("{} isn't signed by the release key", MANIFEST_ASSET))
+}
+
+/// Download this platform's binary from `release`, verify it and put it in
+/// place of the running executable; `force` allows a release that isn't newer.
+/// Returns the path replaced.
+pub fn install(release: &Release, allow_unsigned: bool, force: bool) -> Result<PathBuf> {
+    let name = platform_asset();
+    let binary = release.asset(&name).ok_or_else(|| anyhow!("Release {} has no build for this platform ({})", release.tag, name))?;
+    let manifest_asset = release.asset(MANIFEST_ASSET).ok_or_else(|| anyhow!("Release {} has no {}", release.tag, MANIFEST_ASSET))?;
+    let agent = agent();
+
+    let manifest = fetch(&agent, &manifest_asset.url)?;
+    match (RELEASE_KEY, release.asset(SIGNATURE_ASSET)) {
+        (Some(key), Some(signature)) => verify_signature(key, &manifest, &String::from_utf8_lossy(&fetch(&agent, &signature.url)?))?,
+        (Some(_), None) => bail!("Release {} has no {} - not installed", release.tag, SIGNATURE_ASSET),
+        (None, _) if allow_unsigned => tracing::warn!("[UPDATE] This build has no release key; checking {} against its checksum only", name),
+        (None, _) => bail!("This build has no release key to check signatures with - rerun with --allow-unsigned to trust the checksum alone"),
+    }
+    let manifest = parse_manifest(&manifest)?;
+    check_manifest(&manifest, release, Version::current(), force)?;
+    let expected = manifest.files.get(&name).ok_or_else(|| anyhow!("{} doesn't list {}", MANIFEST_ASSET, name))?;
+
+    tracing::info!("[UPDATE] Downloading {} {}", name, release.tag);
+    let bytes = fetch(&agent, &binary.url)?;
+    let actual = format!("{:x}", Sha256::digest(&bytes));
+    if &actual != expected {
+        bail!("{} has SHA-256 {}, expected {} - not installed", name, actual, expected);
+    }
+
+    let exe = std::env::current_exe()?.canonicalize()?;
+    let partial = Partial::new(&exe)?;
+    std::fs::write(partial.path(), &bytes)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(partial.path(), std::fs::Permissions::from_mode(0o755))?;
+    }
+    // Windows won't replace a running executable, but it will rename one; it
+    // goes back if the new one can't be put in its place
+    #[cfg(windows)]
+    let old = {
+        let old = exe.with_extension("old.exe");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(&exe, &old)?;
+        old
+    };
+    let committed = partial.commit();
+    #[cfg(windows)]
+    if committed.is_err() {
+        let _ = std::fs::rename(&old, &exe);
+    }
+    committed?;
+    Ok(exe)
+}
+
+/// A newer release than this one, looking at the feed at most once a day. Any
+/// failure - offline, feed down - is just no news.
+pub fn newer_release_cached() -> Option<Version> {
+    let cache = DiskCache::new(CACHE_NAME);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let cached = cache.get(CACHE_KEY).and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+    let fresh = cached.as_ref().filter(|entry| entry["checked"].as_u64().is_some_and(|checked| now.saturating_sub(checked) < CHECK_EVERY.as_secs()));
+    let latest = match fresh {
+        Some(entry) => Version::parse(entry["version"].as_str()?)?,
+        None => {
+            let release = latest_release().ok()?;
+            let entry = json!({ "version": release.version.to_string(), "checked": now });
+            let _ = cache.put(CACHE_KEY, entry.to_string().as_bytes());
+            release.version
+        }
+    };
+    (latest > Version::current()).then_some(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    #[test]
+    fn test_release_checked_against_signed_manifest() {
+        let feed = json!({
+            "tag_name": "v9.0.1",
+            "assets": [{ "name": "release.json", "browser_download_url": "https://example.org/release.json" }],
+        });
+        let release = parse_release(&feed).unwrap();
+        assert_eq!(release.version, Version(9, 0, 1));
+        assert!(release.is_newer() && release.asset(MANIFEST_ASSET).is_some());
+        assert!(Version::parse("8.10.0-rc.1").unwrap() > Version::parse("v8.9").unwrap());
+        assert_eq!(Version::parse("8.x"), None);
+
+        let manifest = br#"{"version": "9.0.1", "tag": "v9.0.1", "files": {"chonker8-x86_64-linux": "AB12"}}"#;
+        let parsed = parse_manifest(manifest).unwrap();
+        assert_eq!(parsed.files.get("chonker8-x86_64-linux").map(String::as_str), Some("ab12"));
+        let current = Version(9, 0, 0);
+        assert!(check_manifest(&parsed, &release, current, false).is_ok());
+
+        // An older signed release replayed under the feed's newer tag
+        let replayed = parse_manifest(br#"{"version": "8.7.0", "tag": "v8.7.0", "files": {}}"#).unwrap();
+        assert!(check_manifest(&replayed, &release, current, true).is_err());
+        // Not newer than this build: only with --force
+        assert!(check_manifest(&parsed, &release, Version(9, 0, 1), false).is_err());
+        assert!(check_manifest(&parsed, &release, Version(9, 0, 1), true).is_ok());
+
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let key = BASE64.encode(pair.public_key().as_ref());
+        let signature = BASE64.encode(pair.sign(manifest).as_ref());
+        assert!(verify_signature(&key, manifest, &signature).is_ok());
+        assert!(verify_signature(&key, b"tampered", &signature).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+// Installation self-test - `chonker8 selftest`
+//
+// Builds a one-page PDF in memory and runs it through everything an extraction
+// touches: the external tools and models, rendering, each extraction engine, a
+// database round-trip and search. Every step passes, fails or is skipped - an
+// engine whose tool or model isn't installed is skipped rather than failed, as
+// are missing optional tools - and is timed, so one command tells an operator
+// whether a deployment works and what it lacks. Everything happens in a scratch
+// directory, the database included.
+use anyhow::{anyhow, bail, Result};
+use lopdf::{dictionary, Document, Object, Stream};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::health;
+use crate::pdf_extraction::ExtractorRegistry;
+use crate::pdf_renderer::render_pdf_page;
+use crate::storage::DuckDBStorage;
+use crate::temp_files::scratch_dir;
+
+/// What the sample page says; every engine has to read it back
+pub const SAMPLE_TEXT: &str = "CHONKER SELFTEST";
+/// Large enough for OCR to read from a page render
+const SAMPLE_FONT_SIZE: i64 = 36;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    Skip,
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub subsystem: String,
+    pub outcome: Outcome,
+    pub detail: String,
+    /// None for checks that don't run anything worth timing
+    pub elapsed: Option<Duration>,
+}
+
+impl Step {
+    fn timed(subsystem: impl Into<String>, started: Instant, result: Result<String>) -> Self {
+        let (outcome, detail) = match result {
+            Ok(detail) => (Outcome::Pass, detail),
+            Err(e) => (Outcome::Fail, e.to_string()),
+        };
+        Self { subsystem: subsystem.into(), outcome, detail, elapsed: Some(started.elapsed()) }
+    }
+
+    fn untimed(subsystem: impl Into<String>, outcome: Outcome, detail: impl Into<String>) -> Self {
+        Self { subsystem: subsystem.into(), outcome, detail: detail.into(), elapsed: None }
+    }
+}
+
+/// A Letter page with SAMPLE_TEXT set in Helvetica
+pub fn sample_pdf() -> Document {
+    let mut document = Document::with_version("1.5");
+    let pages_id = document.new_object_id();
+    let font = document.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+    let text = format!("BT /F1 {} Tf 72 680 Td ({}) Tj ET", SAMPLE_FONT_SIZE, SAMPLE_TEXT);
+    let content = document.add_object(Stream::new(dictionary! {}, text.into_bytes()));
+    let page_id = document.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Resources" => dictionary! { "Font" => dictionary! { "F1" => font } },
+        "Contents" => content,
+    });
+    document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![page_id.into()],
+        "Count" => 1,
+    }));
+    let catalog = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    document.trailer.set("Root", catalog);
+    document
+}
+
+/// Whether extracted text has the sample's words in it, however it was laid out
+fn reads_sample(text: &str) -> bool {
+    let words: Vec<String> = text.split_whitespace().map(str::to_uppercase).collect();
+    SAMPLE_TEXT.split_whitespace().all(|word| words.iter().any(|read| read.contains(word)))
+}
+
+fn first_line(text: &str) -> String {
+    text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("(no text)").to_string()
+}
+
+/// Store `content` in a new database and read it back
+fn round_trip(db: &Path, content: &str) -> Result<DuckDBStorage> {
+    let mut storage = DuckDBStorage::new(Some(db))?;
+    storage.store_document("selftest.pdf", content, None)?;
+    let stored = storage.document_by_path("selftest.pdf")?.ok_or_else(|| anyhow!("stored document not found"))?;
+    if stored.content != content {
+        bail!("document came back changed");
+    }
+    Ok(storage)
+}
+
+/// Run every step, in order. Only failing to set up the scratch directory is an error;
+/// everything else is reported as a step.
+pub fn run(registry: &ExtractorRegistry) -> Result<Vec<Step>> {
+    let dir = scratch_dir()?;
+    let pdf = dir.path().join("selftest.pdf");
+    sample_pdf().save(&pdf)?;
+    let mut steps = Vec::new();
+
+    // Tools and models first, so a failure further down has its cause listed above it
+    for check in health::run_checks(&dir.path().join("health.db")) {
+        if check.name == "database" {
+            continue;
+        }
+        let outcome = match (check.ok, check.required) {
+            (true, _) => Outcome::Pass,
+            (false, true) => Outcome::Fail,
+            (false, false) => Outcome::Skip,
+        };
+        steps.push(Step::untimed(check.name, outcome, check.detail));
+    }
+
+    let started = Instant::now();
+    let render = render_pdf_page(&pdf, 0, 612, 792).and_then(|image| {
+        let ink = image.to_luma8().pixels().filter(|pixel| pixel.0[0] < 128).count();
+        if ink == 0 {
+            bail!("page rendered blank");
+        }
+        Ok(format!("{}x{}, {} dark pixels", image.width(), image.height(), ink))
+    });
+    steps.push(Step::timed("render", started, render));
+
+    let mut extracted = None;
+    for name in registry.names() {
+        let engine = registry.get(name).expect("named by the registry");
+        if let Some(missing) = engine.missing() {
+            steps.push(Step::untimed(format!("engine {}", name), Outcome::Skip, missing));
+            continue;
+        }
+        let started = Instant::now();
+        let result = engine.extract(&pdf, 0).and_then(|result| {
+            let line = first_line(&result.text);
+            if !reads_sample(&result.text) {
+                bail!("read \"{}\", expected \"{}\"", line, SAMPLE_TEXT);
+            }
+            extracted.get_or_insert(result.text);
+            Ok(format!("read \"{}\"", line))
+        });
+        steps.push(Step::timed(format!("engine {}", name), started, result));
+    }
+
+    // Storage and search work on whatever an engine read, or the sample text if none could
+    let content = extracted.unwrap_or_else(|| SAMPLE_TEXT.to_string());
+    let started = Instant::now();
+    let storage = match round_trip(&dir.path().join("selftest.db"), &content) {
+        Ok(storage) => {
+            steps.push(Step::timed("storage", started, Ok(format!("{} chars stored and read back", content.chars().count()))));
+            Some(storage)
+        }
+        Err(e) => {
+            steps.push(Step::timed("storage", started, Err(e)));
+            None
+        }
+    };
+
+    match storage {
+        Some(storage) => {
+            let started = Instant::now();
+            let query = SAMPLE_TEXT.split_whitespace().last().unwrap_or(SAMPLE_TEXT).to_lowercase();
+            let search = storage.search(&query, Some(1)).and_then(|hits| match hits.first() {
+                Some(hit) => Ok(format!("\"{}\" found in {}", query, hit.path)),
+                None => bail!("\"{}\" not found", query),
+            });
+            steps.push(Step::timed("search", started, search));
+        }
+        None => steps.push(Step::untimed("search", Outcome::Skip, "no database to search")),
+    }
+
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_pdf_reads_back() {
+        let mut document = sample_pdf();
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+        let document = Document::load_mem(&bytes).unwrap();
+        assert!(reads_sample(&document.extract_text(&[1]).unwrap()));
+
+        assert!(reads_sample("  Chonker\n\n   SELFTEST  "));
+        assert!(!reads_sample("CHONKER"));
+    }
+}
@@ -0,0 +1,402 @@
+// JSON API - extract uploads into the database and read documents back
+//
+// POST /extract                    PDF upload (the file part of multipart/form-data,
+//                                  or a raw body), spooled to disk as it arrives,
+//                                  extracted with the configured engine and stored
+// GET  /search?q=&language=        substring search with snippets
+// GET  /documents                  listing; ?filter= and ?sort= as in `chonker8 list`
+// GET  /documents/:id              one document in full
+// GET  /documents/by-path?path=    the same, looked up by its path
+// GET  /documents/:id/pages/:n     one page's text (1-based) and its blocks with their languages
+// POST /documents                  store an extraction made elsewhere:
+//                                  {"path", "content", "metadata"}
+// GET  /stats                      document count and the `chonker8 stats` text
+//
+// The document and stats routes are what `--db http://...` uses on another
+// machine (storage/remote.rs). When the server has a token, every route
+// answers 401 unless the request carries it as a bearer token.
+//
+// Listings take ?limit= plus either ?page= or ?after=<next_cursor>. Storage calls
+// and extraction run on the blocking pool; the connection sits behind a mutex
+// that is only held for the query itself, never while a PDF is being extracted.
+
+use anyhow::{anyhow, Result};
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Multipart, Path as UrlPath, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use futures::{Stream, StreamExt};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use super::AppState;
+use crate::ingest;
+use crate::pdf_extraction::language::language_code;
+use crate::storage::query::fields;
+use crate::storage::{DuckDBStorage, ListQuery, PageRequest, StoredDocument};
+use crate::temp_files::TEMP_PREFIX;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+/// A failed request: status plus a message sent back as {"error": ...}
+pub(super) struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        tracing::warn!("[SERVER] Request failed: {}", e);
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+fn bad_request(message: impl ToString) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, message.to_string())
+}
+
+fn not_found(message: impl ToString) -> ApiError {
+    ApiError(StatusCode::NOT_FOUND, message.to_string())
+}
+
+/// Let the request through when the server has no token or the request carries it
+pub(super) async fn require_token(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(expected) = state.token.as_deref() else { return next.run(request).await };
+    let offered = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // Comparing digests keeps the time taken independent of how much of the token matched
+    if offered.is_some_and(|offered| Sha256::digest(offered) == Sha256::digest(expected)) {
+        next.run(request).await
+    } else {
+        ApiError(StatusCode::UNAUTHORIZED, "Missing or wrong bearer token".to_string()).into_response()
+    }
+}
+
+/// Run a storage call on the blocking pool
+async fn with_storage<T, F>(state: &AppState, call: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut DuckDBStorage) -> Result<T> + Send + 'static,
+{
+    let storage = state.storage.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut storage = storage.lock().map_err(|_| anyhow!("storage lock poisoned"))?;
+        call(&mut storage)
+    })
+    .await
+    .map_err(|e| anyhow!(e))?;
+    Ok(result?)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct Paging {
+    limit: Option<usize>,
+    page: Option<usize>,
+    after: Option<String>,
+}
+
+impl Paging {
+    fn request(&self) -> PageRequest {
+        PageRequest {
+            limit: self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+            page: self.page.unwrap_or(1),
+            after: self.after.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct SearchParams {
+    q: String,
+    /// ISO 639-1/639-3 code or language name
+    language: Option<String>,
+}
+
+pub(super) async fn search(
+    State(state): State<AppState>,
+    Query(params): Query<SearchParams>,
+    Query(paging): Query<Paging>,
+) -> Result<Json<Value>, ApiError> {
+    if params.q.trim().is_empty() {
+        return Err(bad_request("q must not be empty"));
+    }
+    let language = match params.language.as_deref() {
+        Some(input) => Some(language_code(input).ok_or_else(|| bad_request(format!("Unknown language '{}'", input)))?),
+        None => None,
+    };
+    let request = paging.request();
+    let query = params.q.clone();
+    let page = with_storage(&state, move |storage| storage.search_page(&query, language, &request))
+        .await
+        .map_err(cursor_errors)?;
+
+    let items: Vec<Value> = page
+        .items
+        .iter()
+        .map(|r| json!({ "id": r.id, "path": r.path, "score": r.score, "snippet": r.snippet }))
+        .collect();
+    Ok(Json(json!({ "query": params.q, "items": items, "next_cursor": page.next_cursor })))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ListParams {
+    filter: Option<String>,
+    sort: Option<String>,
+}
+
+pub(super) async fn documents(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+    Query(paging): Query<Paging>,
+) -> Result<Json<Value>, ApiError> {
+    let query = ListQuery {
+        filter: params.filter.as_deref().map(str::parse).transpose().map_err(bad_request)?,
+        sort: params.sort.as_deref().map(str::parse).transpose().map_err(bad_request)?,
+    };
+    let request = paging.request();
+    let page = with_storage(&state, move |storage| storage.list_documents(&query, &request))
+        .await
+        .map_err(cursor_errors)?;
+
+    let items: Vec<Value> = page
+        .items
+        .iter()
+        .map(|doc| Value::Object(fields().map(|f| (f.name.to_string(), doc.json(f.name))).collect()))
+        .collect();
+    Ok(Json(json!({ "items": items, "next_cursor": page.next_cursor })))
+}
+
+/// A bad cursor is the client's mistake, not a server error
+fn cursor_errors(error: ApiError) -> ApiError {
+    match error {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, message) if message.starts_with("Invalid cursor") => {
+            ApiError(StatusCode::BAD_REQUEST, message)
+        }
+        other => other,
+    }
+}
+
+pub(super) async fn document_page(
+    State(state): State<AppState>,
+    UrlPath((id, page)): UrlPath<(i64, usize)>,
+) -> Result<Json<Value>, ApiError> {
+    let (document, blocks) = with_storage(&state, move |storage| {
+        let Some(document) = storage.document_by_id(id)? else { return Ok(None) };
+        let blocks = storage.block_languages(&document.path, page)?;
+        Ok(Some((document, blocks)))
+    })
+    .await?
+    .ok_or_else(|| not_found(format!("No document with id {}", id)))?;
+
+    let pages: Vec<&str> = document.content.split('\x0c').collect();
+    let text = page
+        .checked_sub(1)
+        .and_then(|index| pages.get(index))
+        .ok_or_else(|| not_found(format!("Document {} has pages 1-{}", id, pages.len())))?;
+    let blocks: Vec<Value> = blocks
+        .iter()
+        .map(|b| json!({ "block": b.index, "first_line": b.first_line, "lines": b.lines, "script": b.script, "lang": b.lang, "confidence": b.confidence }))
+        .collect();
+    Ok(Json(json!({ "id": id, "path": document.path, "page": page, "pages": pages.len(), "text": text, "blocks": blocks })))
+}
+
+fn document_json(document: StoredDocument) -> Json<Value> {
+    Json(json!({ "id": document.id, "path": document.path, "content": document.content, "metadata": document.metadata }))
+}
+
+pub(super) async fn document(State(state): State<AppState>, UrlPath(id): UrlPath<i64>) -> Result<Json<Value>, ApiError> {
+    let document = with_storage(&state, move |storage| storage.document_by_id(id))
+        .await?
+        .ok_or_else(|| not_found(format!("No document with id {}", id)))?;
+    Ok(document_json(document))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct PathParams {
+    path: String,
+}
+
+pub(super) async fn document_by_path(
+    State(state): State<AppState>,
+    Query(params): Query<PathParams>,
+) -> Result<Json<Value>, ApiError> {
+    let path = params.path.clone();
+    let document = with_storage(&state, move |storage| storage.document_by_path(&path))
+        .await?
+        .ok_or_else(|| not_found(format!("No document stored for {}", params.path)))?;
+    Ok(document_json(document))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct NewDocument {
+    path: String,
+    content: String,
+    metadata: Option<String>,
+}
+
+pub(super) async fn store_document(
+    State(state): State<AppState>,
+    Json(document): Json<NewDocument>,
+) -> Result<Json<Value>, ApiError> {
+    if document.path.trim().is_empty() {
+        return Err(bad_request("path must not be empty"));
+    }
+    let path = document.path.clone();
+    let id = with_storage(&state, move |storage| {
+        storage.store_document(&document.path, &document.content, document.metadata.as_deref())?;
+        Ok(storage.document_by_path(&document.path)?.map(|doc| doc.id))
+    })
+    .await?;
+    tracing::info!("📥 [SERVER] Stored {} from a remote client", path);
+    Ok(Json(json!({ "id": id, "path": path })))
+}
+
+pub(super) async fn stats(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
+    let (documents, stats) = with_storage(&state, |storage| Ok((storage.document_count()?, storage.get_stats()?))).await?;
+    Ok(Json(json!({ "documents": documents, "stats": stats })))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ExtractParams {
+    /// File name to store a raw (non-multipart) upload under
+    name: Option<String>,
+}
+
+pub(super) async fn extract(
+    State(state): State<AppState>,
+    Query(params): Query<ExtractParams>,
+    request: Request,
+) -> Result<Json<Value>, ApiError> {
+    // Same policy as /extract/stream: refuse rather than queue
+    let Ok(_permit) = state.extraction_slots.clone().try_acquire_owned() else {
+        return Err(ApiError(StatusCode::SERVICE_UNAVAILABLE, "All extraction slots are busy, retry shortly".to_string()));
+    };
+
+    let spool = tempfile::Builder::new().prefix(TEMP_PREFIX).suffix(".pdf").tempfile().map_err(|e| anyhow!(e))?;
+    let content_type = request.headers().get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or("");
+    let (filename, size_bytes) = if content_type.starts_with("multipart/form-data") {
+        let mut multipart = Multipart::from_request(request, &state).await.map_err(|e| ApiError(e.status(), e.body_text()))?;
+        let mut upload = None;
+        while let Some(field) = multipart.next_field().await.map_err(|e| ApiError(e.status(), e.body_text()))? {
+            let filename = field.file_name().filter(|name| !name.is_empty()).map(str::to_string);
+            if filename.is_some() || field.name() == Some("file") {
+                upload = Some((filename, spool_upload(&state, field, spool.path()).await?));
+                break;
+            }
+        }
+        upload.ok_or_else(|| bad_request("No file part in the multipart upload"))?
+    } else {
+        (None, spool_upload(&state, request.into_body().into_data_stream(), spool.path()).await?)
+    };
+    let filename = params.name.or(filename).unwrap_or_else(|| "upload.pdf".to_string());
+
+    let registry = state.registry.clone();
+    let engine = state.engine.clone();
+    let storage = state.storage.clone();
+    let stored = tokio::task::spawn_blocking(move || -> Result<Value, ApiError> {
+        let sha256 = ingest::sha256_file(spool.path())?;
+        let page_count = match lopdf::Document::load(spool.path()) {
+            Ok(document) => document.get_pages().len(),
+            Err(e) => return Err(ApiError(StatusCode::UNPROCESSABLE_ENTITY, format!("Not a readable PDF: {}", e))),
+        };
+
+        let (content, method) = registry.extract_document(&engine, spool.path(), page_count)?;
+        // Uploads have no path of their own; the hash keeps same-named files apart
+        let path = format!("upload:{}/{}", &sha256[..12], filename);
+        let metadata = json!({
+            "sha256": sha256,
+            "pages": page_count,
+            "size_bytes": size_bytes,
+            "engine": method.name(),
+            "source": "upload",
+            "filename": filename,
+        });
+
+        let mut storage = storage.lock().map_err(|_| anyhow!("storage lock poisoned"))?;
+        storage.store_document(&path, &content, Some(&metadata.to_string()))?;
+        let id = storage.document_by_path(&path)?.map(|doc| doc.id);
+        tracing::info!("📥 [SERVER] Stored {} ({} pages, {})", path, page_count, method.name());
+        Ok(json!({
+            "id": id,
+            "path": path,
+            "pages": page_count,
+            "engine": method.name(),
+            "chars": content.chars().count(),
+            "sha256": sha256,
+        }))
+    })
+    .await
+    .map_err(|e| anyhow!(e))??;
+    Ok(Json(stored))
+}
+
+/// Write an upload to the spool file chunk by chunk, as /extract/stream does, so
+/// memory stays at one chunk. Returns the byte count.
+async fn spool_upload<S, E>(state: &AppState, chunks: S, path: &std::path::Path) -> Result<u64, ApiError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::fmt::Display,
+{
+    let mut chunks = std::pin::pin!(chunks);
+    let mut file = tokio::fs::File::create(path).await.map_err(|e| anyhow!(e))?;
+    let mut received = 0u64;
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| bad_request(format!("Upload interrupted: {}", e)))?;
+        received += chunk.len() as u64;
+        if received > state.max_upload_bytes {
+            return Err(ApiError(StatusCode::PAYLOAD_TOO_LARGE, format!("Upload exceeds the {} byte limit", state.max_upload_bytes)));
+        }
+        file.write_all(&chunk).await.map_err(|e| anyhow!(e))?;
+    }
+    file.flush().await.map_err(|e| anyhow!(e))?;
+    Ok(received)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_extraction::ExtractorRegistry;
+    use axum::body::Body;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::Semaphore;
+
+    async fn upload(content_type: &str, body: &'static [u8]) -> StatusCode {
+        let state = AppState {
+            registry: Arc::new(ExtractorRegistry::default()),
+            engine: "auto".to_string(),
+            max_upload_bytes: 64,
+            extraction_slots: Arc::new(Semaphore::new(1)),
+            storage: Arc::new(Mutex::new(DuckDBStorage::new(None).unwrap())),
+            token: None,
+        };
+        let request = Request::builder().header(header::CONTENT_TYPE, content_type).body(Body::from(body)).unwrap();
+        match extract(State(state), Query(ExtractParams { name: None }), request).await {
+            Ok(_) => StatusCode::OK,
+            Err(ApiError(status, _)) => status,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_spooled_from_the_file_part() {
+        // The file part is found past the form field and spooled; it just isn't a PDF
+        let body = b"--xyz\r\nContent-Disposition: form-data; name=\"engine\"\r\n\r\nauto\r\n\
+            --xyz\r\nContent-Disposition: form-data; name=\"file\"; filename=\"scan.pdf\"\r\n\
+            Content-Type: application/pdf\r\n\r\nnot a pdf\r\n--xyz--\r\n";
+        assert_eq!(upload("multipart/form-data; boundary=xyz", body).await, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let no_file = b"--b\r\nContent-Disposition: form-data; name=\"engine\"\r\n\r\nauto\r\n--b--\r\n";
+        assert_eq!(upload("multipart/form-data; boundary=b", no_file).await, StatusCode::BAD_REQUEST);
+        assert_eq!(upload("application/pdf", &[b'%'; 65]).await, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,101 @@
+// HTTP server mode - `chonker8 serve`
+//
+// An axum server on a tokio runtime. The extraction backends are blocking (they
+// shell out to poppler and tesseract), so handlers run them on the blocking pool,
+// and a fixed number of extraction slots keeps a burst of uploads from starting
+// more extractions than the machine can run at once. The JSON API in api.rs
+// stores uploads and reads documents through the same storage the CLI uses.
+//
+// The server listens on 127.0.0.1 unless `--bind` says otherwise. With
+// $CHONKER8_TOKEN set, every route wants `Authorization: Bearer <token>`
+// (storage/remote.rs sends it from the same variable); binding beyond loopback
+// without one is refused, since anyone who can connect could write documents.
+
+mod api;
+mod stream;
+
+use anyhow::Result;
+use axum::extract::DefaultBodyLimit;
+use axum::middleware;
+use axum::routing::{get, post};
+use axum::Router;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use crate::pdf_extraction::ExtractorRegistry;
+use crate::shutdown::Shutdown;
+use crate::storage::DuckDBStorage;
+
+/// Shared secret clients present as a bearer token
+pub const TOKEN_ENV: &str = "CHONKER8_TOKEN";
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind: IpAddr,
+    pub port: u16,
+    /// "auto" or the name of a backend to force
+    pub engine: String,
+    /// Uploads larger than this are rejected mid-stream
+    pub max_upload_bytes: u64,
+    /// Uploads extracted at the same time; further uploads get 503
+    pub max_concurrent: usize,
+    /// Bearer token required on every route, from $CHONKER8_TOKEN
+    pub token: Option<String>,
+}
+
+#[derive(Clone)]
+struct AppState {
+    registry: Arc<ExtractorRegistry>,
+    engine: String,
+    max_upload_bytes: u64,
+    extraction_slots: Arc<Semaphore>,
+    storage: Arc<Mutex<DuckDBStorage>>,
+    token: Option<Arc<str>>,
+}
+
+fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/search", get(api::search))
+        .route("/documents", get(api::documents).post(api::store_document))
+        .route("/documents/by-path", get(api::document_by_path))
+        .route("/documents/:id", get(api::document))
+        .route("/documents/:id/pages/:page", get(api::document_page))
+        .route("/stats", get(api::stats))
+        // Uploads are checked against max_upload_bytes as they are spooled instead
+        .route("/extract", post(api::extract).layer(DefaultBodyLimit::disable()))
+        .route("/extract/stream", post(stream::extract_stream))
+        .route_layer(middleware::from_fn_with_state(state.clone(), api::require_token))
+        .with_state(state)
+}
+
+/// Serve until SIGTERM/SIGINT, letting in-flight requests finish
+pub async fn serve(config: ServerConfig, storage: DuckDBStorage, shutdown: Shutdown) -> Result<()> {
+    if config.token.is_none() && !config.bind.is_loopback() {
+        anyhow::bail!("Refusing to listen on {} without a token - set {} or bind to 127.0.0.1", config.bind, TOKEN_ENV);
+    }
+    let state = AppState {
+        registry: Arc::new(ExtractorRegistry::default()),
+        engine: config.engine.clone(),
+        max_upload_bytes: config.max_upload_bytes,
+        extraction_slots: Arc::new(Semaphore::new(config.max_concurrent.max(1))),
+        storage: Arc::new(Mutex::new(storage)),
+        token: config.token.as_deref().map(Arc::from),
+    };
+
+    let listener = tokio::net::TcpListener::bind((config.bind, config.port)).await?;
+    tracing::info!("🌐 [SERVER] Listening on {}:{} (engine: {}, {} extraction slots, {})",
+        config.bind, config.port, config.engine, config.max_concurrent.max(1),
+        if config.token.is_some() { "token required" } else { "no token" });
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move {
+            while !shutdown.requested() {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            tracing::info!("🛑 [SERVER] Shutdown requested - finishing in-flight requests");
+        })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,270 @@
+// Streaming upload extraction - POST /extract/stream
+//
+// The request body is spooled to a temp file chunk by chunk, so memory per upload
+// stays at one chunk no matter how large the PDF is. While the upload is still
+// arriving, the partial file is periodically run through pdftotext: poppler
+// rebuilds the xref of a truncated file, so linearized and front-loaded PDFs give
+// up their first pages before the last byte lands. A page is only sent early once
+// two attempts at different upload offsets agree on its text - a content stream
+// cut off mid-way changes as more bytes arrive. Everything else is extracted with
+// the configured engine once the upload completes.
+//
+// Results go back as Server-Sent Events through a bounded channel. When the client
+// stops reading events, sends block, the body stops being polled, and TCP
+// back-pressure slows the uploader down instead of pages piling up in memory.
+
+use anyhow::{anyhow, bail, Result};
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::path::Path;
+use std::time::Instant;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use super::AppState;
+use crate::pdf_extraction::extractors::AUTO_ENGINE;
+use crate::pdf_extraction::{DocumentAnalyzer, ExtractionResult, ExtractorRegistry, PageFingerprint};
+use crate::temp_files::TEMP_PREFIX;
+
+/// Events buffered per client before extraction (and then the upload) waits
+const EVENT_BUFFER: usize = 16;
+
+/// New bytes needed before the partial upload is tried again
+const EARLY_ATTEMPT_BYTES: u64 = 256 * 1024;
+
+/// Pages extracted per early attempt, so a huge text-only PDF can't stall the upload
+const EARLY_PAGES_PER_ATTEMPT: usize = 8;
+
+/// The only backend tried on partial uploads - cheap, and poppler repairs truncated files
+const EARLY_ENGINE: &str = "pdftotext";
+
+#[derive(Debug, Serialize)]
+struct PageEvent<'a> {
+    page: usize, // 1-based
+    engine: &'static str,
+    quality: f32,
+    /// Extracted before the upload finished
+    early: bool,
+    text: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct ProgressEvent {
+    received_bytes: u64,
+    pages_sent: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct DoneEvent {
+    pages: usize,
+    early_pages: usize,
+    bytes: u64,
+    elapsed_ms: u64,
+}
+
+pub(super) async fn extract_stream(State(state): State<AppState>, body: Body) -> Response {
+    // Refuse rather than queue: a queued upload would hold its connection open
+    // with nothing being read
+    let Ok(permit) = state.extraction_slots.clone().try_acquire_owned() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "5")],
+            "All extraction slots are busy, retry shortly\n",
+        )
+            .into_response();
+    };
+
+    let (events, receiver) = mpsc::channel::<Event>(EVENT_BUFFER);
+    tokio::spawn(async move {
+        let _permit = permit;
+        if let Err(e) = run_upload(&state, body, &events).await {
+            tracing::warn!("[SERVER] Streaming extraction failed: {}", e);
+            let error = Event::default().event("error").data(e.to_string());
+            let _ = events.send(error).await;
+        }
+    });
+
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|event| (Ok::<_, Infallible>(event), receiver))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+async fn run_upload(state: &AppState, body: Body, events: &mpsc::Sender<Event>) -> Result<()> {
+    let started = Instant::now();
+    let spool = tempfile::Builder::new().prefix(TEMP_PREFIX).tempfile()?;
+    let path = spool.path().to_path_buf();
+    let mut file = tokio::fs::File::create(&path).await?;
+
+    let try_early = state.engine == AUTO_ENGINE || state.engine == EARLY_ENGINE;
+    let mut early = EarlyPages::default();
+    let mut received = 0u64;
+    let mut last_attempt = 0u64;
+
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| anyhow!("upload interrupted: {}", e))?;
+        received += chunk.len() as u64;
+        if received > state.max_upload_bytes {
+            bail!("upload exceeds the {} byte limit", state.max_upload_bytes);
+        }
+        file.write_all(&chunk).await?;
+
+        if try_early && received - last_attempt >= EARLY_ATTEMPT_BYTES {
+            last_attempt = received;
+            file.flush().await?;
+
+            let registry = state.registry.clone();
+            let (attempt_path, first_page) = (path.clone(), early.next_page);
+            let attempt = tokio::task::spawn_blocking(move || {
+                extract_partial(&registry, &attempt_path, first_page)
+            })
+            .await?;
+
+            // Sent before reading more of the body - this is where back-pressure bites
+            for (offset, result) in early.offer(attempt).iter().enumerate() {
+                send(events, page_event(first_page + offset, result, true)?).await?;
+            }
+            let progress = ProgressEvent { received_bytes: received, pages_sent: early.next_page };
+            send(events, Event::default().event("progress").json_data(&progress)?).await?;
+        }
+    }
+    file.flush().await?;
+    drop(file);
+
+    tracing::info!("📥 [SERVER] Upload complete: {} bytes, {} pages sent early", received, early.next_page);
+
+    let registry = state.registry.clone();
+    let engine = state.engine.clone();
+    let first_page = early.next_page;
+    let sender = events.clone();
+    let pages = tokio::task::spawn_blocking(move || {
+        extract_remaining(&registry, &engine, &path, first_page, &sender)
+    })
+    .await??;
+
+    let done = DoneEvent {
+        pages,
+        early_pages: first_page,
+        bytes: received,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    };
+    send(events, Event::default().event("done").json_data(&done)?).await?;
+
+    // The spool is only deleted once every blocking reader is finished with it
+    drop(spool);
+    Ok(())
+}
+
+async fn send(events: &mpsc::Sender<Event>, event: Event) -> Result<()> {
+    events.send(event).await.map_err(|_| anyhow!("client disconnected"))
+}
+
+fn page_event(page_index: usize, result: &ExtractionResult, early: bool) -> Result<Event> {
+    let payload = PageEvent {
+        page: page_index + 1,
+        engine: result.method.name(),
+        quality: result.quality_score,
+        early,
+        text: &result.text,
+    };
+    Ok(Event::default().event("page").json_data(&payload)?)
+}
+
+/// Leading pages readable from the partial file, stopping at the first page that
+/// fails or comes back empty (not arrived yet, or needs OCR after the upload)
+fn extract_partial(registry: &ExtractorRegistry, path: &Path, first_page: usize) -> Vec<ExtractionResult> {
+    let mut results = Vec::new();
+    for page_index in first_page..first_page + EARLY_PAGES_PER_ATTEMPT {
+        match registry.extract_with(EARLY_ENGINE, path, page_index) {
+            Ok(result) if !result.text.trim().is_empty() => results.push(result),
+            _ => break,
+        }
+    }
+    results
+}
+
+/// Extract the pages not sent early from the complete upload with the configured
+/// engine. Runs on the blocking pool; returns the document's page count.
+fn extract_remaining(
+    registry: &ExtractorRegistry,
+    engine: &str,
+    path: &Path,
+    first_page: usize,
+    events: &mpsc::Sender<Event>,
+) -> Result<usize> {
+    let document = lopdf::Document::load(path)?;
+    let page_count = document.get_pages().len();
+    let analyzer = DocumentAnalyzer::new()?;
+
+    for page_index in first_page..page_count {
+        let result = if engine == AUTO_ENGINE {
+            let fingerprint = analyzer
+                .analyze_loaded_page(&document, page_index)
+                .unwrap_or_else(|_| PageFingerprint::new());
+            registry.extract_auto(path, page_index, &fingerprint)?
+        } else {
+            registry.extract_with(engine, path, page_index)?
+        };
+        events
+            .blocking_send(page_event(page_index, &result, false)?)
+            .map_err(|_| anyhow!("client disconnected"))?;
+    }
+    Ok(page_count)
+}
+
+/// Leading pages confirmed from partial uploads. Each attempt's results are held
+/// as candidates and only released when the next attempt reproduces them.
+#[derive(Debug, Default)]
+struct EarlyPages {
+    /// First page not yet sent (0-based)
+    next_page: usize,
+    /// Results for next_page.. from the previous attempt
+    candidates: Vec<ExtractionResult>,
+}
+
+impl EarlyPages {
+    /// Compare an attempt against the previous one and return the pages that match
+    fn offer(&mut self, mut attempt: Vec<ExtractionResult>) -> Vec<ExtractionResult> {
+        let confirmed = attempt
+            .iter()
+            .zip(&self.candidates)
+            .take_while(|(current, previous)| current.text == previous.text)
+            .count();
+
+        self.candidates = attempt.split_off(confirmed);
+        self.next_page += confirmed;
+        attempt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_extraction::ExtractionMethod;
+
+    fn pages(texts: &[&str]) -> Vec<ExtractionResult> {
+        texts.iter().map(|t| ExtractionResult::new(t.to_string(), ExtractionMethod::PdfToText)).collect()
+    }
+
+    #[test]
+    fn test_pages_sent_only_once_two_attempts_agree() {
+        let mut early = EarlyPages::default();
+        assert!(early.offer(pages(&["Page one", "Page tw"])).is_empty());
+
+        // Page 2 grew as more bytes arrived, so only page 1 is stable
+        let confirmed = early.offer(pages(&["Page one", "Page two"]));
+        assert_eq!(confirmed.len(), 1);
+        assert_eq!(early.next_page, 1);
+
+        let confirmed = early.offer(pages(&["Page two", "Page three"]));
+        assert_eq!(confirmed[0].text, "Page two");
+        assert_eq!(early.next_page, 2);
+    }
+}
@@ -0,0 +1,49 @@
+// Graceful shutdown for long-running modes (daemon, watch, serve)
+//
+// The first SIGTERM/SIGINT sets a flag that the work loop checks between jobs, so
+// in-flight documents finish and storage is flushed before exit. A second signal
+// exits immediately for when draining takes too long.
+use anyhow::Result;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    /// Register the SIGTERM/SIGINT handlers
+    pub fn install() -> Result<Self> {
+        let requested = Arc::new(AtomicBool::new(false));
+        for signal in [SIGTERM, SIGINT] {
+            // Order matters: the conditional exit sees the flag set by the first signal
+            signal_hook::flag::register_conditional_shutdown(signal, 130, Arc::clone(&requested))?;
+            signal_hook::flag::register(signal, Arc::clone(&requested))?;
+        }
+        Ok(Self { requested })
+    }
+
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Request shutdown from inside the process (e.g. a fatal error in a worker)
+    pub fn trigger(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Sleep up to `duration`, waking early on shutdown. Returns true if shutdown was requested.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        while Instant::now() < deadline {
+            if self.requested() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(100).min(deadline.saturating_duration_since(Instant::now())));
+        }
+        self.requested()
+    }
+}
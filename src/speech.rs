@@ -0,0 +1,259 @@
+// Spoken export of extracted text - a local TTS engine, stitched with ffmpeg
+//
+// Pages are read paragraph by paragraph (the layout's column gaps would be read
+// out as pauses otherwise). Headings start chapters; each chapter is synthesized
+// on its own so its start time is known, then ffmpeg joins them and writes the
+// chapter markers into the output, in whatever format its extension asks for.
+//
+// Engines: piper (CHONKER8_TTS=piper:<model.onnx>, or piper.onnx in the models
+// directory), `say` on macOS, espeak-ng elsewhere.
+use anyhow::{anyhow, bail, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::pdf_extraction::models;
+use crate::temp_files::Partial;
+use crate::translate::paragraphs;
+
+/// Overrides the engine: "piper:<model.onnx>", "say" or "espeak-ng"
+pub const TTS_ENV: &str = "CHONKER8_TTS";
+/// Under the models directory (see pdf_extraction::models)
+pub const PIPER_MODEL: &str = "piper.onnx";
+
+/// Headings longer than this are taken for ordinary sentences
+const MAX_HEADING_WORDS: usize = 10;
+
+/// A stretch of text read as one chapter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    pub first_page: usize,
+    pub paragraphs: Vec<String>,
+}
+
+/// Split pages (1-based number, text) into chapters at headings - a paragraph's
+/// first line that looks like one. Text before the first heading gets a chapter
+/// named after its page.
+pub fn chapters(pages: &[(usize, &str)]) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for (page, text) in pages {
+        let lines: Vec<&str> = text.lines().collect();
+        for block in lines.split(|line| line.trim().is_empty()).filter(|block| !block.is_empty()) {
+            let first_line = block[0].split_whitespace().collect::<Vec<_>>().join(" ");
+            let heading = looks_like_heading(&first_line);
+            let body = paragraphs(&block[heading as usize..].join("\n"));
+            if heading {
+                chapters.push(Chapter { title: first_line.clone(), first_page: *page, paragraphs: vec![first_line] });
+            }
+            if body.is_empty() {
+                continue;
+            }
+            match chapters.last_mut() {
+                Some(chapter) => chapter.paragraphs.extend(body),
+                None => chapters.push(Chapter { title: format!("Page {}", page), first_page: *page, paragraphs: body }),
+            }
+        }
+    }
+    chapters
+}
+
+/// Short, unpunctuated, and either all caps, title case or numbered ("2.1 Scope")
+pub fn looks_like_heading(paragraph: &str) -> bool {
+    let words: Vec<&str> = paragraph.split_whitespace().collect();
+    if words.is_empty() || words.len() > MAX_HEADING_WORDS || paragraph.ends_with(['.', ',', ';', ':']) {
+        return false;
+    }
+    let letters: Vec<char> = paragraph.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() < 3 {
+        return false;
+    }
+    let all_caps = letters.iter().all(|c| c.is_uppercase());
+    let numbered = words[0].trim_end_matches('.').split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        && words.len() > 1;
+    let capitalized = words.iter().filter(|w| w.chars().next().is_some_and(char::is_uppercase)).count();
+    all_caps || numbered || capitalized * 10 >= words.len() * 7
+}
+
+/// "1-5,8" -> [1, 2, 3, 4, 5, 8], checked against the page count
+pub fn parse_pages(spec: &str, total: usize) -> Result<Vec<usize>> {
+    let mut pages = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (first, last) = match part.split_once('-') {
+            Some((a, b)) => (a.trim().parse::<usize>()?, b.trim().parse::<usize>()?),
+            None => {
+                let page = part.parse::<usize>()?;
+                (page, page)
+            }
+        };
+        if first == 0 || first > last || last > total {
+            bail!("Page range {} out of range (1-{})", part, total);
+        }
+        pages.extend(first..=last);
+    }
+    pages.dedup();
+    Ok(pages)
+}
+
+/// ffmpeg metadata file with one chapter per (title, start ms, end ms)
+pub fn ffmetadata(chapters: &[(String, u64, u64)]) -> String {
+    let escape = |s: &str| {
+        s.chars().fold(String::new(), |mut out, c| {
+            if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                out.push('\\');
+            }
+            out.push(c);
+            out
+        })
+    };
+    let mut out = String::from(";FFMETADATA1\n");
+    for (title, start, end) in chapters {
+        out.push_str(&format!("[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n", start, end, escape(title)));
+    }
+    out
+}
+
+#[derive(Debug, Clone)]
+pub enum TtsEngine {
+    Piper(PathBuf),
+    Say,
+    EspeakNg,
+}
+
+impl TtsEngine {
+    pub fn detect() -> Result<Self> {
+        match std::env::var(TTS_ENV).ok().as_deref() {
+            Some("say") => return Ok(TtsEngine::Say),
+            Some("espeak-ng") => return Ok(TtsEngine::EspeakNg),
+            Some(other) => match other.strip_prefix("piper:") {
+                Some(model) => return Ok(TtsEngine::Piper(PathBuf::from(model))),
+                None => bail!("{} must be piper:<model.onnx>, say or espeak-ng, not '{}'", TTS_ENV, other),
+            },
+            None => {}
+        }
+        let piper = models::locate(PIPER_MODEL);
+        Ok(if piper.exists() {
+            TtsEngine::Piper(piper)
+        } else if cfg!(target_os = "macos") {
+            TtsEngine::Say
+        } else {
+            TtsEngine::EspeakNg
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TtsEngine::Piper(_) => "piper",
+            TtsEngine::Say => "say",
+            TtsEngine::EspeakNg => "espeak-ng",
+        }
+    }
+
+    /// Speak `text` into an audio file in `dir`; returns its path
+    pub fn synthesize(&self, text: &str, dir: &Path, name: &str) -> Result<PathBuf> {
+        let text_path = dir.join(format!("{}.txt", name));
+        std::fs::write(&text_path, text)?;
+        let (out, mut command) = match self {
+            TtsEngine::Piper(model) => {
+                let out = dir.join(format!("{}.wav", name));
+                let mut command = Command::new("piper");
+                command.arg("--model").arg(model).arg("--output_file").arg(&out).stdin(Stdio::piped());
+                (out, command)
+            }
+            TtsEngine::Say => {
+                let out = dir.join(format!("{}.aiff", name));
+                let mut command = Command::new("say");
+                command.arg("-o").arg(&out).arg("-f").arg(&text_path);
+                (out, command)
+            }
+            TtsEngine::EspeakNg => {
+                let out = dir.join(format!("{}.wav", name));
+                let mut command = Command::new("espeak-ng");
+                command.arg("-w").arg(&out).arg("-f").arg(&text_path);
+                (out, command)
+            }
+        };
+
+        let mut child = command
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("{} not available: {}", self.name(), e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!("{} failed: {}", self.name(), String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(out)
+    }
+}
+
+/// Length of an audio file in milliseconds, from ffprobe
+pub fn duration_ms(audio: &Path) -> Result<u64> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(audio)
+        .output()
+        .map_err(|e| anyhow!("ffprobe not available: {}", e))?;
+    if !output.status.success() {
+        bail!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let seconds: f64 = String::from_utf8_lossy(&output.stdout).trim().parse()?;
+    Ok((seconds * 1000.0).round() as u64)
+}
+
+/// Join the segments into `out` with the chapter markers; the format follows its extension
+pub fn join_segments(segments: &[PathBuf], metadata: &str, dir: &Path, out: &Path) -> Result<()> {
+    let list = dir.join("segments.txt");
+    let entries: String = segments
+        .iter()
+        .map(|s| format!("file '{}'\n", s.to_string_lossy().replace('\'', "'\\''")))
+        .collect();
+    std::fs::write(&list, entries)?;
+    let meta = dir.join("chapters.txt");
+    std::fs::write(&meta, metadata)?;
+
+    // ffmpeg writes beside `out` under a name with the same extension, moved over it when done
+    let partial = Partial::new(out)?;
+    let output = Command::new("ffmpeg")
+        .args(["-y", "-v", "error", "-f", "concat", "-safe", "0", "-i"])
+        .arg(&list)
+        .arg("-i")
+        .arg(&meta)
+        .args(["-map", "0:a", "-map_metadata", "1", "-map_chapters", "1"])
+        .arg(partial.path())
+        .output()
+        .map_err(|e| anyhow!("ffmpeg not available: {}", e))?;
+    if !output.status.success() {
+        bail!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    partial.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chapters_split_at_headings() {
+        let pages = [
+            (1, "Acme Corp      Annual Report\n\nThis year went well.\nRevenue grew."),
+            (2, "2.1 Outlook\n\nWe expect more of the same.\x20\n\nRISKS\nNone to speak of."),
+        ];
+        let chapters = chapters(&pages);
+        let titles: Vec<&str> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["Acme Corp Annual Report", "2.1 Outlook", "RISKS"]);
+        assert_eq!(chapters[0].paragraphs[1], "This year went well. Revenue grew.");
+        assert_eq!(chapters[2].paragraphs, vec!["RISKS", "None to speak of."]);
+        assert_eq!(chapters[1].first_page, 2);
+
+        assert_eq!(parse_pages("1-3,5", 5).unwrap(), vec![1, 2, 3, 5]);
+        assert!(parse_pages("4-9", 5).is_err());
+        assert_eq!(
+            ffmetadata(&[("Q1; Q2".to_string(), 0, 1500)]),
+            ";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1500\ntitle=Q1\\; Q2\n"
+        );
+    }
+}
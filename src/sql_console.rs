@@ -0,0 +1,288 @@
+// Interactive SQL console over the corpus database - `chonker8 sql`
+//
+// Statements run once they end with `;`, so they can span lines. Dot commands
+// follow the sqlite3 shell: .tables, .schema [name], .param set|unset|list|clear,
+// .help and .quit, plus .views for the built-in views. Results are printed as an
+// aligned table and paged when they don't fit the terminal. History is kept
+// across sessions. `chonker8 sql --csv "..."` prints one statement's rows as
+// CSV instead, values uncut, for spreadsheets and scripts.
+
+use anyhow::Result;
+use crossterm::terminal;
+use rusqlite::types::Value;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::collections::HashMap;
+use std::io::{stdin, stdout, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use crate::storage::sql::{param_value, SqlOutput};
+use crate::storage::views::VIEWS;
+use crate::storage::DuckDBStorage;
+use crate::tables::csv_field;
+
+/// Longest a cell is shown before it is cut with an ellipsis
+const MAX_CELL_WIDTH: usize = 60;
+
+const HELP: &str = "\
+.tables                 list tables and views
+.views                  built-in views and what they answer
+.schema [NAME]          CREATE statements, for NAME or everything
+.param set NAME VALUE   bind :NAME (or ?N with a number); quote VALUE to force text
+.param unset NAME       forget one parameter
+.param list             show bound parameters
+.param clear            forget all parameters
+.help                   this text
+.quit                   leave (Ctrl-D works too)
+End a statement with ; to run it. Example: SELECT path FROM documents WHERE LENGTH(content) > :min;";
+
+pub struct SqlConsole {
+    storage: DuckDBStorage,
+    params: HashMap<String, Value>,
+}
+
+impl SqlConsole {
+    pub fn new(storage: DuckDBStorage, params: HashMap<String, Value>) -> Self {
+        Self { storage, params }
+    }
+
+    /// Run a single statement or dot command and print the result without paging - for scripts
+    pub fn run_once(&mut self, sql: &str, csv: bool) -> Result<()> {
+        if sql.trim().starts_with('.') {
+            self.dot_command(sql.trim())?;
+            return Ok(());
+        }
+        let output = self.storage.run_sql(sql.trim().trim_end_matches(';'), &self.params)?;
+        if csv && !output.columns.is_empty() {
+            print!("{}", format_csv(&output.columns, &output.rows));
+            return Ok(());
+        }
+        print_output(&output, false)
+    }
+
+    /// Read-eval-print loop until .quit or end of input
+    pub fn run(&mut self) -> Result<()> {
+        let mut editor = DefaultEditor::new()?;
+        let history = history_path();
+        if let Some(path) = &history {
+            let _ = editor.load_history(path);
+        }
+
+        println!("chonker8 sql console - .help for commands, .quit to leave");
+        let mut pending = String::new();
+        loop {
+            let prompt = if pending.is_empty() { "sql> " } else { "...> " };
+            let line = match editor.readline(prompt) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => {
+                    // Ctrl-C drops a half-typed statement, like sqlite3
+                    pending.clear();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(e.into()),
+            };
+
+            let trimmed = line.trim();
+            if pending.is_empty() && trimmed.starts_with('.') {
+                let _ = editor.add_history_entry(trimmed);
+                match self.dot_command(trimmed) {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(e) => {
+                        eprintln!("❌ {}", e);
+                        continue;
+                    }
+                }
+            }
+
+            if !pending.is_empty() {
+                pending.push('\n');
+            }
+            pending.push_str(&line);
+            if !trimmed.ends_with(';') {
+                continue;
+            }
+
+            let _ = editor.add_history_entry(pending.as_str());
+            let sql = std::mem::take(&mut pending);
+            match self.storage.run_sql(sql.trim().trim_end_matches(';'), &self.params) {
+                Ok(output) => print_output(&output, stdout().is_terminal())?,
+                Err(e) => eprintln!("❌ {}", e),
+            }
+        }
+
+        if let Some(path) = &history {
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            let _ = editor.save_history(path);
+        }
+        Ok(())
+    }
+
+    /// Handle a dot command; Ok(false) means quit
+    fn dot_command(&mut self, line: &str) -> Result<bool> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+
+        match (command, args.as_slice()) {
+            (".quit" | ".exit", _) => return Ok(false),
+            (".help", _) => println!("{}", HELP),
+            (".tables", _) => println!("{}", self.storage.table_names()?.join("  ")),
+            (".views", _) => {
+                for view in VIEWS {
+                    println!("{:<24} {}", view.name, view.description);
+                }
+            }
+            (".schema", []) => self.storage.schema_sql(None)?.iter().for_each(|s| println!("{};", s)),
+            (".schema", [name]) => self.storage.schema_sql(Some(name))?.iter().for_each(|s| println!("{};", s)),
+            (".param", ["set", name, value @ ..]) if !value.is_empty() => {
+                let name = name.trim_start_matches([':', '@', '$', '?']);
+                self.params.insert(name.to_string(), param_value(&value.join(" ")));
+            }
+            (".param", ["unset", name]) => {
+                self.params.remove(name.trim_start_matches([':', '@', '$', '?']));
+            }
+            (".param", ["list"]) => {
+                let mut names: Vec<&String> = self.params.keys().collect();
+                names.sort();
+                for name in names {
+                    println!("{:<16} {}", name, display_value(&self.params[name]));
+                }
+            }
+            (".param", ["clear"]) => self.params.clear(),
+            _ => anyhow::bail!("Unknown or incomplete command '{}' - try .help", line),
+        }
+        Ok(true)
+    }
+}
+
+/// Persisted across sessions under the user's data directory
+fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("chonker8").join("sql_history"))
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(n) => n.to_string(),
+        Value::Real(n) => n.to_string(),
+        Value::Text(text) => text.replace(['\n', '\t'], " "),
+        Value::Blob(bytes) => format!("<blob {} bytes>", bytes.len()),
+    }
+}
+
+fn cell(value: &Value) -> String {
+    let text = display_value(value);
+    if text.chars().count() > MAX_CELL_WIDTH {
+        let cut: String = text.chars().take(MAX_CELL_WIDTH - 1).collect();
+        format!("{}…", cut)
+    } else {
+        text
+    }
+}
+
+/// Lines of an aligned table: header, rule, rows. Numeric columns align right.
+pub fn format_table(columns: &[String], rows: &[Vec<Value>]) -> Vec<String> {
+    let cells: Vec<Vec<String>> = rows.iter().map(|row| row.iter().map(cell).collect()).collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| cells.iter().map(|r| r[i].chars().count()).chain([name.chars().count()]).max().unwrap_or(0))
+        .collect();
+    let numeric: Vec<bool> = (0..columns.len())
+        .map(|i| {
+            let mut values = rows.iter().map(|r| &r[i]).filter(|v| !matches!(v, Value::Null)).peekable();
+            values.peek().is_some() && values.all(|v| matches!(v, Value::Integer(_) | Value::Real(_)))
+        })
+        .collect();
+
+    let pad = |text: &str, i: usize| {
+        if numeric[i] {
+            format!("{:>w$}", text, w = widths[i])
+        } else {
+            format!("{:<w$}", text, w = widths[i])
+        }
+    };
+
+    let mut lines = Vec::with_capacity(rows.len() + 2);
+    let header: Vec<String> = columns.iter().enumerate().map(|(i, name)| pad(name, i)).collect();
+    lines.push(header.join(" │ ").trim_end().to_string());
+    lines.push(widths.iter().map(|w| "─".repeat(*w)).collect::<Vec<_>>().join("─┼─"));
+    for row in &cells {
+        let line: Vec<String> = row.iter().enumerate().map(|(i, text)| pad(text, i)).collect();
+        lines.push(line.join(" │ ").trim_end().to_string());
+    }
+    lines
+}
+
+/// Header and rows as CSV; NULL is an empty field
+pub fn format_csv(columns: &[String], rows: &[Vec<Value>]) -> String {
+    let mut out = String::new();
+    let header: Vec<String> = columns.iter().map(|name| csv_field(name)).collect();
+    out.push_str(&header.join(","));
+    out.push_str("\r\n");
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .map(|value| match value {
+                Value::Null => String::new(),
+                Value::Text(text) => csv_field(text),
+                other => csv_field(&display_value(other)),
+            })
+            .collect();
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn print_output(output: &SqlOutput, page: bool) -> Result<()> {
+    if output.columns.is_empty() {
+        println!("{} row(s) changed", output.changed);
+        return Ok(());
+    }
+
+    let lines = format_table(&output.columns, &output.rows);
+    // Leave room for the "more" prompt
+    let screen = terminal::size().map(|(_, rows)| rows as usize).unwrap_or(24).saturating_sub(1).max(3);
+    let mut out = stdout().lock();
+
+    for (shown, line) in lines.iter().enumerate() {
+        if page && shown > 0 && shown % screen == 0 {
+            write!(out, "-- more ({}/{} rows) - Enter to continue, q to stop -- ", shown.saturating_sub(2), output.rows.len())?;
+            out.flush()?;
+            let mut answer = String::new();
+            stdin().lock().read_line(&mut answer)?;
+            if answer.trim().eq_ignore_ascii_case("q") {
+                return Ok(());
+            }
+        }
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out, "({} row{})", output.rows.len(), if output.rows.len() == 1 { "" } else { "s" })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_aligns_numbers_right() {
+        let columns = vec!["path".to_string(), "chars".to_string()];
+        let rows = vec![
+            vec![Value::Text("a.pdf".to_string()), Value::Integer(7)],
+            vec![Value::Text("longer.pdf".to_string()), Value::Integer(1200)],
+        ];
+        let lines = format_table(&columns, &rows);
+        assert_eq!(lines[0], "path       │ chars");
+        assert_eq!(lines[2], "a.pdf      │     7");
+        assert_eq!(lines[3], "longer.pdf │  1200");
+
+        let rows = vec![vec![Value::Text("say \"hi\", twice\nthen stop".to_string()), Value::Null]];
+        assert_eq!(format_csv(&columns, &rows), "path,chars\r\n\"say \"\"hi\"\", twice\nthen stop\",\r\n");
+    }
+}
@@ -0,0 +1,136 @@
+// Reader annotations (highlights, comments, sticky notes) taken from PDFs
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::documents::{into_page, invalid_cursor};
+use super::{DuckDBStorage, Page, PageRequest};
+use crate::pdf_extraction::annotations::Annotation;
+
+/// A stored annotation and the document it belongs to
+#[derive(Debug, Clone)]
+pub struct AnnotationHit {
+    pub id: i64,
+    pub path: String,
+    pub annotation: Annotation,
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS annotations (
+            id INTEGER PRIMARY KEY,
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            color TEXT,
+            rect TEXT NOT NULL,
+            contents TEXT,
+            author TEXT,
+            modified TEXT,
+            quoted TEXT
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_annotations_path ON annotations(path)", [])?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Replace everything stored for a PDF with a fresh read of its annotations
+    pub fn replace_annotations(&mut self, path: &str, annotations: &[Annotation]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM annotations WHERE path = ?1", params![path])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO annotations (path, page, kind, color, rect, contents, author, modified, quoted)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for a in annotations {
+                insert.execute(params![
+                    path,
+                    a.page as i64,
+                    a.kind,
+                    a.color,
+                    serde_json::to_string(&a.rect)?,
+                    a.contents,
+                    a.author,
+                    a.modified,
+                    a.quoted,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Annotations whose comment, quoted text or author contains the query
+    /// (case-insensitive), in the order they were stored
+    pub fn search_annotations(&self, query: &str, request: &PageRequest) -> Result<Page<AnnotationHit>> {
+        let after_id = match request.after.as_deref() {
+            Some(cursor) => Some(cursor.parse::<i64>().map_err(|_| invalid_cursor(cursor))?),
+            None => None,
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, page, kind, color, rect, contents, author, modified, quoted
+             FROM annotations
+             WHERE (contents LIKE '%' || ?1 || '%' OR quoted LIKE '%' || ?1 || '%' OR author LIKE '%' || ?1 || '%')
+               AND (?2 IS NULL OR id > ?2)
+             ORDER BY id
+             LIMIT ?3 OFFSET ?4",
+        )?;
+        let rows = stmt
+            .query_map(params![query, after_id, request.limit as i64 + 1, request.offset()], |row| {
+                let rect: String = row.get(5)?;
+                Ok(AnnotationHit {
+                    id: row.get(0)?,
+                    path: row.get(1)?,
+                    annotation: Annotation {
+                        page: row.get::<_, i64>(2)? as usize,
+                        kind: row.get(3)?,
+                        color: row.get(4)?,
+                        rect: serde_json::from_str(&rect).unwrap_or_default(),
+                        contents: row.get(6)?,
+                        author: row.get(7)?,
+                        modified: row.get(8)?,
+                        quoted: row.get(9)?,
+                    },
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(into_page(rows, request.limit, |hit| hit.id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_matches_comment_and_quoted_text() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        let note = |kind: &str, contents: Option<&str>, quoted: Option<&str>| Annotation {
+            page: 1,
+            kind: kind.to_string(),
+            color: Some("#ffff00".to_string()),
+            rect: [10.0, 20.0, 30.0, 40.0],
+            contents: contents.map(str::to_string),
+            author: None,
+            modified: None,
+            quoted: quoted.map(str::to_string),
+        };
+        storage
+            .replace_annotations("a.pdf", &[
+                note("Highlight", None, Some("Net total due")),
+                note("Text", Some("check the TOTAL"), None),
+                note("Text", Some("unrelated"), None),
+            ])
+            .unwrap();
+
+        let first = storage.search_annotations("total", &PageRequest::first(1)).unwrap();
+        assert_eq!(first.items[0].annotation.quoted.as_deref(), Some("Net total due"));
+        assert_eq!(first.items[0].annotation.rect, [10.0, 20.0, 30.0, 40.0]);
+        let request = PageRequest { limit: 1, page: 1, after: first.next_cursor };
+        let second = storage.search_annotations("total", &request).unwrap();
+        assert_eq!(second.items[0].annotation.kind, "Text");
+        assert!(second.next_cursor.is_none());
+    }
+}
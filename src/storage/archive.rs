@@ -0,0 +1,561 @@
+// Database archives - `chonker8 export`/`import` given a .tar.zst (also `db
+// export`/`db import`), for backups and moving machines
+//
+// An archive is a zstd-compressed tar of JSON: manifest.json (format version,
+// the chonker8 and schema versions that wrote it, row counts) followed by one
+// <table>.jsonl per table, a row per line keyed by column name. Everything
+// stored about the documents goes in - text and metadata, page grids, how each
+// page was extracted, word boxes, OCR/native convergence, translations,
+// annotations, form fields, entities and tags - with the whole edit history:
+// the log, bulk operations, branches, snapshots, the checked-out branch and the
+// rejected edits kept as conflicts, plus the retention and rename audit trails.
+// Left out is what describes this machine or is rebuilt anyway: the file
+// registry and extraction timings, the picker's recent files, pending queues,
+// and the languages, embeddings and search text, which import works out again.
+// Rows are matched to columns by name, so an archive imports into any later
+// schema, and nothing in it depends on SQLite.
+//
+// Importing a document replaces whatever the database had for its path, so
+// importing the same archive twice leaves one copy. What the database numbers
+// itself is numbered afresh: archived edit events get new seqs after its own, in
+// their original order, and bulk operations new ids, and everything pointing at
+// them - event sources (replace-all#<id>, rollback#<id>), branch fork points,
+// snapshots, conflicts - is renumbered to match. A branch or snapshot is known
+// by its name and when it was made; the database having a different one of the
+// same name is a clash, and nothing is imported. The rows go in as one
+// transaction.
+use anyhow::{anyhow, bail, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde_json::{json, Map, Value as Json};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{migrations, reindex, versions, DuckDBStorage};
+
+pub const ARCHIVE_FORMAT: &str = "chonker8-archive";
+/// Bumped when an older chonker8 couldn't read what a newer one writes
+pub const ARCHIVE_VERSION: u64 = 2;
+const MANIFEST: &str = "manifest.json";
+const ZSTD_LEVEL: i32 = 9;
+
+/// How an archived table's rows go back in
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Restore {
+    /// Each replaces what the database had at its path
+    Documents,
+    /// Keyed by document path, and replaced along with the document
+    ByPath,
+    /// Keyed by document id, which the importing database assigns: archived by path
+    Entities,
+    /// Renumbered; events name them in their source
+    Operations,
+    /// Renumbered after the database's own, in order
+    Events,
+    /// Replaced along with the document; the versions they name are renumbered
+    Conflicts,
+    /// Known by name and creation time; the seq they point at is renumbered
+    Branches,
+    Snapshots,
+    /// Taken only into a database with no edit history of its own
+    Head,
+    /// A row already present isn't added twice
+    Audit,
+}
+
+struct Table {
+    name: &'static str,
+    restore: Restore,
+    /// Columns not archived: assigned or rebuilt by the importing database
+    skipped: &'static [&'static str],
+    /// Archived so what points at it can be renumbered, but assigned afresh on import
+    renumbered: Option<&'static str>,
+}
+
+const fn table(name: &'static str, restore: Restore) -> Table {
+    Table { name, restore, skipped: &[], renumbered: None }
+}
+
+/// Tables archived, in the order import needs them: documents first, the edit
+/// log after the operations it names and before what points into it. An archive
+/// of format 1 has a subset in the same order.
+const TABLES: &[Table] = &[
+    Table { skipped: &["id", "search_text"], ..table("documents", Restore::Documents) },
+    table("page_grids", Restore::ByPath),
+    table("page_extractions", Restore::ByPath),
+    table("page_words", Restore::ByPath),
+    table("page_convergence", Restore::ByPath),
+    table("page_translations", Restore::ByPath),
+    Table { skipped: &["id"], ..table("annotations", Restore::ByPath) },
+    Table { skipped: &["id"], ..table("form_fields", Restore::ByPath) },
+    Table { skipped: &["id"], ..table("entities", Restore::Entities) },
+    Table { renumbered: Some("id"), ..table("edit_operations", Restore::Operations) },
+    Table { renumbered: Some("seq"), ..table("edit_events", Restore::Events) },
+    table("document_tags", Restore::ByPath),
+    Table { skipped: &["id"], ..table("edit_conflicts", Restore::Conflicts) },
+    table("version_branches", Restore::Branches),
+    table("version_snapshots", Restore::Snapshots),
+    table("version_head", Restore::Head),
+    Table { skipped: &["id"], ..table("retention_audit", Restore::Audit) },
+    Table { skipped: &["id"], ..table("path_renames", Restore::Audit) },
+];
+
+/// Rows per table, as written or read
+pub type ArchiveCounts = BTreeMap<String, usize>;
+
+fn to_json(value: Value) -> Json {
+    match value {
+        Value::Null => Json::Null,
+        Value::Integer(n) => json!(n),
+        Value::Real(n) => json!(n),
+        Value::Text(text) => Json::String(text),
+        Value::Blob(bytes) => json!({ "base64": BASE64.encode(bytes) }),
+    }
+}
+
+fn from_json(value: &Json) -> Result<Value> {
+    Ok(match value {
+        Json::Null => Value::Null,
+        Json::Bool(flag) => Value::Integer(*flag as i64),
+        Json::Number(n) => match n.as_i64() {
+            Some(n) => Value::Integer(n),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        Json::String(text) => Value::Text(text.clone()),
+        Json::Object(object) => match object.get("base64").and_then(Json::as_str) {
+            Some(encoded) => Value::Blob(BASE64.decode(encoded)?),
+            None => bail!("unexpected object in archive row: {}", value),
+        },
+        Json::Array(_) => bail!("unexpected array in archive row: {}", value),
+    })
+}
+
+fn columns(conn: &Connection, table: &str, skipped: &[&str]) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?;
+    let names = stmt.query_map([table], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(names.into_iter().filter(|name| !skipped.contains(&name.as_str())).collect())
+}
+
+fn append(builder: &mut tar::Builder<impl Write>, name: &str, size: u64, data: impl Read) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+    header.set_cksum();
+    builder.append_data(&mut header, name, data)?;
+    Ok(())
+}
+
+/// Insert the row's values for the table's `columns` with `verb` (INSERT, INSERT OR
+/// REPLACE); returns the new rowid
+fn insert(conn: &Connection, verb: &str, table: &str, columns: &[String], row: &Map<String, Json>) -> Result<i64> {
+    let (names, values): (Vec<&str>, Vec<&Json>) =
+        row.iter().filter(|(name, _)| columns.contains(name)).map(|(name, value)| (name.as_str(), value)).unzip();
+    let values = values.into_iter().map(from_json).collect::<Result<Vec<_>>>()?;
+    let placeholders = vec!["?"; names.len()].join(", ");
+    conn.execute(&format!("{} INTO {} ({}) VALUES ({})", verb, table, names.join(", "), placeholders), params_from_iter(values))?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Insert the row unless one with the same values is there already
+fn insert_new(conn: &Connection, table: &str, columns: &[String], row: &Map<String, Json>) -> Result<()> {
+    let (names, values): (Vec<&str>, Vec<&Json>) =
+        row.iter().filter(|(name, _)| columns.contains(name)).map(|(name, value)| (name.as_str(), value)).unzip();
+    let values = values.into_iter().map(from_json).collect::<Result<Vec<_>>>()?;
+    let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+    let same: Vec<String> = names.iter().zip(&placeholders).map(|(name, placeholder)| format!("{} IS {}", name, placeholder)).collect();
+    conn.execute(
+        &format!(
+            "INSERT INTO {table} ({}) SELECT {} WHERE NOT EXISTS (SELECT 1 FROM {table} WHERE {})",
+            names.join(", "),
+            placeholders.join(", "),
+            same.join(" AND "),
+        ),
+        params_from_iter(values),
+    )?;
+    Ok(())
+}
+
+fn text<'a>(row: &'a Map<String, Json>, column: &str) -> Option<&'a str> {
+    row.get(column).and_then(Json::as_str)
+}
+
+/// What the importing database assigned in place of the archive's numbers
+struct Renumbering {
+    operations: HashMap<i64, i64>,
+    seqs: BTreeMap<i64, i64>,
+    /// The database's last seq before the archived events went in
+    floor: i64,
+    /// Whether the database had no edit history of its own
+    fresh: bool,
+}
+
+impl Renumbering {
+    fn new(conn: &Connection) -> Result<Self> {
+        let floor = versions::last_seq(conn)?;
+        let branches: i64 = conn.query_row("SELECT COUNT(*) FROM version_branches", [], |row| row.get(0))?;
+        Ok(Renumbering { operations: HashMap::new(), seqs: BTreeMap::new(), floor, fresh: floor == 0 && branches == 0 })
+    }
+
+    /// A point in the archived log, as a point in this one: after the same
+    /// archived events. 0 (before any event) stays 0.
+    fn seq(&self, seq: i64) -> i64 {
+        if seq <= 0 {
+            return seq;
+        }
+        self.seqs.range(..=seq).next_back().map_or(self.floor, |(_, renumbered)| *renumbered)
+    }
+
+    /// An event source naming an operation, e.g. replace-all#3, with its new id
+    fn source(&self, source: &str) -> String {
+        let renumbered = source.rsplit_once('#').and_then(|(kind, id)| {
+            let id = self.operations.get(&id.parse().ok()?)?;
+            Some(format!("{}#{}", kind, id))
+        });
+        renumbered.unwrap_or_else(|| source.to_string())
+    }
+
+    fn renumber_seq(&self, row: &mut Map<String, Json>, column: &str) {
+        if let Some(seq) = row.get(column).and_then(Json::as_i64) {
+            row.insert(column.to_string(), json!(self.seq(seq)));
+        }
+    }
+
+    fn renumber_source(&self, row: &mut Map<String, Json>) {
+        if let Some(source) = text(row, "source") {
+            let source = self.source(source);
+            row.insert("source".to_string(), Json::String(source));
+        }
+    }
+}
+
+/// A branch or snapshot from the archive may only replace the same one: a name in
+/// use by anything else here is a clash
+fn check_name(conn: &Connection, table: &str, row: &Map<String, Json>) -> Result<()> {
+    let name = text(row, "name").ok_or_else(|| anyhow!("Archived {} row without a name", table))?;
+    let other = if table == "version_branches" { "version_snapshots" } else { "version_branches" };
+    let taken: bool = conn.query_row(&format!("SELECT COUNT(*) > 0 FROM {} WHERE name = ?1", other), [name], |row| row.get(0))?;
+    let created_at: Option<Option<String>> =
+        conn.query_row(&format!("SELECT created_at FROM {} WHERE name = ?1", table), [name], |row| row.get(0)).optional()?;
+    let same = match created_at {
+        Some(created_at) => created_at.as_deref() == text(row, "created_at"),
+        None => true,
+    };
+    if taken || !same {
+        bail!("'{}' in the archive is a different branch or snapshot than '{}' here - rename one of them first", name, name);
+    }
+    Ok(())
+}
+
+/// Put one archived row back. Returns false for a row with nothing to go with.
+fn restore(conn: &Connection, table: &Table, columns: &[String], mut row: Map<String, Json>, numbers: &mut Renumbering, paths: &mut Vec<String>) -> Result<bool> {
+    match table.restore {
+        Restore::Documents => {
+            let path = text(&row, "path").ok_or_else(|| anyhow!("Archived document without a path"))?.to_string();
+            // Replacing the document gives it a new id, so the old one's entities go now
+            conn.execute("DELETE FROM entities WHERE document_id IN (SELECT id FROM documents WHERE path = ?1)", [&path])?;
+            insert(conn, "INSERT OR REPLACE", table.name, columns, &row)?;
+            paths.push(path);
+        }
+        Restore::ByPath => {
+            insert(conn, "INSERT OR REPLACE", table.name, columns, &row)?;
+        }
+        Restore::Entities => {
+            let path = text(&row, "path").ok_or_else(|| anyhow!("Archived entity without a path"))?;
+            let id: Option<i64> = conn.query_row("SELECT id FROM documents WHERE path = ?1", [path], |row| row.get(0)).optional()?;
+            let Some(id) = id else { return Ok(false) };
+            row.insert("document_id".to_string(), json!(id));
+            insert(conn, "INSERT", table.name, columns, &row)?;
+        }
+        Restore::Operations => {
+            let archived = row.get("id").and_then(Json::as_i64).ok_or_else(|| anyhow!("Archived edit operation without an id"))?;
+            // Imported before: the same kind, summary and time
+            let existing: Option<i64> = conn
+                .query_row(
+                    "SELECT id FROM edit_operations WHERE kind IS ?1 AND summary IS ?2 AND created_at IS ?3",
+                    params![text(&row, "kind"), text(&row, "summary"), text(&row, "created_at")],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let id = match existing {
+                Some(id) => {
+                    conn.execute(
+                        "UPDATE edit_operations SET rolled_back_at = COALESCE(rolled_back_at, ?2) WHERE id = ?1",
+                        params![id, text(&row, "rolled_back_at")],
+                    )?;
+                    id
+                }
+                None => insert(conn, "INSERT", table.name, columns, &row)?,
+            };
+            numbers.operations.insert(archived, id);
+        }
+        Restore::Events => {
+            numbers.renumber_source(&mut row);
+            let seq = insert(conn, "INSERT", table.name, columns, &row)?;
+            // Format 1 archives kept the events in order but not their seqs
+            if let Some(archived) = row.get("seq").and_then(Json::as_i64) {
+                numbers.seqs.insert(archived, seq);
+            }
+        }
+        Restore::Conflicts => {
+            numbers.renumber_source(&mut row);
+            numbers.renumber_seq(&mut row, "expected_seq");
+            numbers.renumber_seq(&mut row, "actual_seq");
+            insert(conn, "INSERT", table.name, columns, &row)?;
+        }
+        Restore::Branches => {
+            check_name(conn, table.name, &row)?;
+            numbers.renumber_seq(&mut row, "base_seq");
+            insert(conn, "INSERT OR REPLACE", table.name, columns, &row)?;
+        }
+        Restore::Snapshots => {
+            check_name(conn, table.name, &row)?;
+            numbers.renumber_seq(&mut row, "seq");
+            insert(conn, "INSERT OR REPLACE", table.name, columns, &row)?;
+        }
+        Restore::Head => {
+            if !numbers.fresh {
+                tracing::info!("[ARCHIVE] Keeping the checked-out branch; the archive had {} checked out", text(&row, "branch").unwrap_or("?"));
+                return Ok(false);
+            }
+            insert(conn, "INSERT OR REPLACE", table.name, columns, &row)?;
+        }
+        Restore::Audit => insert_new(conn, table.name, columns, &row)?,
+    }
+    Ok(true)
+}
+
+impl DuckDBStorage {
+    /// Write every archived table to `out`
+    pub fn export_archive(&self, out: impl Write) -> Result<ArchiveCounts> {
+        // Spooled first: a tar entry's size goes before its data
+        let mut counts = ArchiveCounts::new();
+        let mut spools = Vec::new();
+        for table in TABLES {
+            let sql = match table.restore {
+                Restore::Entities => "SELECT d.path AS path, e.page, e.kind, e.value, e.text
+                                      FROM entities e JOIN documents d ON d.id = e.document_id ORDER BY e.id"
+                    .to_string(),
+                _ => format!("SELECT {} FROM {} ORDER BY rowid", columns(&self.conn, table.name, table.skipped)?.join(", "), table.name),
+            };
+            let mut stmt = self.conn.prepare(&sql)?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let mut spool = BufWriter::new(tempfile::tempfile()?);
+            let mut rows = stmt.query([])?;
+            let mut count = 0;
+            while let Some(row) = rows.next()? {
+                let mut object = Map::new();
+                for (i, column) in columns.iter().enumerate() {
+                    object.insert(column.clone(), to_json(row.get(i)?));
+                }
+                serde_json::to_writer(&mut spool, &object)?;
+                spool.write_all(b"\n")?;
+                count += 1;
+            }
+            let mut spool = spool.into_inner().map_err(|e| e.into_error())?;
+            let size = spool.stream_position()?;
+            spool.rewind()?;
+            counts.insert(table.name.to_string(), count);
+            spools.push((table.name, size, spool));
+        }
+
+        let manifest = json!({
+            "format": ARCHIVE_FORMAT,
+            "version": ARCHIVE_VERSION,
+            "chonker8": env!("CARGO_PKG_VERSION"),
+            "schema_version": migrations::latest_version(),
+            "created_at": SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            "tables": counts,
+        });
+        let manifest = serde_json::to_vec_pretty(&manifest)?;
+
+        let mut builder = tar::Builder::new(zstd::Encoder::new(out, ZSTD_LEVEL)?);
+        append(&mut builder, MANIFEST, manifest.len() as u64, manifest.as_slice())?;
+        for (table, size, spool) in spools {
+            append(&mut builder, &format!("{}.jsonl", table), size, spool)?;
+        }
+        builder.into_inner()?.finish()?.flush()?;
+        Ok(counts)
+    }
+
+    /// Restore an archive written by `export_archive`
+    pub fn import_archive(&mut self, input: impl Read) -> Result<ArchiveCounts> {
+        let mut archive = tar::Archive::new(zstd::Decoder::new(input)?);
+        let mut counts = ArchiveCounts::new();
+        let mut paths = Vec::new();
+        let mut manifest_seen = false;
+        let mut next_table = 0;
+
+        let tx = self.conn.transaction()?;
+        let mut numbers = Renumbering::new(&tx)?;
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let name = entry.path()?.to_string_lossy().to_string();
+            if name == MANIFEST {
+                let manifest: Json = serde_json::from_reader(entry)?;
+                if manifest["format"] != ARCHIVE_FORMAT {
+                    bail!("Not a chonker8 archive");
+                }
+                let version = manifest["version"].as_u64().unwrap_or(0);
+                if version > ARCHIVE_VERSION {
+                    bail!("Archive format {} is newer than this chonker8 reads ({}) - upgrade chonker8", version, ARCHIVE_VERSION);
+                }
+                manifest_seen = true;
+                continue;
+            }
+            if !manifest_seen {
+                bail!("Not a chonker8 archive: {} comes before {}", name, MANIFEST);
+            }
+            let Some(index) = TABLES.iter().position(|table| name == format!("{}.jsonl", table.name)) else {
+                tracing::warn!("[ARCHIVE] Skipping {}, which this chonker8 doesn't know", name);
+                continue;
+            };
+            if index < next_table {
+                bail!("Damaged archive: {} comes after {}.jsonl", name, TABLES[next_table - 1].name);
+            }
+            next_table = index + 1;
+            let table = &TABLES[index];
+
+            let skipped: Vec<&str> = table.skipped.iter().copied().chain(table.renumbered).collect();
+            let known = columns(&tx, table.name, &skipped)?;
+            if table.restore == Restore::Events {
+                numbers.floor = versions::last_seq(&tx)?;
+            }
+            let mut count = 0;
+            for line in BufReader::new(entry).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let row: Map<String, Json> = serde_json::from_str(&line)?;
+                if restore(&tx, table, &known, row, &mut numbers, &mut paths)? {
+                    count += 1;
+                }
+            }
+            // The documents come first; what the database had for them goes before the rest arrives
+            if table.restore == Restore::Documents {
+                let replaced = TABLES.iter().filter(|table| matches!(table.restore, Restore::ByPath | Restore::Events | Restore::Conflicts));
+                for other in replaced {
+                    for path in &paths {
+                        tx.execute(&format!("DELETE FROM {} WHERE path = ?1", other.name), [path])?;
+                    }
+                }
+            }
+            counts.insert(table.name.to_string(), count);
+        }
+        if !manifest_seen {
+            bail!("Not a chonker8 archive: no {}", MANIFEST);
+        }
+        tx.commit()?;
+
+        // Derived data, as storing each document would have made it; no hooks fire for a restore
+        for path in &paths {
+            let Some(document) = self.document_by_path(path)? else { continue };
+            self.record_page_languages(path, &document.content)?;
+            self.record_page_embeddings(path, &document.content)?;
+            reindex::enqueue_document(&self.conn, path)?;
+        }
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coords::GridSize;
+    use crate::entities::{Entity, EntityKind};
+    use crate::storage::PageGrid;
+    use crate::views::text_editor::EditEvent;
+
+    fn base(text: &str) -> Vec<EditEvent> {
+        vec![EditEvent::Base { rows: vec![text.to_string()] }]
+    }
+
+    fn rows(storage: &DuckDBStorage, path: &str) -> Vec<String> {
+        match storage.edit_log(path, 1).unwrap().last().map(|logged| &logged.event) {
+            Some(EditEvent::Base { rows }) => rows.clone(),
+            other => panic!("expected a base event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_archive_round_trip_replaces_documents() {
+        let mut source = DuckDBStorage::new(None).unwrap();
+        source.store_document("a.pdf", "first page\x0csecond page", Some(r#"{"title":"Lease"}"#)).unwrap();
+        let grid = PageGrid { page_width: 612.0, page_height: 792.0, grid: GridSize::new(200, 100) };
+        source.store_page_grid("a.pdf", 1, &grid).unwrap();
+        source.add_tag("a.pdf", "client-x").unwrap();
+        let mut archive = Vec::new();
+        let counts = source.export_archive(&mut archive).unwrap();
+        assert_eq!((counts["documents"], counts["page_grids"], counts["document_tags"]), (1, 1, 1));
+
+        let mut target = DuckDBStorage::new(None).unwrap();
+        target.store_document("a.pdf", "stale", None).unwrap();
+        target.add_tag("a.pdf", "stale-tag").unwrap();
+        for _ in 0..2 {
+            target.import_archive(archive.as_slice()).unwrap();
+        }
+        let document = target.document_by_path("a.pdf").unwrap().unwrap();
+        assert_eq!(document.content, "first page\x0csecond page");
+        assert!(document.metadata.unwrap().contains("Lease"));
+        assert_eq!(target.page_grid("a.pdf", 1).unwrap(), Some(grid));
+        assert_eq!(target.tags("a.pdf").unwrap(), vec!["client-x"]);
+        assert_eq!(target.document_count().unwrap(), 1);
+
+        assert!(target.import_archive(&b"not an archive"[..]).is_err());
+    }
+
+    #[test]
+    fn test_archive_keeps_edit_history_renumbered() {
+        let mut source = DuckDBStorage::new(None).unwrap();
+        source.store_document("a.pdf", "first page", None).unwrap();
+        let id = source.document_by_path("a.pdf").unwrap().unwrap().id;
+        let entity = Entity { kind: EntityKind::Email, value: "a@b.com".to_string(), text: "A@b.com".to_string(), page: 1 };
+        source.replace_entities(id, &[entity]).unwrap();
+        source.store_translation("a.pdf", 1, "de", "erste Seite", "test").unwrap();
+        source.append_edit_events("a.pdf", 1, "tui", &base("first page")).unwrap();
+        source.apply_edit_operation("replace-all", "first -> 1st", &[("a.pdf".to_string(), 1, base("1st page"))]).unwrap();
+        source.create_snapshot("reviewed", None).unwrap();
+        source.create_branch("draft").unwrap();
+        source.append_edit_events("a.pdf", 1, "tui", &base("draft page")).unwrap();
+        let mut archive = Vec::new();
+        source.export_archive(&mut archive).unwrap();
+
+        // A database with history of its own, so every seq and operation id is taken
+        let mut target = DuckDBStorage::new(None).unwrap();
+        target.store_document("b.pdf", "other", None).unwrap();
+        target.append_edit_events("b.pdf", 1, "tui", &base("other")).unwrap();
+        target.apply_edit_operation("replace-all", "other -> else", &[("b.pdf".to_string(), 1, base("else"))]).unwrap();
+        for _ in 0..2 {
+            target.import_archive(archive.as_slice()).unwrap();
+        }
+
+        let log = target.edit_log("a.pdf", 1).unwrap();
+        assert_eq!(log.len(), 2);
+        let operation: i64 = log[1].source.strip_prefix("replace-all#").unwrap().parse().unwrap();
+        assert_eq!(target.edit_operation(operation).unwrap().summary, "first -> 1st");
+        assert_eq!(rows(&target, "a.pdf"), ["1st page"]);
+        assert_eq!(rows(&target, "b.pdf"), ["else"]);
+        let snapshot = target.snapshot("reviewed").unwrap().unwrap();
+        assert_eq!(target.snapshot_log(&snapshot, "a.pdf", 1).unwrap().len(), 2);
+        assert_eq!(target.current_branch().unwrap(), "main");
+        target.switch_branch("draft").unwrap();
+        assert_eq!(rows(&target, "a.pdf"), ["draft page"]);
+        assert_eq!(target.translation("a.pdf", 1, "de").unwrap().as_deref(), Some("erste Seite"));
+        assert_eq!(target.entities(None, None).unwrap().len(), 1);
+
+        // A fresh database takes the checked-out branch too
+        let mut fresh = DuckDBStorage::new(None).unwrap();
+        fresh.import_archive(archive.as_slice()).unwrap();
+        assert_eq!(fresh.current_branch().unwrap(), "draft");
+
+        // A different branch of the same name refuses the whole archive
+        let mut clash = DuckDBStorage::new(None).unwrap();
+        clash.create_branch("draft").unwrap();
+        clash.conn.execute("UPDATE version_branches SET created_at = '2020-01-01 00:00:00'", []).unwrap();
+        assert!(clash.import_archive(archive.as_slice()).unwrap_err().to_string().contains("'draft'"));
+        assert_eq!(clash.document_count().unwrap(), 0);
+    }
+}
@@ -0,0 +1,79 @@
+// Where a corpus lives - a SQLite file on this machine, or a chonker8 server
+//
+// `--db` takes either a path or an http(s):// URL of `chonker8 serve`. The
+// commands that only read documents, list them or store whole extractions go
+// through DocumentStore, so they behave the same against a local file and a
+// corpus shared by several workstations. Everything built on SQL features of
+// the local file (edits, tags, renames, maintenance, ...) needs DuckDBStorage.
+use anyhow::Result;
+use std::path::Path;
+
+use super::remote::RemoteStorage;
+use super::{DocumentSummary, DuckDBStorage, ListQuery, Page, PageRequest, SearchResult, StoredDocument};
+
+pub trait DocumentStore: std::fmt::Debug {
+    /// The database path or server URL, for messages
+    fn location(&self) -> String;
+    fn store_document(&mut self, path: &str, content: &str, metadata: Option<&str>) -> Result<()>;
+    fn document_by_path(&self, path: &str) -> Result<Option<StoredDocument>>;
+    fn document_by_id(&self, id: i64) -> Result<Option<StoredDocument>>;
+    fn search_page(&self, query: &str, language: Option<&str>, request: &PageRequest) -> Result<Page<SearchResult>>;
+    fn list_documents(&self, query: &ListQuery, request: &PageRequest) -> Result<Page<DocumentSummary>>;
+    fn document_count(&self) -> Result<i64>;
+    /// The summary `chonker8 stats` prints
+    fn get_stats(&self) -> Result<String>;
+}
+
+/// Whether `--db` names a server rather than a file
+pub fn is_remote(db: &Path) -> bool {
+    db.to_str().is_some_and(|db| db.starts_with("http://") || db.starts_with("https://"))
+}
+
+/// The store `--db` names: a server for an http(s) URL, otherwise the SQLite file
+pub fn open_store(db: &Path) -> Result<Box<dyn DocumentStore>> {
+    match db.to_str() {
+        Some(url) if is_remote(db) => Ok(Box::new(RemoteStorage::new(url))),
+        _ => Ok(Box::new(LocalStore { path: db.display().to_string(), storage: DuckDBStorage::new(Some(db))? })),
+    }
+}
+
+/// A SQLite file and the path it was opened from
+#[derive(Debug)]
+struct LocalStore {
+    path: String,
+    storage: DuckDBStorage,
+}
+
+impl DocumentStore for LocalStore {
+    fn location(&self) -> String {
+        self.path.clone()
+    }
+
+    fn store_document(&mut self, path: &str, content: &str, metadata: Option<&str>) -> Result<()> {
+        self.storage.store_document(path, content, metadata)
+    }
+
+    fn document_by_path(&self, path: &str) -> Result<Option<StoredDocument>> {
+        self.storage.document_by_path(path)
+    }
+
+    fn document_by_id(&self, id: i64) -> Result<Option<StoredDocument>> {
+        self.storage.document_by_id(id)
+    }
+
+    fn search_page(&self, query: &str, language: Option<&str>, request: &PageRequest) -> Result<Page<SearchResult>> {
+        self.storage.search_page(query, language, request)
+    }
+
+    fn list_documents(&self, query: &ListQuery, request: &PageRequest) -> Result<Page<DocumentSummary>> {
+        self.storage.list_documents(query, request)
+    }
+
+    fn document_count(&self) -> Result<i64> {
+        self.storage.document_count()
+    }
+
+    fn get_stats(&self) -> Result<String> {
+        self.storage.get_stats()
+    }
+}
@@ -0,0 +1,167 @@
+// Optimistic concurrency for the edit log - two editors on one page
+//
+// A page's version is the seq of the last event its log shows on the checked-out
+// branch (0 for a page never edited). A writer says which version its edits were
+// made against; if the log has moved on since, nothing is written, the attempt is
+// kept in edit_conflicts with the rejected events, and the caller gets an
+// EditConflict to merge from. Resolving a conflict stamps how it was resolved.
+use anyhow::Result;
+use rusqlite::{params, Connection, TransactionBehavior};
+
+use super::edits::{insert_events, page_log};
+use super::versions;
+use super::DuckDBStorage;
+use crate::views::text_editor::EditEvent;
+
+/// Page `page` of `path` is at version `actual`, not the `expected` the edits were made against
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Page {page} of {path} was edited elsewhere (now at version {actual}, these edits were made at {expected}) - conflict #{id}")]
+pub struct EditConflict {
+    /// Its row in edit_conflicts
+    pub id: i64,
+    pub path: String,
+    pub page: usize,
+    pub expected: i64,
+    pub actual: i64,
+}
+
+/// A rejected write, as the audit log keeps it
+#[derive(Debug, Clone)]
+pub struct ConflictRecord {
+    pub id: i64,
+    pub path: String,
+    pub page: usize,
+    pub branch: String,
+    pub source: String,
+    pub expected: i64,
+    pub actual: i64,
+    pub events: usize,
+    pub created_at: String,
+    pub resolved_at: Option<String>,
+    pub resolution: Option<String>, // mine, theirs, merged, superseded
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS edit_conflicts (
+            id INTEGER PRIMARY KEY,
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            branch TEXT NOT NULL,
+            source TEXT NOT NULL,
+            expected_seq INTEGER NOT NULL,
+            actual_seq INTEGER NOT NULL,
+            events TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            resolved_at DATETIME,
+            resolution TEXT
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_edit_conflicts_path ON edit_conflicts(path, page)", [])?;
+    Ok(())
+}
+
+fn page_version(conn: &Connection, path: &str, page: usize) -> Result<i64> {
+    let chain = versions::chain(conn, &versions::head(conn)?)?;
+    Ok(page_log(conn, &chain, path, page)?.last().map_or(0, |logged| logged.seq))
+}
+
+impl DuckDBStorage {
+    /// Version of a page's (1-based) log on the checked-out branch; 0 if never edited
+    pub fn edit_version(&self, path: &str, page: usize) -> Result<i64> {
+        page_version(&self.conn, path, page)
+    }
+
+    /// Append events made against version `expected` of a page. Returns the new
+    /// version, or an EditConflict (recorded in the audit log) when the page
+    /// has changed since.
+    pub fn append_edit_events_at(&mut self, path: &str, page: usize, source: &str, expected: i64, events: &[EditEvent]) -> Result<i64> {
+        // Takes the write lock up front so no other writer slips in between the check and the insert
+        let tx = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let actual = page_version(&tx, path, page)?;
+        if actual != expected {
+            tx.execute(
+                "INSERT INTO edit_conflicts (path, page, branch, source, expected_seq, actual_seq, events) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![path, page as i64, versions::head(&tx)?, source, expected, actual, serde_json::to_string(events)?],
+            )?;
+            let id = tx.last_insert_rowid();
+            tx.commit()?;
+            tracing::warn!("[EDITS] Conflict #{} on page {} of {}: at version {}, edits made at {}", id, page, path, actual, expected);
+            return Err(EditConflict { id, path: path.to_string(), page, expected, actual }.into());
+        }
+        insert_events(&tx, path, page, source, events)?;
+        let version = if events.is_empty() { actual } else { tx.last_insert_rowid() };
+        tx.commit()?;
+        Ok(version)
+    }
+
+    /// The events a conflicting write tried to append
+    pub fn conflict_events(&self, id: i64) -> Result<Vec<EditEvent>> {
+        let events: String = self.conn.query_row("SELECT events FROM edit_conflicts WHERE id = ?1", params![id], |row| row.get(0))?;
+        Ok(serde_json::from_str(&events)?)
+    }
+
+    /// Record how a conflict was settled
+    pub fn resolve_edit_conflict(&mut self, id: i64, resolution: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE edit_conflicts SET resolved_at = CURRENT_TIMESTAMP, resolution = ?2 WHERE id = ?1",
+            params![id, resolution],
+        )?;
+        Ok(())
+    }
+
+    /// The conflict audit log, newest first, for one document or all
+    pub fn edit_conflicts(&self, path: Option<&str>) -> Result<Vec<ConflictRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, page, branch, source, expected_seq, actual_seq, events, created_at, resolved_at, resolution
+             FROM edit_conflicts WHERE ?1 IS NULL OR path = ?1 ORDER BY id DESC",
+        )?;
+        let rows = stmt
+            .query_map(params![path], |row| {
+                let events: String = row.get(7)?;
+                Ok(ConflictRecord {
+                    id: row.get(0)?,
+                    path: row.get(1)?,
+                    page: row.get::<_, i64>(2)? as usize,
+                    branch: row.get(3)?,
+                    source: row.get(4)?,
+                    expected: row.get(5)?,
+                    actual: row.get(6)?,
+                    events: serde_json::from_str::<Vec<serde_json::Value>>(&events).map_or(0, |events| events.len()),
+                    created_at: row.get(8)?,
+                    resolved_at: row.get(9)?,
+                    resolution: row.get(10)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_writes_are_refused_and_audited() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        let base = [EditEvent::Base { rows: vec!["abc".to_string()] }];
+        assert_eq!(storage.edit_version("a.pdf", 12).unwrap(), 0);
+        let v1 = storage.append_edit_events_at("a.pdf", 12, "tui", 0, &base).unwrap();
+
+        // Both editors opened the page at v1; the first to save wins
+        let v2 = storage.append_edit_events_at("a.pdf", 12, "tui", v1, &[EditEvent::Undo]).unwrap();
+        let error = storage.append_edit_events_at("a.pdf", 12, "cli", v1, &[EditEvent::Redo]).unwrap_err();
+        let conflict = error.downcast_ref::<EditConflict>().unwrap();
+        assert_eq!((conflict.expected, conflict.actual), (v1, v2));
+        assert_eq!(storage.edit_version("a.pdf", 12).unwrap(), v2);
+
+        let records = storage.edit_conflicts(Some("a.pdf")).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].source.as_str(), records[0].events), ("cli", 1));
+        assert!(matches!(storage.conflict_events(conflict.id).unwrap()[..], [EditEvent::Redo]));
+        storage.resolve_edit_conflict(conflict.id, "theirs").unwrap();
+        assert_eq!(storage.edit_conflicts(None).unwrap()[0].resolution.as_deref(), Some("theirs"));
+    }
+}
@@ -0,0 +1,49 @@
+// Per-page OCR/native convergence scores
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::{hooks, DuckDBStorage, EventKind, StorageEvent};
+use crate::convergence::PageConvergence;
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS page_convergence (
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            token_f1 REAL NOT NULL,
+            word_similarity REAL NOT NULL,
+            native_words INTEGER NOT NULL,
+            ocr_words INTEGER NOT NULL,
+            measured_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (path, page)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Store (or replace) the convergence score for one page
+    pub fn store_convergence(&mut self, path: &str, score: &PageConvergence) -> Result<()> {
+        let data = serde_json::json!({ "token_f1": score.token_f1, "word_similarity": score.word_similarity });
+        let event = StorageEvent::new(EventKind::PageUpdated, path, Some(score.page + 1), data);
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO page_convergence
+                (path, page, token_f1, word_similarity, native_words, ocr_words)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                path,
+                score.page as i64,
+                score.token_f1,
+                score.word_similarity,
+                score.native_words as i64,
+                score.ocr_words as i64,
+            ],
+        )?;
+        hooks::queue_event(&tx, &self.hooks, &event)?;
+        tx.commit()?;
+        hooks::run_commands(&self.hooks, &event);
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+// Paged listing and search over extracted documents
+//
+// Both come in two flavours: `--page N` (LIMIT/OFFSET, handy for jumping around
+// but slower the deeper it goes) and `--after CURSOR` (keyset, constant cost at
+// any depth). Orderings always end on the row id so pages never overlap or skip
+// rows when scores or dates tie.
+use anyhow::{anyhow, Result};
+use rusqlite::params;
+use rusqlite::types::Value;
+
+use super::query::{self, FieldKind, Filter, SortKey};
+use super::{DuckDBStorage, SearchResult};
+use crate::entities::take_type_filter;
+
+/// Characters of context kept on each side of the first match in a search snippet
+const SNIPPET_CONTEXT: i64 = 80;
+
+/// Which slice of a result set to fetch
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    pub limit: usize,
+    /// 1-based page number, used when there is no cursor
+    pub page: usize,
+    /// Cursor from a previous page's `next_cursor`
+    pub after: Option<String>,
+}
+
+impl PageRequest {
+    pub fn first(limit: usize) -> Self {
+        Self { limit, page: 1, after: None }
+    }
+
+    pub(super) fn offset(&self) -> i64 {
+        if self.after.is_some() {
+            0
+        } else {
+            (self.page.max(1) - 1) as i64 * self.limit as i64
+        }
+    }
+}
+
+/// One page of results, with the cursor for the page after it
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// None on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// What to list: optional filter and sort (default: insertion order)
+#[derive(Debug, Clone, Default)]
+pub struct ListQuery {
+    pub filter: Option<Filter>,
+    pub sort: Option<SortKey>,
+}
+
+/// A stored document in full, for exporting to other systems
+#[derive(Debug, Clone)]
+pub struct StoredDocument {
+    pub id: i64,
+    pub path: String,
+    pub content: String,
+    pub metadata: Option<String>,
+}
+
+/// One listed document - every field from query::fields(), in the same order
+#[derive(Debug, Clone)]
+pub struct DocumentSummary {
+    pub values: Vec<Value>,
+}
+
+impl DocumentSummary {
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        let index = query::fields().position(|f| f.name == field)?;
+        self.values.get(index)
+    }
+
+    pub fn id(&self) -> i64 {
+        match self.get("id") {
+            Some(Value::Integer(id)) => *id,
+            _ => 0,
+        }
+    }
+
+    /// Field value for display; empty when NULL
+    pub fn text(&self, field: &str) -> String {
+        match self.get(field) {
+            Some(Value::Integer(n)) => n.to_string(),
+            Some(Value::Real(n)) => format!("{:.2}", n),
+            Some(Value::Text(text)) => text.clone(),
+            _ => String::new(),
+        }
+    }
+
+    pub fn json(&self, field: &str) -> serde_json::Value {
+        match self.get(field) {
+            Some(Value::Integer(n)) => serde_json::json!(n),
+            Some(Value::Real(n)) => serde_json::json!(n),
+            Some(Value::Text(text)) => serde_json::json!(text),
+            _ => serde_json::Value::Null,
+        }
+    }
+}
+
+pub(super) fn invalid_cursor(cursor: &str) -> anyhow::Error {
+    anyhow!("Invalid cursor '{}' - pass the value printed after the previous page", cursor)
+}
+
+/// List cursors are the last row id, or "<sort value>:<id>" when sorted
+fn parse_list_cursor(cursor: &str, sort: Option<&SortKey>) -> Result<(Value, i64)> {
+    let Some(sort) = sort else {
+        let id: i64 = cursor.parse().map_err(|_| invalid_cursor(cursor))?;
+        return Ok((Value::Integer(id), id));
+    };
+    let (value, id) = cursor.rsplit_once(':').ok_or_else(|| invalid_cursor(cursor))?;
+    let id = id.parse().map_err(|_| invalid_cursor(cursor))?;
+    let value = match sort.field.kind {
+        FieldKind::Number => Value::Real(value.parse().map_err(|_| invalid_cursor(cursor))?),
+        FieldKind::Text => Value::Text(value.to_string()),
+    };
+    Ok((value, id))
+}
+
+pub(super) fn list_cursor(doc: &DocumentSummary, sort_value: &Value, sorted: bool) -> String {
+    if !sorted {
+        return doc.id().to_string();
+    }
+    let value = match sort_value {
+        Value::Integer(n) => n.to_string(),
+        Value::Real(n) => n.to_string(),
+        Value::Text(text) => text.clone(),
+        _ => String::new(),
+    };
+    format!("{}:{}", value, doc.id())
+}
+
+/// The value a listing is ordered by for this document - NULL sorts as -1 or ''
+pub(super) fn sort_value(doc: &DocumentSummary, sort: &SortKey) -> Value {
+    match (doc.get(sort.field.name), sort.field.kind) {
+        (Some(Value::Null) | None, FieldKind::Number) => Value::Integer(-1),
+        (Some(Value::Null) | None, FieldKind::Text) => Value::Text(String::new()),
+        (Some(value), _) => value.clone(),
+    }
+}
+
+/// Search cursors are "<score>:<id>" of the last row
+pub(super) fn search_cursor(result: &SearchResult) -> String {
+    format!("{}:{}", result.score as i64, result.id)
+}
+
+fn parse_search_cursor(cursor: &str) -> Result<(i64, i64)> {
+    let parsed = cursor
+        .split_once(':')
+        .and_then(|(score, id)| Some((score.parse().ok()?, id.parse().ok()?)));
+    parsed.ok_or_else(|| invalid_cursor(cursor))
+}
+
+/// Trim the one extra row fetched to detect a following page, and build its cursor
+pub(super) fn into_page<T>(mut items: Vec<T>, limit: usize, cursor_of: impl Fn(&T) -> String) -> Page<T> {
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+    let next_cursor = if has_more { items.last().map(cursor_of) } else { None };
+    Page { items, next_cursor }
+}
+
+impl DuckDBStorage {
+    /// Documents matching the query's filter, in its sort order. Ties always break
+    /// on id ascending, so keyset pages are stable whatever the sort.
+    pub fn list_documents(&self, query: &ListQuery, request: &PageRequest) -> Result<Page<DocumentSummary>> {
+        let columns: Vec<&str> = query::fields().map(|f| f.sql).collect();
+        let sort_sql = query.sort.as_ref().map(|s| s.field.sort_sql()).unwrap_or_else(|| "d.id".to_string());
+        let descending = query.sort.as_ref().map(|s| s.descending).unwrap_or(false);
+
+        let mut conditions = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+        if let Some(filter) = &query.filter {
+            let (sql, params) = filter.to_sql();
+            conditions.push(format!("({})", sql));
+            values.extend(params);
+        }
+        if let Some(cursor) = &request.after {
+            let (after_value, after_id) = parse_list_cursor(cursor, query.sort.as_ref())?;
+            let past = if descending { "<" } else { ">" };
+            conditions.push(format!("({sort} {past} ? OR ({sort} = ? AND d.id > ?))", sort = sort_sql, past = past));
+            values.extend([after_value.clone(), after_value, Value::Integer(after_id)]);
+        }
+        values.push(Value::Integer(request.limit as i64 + 1));
+        values.push(Value::Integer(request.offset()));
+
+        let sql = format!(
+            "SELECT {columns}, {sort} FROM documents d LEFT JOIN files f ON f.path = d.path
+             WHERE {conditions}
+             ORDER BY {sort} {direction}, d.id
+             LIMIT ? OFFSET ?",
+            columns = columns.join(", "),
+            sort = sort_sql,
+            conditions = if conditions.is_empty() { "1".to_string() } else { conditions.join(" AND ") },
+            direction = if descending { "DESC" } else { "ASC" },
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| {
+                let values = (0..columns.len()).map(|i| row.get::<_, Value>(i)).collect::<Result<Vec<_>, _>>()?;
+                Ok((DocumentSummary { values }, row.get::<_, Value>(columns.len())?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let sorted = query.sort.is_some();
+        let page = into_page(rows, request.limit, |(doc, sort_value)| list_cursor(doc, sort_value, sorted));
+        Ok(Page { items: page.items.into_iter().map(|(doc, _)| doc).collect(), next_cursor: page.next_cursor })
+    }
+
+    /// Full documents with an id past `after_id`, in id order - walk the whole
+    /// table in batches by passing the last id seen
+    pub fn documents_after(&self, after_id: i64, limit: usize) -> Result<Vec<StoredDocument>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, content, metadata FROM documents WHERE id > ?1 ORDER BY id LIMIT ?2",
+        )?;
+        let docs = stmt
+            .query_map(params![after_id, limit as i64], |row| {
+                Ok(StoredDocument { id: row.get(0)?, path: row.get(1)?, content: row.get(2)?, metadata: row.get(3)? })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(docs)
+    }
+
+    /// The stored document for a path, if it has been extracted
+    pub fn document_by_path(&self, path: &str) -> Result<Option<StoredDocument>> {
+        let mut stmt = self.conn.prepare("SELECT id, path, content, metadata FROM documents WHERE path = ?1")?;
+        let mut rows = stmt.query_map(params![path], |row| {
+            Ok(StoredDocument { id: row.get(0)?, path: row.get(1)?, content: row.get(2)?, metadata: row.get(3)? })
+        })?;
+        Ok(rows.next().transpose()?)
+    }
+
+    pub fn document_by_id(&self, id: i64) -> Result<Option<StoredDocument>> {
+        let mut stmt = self.conn.prepare("SELECT id, path, content, metadata FROM documents WHERE id = ?1")?;
+        let mut rows = stmt.query_map(params![id], |row| {
+            Ok(StoredDocument { id: row.get(0)?, path: row.get(1)?, content: row.get(2)?, metadata: row.get(3)? })
+        })?;
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Case-insensitive substring search, best matches first. Only a snippet around
+    /// the first match is read back, not the whole document. `language` (ISO 639-3)
+    /// keeps documents with at least one page detected in it.
+    pub fn search_page(&self, query: &str, language: Option<&str>, request: &PageRequest) -> Result<Page<SearchResult>> {
+        // `type:amount` keeps documents with that kind of entity; with nothing
+        // else in the query, every such document matches
+        let (query, kind) = take_type_filter(query)?;
+        let after = request.after.as_deref().map(parse_search_cursor).transpose()?;
+        let (after_score, after_id) = (after.map(|a| a.0), after.map(|a| a.1));
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, score,
+                    SUBSTR(content, MAX(1, first_match - ?5), LENGTH(?1) + 2 * ?5)
+             FROM (
+                 SELECT id, path, COALESCE(search_text, content) AS content,
+                        (LENGTH(content) - LENGTH(REPLACE(LOWER(content), LOWER(?1), ''))) / MAX(LENGTH(?1), 1) AS score,
+                        INSTR(LOWER(content), LOWER(?1)) AS first_match
+                 FROM documents
+                 WHERE COALESCE(search_text, content) LIKE '%' || ?1 || '%'
+                   AND (?7 IS NULL OR EXISTS (
+                       SELECT 1 FROM page_languages l WHERE l.path = documents.path AND l.lang = ?7))
+                   AND (?8 IS NULL OR EXISTS (
+                       SELECT 1 FROM entities e WHERE e.document_id = documents.id AND e.kind = ?8))
+             )
+             WHERE ?2 IS NULL OR score < ?2 OR (score = ?2 AND id > ?3)
+             ORDER BY score DESC, id
+             LIMIT ?4 OFFSET ?6",
+        )?;
+
+        let rows = stmt
+            .query_map(
+                params![
+                    query,
+                    after_score,
+                    after_id,
+                    request.limit as i64 + 1,
+                    SNIPPET_CONTEXT,
+                    request.offset(),
+                    language,
+                    kind.map(|kind| kind.name())
+                ],
+                |row| {
+                    Ok(SearchResult {
+                        id: row.get(0)?,
+                        path: row.get(1)?,
+                        score: row.get::<_, i64>(2)? as f64,
+                        snippet: row.get(3)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(into_page(rows, request.limit, search_cursor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_pages_are_stable_on_ties() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        for i in 0..5 {
+            storage.store_document(&format!("doc{}.pdf", i), "invoice total", None).unwrap();
+        }
+        storage.store_document("many.pdf", "invoice invoice invoice", None).unwrap();
+
+        let first = storage.search_page("invoice", None, &PageRequest::first(2)).unwrap();
+        assert_eq!(first.items[0].path, "many.pdf");
+        assert_eq!(first.items[1].path, "doc0.pdf");
+
+        let mut seen: Vec<String> = first.items.iter().map(|r| r.path.clone()).collect();
+        let mut cursor = first.next_cursor;
+        while let Some(after) = cursor {
+            let page = storage.search_page("invoice", None, &PageRequest { after: Some(after), ..PageRequest::first(2) }).unwrap();
+            seen.extend(page.items.iter().map(|r| r.path.clone()));
+            cursor = page.next_cursor;
+        }
+        assert_eq!(seen, vec!["many.pdf", "doc0.pdf", "doc1.pdf", "doc2.pdf", "doc3.pdf", "doc4.pdf"]);
+
+        let listed = storage.list_documents(&ListQuery::default(), &PageRequest { page: 3, ..PageRequest::first(2) }).unwrap();
+        assert_eq!(listed.items.iter().map(|d| d.id()).collect::<Vec<_>>(), vec![5, 6]);
+        assert!(listed.next_cursor.is_none());
+
+        // Sorted by size descending with ties on id, walked by cursor
+        let query = ListQuery { filter: None, sort: Some("chars:desc".parse().unwrap()) };
+        let first = storage.list_documents(&query, &PageRequest::first(2)).unwrap();
+        assert_eq!(first.items.iter().map(|d| d.id()).collect::<Vec<_>>(), vec![6, 1]);
+        let second = storage.list_documents(&query, &PageRequest { after: first.next_cursor, ..PageRequest::first(2) }).unwrap();
+        assert_eq!(second.items.iter().map(|d| d.id()).collect::<Vec<_>>(), vec![2, 3]);
+    }
+}
@@ -0,0 +1,208 @@
+// Append-only edit log per page - the one history the TUI editor and the CLI share
+//
+// Bulk changes (replace-all) are recorded as an operation: its events are logged
+// with the source "<kind>#<id>", so the whole operation can be found and rolled
+// back together later.
+//
+// Every event belongs to a branch (see versions.rs). Events are written to the
+// checked-out branch, and a page's log is what that branch sees.
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection};
+
+use super::reindex;
+use super::versions::{self, visible};
+use super::DuckDBStorage;
+use crate::views::text_editor::EditEvent;
+
+/// A logged event with where and when it came from
+#[derive(Debug, Clone)]
+pub struct LoggedEdit {
+    pub seq: i64,
+    pub source: String, // tui, cli, replace-all#<id>, rollback#<id>
+    pub event: EditEvent,
+    pub created_at: String,
+}
+
+/// A bulk change spanning many pages
+#[derive(Debug, Clone)]
+pub struct EditOperation {
+    pub id: i64,
+    pub kind: String, // replace-all
+    pub summary: String,
+    pub created_at: String,
+    pub rolled_back_at: Option<String>,
+}
+
+impl EditOperation {
+    /// Source its events are logged under
+    pub fn source(&self) -> String {
+        format!("{}#{}", self.kind, self.id)
+    }
+}
+
+/// Events for one page (1-based) of a document
+pub type PageEvents = (String, usize, Vec<EditEvent>);
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS edit_events (
+            seq INTEGER PRIMARY KEY,
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            event TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            branch TEXT NOT NULL DEFAULT 'main'
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_edit_events_page ON edit_events(path, page)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_edit_events_source ON edit_events(source)", [])?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS edit_operations (
+            id INTEGER PRIMARY KEY,
+            kind TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            rolled_back_at DATETIME
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Append to the checked-out branch
+pub(super) fn insert_events(conn: &Connection, path: &str, page: usize, source: &str, events: &[EditEvent]) -> Result<()> {
+    let branch = versions::head(conn)?;
+    let mut insert = conn.prepare("INSERT INTO edit_events (path, page, source, event, branch) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    for event in events {
+        insert.execute(params![path, page as i64, source, serde_json::to_string(event)?, branch])?;
+    }
+    if !events.is_empty() {
+        reindex::enqueue(conn, path, page)?;
+    }
+    Ok(())
+}
+
+/// A page's log as seen through a branch chain (see versions::chain)
+pub(super) fn page_log(conn: &Connection, chain: &[(String, i64)], path: &str, page: usize) -> Result<Vec<LoggedEdit>> {
+    let mut stmt = conn.prepare(
+        "SELECT seq, source, event, created_at, branch FROM edit_events WHERE path = ?1 AND page = ?2 ORDER BY seq",
+    )?;
+    let rows = stmt
+        .query_map(params![path, page as i64], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?, row.get::<_, String>(4)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    rows.into_iter()
+        .filter(|(seq, _, _, _, branch)| visible(chain, branch, *seq))
+        .map(|(seq, source, event, created_at, _)| Ok(LoggedEdit { seq, source, event: serde_json::from_str(&event)?, created_at }))
+        .collect()
+}
+
+impl DuckDBStorage {
+    /// Append events to a page's (1-based) log in one transaction
+    pub fn append_edit_events(&mut self, path: &str, page: usize, source: &str, events: &[EditEvent]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        insert_events(&tx, path, page, source, events)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Record a bulk change and log all its events in one transaction. Returns the operation.
+    pub fn apply_edit_operation(&mut self, kind: &str, summary: &str, pages: &[PageEvents]) -> Result<EditOperation> {
+        let tx = self.conn.transaction()?;
+        tx.execute("INSERT INTO edit_operations (kind, summary) VALUES (?1, ?2)", params![kind, summary])?;
+        let id = tx.last_insert_rowid();
+        let source = format!("{}#{}", kind, id);
+        for (path, page, events) in pages {
+            insert_events(&tx, path, *page, &source, events)?;
+        }
+        tx.commit()?;
+        self.edit_operation(id)
+    }
+
+    pub fn edit_operation(&self, id: i64) -> Result<EditOperation> {
+        let operation = self.conn.query_row(
+            "SELECT id, kind, summary, created_at, rolled_back_at FROM edit_operations WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(EditOperation {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    summary: row.get(2)?,
+                    created_at: row.get(3)?,
+                    rolled_back_at: row.get(4)?,
+                })
+            },
+        );
+        match operation {
+            Ok(operation) => Ok(operation),
+            Err(rusqlite::Error::QueryReturnedNoRows) => bail!("No edit operation #{}", id),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Every event an operation logged, page by page in log order
+    pub fn edit_operation_events(&self, operation: &EditOperation) -> Result<Vec<PageEvents>> {
+        let chain = versions::chain(&self.conn, &versions::head(&self.conn)?)?;
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, page, event, seq, branch FROM edit_events WHERE source = ?1 ORDER BY path, page, seq")?;
+        let rows = stmt
+            .query_map(params![operation.source()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?, row.get::<_, i64>(3)?, row.get::<_, String>(4)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut pages: Vec<PageEvents> = Vec::new();
+        for (path, page, event, _, _) in rows.into_iter().filter(|(_, _, _, seq, branch)| visible(&chain, branch, *seq)) {
+            let event: EditEvent = serde_json::from_str(&event)?;
+            match pages.last_mut() {
+                Some((last_path, last_page, events)) if *last_path == path && *last_page == page as usize => events.push(event),
+                _ => pages.push((path, page as usize, vec![event])),
+            }
+        }
+        Ok(pages)
+    }
+
+    /// Log the events that undo an operation, marking it rolled back, in one transaction
+    pub fn roll_back_edit_operation(&mut self, operation: &EditOperation, pages: &[PageEvents]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let updated = tx.execute(
+            "UPDATE edit_operations SET rolled_back_at = CURRENT_TIMESTAMP WHERE id = ?1 AND rolled_back_at IS NULL",
+            params![operation.id],
+        )?;
+        if updated == 0 {
+            bail!("Edit operation #{} was already rolled back", operation.id);
+        }
+        let source = format!("rollback#{}", operation.id);
+        for (path, page, events) in pages {
+            insert_events(&tx, path, *page, &source, events)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// A page's (1-based) log on the checked-out branch, oldest first; empty if
+    /// the page was never edited there
+    pub fn edit_log(&self, path: &str, page: usize) -> Result<Vec<LoggedEdit>> {
+        page_log(&self.conn, &versions::chain(&self.conn, &versions::head(&self.conn)?)?, path, page)
+    }
+
+    /// Pages (1-based) of a document that have an edit log on the checked-out branch
+    pub fn edited_pages(&self, path: &str) -> Result<Vec<usize>> {
+        let chain = versions::chain(&self.conn, &versions::head(&self.conn)?)?;
+        let mut stmt = self.conn.prepare("SELECT page, branch, MIN(seq) FROM edit_events WHERE path = ?1 GROUP BY page, branch ORDER BY page")?;
+        let rows = stmt
+            .query_map(params![path], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut pages: Vec<usize> = rows
+            .into_iter()
+            .filter(|(_, branch, first)| visible(&chain, branch, *first))
+            .map(|(page, _, _)| page as usize)
+            .collect();
+        pages.dedup();
+        Ok(pages)
+    }
+}
@@ -0,0 +1,142 @@
+// Page embeddings for semantic search, refreshed whenever a document is stored
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+use crate::embeddings::{cosine, Embedder};
+
+/// Characters of page text shown with a semantic hit
+const SNIPPET_CHARS: usize = 160;
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS page_embeddings (
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            model TEXT NOT NULL,
+            vector BLOB NOT NULL,
+            PRIMARY KEY (path, page)
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_page_embeddings_model ON page_embeddings(model)", [])?;
+    Ok(())
+}
+
+/// A page ranked by similarity to the query
+#[derive(Debug, Clone)]
+pub struct SemanticHit {
+    pub path: String,
+    pub page: usize, // 1-based
+    pub score: f32,  // cosine similarity
+    pub snippet: String,
+}
+
+fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+}
+
+impl DuckDBStorage {
+    /// Embed every stored page with this model from now on
+    pub fn set_embedder(&mut self, embedder: Option<Embedder>) {
+        self.embedder = embedder;
+    }
+
+    pub fn embedder_mut(&mut self) -> Option<&mut Embedder> {
+        self.embedder.as_mut()
+    }
+
+    /// Embed each non-empty page of a document's content. Embeddings are an
+    /// extra, so a page the model fails on is logged and skipped.
+    pub(super) fn record_page_embeddings(&mut self, path: &str, content: &str) -> Result<()> {
+        let Some(embedder) = self.embedder.as_mut() else { return Ok(()) };
+        let mut vectors = Vec::new();
+        for (index, text) in content.split('\x0c').enumerate() {
+            if text.trim().is_empty() {
+                continue;
+            }
+            match embedder.embed(text) {
+                Ok(vector) => vectors.push((index + 1, vector)),
+                Err(e) => tracing::warn!("[EMBED] Page {} of {} failed: {}", index + 1, path, e),
+            }
+        }
+        let model = embedder.model_id().to_string();
+        self.store_page_embeddings(path, &model, &vectors)
+    }
+
+    /// Replace one page's vector
+    pub(super) fn store_page_embedding(&mut self, path: &str, page: usize, model: &str, vector: &[f32]) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO page_embeddings (path, page, model, vector) VALUES (?1, ?2, ?3, ?4)",
+            params![path, page as i64, model, to_blob(vector)],
+        )?;
+        Ok(())
+    }
+
+    pub(super) fn store_page_embeddings(&mut self, path: &str, model: &str, vectors: &[(usize, Vec<f32>)]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM page_embeddings WHERE path = ?1", params![path])?;
+        {
+            let mut insert = tx.prepare("INSERT INTO page_embeddings (path, page, model, vector) VALUES (?1, ?2, ?3, ?4)")?;
+            for (page, vector) in vectors {
+                insert.execute(params![path, *page as i64, model, to_blob(vector)])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Pages embedded with this model
+    pub fn embedded_page_count(&self, model: &str) -> Result<usize> {
+        let count: i64 = self.conn.query_row("SELECT COUNT(*) FROM page_embeddings WHERE model = ?1", params![model], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// The pages most similar to a query vector from the same model, best first.
+    /// A linear scan: fine for a personal library, and no index to keep in sync.
+    pub fn semantic_search(&self, query: &[f32], model: &str, limit: usize) -> Result<Vec<SemanticHit>> {
+        let mut stmt = self.conn.prepare("SELECT path, page, vector FROM page_embeddings WHERE model = ?1")?;
+        let mut ranked = stmt
+            .query_map(params![model], |row| {
+                let blob: Vec<u8> = row.get(2)?;
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize, cosine(query, &from_blob(&blob))))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        ranked.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(&b.0)).then(a.1.cmp(&b.1)));
+        ranked.truncate(limit);
+
+        let mut hits = Vec::with_capacity(ranked.len());
+        for (path, page, score) in ranked {
+            let content: String = self.conn.query_row("SELECT content FROM documents WHERE path = ?1", params![path], |row| row.get(0))?;
+            let text = content.split('\x0c').nth(page - 1).unwrap_or_default();
+            let snippet = text.split_whitespace().collect::<Vec<_>>().join(" ").chars().take(SNIPPET_CHARS).collect();
+            hits.push(SemanticHit { path, page, score, snippet });
+        }
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semantic_search_ranks_by_similarity() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("a.pdf", "Rent agreement\x0cPayment schedule", None).unwrap();
+        storage.store_document("b.pdf", "Lab results", None).unwrap();
+        storage.store_page_embeddings("a.pdf", "test", &[(1, vec![1.0, 0.0]), (2, vec![0.6, 0.8])]).unwrap();
+        storage.store_page_embeddings("b.pdf", "test", &[(1, vec![0.0, 1.0])]).unwrap();
+        storage.store_page_embeddings("c.pdf", "other-model", &[(1, vec![0.0, 1.0])]).unwrap();
+
+        let hits = storage.semantic_search(&[0.0, 1.0], "test", 2).unwrap();
+        assert_eq!(hits.iter().map(|h| (h.path.as_str(), h.page)).collect::<Vec<_>>(), vec![("b.pdf", 1), ("a.pdf", 2)]);
+        assert_eq!(hits[1].snippet, "Payment schedule");
+        assert!((hits[1].score - 0.8).abs() < 1e-6);
+        assert_eq!(storage.embedded_page_count("test").unwrap(), 3);
+    }
+}
@@ -0,0 +1,109 @@
+// Named entities found in document text, one row per occurrence
+//
+// `chonker8 entities extract` fills this from stored documents (see
+// src/entities.rs); value is the normalized form, text the words as written.
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+use crate::entities::{Entity, EntityKind};
+
+/// One stored entity, with the document it was found in
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredEntity {
+    pub document_id: i64,
+    pub path: String,
+    pub page: Option<usize>, // 1-based
+    pub kind: String,
+    pub value: String,
+    pub text: Option<String>,
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS entities (
+            id INTEGER PRIMARY KEY,
+            document_id INTEGER NOT NULL,
+            page INTEGER,
+            kind TEXT NOT NULL,
+            value TEXT NOT NULL,
+            text TEXT
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_entities_document ON entities(document_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_entities_kind ON entities(kind, value)", [])?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Replace a document's entities with a fresh set
+    pub fn replace_entities(&mut self, document_id: i64, entities: &[Entity]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM entities WHERE document_id = ?1", params![document_id])?;
+        {
+            let mut insert = tx.prepare("INSERT INTO entities (document_id, page, kind, value, text) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+            for entity in entities {
+                insert.execute(params![document_id, entity.page as i64, entity.kind.name(), entity.value, entity.text])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stored entities, optionally of one document and/or kind, by document then page
+    pub fn entities(&self, document_id: Option<i64>, kind: Option<EntityKind>) -> Result<Vec<StoredEntity>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.document_id, d.path, e.page, e.kind, e.value, e.text
+             FROM entities e
+             JOIN documents d ON d.id = e.document_id
+             WHERE (?1 IS NULL OR e.document_id = ?1) AND (?2 IS NULL OR e.kind = ?2)
+             ORDER BY e.document_id, e.page, e.id",
+        )?;
+        let entities = stmt
+            .query_map(params![document_id, kind.map(|kind| kind.name())], |row| {
+                Ok(StoredEntity {
+                    document_id: row.get(0)?,
+                    path: row.get(1)?,
+                    page: row.get::<_, Option<i64>>(2)?.map(|page| page as usize),
+                    kind: row.get(3)?,
+                    value: row.get(4)?,
+                    text: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::extract_entities;
+    use crate::storage::PageRequest;
+
+    #[test]
+    fn test_entities_stored_and_filtered_in_search() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("invoice.pdf", "Invoice overdue\x0cTotal due: $1,200.00", None).unwrap();
+        storage.store_document("letter.pdf", "Reminder: overdue, write to billing@acme.example", None).unwrap();
+        for path in ["invoice.pdf", "letter.pdf"] {
+            let doc = storage.document_by_path(path).unwrap().unwrap();
+            storage.replace_entities(doc.id, &extract_entities(&doc.content)).unwrap();
+        }
+
+        let amounts = storage.entities(None, Some(EntityKind::Amount)).unwrap();
+        assert_eq!(amounts.len(), 1);
+        assert_eq!((amounts[0].path.as_str(), amounts[0].page, amounts[0].value.as_str()), ("invoice.pdf", Some(2), "1200.00 USD"));
+        assert_eq!(amounts[0].text.as_deref(), Some("$1,200.00"));
+
+        let search = |query: &str| {
+            let page = storage.search_page(query, None, &PageRequest::first(10)).unwrap();
+            page.items.into_iter().map(|hit| hit.path).collect::<Vec<_>>()
+        };
+        assert_eq!(search("overdue").len(), 2);
+        assert_eq!(search("type:amount overdue"), vec!["invoice.pdf"]);
+        assert_eq!(search("type:email"), vec!["letter.pdf"]);
+        assert!(search("type:phone").is_empty());
+    }
+}
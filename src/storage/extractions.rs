@@ -0,0 +1,166 @@
+// Per-page extraction records - how each page's stored text came out
+//
+// One row per page, replaced whenever the page is extracted again: the method
+// that read it, its quality score, how long it took and the engine version.
+// page_info() puts that together with what other tables hold about the page -
+// its detected language and OCR/native convergence - and auto mode reads the
+// records back to skip pages that came out well last time.
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::DuckDBStorage;
+use crate::pdf_extraction::extractors::engine_version;
+use crate::pdf_extraction::language::PageLanguage;
+use crate::pdf_extraction::ExtractionMethod;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageExtraction {
+    pub page: usize, // 1-based
+    /// ExtractionMethod::name() of what read the page
+    pub method: String,
+    pub quality: f32,
+    pub duration_ms: u64,
+    pub engine_version: String,
+}
+
+impl PageExtraction {
+    /// Whether the page has to be extracted again rather than keep its stored
+    /// text: it scored below `min_quality`, or another version of its engine read it
+    pub fn needs_reextraction(&self, min_quality: f32) -> bool {
+        let same_engine = ExtractionMethod::from_name(&self.method).is_some_and(|method| engine_version(&method) == self.engine_version);
+        self.quality < min_quality || !same_engine
+    }
+}
+
+/// Everything stored about one page's extraction
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageInfo {
+    pub page: usize, // 1-based
+    pub extraction: Option<PageExtraction>,
+    pub extracted_at: Option<String>,
+    pub language: Option<PageLanguage>,
+    /// Token F1 between the OCR and native text, where it was measured
+    pub convergence: Option<f32>,
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS page_extractions (
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            method TEXT NOT NULL,
+            quality REAL NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            engine_version TEXT NOT NULL,
+            extracted_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (path, page)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn extraction_row(row: &rusqlite::Row) -> rusqlite::Result<PageExtraction> {
+    Ok(PageExtraction {
+        page: row.get::<_, i64>(0)? as usize,
+        method: row.get(1)?,
+        quality: row.get::<_, f64>(2)? as f32,
+        duration_ms: row.get::<_, i64>(3)? as u64,
+        engine_version: row.get(4)?,
+    })
+}
+
+impl DuckDBStorage {
+    /// Record how pages were extracted, replacing their earlier records
+    pub fn store_page_extractions(&mut self, path: &str, pages: &[PageExtraction]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for page in pages {
+            tx.execute(
+                "INSERT OR REPLACE INTO page_extractions (path, page, method, quality, duration_ms, engine_version)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![path, page.page as i64, page.method, page.quality, page.duration_ms as i64, page.engine_version],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// A document's page records, in page order
+    pub fn page_extractions(&self, path: &str) -> Result<Vec<PageExtraction>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT page, method, quality, duration_ms, engine_version FROM page_extractions WHERE path = ?1 ORDER BY page",
+        )?;
+        let pages = stmt.query_map(params![path], extraction_row)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(pages)
+    }
+
+    /// What is stored about one page (1-based); None if the document isn't stored
+    /// or has no such page
+    pub fn page_info(&self, path: &str, page: usize) -> Result<Option<PageInfo>> {
+        let pages: Option<usize> = self
+            .conn
+            .query_row("SELECT content FROM documents WHERE path = ?1", params![path], |row| row.get::<_, String>(0))
+            .optional()?
+            .map(|content| content.split('\x0c').count());
+        if !pages.is_some_and(|pages| (1..=pages).contains(&page)) {
+            return Ok(None);
+        }
+
+        let (extraction, extracted_at) = self
+            .conn
+            .query_row(
+                "SELECT page, method, quality, duration_ms, engine_version, extracted_at FROM page_extractions WHERE path = ?1 AND page = ?2",
+                params![path, page as i64],
+                |row| Ok((extraction_row(row)?, row.get::<_, String>(5)?)),
+            )
+            .optional()?
+            .unzip();
+        let language = self.page_languages(path)?.into_iter().find(|language| language.page == page);
+        // Convergence pages are 0-based
+        let convergence = self
+            .conn
+            .query_row(
+                "SELECT token_f1 FROM page_convergence WHERE path = ?1 AND page = ?2",
+                params![path, page as i64 - 1],
+                |row| row.get::<_, f64>(0),
+            )
+            .optional()?
+            .map(|f1| f1 as f32);
+        Ok(Some(PageInfo { page, extraction, extracted_at, language, convergence }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_info_joins_extraction_and_language() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("a.pdf", "The tenant shall pay the rent on the first day of each month.\x0c", None).unwrap();
+        let record = |quality| PageExtraction {
+            page: 1,
+            method: "lopdf".to_string(),
+            quality,
+            duration_ms: 12,
+            engine_version: engine_version(&ExtractionMethod::Lopdf),
+        };
+        storage.store_page_extractions("a.pdf", &[record(0.2)]).unwrap();
+        storage.store_page_extractions("a.pdf", &[record(0.9)]).unwrap();
+        assert_eq!(storage.page_extractions("a.pdf").unwrap(), vec![record(0.9)]);
+        assert!(!record(0.9).needs_reextraction(0.4));
+        assert!(record(0.2).needs_reextraction(0.4));
+        assert!(PageExtraction { engine_version: "chonker8 8.7.0".to_string(), ..record(0.9) }.needs_reextraction(0.4));
+
+        let info = storage.page_info("a.pdf", 1).unwrap().unwrap();
+        assert_eq!(info.extraction, Some(record(0.9)));
+        assert!(info.extracted_at.is_some());
+        assert_eq!(info.language.map(|language| language.lang), Some("eng".to_string()));
+        assert_eq!(info.convergence, None);
+
+        let blank = storage.page_info("a.pdf", 2).unwrap().unwrap();
+        assert_eq!((blank.extraction, blank.language), (None, None));
+        assert!(storage.page_info("a.pdf", 3).unwrap().is_none());
+        assert!(storage.page_info("b.pdf", 1).unwrap().is_none());
+    }
+}
@@ -0,0 +1,207 @@
+// Several databases queried as one - `--db-set 2023.db,2024.db,client-x.db`
+//
+// Each member answers a query on its own, and the hits are merged in the order a
+// single database would have produced them (score, or the --sort field; members
+// in --db-set order on ties). Paging works the same way as on one database: the
+// cursor for the next page holds each member's own cursor, i.e. how far into its
+// results the pages so far have read. Members may be chonker8 servers as well as
+// files (`--db-set local.db,http://corpus.internal:8080`).
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as CURSOR, Engine as _};
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::PathBuf;
+
+use super::documents::{invalid_cursor, list_cursor, search_cursor, sort_value};
+use super::backend::{is_remote, open_store, DocumentStore};
+use super::{DocumentSummary, ListQuery, Page, PageRequest, SearchResult};
+
+/// [federation] in extraction.toml - what a bare `--db-set` queries
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct FederationConfig {
+    #[serde(default)]
+    pub databases: Vec<PathBuf>,
+}
+
+/// A hit and the database it came from
+#[derive(Debug)]
+pub struct Sourced<T> {
+    /// The database's path or server URL as given
+    pub source: String,
+    pub item: T,
+}
+
+#[derive(Debug)]
+struct Member {
+    source: String,
+    storage: Box<dyn DocumentStore>,
+}
+
+#[derive(Debug)]
+pub struct Federation {
+    members: Vec<Member>,
+}
+
+impl Federation {
+    /// Open every database; unlike a single --db, a missing file is an error
+    /// rather than a new empty corpus
+    pub fn open(paths: &[PathBuf]) -> Result<Self> {
+        if paths.is_empty() {
+            bail!("--db-set needs at least one database (or [federation] databases in the config)");
+        }
+        let members = paths
+            .iter()
+            .map(|path| {
+                if !is_remote(path) && !path.is_file() {
+                    bail!("No database at {}", path.display());
+                }
+                Ok(Member { source: path.display().to_string(), storage: open_store(path)? })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { members })
+    }
+
+    /// `search_page` over every member, best matches first
+    pub fn search_page(&self, query: &str, language: Option<&str>, request: &PageRequest) -> Result<Page<Sourced<SearchResult>>> {
+        self.merged(
+            request,
+            |storage, request| storage.search_page(query, language, request),
+            search_cursor,
+            |a, b| b.score.total_cmp(&a.score),
+        )
+    }
+
+    /// `list_documents` over every member. Unsorted, each database is listed in
+    /// full before the next.
+    pub fn list_documents(&self, query: &ListQuery, request: &PageRequest) -> Result<Page<Sourced<DocumentSummary>>> {
+        let sort = query.sort.as_ref();
+        self.merged(
+            request,
+            |storage, request| storage.list_documents(query, request),
+            |doc| match sort {
+                Some(sort) => list_cursor(doc, &sort_value(doc, sort), true),
+                None => list_cursor(doc, &Value::Null, false),
+            },
+            |a, b| match sort {
+                Some(sort) => {
+                    let order = compare_values(&sort_value(a, sort), &sort_value(b, sort));
+                    if sort.descending { order.reverse() } else { order }
+                }
+                None => Ordering::Equal,
+            },
+        )
+    }
+
+    /// Each member's stats under its name, then the combined document count
+    pub fn get_stats(&self) -> Result<String> {
+        let mut stats = Vec::new();
+        let mut total: i64 = 0;
+        for member in &self.members {
+            total += member.storage.document_count()?;
+            stats.push(format!("== {} ==\n{}", member.source, member.storage.get_stats()?));
+        }
+        stats.push(format!("Documents across {} databases: {}", self.members.len(), total));
+        Ok(stats.join("\n\n"))
+    }
+
+    /// Fetch a page from every member, merge them with `order` (stable, so ties
+    /// keep --db-set order and each member's own order) and keep one page
+    fn merged<T>(
+        &self,
+        request: &PageRequest,
+        fetch: impl Fn(&dyn DocumentStore, &PageRequest) -> Result<Page<T>>,
+        cursor_of: impl Fn(&T) -> String,
+        order: impl Fn(&T, &T) -> Ordering,
+    ) -> Result<Page<Sourced<T>>> {
+        let mut positions = match &request.after {
+            Some(cursor) => decode_cursor(cursor, self.members.len())?,
+            None => vec![None; self.members.len()],
+        };
+        // --page N has to read everything before the page from every member
+        let skip = request.offset() as usize;
+        let mut more = false;
+        let mut hits: Vec<(usize, T)> = Vec::new();
+        for (index, member) in self.members.iter().enumerate() {
+            let member_request = PageRequest { limit: skip + request.limit, page: 1, after: positions[index].clone() };
+            let page = fetch(member.storage.as_ref(), &member_request)?;
+            more |= page.next_cursor.is_some();
+            hits.extend(page.items.into_iter().map(|item| (index, item)));
+        }
+        hits.sort_by(|a, b| order(&a.1, &b.1));
+
+        let read = hits.len().min(skip + request.limit);
+        more |= hits.len() > read;
+        let mut items = Vec::new();
+        for (index, item) in hits.into_iter().take(read) {
+            positions[index] = Some(cursor_of(&item));
+            items.push(Sourced { source: self.members[index].source.clone(), item });
+        }
+        let items: Vec<_> = items.into_iter().skip(skip).collect();
+        let next_cursor = if more && !items.is_empty() { Some(encode_cursor(&positions)) } else { None };
+        Ok(Page { items, next_cursor })
+    }
+}
+
+/// SQLite's ordering for the values a sort column holds
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    let number = |value: &Value| match value {
+        Value::Integer(n) => Some(*n as f64),
+        Value::Real(n) => Some(*n),
+        _ => None,
+    };
+    match (a, b, number(a), number(b)) {
+        (_, _, Some(x), Some(y)) => x.total_cmp(&y),
+        (Value::Text(x), Value::Text(y), _, _) => x.cmp(y),
+        // Numbers sort before text, as they do in SQLite
+        (_, _, Some(_), None) => Ordering::Less,
+        (_, _, None, Some(_)) => Ordering::Greater,
+        _ => Ordering::Equal,
+    }
+}
+
+fn encode_cursor(positions: &[Option<String>]) -> String {
+    CURSOR.encode(serde_json::to_string(positions).unwrap_or_default())
+}
+
+fn decode_cursor(cursor: &str, members: usize) -> Result<Vec<Option<String>>> {
+    let positions: Vec<Option<String>> = CURSOR
+        .decode(cursor)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| invalid_cursor(cursor))?;
+    if positions.len() != members {
+        bail!("Cursor '{}' is for {} databases, --db-set has {}", cursor, positions.len(), members);
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::DuckDBStorage;
+
+    #[test]
+    fn test_search_merges_and_pages_across_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = ["2023.db", "2024.db"].iter().map(|name| dir.path().join(name)).collect();
+        let mut first = DuckDBStorage::new(Some(&paths[0])).unwrap();
+        first.store_document("a.pdf", "invoice", None).unwrap();
+        first.store_document("b.pdf", "invoice invoice invoice", None).unwrap();
+        let mut second = DuckDBStorage::new(Some(&paths[1])).unwrap();
+        second.store_document("c.pdf", "invoice invoice", None).unwrap();
+        second.store_document("d.pdf", "receipt", None).unwrap();
+
+        let federation = Federation::open(&paths).unwrap();
+        let page = federation.search_page("invoice", None, &PageRequest::first(2)).unwrap();
+        let hits: Vec<(&str, &str)> = page.items.iter().map(|h| (h.source.as_str(), h.item.path.as_str())).collect();
+        assert_eq!(hits, vec![(paths[0].to_str().unwrap(), "b.pdf"), (paths[1].to_str().unwrap(), "c.pdf")]);
+
+        let rest = federation.search_page("invoice", None, &PageRequest { after: page.next_cursor, ..PageRequest::first(2) }).unwrap();
+        assert_eq!(rest.items.iter().map(|h| h.item.path.as_str()).collect::<Vec<_>>(), vec!["a.pdf"]);
+        assert!(rest.next_cursor.is_none());
+        assert_eq!(federation.search_page("invoice", None, &PageRequest { page: 2, ..PageRequest::first(2) }).unwrap().items.len(), 1);
+
+        assert!(Federation::open(&[dir.path().join("missing.db")]).is_err());
+    }
+}
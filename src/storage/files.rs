@@ -0,0 +1,144 @@
+// File registry - tracks PDFs discovered by ingest before (and after) extraction
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+use super::{hooks, DuckDBStorage, EventKind, StorageEvent};
+
+/// A PDF registered in the corpus by the ingest pre-scan
+#[derive(Debug, Clone)]
+pub struct FileRecord {
+    pub path: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub modified_at: i64,        // Unix seconds from filesystem mtime
+    pub page_count: Option<usize>,
+    pub pdf_version: Option<String>,
+    pub text_coverage: f32,      // First-page fingerprint, 0.0-1.0
+    pub image_coverage: f32,     // First-page fingerprint, 0.0-1.0
+    pub status: String,          // scanned, extracted, failed
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS files (
+            id INTEGER PRIMARY KEY,
+            path TEXT UNIQUE NOT NULL,
+            sha256 TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            modified_at INTEGER NOT NULL,
+            page_count INTEGER,
+            pdf_version TEXT,
+            text_coverage REAL NOT NULL DEFAULT 0,
+            image_coverage REAL NOT NULL DEFAULT 0,
+            status TEXT NOT NULL DEFAULT 'scanned',
+            scanned_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_files_sha256 ON files(sha256)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Insert or refresh a scanned file. Re-registering a changed file resets its status.
+    pub fn upsert_file(&mut self, file: &FileRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO files (path, sha256, size_bytes, modified_at, page_count, pdf_version,
+                                text_coverage, image_coverage, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(path) DO UPDATE SET
+                sha256 = excluded.sha256,
+                size_bytes = excluded.size_bytes,
+                modified_at = excluded.modified_at,
+                page_count = excluded.page_count,
+                pdf_version = excluded.pdf_version,
+                text_coverage = excluded.text_coverage,
+                image_coverage = excluded.image_coverage,
+                status = excluded.status,
+                scanned_at = CURRENT_TIMESTAMP",
+            params![
+                file.path,
+                file.sha256,
+                file.size_bytes as i64,
+                file.modified_at,
+                file.page_count.map(|p| p as i64),
+                file.pdf_version,
+                file.text_coverage,
+                file.image_coverage,
+                file.status,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Register a batch of scanned files in a single transaction
+    pub fn upsert_files(&mut self, files: &[FileRecord]) -> Result<()> {
+        self.conn.execute_batch("BEGIN")?;
+        for file in files {
+            if let Err(e) = self.upsert_file(file) {
+                self.conn.execute_batch("ROLLBACK")?;
+                return Err(e);
+            }
+        }
+        self.conn.execute_batch("COMMIT")?;
+        Ok(())
+    }
+
+    /// Map of path -> (size, mtime) for every registered file, used to skip unchanged files
+    pub fn known_files(&self) -> Result<HashMap<String, (u64, i64)>> {
+        let mut stmt = self.conn.prepare("SELECT path, size_bytes, modified_at FROM files")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, (row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)?)))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Files with the given status, in registration order
+    pub fn files_with_status(&self, status: &str) -> Result<Vec<FileRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, sha256, size_bytes, modified_at, page_count, pdf_version,
+                    text_coverage, image_coverage, status
+             FROM files WHERE status = ?1 ORDER BY id"
+        )?;
+        let files = stmt.query_map(params![status], row_to_file)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(files)
+    }
+
+    pub fn set_file_status(&mut self, path: &str, status: &str) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let changed = tx.execute(
+            "UPDATE files SET status = ?2 WHERE path = ?1 AND status != ?2",
+            params![path, status],
+        )?;
+        if changed == 0 {
+            return Ok(());
+        }
+        let event = StorageEvent::new(EventKind::VerificationChanged, path, None, serde_json::json!({ "status": status }));
+        hooks::queue_event(&tx, &self.hooks, &event)?;
+        tx.commit()?;
+        hooks::run_commands(&self.hooks, &event);
+        Ok(())
+    }
+}
+
+pub(super) fn row_to_file(row: &rusqlite::Row) -> rusqlite::Result<FileRecord> {
+    Ok(FileRecord {
+        path: row.get(0)?,
+        sha256: row.get(1)?,
+        size_bytes: row.get::<_, i64>(2)? as u64,
+        modified_at: row.get(3)?,
+        page_count: row.get::<_, Option<i64>>(4)?.map(|p| p as usize),
+        pdf_version: row.get(5)?,
+        text_coverage: row.get(6)?,
+        image_coverage: row.get(7)?,
+        status: row.get(8)?,
+    })
+}
@@ -0,0 +1,88 @@
+// Form field values read from fillable PDFs - one row per field, queryable as key/value pairs
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+use crate::pdf_extraction::forms::FormField;
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS form_fields (
+            id INTEGER PRIMARY KEY,
+            path TEXT NOT NULL,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            value TEXT,
+            options TEXT NOT NULL,
+            page INTEGER,
+            rect TEXT,
+            read_only INTEGER NOT NULL,
+            required INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_form_fields_path ON form_fields(path)", [])?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Replace everything stored for a PDF with a fresh read of its form
+    pub fn replace_form_fields(&mut self, path: &str, fields: &[FormField]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM form_fields WHERE path = ?1", params![path])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO form_fields (path, name, kind, value, options, page, rect, read_only, required)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for f in fields {
+                insert.execute(params![
+                    path,
+                    f.name,
+                    f.kind,
+                    f.value,
+                    serde_json::to_string(&f.options)?,
+                    f.page.map(|p| p as i64),
+                    f.rect.map(|r| serde_json::to_string(&r)).transpose()?,
+                    f.read_only,
+                    f.required,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stored field names and values for a PDF, in form order
+    pub fn form_values(&self, path: &str) -> Result<Vec<(String, Option<String>)>> {
+        let mut stmt = self.conn.prepare("SELECT name, value FROM form_fields WHERE path = ?1 ORDER BY id")?;
+        let values = stmt
+            .query_map(params![path], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_and_read_values() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        let field = |name: &str, value: Option<&str>| FormField {
+            name: name.to_string(),
+            kind: "text".to_string(),
+            value: value.map(str::to_string),
+            options: Vec::new(),
+            page: Some(1),
+            rect: Some([10.0, 20.0, 30.0, 40.0]),
+            read_only: false,
+            required: false,
+        };
+        storage.replace_form_fields("a.pdf", &[field("name", Some("Ada")), field("city", None)]).unwrap();
+        storage.replace_form_fields("a.pdf", &[field("name", Some("Grace"))]).unwrap();
+        assert_eq!(storage.form_values("a.pdf").unwrap(), vec![("name".to_string(), Some("Grace".to_string()))]);
+        assert!(storage.form_values("b.pdf").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+// Per-page text grid sizes - the page's displayed size and the grid chosen for it
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+use crate::coords::GridSize;
+
+/// A page's size in points (rotation applied) and its grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageGrid {
+    pub page_width: f32,
+    pub page_height: f32,
+    pub grid: GridSize,
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS page_grids (
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            page_width REAL NOT NULL,
+            page_height REAL NOT NULL,
+            cols INTEGER NOT NULL,
+            rows INTEGER NOT NULL,
+            PRIMARY KEY (path, page)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Record the grid used for a page (1-based), replacing any earlier one
+    pub fn store_page_grid(&self, path: &str, page: usize, grid: &PageGrid) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO page_grids (path, page, page_width, page_height, cols, rows)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![path, page as i64, grid.page_width, grid.page_height, grid.grid.cols as i64, grid.grid.rows as i64],
+        )?;
+        Ok(())
+    }
+
+    pub fn page_grid(&self, path: &str, page: usize) -> Result<Option<PageGrid>> {
+        let mut stmt = self.conn.prepare("SELECT page_width, page_height, cols, rows FROM page_grids WHERE path = ?1 AND page = ?2")?;
+        let mut rows = stmt.query_map(params![path, page as i64], |row| {
+            Ok(PageGrid {
+                page_width: row.get::<_, f64>(0)? as f32,
+                page_height: row.get::<_, f64>(1)? as f32,
+                grid: GridSize::new(row.get::<_, i64>(2)? as usize, row.get::<_, i64>(3)? as usize),
+            })
+        })?;
+        Ok(rows.next().transpose()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_grid_replaced_per_page() {
+        let storage = DuckDBStorage::new(None).unwrap();
+        let landscape = PageGrid { page_width: 792.0, page_height: 612.0, grid: GridSize::new(259, 100) };
+        storage.store_page_grid("a.pdf", 2, &PageGrid { grid: GridSize::new(200, 100), ..landscape }).unwrap();
+        storage.store_page_grid("a.pdf", 2, &landscape).unwrap();
+        assert_eq!(storage.page_grid("a.pdf", 2).unwrap(), Some(landscape));
+        assert_eq!(storage.page_grid("a.pdf", 1).unwrap(), None);
+    }
+}
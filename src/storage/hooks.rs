@@ -0,0 +1,294 @@
+// Storage event hooks - push mutations to downstream systems instead of polling
+//
+// Configured under [[hooks]] in the extraction config:
+//
+//   [[hooks]]
+//   events = ["document_added"]          # empty or missing = every event
+//   command = "scripts/reindex.sh"       # event JSON on stdin, CHONKER_* env vars
+//   webhook = "http://search:9200/hook"  # event JSON POSTed
+//   outbox = true                        # appended to the outbox table
+//
+// The outbox is the reliable option: its rows are written in the transaction that
+// makes the change, so an event exists exactly when its change does, and consumers
+// read everything past the last id they processed. Webhooks go through the same
+// kind of table - webhook_outbox rows are queued in that transaction and POSTed by
+// spawn_webhook_worker, which retries with backoff and never holds up a mutation.
+// ingest, watch, daemon and serve run the worker; deliveries queued by other
+// commands wait for the next of those. Commands are started after the commit and
+// left to run, and a failing one is only logged.
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::DuckDBStorage;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the webhook worker sleeps when nothing is due
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Deliveries tried per batch
+const BATCH: usize = 20;
+/// A delivery failing this many times stays in webhook_outbox and is not retried
+const MAX_WEBHOOK_ATTEMPTS: i64 = 6;
+/// First retry delay; doubles with every failed attempt
+const RETRY_BASE_SECS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A document's extracted text was stored (new or replaced)
+    DocumentAdded,
+    /// A page's OCR/native convergence score was stored
+    PageUpdated,
+    /// A file's verification status changed (extracted, failed, needs review, ...)
+    VerificationChanged,
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::DocumentAdded => "document_added",
+            EventKind::PageUpdated => "page_updated",
+            EventKind::VerificationChanged => "verification_changed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HookConfig {
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    pub command: Option<String>,
+    pub webhook: Option<String>,
+    #[serde(default)]
+    pub outbox: bool,
+}
+
+impl HookConfig {
+    fn wants(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageEvent {
+    pub event: EventKind,
+    pub path: String,
+    /// 1-based, for page events
+    pub page: Option<usize>,
+    pub data: serde_json::Value,
+    pub at: String,
+}
+
+impl StorageEvent {
+    pub fn new(event: EventKind, path: &str, page: Option<usize>, data: serde_json::Value) -> Self {
+        Self { event, path: path.to_string(), page, data, at: chrono::Utc::now().to_rfc3339() }
+    }
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outbox (
+            id INTEGER PRIMARY KEY,
+            event TEXT NOT NULL,
+            path TEXT NOT NULL,
+            page INTEGER,
+            payload TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    // next_attempt_at is unix seconds; 0 means as soon as the worker gets to it
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS webhook_outbox (
+            id INTEGER PRIMARY KEY,
+            url TEXT NOT NULL,
+            path TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Write the outbox and webhook rows for an event on `conn`, the transaction
+/// making the change, so they commit or roll back together with it
+pub(super) fn queue_event(conn: &Connection, hooks: &[HookConfig], event: &StorageEvent) -> Result<()> {
+    let mut subscribed = hooks.iter().filter(|h| h.wants(event.event)).peekable();
+    if subscribed.peek().is_none() {
+        return Ok(());
+    }
+    let payload = serde_json::to_string(event)?;
+    for hook in subscribed {
+        if hook.outbox {
+            conn.execute(
+                "INSERT INTO outbox (event, path, page, payload) VALUES (?1, ?2, ?3, ?4)",
+                params![event.event.name(), event.path, event.page.map(|p| p as i64), payload],
+            )?;
+        }
+        if let Some(url) = &hook.webhook {
+            conn.execute(
+                "INSERT INTO webhook_outbox (url, path, payload) VALUES (?1, ?2, ?3)",
+                params![url, event.path, payload],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Start the command hooks for an event whose change has been committed
+pub(super) fn run_commands(hooks: &[HookConfig], event: &StorageEvent) {
+    for command in hooks.iter().filter(|h| h.wants(event.event)).filter_map(|h| h.command.as_deref()) {
+        let result = serde_json::to_string(event).map_err(anyhow::Error::from).and_then(|payload| run_command(command, event, &payload));
+        if let Err(e) = result {
+            tracing::warn!("[HOOKS] Command '{}' failed for {}: {}", command, event.path, e);
+        }
+    }
+}
+
+/// Deliver queued webhooks in the background for as long as the process runs
+pub fn spawn_webhook_worker<F>(open: F) -> JoinHandle<()>
+where
+    F: FnOnce() -> Result<DuckDBStorage> + Send + 'static,
+{
+    thread::spawn(move || {
+        let storage = match open() {
+            Ok(storage) => storage,
+            Err(e) => {
+                tracing::warn!("[HOOKS] Webhook worker not started: {}", e);
+                return;
+            }
+        };
+        loop {
+            match storage.deliver_webhooks(BATCH) {
+                Ok(0) => thread::sleep(POLL_INTERVAL),
+                Ok(tried) => tracing::debug!("[HOOKS] Tried {} webhook deliveries", tried),
+                Err(e) => {
+                    tracing::warn!("[HOOKS] Webhook delivery failed: {}", e);
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+    })
+}
+
+impl DuckDBStorage {
+    /// Replace the configured hooks; call again after a config reload
+    pub fn set_hooks(&mut self, hooks: Vec<HookConfig>) {
+        self.hooks = hooks;
+    }
+
+    /// POST up to `limit` due webhook deliveries, oldest first. A delivered row is
+    /// removed; a failed one is retried later with a doubling delay. Returns how
+    /// many were tried.
+    pub fn deliver_webhooks(&self, limit: usize) -> Result<usize> {
+        let now = chrono::Utc::now().timestamp();
+        let due: Vec<(i64, String, String, String, i64)> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, url, path, payload, attempts FROM webhook_outbox
+                 WHERE attempts < ?1 AND next_attempt_at <= ?2 ORDER BY id LIMIT ?3",
+            )?;
+            let rows = stmt.query_map(params![MAX_WEBHOOK_ATTEMPTS, now, limit as i64], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        for (id, url, path, payload, attempts) in &due {
+            match post_webhook(url, payload) {
+                Ok(()) => {
+                    self.conn.execute("DELETE FROM webhook_outbox WHERE id = ?1", params![id])?;
+                }
+                Err(e) => {
+                    let attempts = attempts + 1;
+                    let retry_at = now + (RETRY_BASE_SECS << (attempts - 1).min(16));
+                    self.conn.execute(
+                        "UPDATE webhook_outbox SET attempts = ?2, next_attempt_at = ?3, last_error = ?4 WHERE id = ?1",
+                        params![id, attempts, retry_at, e.to_string()],
+                    )?;
+                    if attempts >= MAX_WEBHOOK_ATTEMPTS {
+                        tracing::warn!("[HOOKS] Giving up on webhook {} for {} after {} attempts: {}", url, path, attempts, e);
+                    } else {
+                        tracing::warn!("[HOOKS] Webhook {} failed for {}, retrying in {}s: {}", url, path, retry_at - now, e);
+                    }
+                }
+            }
+        }
+        Ok(due.len())
+    }
+}
+
+/// Start the command through the shell and hand it the event; it is not waited on
+/// beyond a reaper thread, so a slow consumer can't stall extraction
+fn run_command(command: &str, event: &StorageEvent, payload: &str) -> Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .env("CHONKER_EVENT", event.event.name())
+        .env("CHONKER_PATH", &event.path)
+        .env("CHONKER_PAGE", event.page.map(|p| p.to_string()).unwrap_or_default())
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores stdin closes the pipe early; that's its choice
+        let _ = stdin.write_all(payload.as_bytes());
+    }
+    std::thread::spawn(move || {
+        if let Ok(status) = child.wait() {
+            if !status.success() {
+                tracing::warn!("[HOOKS] Hook command exited with {}", status);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn post_webhook(url: &str, payload: &str) -> Result<()> {
+    let agent = ureq::AgentBuilder::new().timeout(WEBHOOK_TIMEOUT).build();
+    agent.post(url).set("Content-Type", "application/json").send_string(payload)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_outbox_receives_subscribed_events_only() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.set_hooks(vec![HookConfig { events: vec![EventKind::DocumentAdded], outbox: true, ..Default::default() }]);
+
+        storage.store_document("a.pdf", "text", None).unwrap();
+        storage.set_file_status("a.pdf", "extracted").unwrap();
+
+        let rows = storage.run_sql("SELECT event, path, payload FROM outbox", &HashMap::new()).unwrap().rows;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][0], rusqlite::types::Value::Text("document_added".to_string()));
+        let rusqlite::types::Value::Text(payload) = &rows[0][2] else { panic!("payload is not text") };
+        let payload: serde_json::Value = serde_json::from_str(payload).unwrap();
+        assert_eq!(payload["data"]["chars"], 4);
+    }
+
+    #[test]
+    fn test_webhooks_queued_with_the_change_and_retried_later() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        // Nothing listens on port 1, so the delivery fails straight away
+        storage.set_hooks(vec![HookConfig { webhook: Some("http://127.0.0.1:1/hook".to_string()), ..Default::default() }]);
+        storage.store_document("a.pdf", "text", None).unwrap();
+
+        let queued = |storage: &DuckDBStorage| {
+            storage.run_sql("SELECT attempts FROM webhook_outbox", &HashMap::new()).unwrap().rows
+        };
+        assert_eq!(queued(&storage), vec![vec![rusqlite::types::Value::Integer(0)]]);
+        assert_eq!(storage.deliver_webhooks(10).unwrap(), 1);
+        assert_eq!(queued(&storage), vec![vec![rusqlite::types::Value::Integer(1)]]);
+        // Not due again until the backoff has passed
+        assert_eq!(storage.deliver_webhooks(10).unwrap(), 0);
+    }
+}
@@ -0,0 +1,174 @@
+// Detected language per page and per block, refreshed whenever a document is stored
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+use crate::pdf_extraction::language::{detect_page_languages, segment_blocks, PageLanguage, TextBlock};
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS page_languages (
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            lang TEXT NOT NULL,
+            confidence REAL NOT NULL,
+            chars INTEGER NOT NULL,
+            PRIMARY KEY (path, page)
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_page_languages_lang ON page_languages(lang)", [])?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS block_languages (
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            block INTEGER NOT NULL,
+            first_line INTEGER NOT NULL,
+            lines INTEGER NOT NULL,
+            script TEXT,
+            lang TEXT,
+            confidence REAL NOT NULL,
+            chars INTEGER NOT NULL,
+            PRIMARY KEY (path, page, block)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// How much of the database is in one language
+#[derive(Debug, Clone)]
+pub struct LanguageCount {
+    pub lang: String,
+    pub documents: usize,
+    pub pages: usize,
+}
+
+impl DuckDBStorage {
+    /// Detect and record the language of each page, and each block of each page, of a document's content
+    pub(super) fn record_page_languages(&mut self, path: &str, content: &str) -> Result<()> {
+        let pages = detect_page_languages(content);
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM page_languages WHERE path = ?1", params![path])?;
+        tx.execute("DELETE FROM block_languages WHERE path = ?1", params![path])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO page_languages (path, page, lang, confidence, chars) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for page in &pages {
+                insert.execute(params![path, page.page as i64, page.lang, page.confidence, page.chars as i64])?;
+            }
+            let mut insert = tx.prepare(
+                "INSERT INTO block_languages (path, page, block, first_line, lines, script, lang, confidence, chars)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for (index, text) in content.split('\x0c').enumerate() {
+                for block in segment_blocks(text) {
+                    insert.execute(params![
+                        path,
+                        index as i64 + 1,
+                        block.index as i64,
+                        block.first_line as i64,
+                        block.lines as i64,
+                        block.script,
+                        block.lang,
+                        block.confidence,
+                        block.chars as i64
+                    ])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// A document's detected pages (1-based), in page order
+    pub fn page_languages(&self, path: &str) -> Result<Vec<PageLanguage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT page, lang, confidence, chars FROM page_languages WHERE path = ?1 ORDER BY page",
+        )?;
+        let pages = stmt
+            .query_map(params![path], |row| {
+                Ok(PageLanguage {
+                    page: row.get::<_, i64>(0)? as usize,
+                    lang: row.get(1)?,
+                    confidence: row.get(2)?,
+                    chars: row.get::<_, i64>(3)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(pages)
+    }
+
+    /// Blocks of one page (1-based) with their script and language, in page order
+    pub fn block_languages(&self, path: &str, page: usize) -> Result<Vec<TextBlock>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT block, first_line, lines, script, lang, confidence, chars FROM block_languages
+             WHERE path = ?1 AND page = ?2 ORDER BY block",
+        )?;
+        let blocks = stmt
+            .query_map(params![path, page as i64], |row| {
+                Ok(TextBlock {
+                    index: row.get::<_, i64>(0)? as usize,
+                    first_line: row.get::<_, i64>(1)? as usize,
+                    lines: row.get::<_, i64>(2)? as usize,
+                    script: row.get(3)?,
+                    lang: row.get(4)?,
+                    confidence: row.get(5)?,
+                    chars: row.get::<_, i64>(6)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(blocks)
+    }
+
+    /// Every detected language, most pages first
+    pub fn language_counts(&self) -> Result<Vec<LanguageCount>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT lang, COUNT(DISTINCT path), COUNT(*) FROM page_languages GROUP BY lang ORDER BY COUNT(*) DESC, lang",
+        )?;
+        let counts = stmt
+            .query_map([], |row| {
+                Ok(LanguageCount {
+                    lang: row.get(0)?,
+                    documents: row.get::<_, i64>(1)? as usize,
+                    pages: row.get::<_, i64>(2)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{ListQuery, PageRequest};
+
+    #[test]
+    fn test_languages_follow_stored_documents() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        let english = "The invoice total is due at the end of the month, payable by bank transfer.";
+        let french = "La facture est payable à la fin du mois par virement bancaire sur notre compte.";
+        storage.store_document("en.pdf", &format!("{}\x0c{}", english, english), None).unwrap();
+        storage.store_document("mixed.pdf", &format!("{}\x0c{} {}", english, french, french), None).unwrap();
+
+        let pages = storage.page_languages("mixed.pdf").unwrap();
+        assert_eq!(pages.iter().map(|p| p.lang.as_str()).collect::<Vec<_>>(), vec!["eng", "fra"]);
+
+        let found = storage.search_page("facture", Some("fra"), &PageRequest::first(10)).unwrap();
+        assert_eq!(found.items.len(), 1);
+        assert!(storage.search_page("invoice", Some("fra"), &PageRequest::first(10)).unwrap().items.iter().all(|r| r.path == "mixed.pdf"));
+
+        let listed = storage.list_documents(&ListQuery { filter: Some("language=fra".parse().unwrap()), sort: None }, &PageRequest::first(10)).unwrap();
+        assert_eq!(listed.items.iter().map(|d| d.text("path")).collect::<Vec<_>>(), vec!["mixed.pdf"]);
+
+        let counts = storage.language_counts().unwrap();
+        assert_eq!((counts[0].lang.as_str(), counts[0].documents, counts[0].pages), ("eng", 2, 3));
+
+        // One page, two languages
+        storage.store_document("lease.pdf", &format!("{}\n\n{}", english, french), None).unwrap();
+        let blocks = storage.block_languages("lease.pdf", 1).unwrap();
+        assert_eq!(blocks.iter().map(|b| (b.first_line, b.lang.as_deref())).collect::<Vec<_>>(), vec![(0, Some("eng")), (2, Some("fra"))]);
+    }
+}
@@ -0,0 +1,323 @@
+// Document metadata - typed core fields plus custom fields declared in config
+//
+// The metadata column is a JSON object. Next to what ingest records about a run
+// (engine, pages, quality, ...) it carries five core fields with fixed types -
+// title, author, doc_date, classification, language - and a "fields" object for
+// custom fields a deployment declares:
+//
+//     [metadata.fields.client]
+//     type = "text"
+//     [metadata.fields.amount]
+//     type = "number"
+//     help = "invoice total"
+//
+// Every write is checked: core fields must fit their types, custom fields must
+// be declared and fit theirs. Values are stored normalized (dates YYYY-MM-DD,
+// languages ISO 639-3, numbers as numbers) so list filters compare like with
+// like, and custom fields are filterable and sortable by name.
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use super::query;
+use super::DuckDBStorage;
+use crate::pdf_extraction::language::language_code;
+
+/// Key of the custom fields object inside the metadata JSON
+const CUSTOM_KEY: &str = "fields";
+
+static SCHEMA: OnceLock<MetadataConfig> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Text,
+    Number,
+    /// YYYY-MM-DD
+    Date,
+    Bool,
+}
+
+impl FieldType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldType::Text => "text",
+            FieldType::Number => "number",
+            FieldType::Date => "date",
+            FieldType::Bool => "bool",
+        }
+    }
+
+    /// The value as it is stored, or why it doesn't fit. Strings are accepted
+    /// for every type, so values typed on the command line convert too.
+    pub fn coerce(&self, name: &str, value: &Value) -> Result<Value> {
+        let coerced = match (self, value) {
+            (FieldType::Text, Value::String(_)) | (FieldType::Number, Value::Number(_)) | (FieldType::Bool, Value::Bool(_)) => {
+                Some(value.clone())
+            }
+            (FieldType::Number, Value::String(s)) => {
+                let s = s.trim();
+                s.parse::<i64>().ok().map(Value::from).or_else(|| s.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(Value::Number))
+            }
+            (FieldType::Date, Value::String(s)) => {
+                NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok().map(|date| Value::String(date.format("%Y-%m-%d").to_string()))
+            }
+            (FieldType::Bool, Value::String(s)) => match s.trim().to_lowercase().as_str() {
+                "true" | "yes" => Some(Value::Bool(true)),
+                "false" | "no" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            _ => None,
+        };
+        coerced.ok_or_else(|| anyhow!("Metadata field '{}' expects a {}, got {}", name, self.name(), value))
+    }
+}
+
+/// A field declared under [metadata.fields]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CustomField {
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+    /// Shown in `list --columns` help
+    #[serde(default)]
+    pub help: String,
+}
+
+/// The [metadata] table of extraction.toml
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MetadataConfig {
+    #[serde(default)]
+    pub fields: BTreeMap<String, CustomField>,
+}
+
+/// Fields every document may have, with the types they are held to
+pub const CORE_FIELDS: &[(&str, FieldType)] = &[
+    ("title", FieldType::Text),
+    ("author", FieldType::Text),
+    ("doc_date", FieldType::Date),
+    ("classification", FieldType::Text),
+    ("language", FieldType::Text),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct CoreMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// The date the document carries, YYYY-MM-DD
+    pub doc_date: Option<String>,
+    /// Document class, e.g. invoice or contract
+    pub classification: Option<String>,
+    /// ISO 639-3; overrides the detected language in listings
+    pub language: Option<String>,
+}
+
+/// The typed part of a document's metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentMetadata {
+    pub core: CoreMetadata,
+    /// Custom fields, by name
+    pub fields: BTreeMap<String, Value>,
+}
+
+/// Use these custom fields for the rest of the process; the first call wins.
+/// Call it before parsing list filters so they can name the fields.
+pub fn set_metadata_schema(config: &MetadataConfig) -> Result<()> {
+    for name in config.fields.keys() {
+        let well_formed = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !well_formed {
+            bail!("Metadata field '{}' must be lowercase letters, digits and _", name);
+        }
+        if CORE_FIELDS.iter().any(|(core, _)| core == name) || query::FIELDS.iter().any(|f| f.name == name) {
+            bail!("Metadata field '{}' is built in and can't be redeclared", name);
+        }
+    }
+    if SCHEMA.set(config.clone()).is_ok() {
+        query::register_custom_fields(&config.fields);
+    }
+    Ok(())
+}
+
+fn schema() -> &'static BTreeMap<String, CustomField> {
+    static EMPTY: BTreeMap<String, CustomField> = BTreeMap::new();
+    SCHEMA.get().map_or(&EMPTY, |config| &config.fields)
+}
+
+/// Check one field and return its stored value; `name` is a core or custom field
+fn coerce_field(name: &str, value: &Value) -> Result<Value> {
+    if let Some((_, kind)) = CORE_FIELDS.iter().find(|(core, _)| *core == name) {
+        let value = kind.coerce(name, value)?;
+        if name == "language" {
+            let typed = value.as_str().unwrap_or_default();
+            let code = language_code(typed).ok_or_else(|| anyhow!("Unknown language '{}'", typed))?;
+            return Ok(Value::String(code.to_string()));
+        }
+        return Ok(value);
+    }
+    let declared = schema()
+        .get(name)
+        .ok_or_else(|| anyhow!("Unknown metadata field '{}' - declare it under [metadata.fields] in extraction.toml", name))?;
+    declared.kind.coerce(name, value)
+}
+
+/// Validate and normalize every typed field of a metadata object in place
+fn validate(object: &mut Map<String, Value>) -> Result<()> {
+    for (name, _) in CORE_FIELDS {
+        if let Some(value) = object.get(*name).filter(|value| !value.is_null()) {
+            let value = coerce_field(name, value)?;
+            object.insert(name.to_string(), value);
+        }
+    }
+    if let Some(custom) = object.get_mut(CUSTOM_KEY) {
+        let custom = custom.as_object_mut().ok_or_else(|| anyhow!("Metadata '{}' must be an object of custom fields", CUSTOM_KEY))?;
+        custom.retain(|_, value| !value.is_null());
+        for (name, value) in custom.iter_mut() {
+            if CORE_FIELDS.iter().any(|(core, _)| core == name) {
+                bail!("'{}' is a core metadata field, not a custom one", name);
+            }
+            *value = coerce_field(name, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_object(metadata: &str) -> Result<Map<String, Value>> {
+    match serde_json::from_str(metadata) {
+        Ok(Value::Object(object)) => Ok(object),
+        _ => bail!("Metadata must be a JSON object"),
+    }
+}
+
+fn stored_object(conn: &Connection, path: &str) -> Result<Option<Map<String, Value>>> {
+    let metadata: Option<Option<String>> =
+        conn.query_row("SELECT metadata FROM documents WHERE path = ?1", params![path], |row| row.get(0)).optional()?;
+    Ok(metadata.flatten().and_then(|metadata| parse_object(&metadata).ok()))
+}
+
+/// The metadata store_document writes: checked against the schema, with core and
+/// custom fields of the row it replaces carried over where the new one has none,
+/// so re-extracting a document doesn't drop what people set on it
+pub(super) fn prepare(conn: &Connection, path: &str, metadata: Option<&str>) -> Result<Option<String>> {
+    let mut object = match metadata {
+        Some(metadata) => parse_object(metadata)?,
+        None => Map::new(),
+    };
+    validate(&mut object)?;
+    if let Some(previous) = stored_object(conn, path)? {
+        for (name, _) in CORE_FIELDS {
+            if let Some(value) = previous.get(*name).filter(|value| !value.is_null()) {
+                object.entry(name.to_string()).or_insert_with(|| value.clone());
+            }
+        }
+        if let Some(Value::Object(kept)) = previous.get(CUSTOM_KEY) {
+            if let Value::Object(custom) = object.entry(CUSTOM_KEY).or_insert_with(|| Value::Object(Map::new())) {
+                for (name, value) in kept {
+                    custom.entry(name.clone()).or_insert_with(|| value.clone());
+                }
+            }
+        }
+    }
+    Ok(match metadata {
+        None if object.is_empty() => None,
+        _ => Some(Value::Object(object).to_string()),
+    })
+}
+
+fn typed(object: &Map<String, Value>) -> DocumentMetadata {
+    let text = |name: &str| object.get(name).and_then(Value::as_str).map(str::to_string);
+    DocumentMetadata {
+        core: CoreMetadata {
+            title: text("title"),
+            author: text("author"),
+            doc_date: text("doc_date"),
+            classification: text("classification"),
+            language: text("language"),
+        },
+        fields: match object.get(CUSTOM_KEY) {
+            Some(Value::Object(custom)) => custom.iter().map(|(name, value)| (name.clone(), value.clone())).collect(),
+            _ => BTreeMap::new(),
+        },
+    }
+}
+
+impl DuckDBStorage {
+    /// Core and custom fields of a stored document; None if it isn't stored
+    pub fn document_metadata(&self, path: &str) -> Result<Option<DocumentMetadata>> {
+        let exists: bool = self.conn.query_row("SELECT COUNT(*) > 0 FROM documents WHERE path = ?1", params![path], |row| row.get(0))?;
+        if !exists {
+            return Ok(None);
+        }
+        Ok(Some(stored_object(&self.conn, path)?.map(|object| typed(&object)).unwrap_or_default()))
+    }
+
+    /// Set fields from typed-in values and clear others, core or custom by
+    /// name. Nothing is written unless every value fits.
+    pub fn set_metadata_fields(&mut self, path: &str, set: &[(String, String)], unset: &[String]) -> Result<DocumentMetadata> {
+        if self.document_metadata(path)?.is_none() {
+            bail!("{} isn't stored - extract it first", path);
+        }
+        let mut object = stored_object(&self.conn, path)?.unwrap_or_default();
+        let is_core = |name: &str| CORE_FIELDS.iter().any(|(core, _)| *core == name);
+        for name in unset {
+            if is_core(name) {
+                object.remove(name);
+            } else if let Some(Value::Object(custom)) = object.get_mut(CUSTOM_KEY) {
+                custom.remove(name);
+            }
+        }
+        for (name, raw) in set {
+            let value = coerce_field(name, &Value::String(raw.clone()))?;
+            if is_core(name) {
+                object.insert(name.clone(), value);
+            } else if let Value::Object(custom) = object.entry(CUSTOM_KEY).or_insert_with(|| Value::Object(Map::new())) {
+                custom.insert(name.clone(), value);
+            }
+        }
+        validate(&mut object)?;
+        self.conn.execute(
+            "UPDATE documents SET metadata = ?2 WHERE path = ?1",
+            params![path, Value::Object(object.clone()).to_string()],
+        )?;
+        Ok(typed(&object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::query::Filter;
+    use crate::storage::{ListQuery, PageRequest};
+
+    #[test]
+    fn test_fields_are_typed_on_write_and_filterable() {
+        let mut config = MetadataConfig::default();
+        config.fields.insert("amount".to_string(), CustomField { kind: FieldType::Number, help: String::new() });
+        set_metadata_schema(&config).unwrap();
+
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("a.pdf", "Invoice", Some(r#"{"pages": 1, "language": "German"}"#)).unwrap();
+        storage.store_document("b.pdf", "Invoice", Some(r#"{"fields": {"amount": "90"}}"#)).unwrap();
+        assert!(storage.store_document("c.pdf", "x", Some(r#"{"doc_date": "March 3rd"}"#)).is_err());
+        assert!(storage.store_document("c.pdf", "x", Some(r#"{"fields": {"client": "ACME"}}"#)).is_err());
+        assert!(storage.store_document("c.pdf", "x", Some("free text")).is_err());
+
+        let meta = storage
+            .set_metadata_fields("a.pdf", &[("amount".to_string(), "1200".to_string()), ("doc_date".to_string(), "2024-03-01".to_string())], &[])
+            .unwrap();
+        assert_eq!(meta.core.language.as_deref(), Some("deu"));
+        assert_eq!(meta.fields["amount"], Value::from(1200));
+        assert!(storage.set_metadata_fields("a.pdf", &[("amount".to_string(), "lots".to_string())], &[]).is_err());
+
+        // Re-extracting keeps what was set
+        storage.store_document("a.pdf", "Invoice v2", Some(r#"{"pages": 2}"#)).unwrap();
+        assert_eq!(storage.document_metadata("a.pdf").unwrap().unwrap().core.doc_date.as_deref(), Some("2024-03-01"));
+
+        let query = ListQuery { filter: Some("amount>1000 AND doc_date>=2024-01-01".parse::<Filter>().unwrap()), sort: None };
+        let listed = storage.list_documents(&query, &PageRequest::first(10)).unwrap();
+        assert_eq!(listed.items.iter().map(|doc| doc.text("path")).collect::<Vec<_>>(), vec!["a.pdf"]);
+    }
+}
@@ -0,0 +1,163 @@
+// Schema migrations - numbered upgrades recorded in schema_version
+//
+// Tables are created in their current shape, so a new database is stamped with
+// the latest version and nothing runs; these bring databases written by older
+// releases up to date. They run in order when a database is opened, before any
+// table is created, each in its own transaction with its version row, so an
+// interrupted upgrade leaves the database at the last version that finished.
+// Each one checks before it changes anything, because databases from before
+// schema_version existed (version 0) may already have some of them, or lack the
+// table altogether. A database from a newer chonker8 than this one is refused
+// rather than written to.
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+/// Every migration, oldest first; append only, never renumber
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "documents.search_text for text with edits applied", up: add_search_text },
+    Migration { version: 2, name: "edit_events.branch, existing edits on main", up: add_edit_branch },
+    Migration { version: 3, name: "entities.text for the words an entity was read from", up: add_entity_text },
+];
+
+fn has_table(conn: &Connection, table: &str) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )?)
+}
+
+/// Add a column to a table that exists without it; a missing table is created whole later
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let present: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get(0),
+    )?;
+    if has_table(conn, table)? && !present {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+fn add_search_text(conn: &Connection) -> Result<()> {
+    add_column(conn, "documents", "search_text", "TEXT")
+}
+
+fn add_edit_branch(conn: &Connection) -> Result<()> {
+    add_column(conn, "edit_events", "branch", "TEXT NOT NULL DEFAULT 'main'")
+}
+
+fn add_entity_text(conn: &Connection) -> Result<()> {
+    add_column(conn, "entities", "text", "TEXT")
+}
+
+/// The newest schema this build knows
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+fn current_version(conn: &Connection) -> Result<i64> {
+    if !has_table(conn, "schema_version")? {
+        return Ok(0);
+    }
+    Ok(conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))?)
+}
+
+fn check_not_newer(version: i64) -> Result<()> {
+    if version > latest_version() {
+        bail!(
+            "Database schema is version {}, but this chonker8 only knows up to {} - upgrade chonker8 rather than risk the database",
+            version,
+            latest_version()
+        );
+    }
+    Ok(())
+}
+
+/// Bring an open database up to date, before its tables are created. Returns
+/// the migrations applied.
+pub(super) fn run(conn: &mut Connection) -> Result<Vec<&'static Migration>> {
+    let fresh = !has_table(conn, "documents")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    let version = current_version(conn)?;
+    check_not_newer(version)?;
+    if fresh {
+        for migration in MIGRATIONS.iter().filter(|migration| migration.version > version) {
+            conn.execute("INSERT INTO schema_version (version, name) VALUES (?1, ?2)", params![migration.version, migration.name])?;
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > version) {
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)?;
+        tx.execute("INSERT INTO schema_version (version, name) VALUES (?1, ?2)", params![migration.version, migration.name])?;
+        tx.commit()?;
+        tracing::info!("[DB] Migrated to schema version {}: {}", migration.version, migration.name);
+        applied.push(migration);
+    }
+    Ok(applied)
+}
+
+/// The database's schema version and what opening it would apply, without
+/// changing anything. A database that doesn't exist yet will be created current.
+pub fn pending_migrations(db: &Path) -> Result<(i64, Vec<&'static Migration>)> {
+    if !db.exists() {
+        return Ok((latest_version(), Vec::new()));
+    }
+    let conn = Connection::open_with_flags(db, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let version = current_version(&conn)?;
+    check_not_newer(version)?;
+    if !has_table(&conn, "documents")? {
+        return Ok((latest_version(), Vec::new()));
+    }
+    Ok((version, MIGRATIONS.iter().filter(|migration| migration.version > version).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::DuckDBStorage;
+
+    #[test]
+    fn test_old_database_upgraded_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("old.db");
+        {
+            // As a release from before search_text and branches left it
+            let conn = Connection::open(&db).unwrap();
+            conn.execute("CREATE TABLE documents (id INTEGER PRIMARY KEY, path TEXT UNIQUE NOT NULL, content TEXT NOT NULL, metadata TEXT, created_at DATETIME DEFAULT CURRENT_TIMESTAMP)", []).unwrap();
+            conn.execute("CREATE TABLE edit_events (seq INTEGER PRIMARY KEY, path TEXT NOT NULL, page INTEGER NOT NULL, source TEXT NOT NULL, event TEXT NOT NULL, created_at DATETIME DEFAULT CURRENT_TIMESTAMP)", []).unwrap();
+            conn.execute("INSERT INTO edit_events (path, page, source, event) VALUES ('a.pdf', 1, 'tui', '{}')", []).unwrap();
+        }
+        assert_eq!(pending_migrations(&db).unwrap().1.len(), MIGRATIONS.len());
+
+        let storage = DuckDBStorage::new(Some(&db)).unwrap();
+        let branch: String = storage.conn.query_row("SELECT branch FROM edit_events", [], |row| row.get(0)).unwrap();
+        assert_eq!(branch, "main");
+        drop(storage);
+        let (version, pending) = pending_migrations(&db).unwrap();
+        assert_eq!((version, pending.len()), (latest_version(), 0));
+
+        // Written by a newer chonker8
+        Connection::open(&db).unwrap().execute("INSERT INTO schema_version (version, name) VALUES (?1, 'future')", [latest_version() + 1]).unwrap();
+        assert!(pending_migrations(&db).is_err());
+        assert!(DuckDBStorage::new(Some(&db)).is_err());
+    }
+}
@@ -3,25 +3,95 @@ use anyhow::Result;
 use rusqlite::{params, Connection};
 use std::path::Path;
 
+use crate::embeddings::Embedder;
+use crate::pdf_extraction::language::language_name;
+use crate::search_index::{self, SearchIndexer};
+
+mod annotations;
+mod archive;
+mod backend;
+mod conflicts;
+mod convergence;
+mod documents;
+mod edits;
+mod embeddings;
+mod entities;
+mod extractions;
+mod federation;
+mod files;
+mod forms;
+mod grids;
+mod hooks;
+mod languages;
+mod metadata;
+mod migrations;
+pub mod query;
+mod recent;
+mod reindex;
+mod remote;
+mod renames;
+mod retention;
+mod runs;
+pub mod sql;
+mod tags;
+mod translations;
+mod usage;
+mod versions;
+pub mod views;
+mod words;
+
+pub use annotations::AnnotationHit;
+pub use archive::ArchiveCounts;
+pub use backend::{is_remote, open_store, DocumentStore};
+pub use conflicts::{ConflictRecord, EditConflict};
+pub use documents::{DocumentSummary, ListQuery, Page, PageRequest, StoredDocument};
+pub use edits::{EditOperation, LoggedEdit, PageEvents};
+pub use embeddings::SemanticHit;
+pub use entities::StoredEntity;
+pub use extractions::{PageExtraction, PageInfo};
+pub use federation::{Federation, FederationConfig, Sourced};
+pub use files::FileRecord;
+pub use grids::PageGrid;
+pub use hooks::{spawn_webhook_worker, EventKind, HookConfig, StorageEvent};
+pub use languages::LanguageCount;
+pub use metadata::{set_metadata_schema, CoreMetadata, CustomField, DocumentMetadata, FieldType, MetadataConfig, CORE_FIELDS};
+pub use migrations::{latest_version as latest_schema_version, pending_migrations, Migration};
+pub use reindex::spawn_index_worker;
+pub use remote::RemoteStorage;
+pub use retention::{RetentionAudit, RetentionCandidate, RetentionConfig};
+pub use runs::RunRecord;
+pub use usage::{DocumentUsage, StorageForecast};
+pub use versions::{Branch, Snapshot, MAIN_BRANCH};
+
+/// Database the CLI and the TUI use unless told otherwise
+pub const DEFAULT_DB_PATH: &str = "chonker8.db";
+
 #[derive(Debug)]
 pub struct DuckDBStorage {
     conn: Connection,
+    hooks: Vec<HookConfig>,
+    indexer: Option<SearchIndexer>,
+    embedder: Option<Embedder>,
 }
 
 #[derive(Debug)]
 pub struct SearchResult {
-    pub score: f64,
-    pub content: String,
+    pub id: i64,
+    pub score: f64, // occurrences of the query
+    pub snippet: String, // text around the first match
     pub path: String,
 }
 
 impl DuckDBStorage {
     pub fn new(path: Option<&Path>) -> Result<Self> {
-        let conn = match path {
+        let mut conn = match path {
             Some(p) => Connection::open(p)?,
             None => Connection::open_in_memory()?,
         };
         
+        // Upgrade what an older release left before anything is created
+        migrations::run(&mut conn)?;
+        
         // Create tables
         conn.execute(
             "CREATE TABLE IF NOT EXISTS documents (
@@ -29,7 +99,8 @@ impl DuckDBStorage {
                 path TEXT UNIQUE NOT NULL,
                 content TEXT NOT NULL,
                 metadata TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                search_text TEXT
             )",
             [],
         )?;
@@ -39,48 +110,82 @@ impl DuckDBStorage {
             [],
         )?;
         
-        Ok(DuckDBStorage { conn })
+        files::create_tables(&conn)?;
+        annotations::create_tables(&conn)?;
+        forms::create_tables(&conn)?;
+        grids::create_tables(&conn)?;
+        extractions::create_tables(&conn)?;
+        runs::create_tables(&conn)?;
+        convergence::create_tables(&conn)?;
+        entities::create_tables(&conn)?;
+        hooks::create_tables(&conn)?;
+        words::create_tables(&conn)?;
+        languages::create_tables(&conn)?;
+        edits::create_tables(&conn)?;
+        conflicts::create_tables(&conn)?;
+        versions::create_tables(&conn)?;
+        reindex::create_tables(&conn)?;
+        embeddings::create_tables(&conn)?;
+        translations::create_tables(&conn)?;
+        renames::create_tables(&conn)?;
+        tags::create_tables(&conn)?;
+        recent::create_tables(&conn)?;
+        retention::create_tables(&conn)?;
+        views::create_views(&conn)?;
+        
+        Ok(DuckDBStorage { conn, hooks: Vec::new(), indexer: None, embedder: None })
     }
     
     pub fn store_document(&mut self, path: &str, content: &str, metadata: Option<&str>) -> Result<()> {
-        self.conn.execute(
+        let metadata = metadata::prepare(&self.conn, path, metadata)?;
+        let metadata = metadata.as_deref();
+        let parsed = metadata.and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok());
+        let data = serde_json::json!({ "chars": content.chars().count(), "metadata": parsed });
+        let event = StorageEvent::new(EventKind::DocumentAdded, path, None, data);
+        let tx = self.conn.transaction()?;
+        tx.execute(
             "INSERT OR REPLACE INTO documents (path, content, metadata) VALUES (?1, ?2, ?3)",
             params![path, content, metadata],
         )?;
+        // The new extraction has no edits applied yet
+        reindex::enqueue_document(&tx, path)?;
+        hooks::queue_event(&tx, &self.hooks, &event)?;
+        tx.commit()?;
+        hooks::run_commands(&self.hooks, &event);
+        self.record_page_languages(path, content)?;
+        self.record_page_embeddings(path, content)?;
+
+        if let Some(indexer) = &self.indexer {
+            let docs = search_index::page_docs(path, content, metadata);
+            // The search index is a copy; a push failure must not lose the stored document
+            if let Err(e) = indexer.push(&docs) {
+                tracing::warn!("[INDEX] Push failed for {}: {}", path, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Push every stored document to this search index as it is stored
+    pub fn set_indexer(&mut self, indexer: Option<SearchIndexer>) {
+        self.indexer = indexer;
+    }
+    
+    /// Checkpoint pending writes to the main database file before shutdown
+    pub fn flush(&self) -> Result<()> {
+        self.conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
         Ok(())
     }
     
     pub fn search(&self, query: &str, limit: Option<usize>) -> Result<Vec<SearchResult>> {
-        let limit = limit.unwrap_or(10);
-        
-        // Simple LIKE search for now
-        let mut stmt = self.conn.prepare(
-            "SELECT path, content, 
-             LENGTH(content) - LENGTH(REPLACE(LOWER(content), LOWER(?1), '')) AS score
-             FROM documents 
-             WHERE content LIKE '%' || ?1 || '%'
-             ORDER BY score DESC
-             LIMIT ?2"
-        )?;
-        
-        let results = stmt.query_map(params![query, limit], |row| {
-            Ok(SearchResult {
-                path: row.get(0)?,
-                content: row.get(1)?,
-                score: row.get::<_, i64>(2)? as f64,
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
-        
-        Ok(results)
+        Ok(self.search_page(query, None, &PageRequest::first(limit.unwrap_or(10)))?.items)
     }
     
+    pub fn document_count(&self) -> Result<i64> {
+        Ok(self.conn.query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))?)
+    }
+
     pub fn get_stats(&self) -> Result<String> {
-        let count: i64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM documents",
-            [],
-            |row| row.get(0),
-        )?;
+        let count = self.document_count()?;
         
         let total_size: Option<i64> = self.conn.query_row(
             "SELECT SUM(LENGTH(content)) FROM documents",
@@ -88,10 +193,20 @@ impl DuckDBStorage {
             |row| row.get(0),
         ).unwrap_or(None);
         
-        Ok(format!(
-            "Documents: {}\nTotal size: {} bytes",
+        let mut stats = format!(
+            "Documents: {}\nTotal size: {} bytes\nPending index updates: {} pages",
             count,
-            total_size.unwrap_or(0)
-        ))
+            total_size.unwrap_or(0),
+            self.pending_index_count()?
+        );
+        let languages = self.language_counts()?;
+        if !languages.is_empty() {
+            stats.push_str("\nLanguages:");
+            for count in languages {
+                stats.push_str(&format!("\n  {} ({}): {} documents, {} pages",
+                    language_name(&count.lang), count.lang, count.documents, count.pages));
+            }
+        }
+        Ok(stats)
     }
 }
\ No newline at end of file
@@ -0,0 +1,379 @@
+// List query language - the fields `list` can show, sort and filter on
+//
+// Filters are `field op value` terms joined by AND / OR (AND binds tighter), e.g.
+// `pages>100 AND tag=contract`. Operators: = != > >= < <= and ~ (substring).
+// Values may be quoted: `path~"annual report"`. Everything compiles to SQL with
+// bound parameters; field names only ever come from the fixed table below or
+// from the custom metadata fields declared in config.
+use anyhow::{anyhow, bail, Result};
+use rusqlite::types::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use super::metadata::{CustomField, FieldType};
+
+static CUSTOM_FIELDS: OnceLock<Vec<Field>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Number,
+    Text,
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: FieldKind,
+    pub help: &'static str,
+    /// Expression over `documents d LEFT JOIN files f`
+    pub(super) sql: &'static str,
+}
+
+/// Every field `list` knows about, in default display order
+pub const FIELDS: &[Field] = &[
+    Field { name: "id", kind: FieldKind::Number, help: "document id", sql: "d.id" },
+    Field { name: "path", kind: FieldKind::Text, help: "source path", sql: "d.path" },
+    Field { name: "chars", kind: FieldKind::Number, help: "characters of extracted text", sql: "LENGTH(d.content)" },
+    Field {
+        name: "size",
+        kind: FieldKind::Number,
+        help: "PDF size in bytes",
+        sql: "COALESCE(f.size_bytes, CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.size_bytes') END)",
+    },
+    Field {
+        name: "pages",
+        kind: FieldKind::Number,
+        help: "page count",
+        sql: "COALESCE(f.page_count, CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.pages') END)",
+    },
+    Field {
+        name: "quality",
+        kind: FieldKind::Number,
+        help: "mean extraction quality, 0-1",
+        sql: "CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.mean_quality') END",
+    },
+    Field {
+        name: "engine",
+        kind: FieldKind::Text,
+        help: "backend that extracted most pages",
+        sql: "CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.engine') END",
+    },
+    Field {
+        name: "tag",
+        kind: FieldKind::Text,
+        help: "document class (invoice, contract, ...), from ingest or set with `meta`",
+        sql: "CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.classification') END",
+    },
+    Field {
+        name: "title",
+        kind: FieldKind::Text,
+        help: "document title",
+        sql: "CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.title') END",
+    },
+    Field {
+        name: "author",
+        kind: FieldKind::Text,
+        help: "document author",
+        sql: "CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.author') END",
+    },
+    Field {
+        name: "doc_date",
+        kind: FieldKind::Text,
+        help: "date the document carries, YYYY-MM-DD",
+        sql: "CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.doc_date') END",
+    },
+    Field {
+        name: "language",
+        kind: FieldKind::Text,
+        help: "language (ISO 639-3) as set, else the detected one covering most of the text",
+        sql: "COALESCE(CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.language') END,
+            (SELECT l.lang FROM page_languages l WHERE l.path = d.path GROUP BY l.lang ORDER BY SUM(l.chars) DESC LIMIT 1))",
+    },
+    Field { name: "status", kind: FieldKind::Text, help: "registry status", sql: "f.status" },
+    Field { name: "date", kind: FieldKind::Text, help: "extraction time (UTC)", sql: "d.created_at" },
+];
+
+/// Make custom metadata fields listable; names are checked by set_metadata_schema
+pub(super) fn register_custom_fields(fields: &BTreeMap<String, CustomField>) {
+    let leak = |s: String| -> &'static str { Box::leak(s.into_boxed_str()) };
+    let custom = fields
+        .iter()
+        .map(|(name, field)| {
+            let value = format!("json_extract(d.metadata, '$.fields.{}')", name);
+            // Booleans come out of json_extract as 1/0; list them as true/false
+            let (kind, value) = match field.kind {
+                FieldType::Number => (FieldKind::Number, value),
+                FieldType::Text | FieldType::Date => (FieldKind::Text, value),
+                FieldType::Bool => (FieldKind::Text, format!("CASE {} WHEN 1 THEN 'true' WHEN 0 THEN 'false' END", value)),
+            };
+            let help = if field.help.is_empty() { format!("custom {} field", field.kind.name()) } else { field.help.clone() };
+            Field {
+                name: leak(name.clone()),
+                kind,
+                help: leak(help),
+                sql: leak(format!("CASE WHEN json_valid(d.metadata) THEN {} END", value)),
+            }
+        })
+        .collect();
+    let _ = CUSTOM_FIELDS.set(custom);
+}
+
+/// The built-in fields, then any custom metadata fields
+pub fn fields() -> impl Iterator<Item = &'static Field> {
+    FIELDS.iter().chain(CUSTOM_FIELDS.get().into_iter().flatten())
+}
+
+pub fn field(name: &str) -> Result<&'static Field> {
+    let name = name.trim().to_lowercase();
+    fields().find(|f| f.name == name).ok_or_else(|| {
+        let known: Vec<&str> = fields().map(|f| f.name).collect();
+        anyhow!("Unknown field '{}' (available: {})", name, known.join(", "))
+    })
+}
+
+impl Field {
+    /// Typed SQL value for a user-supplied literal
+    pub(super) fn literal(&self, raw: &str) -> Result<Value> {
+        match self.kind {
+            FieldKind::Number => raw
+                .parse::<f64>()
+                .map(Value::Real)
+                .map_err(|_| anyhow!("'{}' expects a number, got '{}'", self.name, raw)),
+            FieldKind::Text => Ok(Value::Text(raw.to_string())),
+        }
+    }
+
+    /// Expression with NULLs replaced so keyset comparisons stay total
+    pub(super) fn sort_sql(&self) -> String {
+        match self.kind {
+            FieldKind::Number => format!("COALESCE({}, -1)", self.sql),
+            FieldKind::Text => format!("COALESCE({}, '')", self.sql),
+        }
+    }
+}
+
+/// `--sort size` or `--sort size:desc`
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    pub field: &'static Field,
+    pub descending: bool,
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, direction) = s.split_once(':').unwrap_or((s, "asc"));
+        let descending = match direction.to_lowercase().as_str() {
+            "asc" => false,
+            "desc" => true,
+            other => bail!("Sort direction must be asc or desc, got '{}'", other),
+        };
+        Ok(SortKey { field: field(name)?, descending })
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.field.name, if self.descending { "desc" } else { "asc" })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+}
+
+impl Op {
+    fn sql(&self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Contains => "LIKE",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Condition {
+    field: &'static Field,
+    op: Op,
+    value: Value,
+}
+
+/// Parsed --filter: OR of AND-groups
+#[derive(Debug, Clone)]
+pub struct Filter {
+    any_of: Vec<Vec<Condition>>,
+    /// As written, for passing the filter on to a remote store
+    text: String,
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser { chars: s.chars().collect(), pos: 0 };
+        let mut any_of = vec![Vec::new()];
+
+        loop {
+            let name = parser.identifier()?;
+            let field = field(&name)?;
+            let op = parser.operator()?;
+            let raw = parser.value()?;
+            let value = match op {
+                Op::Contains => Value::Text(raw),
+                _ => field.literal(&raw)?,
+            };
+            any_of.last_mut().unwrap().push(Condition { field, op, value });
+
+            parser.skip_whitespace();
+            if parser.at_end() {
+                break;
+            }
+            match parser.identifier()?.to_uppercase().as_str() {
+                "AND" => {}
+                "OR" => any_of.push(Vec::new()),
+                other => bail!("Expected AND or OR in filter, got '{}'", other),
+            }
+        }
+
+        Ok(Filter { any_of, text: s.to_string() })
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl Filter {
+    /// SQL boolean expression and its parameters, in order
+    pub(super) fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut params = Vec::new();
+        let groups: Vec<String> = self
+            .any_of
+            .iter()
+            .map(|group| {
+                let terms: Vec<String> = group
+                    .iter()
+                    .map(|c| {
+                        params.push(c.value.clone());
+                        match c.op {
+                            Op::Contains => format!("{} LIKE '%' || ? || '%'", c.field.sql),
+                            op => format!("{} {} ?", c.field.sql, op.sql()),
+                        }
+                    })
+                    .collect();
+                format!("({})", terms.join(" AND "))
+            })
+            .collect();
+        (groups.join(" OR "), params)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while !self.at_end() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while !self.at_end() && (self.chars[self.pos].is_alphanumeric() || self.chars[self.pos] == '_') {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            bail!("Expected a field name at position {} of the filter", start + 1);
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn operator(&mut self) -> Result<Op> {
+        self.skip_whitespace();
+        let next = |offset: usize| self.chars.get(self.pos + offset).copied();
+        let (op, len) = match (next(0), next(1)) {
+            (Some('>'), Some('=')) => (Op::Ge, 2),
+            (Some('<'), Some('=')) => (Op::Le, 2),
+            (Some('!'), Some('=')) => (Op::Ne, 2),
+            (Some('>'), _) => (Op::Gt, 1),
+            (Some('<'), _) => (Op::Lt, 1),
+            (Some('='), _) => (Op::Eq, 1),
+            (Some('~'), _) => (Op::Contains, 1),
+            _ => bail!("Expected one of = != > >= < <= ~ at position {} of the filter", self.pos + 1),
+        };
+        self.pos += len;
+        Ok(op)
+    }
+
+    fn value(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        if let Some(quote @ ('"' | '\'')) = self.chars.get(self.pos).copied() {
+            self.pos += 1;
+            let start = self.pos;
+            while !self.at_end() && self.chars[self.pos] != quote {
+                self.pos += 1;
+            }
+            if self.at_end() {
+                bail!("Unterminated quote in filter");
+            }
+            let value = self.chars[start..self.pos].iter().collect();
+            self.pos += 1;
+            return Ok(value);
+        }
+
+        let start = self.pos;
+        while !self.at_end() && !self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            bail!("Missing value at the end of the filter");
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_compiles_to_bound_sql() {
+        let filter: Filter = "pages>100 AND tag=contract or path ~ \"annual report\"".parse().unwrap();
+        let (sql, params) = filter.to_sql();
+        assert!(sql.contains(" > ? AND "), "{}", sql);
+        assert_eq!(sql.matches(" OR (").count(), 1);
+        assert_eq!(params, vec![
+            Value::Real(100.0),
+            Value::Text("contract".to_string()),
+            Value::Text("annual report".to_string()),
+        ]);
+
+        assert!("pages>lots".parse::<Filter>().is_err());
+        assert!("color=red".parse::<Filter>().is_err());
+    }
+}
@@ -0,0 +1,86 @@
+// Recent and pinned files - what the TUI's file picker offers before anything is typed
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS picker_files (
+            path TEXT PRIMARY KEY,
+            opened_at DATETIME,
+            pinned INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Note that the TUI opened a file
+    pub fn record_opened(&self, path: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO picker_files (path, opened_at) VALUES (?1, CURRENT_TIMESTAMP)
+             ON CONFLICT(path) DO UPDATE SET opened_at = CURRENT_TIMESTAMP",
+            params![path],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_pinned(&self, path: &str, pinned: bool) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO picker_files (path, pinned) VALUES (?1, ?2)
+             ON CONFLICT(path) DO UPDATE SET pinned = ?2",
+            params![path, pinned],
+        )?;
+        Ok(())
+    }
+
+    /// Pinned paths, alphabetically
+    pub fn pinned_files(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT path FROM picker_files WHERE pinned ORDER BY path")?;
+        let paths = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    /// Paths most recently opened in the TUI or extracted, newest first
+    pub fn recent_files(&self, limit: usize) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, MAX(at) AS last FROM (
+                SELECT path, opened_at AS at FROM picker_files WHERE opened_at IS NOT NULL
+                UNION ALL
+                SELECT path, created_at FROM documents
+             )
+             GROUP BY path
+             ORDER BY last DESC, path
+             LIMIT ?1",
+        )?;
+        let paths = stmt.query_map(params![limit as i64], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_files_merge_opened_and_extracted() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("extracted.pdf", "text", None).unwrap();
+        storage.record_opened("opened.pdf").unwrap();
+        storage.conn.execute("UPDATE documents SET created_at = '2024-01-01 00:00:00'", []).unwrap();
+        assert_eq!(storage.recent_files(10).unwrap(), vec!["opened.pdf", "extracted.pdf"]);
+        assert_eq!(storage.recent_files(1).unwrap(), vec!["opened.pdf"]);
+
+        // Pinning doesn't make a file recent, and opening it again keeps the pin
+        storage.set_pinned("pinned.pdf", true).unwrap();
+        storage.record_opened("opened.pdf").unwrap();
+        storage.set_pinned("opened.pdf", true).unwrap();
+        storage.record_opened("opened.pdf").unwrap();
+        assert_eq!(storage.pinned_files().unwrap(), vec!["opened.pdf", "pinned.pdf"]);
+        assert_eq!(storage.recent_files(10).unwrap().len(), 2);
+        storage.set_pinned("pinned.pdf", false).unwrap();
+        assert_eq!(storage.pinned_files().unwrap(), vec!["opened.pdf"]);
+    }
+}
@@ -0,0 +1,197 @@
+// Keeping search current with edits - a queue of pages whose searchable text changed
+//
+// Search reads documents.search_text, the stored extraction with edited pages
+// applied (NULL while it is the extraction itself). Every change to what a
+// page shows - an edit logged, a branch checked out or merged, a document
+// re-stored - queues the page. Draining the queue rewrites just that page of
+// search_text, re-embeds just that page, and pushes just that page to the
+// search index, so no full reindex is ever needed. Without an embedding model
+// a page's old vector is left as it was.
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::DuckDBStorage;
+use crate::search_index;
+use crate::views::text_editor::{grid_lines, replay, EditEvent};
+
+/// How long the worker sleeps when the queue is empty
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Pages drained per batch
+const BATCH: usize = 50;
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    // Re-queuing a page replaces its row, so the rowid says which change was last seen
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS index_queue (
+            id INTEGER PRIMARY KEY,
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            queued_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (path, page)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub(super) fn enqueue(conn: &Connection, path: &str, page: usize) -> Result<()> {
+    conn.execute("INSERT OR REPLACE INTO index_queue (path, page) VALUES (?1, ?2)", params![path, page as i64])?;
+    Ok(())
+}
+
+/// Queue every page of a document that has an edit log
+pub(super) fn enqueue_document(conn: &Connection, path: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO index_queue (path, page) SELECT DISTINCT path, page FROM edit_events WHERE path = ?1",
+        params![path],
+    )?;
+    Ok(())
+}
+
+/// Queue every page with events on these branches
+pub(super) fn enqueue_branches(conn: &Connection, branches: &[&str]) -> Result<()> {
+    let mut insert = conn.prepare(
+        "INSERT OR REPLACE INTO index_queue (path, page) SELECT DISTINCT path, page FROM edit_events WHERE branch = ?1",
+    )?;
+    for branch in branches {
+        insert.execute(params![branch])?;
+    }
+    Ok(())
+}
+
+/// Drain the queue in the background for as long as the process runs. The
+/// storage is opened on the worker thread, so loading an embedding model there
+/// doesn't hold up the caller.
+pub fn spawn_index_worker<F>(open: F) -> JoinHandle<()>
+where
+    F: FnOnce() -> Result<DuckDBStorage> + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut storage = match open() {
+            Ok(storage) => storage,
+            Err(e) => {
+                tracing::warn!("[INDEX] Queue worker not started: {}", e);
+                return;
+            }
+        };
+        loop {
+            match storage.process_index_queue(BATCH) {
+                Ok(0) => thread::sleep(POLL_INTERVAL),
+                Ok(done) => tracing::debug!("[INDEX] Reindexed {} queued pages", done),
+                Err(e) => {
+                    tracing::warn!("[INDEX] Queue drain failed: {}", e);
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+    })
+}
+
+impl DuckDBStorage {
+    /// Pages waiting for their search text, embedding and index entry to catch up
+    pub fn pending_index_count(&self) -> Result<i64> {
+        Ok(self.conn.query_row("SELECT COUNT(*) FROM index_queue", [], |row| row.get(0))?)
+    }
+
+    /// Bring up to `limit` queued pages up to date, oldest first. Returns how many were done.
+    pub fn process_index_queue(&mut self, limit: usize) -> Result<usize> {
+        let queued: Vec<(i64, String, i64)> = {
+            let mut stmt = self.conn.prepare("SELECT id, path, page FROM index_queue ORDER BY id LIMIT ?1")?;
+            let rows = stmt.query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        for (id, path, page) in &queued {
+            self.reindex_page(path, *page as usize)?;
+            // A page queued again meanwhile has a new id and stays for the next round
+            self.conn.execute("DELETE FROM index_queue WHERE id = ?1", params![id])?;
+        }
+        Ok(queued.len())
+    }
+
+    fn reindex_page(&mut self, path: &str, page: usize) -> Result<()> {
+        let row: Option<(String, Option<String>, Option<String>)> = self
+            .conn
+            .query_row("SELECT content, search_text, metadata FROM documents WHERE path = ?1", params![path], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .optional()?;
+        // Edits to a document that was never stored have nothing to search
+        let Some((content, search_text, metadata)) = row else { return Ok(()) };
+        let Some(extracted) = content.split('\x0c').nth(page - 1) else { return Ok(()) };
+
+        let log = self.edit_log(path, page)?;
+        let text = if log.is_empty() {
+            extracted.to_string()
+        } else {
+            let events: Vec<EditEvent> = log.into_iter().map(|logged| logged.event).collect();
+            grid_lines(&replay(&events).0).join("\n")
+        };
+
+        let mut pages: Vec<&str> = search_text.as_deref().unwrap_or(&content).split('\x0c').collect();
+        if let Some(slot) = pages.get_mut(page - 1) {
+            *slot = &text;
+        }
+        let searchable = pages.join("\x0c");
+        let stored = (searchable != content).then_some(searchable.as_str());
+        self.conn.execute("UPDATE documents SET search_text = ?2 WHERE path = ?1", params![path, stored])?;
+
+        if let Some(embedder) = self.embedder.as_mut() {
+            if text.trim().is_empty() {
+                self.conn.execute("DELETE FROM page_embeddings WHERE path = ?1 AND page = ?2", params![path, page as i64])?;
+            } else {
+                match embedder.embed(&text) {
+                    Ok(vector) => {
+                        let model = embedder.model_id().to_string();
+                        self.store_page_embedding(path, page, &model, &vector)?;
+                    }
+                    Err(e) => tracing::warn!("[EMBED] Page {} of {} failed: {}", page, path, e),
+                }
+            }
+        }
+        if let Some(indexer) = &self.indexer {
+            let docs: Vec<_> = search_index::page_docs(path, &searchable, metadata.as_deref()).into_iter().filter(|doc| doc.page == page).collect();
+            if let Err(e) = indexer.push(&docs) {
+                tracing::warn!("[INDEX] Push failed for page {} of {}: {}", page, path, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::views::text_editor::{CellEdit, EditCommand};
+
+    #[test]
+    fn test_edited_page_becomes_searchable_after_drain() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("a.pdf", "Invoice\x0cTotai 10", None).unwrap();
+        let fix = EditCommand { edits: vec![CellEdit { row: 0, col: 4, before: 'i', after: 'l' }], cursor_before: (4, 0), cursor_after: (5, 0) };
+        storage
+            .append_edit_events("a.pdf", 2, "tui", &[EditEvent::Base { rows: vec!["Totai 10".to_string()] }, EditEvent::Edit { command: fix, merged: false }])
+            .unwrap();
+        assert_eq!(storage.pending_index_count().unwrap(), 1);
+        assert!(storage.search("Total", None).unwrap().is_empty());
+
+        assert_eq!(storage.process_index_queue(10).unwrap(), 1);
+        assert_eq!(storage.pending_index_count().unwrap(), 0);
+        assert_eq!(storage.search("Total", None).unwrap().len(), 1);
+        // The stored extraction itself is untouched
+        assert_eq!(storage.document_by_path("a.pdf").unwrap().unwrap().content, "Invoice\x0cTotai 10");
+
+        // Re-storing starts from the new extraction and re-applies the edits
+        storage.store_document("a.pdf", "Invoice\x0cTotai 10", None).unwrap();
+        assert_eq!(storage.pending_index_count().unwrap(), 1);
+        storage.process_index_queue(10).unwrap();
+        assert_eq!(storage.search("Total", None).unwrap().len(), 1);
+
+        // A rename takes the queued pages along
+        storage.store_document("a.pdf", "Invoice\x0cTotai 10", None).unwrap();
+        storage.rename_path("a.pdf", "b.pdf").unwrap();
+        storage.process_index_queue(10).unwrap();
+        assert_eq!(storage.search("Total", None).unwrap().len(), 1);
+    }
+}
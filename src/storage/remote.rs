@@ -0,0 +1,182 @@
+// A chonker8 server as the corpus - `--db http://corpus.internal:8080`
+//
+// Speaks the JSON API of `chonker8 serve` (server/api.rs), so workstations share
+// one central database while list, search and stats print what they would for a
+// local file. Cursors are the server's own and pass through untouched. The
+// server caps a page at 100 rows. $CHONKER8_TOKEN, when set, goes out as the
+// bearer token the server checks.
+use anyhow::{anyhow, Result};
+use rusqlite::types::Value;
+use serde_json::json;
+use std::time::Duration;
+
+use super::backend::DocumentStore;
+use super::query::fields;
+use super::{DocumentSummary, ListQuery, Page, PageRequest, SearchResult, StoredDocument};
+use crate::server::TOKEN_ENV;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub struct RemoteStorage {
+    base: String,
+    agent: ureq::Agent,
+    token: Option<String>,
+}
+
+impl RemoteStorage {
+    pub fn new(url: &str) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+        let token = std::env::var(TOKEN_ENV).ok().filter(|token| !token.is_empty());
+        Self { base: url.trim_end_matches('/').to_string(), agent, token }
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self.agent.request(method, &format!("{}{}", self.base, path));
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    fn get(&self, path: &str) -> ureq::Request {
+        self.request("GET", path)
+    }
+
+    fn paged(&self, path: &str, request: &PageRequest) -> ureq::Request {
+        let paged = self.get(path).query("limit", &request.limit.to_string());
+        match &request.after {
+            Some(after) => paged.query("after", after),
+            None => paged.query("page", &request.page.to_string()),
+        }
+    }
+
+    /// Send a request and parse the JSON reply; Ok(None) when the server says 404
+    fn call(&self, request: ureq::Request, body: Option<serde_json::Value>) -> Result<Option<serde_json::Value>> {
+        let response = match body {
+            Some(body) => request.set("Content-Type", "application/json").send_string(&body.to_string()),
+            None => request.call(),
+        };
+        match response {
+            Ok(response) => Ok(Some(serde_json::from_str(&response.into_string()?)?)),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(ureq::Error::Status(401, _)) => Err(anyhow!("{} wants a token - set {} to the server's", self.base, TOKEN_ENV)),
+            Err(ureq::Error::Status(status, response)) => {
+                let reply: serde_json::Value = serde_json::from_str(&response.into_string().unwrap_or_default()).unwrap_or_default();
+                Err(anyhow!("{} answered HTTP {}: {}", self.base, status, reply["error"].as_str().unwrap_or("no details")))
+            }
+            Err(e) => Err(anyhow!("{} unreachable: {}", self.base, e)),
+        }
+    }
+
+    fn document(&self, request: ureq::Request) -> Result<Option<StoredDocument>> {
+        let Some(doc) = self.call(request, None)? else { return Ok(None) };
+        Ok(Some(StoredDocument {
+            id: doc["id"].as_i64().unwrap_or_default(),
+            path: doc["path"].as_str().unwrap_or_default().to_string(),
+            content: doc["content"].as_str().unwrap_or_default().to_string(),
+            metadata: doc["metadata"].as_str().map(str::to_string),
+        }))
+    }
+}
+
+impl DocumentStore for RemoteStorage {
+    fn location(&self) -> String {
+        self.base.clone()
+    }
+
+    fn store_document(&mut self, path: &str, content: &str, metadata: Option<&str>) -> Result<()> {
+        let request = self.request("POST", "/documents");
+        self.call(request, Some(json!({ "path": path, "content": content, "metadata": metadata })))?;
+        Ok(())
+    }
+
+    fn document_by_path(&self, path: &str) -> Result<Option<StoredDocument>> {
+        self.document(self.get("/documents/by-path").query("path", path))
+    }
+
+    fn document_by_id(&self, id: i64) -> Result<Option<StoredDocument>> {
+        self.document(self.get(&format!("/documents/{}", id)))
+    }
+
+    fn search_page(&self, query: &str, language: Option<&str>, request: &PageRequest) -> Result<Page<SearchResult>> {
+        let mut search = self.paged("/search", request).query("q", query);
+        if let Some(language) = language {
+            search = search.query("language", language);
+        }
+        let reply = self.call(search, None)?.unwrap_or_default();
+        let items = items(&reply)
+            .iter()
+            .map(|hit| SearchResult {
+                id: hit["id"].as_i64().unwrap_or_default(),
+                score: hit["score"].as_f64().unwrap_or_default(),
+                snippet: hit["snippet"].as_str().unwrap_or_default().to_string(),
+                path: hit["path"].as_str().unwrap_or_default().to_string(),
+            })
+            .collect();
+        Ok(Page { items, next_cursor: next_cursor(&reply) })
+    }
+
+    fn list_documents(&self, query: &ListQuery, request: &PageRequest) -> Result<Page<DocumentSummary>> {
+        let mut list = self.paged("/documents", request);
+        if let Some(filter) = &query.filter {
+            list = list.query("filter", &filter.to_string());
+        }
+        if let Some(sort) = &query.sort {
+            list = list.query("sort", &sort.to_string());
+        }
+        let reply = self.call(list, None)?.unwrap_or_default();
+        Ok(Page { items: items(&reply).iter().map(summary).collect(), next_cursor: next_cursor(&reply) })
+    }
+
+    fn document_count(&self) -> Result<i64> {
+        Ok(self.call(self.get("/stats"), None)?.unwrap_or_default()["documents"].as_i64().unwrap_or_default())
+    }
+
+    fn get_stats(&self) -> Result<String> {
+        let reply = self.call(self.get("/stats"), None)?.unwrap_or_default();
+        Ok(format!("Server: {}\n{}", self.base, reply["stats"].as_str().unwrap_or_default()))
+    }
+}
+
+fn items(reply: &serde_json::Value) -> &[serde_json::Value] {
+    reply["items"].as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn next_cursor(reply: &serde_json::Value) -> Option<String> {
+    reply["next_cursor"].as_str().map(str::to_string)
+}
+
+/// A listed document from its JSON object, fields back in query::fields() order
+fn summary(item: &serde_json::Value) -> DocumentSummary {
+    let values = fields()
+        .map(|field| match &item[field.name] {
+            serde_json::Value::Number(n) => n.as_i64().map(Value::Integer).unwrap_or_else(|| Value::Real(n.as_f64().unwrap_or_default())),
+            serde_json::Value::String(text) => Value::Text(text.clone()),
+            _ => Value::Null,
+        })
+        .collect();
+    DocumentSummary { values }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::DuckDBStorage;
+
+    #[test]
+    fn test_listed_documents_survive_the_json_round_trip() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("a.pdf", "Invoice\x0cTotal", Some(r#"{"pages": 2, "mean_quality": 0.75}"#)).unwrap();
+        let query = ListQuery { filter: Some("pages>=2 AND path~\"a.pdf\"".parse().unwrap()), sort: Some("pages:desc".parse().unwrap()) };
+        let listed = storage.list_documents(&query, &PageRequest::first(10)).unwrap().items;
+        assert_eq!(listed.len(), 1);
+
+        // What the server sends, and the query strings the client sends it
+        let item = serde_json::Value::Object(fields().map(|f| (f.name.to_string(), listed[0].json(f.name))).collect());
+        let back = summary(&item);
+        assert_eq!(fields().map(|f| back.text(f.name)).collect::<Vec<_>>(), fields().map(|f| listed[0].text(f.name)).collect::<Vec<_>>());
+        assert_eq!(query.filter.unwrap().to_string(), "pages>=2 AND path~\"a.pdf\"");
+        assert_eq!(query.sort.unwrap().to_string(), "pages:desc");
+    }
+}
@@ -0,0 +1,97 @@
+// Renamed files - moves a document's rows to its new path and keeps a log of renames
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+
+/// Every table keyed by file path; the outbox keeps the path events were sent under
+pub(super) const PATH_TABLES: &[&str] = &[
+    "documents",
+    "files",
+    "annotations",
+    "form_fields",
+    "page_convergence",
+    "page_extractions",
+    "edit_events",
+    "edit_conflicts",
+    "index_queue",
+    "page_embeddings",
+    "page_languages",
+    "block_languages",
+    "extraction_runs",
+    "page_translations",
+    "page_words",
+    "page_grids",
+    "document_tags",
+    "picker_files",
+];
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS path_renames (
+            id INTEGER PRIMARY KEY,
+            old_path TEXT NOT NULL,
+            new_path TEXT NOT NULL,
+            renamed_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Point everything stored for `old` at `new`, in one transaction. Fails if
+    /// `new` already has a document or file registered.
+    pub fn rename_path(&mut self, old: &str, new: &str) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let taken: i64 = tx.query_row(
+            "SELECT (SELECT COUNT(*) FROM documents WHERE path = ?1) + (SELECT COUNT(*) FROM files WHERE path = ?1)",
+            params![new],
+            |row| row.get(0),
+        )?;
+        if taken > 0 {
+            bail!("{} is already in the database", new);
+        }
+        for table in PATH_TABLES {
+            tx.execute(&format!("UPDATE {} SET path = ?2 WHERE path = ?1", table), params![old, new])?;
+        }
+        tx.execute("INSERT INTO path_renames (old_path, new_path) VALUES (?1, ?2)", params![old, new])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Every rename, oldest first, as (old path, new path, when)
+    pub fn path_renames(&self) -> Result<Vec<(String, String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT old_path, new_path, renamed_at FROM path_renames ORDER BY id")?;
+        let renames = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(renames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::views::text_editor::EditEvent;
+
+    #[test]
+    fn test_rename_moves_rows_and_refuses_clash() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("scan0001.pdf", "Invoice 42", None).unwrap();
+        storage.store_translation("scan0001.pdf", 1, "fr", "Facture 42", "api:test").unwrap();
+        storage.append_edit_events("scan0001.pdf", 1, "tui", &[EditEvent::Undo]).unwrap();
+        assert!(storage.append_edit_events_at("scan0001.pdf", 1, "cli", 0, &[EditEvent::Redo]).is_err());
+        storage.store_document("taken.pdf", "Other", None).unwrap();
+
+        storage.rename_path("scan0001.pdf", "2024-03-14_ACME_Invoice_42.pdf").unwrap();
+        assert!(storage.document_by_path("scan0001.pdf").unwrap().is_none());
+        assert!(storage.document_by_path("2024-03-14_ACME_Invoice_42.pdf").unwrap().is_some());
+        assert!(storage.translation("2024-03-14_ACME_Invoice_42.pdf", 1, "fr").unwrap().is_some());
+        assert_eq!(storage.path_renames().unwrap().len(), 1);
+        assert_eq!(storage.edit_conflicts(Some("2024-03-14_ACME_Invoice_42.pdf")).unwrap().len(), 1);
+
+        assert!(storage.rename_path("2024-03-14_ACME_Invoice_42.pdf", "taken.pdf").is_err());
+        assert!(storage.document_by_path("2024-03-14_ACME_Invoice_42.pdf").unwrap().is_some());
+    }
+}
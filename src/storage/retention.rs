@@ -0,0 +1,161 @@
+// Retention rules - purging stored extraction data that is too old or marked disposable
+//
+// A purge removes every row kept for a path (the PATH_TABLES list renames use,
+// plus entities, undelivered outbox events and rename history) in one
+// transaction, and writes an audit row that is never purged itself: the path,
+// the rule that selected it and how many rows went. The PDF on disk is not touched.
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use super::renames::PATH_TABLES;
+use super::DuckDBStorage;
+
+/// [retention] in extraction.toml
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RetentionConfig {
+    /// Purge documents extracted longer ago than this
+    #[serde(default)]
+    pub max_age_years: Option<u32>,
+    /// Purge documents with this tag whatever their age
+    #[serde(default = "default_ephemeral_tag")]
+    pub ephemeral_tag: Option<String>,
+}
+
+fn default_ephemeral_tag() -> Option<String> { Some("ephemeral".to_string()) }
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self { max_age_years: None, ephemeral_tag: default_ephemeral_tag() }
+    }
+}
+
+/// A path a rule selects, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetentionCandidate {
+    pub path: String,
+    pub reason: String,
+}
+
+/// One purge, as recorded for audit
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionAudit {
+    pub path: String,
+    pub reason: String,
+    pub rows: usize,
+    pub purged_at: String,
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS retention_audit (
+            id INTEGER PRIMARY KEY,
+            path TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            rows INTEGER NOT NULL,
+            purged_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Paths the rules select, each once (age is reported before the tag)
+    pub fn retention_candidates(&self, rules: &RetentionConfig) -> Result<Vec<RetentionCandidate>> {
+        let mut candidates: Vec<RetentionCandidate> = Vec::new();
+        if let Some(years) = rules.max_age_years {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT path FROM documents WHERE created_at < datetime('now', ?1) ORDER BY created_at")?;
+            let paths = stmt.query_map(params![format!("-{} years", years)], |row| row.get::<_, String>(0))?;
+            for path in paths {
+                candidates.push(RetentionCandidate { path: path?, reason: format!("extracted more than {} years ago", years) });
+            }
+        }
+        if let Some(tag) = &rules.ephemeral_tag {
+            for path in self.tagged(tag)? {
+                if !candidates.iter().any(|c| c.path == path) {
+                    candidates.push(RetentionCandidate { path, reason: format!("tagged {}", tag) });
+                }
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Delete everything stored for a path and audit it; returns the rows deleted
+    pub fn purge_path(&mut self, path: &str, reason: &str) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut rows = tx.execute(
+            "DELETE FROM entities WHERE document_id IN (SELECT id FROM documents WHERE path = ?1)",
+            params![path],
+        )?;
+        for table in PATH_TABLES {
+            rows += tx.execute(&format!("DELETE FROM {} WHERE path = ?1", table), params![path])?;
+        }
+        rows += tx.execute("DELETE FROM outbox WHERE path = ?1", params![path])?;
+        rows += tx.execute("DELETE FROM webhook_outbox WHERE path = ?1", params![path])?;
+        rows += tx.execute("DELETE FROM path_renames WHERE old_path = ?1 OR new_path = ?1", params![path])?;
+        tx.execute(
+            "INSERT INTO retention_audit (path, reason, rows) VALUES (?1, ?2, ?3)",
+            params![path, reason, rows as i64],
+        )?;
+        tx.commit()?;
+        Ok(rows)
+    }
+
+    /// Every purge so far, oldest first
+    pub fn retention_audit(&self) -> Result<Vec<RetentionAudit>> {
+        let mut stmt = self.conn.prepare("SELECT path, reason, rows, purged_at FROM retention_audit ORDER BY id")?;
+        let audit = stmt
+            .query_map([], |row| {
+                Ok(RetentionAudit {
+                    path: row.get(0)?,
+                    reason: row.get(1)?,
+                    rows: row.get::<_, i64>(2)? as usize,
+                    purged_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(audit)
+    }
+
+    /// Give the space of deleted rows back to the filesystem, so purged text is
+    /// not left readable in free pages of the database file
+    pub fn vacuum(&self) -> Result<()> {
+        self.conn.execute_batch("VACUUM; PRAGMA optimize;")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::views::text_editor::EditEvent;
+
+    #[test]
+    fn test_age_and_tag_rules_purge_and_audit() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("old.pdf", "Invoice 1", None).unwrap();
+        storage.store_document("new.pdf", "Invoice 2", None).unwrap();
+        storage.store_document("scratch.pdf", "Draft", None).unwrap();
+        storage.store_translation("old.pdf", 1, "fr", "Facture 1", "api:test").unwrap();
+        storage.append_edit_events("old.pdf", 1, "tui", &[EditEvent::Undo]).unwrap();
+        assert!(storage.append_edit_events_at("old.pdf", 1, "cli", 0, &[EditEvent::Redo]).is_err());
+        storage.conn.execute("UPDATE documents SET created_at = datetime('now', '-8 years') WHERE path = 'old.pdf'", []).unwrap();
+        storage.add_tag("scratch.pdf", "ephemeral").unwrap();
+
+        let rules = RetentionConfig { max_age_years: Some(7), ..Default::default() };
+        let candidates = storage.retention_candidates(&rules).unwrap();
+        let paths: Vec<&str> = candidates.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["old.pdf", "scratch.pdf"]);
+
+        assert!(storage.purge_path("old.pdf", &candidates[0].reason).unwrap() >= 2);
+        assert!(storage.document_by_path("old.pdf").unwrap().is_none());
+        assert!(storage.translation("old.pdf", 1, "fr").unwrap().is_none());
+        assert!(storage.edit_conflicts(Some("old.pdf")).unwrap().is_empty());
+        assert!(storage.document_by_path("new.pdf").unwrap().is_some());
+        let audit = storage.retention_audit().unwrap();
+        assert_eq!((audit[0].path.as_str(), audit[0].reason.as_str()), ("old.pdf", "extracted more than 7 years ago"));
+    }
+}
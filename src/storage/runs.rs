@@ -0,0 +1,67 @@
+// Extraction run history - timings used to fit the cost model
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+
+/// One timed document extraction
+#[derive(Debug, Clone)]
+pub struct RunRecord {
+    pub path: String,
+    pub engine: String,
+    pub page_count: usize,
+    pub image_coverage: f32,
+    pub duration_ms: u64,
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS extraction_runs (
+            id INTEGER PRIMARY KEY,
+            path TEXT NOT NULL,
+            engine TEXT NOT NULL,
+            page_count INTEGER NOT NULL,
+            image_coverage REAL NOT NULL DEFAULT 0,
+            duration_ms INTEGER NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    pub fn record_run(&mut self, run: &RunRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO extraction_runs (path, engine, page_count, image_coverage, duration_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                run.path,
+                run.engine,
+                run.page_count as i64,
+                run.image_coverage,
+                run.duration_ms as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent runs first, capped at `limit`
+    pub fn recent_runs(&self, limit: usize) -> Result<Vec<RunRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, engine, page_count, image_coverage, duration_ms
+             FROM extraction_runs ORDER BY id DESC LIMIT ?1"
+        )?;
+        let runs = stmt.query_map(params![limit as i64], |row| {
+            Ok(RunRecord {
+                path: row.get(0)?,
+                engine: row.get(1)?,
+                page_count: row.get::<_, i64>(2)? as usize,
+                image_coverage: row.get(3)?,
+                duration_ms: row.get::<_, i64>(4)? as u64,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(runs)
+    }
+}
@@ -0,0 +1,125 @@
+// Raw SQL access for the `sql` console - statements, parameters and schema lookup
+use anyhow::{bail, Result};
+use rusqlite::types::Value;
+use std::collections::HashMap;
+
+use super::DuckDBStorage;
+
+/// Result of one statement: rows for queries, a change count for everything else
+#[derive(Debug, Clone, Default)]
+pub struct SqlOutput {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub changed: usize,
+}
+
+/// Typed value for a parameter given on the command line or with `.param set`.
+/// Quotes force text, so `'007'` stays a string.
+pub fn param_value(raw: &str) -> Value {
+    let quoted = raw.len() >= 2
+        && ((raw.starts_with('\'') && raw.ends_with('\'')) || (raw.starts_with('"') && raw.ends_with('"')));
+    if quoted {
+        return Value::Text(raw[1..raw.len() - 1].to_string());
+    }
+    if raw.eq_ignore_ascii_case("null") {
+        return Value::Null;
+    }
+    if let Ok(n) = raw.parse::<i64>() {
+        return Value::Integer(n);
+    }
+    if let Ok(n) = raw.parse::<f64>() {
+        return Value::Real(n);
+    }
+    Value::Text(raw.to_string())
+}
+
+impl DuckDBStorage {
+    /// Run one statement. Named parameters (`:name`, `@name`, `$name`) are looked up
+    /// without their prefix; positional ones (`?`, `?3`) by their 1-based index.
+    pub fn run_sql(&self, sql: &str, params: &HashMap<String, Value>) -> Result<SqlOutput> {
+        let mut stmt = self.conn.prepare(sql)?;
+
+        for index in 1..=stmt.parameter_count() {
+            let key = match stmt.parameter_name(index) {
+                Some(name) if !name.starts_with('?') => name[1..].to_string(),
+                _ => index.to_string(),
+            };
+            let Some(value) = params.get(&key) else {
+                bail!("No value for parameter '{}' - set it with .param set {} VALUE", key, key);
+            };
+            stmt.raw_bind_parameter(index, value)?;
+        }
+
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        if columns.is_empty() {
+            let changed = stmt.raw_execute()?;
+            return Ok(SqlOutput { columns, rows: Vec::new(), changed });
+        }
+
+        let mut rows = Vec::new();
+        let mut cursor = stmt.raw_query();
+        while let Some(row) = cursor.next()? {
+            rows.push((0..columns.len()).map(|i| row.get::<_, Value>(i)).collect::<Result<Vec<_>, _>>()?);
+        }
+        Ok(SqlOutput { columns, rows, changed: 0 })
+    }
+
+    /// Tables and views, internal sqlite_ ones excluded
+    pub fn table_names(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%'
+             ORDER BY name",
+        )?;
+        let names = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+        Ok(names)
+    }
+
+    /// CREATE statements for one table or view and its indexes, or for everything
+    pub fn schema_sql(&self, name: Option<&str>) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT sql FROM sqlite_master
+             WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+               AND (?1 IS NULL OR tbl_name = ?1 COLLATE NOCASE)
+             ORDER BY tbl_name, type DESC, name",
+        )?;
+        let statements = stmt.query_map([name], |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+        if statements.is_empty() {
+            if let Some(name) = name {
+                bail!("No table or view named '{}' (try .tables)", name);
+            }
+        }
+        Ok(statements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_sql_binds_named_and_positional_params() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("a.pdf", "short", None).unwrap();
+        storage.store_document("b.pdf", "a much longer text", None).unwrap();
+
+        let params = HashMap::from([
+            ("min".to_string(), param_value("6")),
+            ("2".to_string(), param_value("'b.pdf'")),
+        ]);
+        let output = storage
+            .run_sql("SELECT path, LENGTH(content) AS chars FROM documents WHERE LENGTH(content) > :min AND path = ?2", &params)
+            .unwrap();
+        assert_eq!(output.columns, vec!["path", "chars"]);
+        assert_eq!(output.rows, vec![vec![Value::Text("b.pdf".to_string()), Value::Integer(18)]]);
+
+        let output = storage.run_sql("DELETE FROM documents WHERE path = :path", &HashMap::from([
+            ("path".to_string(), param_value("a.pdf")),
+        ])).unwrap();
+        assert_eq!(output.changed, 1);
+
+        assert!(storage.run_sql("SELECT :missing", &HashMap::new()).is_err());
+        assert!(storage.table_names().unwrap().contains(&"documents".to_string()));
+        assert!(storage.schema_sql(Some("DOCUMENTS")).unwrap()[0].starts_with("CREATE TABLE documents"));
+    }
+}
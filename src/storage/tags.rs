@@ -0,0 +1,63 @@
+// Free-form document tags - `chonker8 tag`, and what retention rules select on
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS document_tags (
+            path TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            tagged_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (path, tag)
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_document_tags_tag ON document_tags(tag)", [])?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Tag a document; tagging it again is a no-op
+    pub fn add_tag(&self, path: &str, tag: &str) -> Result<()> {
+        self.conn.execute("INSERT OR IGNORE INTO document_tags (path, tag) VALUES (?1, ?2)", params![path, tag])?;
+        Ok(())
+    }
+
+    /// Whether the tag was there to remove
+    pub fn remove_tag(&self, path: &str, tag: &str) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM document_tags WHERE path = ?1 AND tag = ?2", params![path, tag])? > 0)
+    }
+
+    /// A document's tags, alphabetically
+    pub fn tags(&self, path: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT tag FROM document_tags WHERE path = ?1 ORDER BY tag")?;
+        let tags = stmt.query_map(params![path], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+
+    /// Paths carrying a tag, alphabetically
+    pub fn tagged(&self, tag: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT path FROM document_tags WHERE tag = ?1 ORDER BY path")?;
+        let paths = stmt.query_map(params![tag], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_remove_and_list_tags() {
+        let storage = DuckDBStorage::new(None).unwrap();
+        storage.add_tag("a.pdf", "ephemeral").unwrap();
+        storage.add_tag("a.pdf", "ephemeral").unwrap();
+        storage.add_tag("a.pdf", "client-x").unwrap();
+        assert_eq!(storage.tags("a.pdf").unwrap(), vec!["client-x", "ephemeral"]);
+        assert_eq!(storage.tagged("ephemeral").unwrap(), vec!["a.pdf"]);
+        assert!(storage.remove_tag("a.pdf", "ephemeral").unwrap());
+        assert!(!storage.remove_tag("a.pdf", "ephemeral").unwrap());
+    }
+}
@@ -0,0 +1,63 @@
+// Translated text layers, one per page and target language, kept alongside the original
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS page_translations (
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            lang TEXT NOT NULL,
+            text TEXT NOT NULL,
+            engine TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (path, page, lang)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Store (or replace) a page's (1-based) translation
+    pub fn store_translation(&mut self, path: &str, page: usize, lang: &str, text: &str, engine: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO page_translations (path, page, lang, text, engine) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![path, page as i64, lang, text, engine],
+        )?;
+        Ok(())
+    }
+
+    /// A page's translation, if it has one in that language
+    pub fn translation(&self, path: &str, page: usize, lang: &str) -> Result<Option<String>> {
+        let mut stmt = self.conn.prepare("SELECT text FROM page_translations WHERE path = ?1 AND page = ?2 AND lang = ?3")?;
+        let mut rows = stmt.query_map(params![path, page as i64, lang], |row| row.get::<_, String>(0))?;
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Languages a document has been translated into, alphabetically
+    pub fn translated_languages(&self, path: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT lang FROM page_translations WHERE path = ?1 ORDER BY lang")?;
+        let langs = stmt.query_map(params![path], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+        Ok(langs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translations_are_per_language() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_translation("a.pdf", 1, "en", "Invoice", "api:test").unwrap();
+        storage.store_translation("a.pdf", 1, "fr", "Facture", "api:test").unwrap();
+        storage.store_translation("a.pdf", 1, "en", "Invoice no. 42", "api:test").unwrap();
+
+        assert_eq!(storage.translation("a.pdf", 1, "en").unwrap().as_deref(), Some("Invoice no. 42"));
+        assert_eq!(storage.translation("a.pdf", 2, "en").unwrap(), None);
+        assert_eq!(storage.translated_languages("a.pdf").unwrap(), vec!["en", "fr"]);
+    }
+}
@@ -0,0 +1,137 @@
+// Where the database's bytes go, per document, and how fast it is growing
+//
+// Sizes are the bytes of row data each kind of record holds - text and blobs
+// by length, fixed columns at a flat per-row estimate. Indexes and SQLite page
+// slack are not attributed to documents, so the columns add up to less than
+// the file. Page images are never stored; `pdf` is the source file on disk,
+// from the ingest scan, for comparison.
+use anyhow::Result;
+use rusqlite::params;
+
+use super::DuckDBStorage;
+
+/// Bytes a row's numeric columns and bookkeeping are counted as
+const ROW_OVERHEAD: i64 = 32;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentUsage {
+    pub path: String,
+    /// Extracted text and metadata
+    pub text: i64,
+    /// Page grid sizes and word boxes
+    pub grids: i64,
+    pub embeddings: i64,
+    /// Edit log on every branch, and translations
+    pub versions: i64,
+    pub pdf: Option<i64>,
+}
+
+impl DocumentUsage {
+    /// Bytes in the database, the source PDF not included
+    pub fn total(&self) -> i64 {
+        self.text + self.grids + self.embeddings + self.versions
+    }
+}
+
+/// Database size now and its growth at the recent ingest rate
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageForecast {
+    /// The database file, indexes and free pages included
+    pub db_bytes: i64,
+    pub documents: i64,
+    /// Documents stored in the last `window_days`
+    pub recent_documents: i64,
+    pub window_days: i64,
+    /// File bytes per stored document, on average
+    pub bytes_per_document: i64,
+}
+
+impl StorageForecast {
+    pub fn documents_per_day(&self) -> f64 {
+        self.recent_documents as f64 / self.window_days.max(1) as f64
+    }
+
+    /// Expected file size `days` from now
+    pub fn projected(&self, days: i64) -> i64 {
+        self.db_bytes + (self.documents_per_day() * days as f64 * self.bytes_per_document as f64) as i64
+    }
+}
+
+impl DuckDBStorage {
+    /// Per-document breakdown, largest first; `limit` 0 for all
+    pub fn storage_usage(&self, limit: usize) -> Result<Vec<DocumentUsage>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT d.path,
+                LENGTH(CAST(d.content AS BLOB)) + COALESCE(LENGTH(CAST(d.metadata AS BLOB)), 0),
+                (SELECT COUNT(*) * {overhead} FROM page_grids g WHERE g.path = d.path)
+                    + (SELECT COALESCE(SUM(LENGTH(CAST(w.text AS BLOB)) + {overhead}), 0) FROM page_words w WHERE w.path = d.path),
+                (SELECT COALESCE(SUM(LENGTH(e.vector)), 0) FROM page_embeddings e WHERE e.path = d.path),
+                (SELECT COALESCE(SUM(LENGTH(CAST(v.event AS BLOB)) + {overhead}), 0) FROM edit_events v WHERE v.path = d.path)
+                    + (SELECT COALESCE(SUM(LENGTH(CAST(t.text AS BLOB)) + {overhead}), 0) FROM page_translations t WHERE t.path = d.path),
+                (SELECT f.size_bytes FROM files f WHERE f.path = d.path)
+             FROM documents d",
+            overhead = ROW_OVERHEAD
+        ))?;
+        let mut usage = stmt
+            .query_map([], |row| {
+                Ok(DocumentUsage {
+                    path: row.get(0)?,
+                    text: row.get(1)?,
+                    grids: row.get(2)?,
+                    embeddings: row.get(3)?,
+                    versions: row.get(4)?,
+                    pdf: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        usage.sort_by(|a, b| b.total().cmp(&a.total()).then_with(|| a.path.cmp(&b.path)));
+        if limit > 0 {
+            usage.truncate(limit);
+        }
+        Ok(usage)
+    }
+
+    /// Growth over the last `window_days` of ingest, for projecting the file size
+    pub fn storage_forecast(&self, window_days: i64) -> Result<StorageForecast> {
+        let page_count: i64 = self.conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: i64 = self.conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        let documents = self.document_count()?;
+        let recent_documents: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM documents WHERE created_at >= datetime('now', ?1)",
+            params![format!("-{} days", window_days)],
+            |row| row.get(0),
+        )?;
+        let db_bytes = page_count * page_size;
+        Ok(StorageForecast {
+            db_bytes,
+            documents,
+            recent_documents,
+            window_days,
+            bytes_per_document: if documents > 0 { db_bytes / documents } else { 0 },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::views::text_editor::EditEvent;
+
+    #[test]
+    fn test_usage_attributes_rows_to_documents() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("small.pdf", "hi", None).unwrap();
+        storage.store_document("big.pdf", &"x".repeat(5000), None).unwrap();
+        storage.append_edit_events("small.pdf", 1, "tui", &[EditEvent::Undo]).unwrap();
+
+        let usage = storage.storage_usage(0).unwrap();
+        assert_eq!(usage.iter().map(|u| u.path.as_str()).collect::<Vec<_>>(), vec!["big.pdf", "small.pdf"]);
+        assert_eq!(usage[0].text, 5000);
+        assert_eq!(usage[1].versions, serde_json::to_string(&EditEvent::Undo).unwrap().len() as i64 + ROW_OVERHEAD);
+        assert_eq!(storage.storage_usage(1).unwrap().len(), 1);
+
+        let forecast = storage.storage_forecast(30).unwrap();
+        assert_eq!((forecast.documents, forecast.recent_documents), (2, 2));
+        assert!(forecast.projected(365) > forecast.db_bytes);
+    }
+}
@@ -0,0 +1,382 @@
+// Named snapshots and branches over the page edit log
+//
+// The edit log is already a version history: events carry a seq that grows across
+// every page, and a page at seq N is its events up to N replayed. A snapshot names
+// such a point on a branch ("pre-cleanup"). A branch forks from another at a seq;
+// it sees its parent's events up to the fork plus its own, so reviewers can try
+// aggressive corrections without touching main. Whatever writes to the log - the
+// TUI editor, undo/redo, replace-all - writes to the checked-out branch.
+//
+// Merging copies a branch's events onto its parent as new events, so branches
+// forked from the parent earlier never see them appear in their past. A page the
+// parent also changed since the fork is a conflict, and then nothing is merged.
+// Discarding hides a branch; its events stay in the log.
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use super::edits::{insert_events, page_log, LoggedEdit};
+use super::reindex;
+use super::DuckDBStorage;
+use crate::views::text_editor::{replay, EditEvent};
+
+pub const MAIN_BRANCH: &str = "main";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Branch {
+    pub name: String,
+    /// None for main
+    pub parent: Option<String>,
+    /// Last parent event the branch sees
+    pub base_seq: i64,
+    /// Events logged on the branch itself
+    pub events: i64,
+    pub created_at: Option<String>,
+    pub merged_at: Option<String>,
+    pub discarded_at: Option<String>,
+}
+
+impl Branch {
+    pub fn is_open(&self) -> bool {
+        self.merged_at.is_none() && self.discarded_at.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub branch: String,
+    pub seq: i64,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS version_branches (
+            name TEXT PRIMARY KEY,
+            parent TEXT NOT NULL,
+            base_seq INTEGER NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            merged_at DATETIME,
+            discarded_at DATETIME
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS version_snapshots (
+            name TEXT PRIMARY KEY,
+            branch TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            note TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    // One row: the checked-out branch; no row means main
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS version_head (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            branch TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// The checked-out branch
+pub(super) fn head(conn: &Connection) -> Result<String> {
+    let branch: Option<String> = conn.query_row("SELECT branch FROM version_head WHERE id = 1", [], |row| row.get(0)).optional()?;
+    Ok(branch.unwrap_or_else(|| MAIN_BRANCH.to_string()))
+}
+
+/// Check out a branch; every page either side has edited now reads differently
+fn set_head(conn: &Connection, branch: &str) -> Result<()> {
+    let before = chain(conn, &head(conn)?)?;
+    let after = chain(conn, branch)?;
+    let touched: Vec<&str> = before.iter().chain(&after).map(|(name, _)| name.as_str()).collect();
+    reindex::enqueue_branches(conn, &touched)?;
+    conn.execute("INSERT OR REPLACE INTO version_head (id, branch) VALUES (1, ?1)", params![branch])?;
+    Ok(())
+}
+
+/// The branches whose events `branch` sees, each with the last seq it sees of
+/// them: the branch itself in full, then its parent up to the fork, and so on
+pub(super) fn chain(conn: &Connection, branch: &str) -> Result<Vec<(String, i64)>> {
+    let mut chain = vec![(branch.to_string(), i64::MAX)];
+    let mut current = branch.to_string();
+    while current != MAIN_BRANCH {
+        let fork: Option<(String, i64)> = conn
+            .query_row("SELECT parent, base_seq FROM version_branches WHERE name = ?1", params![current], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()?;
+        let Some((parent, base_seq)) = fork else { bail!("No branch '{}'", current) };
+        let upto = chain.last().map_or(base_seq, |(_, upto)| base_seq.min(*upto));
+        chain.push((parent.clone(), upto));
+        current = parent;
+    }
+    Ok(chain)
+}
+
+pub(super) fn visible(chain: &[(String, i64)], branch: &str, seq: i64) -> bool {
+    chain.iter().any(|(name, upto)| name == branch && seq <= *upto)
+}
+
+pub(super) fn last_seq(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM edit_events", [], |row| row.get(0))?)
+}
+
+/// The grid a log leaves a page with; its base when the log stops before any edit
+fn grid_of(log: &[LoggedEdit], fallback: &[LoggedEdit]) -> Vec<String> {
+    let events: Vec<EditEvent> = if log.is_empty() {
+        fallback.iter().take(1).map(|logged| logged.event.clone()).collect()
+    } else {
+        log.iter().map(|logged| logged.event.clone()).collect()
+    };
+    replay(&events).0.iter().map(|row| row.iter().collect()).collect()
+}
+
+impl DuckDBStorage {
+    pub fn current_branch(&self) -> Result<String> {
+        head(&self.conn)
+    }
+
+    /// main first, then branches oldest first
+    pub fn branches(&self) -> Result<Vec<Branch>> {
+        let main_events: i64 =
+            self.conn.query_row("SELECT COUNT(*) FROM edit_events WHERE branch = ?1", params![MAIN_BRANCH], |row| row.get(0))?;
+        let mut branches = vec![Branch {
+            name: MAIN_BRANCH.to_string(),
+            parent: None,
+            base_seq: 0,
+            events: main_events,
+            created_at: None,
+            merged_at: None,
+            discarded_at: None,
+        }];
+        let mut stmt = self.conn.prepare(
+            "SELECT b.name, b.parent, b.base_seq, (SELECT COUNT(*) FROM edit_events e WHERE e.branch = b.name),
+                    b.created_at, b.merged_at, b.discarded_at
+             FROM version_branches b ORDER BY b.created_at, b.rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Branch {
+                name: row.get(0)?,
+                parent: Some(row.get(1)?),
+                base_seq: row.get(2)?,
+                events: row.get(3)?,
+                created_at: row.get(4)?,
+                merged_at: row.get(5)?,
+                discarded_at: row.get(6)?,
+            })
+        })?;
+        for branch in rows {
+            branches.push(branch?);
+        }
+        Ok(branches)
+    }
+
+    fn branch(&self, name: &str) -> Result<Option<Branch>> {
+        Ok(self.branches()?.into_iter().find(|b| b.name == name))
+    }
+
+    /// Fork a branch from the checked-out one as it is now, and check it out
+    pub fn create_branch(&mut self, name: &str) -> Result<Branch> {
+        if self.branch(name)?.is_some() || self.snapshot(name)?.is_some() {
+            bail!("'{}' is already a branch or snapshot name", name);
+        }
+        let tx = self.conn.transaction()?;
+        let parent = head(&tx)?;
+        tx.execute(
+            "INSERT INTO version_branches (name, parent, base_seq) VALUES (?1, ?2, ?3)",
+            params![name, parent, last_seq(&tx)?],
+        )?;
+        set_head(&tx, name)?;
+        tx.commit()?;
+        self.branch(name)?.ok_or_else(|| anyhow::anyhow!("Branch '{}' vanished", name))
+    }
+
+    /// Check out an open branch; later edits are logged on it
+    pub fn switch_branch(&mut self, name: &str) -> Result<()> {
+        match self.branch(name)? {
+            Some(branch) if branch.is_open() => set_head(&self.conn, name),
+            Some(_) => bail!("Branch '{}' was merged or discarded", name),
+            None => bail!("No branch '{}'", name),
+        }
+    }
+
+    /// Name the checked-out branch as it is now
+    pub fn create_snapshot(&mut self, name: &str, note: Option<&str>) -> Result<Snapshot> {
+        if self.branch(name)?.is_some() || self.snapshot(name)?.is_some() {
+            bail!("'{}' is already a branch or snapshot name", name);
+        }
+        self.conn.execute(
+            "INSERT INTO version_snapshots (name, branch, seq, note) VALUES (?1, ?2, ?3, ?4)",
+            params![name, head(&self.conn)?, last_seq(&self.conn)?, note],
+        )?;
+        self.snapshot(name)?.ok_or_else(|| anyhow::anyhow!("Snapshot '{}' vanished", name))
+    }
+
+    pub fn snapshots(&self) -> Result<Vec<Snapshot>> {
+        let mut stmt = self.conn.prepare("SELECT name, branch, seq, note, created_at FROM version_snapshots ORDER BY seq, rowid")?;
+        let snapshots = stmt
+            .query_map([], |row| {
+                Ok(Snapshot { name: row.get(0)?, branch: row.get(1)?, seq: row.get(2)?, note: row.get(3)?, created_at: row.get(4)? })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(snapshots)
+    }
+
+    pub fn snapshot(&self, name: &str) -> Result<Option<Snapshot>> {
+        Ok(self.snapshots()?.into_iter().find(|s| s.name == name))
+    }
+
+    /// A page's log as it stood when the snapshot was taken
+    pub fn snapshot_log(&self, snapshot: &Snapshot, path: &str, page: usize) -> Result<Vec<LoggedEdit>> {
+        let chain: Vec<(String, i64)> =
+            chain(&self.conn, &snapshot.branch)?.into_iter().map(|(branch, upto)| (branch, upto.min(snapshot.seq))).collect();
+        page_log(&self.conn, &chain, path, page)
+    }
+
+    /// Put every edited page of the checked-out branch back as it was at a
+    /// snapshot. Each changed page gets a new base event, so the restore is
+    /// itself in the log (and clears the page's undo stack). Returns the pages changed.
+    pub fn restore_snapshot(&mut self, name: &str) -> Result<usize> {
+        let snapshot = self.snapshot(name)?.ok_or_else(|| anyhow::anyhow!("No snapshot '{}'", name))?;
+        let pages: Vec<(String, i64)> = {
+            let mut stmt = self.conn.prepare("SELECT DISTINCT path, page FROM edit_events ORDER BY path, page")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        let source = format!("checkout:{}", name);
+        let tx = self.conn.transaction()?;
+        let current = chain(&tx, &head(&tx)?)?;
+        let mut changed = 0;
+        for (path, page) in pages {
+            let page = page as usize;
+            let now = page_log(&tx, &current, &path, page)?;
+            let then: Vec<LoggedEdit> = {
+                let chain: Vec<(String, i64)> =
+                    chain(&tx, &snapshot.branch)?.into_iter().map(|(branch, upto)| (branch, upto.min(snapshot.seq))).collect();
+                page_log(&tx, &chain, &path, page)?
+            };
+            if now.is_empty() && then.is_empty() {
+                continue;
+            }
+            let (rows_now, rows_then) = (grid_of(&now, &then), grid_of(&then, &now));
+            if rows_now != rows_then {
+                insert_events(&tx, &path, page, &source, &[EditEvent::Base { rows: rows_then }])?;
+                changed += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Copy a branch's edits onto its parent and close it. Fails, merging
+    /// nothing, if the parent changed any of the same pages since the fork.
+    /// Returns the pages merged.
+    pub fn merge_branch(&mut self, name: &str) -> Result<usize> {
+        let branch = self.branch(name)?.ok_or_else(|| anyhow::anyhow!("No branch '{}'", name))?;
+        let Some(parent) = branch.parent.clone() else { bail!("main has nothing to merge into") };
+        if !branch.is_open() {
+            bail!("Branch '{}' was already merged or discarded", name);
+        }
+        let pages: Vec<(String, i64)> = {
+            let mut stmt = self.conn.prepare("SELECT DISTINCT path, page FROM edit_events WHERE branch = ?1 ORDER BY path, page")?;
+            let rows = stmt.query_map(params![name], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        let mut conflicts = Vec::new();
+        for (path, page) in &pages {
+            let changed: bool = self.conn.query_row(
+                "SELECT COUNT(*) > 0 FROM edit_events WHERE branch = ?1 AND path = ?2 AND page = ?3 AND seq > ?4",
+                params![parent, path, page, branch.base_seq],
+                |row| row.get(0),
+            )?;
+            if changed {
+                conflicts.push(format!("{} page {}", path, page));
+            }
+        }
+        if !conflicts.is_empty() {
+            bail!("{} changed on '{}' since '{}' was forked: {} - nothing merged", conflicts.len(), parent, name, conflicts.join(", "));
+        }
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO edit_events (path, page, source, event, created_at, branch)
+             SELECT path, page, source, event, created_at, ?1 FROM edit_events WHERE branch = ?2 ORDER BY seq",
+            params![parent, name],
+        )?;
+        tx.execute("UPDATE version_branches SET merged_at = CURRENT_TIMESTAMP WHERE name = ?1", params![name])?;
+        reindex::enqueue_branches(&tx, &[name])?;
+        if head(&tx)? == name {
+            set_head(&tx, &parent)?;
+        }
+        tx.commit()?;
+        Ok(pages.len())
+    }
+
+    /// Close a branch without merging it; if it was checked out, its parent is
+    pub fn discard_branch(&mut self, name: &str) -> Result<()> {
+        let branch = self.branch(name)?.ok_or_else(|| anyhow::anyhow!("No branch '{}'", name))?;
+        let Some(parent) = branch.parent.clone() else { bail!("main can't be discarded") };
+        if !branch.is_open() {
+            bail!("Branch '{}' was already merged or discarded", name);
+        }
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE version_branches SET discarded_at = CURRENT_TIMESTAMP WHERE name = ?1", params![name])?;
+        if head(&tx)? == name {
+            set_head(&tx, &parent)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(col: usize, before: char, after: char) -> EditEvent {
+        use crate::views::text_editor::{CellEdit, EditCommand};
+        let command = EditCommand { edits: vec![CellEdit { row: 0, col, before, after }], cursor_before: (col, 0), cursor_after: (col + 1, 0) };
+        EditEvent::Edit { command, merged: false }
+    }
+
+    fn text(storage: &DuckDBStorage) -> String {
+        let events: Vec<EditEvent> = storage.edit_log("a.pdf", 1).unwrap().into_iter().map(|l| l.event).collect();
+        replay(&events).0[0].iter().collect()
+    }
+
+    #[test]
+    fn test_branch_merge_discard_and_snapshot_restore() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.append_edit_events("a.pdf", 1, "tui", &[EditEvent::Base { rows: vec!["abc".to_string()] }, typed(0, 'a', 'A')]).unwrap();
+        storage.create_snapshot("pre-cleanup", None).unwrap();
+
+        storage.create_branch("aggressive").unwrap();
+        storage.append_edit_events("a.pdf", 1, "tui", &[typed(1, 'b', 'B')]).unwrap();
+        assert_eq!(text(&storage), "ABc");
+        storage.switch_branch(MAIN_BRANCH).unwrap();
+        assert_eq!(text(&storage), "Abc");
+
+        storage.merge_branch("aggressive").unwrap();
+        assert_eq!(text(&storage), "ABc");
+        assert!(storage.switch_branch("aggressive").is_err());
+
+        // A page edited on both sides is not merged
+        storage.create_branch("tried").unwrap();
+        storage.append_edit_events("a.pdf", 1, "tui", &[typed(2, 'c', 'C')]).unwrap();
+        storage.switch_branch(MAIN_BRANCH).unwrap();
+        storage.append_edit_events("a.pdf", 1, "cli", &[EditEvent::Undo]).unwrap();
+        assert!(storage.merge_branch("tried").is_err());
+        storage.discard_branch("tried").unwrap();
+        assert_eq!(text(&storage), "Abc");
+
+        storage.append_edit_events("a.pdf", 1, "tui", &[typed(2, 'c', 'Z')]).unwrap();
+        assert_eq!(storage.restore_snapshot("pre-cleanup").unwrap(), 1);
+        assert_eq!(text(&storage), "Abc");
+    }
+}
@@ -0,0 +1,83 @@
+// Built-in views for routine questions, so nobody has to reverse-engineer the schema
+//
+// Views are dropped and recreated every time the database is opened, so their
+// definitions follow the binary rather than whichever version created the file.
+use anyhow::Result;
+use rusqlite::Connection;
+
+pub struct BuiltinView {
+    pub name: &'static str,
+    pub description: &'static str,
+    sql: &'static str,
+}
+
+pub const VIEWS: &[BuiltinView] = &[
+    BuiltinView {
+        name: "documents_with_quality",
+        description: "one row per document: pages, chars, engine, mean quality, review flag and mean OCR agreement (token F1)",
+        sql: "SELECT d.id, d.path,
+                     CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.pages') END AS pages,
+                     LENGTH(d.content) AS chars,
+                     CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.engine') END AS engine,
+                     CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.mean_quality') END AS mean_quality,
+                     CASE WHEN json_valid(d.metadata) THEN json_extract(d.metadata, '$.needs_review') END AS needs_review,
+                     AVG(pc.token_f1) AS ocr_agreement,
+                     d.created_at
+              FROM documents d
+              LEFT JOIN page_convergence pc ON pc.path = d.path
+              GROUP BY d.id",
+    },
+    BuiltinView {
+        name: "pages_needing_review",
+        description: "pages (1-based) where OCR and the text layer disagree (token F1 below 0.8), worst first",
+        sql: "SELECT d.id AS document_id, pc.path, pc.page + 1 AS page,
+                     pc.token_f1, pc.word_similarity, pc.native_words, pc.ocr_words, pc.measured_at
+              FROM page_convergence pc
+              LEFT JOIN documents d ON d.path = pc.path
+              WHERE pc.token_f1 < 0.8
+              ORDER BY pc.token_f1, pc.path, pc.page",
+    },
+    BuiltinView {
+        name: "entity_counts_by_doc",
+        description: "entity occurrences and distinct values per document and entity kind",
+        sql: "SELECT d.id AS document_id, d.path, e.kind,
+                     COUNT(*) AS occurrences, COUNT(DISTINCT e.value) AS distinct_values
+              FROM entities e
+              JOIN documents d ON d.id = e.document_id
+              GROUP BY d.id, e.kind",
+    },
+];
+
+pub(super) fn create_views(conn: &Connection) -> Result<()> {
+    for view in VIEWS {
+        conn.execute_batch(&format!(
+            "DROP VIEW IF EXISTS {name}; CREATE VIEW {name} AS {sql};",
+            name = view.name,
+            sql = view.sql
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::DuckDBStorage;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_builtin_views_are_queryable() {
+        let mut storage = DuckDBStorage::new(None).unwrap();
+        storage.store_document("a.pdf", "text", Some(r#"{"pages": 3, "mean_quality": 0.9}"#)).unwrap();
+        // Free-form metadata from before it was validated on write
+        storage.conn.execute("INSERT INTO documents (path, content, metadata) VALUES ('b.pdf', 'text', 'not json')", []).unwrap();
+
+        for view in VIEWS {
+            let sql = format!("SELECT * FROM {}", view.name);
+            storage.run_sql(&sql, &HashMap::new()).unwrap();
+        }
+        let output = storage.run_sql("SELECT pages, mean_quality FROM documents_with_quality ORDER BY id", &HashMap::new()).unwrap();
+        assert_eq!(output.rows[0][0], rusqlite::types::Value::Integer(3));
+        assert_eq!(output.rows[1][0], rusqlite::types::Value::Null);
+    }
+}
@@ -0,0 +1,56 @@
+// Positioned words per page - kept for pages whose boxes came from outside
+// (imported hOCR/ALTO) and can't be recomputed from the PDF
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::DuckDBStorage;
+use crate::pdf_extraction::bbox::BoxWord;
+
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS page_words (
+            path TEXT NOT NULL,
+            page INTEGER NOT NULL,
+            seq INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            x0 REAL NOT NULL,
+            y0 REAL NOT NULL,
+            x1 REAL NOT NULL,
+            y1 REAL NOT NULL,
+            PRIMARY KEY (path, page, seq)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+impl DuckDBStorage {
+    /// Replace the words of one page (0-based), boxes in PDF points from the top-left
+    pub fn replace_page_words(&mut self, path: &str, page: usize, words: &[BoxWord]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM page_words WHERE path = ?1 AND page = ?2", params![path, page as i64])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO page_words (path, page, seq, text, x0, y0, x1, y1) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for (seq, word) in words.iter().enumerate() {
+                insert.execute(params![path, page as i64, seq as i64, word.text, word.x0, word.y0, word.x1, word.y1])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stored words of one page (0-based) in reading order; empty if none were stored
+    pub fn page_words(&self, path: &str, page: usize) -> Result<Vec<BoxWord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT text, x0, y0, x1, y1 FROM page_words WHERE path = ?1 AND page = ?2 ORDER BY seq",
+        )?;
+        let words = stmt
+            .query_map(params![path, page as i64], |row| {
+                Ok(BoxWord { text: row.get(0)?, x0: row.get(1)?, y0: row.get(2)?, x1: row.get(3)?, y1: row.get(4)? })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(words)
+    }
+}
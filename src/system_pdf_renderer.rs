@@ -3,7 +3,9 @@ use anyhow::Result;
 use image::DynamicImage;
 use std::path::Path;
 use std::process::Command;
-use tempfile::TempDir;
+
+use crate::page_images;
+use crate::temp_files::{self, scratch_dir};
 
 pub struct SystemPdfRenderer;
 
@@ -13,10 +15,26 @@ impl SystemPdfRenderer {
     }
 
     pub fn render_page_to_bitmap(&self, pdf_path: &Path, page_num: usize, width: u32, height: u32) -> Result<DynamicImage> {
-        eprintln!("[SYSTEM] Using pdftoppm to render page {} at {}x{}", page_num, width, height);
+        crate::debug_log!("SYSTEM", "Using pdftoppm to render page {} at {}x{}", page_num, width, height);
+        
+        // Shared with every other render of this page at this size
+        let setting = format!("{}x{}", width, height);
+        let png = page_images::cached_png(pdf_path, page_num, &setting, || Self::render_png(pdf_path, page_num, width, height))?;
+        let image = image::load_from_memory(&png)?;
         
+        crate::debug_log!("SYSTEM", "✅ Page rendered successfully: {}x{}", image.width(), image.height());
+        // A copy for --debug-artifacts
+        if let Some(copy) = temp_files::debug_artifact(&format!("render-p{}-{}x{}.png", page_num + 1, width, height)) {
+            image.save(&copy).ok();
+        }
+        
+        Ok(image)
+    }
+
+    /// pdftoppm's PNG of one page scaled to width x height
+    fn render_png(pdf_path: &Path, page_num: usize, width: u32, height: u32) -> Result<Vec<u8>> {
         // Create a temporary directory for output
-        let temp_dir = TempDir::new()?;
+        let temp_dir = scratch_dir()?;
         let output_prefix = temp_dir.path().join("page");
         
         // Use pdftoppm to convert PDF page to PNG
@@ -48,22 +66,13 @@ impl SystemPdfRenderer {
             // Try without page number suffix
             let alt_file = temp_dir.path().join("page-1.png");
             if alt_file.exists() {
-                eprintln!("[SYSTEM] Loading rendered page from {:?}", alt_file);
-                let image = image::open(&alt_file)?;
-                eprintln!("[SYSTEM] ✅ Page rendered successfully: {}x{}", image.width(), image.height());
-                return Ok(image);
+                crate::debug_log!("SYSTEM", "Loading rendered page from {:?}", alt_file);
+                return Ok(std::fs::read(&alt_file)?);
             }
             return Err(anyhow::anyhow!("Output file not found at {:?}", output_file));
         }
         
-        eprintln!("[SYSTEM] Loading rendered page from {:?}", output_file);
-        let image = image::open(&output_file)?;
-        
-        // Save a debug copy
-        image.save("/tmp/system_render_output.png").ok();
-        eprintln!("[SYSTEM] ✅ Page rendered successfully: {}x{} - saved to /tmp/system_render_output.png", 
-                 image.width(), image.height());
-        
-        Ok(image)
+        crate::debug_log!("SYSTEM", "Loading rendered page from {:?}", output_file);
+        Ok(std::fs::read(&output_file)?)
     }
-}
\ No newline at end of file
+}
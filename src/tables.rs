@@ -0,0 +1,265 @@
+// Table detection and CSV export from positioned words
+//
+// Words are grouped into lines by baseline, lines are split into cell segments at
+// wide horizontal gaps, and runs of 3+ consecutive multi-segment lines whose
+// segments share column boundaries become a table. Cell boxes are kept in PDF
+// points so JSON consumers can map cells back onto the page.
+
+use serde::Serialize;
+
+use crate::pdf_extraction::bbox::{BoxWord, PageWords};
+
+/// Minimum rows (including a header) for a run of aligned lines to count as a table
+const MIN_TABLE_ROWS: usize = 3;
+
+/// A horizontal gap wider than this many line heights separates two cells
+const CELL_GAP_LINE_HEIGHTS: f32 = 1.0;
+
+/// A vertical gap wider than this many line heights ends a table
+const ROW_GAP_LINE_HEIGHTS: f32 = 2.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Rect {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+}
+
+impl Rect {
+    fn of(word: &BoxWord) -> Self {
+        Rect { x0: word.x0, y0: word.y0, x1: word.x1, y1: word.y1 }
+    }
+
+    fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableCell {
+    pub row: usize,
+    pub col: usize,
+    pub text: String,
+    /// None for empty cells
+    pub bbox: Option<Rect>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Table {
+    pub page: usize, // 1-based, matches what users see
+    pub index: usize,
+    pub bbox: Rect,
+    pub headers: Vec<String>,
+    /// True when the first row looked like a header; otherwise headers are generated
+    pub header_inferred: bool,
+    pub rows: Vec<Vec<String>>,
+    /// Every grid cell with its box; row 0 is the header row when header_inferred
+    pub cells: Vec<TableCell>,
+}
+
+// A run of words on one line with no wide gap inside
+#[derive(Debug, Clone)]
+struct Segment {
+    text: String,
+    rect: Rect,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    segments: Vec<Segment>,
+    rect: Rect,
+    height: f32,
+}
+
+/// Find tables on a page. `page` is 1-based.
+pub fn detect_tables(page: usize, words: &PageWords) -> Vec<Table> {
+    let lines = group_lines(&words.words);
+    let mut tables = Vec::new();
+    let mut run: Vec<&Line> = Vec::new();
+
+    for line in &lines {
+        let continues = match run.last() {
+            Some(previous) => {
+                line.segments.len() >= 2
+                    && line.rect.y0 - previous.rect.y1 <= previous.height * ROW_GAP_LINE_HEIGHTS
+            }
+            None => line.segments.len() >= 2,
+        };
+
+        if continues {
+            run.push(line);
+        } else {
+            tables.extend(build_table(page, tables.len(), &run));
+            run.clear();
+            if line.segments.len() >= 2 {
+                run.push(line);
+            }
+        }
+    }
+    tables.extend(build_table(page, tables.len(), &run));
+    tables
+}
+
+fn group_lines(words: &[BoxWord]) -> Vec<Line> {
+    let mut sorted: Vec<&BoxWord> = words.iter().filter(|w| !w.text.trim().is_empty()).collect();
+    sorted.sort_by(|a, b| a.center_y().total_cmp(&b.center_y()).then(a.x0.total_cmp(&b.x0)));
+
+    let mut rows: Vec<Vec<&BoxWord>> = Vec::new();
+    for word in sorted {
+        match rows.last_mut() {
+            Some(row) if (row[0].center_y() - word.center_y()).abs() < row[0].height().max(1.0) * 0.5 => row.push(word),
+            _ => rows.push(vec![word]),
+        }
+    }
+
+    rows.into_iter()
+        .map(|mut row| {
+            row.sort_by(|a, b| a.x0.total_cmp(&b.x0));
+            let height = row.iter().map(|w| w.height()).fold(0.0, f32::max).max(1.0);
+
+            let mut segments: Vec<Segment> = Vec::new();
+            for word in row {
+                match segments.last_mut() {
+                    Some(segment) if word.x0 - segment.rect.x1 <= height * CELL_GAP_LINE_HEIGHTS => {
+                        segment.text.push(' ');
+                        segment.text.push_str(&word.text);
+                        segment.rect = segment.rect.union(&Rect::of(word));
+                    }
+                    _ => segments.push(Segment { text: word.text.clone(), rect: Rect::of(word) }),
+                }
+            }
+
+            let rect = segments.iter().skip(1).fold(segments[0].rect, |acc, s| acc.union(&s.rect));
+            Line { segments, rect, height }
+        })
+        .collect()
+}
+
+fn build_table(page: usize, index: usize, lines: &[&Line]) -> Option<Table> {
+    if lines.len() < MIN_TABLE_ROWS {
+        return None;
+    }
+
+    // Columns are the union of overlapping segment x-ranges across all rows
+    let mut columns: Vec<(f32, f32)> = lines
+        .iter()
+        .flat_map(|line| line.segments.iter().map(|s| (s.rect.x0, s.rect.x1)))
+        .collect();
+    columns.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<(f32, f32)> = Vec::new();
+    for (x0, x1) in columns {
+        match merged.last_mut() {
+            Some(last) if x0 <= last.1 => last.1 = last.1.max(x1),
+            _ => merged.push((x0, x1)),
+        }
+    }
+    if merged.len() < 2 {
+        return None;
+    }
+
+    let mut rows = vec![vec![String::new(); merged.len()]; lines.len()];
+    let mut cells = Vec::new();
+    let mut bbox = lines[0].rect;
+
+    for (row_index, line) in lines.iter().enumerate() {
+        bbox = bbox.union(&line.rect);
+        let mut rects: Vec<Option<Rect>> = vec![None; merged.len()];
+
+        for segment in &line.segments {
+            let col = merged.iter().position(|(x0, x1)| segment.rect.x0 >= *x0 && segment.rect.x0 <= *x1)?;
+            let cell = &mut rows[row_index][col];
+            if !cell.is_empty() {
+                cell.push(' ');
+            }
+            cell.push_str(&segment.text);
+            rects[col] = Some(rects[col].map_or(segment.rect, |r| r.union(&segment.rect)));
+        }
+
+        for (col, rect) in rects.into_iter().enumerate() {
+            cells.push(TableCell { row: row_index, col, text: rows[row_index][col].clone(), bbox: rect });
+        }
+    }
+
+    let header_inferred = looks_like_header(&rows);
+    let headers = if header_inferred {
+        rows.remove(0)
+    } else {
+        (1..=merged.len()).map(|i| format!("column_{}", i)).collect()
+    };
+
+    Some(Table { page, index, bbox, headers, header_inferred, rows, cells })
+}
+
+/// A header row is fully populated with distinct non-numeric labels, over a body
+/// that has numbers or gaps (a fully-populated text-only block is ambiguous)
+fn looks_like_header(rows: &[Vec<String>]) -> bool {
+    let is_numeric = |cell: &str| {
+        let stripped: String = cell.chars().filter(|c| !matches!(c, '$' | '€' | '£' | ',' | '%' | ' ')).collect();
+        !stripped.is_empty() && stripped.parse::<f64>().is_ok()
+    };
+
+    let first = &rows[0];
+    let labels = first.iter().all(|c| !c.is_empty() && !is_numeric(c));
+    let distinct = first.iter().collect::<std::collections::HashSet<_>>().len() == first.len();
+    let body = &rows[1..];
+    let body_differs = body.iter().flatten().any(|c| c.is_empty() || is_numeric(c));
+
+    labels && distinct && body_differs
+}
+
+/// Render a table as CSV (RFC 4180 quoting) with the header row first
+pub fn to_csv(table: &Table) -> String {
+    let mut out = String::new();
+    for row in std::iter::once(&table.headers).chain(table.rows.iter()) {
+        let line: Vec<String> = row.iter().map(|cell| csv_field(cell)).collect();
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// One RFC 4180 field, quoted when it holds a comma, quote or line break
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, x0: f32, y0: f32) -> BoxWord {
+        BoxWord { text: text.to_string(), x0, y0, x1: x0 + 6.0 * text.len() as f32, y1: y0 + 10.0 }
+    }
+
+    #[test]
+    fn test_detects_table_with_header() {
+        let mut words = vec![word("Quarterly", 50.0, 20.0), word("report", 110.0, 20.0)];
+        for (i, (item, qty, price)) in [("Item", "Qty", "Price"), ("Widget", "4", "$2.50"), ("Gadget", "10", "$1,200.00")]
+            .iter()
+            .enumerate()
+        {
+            let y = 60.0 + i as f32 * 14.0;
+            words.push(word(item, 50.0, y));
+            words.push(word(qty, 200.0, y));
+            words.push(word(price, 300.0, y));
+        }
+
+        let tables = detect_tables(1, &PageWords { width: 612.0, height: 792.0, words });
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert!(table.header_inferred);
+        assert_eq!(table.headers, vec!["Item", "Qty", "Price"]);
+        assert_eq!(table.rows[1], vec!["Gadget", "10", "$1,200.00"]);
+        assert_eq!(to_csv(table).lines().nth(2), Some("Gadget,10,\"$1,200.00\""));
+    }
+}
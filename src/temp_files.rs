@@ -0,0 +1,150 @@
+// Temporary files - partial outputs, scratch directories and debug artifacts
+//
+// Outputs are written to a hidden partial file beside their destination and
+// renamed over it once complete, so an interrupted run leaves the old file (or
+// none), never half of a new one. Scratch directories for page renders and the
+// like go under the system temp dir with a chonker8- prefix and are removed when
+// dropped. A run killed outright can't clean up after itself, so what it leaves
+// behind - partials next to outputs, scratch directories - is swept by later runs
+// once it is a day old. Intermediate images are only kept with --debug-artifacts.
+use anyhow::Result;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+/// Start of every scratch directory and spool file name
+pub const TEMP_PREFIX: &str = "chonker8-";
+/// Start of a partial output's name; the writer's pid and the output's name follow
+const PARTIAL_PREFIX: &str = ".chonker8-partial-";
+/// Leftovers younger than this may still belong to a running process
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+static DEBUG_ARTIFACTS: OnceLock<PathBuf> = OnceLock::new();
+
+/// An output being written. Renamed onto the target by `commit`; removed if dropped before.
+pub struct Partial {
+    path: PathBuf,
+    target: PathBuf,
+    committed: bool,
+}
+
+impl Partial {
+    pub fn new(target: &Path) -> Result<Self> {
+        let dir = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let name = target.file_name().ok_or_else(|| anyhow::anyhow!("{} is not a file path", target.display()))?;
+        sweep(dir, PARTIAL_PREFIX);
+        // Keeps the target's extension, for tools that pick a format by it
+        let path = dir.join(format!("{}{}-{}", PARTIAL_PREFIX, std::process::id(), name.to_string_lossy()));
+        Ok(Self { path, target: target.to_path_buf(), committed: false })
+    }
+
+    /// Where to write the output for now
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn commit(mut self) -> Result<()> {
+        fs::rename(&self.path, &self.target)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for Partial {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// `fs::write`, but all or nothing
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    write_with(path, |out| Ok(out.write_all(contents.as_ref())?))
+}
+
+/// Stream an output into place; nothing appears at `path` unless `write` succeeds
+pub fn write_with(path: &Path, write: impl FnOnce(&mut dyn Write) -> Result<()>) -> Result<()> {
+    let partial = Partial::new(path)?;
+    let mut out = BufWriter::new(fs::File::create(partial.path())?);
+    write(&mut out)?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    partial.commit()
+}
+
+/// A scratch directory, removed when dropped
+pub fn scratch_dir() -> Result<TempDir> {
+    Ok(tempfile::Builder::new().prefix(TEMP_PREFIX).tempdir()?)
+}
+
+/// Remove scratch directories and files earlier runs left in the temp dir.
+/// Returns how many went.
+pub fn sweep_stale() -> usize {
+    sweep(&std::env::temp_dir(), TEMP_PREFIX)
+}
+
+fn sweep(dir: &Path, prefix: &str) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else { return 0 };
+    let own = format!("{}{}-", PARTIAL_PREFIX, std::process::id());
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(prefix) || name.starts_with(&own) {
+            continue;
+        }
+        let age = entry.metadata().and_then(|meta| meta.modified()).ok().and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        if age.is_none_or(|age| age < STALE_AFTER) {
+            continue;
+        }
+        let path = entry.path();
+        let gone = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        removed += gone.is_ok() as usize;
+    }
+    removed
+}
+
+/// Keep intermediate images (renders, OCR inputs) in `dir` for the rest of the process
+pub fn set_debug_artifacts(dir: PathBuf) {
+    let _ = DEBUG_ARTIFACTS.set(dir);
+}
+
+/// Where to save a debug artifact called `name`, when --debug-artifacts is on
+pub fn debug_artifact(name: &str) -> Option<PathBuf> {
+    let dir = DEBUG_ARTIFACTS.get()?;
+    fs::create_dir_all(dir).ok()?;
+    Some(dir.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_write_leaves_old_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("report.json");
+        write(&target, "old").unwrap();
+
+        let failed = write_with(&target, |out| {
+            out.write_all(b"half of a new")?;
+            anyhow::bail!("interrupted")
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // A day-old partial from a killed run is swept by the next write
+        let stale = dir.path().join(format!("{}1-report.json", PARTIAL_PREFIX));
+        fs::write(&stale, "leftover").unwrap();
+        fs::File::options().write(true).open(&stale).unwrap().set_modified(SystemTime::now() - 2 * STALE_AFTER).unwrap();
+        write(&target, "new").unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        assert!(!stale.exists());
+    }
+}
@@ -1,37 +1,249 @@
+// Color themes - every color the TUI draws with, by what it is for
+//
+// Views ask for a role (error, diff_added, search_match, ...) rather than a
+// color, so one palette restyles the whole interface. Two palettes are built in,
+// dark (Ghostty-inspired, the default) and light; ui.toml picks one with
+// [theme] palette and can define more, each starting from a built-in one:
+//
+//   [palettes.solarized]
+//   base = "dark"
+//   error = "#dc322f"
+//   accent = "cyan"
+//
+// Colors are #rrggbb or a terminal color name (red, dark_grey, ...). The theme
+// in use is process-wide and swapped when ui.toml is reloaded.
+use anyhow::{anyhow, bail, Result};
 use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
 
-// Ghostty-inspired color theme for pure crossterm
-pub struct ChonkerTheme;
+/// A palette defined in ui.toml: a built-in one to start from and the roles it changes
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct PaletteConfig {
+    /// dark or light; dark if unset
+    #[serde(default)]
+    pub base: Option<String>,
+    #[serde(flatten)]
+    pub colors: BTreeMap<String, String>,
+}
+
+macro_rules! roles {
+    ($($role:ident: $doc:literal),* $(,)?) => {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub struct ChonkerTheme {
+            $(#[doc = $doc] pub $role: Color,)*
+        }
+
+        impl ChonkerTheme {
+            /// Every role a palette can set
+            pub const ROLES: &'static [&'static str] = &[$(stringify!($role)),*];
+
+            fn set(&mut self, role: &str, color: Color) -> Result<()> {
+                match role {
+                    $(stringify!($role) => self.$role = color,)*
+                    _ => bail!("unknown theme role '{}' (roles: {})", role, Self::ROLES.join(", ")),
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+roles! {
+    text: "Body text",
+    text_secondary: "Less important text, e.g. file sizes",
+    text_dim: "Unchanged diff lines, hints, panel borders",
+    text_header: "Headings",
+    background: "Behind panels that fill their area",
+    status_bg: "Status bar background",
+    status_fg: "Status bar text",
+    title_bg: "Behind panel titles",
+    title_pdf: "The page panel's title",
+    title_text: "The text panel's title",
+    heading: "Screen titles",
+    accent: "Frames, borders and the line between the panes",
+    accent_text: "Labels in the file picker",
+    accent_load_file: "The file picker's title",
+    page_status: "Page number and loading state",
+    success: "Done, saved, added",
+    warning: "Needs attention",
+    error: "Failed",
+    diff_added: "Lines an edit added",
+    diff_removed: "Lines an edit removed",
+    merge_theirs: "Rows only the other session changed",
+    merge_both: "Rows both sessions changed the same way",
+    conflict_ours: "This session's side of a conflict",
+    conflict_theirs: "The other session's side of a conflict",
+    search_match: "Search matches",
+    search_current: "The match the cursor is on",
+    selection: "Block selection",
+    cursor: "The cursor cell",
+    on_highlight: "Text on any highlighted background",
+}
 
 impl ChonkerTheme {
-    // Dark mode backgrounds - pure black
-    pub fn bg_secondary_dark() -> Color { Color::Rgb { r: 0, g: 0, b: 0 } }
-    pub fn bg_status_dark() -> Color { Color::Rgb { r: 0, g: 0, b: 0 } }
-    
-    // Light mode backgrounds
-    pub fn bg_secondary_light() -> Color { Color::Rgb { r: 250, g: 250, b: 250 } }
-    
-    // Accent colors (same for both modes)
-    pub fn accent_pdf() -> Color { Color::Rgb { r: 129, g: 162, b: 190 } }   // #81A2BE - Soft blue
-    pub fn accent_text() -> Color { Color::Rgb { r: 143, g: 161, b: 179 } }  // #8FA1B3 - Muted cyan
-    pub fn accent_options() -> Color { Color::Rgb { r: 100, g: 149, b: 237 } } // #6495ED - Cornflower blue
-    pub fn accent_load_file() -> Color { Color::Rgb { r: 169, g: 133, b: 202 } } // #A985CA - Soft purple
-    
-    // Text colors for dark mode
-    pub fn text_primary_dark() -> Color { Color::Rgb { r: 197, g: 200, b: 198 } }
-    pub fn text_secondary_dark() -> Color { Color::Rgb { r: 150, g: 152, b: 150 } }
-    pub fn text_dim_dark() -> Color { Color::Rgb { r: 96, g: 99, b: 102 } }
-    pub fn text_header_dark() -> Color { Color::Rgb { r: 255, g: 255, b: 255 } }
-    pub fn text_status_dark() -> Color { Color::Rgb { r: 197, g: 200, b: 198 } }
-    
-    // Functional colors (same for both modes)
-    pub fn success() -> Color { Color::Rgb { r: 181, g: 189, b: 104 } }      // #B5BD68 - Green
-    
-    // For backwards compatibility - default to dark mode
-    pub fn bg_secondary() -> Color { Self::bg_secondary_dark() }
-    pub fn bg_status() -> Color { Self::bg_status_dark() }
-    pub fn text_primary() -> Color { Self::text_primary_dark() }
-    pub fn text_secondary() -> Color { Self::text_secondary_dark() }
-    pub fn text_dim() -> Color { Self::text_dim_dark() }
-    pub fn text_header() -> Color { Self::text_header_dark() }
-}
\ No newline at end of file
+    pub fn dark() -> Self {
+        Self {
+            text: Color::Rgb { r: 197, g: 200, b: 198 },
+            text_secondary: Color::Rgb { r: 150, g: 152, b: 150 },
+            text_dim: Color::Rgb { r: 96, g: 99, b: 102 },
+            text_header: Color::Rgb { r: 255, g: 255, b: 255 },
+            background: Color::Rgb { r: 0, g: 0, b: 0 },
+            status_bg: Color::DarkBlue,
+            status_fg: Color::White,
+            title_bg: Color::Rgb { r: 30, g: 30, b: 40 },
+            title_pdf: Color::Yellow,
+            title_text: Color::Green,
+            heading: Color::Yellow,
+            accent: Color::Cyan,
+            accent_text: Color::Rgb { r: 143, g: 161, b: 179 },      // #8FA1B3 - Muted cyan
+            accent_load_file: Color::Rgb { r: 169, g: 133, b: 202 }, // #A985CA - Soft purple
+            page_status: Color::DarkYellow,
+            success: Color::Rgb { r: 181, g: 189, b: 104 },          // #B5BD68 - Green
+            warning: Color::Yellow,
+            error: Color::Red,
+            diff_added: Color::Green,
+            diff_removed: Color::Red,
+            merge_theirs: Color::Cyan,
+            merge_both: Color::Yellow,
+            conflict_ours: Color::Red,
+            conflict_theirs: Color::Magenta,
+            search_match: Color::DarkYellow,
+            search_current: Color::Yellow,
+            selection: Color::Cyan,
+            cursor: Color::White,
+            on_highlight: Color::Black,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            text: Color::Rgb { r: 40, g: 42, b: 46 },
+            text_secondary: Color::Rgb { r: 90, g: 94, b: 100 },
+            text_dim: Color::Rgb { r: 150, g: 152, b: 156 },
+            text_header: Color::Rgb { r: 0, g: 0, b: 0 },
+            background: Color::Rgb { r: 250, g: 250, b: 250 },
+            status_bg: Color::Rgb { r: 210, g: 222, b: 240 },
+            status_fg: Color::Rgb { r: 20, g: 30, b: 60 },
+            title_bg: Color::Rgb { r: 230, g: 232, b: 238 },
+            title_pdf: Color::Rgb { r: 150, g: 100, b: 0 },
+            title_text: Color::Rgb { r: 40, g: 130, b: 40 },
+            heading: Color::Rgb { r: 150, g: 100, b: 0 },
+            accent: Color::Rgb { r: 0, g: 120, b: 140 },
+            accent_text: Color::Rgb { r: 60, g: 100, b: 120 },
+            accent_load_file: Color::Rgb { r: 120, g: 70, b: 160 },
+            page_status: Color::Rgb { r: 150, g: 100, b: 0 },
+            success: Color::Rgb { r: 40, g: 130, b: 40 },
+            warning: Color::Rgb { r: 180, g: 120, b: 0 },
+            error: Color::Rgb { r: 190, g: 30, b: 30 },
+            diff_added: Color::Rgb { r: 40, g: 130, b: 40 },
+            diff_removed: Color::Rgb { r: 190, g: 30, b: 30 },
+            merge_theirs: Color::Rgb { r: 0, g: 120, b: 140 },
+            merge_both: Color::Rgb { r: 150, g: 100, b: 0 },
+            conflict_ours: Color::Rgb { r: 190, g: 30, b: 30 },
+            conflict_theirs: Color::Rgb { r: 150, g: 40, b: 150 },
+            search_match: Color::Rgb { r: 250, g: 225, b: 140 },
+            search_current: Color::Rgb { r: 250, g: 180, b: 60 },
+            selection: Color::Rgb { r: 170, g: 210, b: 240 },
+            cursor: Color::Rgb { r: 40, g: 42, b: 46 },
+            on_highlight: Color::Rgb { r: 0, g: 0, b: 0 },
+        }
+    }
+
+    /// A built-in palette or one defined under [palettes] in ui.toml
+    pub fn resolve(name: &str, palettes: &BTreeMap<String, PaletteConfig>) -> Result<Self> {
+        match name {
+            "dark" => return Ok(Self::dark()),
+            "light" => return Ok(Self::light()),
+            _ => {}
+        }
+        let palette = palettes.get(name).ok_or_else(|| anyhow!("no palette '{}' (dark, light or one under [palettes])", name))?;
+        let mut theme = match palette.base.as_deref().unwrap_or("dark") {
+            "dark" => Self::dark(),
+            "light" => Self::light(),
+            other => bail!("palette '{}' is based on '{}', which isn't dark or light", name, other),
+        };
+        for (role, color) in &palette.colors {
+            theme.set(role, parse_color(color)?)?;
+        }
+        Ok(theme)
+    }
+
+    /// Set one role from a color string, e.g. a legacy [theme] highlight
+    pub fn with(mut self, role: &str, color: &str) -> Result<Self> {
+        self.set(role, parse_color(color)?)?;
+        Ok(self)
+    }
+}
+
+impl Default for ChonkerTheme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// "#81a2be", "red", "dark_grey", ...
+pub fn parse_color(text: &str) -> Result<Color> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix('#') {
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok());
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb { r, g, b }),
+            _ => Err(anyhow!("'{}' isn't a #rrggbb color", text)),
+        };
+    }
+    Ok(match text.to_lowercase().replace(['-', ' '], "_").as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        "grey" | "gray" => Color::Grey,
+        "dark_grey" | "dark_gray" => Color::DarkGrey,
+        "dark_red" => Color::DarkRed,
+        "dark_green" => Color::DarkGreen,
+        "dark_yellow" => Color::DarkYellow,
+        "dark_blue" => Color::DarkBlue,
+        "dark_magenta" => Color::DarkMagenta,
+        "dark_cyan" => Color::DarkCyan,
+        "default" | "reset" => Color::Reset,
+        _ => bail!("unknown color '{}' (#rrggbb or a name like red, dark_grey)", text),
+    })
+}
+
+static CURRENT: RwLock<Option<ChonkerTheme>> = RwLock::new(None);
+
+/// The theme in use; dark until one is set
+pub fn current() -> ChonkerTheme {
+    CURRENT.read().unwrap_or_else(|e| e.into_inner()).unwrap_or_default()
+}
+
+/// Switch every view to `theme`, e.g. after ui.toml changed
+pub fn set_current(theme: ChonkerTheme) {
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = Some(theme);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_palette_overrides_its_base() {
+        let mut palettes = BTreeMap::new();
+        let colors = [("error", "#dc322f"), ("accent", "dark-cyan")].map(|(role, color)| (role.to_string(), color.to_string()));
+        palettes.insert("solarized".to_string(), PaletteConfig { base: Some("light".to_string()), colors: colors.into() });
+
+        let theme = ChonkerTheme::resolve("solarized", &palettes).unwrap();
+        assert_eq!(theme.error, Color::Rgb { r: 0xdc, g: 0x32, b: 0x2f });
+        assert_eq!(theme.accent, Color::DarkCyan);
+        assert_eq!(theme.text, ChonkerTheme::light().text);
+
+        assert!(ChonkerTheme::resolve("missing", &palettes).is_err());
+        assert!(ChonkerTheme::dark().with("shadow", "red").is_err());
+        assert!(parse_color("#12345").is_err());
+    }
+}
@@ -0,0 +1,274 @@
+// Translation of extracted text - a local ONNX seq2seq model, or a translation API
+//
+// A model exported for a target language lives in translate-<lang>/ under the
+// models directory (encoder_model.onnx, decoder_model.onnx, tokenizer.json and
+// the model's config.json), and is used when present. Otherwise a LibreTranslate-compatible
+// API configured under [translate] in extraction.toml is called. Pages are
+// translated paragraph by paragraph: the layout grid's column gaps mean nothing
+// to a translation model, so each paragraph's lines are joined first, and the
+// translated layer is plain reflowed text.
+use anyhow::{anyhow, Result};
+use ort::session::{Session, SessionInputValue};
+use ort::value::Tensor;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokenizers::{Tokenizer, TruncationParams};
+
+use crate::pdf_extraction::models;
+
+
+/// Source tokens per paragraph; longer paragraphs are truncated by the tokenizer
+const MAX_SOURCE_TOKENS: usize = 512;
+/// Generated tokens per paragraph
+const MAX_OUTPUT_TOKENS: usize = 512;
+const API_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TranslateConfig {
+    /// LibreTranslate-compatible endpoint, e.g. http://localhost:5000
+    pub endpoint: String,
+    /// The key itself, or "env:NAME" to read it from an environment variable
+    pub api_key: Option<String>,
+    /// Source language code, or "auto" to let the service detect it
+    #[serde(default = "default_source")]
+    pub source: String,
+}
+
+fn default_source() -> String { "auto".to_string() }
+
+pub enum Translator {
+    Onnx(Box<OnnxTranslator>),
+    Api { agent: ureq::Agent, config: TranslateConfig, target: String },
+}
+
+impl Translator {
+    /// The local model for `target` if installed, else the configured API
+    pub fn load(target: &str, config: Option<&TranslateConfig>) -> Result<Self> {
+        let dir = model_dir(target);
+        if dir.join("encoder_model.onnx").exists() {
+            return Ok(Translator::Onnx(Box::new(OnnxTranslator::load(&dir)?)));
+        }
+        let config = config.ok_or_else(|| {
+            anyhow!("No translation model in {} and no [translate] endpoint in extraction.toml", dir.display())
+        })?;
+        let agent = ureq::AgentBuilder::new().timeout(API_TIMEOUT).build();
+        Ok(Translator::Api { agent, config: config.clone(), target: target.to_string() })
+    }
+
+    /// Recorded with each translated page
+    pub fn engine(&self) -> String {
+        match self {
+            Translator::Onnx(model) => format!("onnx:{}", model.model_id),
+            Translator::Api { config, .. } => format!("api:{}", config.endpoint),
+        }
+    }
+
+    /// Translate a page of grid text, paragraph by paragraph
+    pub fn translate_page(&mut self, page: &str) -> Result<String> {
+        let mut translated = Vec::new();
+        for paragraph in paragraphs(page) {
+            translated.push(self.translate(&paragraph)?);
+        }
+        Ok(translated.join("\n\n"))
+    }
+
+    fn translate(&mut self, text: &str) -> Result<String> {
+        match self {
+            Translator::Onnx(model) => model.translate(text),
+            Translator::Api { agent, config, target } => {
+                let mut body = serde_json::json!({ "q": text, "source": config.source, "target": target, "format": "text" });
+                if let Some(key) = &config.api_key {
+                    body["api_key"] = serde_json::Value::String(resolve_key(key)?);
+                }
+                let response = agent
+                    .post(&format!("{}/translate", config.endpoint.trim_end_matches('/')))
+                    .set("Content-Type", "application/json")
+                    .send_string(&body.to_string())
+                    .map_err(|e| anyhow!("Translation request failed: {}", e))?;
+                let result: serde_json::Value = serde_json::from_str(&response.into_string()?)?;
+                result["translatedText"]
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("Translation response has no translatedText: {}", result))
+            }
+        }
+    }
+}
+
+/// Where the local model for a target language is looked for
+pub fn model_dir(target: &str) -> PathBuf {
+    models::locate(&format!("translate-{}", target))
+}
+
+fn resolve_key(key: &str) -> Result<String> {
+    match key.strip_prefix("env:") {
+        Some(var) => std::env::var(var).map_err(|_| anyhow!("[translate] environment variable {} is not set", var)),
+        None => Ok(key.to_string()),
+    }
+}
+
+/// Encoder-decoder model (Marian, M2M100, NLLB) exported to ONNX without a
+/// KV cache, decoded greedily
+pub struct OnnxTranslator {
+    encoder: Session,
+    decoder: Session,
+    tokenizer: Tokenizer,
+    model_id: String,
+    decoder_start: i64,
+    eos: i64,
+    /// Target-language token multilingual models must start their output with
+    forced_bos: Option<i64>,
+}
+
+impl OnnxTranslator {
+    pub fn load(dir: &Path) -> Result<Self> {
+        let _ = ort::init();
+        let session = |file: &str| -> Result<Session> {
+            Ok(Session::builder()?
+                .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
+                .with_intra_threads(4)?
+                .commit_from_file(dir.join(file))?)
+        };
+        let tokenizer_path = dir.join("tokenizer.json");
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|e| anyhow!("Loading {}: {}", tokenizer_path.display(), e))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams { max_length: MAX_SOURCE_TOKENS, ..Default::default() }))
+            .map_err(|e| anyhow!("Tokenizer truncation: {}", e))?;
+
+        let config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("config.json"))?)?;
+        let token = |key: &str| config[key].as_i64();
+        let eos = token("eos_token_id").ok_or_else(|| anyhow!("{}/config.json has no eos_token_id", dir.display()))?;
+        Ok(Self {
+            encoder: session("encoder_model.onnx")?,
+            decoder: session("decoder_model.onnx")?,
+            tokenizer,
+            model_id: dir.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+            decoder_start: token("decoder_start_token_id").unwrap_or(eos),
+            eos,
+            forced_bos: token("forced_bos_token_id"),
+        })
+    }
+
+    fn translate(&mut self, text: &str) -> Result<String> {
+        let encoding = self.tokenizer.encode(text, true).map_err(|e| anyhow!("Tokenizing: {}", e))?;
+        let ids: Vec<i64> = encoding.get_ids().iter().map(|&id| id as i64).collect();
+        let mask: Vec<i64> = encoding.get_attention_mask().iter().map(|&m| m as i64).collect();
+        let tokens = ids.len();
+
+        let encoder_inputs: Vec<(String, SessionInputValue)> = vec![
+            ("input_ids".to_string(), Tensor::from_array(([1usize, tokens], ids.into_boxed_slice()))?.into()),
+            ("attention_mask".to_string(), Tensor::from_array(([1usize, tokens], mask.clone().into_boxed_slice()))?.into()),
+        ];
+        let encoded = self.encoder.run(encoder_inputs)?;
+        let (shape, states) = encoded[0].try_extract_tensor::<f32>()?;
+        let hidden = shape[2] as usize;
+        let states = states.to_vec();
+        drop(encoded);
+
+        let mut output: Vec<i64> = vec![self.decoder_start];
+        output.extend(self.forced_bos);
+        while output.len() < MAX_OUTPUT_TOKENS {
+            let mut inputs: Vec<(String, SessionInputValue)> = Vec::new();
+            for input in &self.decoder.inputs {
+                let value: SessionInputValue = match input.name.as_str() {
+                    "input_ids" => Tensor::from_array(([1usize, output.len()], output.clone().into_boxed_slice()))?.into(),
+                    "encoder_attention_mask" => Tensor::from_array(([1usize, tokens], mask.clone().into_boxed_slice()))?.into(),
+                    "encoder_hidden_states" => Tensor::from_array(([1usize, tokens, hidden], states.clone().into_boxed_slice()))?.into(),
+                    other => return Err(anyhow!("Translation decoder wants an unsupported input '{}'", other)),
+                };
+                inputs.push((input.name.clone(), value));
+            }
+            let outputs = self.decoder.run(inputs)?;
+            let (shape, logits) = outputs[0].try_extract_tensor::<f32>()?;
+            let vocab = shape[2] as usize;
+            let next = argmax(&logits[logits.len() - vocab..]) as i64;
+            if next == self.eos {
+                break;
+            }
+            output.push(next);
+        }
+
+        let ids: Vec<u32> = output.iter().map(|&id| id as u32).collect();
+        self.tokenizer.decode(&ids, true).map_err(|e| anyhow!("Decoding: {}", e))
+    }
+}
+
+fn argmax(values: &[f32]) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .fold((0, f32::MIN), |best, (i, &v)| if v > best.1 { (i, v) } else { best })
+        .0
+}
+
+/// A page's paragraphs as single lines: blank lines separate paragraphs, and the
+/// layout's runs of spaces collapse to one
+pub fn paragraphs(page: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in page.lines().map(str::trim).chain(std::iter::once("")) {
+        if line.is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(current.join(" ").split_whitespace().collect::<Vec<_>>().join(" "));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    paragraphs
+}
+
+/// Word-wrap text to `width` columns, keeping its line breaks
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The original page on the left, its translation wrapped on the right
+pub fn side_by_side(original: &str, translated: &str, width: usize) -> String {
+    let left: Vec<String> = original.lines().map(|line| line.trim_end().chars().take(width).collect()).collect();
+    let right = wrap(translated, width);
+    (0..left.len().max(right.len()))
+        .map(|i| {
+            let l = left.get(i).map(String::as_str).unwrap_or("");
+            let r = right.get(i).map(String::as_str).unwrap_or("");
+            format!("{:<width$} │ {}", l, r, width = width).trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paragraphs_and_side_by_side() {
+        let page = "Rechnung      Nr. 42\n   Bitte zahlen Sie\n innerhalb von 14 Tagen.\n\n\nVielen Dank";
+        assert_eq!(
+            paragraphs(page),
+            vec!["Rechnung Nr. 42 Bitte zahlen Sie innerhalb von 14 Tagen.", "Vielen Dank"]
+        );
+
+        assert_eq!(wrap("Please pay within 14 days.\n\nThank you", 12), vec!["Please pay", "within 14", "days.", "", "Thank you"]);
+        assert_eq!(
+            side_by_side("Rechnung\nBitte zahlen", "Invoice please pay", 12),
+            "Rechnung     │ Invoice\nBitte zahlen │ please pay"
+        );
+    }
+}
@@ -1,7 +1,10 @@
 // Hot-reloadable UI configuration structures
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::PathBuf;
 use anyhow::Result;
+use chonker8::theme::{ChonkerTheme, PaletteConfig};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UIConfig {
@@ -11,6 +14,13 @@ pub struct UIConfig {
     pub panels: PanelsConfig,
     #[serde(default)]
     pub hotkeys: HotkeyConfig,
+    #[serde(default)]
+    pub updates: UpdatesConfig,
+    #[serde(default)]
+    pub picker: PickerConfig,
+    /// User-defined palettes, chosen by [theme] palette
+    #[serde(default)]
+    pub palettes: BTreeMap<String, PaletteConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -22,15 +32,23 @@ pub struct LayoutConfig {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ThemeConfig {
+    /// dark, light or a name under [palettes]
+    #[serde(default = "default_palette")]
+    pub palette: String,
     pub border: String,
-    pub highlight: String,
+    /// Overrides the palette's accent (titles and frames)
+    #[serde(default)]
+    pub highlight: Option<String>,
     pub background: String,
-    pub text_color: String,
+    /// Overrides the palette's text color
+    #[serde(default)]
+    pub text_color: Option<String>,
     #[serde(default = "default_true")]
     pub clear_on_resize: bool,
 }
 
 fn default_true() -> bool { true }
+fn default_palette() -> String { "dark".to_string() }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PanelsConfig {
@@ -43,6 +61,9 @@ pub struct PdfPanelConfig {
     pub width_percent: f32,
     pub show_page_num: bool,
     pub show_scroll_bar: bool,
+    /// Scroll the page image along with the text pane, showing the rows in view
+    #[serde(default)]
+    pub sync_scroll: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -53,6 +74,50 @@ pub struct TextPanelConfig {
     pub line_numbers: bool,
 }
 
+/// The status bar's notice when a newer release is out
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UpdatesConfig {
+    /// Look at the release feed (at most once a day) when the TUI starts
+    #[serde(default = "default_true")]
+    pub check: bool,
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self { check: true }
+    }
+}
+
+/// Where the file picker looks for PDFs
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PickerConfig {
+    /// Directories searched recursively, in order; ~ is the home directory
+    #[serde(default = "default_roots")]
+    pub roots: Vec<String>,
+}
+
+impl Default for PickerConfig {
+    fn default() -> Self {
+        Self { roots: default_roots() }
+    }
+}
+
+impl PickerConfig {
+    pub fn search_roots(&self) -> Vec<PathBuf> {
+        self.roots
+            .iter()
+            .map(|root| match (root.strip_prefix('~'), dirs::home_dir()) {
+                (Some(rest), Some(home)) => home.join(rest.trim_start_matches('/')),
+                _ => PathBuf::from(root),
+            })
+            .collect()
+    }
+}
+
+fn default_roots() -> Vec<String> {
+    ["~/Downloads", "~/Desktop", "~/Documents", "."].map(String::from).to_vec()
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct HotkeyConfig {
     #[serde(default = "default_quit")]
@@ -109,26 +174,15 @@ impl UIConfig {
         }
     }
     
-    pub fn get_highlight_color(&self) -> crossterm::style::Color {
-        use crossterm::style::Color;
-        match self.theme.highlight.as_str() {
-            "yellow" => Color::Yellow,
-            "green" => Color::Green,
-            "blue" => Color::Blue,
-            "red" => Color::Red,
-            "magenta" => Color::Magenta,
-            "cyan" => Color::Cyan,
-            _ => Color::White,
+    /// The palette [theme] picks, with its highlight and text_color on top
+    pub fn theme(&self) -> Result<ChonkerTheme> {
+        let mut theme = ChonkerTheme::resolve(&self.theme.palette, &self.palettes)?;
+        if let Some(highlight) = &self.theme.highlight {
+            theme = theme.with("accent", highlight)?;
         }
-    }
-    
-    pub fn get_text_color(&self) -> crossterm::style::Color {
-        use crossterm::style::Color;
-        match self.theme.text_color.as_str() {
-            "white" => Color::White,
-            "grey" | "gray" => Color::Grey,
-            "black" => Color::Black,
-            _ => Color::White,
+        if let Some(text_color) = &self.theme.text_color {
+            theme = theme.with("text", text_color)?;
         }
+        Ok(theme)
     }
-}
\ No newline at end of file
+}
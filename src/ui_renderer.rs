@@ -8,16 +8,108 @@ use crossterm::{
     terminal::{self, Clear, ClearType},
 };
 use std::io::{self, stdout, Write};
+use std::ops::Range;
 use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use image::DynamicImage;
-use chonker8::integrated_file_picker::IntegratedFilePicker;
-use chonker8::{pdf_renderer, content_extractor};
-use chonker8::kitty_protocol::KittyProtocol;
+use chonker8::integrated_file_picker::{IntegratedFilePicker, RECENT_LIMIT};
+use chonker8::{pdf_renderer, content_extractor, reflow};
+use chonker8::graphics::{self, CellArea, GraphicsBackend};
+use chonker8::render_cache::{self, RenderCache, RenderKey};
+use chonker8::embeddings::Embedder;
+use chonker8::storage::{spawn_index_worker, DuckDBStorage, EditConflict, PageGrid, DEFAULT_DB_PATH};
+use chonker8::coords::{GridCell, GridSize, RowMapping, ScreenCell};
+use chonker8::pdf_extraction::document_analyzer::displayed_page_dimensions;
+use crate::config::{GRID_HEIGHT, GRID_WIDTH};
+use chonker8::clipboard;
+use chonker8::self_update::{self, Version};
+use chonker8::theme;
+use chonker8::translate;
+use chonker8::views::page_overview::{self, PageOverview};
+use chonker8::views::text_editor::{
+    block_text, diff_lines, diff_stats, edit_between, grid_lines, merge3, replay, DiffLine, EditEvent, EditPanelRenderer, RowMerge,
+};
+
+/// Size the left panel's page images are rendered at
+const PAGE_RENDER_SIZE: (u32, u32) = (800, 1000);
+
+/// Image and text for a page, produced off the UI thread
+struct LoadedPage {
+    page: usize, // 1-based
+    image: Result<Arc<DynamicImage>, String>,
+    text: String,
+}
+
+/// Which version of the page the text panel shows; only Edited takes edits
+#[derive(Debug, Clone, PartialEq)]
+enum PageView {
+    Edited,
+    Raw,
+    /// A stored translation, by language (see `chonker8 translate`)
+    Translated(String),
+}
+
+/// A side of the PDF viewer, for giving it the whole width
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pane {
+    Pdf,
+    Text,
+}
+
+/// How far the split between the panes can move, in percent of the width from the left
+const SPLIT_RANGE: (f32, f32) = (20.0, 80.0);
+/// Percent one Ctrl+Left/Right moves the split by
+pub const SPLIT_STEP: f32 = 5.0;
+/// Text rows one mouse wheel step scrolls by while the panes scroll together
+const SYNC_SCROLL_ROWS: isize = 3;
+/// A second click on the same cell within this long selects the word there
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Column the translated text is wrapped at
+const TRANSLATION_WIDTH: usize = 80;
+
+/// Edits refused because the page changed elsewhere, waiting on a choice in the merge view
+struct PageConflict {
+    /// In the conflict audit log
+    id: i64,
+    path: PathBuf,
+    page: usize,
+    /// The page as it was when editing started
+    base: Vec<Vec<char>>,
+    ours: Vec<Vec<char>>,
+    /// The page as the log has it now, at `version`
+    theirs: Vec<Vec<char>>,
+    version: i64,
+}
+
+/// How the merge view settles a conflict
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictChoice {
+    Mine,
+    Theirs,
+    /// Both sides' changes, mine where they clash
+    Merged,
+}
+
+impl ConflictChoice {
+    fn name(self) -> &'static str {
+        match self {
+            ConflictChoice::Mine => "mine",
+            ConflictChoice::Theirs => "theirs",
+            ConflictChoice::Merged => "merged",
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Screen {
     FilePicker,
     PdfViewer,
+    /// Thumbnails of every page of the open PDF (Ctrl+O from the viewer)
+    Overview,
+    Diff,
     Debug,
 }
 
@@ -33,7 +125,15 @@ pub struct UIRenderer {
     available_screens: Vec<Screen>,
     file_picker: Option<IntegratedFilePicker>,
     current_pdf_path: Option<PathBuf>,
-    current_pdf_image: Option<DynamicImage>,
+    current_pdf_image: Option<Arc<DynamicImage>>,
+    render_cache: RenderCache,
+    /// Page thumbnails for the overview, kept apart so they don't evict full pages
+    thumbnail_cache: RenderCache,
+    overview: PageOverview,
+    /// Visible thumbnails that were rendered at the last draw of the overview
+    overview_ready: usize,
+    /// Background load for the page being navigated to; replacing it drops a stale load's result
+    page_load: Option<Receiver<LoadedPage>>,
     dark_mode: bool,
     extraction_method: Option<String>,
     extraction_quality: Option<f32>,
@@ -41,36 +141,69 @@ pub struct UIRenderer {
     debug_messages: Vec<String>,
     debug_scroll_offset: usize,
     debug_messages_loaded: bool,
-    kitty: KittyProtocol,
-    current_image_id: Option<u32>,
-    image_sent: bool,
+    graphics: Box<dyn GraphicsBackend>,
+    image_dirty: bool,
+    editor: EditPanelRenderer,
+    /// Where edits are logged, shared with `chonker8 edits`; None if the database can't be opened
+    edit_log: Option<DuckDBStorage>,
+    /// Log version the editor's page was loaded at; saves are checked against it
+    edit_version: i64,
+    conflict: Option<PageConflict>,
+    /// Ctrl+R shows the page as extracted, Ctrl+T its translations
+    page_view: PageView,
+    diff_scroll_offset: usize,
+    /// Columns per inch when the grid is sized to each page; None keeps the fixed grid
+    auto_grid: Option<f32>,
+    /// A pane given the whole width for now; the split comes back when it's toggled off
+    maximized: Option<Pane>,
+    /// The slice of the page on show while scrolling is synced, and the pixel rows it covers
+    synced_band: Option<(Range<u32>, DynamicImage)>,
+    /// When and where the text panel was last clicked, to spot double clicks
+    last_click: Option<(Instant, GridCell)>,
+    /// The startup look at the release feed, until it answers
+    update_check: Option<Receiver<Option<Version>>>,
+    /// A newer release, announced in the status bar
+    available_update: Option<Version>,
 }
 
 impl UIRenderer {
     pub fn new(config: UIConfig) -> Self {
-        // Initialize the file picker
-        let file_picker = match IntegratedFilePicker::new() {
-            Ok(picker) => Some(picker),
+        let edit_log = match DuckDBStorage::new(Some(std::path::Path::new(DEFAULT_DB_PATH))) {
+            Ok(storage) => {
+                // Edited pages become searchable (and re-embedded) as they are saved
+                spawn_index_worker(|| {
+                    let mut storage = DuckDBStorage::new(Some(std::path::Path::new(DEFAULT_DB_PATH)))?;
+                    storage.set_embedder(Embedder::load_default().unwrap_or_default());
+                    Ok(storage)
+                });
+                Some(storage)
+            }
             Err(e) => {
-                eprintln!("Warning: Failed to initialize file picker: {}", e);
+                eprintln!("Warning: Edits won't be saved, {} can't be opened: {}", DEFAULT_DB_PATH, e);
                 None
             }
         };
         
-        let mut kitty = KittyProtocol::new();
-        
-        // FORCE ENABLE KITTY FOR TESTING
-        kitty.force_enable();
-        eprintln!("[KITTY] *** FORCE-ENABLED KITTY PROTOCOL FOR TESTING ***");
+        let file_picker = Some(Self::open_file_picker(&config, edit_log.as_ref()));
         
-        // Kitty is MANDATORY for this viewer
-        if kitty.is_supported() {
-            eprintln!("[DEBUG] ✅ Kitty graphics protocol ACTIVE!");
-        } else {
-            eprintln!("[WARNING] ⚠️  Kitty not detected - PDF images require Kitty terminal!");
-            eprintln!("[WARNING] Run with: kitty ./target/release/chonker8-hot [pdf]");
+        match config.theme() {
+            Ok(palette) => theme::set_current(palette),
+            Err(e) => eprintln!("Warning: Using the dark palette, ui.toml's theme is invalid: {}", e),
         }
         
+        // Off the UI thread - the feed may be slow or unreachable
+        let update_check = config.updates.check.then(|| {
+            let (sender, receiver) = mpsc::channel();
+            std::thread::spawn(move || {
+                let _ = sender.send(self_update::newer_release_cached());
+            });
+            receiver
+        });
+        
+        let protocol = graphics::detect();
+        eprintln!("[GRAPHICS] Using {} for the PDF panel (override with {}=kitty|iterm2|sixel|halfblock)",
+            protocol.name(), graphics::GRAPHICS_ENV);
+        
         Self {
             config,
             pdf_content: vec![vec![' '; 80]; 24], // Default empty content
@@ -80,10 +213,15 @@ impl UIRenderer {
             cursor_x: 0,
             cursor_y: 0,
             current_screen: Screen::FilePicker,
-            available_screens: vec![Screen::FilePicker, Screen::PdfViewer, Screen::Debug],
+            available_screens: vec![Screen::FilePicker, Screen::PdfViewer, Screen::Diff, Screen::Debug],
             file_picker,
             current_pdf_path: None,
             current_pdf_image: None,
+            render_cache: RenderCache::new(render_cache::DEFAULT_CAPACITY, Self::render_page),
+            thumbnail_cache: RenderCache::new(page_overview::THUMBNAIL_CAPACITY, Self::render_page),
+            overview: PageOverview::new(1, 1),
+            overview_ready: 0,
+            page_load: None,
             dark_mode: false,
             extraction_method: None,
             extraction_quality: None,
@@ -91,18 +229,283 @@ impl UIRenderer {
             debug_messages: Vec::new(),
             debug_scroll_offset: 0,
             debug_messages_loaded: false,
-            kitty,
-            current_image_id: None,
-            image_sent: false,
+            graphics: graphics::backend(protocol),
+            image_dirty: true,
+            editor: EditPanelRenderer::new(),
+            edit_log,
+            edit_version: 0,
+            conflict: None,
+            page_view: PageView::Edited,
+            diff_scroll_offset: 0,
+            auto_grid: None,
+            maximized: None,
+            synced_band: None,
+            last_click: None,
+            update_check,
+            available_update: None,
         }
     }
     
+    /// A picker indexing the configured roots, with pinned and recent files from storage
+    fn open_file_picker(config: &UIConfig, storage: Option<&DuckDBStorage>) -> IntegratedFilePicker {
+        let mut picker = IntegratedFilePicker::new(config.picker.search_roots());
+        if let Some(storage) = storage {
+            let pinned = storage.pinned_files().unwrap_or_default();
+            let recent = storage.recent_files(RECENT_LIMIT).unwrap_or_default();
+            picker.set_shortcuts(&pinned, &recent);
+        }
+        picker
+    }
+    
+    fn render_page(key: &RenderKey) -> Result<DynamicImage> {
+        let image = pdf_renderer::render_pdf_page(&key.path, key.page, key.width, key.height)?;
+        Ok(if key.dark_mode { Self::apply_dark_mode_filter(image) } else { image })
+    }
+    
+    /// Size the text grid to each page's shape, `cols_per_inch` across, instead of the fixed grid
+    pub fn set_auto_grid(&mut self, cols_per_inch: Option<f32>) {
+        self.auto_grid = cols_per_inch;
+    }
+    
+    /// The grid a page (1-based) is laid out in. With auto-grid every page's size is
+    /// read once and kept in the database with the grid chosen for it.
+    fn grid_size(&mut self, path: &std::path::Path, page: usize) -> GridSize {
+        let fixed = GridSize::new(GRID_WIDTH, GRID_HEIGHT);
+        let Some(cols_per_inch) = self.auto_grid else {
+            return fixed;
+        };
+        let key = path.to_string_lossy().to_string();
+        let stored = self.edit_log.as_ref().and_then(|storage| storage.page_grid(&key, page).ok().flatten());
+        let sizes = match stored {
+            Some(stored) => vec![(stored.page_width, stored.page_height)],
+            None => match Self::page_sizes(path) {
+                Ok(sizes) => sizes,
+                Err(e) => {
+                    self.add_debug_message(format!("Page sizes unknown, using the fixed grid: {}", e));
+                    return fixed;
+                }
+            },
+        };
+        let first_page = if stored.is_some() { page } else { 1 };
+        let grids: Vec<PageGrid> = sizes
+            .into_iter()
+            .map(|(page_width, page_height)| PageGrid { page_width, page_height, grid: GridSize::for_page(page_width, page_height, cols_per_inch) })
+            .collect();
+        // Already stored, unless --cols-per-inch changed since
+        if stored.is_none_or(|stored| grids.first().is_some_and(|grid| grid.grid != stored.grid)) {
+            let result = self.edit_log.as_ref().map(|storage| {
+                grids.iter().enumerate().try_for_each(|(index, grid)| storage.store_page_grid(&key, first_page + index, grid))
+            });
+            if let Some(Err(e)) = result {
+                self.add_debug_message(format!("Failed to store page grids: {}", e));
+            }
+        }
+        grids.get(page - first_page).map_or(fixed, |grid| grid.grid)
+    }
+    
+    /// Displayed size in points of every page, rotation applied
+    fn page_sizes(path: &std::path::Path) -> Result<Vec<(f32, f32)>> {
+        let document = lopdf::Document::load(path)?;
+        (0..document.get_pages().len()).map(|index| displayed_page_dimensions(&document, index)).collect()
+    }
+    
     pub fn update_config(&mut self, config: UIConfig) {
+        // A palette with a typo keeps the colors on screen rather than falling back
+        match config.theme() {
+            Ok(palette) => theme::set_current(palette),
+            Err(e) => self.add_debug_message(format!("[THEME] ERROR: Keeping the current palette: {}", e)),
+        }
+        if !config.updates.check {
+            self.available_update = None;
+        }
+        if config.picker != self.config.picker {
+            self.file_picker = Some(Self::open_file_picker(&config, self.edit_log.as_ref()));
+        }
         self.config = config;
     }
     
     pub fn set_pdf_content(&mut self, content: Vec<Vec<char>>) {
         self.pdf_content = content;
+        self.diff_scroll_offset = 0;
+        // Paging keeps the version being viewed
+        self.show_view(self.page_view.clone());
+    }
+    
+    /// Put a version of the current page in the text panel
+    fn show_view(&mut self, view: PageView) {
+        match &view {
+            PageView::Edited => {
+                self.editor.set_buffer(self.pdf_content.clone());
+                self.restore_edits();
+            }
+            PageView::Raw => self.editor.set_buffer(self.pdf_content.clone()),
+            PageView::Translated(lang) => {
+                let text = match (&self.edit_log, &self.current_pdf_path) {
+                    (Some(storage), Some(path)) => storage.translation(&path.to_string_lossy(), self.current_page, lang),
+                    _ => Ok(None),
+                };
+                let text = match text {
+                    Ok(Some(text)) => text,
+                    Ok(None) => format!("(page {} has no {} translation)", self.current_page, lang),
+                    Err(e) => format!("(failed to read the translation: {})", e),
+                };
+                let grid = translate::wrap(&text, TRANSLATION_WIDTH).iter().map(|line| line.chars().collect()).collect();
+                self.editor.set_buffer(grid);
+            }
+        }
+        self.page_view = view;
+    }
+    
+    /// Switch between the edited page and the raw extraction
+    fn toggle_raw(&mut self) {
+        self.save_edits();
+        let view = if self.page_view == PageView::Raw { PageView::Edited } else { PageView::Raw };
+        self.show_view(view);
+    }
+    
+    /// Step through the document's translations, then back to the edited page
+    fn cycle_translation(&mut self) {
+        let (Some(storage), Some(path)) = (&self.edit_log, &self.current_pdf_path) else {
+            return;
+        };
+        let langs = match storage.translated_languages(&path.to_string_lossy()) {
+            Ok(langs) => langs,
+            Err(e) => {
+                self.add_debug_message(format!("Failed to read translations: {}", e));
+                return;
+            }
+        };
+        if langs.is_empty() {
+            self.add_debug_message("No translations - run `chonker8 translate <pdf> --to <lang>` first".to_string());
+            return;
+        }
+        let next = match &self.page_view {
+            PageView::Translated(lang) => langs.iter().position(|l| l == lang).and_then(|i| langs.get(i + 1)),
+            _ => langs.first(),
+        };
+        let view = next.map_or(PageView::Edited, |lang| PageView::Translated(lang.clone()));
+        self.save_edits();
+        self.show_view(view);
+    }
+    
+    /// Whether the page shown differs from its raw extraction
+    fn is_modified(&self) -> bool {
+        self.page_view == PageView::Edited && *self.editor.buffer() != self.pdf_content
+    }
+    
+    /// A page edited before (here or from the CLI) opens as its edit log left it,
+    /// undo history included
+    fn restore_edits(&mut self) {
+        let (Some(storage), Some(path)) = (&self.edit_log, &self.current_pdf_path) else {
+            return;
+        };
+        match storage.edit_log(&path.to_string_lossy(), self.current_page) {
+            Ok(log) => {
+                self.edit_version = log.last().map_or(0, |logged| logged.seq);
+                if !log.is_empty() {
+                    let events: Vec<_> = log.into_iter().map(|logged| logged.event).collect();
+                    self.editor.load_events(&events);
+                }
+            }
+            Err(e) => self.add_debug_message(format!("Failed to read edit log: {}", e)),
+        }
+    }
+    
+    /// Append the editor's new events to the page's edit log, unless someone
+    /// else has changed the page since it was loaded - then open the merge view
+    fn save_edits(&mut self) {
+        let events = self.editor.take_events();
+        if events.is_empty() {
+            return;
+        }
+        // Edits made while a conflict on this page is open are settled with it
+        if let Some(conflict) = &mut self.conflict {
+            if Some(&conflict.path) == self.current_pdf_path.as_ref() && conflict.page == self.current_page {
+                conflict.ours = self.editor.buffer().clone();
+                return;
+            }
+        }
+        let (Some(storage), Some(path)) = (&mut self.edit_log, &self.current_pdf_path) else {
+            return;
+        };
+        match storage.append_edit_events_at(&path.to_string_lossy(), self.current_page, "tui", self.edit_version, &events) {
+            Ok(version) => self.edit_version = version,
+            Err(e) => match e.downcast::<EditConflict>() {
+                Ok(conflict) => {
+                    let (path, page) = (path.clone(), self.current_page);
+                    let base = self.page_at(&path, page, conflict.expected);
+                    let ours = self.editor.buffer().clone();
+                    self.open_conflict(conflict, path, page, base, ours);
+                }
+                Err(e) => self.add_debug_message(format!("Failed to save edits: {}", e)),
+            },
+        }
+    }
+    
+    /// A page's grid as its log stood at `version`; the raw extraction before any edit
+    fn page_at(&self, path: &std::path::Path, page: usize, version: i64) -> Vec<Vec<char>> {
+        let log = match &self.edit_log {
+            Some(storage) => storage.edit_log(&path.to_string_lossy(), page).unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let events: Vec<EditEvent> = log.into_iter().filter(|logged| logged.seq <= version).map(|logged| logged.event).collect();
+        if events.is_empty() {
+            self.pdf_content.clone()
+        } else {
+            replay(&events).0
+        }
+    }
+    
+    fn open_conflict(&mut self, conflict: EditConflict, path: PathBuf, page: usize, base: Vec<Vec<char>>, ours: Vec<Vec<char>>) {
+        let theirs = self.page_at(&path, page, conflict.actual);
+        self.add_debug_message(format!("{} - choose in the merge view", conflict));
+        self.conflict = Some(PageConflict { id: conflict.id, path, page, base, ours, theirs, version: conflict.actual });
+        self.diff_scroll_offset = 0;
+        self.set_screen(Screen::Diff);
+    }
+    
+    pub fn has_conflict(&self) -> bool {
+        self.conflict.is_some()
+    }
+    
+    /// Write the chosen version over the page's current log and record the choice
+    pub fn resolve_conflict(&mut self, choice: ConflictChoice) {
+        let Some(conflict) = self.conflict.take() else { return };
+        let Some(storage) = &mut self.edit_log else { return };
+        let target = match choice {
+            ConflictChoice::Mine => conflict.ours.clone(),
+            ConflictChoice::Theirs => conflict.theirs.clone(),
+            ConflictChoice::Merged => merge3(&conflict.base, &conflict.ours, &conflict.theirs).grid,
+        };
+        let path = conflict.path.to_string_lossy().to_string();
+        if let Some(command) = edit_between(&conflict.theirs, &target) {
+            let event = EditEvent::Edit { command, merged: false };
+            match storage.append_edit_events_at(&path, conflict.page, "tui", conflict.version, &[event]) {
+                Ok(_) => {}
+                Err(e) => {
+                    match e.downcast::<EditConflict>() {
+                        // Changed yet again while the merge view was open
+                        Ok(again) => {
+                            let _ = storage.resolve_edit_conflict(conflict.id, "superseded");
+                            self.open_conflict(again, conflict.path, conflict.page, conflict.base, conflict.ours);
+                        }
+                        Err(e) => {
+                            self.add_debug_message(format!("Failed to save the resolution: {}", e));
+                            self.conflict = Some(conflict);
+                        }
+                    }
+                    return;
+                }
+            }
+        }
+        if let Err(e) = storage.resolve_edit_conflict(conflict.id, choice.name()) {
+            self.add_debug_message(format!("Failed to record the resolution: {}", e));
+        }
+        self.add_debug_message(format!("Conflict #{} on page {} resolved: {}", conflict.id, conflict.page, choice.name()));
+        if Some(&conflict.path) == self.current_pdf_path.as_ref() && conflict.page == self.current_page && self.page_view == PageView::Edited {
+            self.show_view(PageView::Edited);
+        }
+        self.set_screen(Screen::PdfViewer);
     }
     
     pub fn set_total_pages(&mut self, total: usize) {
@@ -121,7 +524,7 @@ impl UIRenderer {
         if let Ok(mut file) = std::fs::OpenOptions::new()
             .create(true)
             .append(true)
-            .open("/tmp/chonker8_debug.log")
+            .open(chonker8::debug_log::TUI_LOG)
         {
             use std::io::Write;
             let _ = writeln!(file, "[{}] [RUNTIME] {}", 
@@ -137,7 +540,7 @@ impl UIRenderer {
     
     pub fn load_debug_log(&mut self) {
         // Read any new messages from the debug log file
-        if let Ok(contents) = std::fs::read_to_string("/tmp/chonker8_debug.log") {
+        if let Ok(contents) = std::fs::read_to_string(chonker8::debug_log::TUI_LOG) {
             for line in contents.lines() {
                 // Check if we already have this message (avoid duplicates)
                 if !self.debug_messages.contains(&line.to_string()) {
@@ -157,34 +560,35 @@ impl UIRenderer {
     
     fn get_message_color(&self, message: &str) -> Color {
         // Simple syntax highlighting based on message content
+        let theme = theme::current();
         if message.contains("ERROR") || message.contains("failed") || message.contains("error:") {
-            Color::Red
+            theme.error
         } else if message.contains("WARNING") || message.contains("warning:") {
-            Color::Yellow
+            theme.warning
         } else if message.contains("SUCCESS") || message.contains("successful") || message.contains("complete") {
-            Color::Green
-        } else if message.contains("[EXTRACTION]") || message.contains("[RUNTIME]") {
-            Color::Cyan
-        } else if message.contains("[BUILD]") {
-            Color::Blue
+            theme.success
+        } else if message.contains("[EXTRACTION]") || message.contains("[RUNTIME]") || message.contains("[BUILD]") {
+            theme.accent
         } else {
-            Color::White
+            theme.text
         }
     }
     
     pub fn render(&mut self) -> Result<()> {
-        eprintln!("[DEBUG] render() called, current_screen: {:?}", self.current_screen);
-        eprintln!("[DEBUG] Has PDF image: {}", self.current_pdf_image.is_some());
-        eprintln!("[DEBUG] PDF path: {:?}", self.current_pdf_path);
+        chonker8::debug_log_throttled!("DEBUG", "render() called, current_screen: {:?}", self.current_screen);
+        chonker8::debug_log_throttled!("DEBUG", "Has PDF image: {}", self.current_pdf_image.is_some());
+        chonker8::debug_log_throttled!("DEBUG", "PDF path: {:?}", self.current_pdf_path);
         let result = match self.current_screen {
             Screen::FilePicker => self.render_file_picker_screen(),
             Screen::PdfViewer => {
-                eprintln!("[DEBUG] Calling render_pdf_screen()");
+                chonker8::debug_log_throttled!("DEBUG", "Calling render_pdf_screen()");
                 self.render_pdf_screen()
             },
+            Screen::Overview => self.render_overview_screen(),
+            Screen::Diff => self.render_diff_screen(),
             Screen::Debug => self.render_debug_screen(),
         };
-        eprintln!("[DEBUG] render() complete, result: {:?}", result.is_ok());
+        chonker8::debug_log_throttled!("DEBUG", "render() complete, result: {:?}", result.is_ok());
         result
     }
     
@@ -192,6 +596,8 @@ impl UIRenderer {
         match self.current_screen {
             Screen::FilePicker => self.render_integrated_file_picker_screen(file_picker),
             Screen::PdfViewer => self.render_pdf_screen(),
+            Screen::Overview => self.render_overview_screen(),
+            Screen::Diff => self.render_diff_screen(),
             Screen::Debug => self.render_debug_screen(),
         }
     }
@@ -209,7 +615,7 @@ impl UIRenderer {
                 stdout(),
                 Clear(ClearType::All),
                 MoveTo(0, 0),
-                SetForegroundColor(crossterm::style::Color::Yellow),
+                SetForegroundColor(theme::current().warning),
                 Print("⚠️ File picker not available - using fallback"),
                 ResetColor,
                 MoveTo(0, 2),
@@ -228,11 +634,13 @@ impl UIRenderer {
     }
     
     fn render_pdf_screen(&mut self) -> Result<()> {
-        eprintln!("[DEBUG] render_pdf_screen called");
+        chonker8::debug_log_throttled!("DEBUG", "render_pdf_screen called");
         // Chonker7-style split view: PDF image on left, text extraction on right
         let (width, height) = terminal::size()?;
-        let split_x = width / 2;
-        eprintln!("[DEBUG] Terminal size: {}x{}, split at {}", width, height, split_x);
+        let split_x = self.split_column(width);
+        let (show_pdf, show_text) = (split_x > 0, split_x < width);
+        let theme = theme::current();
+        chonker8::debug_log_throttled!("DEBUG", "Terminal size: {}x{}, split at {}", width, height, split_x);
         
         execute!(
             stdout(),
@@ -242,95 +650,132 @@ impl UIRenderer {
         )?;
         
         // Draw a clear vertical split line first
-        execute!(stdout(), SetForegroundColor(Color::Cyan))?;
-        for y in 0..height - 1 {
-            execute!(stdout(), MoveTo(split_x, y), Print("│"))?;
+        if show_pdf && show_text {
+            execute!(stdout(), SetForegroundColor(theme.accent))?;
+            for y in 0..height - 1 {
+                execute!(stdout(), MoveTo(split_x, y), Print("│"))?;
+            }
         }
         
         // Draw panel headers
-        execute!(
-            stdout(),
-            MoveTo(2, 0),
-            SetForegroundColor(Color::Yellow),
-            SetAttributes(Attributes::from(Attribute::Bold)),
-            Print("◀ PDF RENDER (lopdf→kitty) ▶"),
-            SetAttributes(Attributes::from(Attribute::Reset))
-        )?;
-        
-        execute!(
-            stdout(),
-            MoveTo(split_x + 2, 0),
-            SetForegroundColor(Color::Green),
-            SetAttributes(Attributes::from(Attribute::Bold)),
-            Print("◀ TEXT EXTRACTION (pdftotext) ▶"),
-            SetAttributes(Attributes::from(Attribute::Reset))
-        )?;
-        
-        // Left Panel - PDF Render
-        eprintln!("[DEBUG] Rendering left panel (PDF)");
-        execute!(stdout(), SetForegroundColor(Color::White))?;
-        
-        // Show PDF status
-        let pdf_status = format!(" Page {}/{} ", self.current_page, self.total_pages);
-        execute!(
-            stdout(),
-            MoveTo(2, 1),
-            SetForegroundColor(Color::DarkYellow),
-            Print(&pdf_status),
-            SetForegroundColor(Color::White)
-        )?;
+        if show_pdf {
+            execute!(
+                stdout(),
+                MoveTo(2, 0),
+                SetForegroundColor(theme.title_pdf),
+                SetAttributes(Attributes::from(Attribute::Bold)),
+                Print(format!("◀ PDF RENDER (lopdf→{}) ▶", self.graphics.protocol().name())),
+                SetAttributes(Attributes::from(Attribute::Reset))
+            )?;
+        }
         
-        // Render PDF content or image
-        if self.current_pdf_image.is_some() {
-            eprintln!("[DEBUG] Have PDF image, attempting Kitty display");
-            let (img_w, img_h) = self.current_pdf_image.as_ref().map(|i| (i.width(), i.height())).unwrap();
-            eprintln!("[DEBUG] Image dimensions: {}x{}", img_w, img_h);
-            eprintln!("[DEBUG] Kitty supported: {}", self.kitty.is_supported());
-            eprintln!("[DEBUG] Display area: x=2, y=2, width={}, height={}", split_x - 4, height - 4);
-            
-            // Try to display the PDF image in the left panel
-            self.render_pdf_content(2, 2, split_x - 4, height - 4)?;
-            
-            // Also show some debug info on screen
+        if show_text {
             execute!(
                 stdout(),
-                MoveTo(2, height - 3),
-                SetForegroundColor(Color::DarkGrey),
-                Print(format!("PDF: {}x{}", 
-                    self.current_pdf_image.as_ref().map(|i| i.width()).unwrap_or(0),
-                    self.current_pdf_image.as_ref().map(|i| i.height()).unwrap_or(0)
-                )),
-                SetForegroundColor(Color::White)
+                MoveTo(split_x + 2, 0),
+                SetForegroundColor(theme.title_text),
+                SetAttributes(Attributes::from(Attribute::Bold)),
+                Print("◀ TEXT EXTRACTION (pdftotext) ▶"),
+                SetAttributes(Attributes::from(Attribute::Reset))
             )?;
-        } else {
-            eprintln!("[DEBUG] No PDF image loaded!");
+        }
+        
+        if show_pdf {
+            // Left Panel - PDF Render
+            chonker8::debug_log_throttled!("DEBUG", "Rendering left panel (PDF)");
+            execute!(stdout(), SetForegroundColor(theme.text))?;
+        
+            // Show PDF status
+            let mut pdf_status = if self.is_loading_page() {
+                format!(" Page {}/{} ⏳ loading… ", self.current_page, self.total_pages)
+            } else {
+                format!(" Page {}/{} ", self.current_page, self.total_pages)
+            };
+            if self.config.panels.pdf.sync_scroll {
+                pdf_status.push_str("⇅ synced ");
+            }
             execute!(
                 stdout(),
-                MoveTo(2, 5),
-                SetForegroundColor(Color::Red),
-                Print("[ERROR: No PDF image loaded]")
+                MoveTo(2, 1),
+                SetForegroundColor(theme.page_status),
+                Print(&pdf_status),
+                SetForegroundColor(theme.text)
             )?;
+        
+            // Render PDF content or image
+            if self.current_pdf_image.is_some() {
+                chonker8::debug_log_throttled!("DEBUG", "Have PDF image, attempting {} display", self.graphics.protocol().name());
+                let (img_w, img_h) = self.current_pdf_image.as_ref().map(|i| (i.width(), i.height())).unwrap();
+                chonker8::debug_log_throttled!("DEBUG", "Image dimensions: {}x{}", img_w, img_h);
+                chonker8::debug_log_throttled!("DEBUG", "Display area: x=2, y=2, width={}, height={}", split_x.saturating_sub(4), height - 4);
+            
+                // Try to display the PDF image in the left panel
+                self.render_pdf_content(2, 2, split_x.saturating_sub(4), height - 4)?;
+            
+                // Also show some debug info on screen
+                execute!(
+                    stdout(),
+                    MoveTo(2, height - 3),
+                    SetForegroundColor(theme.text_dim),
+                    Print(format!("PDF: {}x{}", 
+                        self.current_pdf_image.as_ref().map(|i| i.width()).unwrap_or(0),
+                        self.current_pdf_image.as_ref().map(|i| i.height()).unwrap_or(0)
+                    )),
+                    SetForegroundColor(theme.text)
+                )?;
+            } else {
+                chonker8::debug_log_throttled!("DEBUG", "No PDF image loaded!");
+                execute!(
+                    stdout(),
+                    MoveTo(2, 5),
+                    SetForegroundColor(theme.error),
+                    Print("[ERROR: No PDF image loaded]")
+                )?;
+            }
+            chonker8::debug_log_throttled!("DEBUG", "Left panel rendered");
         }
-        eprintln!("[DEBUG] Left panel rendered");
         
         // Render text extraction on right side
-        self.render_text_extraction_panel(split_x, 0, width - split_x, height - 2)?;
+        if show_text {
+            self.render_text_extraction_panel(split_x, 0, width - split_x, height - 2)?;
+        }
         
         // Status bar
-        let status_text = if let Some(path) = &self.current_pdf_path {
-            format!("PDF: {} | Page: {}/{} | Tab: Cycle • Esc: Exit", 
+        let status_text = if let Some(rect) = self.editor.block_selection() {
+            format!("-- BLOCK {}x{} -- | Arrows: Resize • y/Ctrl+C: Copy • Esc: Cancel", rect.width(), rect.height())
+        } else if self.editor.search().typing {
+            format!("{} | Enter: Done • Esc: Cancel", self.editor.search().prompt())
+        } else if self.editor.search().is_active() && !self.editor.is_inserting() {
+            format!("{} | n/N: Next/Prev • i: Insert • Esc: Clear", self.editor.search().prompt())
+        } else if let Some(path) = &self.current_pdf_path {
+            let version = match &self.page_view {
+                PageView::Raw => " [RAW]".to_string(),
+                PageView::Translated(lang) => format!(" [{}]", lang.to_uppercase()),
+                PageView::Edited if self.is_modified() => " [modified]".to_string(),
+                PageView::Edited => String::new(),
+            };
+            let hint = if self.editor.is_inserting() { "-- INSERT -- | Esc: Stop inserting" } else { "i: Insert • /: Search • Tab: Cycle • Esc: Exit" };
+            format!("PDF: {} | Page: {}/{}{} | {}", 
                 path.file_name().unwrap_or_default().to_string_lossy(),
                 self.current_page, 
-                self.total_pages)
+                self.total_pages,
+                version,
+                hint)
         } else {
             "PDF - TEST Screen | Tab: Cycle • Esc: Exit".to_string()
         };
+        let status_text = match self.available_update {
+            Some(version) => format!("⬆ {} available (chonker8 self-update) | {}", version, status_text),
+            None => status_text,
+        };
+        // Cut rather than wrap onto a second line in narrow terminals
+        let status_text: String = status_text.chars().take(width as usize - 2).collect();
         
         execute!(
             stdout(),
             MoveTo(0, height - 1),
-            SetBackgroundColor(Color::DarkBlue),
-            SetForegroundColor(Color::White),
+            SetBackgroundColor(theme.status_bg),
+            SetForegroundColor(theme.status_fg),
             Print(format!(" {:<width$} ", status_text, width = width as usize - 2)),
             ResetColor
         )?;
@@ -339,8 +784,198 @@ impl UIRenderer {
         Ok(())
     }
     
+    fn render_overview_screen(&mut self) -> Result<()> {
+        let (width, height) = terminal::size()?;
+        let theme = theme::current();
+        execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0), Hide)?;
+        
+        let Some(path) = self.current_pdf_path.clone() else {
+            execute!(
+                stdout(),
+                MoveTo(2, 2),
+                SetForegroundColor(theme.warning),
+                Print("No PDF open - pick one in the file picker first"),
+                ResetColor
+            )?;
+            stdout().flush()?;
+            return Ok(());
+        };
+        
+        let title = format!(" ▦ {} - {} pages ", path.file_name().unwrap_or_default().to_string_lossy(), self.total_pages);
+        execute!(
+            stdout(),
+            MoveTo(2, 0),
+            SetForegroundColor(theme.heading),
+            SetAttributes(Attributes::from(Attribute::Bold)),
+            Print(&title),
+            SetAttributes(Attributes::from(Attribute::Reset))
+        )?;
+        
+        // Rows 1 to height-2 hold the grid, the last is the status bar
+        let area = CellArea { x: 1, y: 1, cols: width.saturating_sub(2), rows: height.saturating_sub(2) };
+        self.overview.layout(area.cols, area.rows);
+        let keys: Vec<RenderKey> = self.overview.visible().map(|page| self.thumbnail_key(&path, page + 1)).collect();
+        self.thumbnail_cache.prerender(keys.clone());
+        
+        // Only what's already rendered - the rest show as placeholders until poll_thumbnails sees them land
+        let cache = &self.thumbnail_cache;
+        let first = self.overview.visible().start;
+        let sheet = self.overview.contact_sheet(|page| {
+            let key = &keys[page - first];
+            if cache.contains(key) { cache.get(key).ok() } else { None }
+        });
+        self.overview_ready = keys.iter().filter(|key| cache.contains(key)).count();
+        if let Err(e) = self.graphics.show(&sheet, area, true) {
+            execute!(stdout(), MoveTo(2, 2), SetForegroundColor(theme.error), Print(format!("⚠️  GRAPHICS ERROR: {}", e)), ResetColor)?;
+        }
+        // The page panel has to be sent again when it is next shown
+        self.image_dirty = true;
+        
+        let status_text = format!("Page {}/{} | Arrows: Move • PgUp/PgDn: Screen • Home/End: First/Last • Enter: Open page • Esc/Ctrl+O: Back",
+            self.overview.selected_page(), self.total_pages);
+        execute!(
+            stdout(),
+            MoveTo(0, height - 1),
+            SetBackgroundColor(theme.status_bg),
+            SetForegroundColor(theme.status_fg),
+            Print(format!(" {:<width$} ", status_text, width = width as usize - 2)),
+            ResetColor
+        )?;
+        stdout().flush()?;
+        Ok(())
+    }
+    
+    /// The page with its edits, whichever version the editor is showing
+    fn edited_grid(&self) -> Vec<Vec<char>> {
+        if self.page_view == PageView::Edited {
+            return self.editor.buffer().clone();
+        }
+        let (Some(storage), Some(path)) = (&self.edit_log, &self.current_pdf_path) else {
+            return self.pdf_content.clone();
+        };
+        match storage.edit_log(&path.to_string_lossy(), self.current_page) {
+            Ok(log) if !log.is_empty() => {
+                let events: Vec<_> = log.into_iter().map(|logged| logged.event).collect();
+                replay(&events).0
+            }
+            _ => self.pdf_content.clone(),
+        }
+    }
+    
+    /// Raw extraction against the edited page, line by line
+    fn render_diff_screen(&mut self) -> Result<()> {
+        if self.conflict.is_some() {
+            return self.render_merge_screen();
+        }
+        let (width, height) = terminal::size()?;
+        let theme = theme::current();
+        let diff = diff_lines(&grid_lines(&self.pdf_content), &grid_lines(&self.edited_grid()));
+        let (added, removed) = diff_stats(&diff);
+        let content_height = height.saturating_sub(3) as usize;
+        self.diff_scroll_offset = self.diff_scroll_offset.min(diff.len().saturating_sub(content_height));
+        
+        execute!(
+            stdout(),
+            Clear(ClearType::All),
+            MoveTo(0, 0),
+            SetForegroundColor(theme.heading),
+            Print(format!("DIFF - page {}: raw extraction → edited ({} added, {} removed)", self.current_page, added, removed)),
+            ResetColor
+        )?;
+        
+        let max_width = width.saturating_sub(2) as usize;
+        for (i, line) in diff.iter().skip(self.diff_scroll_offset).take(content_height).enumerate() {
+            let color = match line {
+                DiffLine::Same(_) => theme.text_dim,
+                DiffLine::Added(_) => theme.diff_added,
+                DiffLine::Removed(_) => theme.diff_removed,
+            };
+            let text: String = format!("{} {}", line.marker(), line.text()).chars().take(max_width).collect();
+            execute!(stdout(), MoveTo(0, 2 + i as u16), SetForegroundColor(color), Print(text), ResetColor)?;
+        }
+        if added + removed == 0 {
+            execute!(stdout(), MoveTo(0, 2), SetForegroundColor(theme.text_dim), Print("  No edits on this page"), ResetColor)?;
+        }
+        
+        let status_text = format!(
+            " {}-{} of {} lines | ↑↓/Mouse: Scroll | PgUp/Dn: Page | Tab | Esc ",
+            (self.diff_scroll_offset + 1).min(diff.len()),
+            (self.diff_scroll_offset + content_height).min(diff.len()),
+            diff.len()
+        );
+        execute!(
+            stdout(),
+            MoveTo(0, height - 1),
+            SetAttributes(Attributes::from(Attribute::Reverse)),
+            Print(format!("{:<width$}", status_text, width = width as usize)),
+            SetAttributes(Attributes::from(Attribute::Reset))
+        )?;
+        
+        stdout().flush()?;
+        Ok(())
+    }
+    
+    /// Three-way view of a conflict: rows only one side changed, rows both did,
+    /// and rows where the two clash, mine above theirs
+    fn render_merge_screen(&mut self) -> Result<()> {
+        let Some(conflict) = &self.conflict else { return Ok(()) };
+        let (width, height) = terminal::size()?;
+        let merge = merge3(&conflict.base, &conflict.ours, &conflict.theirs);
+        let theme = theme::current();
+        let row_text = |grid: &[Vec<char>], row: usize| grid.get(row).map(|cells| cells.iter().collect::<String>().trim_end().to_string()).unwrap_or_default();
+        
+        let mut lines: Vec<(String, Color)> = Vec::new();
+        for (row, kind) in merge.rows.iter().enumerate() {
+            match kind {
+                RowMerge::Same => lines.push((format!("    {}", row_text(&merge.grid, row)), theme.text_dim)),
+                RowMerge::Ours => lines.push((format!("<   {}", row_text(&merge.grid, row)), theme.diff_added)),
+                RowMerge::Theirs => lines.push((format!(">   {}", row_text(&merge.grid, row)), theme.merge_theirs)),
+                RowMerge::Both => lines.push((format!("=   {}", row_text(&merge.grid, row)), theme.merge_both)),
+                RowMerge::Conflict => {
+                    lines.push((format!("!<  {}", row_text(&conflict.ours, row)), theme.conflict_ours));
+                    lines.push((format!("!>  {}", row_text(&conflict.theirs, row)), theme.conflict_theirs));
+                }
+            }
+        }
+        while lines.last().is_some_and(|(text, _)| text.trim().is_empty()) {
+            lines.pop();
+        }
+        let content_height = height.saturating_sub(3) as usize;
+        self.diff_scroll_offset = self.diff_scroll_offset.min(lines.len().saturating_sub(content_height));
+        
+        execute!(
+            stdout(),
+            Clear(ClearType::All),
+            MoveTo(0, 0),
+            SetForegroundColor(theme.error),
+            Print(format!(
+                "CONFLICT #{} - page {} was edited elsewhere while you edited it ({} clashing cells)",
+                conflict.id, conflict.page, merge.conflicts
+            )),
+            ResetColor
+        )?;
+        let max_width = width.saturating_sub(2) as usize;
+        for (i, (text, color)) in lines.iter().skip(self.diff_scroll_offset).take(content_height).enumerate() {
+            let text: String = text.chars().take(max_width).collect();
+            execute!(stdout(), MoveTo(0, 2 + i as u16), SetForegroundColor(*color), Print(text), ResetColor)?;
+        }
+        
+        let status_text = " < mine  > theirs  = both  ! clash | o: Keep mine | t: Take theirs | m: Merge (mine wins clashes) | ↑↓: Scroll ";
+        execute!(
+            stdout(),
+            MoveTo(0, height - 1),
+            SetAttributes(Attributes::from(Attribute::Reverse)),
+            Print(format!("{:<width$}", status_text, width = width as usize)),
+            SetAttributes(Attributes::from(Attribute::Reset))
+        )?;
+        
+        stdout().flush()?;
+        Ok(())
+    }
+    
     fn render_debug_screen(&mut self) -> Result<()> {
         let (width, height) = terminal::size()?;
+        let theme = theme::current();
         
         // Clear screen
         execute!(
@@ -353,14 +988,14 @@ impl UIRenderer {
         execute!(
             stdout(),
             MoveTo(0, 0),
-            SetForegroundColor(Color::Cyan),
+            SetForegroundColor(theme.accent),
             Print(format!("╔{}╗", "═".repeat((width - 2) as usize))),
             MoveTo(0, 1),
             Print("║"),
             MoveTo(2, 1),
-            SetForegroundColor(Color::Yellow),
+            SetForegroundColor(theme.heading),
             Print("DEBUG OUTPUT"),
-            SetForegroundColor(Color::Cyan),
+            SetForegroundColor(theme.accent),
             MoveTo(width - 1, 1),
             Print("║"),
             MoveTo(0, 2),
@@ -395,11 +1030,11 @@ impl UIRenderer {
             execute!(
                 stdout(),
                 MoveTo(0, y_pos),
-                SetForegroundColor(Color::Cyan),
+                SetForegroundColor(theme.accent),
                 Print("║ "),
                 SetForegroundColor(msg_color),
                 Print(format!("{:<width$}", display_msg, width = max_width)),
-                SetForegroundColor(Color::Cyan),
+                SetForegroundColor(theme.accent),
                 MoveTo(width - 1, y_pos),
                 Print("║"),
                 ResetColor
@@ -412,7 +1047,7 @@ impl UIRenderer {
             execute!(
                 stdout(),
                 MoveTo(0, y_pos),
-                SetForegroundColor(Color::Cyan),
+                SetForegroundColor(theme.accent),
                 Print("║"),
                 MoveTo(width - 1, y_pos),
                 Print("║"),
@@ -424,7 +1059,7 @@ impl UIRenderer {
         execute!(
             stdout(),
             MoveTo(0, height - 2),
-            SetForegroundColor(Color::Cyan),
+            SetForegroundColor(theme.accent),
             Print(format!("╚{}╝", "═".repeat((width - 2) as usize))),
             ResetColor
         )?;
@@ -452,10 +1087,11 @@ impl UIRenderer {
     
     fn render_pdf_panel(&mut self, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
         let (tl, tr, bl, br, h_line, v_line, _, _) = self.config.get_border_chars();
+        let theme = theme::current();
         
         // Draw border if not "none"
         if self.config.theme.border != "none" {
-            execute!(stdout(), SetForegroundColor(self.config.get_highlight_color()))?;
+            execute!(stdout(), SetForegroundColor(theme.accent))?;
             
             // Top border
             execute!(stdout(), MoveTo(x, y), Print(tl))?;
@@ -479,13 +1115,13 @@ impl UIRenderer {
         }
         
         // Draw title with rendering method indicator and scroll info
-        let title = format!(" 📄 PDF Page {}/{} [lopdf-vello-kitty] Scroll: {} ", 
-                          self.current_page, self.total_pages, self.scroll_offset);
+        let title = format!(" 📄 PDF Page {}/{} [lopdf-vello-{}] Scroll: {} ", 
+                          self.current_page, self.total_pages, self.graphics.protocol().name(), self.scroll_offset);
         execute!(
             stdout(),
             MoveTo(x + 2, y),
-            SetBackgroundColor(Color::Rgb { r: 30, g: 30, b: 40 }),
-            SetForegroundColor(Color::Rgb { r: 100, g: 200, b: 255 }),
+            SetBackgroundColor(theme.title_bg),
+            SetForegroundColor(theme.title_pdf),
             Print(&title),
             ResetColor
         )?;
@@ -503,10 +1139,11 @@ impl UIRenderer {
     
     fn render_text_panel(&self, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
         let (tl, tr, bl, br, h_line, v_line, _, _) = self.config.get_border_chars();
+        let theme = theme::current();
         
         // Draw border if not "none"
         if self.config.theme.border != "none" {
-            execute!(stdout(), SetForegroundColor(self.config.get_highlight_color()))?;
+            execute!(stdout(), SetForegroundColor(theme.accent))?;
             
             // Top border
             execute!(stdout(), MoveTo(x, y), Print(tl))?;
@@ -534,8 +1171,8 @@ impl UIRenderer {
         execute!(
             stdout(),
             MoveTo(x + 2, y),
-            SetBackgroundColor(Color::Rgb { r: 30, g: 30, b: 40 }),
-            SetForegroundColor(Color::Rgb { r: 255, g: 200, b: 100 }),
+            SetBackgroundColor(theme.title_bg),
+            SetForegroundColor(theme.title_text),
             Print(title),
             ResetColor
         )?;
@@ -562,166 +1199,55 @@ impl UIRenderer {
     
     
     fn render_pdf_content(&mut self, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
-        // ALWAYS use Kitty protocol - NO FALLBACK
-        if let Some(ref image) = self.current_pdf_image {
-            // Send image on first render or if screen was cleared
-            if !self.image_sent {
-                eprintln!("[DEBUG] Sending Kitty image");
-                self.image_sent = true;
-            } else {
-                // Image already on screen, just return
-                return Ok(());
-            }
-            
-            // Use inline Kitty implementation with correct protocol
-            struct KittyImage;
-            impl KittyImage {
-                fn send_image_positioned(image: &DynamicImage, width: u32, height: u32, x: u16, y: u16) -> Result<()> {
-                    // Convert to PNG
-                    let mut png_data = Vec::new();
-                    image.write_to(&mut std::io::Cursor::new(&mut png_data), image::ImageFormat::Png)?;
-                    
-                    // Base64 encode
-                    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-                    let encoded = BASE64.encode(&png_data);
-                    
-                    // First, clear the terminal properly
-                    std::io::stdout().flush()?;
-                    
-                    // Move cursor to position using crossterm, not raw escape codes
-                    execute!(stdout(), MoveTo(x, y))?;
-                    
-                    // Clear any existing images - use raw bytes to ensure proper escape
-                    use std::io::Write;
-                    let clear_cmd = b"\x1b_Ga=d\x1b\\";
-                    std::io::stdout().write_all(clear_cmd)?;
-                    std::io::stdout().flush()?;
-                    
-                    // Kitty protocol requires chunking for large images
-                    // Maximum chunk size is 4096 bytes
-                    const CHUNK_SIZE: usize = 4096;
-                    let encoded_bytes = encoded.as_bytes();
-                    
-                    if encoded_bytes.len() <= CHUNK_SIZE {
-                        // Small image, send in one go
-                        // Use c= and r= for cell dimensions (what we want to display in)
-                        let mut cmd = Vec::new();
-                        cmd.extend_from_slice(b"\x1b_Ga=T,f=100,c=");
-                        cmd.extend_from_slice(width.to_string().as_bytes());
-                        cmd.extend_from_slice(b",r=");
-                        cmd.extend_from_slice(height.to_string().as_bytes());
-                        cmd.extend_from_slice(b";");
-                        cmd.extend_from_slice(encoded_bytes);
-                        cmd.extend_from_slice(b"\x1b\\");
-                        
-                        std::io::stdout().write_all(&cmd)?;
-                    } else {
-                        // Large image, send in chunks
-                        let chunks: Vec<&[u8]> = encoded_bytes.chunks(CHUNK_SIZE).collect();
-                        
-                        for (i, chunk) in chunks.iter().enumerate() {
-                            let mut cmd = Vec::new();
-                            cmd.extend_from_slice(b"\x1b_G");
-                            
-                            if i == 0 {
-                                // First chunk has the full header
-                                // Use c= and r= for cell dimensions
-                                cmd.extend_from_slice(b"a=T,f=100,c=");
-                                cmd.extend_from_slice(width.to_string().as_bytes());
-                                cmd.extend_from_slice(b",r=");
-                                cmd.extend_from_slice(height.to_string().as_bytes());
-                                cmd.extend_from_slice(b",m=1;");
-                            } else if i == chunks.len() - 1 {
-                                // Last chunk
-                                cmd.extend_from_slice(b"m=0;");
-                            } else {
-                                // Middle chunks
-                                cmd.extend_from_slice(b"m=1;");
-                            }
-                            
-                            cmd.extend_from_slice(chunk);
-                            cmd.extend_from_slice(b"\x1b\\");
-                            
-                            std::io::stdout().write_all(&cmd)?;
-                            std::io::stdout().flush()?;
-                        }
-                        
-                        eprintln!("[KITTY] Sent large image in {} chunks", chunks.len());
-                    }
-                    
-                    std::io::stdout().flush()?;
-                    
-                    eprintln!("[KITTY] Sent image {}x{} at ({},{}), {} bytes encoded", 
-                             width, height, x, y, encoded.len());
-                    
-                    Ok(())
-                }
-            }
-            
-            // Calculate scale to fit within the left panel (half the terminal width)
-            // The panel dimensions are in terminal cells, not pixels
-            // Kitty graphics use cells as units for placement
-            let panel_width_cells = width.saturating_sub(4);  // Leave some padding
-            let panel_height_cells = height.saturating_sub(4);
-            
-            // For Kitty protocol, we specify size in terminal cells
-            // The image will be scaled to fit within these dimensions
-            let display_width = panel_width_cells.min(50) as u32;  // Cap at reasonable size
-            let display_height = panel_height_cells.min(35) as u32;
-            
-            // Position at top-left of the panel with small margin
-            let image_x = x + 2;
-            let image_y = y + 2;
-            
-            eprintln!("[DEBUG] Original: {}x{}, Display: {}x{}, Position: ({}, {})", 
-                     image.width(), image.height(), display_width, display_height, image_x, image_y);
-            
-            // Move cursor to position
+        let Some(page_image) = self.current_pdf_image.clone() else {
+            eprintln!("[ERROR] No PDF image loaded!");
             execute!(
                 stdout(),
-                MoveTo(image_x, image_y)
+                MoveTo(x + 2, y + height/2),
+                SetForegroundColor(theme::current().error),
+                Print("⚠️  NO PDF IMAGE ⚠️"),
+                ResetColor
             )?;
-            
-            // Send image at fixed position within panel
-            match KittyImage::send_image_positioned(image, display_width, display_height, image_x, image_y) {
-                Ok(_) => {
-                    eprintln!("[DEBUG] ✅ KITTY IMAGE SENT SUCCESSFULLY!");
-                }
-                Err(e) => {
-                    eprintln!("[ERROR] KITTY FAILED: {}", e);
-                    eprintln!("[ERROR] This viewer REQUIRES Kitty-compatible terminal!");
-                    
-                    // Show error message on screen
-                    execute!(
-                        stdout(),
-                        MoveTo(x + 2, y + height/2),
-                        SetForegroundColor(Color::Red),
-                        Print("⚠️  KITTY ERROR ⚠️"),
-                        MoveTo(x + 2, y + height/2 + 2),
-                        Print(&format!("Error: {}", e)),
-                        SetForegroundColor(Color::White)
-                    )?;
-                }
+            return Ok(());
+        };
+
+        // Synced: only the pixel rows behind the text rows in view, so both panes show the same stretch
+        let mut changed = self.image_dirty;
+        let image = if self.config.panels.pdf.sync_scroll {
+            let visible = self.editor.visible_rows();
+            let band = RowMapping::new(self.editor.buffer().len(), page_image.height()).band(visible.start, visible.len());
+            if changed || self.synced_band.as_ref().map(|(shown, _)| shown) != Some(&band) {
+                let slice = page_image.crop_imm(0, band.start, page_image.width(), band.len() as u32);
+                self.synced_band = Some((band, slice));
+                changed = true;
             }
+            self.synced_band.as_ref().map(|(_, slice)| slice).unwrap_or(&page_image)
         } else {
-            // No image - but this shouldn't happen
-            eprintln!("[ERROR] No PDF image loaded!");
+            &page_image
+        };
+
+        // The last row is left for the info line
+        let area = CellArea { x, y, cols: width, rows: height.saturating_sub(1) };
+        if let Err(e) = self.graphics.show(image, area, changed) {
+            eprintln!("[ERROR] {} graphics failed: {}", self.graphics.protocol().name(), e);
             execute!(
                 stdout(),
                 MoveTo(x + 2, y + height/2),
-                SetForegroundColor(Color::Red),
-                Print("⚠️  NO PDF IMAGE ⚠️"),
-                SetForegroundColor(Color::White)
+                SetForegroundColor(theme::current().error),
+                Print("⚠️  GRAPHICS ERROR ⚠️"),
+                MoveTo(x + 2, y + height/2 + 2),
+                Print(&format!("Error: {}", e)),
+                ResetColor
             )?;
         }
-        
+        self.image_dirty = false;
         Ok(())
     }
     
     
     
     fn render_text_content(&self, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
-        execute!(stdout(), SetForegroundColor(self.config.get_text_color()))?;
+        execute!(stdout(), SetForegroundColor(theme::current().text))?;
         
         // Extract text from pdf_content
         let text: String = self.pdf_content
@@ -731,14 +1257,7 @@ impl UIRenderer {
             .join("\n");
         
         let lines: Vec<String> = if self.config.panels.text.wrap_text {
-            // Simple word wrapping
-            text.split('\n').flat_map(|line| {
-                line.chars()
-                    .collect::<Vec<_>>()
-                    .chunks(width as usize)
-                    .map(|chunk| chunk.iter().collect::<String>())
-                    .collect::<Vec<_>>()
-            }).collect()
+            reflow::reflow(&text, width as usize)
         } else {
             text.lines().map(|s| s.to_string()).collect()
         };
@@ -792,21 +1311,227 @@ impl UIRenderer {
     
     // Navigation methods
     pub fn next_page(&mut self) {
+        let previous = self.current_page;
         if self.current_page < self.total_pages {
             self.current_page += 1;
         } else {
             self.current_page = 1; // Cycle back to first page
         }
         self.scroll_offset = 0;
+        if self.current_page != previous {
+            self.start_page_load();
+        }
     }
     
     pub fn prev_page(&mut self) {
         if self.current_page > 1 {
             self.current_page -= 1;
             self.scroll_offset = 0;
+            self.start_page_load();
+        }
+    }
+    
+    /// Move the split between the panes by `percent` of the width; [panels.pdf] width_percent
+    /// in ui.toml is where it starts
+    pub fn adjust_split(&mut self, percent: f32) {
+        let pdf = (self.config.panels.pdf.width_percent + percent).clamp(SPLIT_RANGE.0, SPLIT_RANGE.1);
+        self.config.panels.pdf.width_percent = pdf;
+        self.config.panels.text.width_percent = 100.0 - pdf;
+        self.maximized = None;
+        self.image_dirty = true;
+    }
+    
+    /// Give a pane the whole width, or bring the split back if it has it already
+    pub fn toggle_maximized(&mut self, pane: Pane) {
+        self.maximized = if self.maximized == Some(pane) { None } else { Some(pane) };
+        if self.maximized == Some(Pane::Text) {
+            // Protocols that keep images in the terminal would leave the page showing under the text
+            if let Err(e) = self.graphics.clear() {
+                self.add_debug_message(format!("Failed to clear the page image: {}", e));
+            }
+        }
+        self.image_dirty = true;
+    }
+    
+    /// Have the page image follow the text pane's scrolling, or show the whole page again
+    pub fn toggle_sync_scroll(&mut self) {
+        let sync = !self.config.panels.pdf.sync_scroll;
+        self.config.panels.pdf.sync_scroll = sync;
+        self.synced_band = None;
+        self.image_dirty = true;
+        self.add_debug_message(format!("Sync scroll {}", if sync { "on" } else { "off" }));
+    }
+    
+    /// Column of the line between the panes: 0 or the full width when one is maximized
+    fn split_column(&self, width: u16) -> u16 {
+        match self.maximized {
+            Some(Pane::Pdf) => width,
+            Some(Pane::Text) => 0,
+            None => {
+                let percent = self.config.panels.pdf.width_percent.clamp(SPLIT_RANGE.0, SPLIT_RANGE.1);
+                (width as f32 * percent / 100.0).round() as u16
+            }
+        }
+    }
+    
+    /// Open the overview with the current page selected
+    pub fn open_overview(&mut self) {
+        if self.current_pdf_path.is_none() {
+            self.add_debug_message("Open a PDF before the page overview".to_string());
+            return;
+        }
+        self.overview = PageOverview::new(self.total_pages, self.current_page);
+        self.set_screen(Screen::Overview);
+    }
+    
+    /// Navigate the overview. Returns true when the key was used.
+    pub fn handle_overview_input(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        match key.code {
+            KeyCode::Left => self.overview.move_by(-1, 0),
+            KeyCode::Right => self.overview.move_by(1, 0),
+            KeyCode::Up => self.overview.move_by(0, -1),
+            KeyCode::Down => self.overview.move_by(0, 1),
+            KeyCode::PageUp => self.overview.page_by(-1),
+            KeyCode::PageDown => self.overview.page_by(1),
+            KeyCode::Home => self.overview.select_first(),
+            KeyCode::End => self.overview.select_last(),
+            KeyCode::Enter => {
+                self.go_to_page(self.overview.selected_page());
+                self.set_screen(Screen::PdfViewer);
+            }
+            KeyCode::Esc => self.set_screen(Screen::PdfViewer),
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => self.set_screen(Screen::PdfViewer),
+            _ => return false,
+        }
+        true
+    }
+    
+    /// Redraw the overview when thumbnails have finished rendering since it was last drawn
+    pub fn poll_thumbnails(&mut self) -> bool {
+        let Some(path) = self.current_pdf_path.as_deref() else {
+            return false;
+        };
+        if self.current_screen != Screen::Overview {
+            return false;
+        }
+        let ready = self.overview.visible().filter(|page| self.thumbnail_cache.contains(&self.thumbnail_key(path, page + 1))).count();
+        ready != self.overview_ready
+    }
+    
+    /// Jump straight to a page (1-based)
+    pub fn go_to_page(&mut self, page: usize) {
+        let page = page.clamp(1, self.total_pages.max(1));
+        if page != self.current_page {
+            self.current_page = page;
+            self.scroll_offset = 0;
+            self.start_page_load();
         }
     }
     
+    fn thumbnail_key(&self, path: &std::path::Path, page: usize) -> RenderKey {
+        let (width, height) = page_overview::THUMBNAIL_SIZE;
+        RenderKey { path: path.to_path_buf(), page: page - 1, width, height, dark_mode: true }
+    }
+    
+    /// Cache key for a page of the open PDF; the panel always shows the dark-filtered render
+    fn page_key(&self, path: &std::path::Path, page: usize) -> RenderKey {
+        let (width, height) = PAGE_RENDER_SIZE;
+        RenderKey { path: path.to_path_buf(), page: page - 1, width, height, dark_mode: true }
+    }
+    
+    /// Render and extract the current page on a background thread so the UI keeps
+    /// responding; the result is picked up by poll_page_load()
+    fn start_page_load(&mut self) {
+        let Some(path) = self.current_pdf_path.clone() else {
+            return;
+        };
+        self.prerender_neighbours(&path);
+        let (sender, receiver) = mpsc::channel();
+        let cache = self.render_cache.clone();
+        let key = self.page_key(&path, self.current_page);
+        let page = self.current_page;
+        std::thread::spawn(move || {
+            let image = cache.get(&key).map_err(|e| e.to_string());
+            let text = Self::extract_text_simple(&path, page - 1)
+                .unwrap_or_else(|e| format!("Text extraction failed: {}", e));
+            // The receiver is gone if the user already moved on to another page
+            let _ = sender.send(LoadedPage { page, image, text });
+        });
+        self.page_load = Some(receiver);
+    }
+    
+    /// Apply a finished page load. Returns true when the screen needs redrawing.
+    pub fn poll_page_load(&mut self) -> bool {
+        let Some(receiver) = &self.page_load else {
+            return false;
+        };
+        let loaded = match receiver.try_recv() {
+            Ok(loaded) => loaded,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => {
+                self.page_load = None;
+                return true;
+            }
+        };
+        self.page_load = None;
+        if loaded.page != self.current_page {
+            return false;
+        }
+        
+        match loaded.image {
+            Ok(image) => {
+                self.current_pdf_image = Some(image);
+                self.image_dirty = true;
+            }
+            Err(e) => self.add_debug_message(format!("Failed to render page {}: {}", loaded.page, e)),
+        }
+        let grid = match self.current_pdf_path.clone() {
+            Some(path) => self.grid_size(&path, loaded.page),
+            None => GridSize::new(GRID_WIDTH, GRID_HEIGHT),
+        };
+        let matrix = self.text_to_matrix(&loaded.text, grid.cols, grid.rows);
+        self.set_pdf_content(matrix);
+        true
+    }
+    
+    /// Pick up the release feed's answer; true when there is a notice to show
+    /// Whether the file picker's list changed as indexing went on, when it is on screen
+    pub fn poll_file_index(&mut self) -> bool {
+        match &mut self.file_picker {
+            Some(picker) if self.current_screen == Screen::FilePicker => picker.poll(),
+            _ => false,
+        }
+    }
+    
+    pub fn poll_update_check(&mut self) -> bool {
+        let Some(receiver) = &self.update_check else {
+            return false;
+        };
+        match receiver.try_recv() {
+            Err(TryRecvError::Empty) => false,
+            answer => {
+                self.update_check = None;
+                self.available_update = answer.ok().flatten().filter(|_| self.config.updates.check);
+                self.available_update.is_some()
+            }
+        }
+    }
+    
+    pub fn is_loading_page(&self) -> bool {
+        self.page_load.is_some()
+    }
+    
+    fn prerender_neighbours(&self, path: &std::path::Path) {
+        let neighbours = [self.current_page + 1, self.current_page.saturating_sub(1)];
+        let keys = neighbours
+            .into_iter()
+            .filter(|page| (1..=self.total_pages).contains(page) && *page != self.current_page)
+            .map(|page| self.page_key(path, page))
+            .collect();
+        self.render_cache.prerender(keys);
+    }
+    
     pub fn scroll_up(&mut self) {
         match self.current_screen {
             Screen::Debug => {
@@ -814,6 +1539,8 @@ impl UIRenderer {
                     self.debug_scroll_offset -= 1;
                 }
             }
+            Screen::Diff => self.scroll_diff(-1),
+            Screen::PdfViewer if self.config.panels.pdf.sync_scroll => self.editor.scroll_rows(-SYNC_SCROLL_ROWS),
             _ => {
                 // Larger scroll steps for PDF image viewing
                 if self.scroll_offset > 0 {
@@ -830,6 +1557,8 @@ impl UIRenderer {
                     self.debug_scroll_offset += 1;
                 }
             }
+            Screen::Diff => self.scroll_diff(1),
+            Screen::PdfViewer if self.config.panels.pdf.sync_scroll => self.editor.scroll_rows(SYNC_SCROLL_ROWS),
             _ => {
                 // Larger scroll steps for PDF image viewing (up to 100 to see off-screen images)
                 if self.scroll_offset < 100 {
@@ -840,6 +1569,11 @@ impl UIRenderer {
     }
     
     
+    /// Scroll the diff screen; clamped to the diff's length when rendered
+    pub fn scroll_diff(&mut self, lines: isize) {
+        self.diff_scroll_offset = self.diff_scroll_offset.saturating_add_signed(lines);
+    }
+    
     pub fn toggle_wrap(&mut self) {
         self.config.panels.text.wrap_text = !self.config.panels.text.wrap_text;
     }
@@ -932,6 +1666,13 @@ impl UIRenderer {
     pub fn handle_file_picker_input(&mut self, key: crossterm::event::KeyEvent) -> Result<Option<String>> {
         if let Some(file_picker) = &mut self.file_picker {
             match key.code {
+                crossterm::event::KeyCode::Char('p') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                    if let Some((path, pinned)) = file_picker.toggle_pin() {
+                        if let Some(Err(e)) = self.edit_log.as_ref().map(|storage| storage.set_pinned(&path, pinned)) {
+                            self.add_debug_message(format!("Failed to save the pin for {}: {}", path, e));
+                        }
+                    }
+                }
                 crossterm::event::KeyCode::Char(c) => {
                     file_picker.handle_char(c)?;
                 }
@@ -946,7 +1687,12 @@ impl UIRenderer {
                 }
                 crossterm::event::KeyCode::Enter => {
                     if let Some(selected_file) = file_picker.get_selected_file() {
-                        return Ok(Some(selected_file.to_string_lossy().to_string()));
+                        let selected_file = selected_file.to_string_lossy().to_string();
+                        file_picker.record_opened(&selected_file);
+                        if let Some(Err(e)) = self.edit_log.as_ref().map(|storage| storage.record_opened(&selected_file)) {
+                            self.add_debug_message(format!("Failed to add {} to recent files: {}", selected_file, e));
+                        }
+                        return Ok(Some(selected_file));
                     }
                 }
                 _ => {}
@@ -959,6 +1705,8 @@ impl UIRenderer {
         match self.current_screen {
             Screen::FilePicker => "File Picker", 
             Screen::PdfViewer => "PDF Viewer",
+            Screen::Overview => "Overview",
+            Screen::Diff => "Diff",
             Screen::Debug => "Debug",
         }
     }
@@ -969,41 +1717,39 @@ impl UIRenderer {
         // Clear debug messages for new PDF load
         self.debug_messages.clear();
         self.debug_scroll_offset = 0;
-        self.image_sent = false; // Reset flag for new PDF
+        self.image_dirty = true; // Send the new PDF's image on next render
         
         let msg = format!("A-B Comparison: Loading PDF {:?}", pdf_path);
         eprintln!("[INFO] Left pane: lopdf-kitty rendering");
         eprintln!("[INFO] Right pane: pdftotext extraction");
         self.add_debug_message(msg.clone());
-        eprintln!("[DEBUG] {}", msg);
+        chonker8::debug_log!("DEBUG", "{}", msg);
         
         // Load PDF page count - chonker7 style with fresh instance
         self.add_debug_message("Getting page count...".to_string());
-        eprintln!("[DEBUG] Getting page count...");
+        chonker8::debug_log!("DEBUG", "Getting page count...");
         self.total_pages = content_extractor::get_page_count(&pdf_path)?;
         self.current_page = 1;
         let msg = format!("Page count: {}", self.total_pages);
         self.add_debug_message(msg.clone());
-        eprintln!("[DEBUG] {}", msg);
+        chonker8::debug_log!("DEBUG", "{}", msg);
         
         // Render first page image - same size as chonker7
         self.add_debug_message("Rendering PDF with lopdf-kitty...".to_string());
-        eprintln!("[DEBUG] Rendering PDF with direct bitmap renderer...");
-        let mut image = pdf_renderer::render_pdf_page(&pdf_path, 0, 800, 1000)?;  // Same as chonker7
-        
-        // Apply dark mode filter for better visibility
-        image = self.apply_dark_mode_filter(image);
+        chonker8::debug_log!("DEBUG", "Rendering PDF with direct bitmap renderer...");
+        // Dark mode filter is applied by the render cache; same size as chonker7
+        let image = self.render_cache.get(&self.page_key(&pdf_path, 1))?;
         self.add_debug_message("PDF page rendered".to_string());
-        eprintln!("[DEBUG] PDF page rendered");
+        chonker8::debug_log!("DEBUG", "PDF page rendered");
         
         // Use intelligent document-agnostic extraction - with fallback
         self.add_debug_message("Creating analyzer...".to_string());
-        eprintln!("[DEBUG] Creating analyzer...");
+        chonker8::debug_log!("DEBUG", "Creating analyzer...");
         
         let fingerprint = match DocumentAnalyzer::new() {
             Ok(analyzer) => {
                 self.add_debug_message("Analyzing page...".to_string());
-                eprintln!("[DEBUG] Analyzing page...");
+                chonker8::debug_log!("DEBUG", "Analyzing page...");
                 match analyzer.analyze_page(&pdf_path, 0) {
                     Ok(fp) => {
                         let msg = format!("Analysis complete: text={:.1}%, image={:.1}%, has_tables={}, text_quality={:.2}", 
@@ -1012,7 +1758,7 @@ impl UIRenderer {
                             fp.has_tables,
                             fp.text_quality);
                         self.add_debug_message(msg.clone());
-                        eprintln!("[DEBUG] {}", msg);
+                        chonker8::debug_log!("DEBUG", "{}", msg);
                         fp
                     }
                     Err(e) => {
@@ -1029,7 +1775,7 @@ impl UIRenderer {
         
         // Extract text using pdftotext for the right panel
         self.add_debug_message("Extracting text with pdftotext...".to_string());
-        eprintln!("[DEBUG] Running pdftotext with layout preservation...");
+        chonker8::debug_log!("DEBUG", "Running pdftotext with layout preservation...");
         
         let extraction_result = match std::process::Command::new("pdftotext")
             .args(&[
@@ -1043,12 +1789,13 @@ impl UIRenderer {
             .output() {
             Ok(output) if output.status.success() => {
                 let text = String::from_utf8_lossy(&output.stdout).to_string();
-                eprintln!("[DEBUG] pdftotext extracted {} characters", text.len());
+                chonker8::debug_log!("DEBUG", "pdftotext extracted {} characters", text.len());
                 crate::pdf_extraction::ExtractionResult {
                     text,
                     quality_score: 0.8,
                     method: crate::pdf_extraction::ExtractionMethod::PdfToText,
                     extraction_time_ms: 0,
+                    correction: None,
                 }
             }
             _ => {
@@ -1058,6 +1805,7 @@ impl UIRenderer {
                     quality_score: 0.0,
                     method: crate::pdf_extraction::ExtractionMethod::PdfToText,
                     extraction_time_ms: 0,
+                    correction: None,
                 }
             }
         };
@@ -1065,7 +1813,7 @@ impl UIRenderer {
         let msg = format!("Extraction complete using method: {:?}, quality: {:.2}", 
             extraction_result.method, extraction_result.quality_score);
         self.add_debug_message(msg.clone());
-        eprintln!("[DEBUG] {}", msg);
+        chonker8::debug_log!("DEBUG", "{}", msg);
         
         // Store metadata
         self.extraction_method = Some(format!("{:?}", extraction_result.method));
@@ -1107,12 +1855,14 @@ impl UIRenderer {
         let text_with_metadata = format!("{}{}", metadata_header, extraction_result.text);
         
         // Convert extracted text to grid format for display
-        let text_matrix = self.text_to_matrix(&text_with_metadata, 200, 100);
+        let grid = self.grid_size(&pdf_path, 1);
+        let text_matrix = self.text_to_matrix(&text_with_metadata, grid.cols, grid.rows);
         
         // Update state
+        self.prerender_neighbours(&pdf_path);
         self.current_pdf_path = Some(pdf_path);
         self.current_pdf_image = Some(image);
-        self.pdf_content = text_matrix;
+        self.set_pdf_content(text_matrix);
         
         // Store fingerprint info for display
         self.dark_mode = fingerprint.text_coverage > 0.8; // Just as a flag for now
@@ -1120,7 +1870,7 @@ impl UIRenderer {
         Ok(())
     }
     
-    fn extract_text_simple(&self, pdf_path: &PathBuf, page: usize) -> Result<String> {
+    fn extract_text_simple(pdf_path: &std::path::Path, page: usize) -> Result<String> {
         use std::process::Command;
         
         // Try pdftotext first (cleaner output)
@@ -1129,7 +1879,7 @@ impl UIRenderer {
                 "-f", &(page + 1).to_string(),
                 "-l", &(page + 1).to_string(),
                 "-layout",
-                pdf_path.to_str().unwrap(),
+                &pdf_path.to_string_lossy(),
                 "-"
             ])
             .output();
@@ -1162,7 +1912,7 @@ impl UIRenderer {
     }
     
     /// Apply dark mode filter to PDF image for better visibility in terminal
-    fn apply_dark_mode_filter(&self, image: DynamicImage) -> DynamicImage {
+    fn apply_dark_mode_filter(image: DynamicImage) -> DynamicImage {
         use image::{ImageBuffer, Rgba};
         
         let rgba_image = image.to_rgba8();
@@ -1204,9 +1954,10 @@ impl UIRenderer {
         DynamicImage::ImageRgba8(buffer)
     }
     
-    fn render_text_extraction_panel(&self, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
+    fn render_text_extraction_panel(&mut self, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
+        let theme = theme::current();
         // Draw border
-        execute!(stdout(), SetForegroundColor(Color::DarkGrey))?;
+        execute!(stdout(), SetForegroundColor(theme.text_dim))?;
         for row in 0..height {
             execute!(stdout(), MoveTo(x, y + row), Print("│"))?; // Left border
         }
@@ -1215,34 +1966,200 @@ impl UIRenderer {
         execute!(
             stdout(),
             MoveTo(x + 2, y + 1),
-            SetForegroundColor(Color::Green),
+            SetForegroundColor(theme.title_text),
             Print("Text Extraction"),
             ResetColor
         )?;
         
-        // Render extracted text content
+        // Render extracted text content through the editor (cursor, scrolling, edits)
         let content_start_y = y + 3;
         let content_height = height.saturating_sub(4);
         let content_width = width.saturating_sub(4);
         
-        for (row_idx, row) in self.pdf_content.iter().enumerate().take(content_height as usize) {
-            let display_y = content_start_y + row_idx as u16;
-            if display_y >= y + height {
-                break;
+        self.editor.render(x + 2, content_start_y, content_width, content_height)?;
+        
+        Ok(())
+    }
+    
+    /// Route a key to the text editor panel. Returns true if the editor consumed it.
+    pub fn handle_editor_input(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let handled = self.editor_input(key);
+        if handled {
+            self.save_edits();
+        }
+        handled
+    }
+    
+    /// Route a mouse event to the text editor panel: a click places the cursor,
+    /// dragging selects a block, a double click selects a word. Returns true if
+    /// the event landed on the text.
+    pub fn handle_editor_mouse(&mut self, mouse: crossterm::event::MouseEvent) -> bool {
+        use crossterm::event::{MouseButton, MouseEventKind};
+        if self.current_screen != Screen::PdfViewer || self.maximized == Some(Pane::Pdf) {
+            return false;
+        }
+        let Some(cell) = self.editor.cell_at(ScreenCell::new(mouse.column, mouse.row)) else { return false };
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let double = self.last_click.is_some_and(|(at, last)| last == cell && at.elapsed() < DOUBLE_CLICK);
+                if double && self.editor.select_word(cell) {
+                    self.last_click = None;
+                } else {
+                    self.editor.click(cell);
+                    self.last_click = Some((Instant::now(), cell));
+                }
+                true
             }
-            
-            execute!(stdout(), MoveTo(x + 2, display_y))?;
-            
-            // Convert chars to string for display
-            let line: String = row.iter().take(content_width as usize).collect();
-            execute!(
-                stdout(),
-                SetForegroundColor(Color::White),
-                Print(&line),
-                ResetColor
-            )?;
+            MouseEventKind::Drag(MouseButton::Left) => {
+                self.editor.drag_to(cell);
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    fn copy_block_selection(&mut self) {
+        let Some(rect) = self.editor.block_selection() else { return };
+        self.copy_to_clipboard(&block_text(self.editor.buffer(), rect), &format!("{}x{} block", rect.width(), rect.height()));
+        self.editor.clear_block_selection();
+    }
+    
+    /// Ctrl+C without a block copies the cursor's line, Ctrl+A the whole page
+    fn copy_rows(&mut self, whole_page: bool) {
+        let buffer = self.editor.buffer();
+        let (text, what) = if whole_page {
+            (clipboard::grid_text(buffer), format!("page {}", self.current_page))
+        } else {
+            let row = self.editor.cursor().1;
+            (clipboard::grid_text(buffer.get(row..=row).unwrap_or(&[])), format!("line {}", row + 1))
+        };
+        self.copy_to_clipboard(&text, &what);
+    }
+    
+    fn copy_to_clipboard(&mut self, text: &str, what: &str) {
+        match clipboard::copy(text) {
+            Ok(method) => self.add_debug_message(format!("Copied {} ({} chars) via {}", what, text.chars().count(), method)),
+            Err(e) => self.add_debug_message(format!("Copy failed: {}", e)),
+        }
+    }
+    
+    /// Keys that change the grid rather than move around or search it
+    fn is_edit_key(&self, key: &crossterm::event::KeyEvent) -> bool {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('z') | KeyCode::Char('y') if ctrl => true,
+            _ if ctrl || self.editor.search().typing || !self.editor.is_inserting() => false,
+            KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Delete => true,
+            _ => false,
+        }
+    }
+    
+    fn editor_input(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        
+        // Block selection - arrows drag the corner, y or Ctrl+C copies, Esc cancels
+        if self.editor.block_selection().is_some() {
+            match (key.code, key.modifiers.contains(KeyModifiers::CONTROL)) {
+                (KeyCode::Char('y'), false) | (KeyCode::Char('c'), true) => self.copy_block_selection(),
+                (KeyCode::Esc, _) | (KeyCode::Char('v'), true) => self.editor.clear_block_selection(),
+                (KeyCode::Up, _) => self.editor.move_cursor(0, -1),
+                (KeyCode::Down, _) => self.editor.move_cursor(0, 1),
+                (KeyCode::Left, _) => self.editor.move_cursor(-1, 0),
+                (KeyCode::Right, _) => self.editor.move_cursor(1, 0),
+                // Nothing else edits while a block is selected
+                _ => {}
+            }
+            return true;
         }
         
-        Ok(())
+        // The raw extraction and translations are for comparing - edits only go to the edited version
+        if self.page_view != PageView::Edited && self.is_edit_key(&key) {
+            self.add_debug_message("Only the edited version takes edits - Ctrl+R/Ctrl+T to switch back".to_string());
+            return true;
+        }
+        
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return match key.code {
+                KeyCode::Char('r') => {
+                    self.toggle_raw();
+                    true
+                }
+                KeyCode::Char('t') => {
+                    self.cycle_translation();
+                    true
+                }
+                KeyCode::Char('v') => {
+                    self.editor.start_block_selection();
+                    true
+                }
+                KeyCode::Char('c') => {
+                    self.copy_rows(false);
+                    true
+                }
+                KeyCode::Char('a') => {
+                    self.copy_rows(true);
+                    true
+                }
+                KeyCode::Char('z') => {
+                    if !self.editor.undo() {
+                        self.add_debug_message("Nothing to undo".to_string());
+                    }
+                    true
+                }
+                KeyCode::Char('y') => {
+                    if !self.editor.redo() {
+                        self.add_debug_message("Nothing to redo".to_string());
+                    }
+                    true
+                }
+                _ => false,
+            };
+        }
+        
+        // Typing a search query
+        if self.editor.search().typing {
+            match key.code {
+                KeyCode::Enter => self.editor.confirm_search(),
+                KeyCode::Esc => self.editor.clear_search(),
+                KeyCode::Backspace => self.editor.search_pop(),
+                KeyCode::Char(c) => self.editor.search_push(c),
+                _ => return false,
+            }
+            return true;
+        }
+        
+        let inserting = self.editor.is_inserting();
+        match key.code {
+            KeyCode::Up => self.editor.move_cursor(0, -1),
+            KeyCode::Down => self.editor.move_cursor(0, 1),
+            KeyCode::Left => self.editor.move_cursor(-1, 0),
+            KeyCode::Right => self.editor.move_cursor(1, 0),
+            KeyCode::Backspace if inserting => self.editor.delete_backward(),
+            KeyCode::Delete if inserting => self.editor.delete_forward(),
+            KeyCode::Char(c) if inserting => self.editor.insert_char(c),
+            KeyCode::Esc if inserting => self.editor.set_inserting(false),
+            // Not inserting - / searches, n/N step through the hits, i goes back to typing
+            KeyCode::Char('/') => self.editor.start_search(),
+            KeyCode::Char('n') => self.step_match(true),
+            KeyCode::Char('N') => self.step_match(false),
+            KeyCode::Char('i') => self.editor.set_inserting(true),
+            KeyCode::Esc if self.editor.search().is_active() => self.editor.clear_search(),
+            // Any other letter is swallowed rather than typed
+            KeyCode::Char(_) => {}
+            _ => return false,
+        }
+        true
+    }
+    
+    fn step_match(&mut self, forward: bool) {
+        let found = if forward { self.editor.next_match() } else { self.editor.prev_match() };
+        if !found {
+            let message = match self.editor.search().query.as_str() {
+                "" => "No search yet - / to start one".to_string(),
+                query => format!("Pattern not found: {}", query),
+            };
+            self.add_debug_message(message);
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,7 @@
+// Views - what you see on screen
+//
+// - text_editor: right side of the PDF viewer, editable extracted text grid
+// - page_overview: thumbnail grid of every page, for jumping around long documents
+
+pub mod page_overview;
+pub mod text_editor;
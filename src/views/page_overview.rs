@@ -0,0 +1,166 @@
+// Page overview - a grid of small page renders to pick a page from
+//
+// The grid is drawn as one contact-sheet image so it goes through the same
+// graphics backend as the page panel, whatever protocol that is. Thumbnails come
+// from their own render cache (big enough for a few screens of them, so they
+// don't push the full-size pages out); pages not rendered yet show as blank tiles
+// with their number until their render lands.
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::overlay_gif::draw_text;
+
+/// Size thumbnails are rendered at
+pub const THUMBNAIL_SIZE: (u32, u32) = (112, 140);
+/// Thumbnails kept - about 60 KB each
+pub const THUMBNAIL_CAPACITY: usize = 256;
+/// Terminal cells per tile; with cells about twice as tall as wide this matches TILE_PIXELS
+pub const TILE_CELLS: (u16, u16) = (16, 10);
+const TILE_PIXELS: (u32, u32) = (128, 160);
+/// Strip under each thumbnail for its page number
+const LABEL_HEIGHT: u32 = 14;
+
+const BACKGROUND: Rgba<u8> = Rgba([20, 20, 28, 255]);
+const PLACEHOLDER: Rgba<u8> = Rgba([45, 45, 58, 255]);
+const LABEL: Rgba<u8> = Rgba([170, 170, 190, 255]);
+const SELECTED: Rgba<u8> = Rgba([100, 200, 255, 255]);
+
+/// Which page is selected and which rows of the grid are on screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageOverview {
+    /// 0-based
+    selected: usize,
+    total: usize,
+    columns: usize,
+    rows: usize,
+    /// First grid row on screen
+    top_row: usize,
+}
+
+impl PageOverview {
+    pub fn new(total: usize, selected_page: usize) -> Self {
+        let total = total.max(1);
+        Self { selected: selected_page.clamp(1, total) - 1, total, columns: 1, rows: 1, top_row: 0 }
+    }
+
+    /// Fit the grid to an area of `cols` x `rows` cells; returns the tiles across and down
+    pub fn layout(&mut self, cols: u16, rows: u16) -> (usize, usize) {
+        self.columns = (cols / TILE_CELLS.0).max(1) as usize;
+        self.rows = (rows / TILE_CELLS.1).max(1) as usize;
+        self.scroll_to_selected();
+        (self.columns, self.rows)
+    }
+
+    /// 1-based
+    pub fn selected_page(&self) -> usize {
+        self.selected + 1
+    }
+
+    /// Move the selection by whole tiles, stopping at the first and last page
+    pub fn move_by(&mut self, across: isize, down: isize) {
+        let target = self.selected as isize + across + down * self.columns as isize;
+        self.selected = target.clamp(0, self.total as isize - 1) as usize;
+        self.scroll_to_selected();
+    }
+
+    /// A screenful of rows up or down
+    pub fn page_by(&mut self, screens: isize) {
+        self.move_by(0, screens * self.rows as isize);
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+        self.scroll_to_selected();
+    }
+
+    pub fn select_last(&mut self) {
+        self.selected = self.total - 1;
+        self.scroll_to_selected();
+    }
+
+    /// Pages on screen, 0-based
+    pub fn visible(&self) -> Range<usize> {
+        let first = self.top_row * self.columns;
+        first..(first + self.columns * self.rows).min(self.total)
+    }
+
+    fn scroll_to_selected(&mut self) {
+        let row = self.selected / self.columns;
+        if row < self.top_row {
+            self.top_row = row;
+        } else if row >= self.top_row + self.rows {
+            self.top_row = row + 1 - self.rows;
+        }
+    }
+
+    /// The visible tiles as one image. `thumbnail` gives a page's render (0-based)
+    /// when there is one yet.
+    pub fn contact_sheet(&self, thumbnail: impl Fn(usize) -> Option<Arc<DynamicImage>>) -> DynamicImage {
+        let (tile_width, tile_height) = TILE_PIXELS;
+        let mut sheet = RgbaImage::from_pixel(tile_width * self.columns as u32, tile_height * self.rows as u32, BACKGROUND);
+        let (slot_width, slot_height) = (tile_width - 8, tile_height - 8 - LABEL_HEIGHT);
+        for (i, page) in self.visible().enumerate() {
+            let left = (i % self.columns) as u32 * tile_width;
+            let top = (i / self.columns) as u32 * tile_height;
+            if page == self.selected {
+                fill(&mut sheet, left + 1, top + 1, tile_width - 2, tile_height - 2, SELECTED);
+                fill(&mut sheet, left + 3, top + 3, tile_width - 6, tile_height - 6, BACKGROUND);
+            }
+            match thumbnail(page) {
+                Some(image) => {
+                    let fitted = image.resize(slot_width, slot_height, imageops::FilterType::Triangle).to_rgba8();
+                    let x = left + 4 + (slot_width - fitted.width()) / 2;
+                    let y = top + 4 + (slot_height - fitted.height()) / 2;
+                    imageops::overlay(&mut sheet, &fitted, x as i64, y as i64);
+                }
+                None => fill(&mut sheet, left + 4 + slot_width / 6, top + 4, slot_width * 2 / 3, slot_height, PLACEHOLDER),
+            }
+            let label = (page + 1).to_string();
+            let color = if page == self.selected { SELECTED } else { LABEL };
+            let label_x = left + (tile_width - 6 * label.len() as u32) / 2;
+            draw_text(&mut sheet, label_x, top + tile_height - 4 - LABEL_HEIGHT + 3, &label, 1, color);
+        }
+        DynamicImage::ImageRgba8(sheet)
+    }
+}
+
+fn fill(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for py in y..(y + height).min(image.height()) {
+        for px in x..(x + width).min(image.width()) {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navigation_scrolls_and_sheet_marks_selection() {
+        // 120 pages, 4 across and 2 down
+        let mut overview = PageOverview::new(120, 1);
+        assert_eq!(overview.layout(70, 25), (4, 2));
+        assert_eq!(overview.visible(), 0..8);
+
+        overview.move_by(1, 2);
+        assert_eq!(overview.selected_page(), 10);
+        assert_eq!(overview.visible(), 4..12);
+        overview.page_by(1);
+        assert_eq!(overview.selected_page(), 18);
+        overview.move_by(-100, 0);
+        assert_eq!(overview.selected_page(), 1);
+        assert_eq!(overview.visible(), 0..8);
+        overview.select_last();
+        assert_eq!(overview.visible(), 112..120);
+
+        let page = Arc::new(DynamicImage::ImageRgba8(RgbaImage::from_pixel(112, 140, Rgba([255, 255, 255, 255]))));
+        let sheet = overview.contact_sheet(|i| (i == 119).then(|| page.clone())).to_rgba8();
+        assert_eq!(sheet.dimensions(), (4 * 128, 2 * 160));
+        // Last tile (bottom right) is selected and has its render, the one before is a placeholder
+        assert_eq!(*sheet.get_pixel(3 * 128 + 1, 160 + 1), SELECTED);
+        assert_eq!(*sheet.get_pixel(3 * 128 + 64, 160 + 60), Rgba([255, 255, 255, 255]));
+        assert_eq!(*sheet.get_pixel(2 * 128 + 64, 160 + 60), PLACEHOLDER);
+    }
+}
@@ -0,0 +1,107 @@
+// Line-level diff between two versions of a page's grid
+//
+// Versions are the raw extraction, a re-extraction, or any point in the edit
+// log. Rows are compared with trailing blanks trimmed, since padding is an
+// artifact of the grid rather than a change anyone made.
+
+/// One line of a diff, in display order
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+impl DiffLine {
+    pub fn text(&self) -> &str {
+        match self {
+            DiffLine::Same(text) | DiffLine::Added(text) | DiffLine::Removed(text) => text,
+        }
+    }
+
+    /// "+", "-" or " ", as in a unified diff
+    pub fn marker(&self) -> char {
+        match self {
+            DiffLine::Same(_) => ' ',
+            DiffLine::Added(_) => '+',
+            DiffLine::Removed(_) => '-',
+        }
+    }
+}
+
+/// A grid's rows as lines, trailing blanks dropped
+pub fn grid_lines(grid: &[Vec<char>]) -> Vec<String> {
+    let mut lines: Vec<String> = grid.iter().map(|row| row.iter().collect::<String>().trim_end().to_string()).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Longest-common-subsequence diff; within a changed run, removals come before additions
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    // common[i][j] = LCS length of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i].clone()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(DiffLine::Removed(old[i].clone()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j].clone()));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Number of (added, removed) lines
+pub fn diff_stats(lines: &[DiffLine]) -> (usize, usize) {
+    lines.iter().fold((0, 0), |(added, removed), line| match line {
+        DiffLine::Added(_) => (added + 1, removed),
+        DiffLine::Removed(_) => (added, removed + 1),
+        DiffLine::Same(_) => (added, removed),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_marks_changed_lines() {
+        let grid = |lines: &[&str]| -> Vec<Vec<char>> { lines.iter().map(|l| l.chars().collect()).collect() };
+        let raw = grid_lines(&grid(&["Invoice   ", "Tota1: 42.00", "Thank you", "   "]));
+        let edited = grid_lines(&grid(&["Invoice", "Total: 42.00", "Thank you", "Paid 3 May"]));
+        assert_eq!(raw.len(), 3);
+
+        let diff = diff_lines(&raw, &edited);
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("Invoice".to_string()),
+                DiffLine::Removed("Tota1: 42.00".to_string()),
+                DiffLine::Added("Total: 42.00".to_string()),
+                DiffLine::Same("Thank you".to_string()),
+                DiffLine::Added("Paid 3 May".to_string()),
+            ]
+        );
+        assert_eq!(diff_stats(&diff), (2, 1));
+        assert!(diff_lines(&raw, &raw).iter().all(|line| line.marker() == ' '));
+    }
+}
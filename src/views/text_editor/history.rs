@@ -0,0 +1,262 @@
+// Undo/redo history for the text editor - command pattern over grid cell edits
+//
+// Every mutation is also an EditEvent. Appended to a per-page log (see
+// storage::edits), the events are the source of truth: `replay` rebuilds the grid
+// and the undo/redo stacks from them, so an edit made in the TUI can be undone
+// from the CLI and the other way round.
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of undo steps kept per buffer
+pub const MAX_UNDO_DEPTH: usize = 500;
+
+/// A single cell change in the text grid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellEdit {
+    pub row: usize,
+    pub col: usize,
+    pub before: char,
+    pub after: char,
+}
+
+/// One undoable step - a group of cell edits plus the cursor on either side of it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditCommand {
+    pub edits: Vec<CellEdit>,
+    pub cursor_before: (usize, usize), // (x, y)
+    pub cursor_after: (usize, usize),
+}
+
+impl EditCommand {
+    /// Apply the command to a grid, forwards (redo) or backwards (undo)
+    pub fn apply(&self, grid: &mut Vec<Vec<char>>, forward: bool) {
+        let mut apply_one = |edit: &CellEdit| {
+            if grid.len() <= edit.row {
+                grid.resize(edit.row + 1, Vec::new());
+            }
+            let row = &mut grid[edit.row];
+            if row.len() <= edit.col {
+                row.resize(edit.col + 1, ' ');
+            }
+            row[edit.col] = if forward { edit.after } else { edit.before };
+        };
+
+        // Undo walks edits in reverse so overlapping cells end up in their original state
+        if forward {
+            self.edits.iter().for_each(&mut apply_one);
+        } else {
+            self.edits.iter().rev().for_each(&mut apply_one);
+        }
+    }
+}
+
+/// Undo/redo stacks. Consecutive typing is coalesced into one step until the
+/// group is broken (cursor movement, undo, or a pause in editing).
+#[derive(Debug)]
+pub struct EditHistory {
+    undo_stack: Vec<EditCommand>,
+    redo_stack: Vec<EditCommand>,
+    max_depth: usize,
+    group_open: bool,
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        Self::with_depth(MAX_UNDO_DEPTH)
+    }
+
+    pub fn with_depth(max_depth: usize) -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            max_depth: max_depth.max(1),
+            group_open: false,
+        }
+    }
+
+    /// Record an edit that has already been applied to the buffer. Returns whether
+    /// it was merged into the previous step.
+    pub fn record(&mut self, command: EditCommand) -> bool {
+        let merge = self.group_open
+            && self.undo_stack.last().is_some_and(|last| last.cursor_after == command.cursor_before);
+        self.push(command, merge);
+        merge
+    }
+
+    /// Record an edit, merging it into the previous step or not as told - for
+    /// replaying a log, where grouping was already decided when the edit was made
+    pub fn push(&mut self, command: EditCommand, merge: bool) {
+        self.redo_stack.clear();
+
+        if merge {
+            if let Some(last) = self.undo_stack.last_mut() {
+                last.edits.extend(command.edits);
+                last.cursor_after = command.cursor_after;
+                return;
+            }
+        }
+
+        self.undo_stack.push(command);
+        if self.undo_stack.len() > self.max_depth {
+            self.undo_stack.remove(0);
+        }
+        self.group_open = true;
+    }
+
+    /// Stop coalescing - the next edit starts a new undo step
+    pub fn break_group(&mut self) {
+        self.group_open = false;
+    }
+
+    /// Pop the latest step; the caller reverts it with `apply(grid, false)`
+    pub fn undo(&mut self) -> Option<EditCommand> {
+        self.group_open = false;
+        let command = self.undo_stack.pop()?;
+        self.redo_stack.push(command.clone());
+        Some(command)
+    }
+
+    /// Pop the latest undone step; the caller re-applies it with `apply(grid, true)`
+    pub fn redo(&mut self) -> Option<EditCommand> {
+        self.group_open = false;
+        let command = self.redo_stack.pop()?;
+        self.undo_stack.push(command.clone());
+        Some(command)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.group_open = false;
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One entry of a page's edit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EditEvent {
+    /// The grid as extracted, before any edit; always the first event
+    Base { rows: Vec<String> },
+    Edit { command: EditCommand, merged: bool },
+    Undo,
+    Redo,
+}
+
+impl EditEvent {
+    pub fn base(grid: &[Vec<char>]) -> Self {
+        EditEvent::Base { rows: grid.iter().map(|row| row.iter().collect()).collect() }
+    }
+}
+
+/// Rebuild the grid and the undo/redo stacks from a log. Undo and redo events
+/// with nothing to act on are skipped, as they were when they were made.
+pub fn replay(events: &[EditEvent]) -> (Vec<Vec<char>>, EditHistory) {
+    let mut grid: Vec<Vec<char>> = Vec::new();
+    let mut history = EditHistory::new();
+    for event in events {
+        match event {
+            EditEvent::Base { rows } => {
+                grid = rows.iter().map(|row| row.chars().collect()).collect();
+                history.clear();
+            }
+            EditEvent::Edit { command, merged } => {
+                command.apply(&mut grid, true);
+                history.push(command.clone(), *merged);
+            }
+            EditEvent::Undo => {
+                if let Some(command) = history.undo() {
+                    command.apply(&mut grid, false);
+                }
+            }
+            EditEvent::Redo => {
+                if let Some(command) = history.redo() {
+                    command.apply(&mut grid, true);
+                }
+            }
+        }
+    }
+    (grid, history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(col: usize, before: char, after: char) -> EditCommand {
+        EditCommand {
+            edits: vec![CellEdit { row: 0, col, before, after }],
+            cursor_before: (col, 0),
+            cursor_after: (col + 1, 0),
+        }
+    }
+
+    #[test]
+    fn test_undo_redo_roundtrip() {
+        let mut grid = vec![vec!['a', 'b', 'c']];
+        let mut history = EditHistory::new();
+
+        let cmd = typed(1, 'b', 'X');
+        cmd.apply(&mut grid, true);
+        history.record(cmd);
+        assert_eq!(grid[0], vec!['a', 'X', 'c']);
+
+        history.undo().unwrap().apply(&mut grid, false);
+        assert_eq!(grid[0], vec!['a', 'b', 'c']);
+
+        history.redo().unwrap().apply(&mut grid, true);
+        assert_eq!(grid[0], vec!['a', 'X', 'c']);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_typing_coalesces_until_group_break() {
+        let mut history = EditHistory::new();
+        history.record(typed(0, ' ', 'h'));
+        history.record(typed(1, ' ', 'i'));
+        history.break_group();
+        history.record(typed(2, ' ', '!'));
+
+        assert_eq!(history.undo().unwrap().edits.len(), 1);
+        assert_eq!(history.undo().unwrap().edits.len(), 2);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_replay_matches_live_history() {
+        let mut grid = vec![vec![' ', ' ', ' ']];
+        let mut history = EditHistory::new();
+        let mut log = vec![EditEvent::base(&grid)];
+
+        for command in [typed(0, ' ', 'h'), typed(1, ' ', 'i')] {
+            command.apply(&mut grid, true);
+            let merged = history.record(command.clone());
+            log.push(EditEvent::Edit { command, merged });
+        }
+        history.break_group();
+        let command = typed(2, ' ', '!');
+        command.apply(&mut grid, true);
+        let merged = history.record(command.clone());
+        log.push(EditEvent::Edit { command, merged });
+        history.undo().unwrap().apply(&mut grid, false);
+        log.push(EditEvent::Undo);
+
+        let json = serde_json::to_string(&log).unwrap();
+        let (replayed, mut replayed_history) = replay(&serde_json::from_str::<Vec<EditEvent>>(&json).unwrap());
+        assert_eq!(replayed, grid);
+        assert_eq!(replayed_history.undo(), history.undo());
+        assert_eq!(replayed_history.can_redo(), history.can_redo());
+    }
+}
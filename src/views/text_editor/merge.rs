@@ -0,0 +1,104 @@
+// Three-way merge of a page's grid - for two editors who changed one page at once
+//
+// Base is the page as both started from, ours the local edits, theirs what the
+// log holds now. Grids are merged cell by cell: a cell changed on one side takes
+// that side's character, a cell both changed to different characters is a
+// conflict. The merged grid keeps ours for conflicting cells.
+use super::history::{CellEdit, EditCommand};
+
+/// How a row of the merge came about
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowMerge {
+    Same,
+    Ours,
+    Theirs,
+    /// Both sides changed it, in different cells or the same way
+    Both,
+    /// Both sides changed a cell differently
+    Conflict,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    /// Shaped like theirs
+    pub grid: Vec<Vec<char>>,
+    pub rows: Vec<RowMerge>,
+    pub conflicts: usize,
+}
+
+fn cell(grid: &[Vec<char>], row: usize, col: usize) -> char {
+    grid.get(row).and_then(|cells| cells.get(col)).copied().unwrap_or(' ')
+}
+
+pub fn merge3(base: &[Vec<char>], ours: &[Vec<char>], theirs: &[Vec<char>]) -> Merge {
+    let mut grid = theirs.to_vec();
+    let mut rows = Vec::with_capacity(theirs.len());
+    let mut conflicts = 0;
+    for (row, cells) in grid.iter_mut().enumerate() {
+        let (mut ours_changed, mut theirs_changed, mut conflicted) = (false, false, false);
+        for (col, merged) in cells.iter_mut().enumerate() {
+            let (b, o, t) = (cell(base, row, col), cell(ours, row, col), cell(theirs, row, col));
+            ours_changed |= o != b;
+            theirs_changed |= t != b;
+            if o != b && t != b && o != t {
+                conflicted = true;
+                conflicts += 1;
+            }
+            if o != b {
+                *merged = o;
+            }
+        }
+        rows.push(match (conflicted, ours_changed, theirs_changed) {
+            (true, _, _) => RowMerge::Conflict,
+            (false, true, true) => RowMerge::Both,
+            (false, true, false) => RowMerge::Ours,
+            (false, false, true) => RowMerge::Theirs,
+            (false, false, false) => RowMerge::Same,
+        });
+    }
+    Merge { grid, rows, conflicts }
+}
+
+/// The one edit that turns `from` into `to`, cells outside `from` ignored; None
+/// when they already match
+pub fn edit_between(from: &[Vec<char>], to: &[Vec<char>]) -> Option<EditCommand> {
+    let mut edits = Vec::new();
+    for (row, cells) in from.iter().enumerate() {
+        for (col, &before) in cells.iter().enumerate() {
+            let after = cell(to, row, col);
+            if after != before {
+                edits.push(CellEdit { row, col, before, after });
+            }
+        }
+    }
+    let cursor = edits.first().map(|edit| (edit.col, edit.row))?;
+    Some(EditCommand { edits, cursor_before: cursor, cursor_after: cursor })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Vec<Vec<char>> {
+        rows.iter().map(|row| row.chars().collect()).collect()
+    }
+
+    #[test]
+    fn test_one_sided_changes_merge_and_clashes_keep_ours() {
+        let base = grid(&["total 10", "date", "page"]);
+        let ours = grid(&["Total 10", "date", "pAge"]);
+        let theirs = grid(&["total 10", "Date", "paGe"]);
+        let merge = merge3(&base, &ours, &theirs);
+        assert_eq!(merge.grid, grid(&["Total 10", "Date", "pAGe"]));
+        assert_eq!(merge.rows, vec![RowMerge::Ours, RowMerge::Theirs, RowMerge::Both]);
+        assert_eq!(merge.conflicts, 0);
+
+        let clash = merge3(&base, &grid(&["total 19", "date", "page"]), &grid(&["total 18", "date", "page"]));
+        assert_eq!(clash.rows[0], RowMerge::Conflict);
+        assert_eq!(clash.grid[0].iter().collect::<String>(), "total 19");
+
+        let edit = edit_between(&theirs, &merge.grid).unwrap();
+        assert_eq!(edit.edits.len(), 2);
+        assert!(edit_between(&theirs, &theirs).is_none());
+    }
+}
@@ -0,0 +1,641 @@
+// Text editor panel - editable view of the extracted text grid
+//
+// The buffer is the spatial character grid produced by extraction. Editing is
+// overtype-only so corrections never shift the layout of the rest of the page.
+use anyhow::Result;
+use crossterm::{
+    cursor::MoveTo,
+    execute,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+};
+use std::io::stdout;
+use std::ops::Range;
+
+use crate::coords::{GridCell, ScreenCell, Viewport};
+use crate::theme;
+
+pub mod diff;
+pub mod history;
+pub mod merge;
+pub mod replace;
+pub mod search;
+pub mod selection;
+
+pub use diff::{diff_lines, diff_stats, grid_lines, DiffLine};
+pub use history::{replay, CellEdit, EditCommand, EditEvent, EditHistory};
+pub use merge::{edit_between, merge3, Merge, RowMerge};
+pub use replace::{replace_in_grid, revert, LineChange};
+pub use search::{SearchMatch, SearchState};
+pub use selection::{block_text, BlockRect};
+
+/// A run of highlighted cells on one buffer row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Highlight {
+    pub row: usize,
+    pub col: usize,
+    pub len: usize,
+    pub color: Color,
+}
+
+pub struct EditPanelRenderer {
+    buffer: Vec<Vec<char>>,
+    cursor_x: usize,
+    cursor_y: usize,
+    scroll_x: usize,
+    scroll_y: usize,
+    // Viewport size from the last render or resize(). clamp_scroll() re-validates
+    // the scroll offsets whenever this or the buffer changes.
+    viewport_width: usize,
+    viewport_height: usize,
+    /// Where the last render put the top-left cell, for mapping mouse clicks
+    origin: ScreenCell,
+    history: EditHistory,
+    /// Events not yet handed to the edit log, oldest first
+    pending_events: Vec<EditEvent>,
+    search: SearchState,
+    /// Corner the block selection started from (col, row); the cursor is the other
+    block_anchor: Option<(usize, usize)>,
+    /// Printable keys type into the grid; otherwise they search and navigate
+    inserting: bool,
+}
+
+impl EditPanelRenderer {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            cursor_x: 0,
+            cursor_y: 0,
+            scroll_x: 0,
+            scroll_y: 0,
+            viewport_width: 80,
+            viewport_height: 24,
+            origin: ScreenCell::new(0, 0),
+            history: EditHistory::new(),
+            pending_events: Vec::new(),
+            search: SearchState::default(),
+            block_anchor: None,
+            inserting: true,
+        }
+    }
+
+    /// Replace the buffer (new page or new document). Edit history belongs to the
+    /// old buffer, so it is dropped.
+    pub fn set_buffer(&mut self, buffer: Vec<Vec<char>>) {
+        self.pending_events = vec![EditEvent::base(&buffer)];
+        self.buffer = buffer;
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.scroll_x = 0;
+        self.scroll_y = 0;
+        self.history.clear();
+        self.block_anchor = None;
+        self.refresh_search();
+        self.clamp_scroll();
+    }
+
+    /// Replace the buffer and history with what a page's edit log says they are
+    pub fn load_events(&mut self, events: &[EditEvent]) {
+        let (buffer, history) = replay(events);
+        self.set_buffer(buffer);
+        self.history = history;
+        self.history.break_group();
+        self.pending_events.clear();
+    }
+
+    /// Events to append to the edit log. Nothing is handed out until there is a
+    /// real edit, so merely viewing a page doesn't start a log for it.
+    pub fn take_events(&mut self) -> Vec<EditEvent> {
+        if self.pending_events.iter().all(|e| matches!(e, EditEvent::Base { .. })) {
+            return Vec::new();
+        }
+        std::mem::take(&mut self.pending_events)
+    }
+
+    /// Update the viewport size (terminal resize) and re-validate scrolling
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.viewport_width = width as usize;
+        self.viewport_height = height as usize;
+        self.ensure_cursor_visible();
+        self.clamp_scroll();
+    }
+
+    pub fn scroll(&self) -> (usize, usize) {
+        (self.scroll_x, self.scroll_y)
+    }
+
+    /// Rows the viewport covers, including any past the end of the text
+    pub fn visible_rows(&self) -> Range<usize> {
+        self.scroll_y..self.scroll_y + self.viewport_height.max(1)
+    }
+
+    /// Scroll by whole rows without moving the view back to the cursor; the cursor
+    /// is carried along when it would go off screen
+    pub fn scroll_rows(&mut self, rows: isize) {
+        self.scroll_y = self.scroll_y.saturating_add_signed(rows);
+        self.clamp_scroll();
+        let last_visible = self.scroll_y + self.viewport_height.max(1) - 1;
+        self.cursor_y = self.cursor_y.clamp(self.scroll_y, last_visible).min(self.buffer.len().saturating_sub(1));
+    }
+
+    pub fn buffer(&self) -> &Vec<Vec<char>> {
+        &self.buffer
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor_x, self.cursor_y)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Draw the visible part of the buffer into the given screen rectangle
+    pub fn render(&mut self, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
+        self.render_with_highlights(x, y, width, height, &[])
+    }
+
+    /// Draw the buffer with extra highlighted cell runs on top of the search
+    /// matches. Later highlights win where runs overlap.
+    pub fn render_with_highlights(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        highlights: &[Highlight],
+    ) -> Result<()> {
+        if (width as usize, height as usize) != (self.viewport_width, self.viewport_height) {
+            self.resize(width, height);
+        }
+        self.origin = ScreenCell::new(x, y);
+
+        let mut all_highlights = self.search_highlights();
+        all_highlights.extend(self.selection_highlights());
+        all_highlights.extend_from_slice(highlights);
+
+        let theme = theme::current();
+        let view = self.viewport(ScreenCell::new(x, y));
+        for screen_row in 0..height {
+            let row_start = view.grid_at(0, screen_row);
+            let line_start = view.to_screen(row_start).unwrap_or(ScreenCell::new(x, y + screen_row));
+            execute!(stdout(), MoveTo(line_start.x, line_start.y))?;
+
+            // Background per visible cell, then print runs of equal background
+            let cells: Vec<char> = (0..width)
+                .map(|dx| {
+                    let cell = view.grid_at(dx, screen_row);
+                    self.char_at(cell.col, cell.row)
+                })
+                .collect();
+            let mut backgrounds: Vec<Option<Color>> = vec![None; cells.len()];
+            for h in all_highlights.iter().filter(|h| h.row == row_start.row) {
+                for col in h.col..h.col + h.len {
+                    if let Some(screen) = view.to_screen(GridCell::new(col, h.row)) {
+                        backgrounds[(screen.x - x) as usize] = Some(h.color);
+                    }
+                }
+            }
+
+            let mut start = 0;
+            while start < cells.len() {
+                let bg = backgrounds[start];
+                let end = (start..cells.len()).find(|&i| backgrounds[i] != bg).unwrap_or(cells.len());
+                let run: String = cells[start..end].iter().collect();
+                match bg {
+                    Some(color) => execute!(
+                        stdout(),
+                        SetBackgroundColor(color),
+                        SetForegroundColor(theme.on_highlight),
+                        Print(run),
+                        ResetColor
+                    )?,
+                    None => execute!(stdout(), SetForegroundColor(theme.text), Print(run), ResetColor)?,
+                }
+                start = end;
+            }
+        }
+
+        // Draw the cursor cell with an inverted block
+        if let Some(screen) = view.to_screen(GridCell::new(self.cursor_x, self.cursor_y)) {
+            let ch = self.char_at(self.cursor_x, self.cursor_y);
+            execute!(
+                stdout(),
+                MoveTo(screen.x, screen.y),
+                SetBackgroundColor(theme.cursor),
+                SetForegroundColor(theme.on_highlight),
+                Print(ch),
+                ResetColor
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The part of the grid on screen, drawn with its top-left corner at `origin`
+    pub fn viewport(&self, origin: ScreenCell) -> Viewport {
+        Viewport {
+            origin,
+            scroll: GridCell::new(self.scroll_x, self.scroll_y),
+            width: self.viewport_width as u16,
+            height: self.viewport_height as u16,
+        }
+    }
+
+    /// The buffer cell under a terminal cell, if it's inside the panel as last drawn
+    pub fn cell_at(&self, screen: ScreenCell) -> Option<GridCell> {
+        self.viewport(self.origin).to_grid(screen)
+    }
+
+    fn char_at(&self, col: usize, row: usize) -> char {
+        self.buffer
+            .get(row)
+            .and_then(|r| r.get(col))
+            .copied()
+            .unwrap_or(' ')
+    }
+
+    // Cursor movement - any movement ends the current typing group
+    pub fn move_cursor(&mut self, dx: isize, dy: isize) {
+        self.history.break_group();
+        let max_y = self.buffer.len().saturating_sub(1);
+        self.cursor_y = (self.cursor_y as isize + dy).clamp(0, max_y as isize) as usize;
+        // A block keeps its columns across short rows, so it may reach past a row's end
+        let row_len = match self.block_anchor {
+            Some(_) => self.buffer.iter().map(|r| r.len()).max().unwrap_or(0),
+            None => self.buffer.get(self.cursor_y).map(|r| r.len()).unwrap_or(0),
+        };
+        let max_x = row_len.max(1) - 1;
+        self.cursor_x = (self.cursor_x as isize + dx).clamp(0, max_x as isize) as usize;
+        self.ensure_cursor_visible();
+    }
+
+    /// Overtype the character under the cursor and advance
+    pub fn insert_char(&mut self, ch: char) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        self.set_cells(vec![(y, x, ch)], (x + 1, y));
+    }
+
+    /// Blank the character left of the cursor and move onto it
+    pub fn delete_backward(&mut self) {
+        if self.cursor_x == 0 {
+            return;
+        }
+        let (x, y) = (self.cursor_x - 1, self.cursor_y);
+        self.set_cells(vec![(y, x, ' ')], (x, y));
+    }
+
+    /// Blank the character under the cursor
+    pub fn delete_forward(&mut self) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        self.set_cells(vec![(y, x, ' ')], (x, y));
+    }
+
+    /// Apply a set of (row, col, char) changes as one undoable command
+    pub fn set_cells(&mut self, changes: Vec<(usize, usize, char)>, cursor_after: (usize, usize)) {
+        let edits: Vec<CellEdit> = changes
+            .into_iter()
+            .map(|(row, col, after)| CellEdit { row, col, before: self.char_at(col, row), after })
+            .filter(|edit| edit.before != edit.after)
+            .collect();
+
+        let command = EditCommand {
+            edits,
+            cursor_before: (self.cursor_x, self.cursor_y),
+            cursor_after,
+        };
+        command.apply(&mut self.buffer, true);
+        self.cursor_x = command.cursor_after.0;
+        self.cursor_y = command.cursor_after.1;
+        if !command.edits.is_empty() {
+            let merged = self.history.record(command.clone());
+            self.pending_events.push(EditEvent::Edit { command, merged });
+            self.refresh_search();
+        }
+        self.ensure_cursor_visible();
+        self.clamp_scroll();
+    }
+
+    /// Revert the last edit step. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo() {
+            Some(command) => {
+                command.apply(&mut self.buffer, false);
+                self.pending_events.push(EditEvent::Undo);
+                (self.cursor_x, self.cursor_y) = command.cursor_before;
+                self.refresh_search();
+                self.ensure_cursor_visible();
+                self.clamp_scroll();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-apply the last undone step. Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo() {
+            Some(command) => {
+                command.apply(&mut self.buffer, true);
+                self.pending_events.push(EditEvent::Redo);
+                (self.cursor_x, self.cursor_y) = command.cursor_after;
+                self.refresh_search();
+                self.ensure_cursor_visible();
+                self.clamp_scroll();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Mouse - a click places the cursor, dragging selects a block, a double click a word
+
+    /// Put the cursor on a clicked cell, clamped to the text like arrow movement
+    pub fn click(&mut self, cell: GridCell) {
+        self.block_anchor = None;
+        self.cursor_y = cell.row;
+        self.cursor_x = cell.col;
+        self.move_cursor(0, 0);
+    }
+
+    /// Drag the selection's far corner to a cell, anchoring it at the cursor on the first drag
+    pub fn drag_to(&mut self, cell: GridCell) {
+        if self.block_anchor.is_none() {
+            self.start_block_selection();
+        }
+        self.cursor_y = cell.row;
+        self.cursor_x = cell.col;
+        self.move_cursor(0, 0);
+    }
+
+    /// Select the run of non-blank cells under a cell. Returns false on a blank.
+    pub fn select_word(&mut self, cell: GridCell) -> bool {
+        let Some(row) = self.buffer.get(cell.row) else { return false };
+        if row.get(cell.col).is_none_or(|ch| ch.is_whitespace()) {
+            return false;
+        }
+        let start = row[..cell.col].iter().rposition(|ch| ch.is_whitespace()).map_or(0, |i| i + 1);
+        let end = row[cell.col..].iter().position(|ch| ch.is_whitespace()).map_or(row.len(), |i| cell.col + i) - 1;
+        self.history.break_group();
+        self.block_anchor = Some((start, cell.row));
+        (self.cursor_x, self.cursor_y) = (end, cell.row);
+        self.ensure_cursor_visible();
+        true
+    }
+
+    // Block selection - Ctrl+V anchors a corner at the cursor, movement drags the other
+
+    pub fn start_block_selection(&mut self) {
+        self.history.break_group();
+        self.block_anchor = Some((self.cursor_x, self.cursor_y));
+    }
+
+    pub fn clear_block_selection(&mut self) {
+        self.block_anchor = None;
+    }
+
+    pub fn block_selection(&self) -> Option<BlockRect> {
+        self.block_anchor.map(|anchor| BlockRect::spanning(anchor, (self.cursor_x, self.cursor_y)))
+    }
+
+    fn selection_highlights(&self) -> Vec<Highlight> {
+        let Some(rect) = self.block_selection() else { return Vec::new() };
+        (rect.top..=rect.bottom)
+            .map(|row| Highlight { row, col: rect.left, len: rect.width(), color: theme::current().selection })
+            .collect()
+    }
+
+    // Modes - Esc stops inserting so `/` and n/N can search, i starts again
+
+    pub fn is_inserting(&self) -> bool {
+        self.inserting
+    }
+
+    pub fn set_inserting(&mut self, inserting: bool) {
+        self.history.break_group();
+        self.inserting = inserting;
+    }
+
+    // Search - `/` starts typing a query, matches update on every keystroke
+
+    pub fn search(&self) -> &SearchState {
+        &self.search
+    }
+
+    pub fn start_search(&mut self) {
+        self.search.clear();
+        self.search.typing = true;
+    }
+
+    pub fn search_push(&mut self, ch: char) {
+        self.search.query.push(ch);
+        self.update_search();
+    }
+
+    pub fn search_pop(&mut self) {
+        self.search.query.pop();
+        self.update_search();
+    }
+
+    /// Stop editing the query but keep the matches for n/N navigation
+    pub fn confirm_search(&mut self) {
+        self.search.typing = false;
+        if self.search.query.is_empty() {
+            self.search.clear();
+        }
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search.clear();
+    }
+
+    pub fn next_match(&mut self) -> bool {
+        let found = self.search.next_match();
+        self.jump_to_match(found)
+    }
+
+    pub fn prev_match(&mut self) -> bool {
+        let found = self.search.prev_match();
+        self.jump_to_match(found)
+    }
+
+    fn update_search(&mut self) {
+        let from = (self.cursor_y, self.cursor_x);
+        self.search.update(&self.buffer, from);
+        let current = self.search.current_match();
+        self.jump_to_match(current);
+    }
+
+    // Matches are recomputed after edits so highlights never point at stale text
+    fn refresh_search(&mut self) {
+        if !self.search.query.is_empty() {
+            let current = self.search.current;
+            self.search.matches = search::find_matches(&self.buffer, &self.search.query);
+            self.search.current = current.filter(|&i| i < self.search.matches.len());
+        }
+    }
+
+    fn jump_to_match(&mut self, found: Option<SearchMatch>) -> bool {
+        match found {
+            Some(m) => {
+                self.history.break_group();
+                self.cursor_x = m.col;
+                self.cursor_y = m.row;
+                self.ensure_cursor_visible();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn search_highlights(&self) -> Vec<Highlight> {
+        let current = self.search.current_match();
+        let theme = theme::current();
+        self.search
+            .matches
+            .iter()
+            .map(|m| Highlight {
+                row: m.row,
+                col: m.col,
+                len: m.len,
+                color: if Some(*m) == current { theme.search_current } else { theme.search_match },
+            })
+            .collect()
+    }
+
+    /// Pull scroll offsets back inside the content. Called after every resize and
+    /// buffer update so a shrinking buffer or growing viewport never leaves the
+    /// panel scrolled past the end of the text.
+    pub fn clamp_scroll(&mut self) {
+        let widest = self.buffer.iter().map(|row| row.len()).max().unwrap_or(0);
+        // The cursor may sit one past the end of a row after typing
+        let content_width = widest.max(self.cursor_x + 1);
+        let content_height = self.buffer.len().max(self.cursor_y + 1);
+
+        self.scroll_x = self.scroll_x.min(content_width.saturating_sub(self.viewport_width));
+        self.scroll_y = self.scroll_y.min(content_height.saturating_sub(self.viewport_height));
+    }
+
+    /// Scroll so the cursor stays within the viewport
+    fn ensure_cursor_visible(&mut self) {
+        if self.cursor_y < self.scroll_y {
+            self.scroll_y = self.cursor_y;
+        } else if self.viewport_height > 0 && self.cursor_y >= self.scroll_y + self.viewport_height {
+            self.scroll_y = self.cursor_y + 1 - self.viewport_height;
+        }
+
+        if self.cursor_x < self.scroll_x {
+            self.scroll_x = self.cursor_x;
+        } else if self.viewport_width > 0 && self.cursor_x >= self.scroll_x + self.viewport_width {
+            self.scroll_x = self.cursor_x + 1 - self.viewport_width;
+        }
+    }
+}
+
+impl Default for EditPanelRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(count: usize, width: usize) -> Vec<Vec<char>> {
+        vec![vec!['x'; width]; count]
+    }
+
+    #[test]
+    fn test_buffer_smaller_than_viewport_never_scrolls() {
+        let mut editor = EditPanelRenderer::new();
+        editor.set_buffer(lines(5, 10));
+        editor.resize(80, 24);
+
+        editor.scroll_x = 7;
+        editor.scroll_y = 3;
+        editor.clamp_scroll();
+        assert_eq!(editor.scroll(), (0, 0));
+    }
+
+    #[test]
+    fn test_shrink_while_scrolled() {
+        let mut editor = EditPanelRenderer::new();
+        editor.set_buffer(lines(100, 200));
+        editor.resize(40, 10);
+        editor.cursor_x = 150;
+        editor.cursor_y = 90;
+        editor.ensure_cursor_visible();
+        assert_eq!(editor.scroll(), (111, 81));
+
+        // Shorter page loaded while scrolled deep into the old one
+        editor.buffer = lines(20, 60);
+        editor.cursor_x = 0;
+        editor.cursor_y = 0;
+        editor.clamp_scroll();
+        assert_eq!(editor.scroll(), (20, 10));
+
+        // Terminal grows taller than the remaining content
+        editor.resize(40, 30);
+        assert_eq!(editor.scroll().1, 0);
+    }
+
+    #[test]
+    fn test_mouse_click_drag_and_word() {
+        let mut editor = EditPanelRenderer::new();
+        editor.set_buffer(["Invoice  No. 4417", "Total    89.00"].iter().map(|line| line.chars().collect()).collect());
+        editor.resize(40, 10);
+        editor.origin = ScreenCell::new(30, 3);
+        editor.scroll_x = 2;
+
+        // Panel column 4 is buffer column 6
+        let cell = editor.cell_at(ScreenCell::new(34, 4)).unwrap();
+        assert_eq!(cell, GridCell::new(6, 1));
+        assert_eq!(editor.cell_at(ScreenCell::new(29, 4)), None);
+        editor.click(GridCell::new(50, 1));
+        assert_eq!(editor.cursor(), (13, 1));
+
+        editor.click(GridCell::new(1, 0));
+        editor.drag_to(GridCell::new(3, 1));
+        assert_eq!(editor.block_selection(), Some(BlockRect::spanning((1, 0), (3, 1))));
+
+        assert!(editor.select_word(GridCell::new(14, 0)));
+        assert_eq!(block_text(editor.buffer(), editor.block_selection().unwrap()), "4417");
+        assert!(!editor.select_word(GridCell::new(8, 0)));
+    }
+
+    #[test]
+    fn test_scroll_rows_carries_cursor() {
+        let mut editor = EditPanelRenderer::new();
+        editor.set_buffer(lines(100, 10));
+        editor.resize(40, 10);
+        editor.scroll_rows(25);
+        assert_eq!(editor.visible_rows(), 25..35);
+        assert_eq!(editor.cursor(), (0, 25));
+        editor.scroll_rows(500);
+        assert_eq!(editor.visible_rows(), 90..100);
+        editor.scroll_rows(-95);
+        assert_eq!(editor.cursor(), (0, 9));
+    }
+
+    #[test]
+    fn test_leaving_insert_mode_ends_undo_step() {
+        let mut editor = EditPanelRenderer::new();
+        editor.set_buffer(vec![vec![' '; 5]]);
+        assert!(editor.is_inserting());
+
+        editor.insert_char('a');
+        editor.insert_char('b');
+        editor.set_inserting(false);
+        editor.set_inserting(true);
+        editor.insert_char('c');
+
+        assert!(editor.undo());
+        assert_eq!(editor.buffer()[0].iter().collect::<String>(), "ab   ");
+        assert!(editor.undo());
+        assert_eq!(editor.buffer()[0].iter().collect::<String>(), "     ");
+    }
+}
@@ -0,0 +1,97 @@
+// Find-and-replace over a text grid, as edit commands
+//
+// A replacement can change a line's length, so the rest of that line shifts; the
+// change is still recorded cell by cell (padding with spaces) so it undoes and
+// replays like any edit made in the editor. Matches never span lines.
+use regex::Regex;
+
+use super::history::{CellEdit, EditCommand};
+
+/// One changed line, for previews
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineChange {
+    pub row: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// Replace every match in the grid. None when nothing matches.
+pub fn replace_in_grid(grid: &[Vec<char>], pattern: &Regex, replacement: &str) -> Option<(EditCommand, Vec<LineChange>)> {
+    let mut edits = Vec::new();
+    let mut changes = Vec::new();
+    let mut cursor_before = None;
+    let mut cursor_after = (0, 0);
+
+    for (row, cells) in grid.iter().enumerate() {
+        let before: String = cells.iter().collect();
+        let Some(first) = pattern.find(&before) else { continue };
+        let after = pattern.replace_all(&before, replacement).to_string();
+        if after == before {
+            continue;
+        }
+
+        let (old, new): (Vec<char>, Vec<char>) = (before.chars().collect(), after.chars().collect());
+        for col in 0..old.len().max(new.len()) {
+            let (was, now) = (old.get(col).copied().unwrap_or(' '), new.get(col).copied().unwrap_or(' '));
+            if was != now {
+                edits.push(CellEdit { row, col, before: was, after: now });
+            }
+        }
+        cursor_before.get_or_insert((before[..first.start()].chars().count(), row));
+        cursor_after = (new.len(), row);
+        changes.push(LineChange { row, before, after });
+    }
+
+    let cursor_before = cursor_before?;
+    Some((EditCommand { edits, cursor_before, cursor_after }, changes))
+}
+
+/// The command that takes a grid back from `command`, provided none of its cells
+/// were changed again since. Err lists the rows that were.
+pub fn revert(grid: &[Vec<char>], command: &EditCommand) -> Result<EditCommand, Vec<usize>> {
+    let mut conflicts: Vec<usize> = command
+        .edits
+        .iter()
+        .filter(|edit| grid.get(edit.row).and_then(|row| row.get(edit.col)).copied().unwrap_or(' ') != edit.after)
+        .map(|edit| edit.row)
+        .collect();
+    if !conflicts.is_empty() {
+        conflicts.dedup();
+        return Err(conflicts);
+    }
+    Ok(EditCommand {
+        edits: command.edits.iter().rev().map(|e| CellEdit { row: e.row, col: e.col, before: e.after, after: e.before }).collect(),
+        cursor_before: command.cursor_after,
+        cursor_after: command.cursor_before,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_and_revert() {
+        let mut grid: Vec<Vec<char>> = ["Paid to ACME Corp.", "no match", "ACME Corp / ACME Corp"].iter().map(|l| l.chars().collect()).collect();
+        let original = grid.clone();
+        let pattern = Regex::new(&regex::escape("ACME Corp")).unwrap();
+
+        let (command, changes) = replace_in_grid(&grid, &pattern, "ACME Corporation").unwrap();
+        assert_eq!(changes.iter().map(|c| c.row).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(changes[0].after, "Paid to ACME Corporation.");
+        assert_eq!(command.cursor_before, (8, 0));
+
+        command.apply(&mut grid, true);
+        assert_eq!(grid[2].iter().collect::<String>(), "ACME Corporation / ACME Corporation");
+        let back = revert(&grid, &command).unwrap();
+        back.apply(&mut grid, true);
+        let trimmed: Vec<String> = grid.iter().map(|r| r.iter().collect::<String>().trim_end().to_string()).collect();
+        assert_eq!(trimmed, original.iter().map(|r| r.iter().collect::<String>()).collect::<Vec<_>>());
+
+        // Edited again since: the rollback refuses instead of clobbering it
+        command.apply(&mut grid, true);
+        grid[0][20] = 'x';
+        assert_eq!(revert(&grid, &command), Err(vec![0]));
+        assert!(replace_in_grid(&grid, &Regex::new("zzz").unwrap(), "y").is_none());
+    }
+}
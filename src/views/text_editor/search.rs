@@ -0,0 +1,124 @@
+// Incremental search over the text grid
+
+/// A match location in the grid (row, starting column, length in chars)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchMatch {
+    pub row: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+/// Search state for `/` mode. While `typing`, keystrokes edit the query;
+/// afterwards n/N step through the matches until the search is cleared.
+#[derive(Debug, Default)]
+pub struct SearchState {
+    pub query: String,
+    pub matches: Vec<SearchMatch>,
+    pub current: Option<usize>,
+    pub typing: bool,
+}
+
+impl SearchState {
+    pub fn is_active(&self) -> bool {
+        self.typing || !self.query.is_empty()
+    }
+
+    /// Recompute matches and select the first one at or after (row, col)
+    pub fn update(&mut self, buffer: &[Vec<char>], from: (usize, usize)) {
+        self.matches = find_matches(buffer, &self.query);
+        self.current = self.matches
+            .iter()
+            .position(|m| (m.row, m.col) >= from)
+            .or(if self.matches.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn next_match(&mut self) -> Option<SearchMatch> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let next = self.current.map(|i| (i + 1) % self.matches.len()).unwrap_or(0);
+        self.current = Some(next);
+        Some(self.matches[next])
+    }
+
+    pub fn prev_match(&mut self) -> Option<SearchMatch> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let len = self.matches.len();
+        let prev = self.current.map(|i| (i + len - 1) % len).unwrap_or(len - 1);
+        self.current = Some(prev);
+        Some(self.matches[prev])
+    }
+
+    pub fn current_match(&self) -> Option<SearchMatch> {
+        self.current.and_then(|i| self.matches.get(i).copied())
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Status line text, e.g. "/invoice [2/7]"
+    pub fn prompt(&self) -> String {
+        let position = match (self.current, self.matches.len()) {
+            (_, 0) if !self.query.is_empty() => " [no matches]".to_string(),
+            (Some(i), total) => format!(" [{}/{}]", i + 1, total),
+            _ => String::new(),
+        };
+        format!("/{}{}", self.query, position)
+    }
+}
+
+/// Find every occurrence of `query` in the grid, row by row. Smart case: an
+/// all-lowercase query matches case-insensitively.
+pub fn find_matches(buffer: &[Vec<char>], query: &str) -> Vec<SearchMatch> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let ignore_case = !query.chars().any(|c| c.is_uppercase());
+    let normalize = |c: char| if ignore_case { c.to_lowercase().next().unwrap_or(c) } else { c };
+    let needle: Vec<char> = query.chars().map(normalize).collect();
+
+    let mut matches = Vec::new();
+    for (row_idx, row) in buffer.iter().enumerate() {
+        if row.len() < needle.len() {
+            continue;
+        }
+        let hay: Vec<char> = row.iter().map(|&c| normalize(c)).collect();
+        let mut col = 0;
+        while col + needle.len() <= hay.len() {
+            if hay[col..col + needle.len()] == needle[..] {
+                matches.push(SearchMatch { row: row_idx, col, len: needle.len() });
+                col += needle.len();
+            } else {
+                col += 1;
+            }
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(lines: &[&str]) -> Vec<Vec<char>> {
+        lines.iter().map(|l| l.chars().collect()).collect()
+    }
+
+    #[test]
+    fn test_smart_case_and_wraparound() {
+        let buffer = grid(&["Total due", "total: 42", "TOTAL"]);
+        assert_eq!(find_matches(&buffer, "total").len(), 3);
+        assert_eq!(find_matches(&buffer, "Total").len(), 1);
+
+        let mut search = SearchState { query: "total".to_string(), ..Default::default() };
+        search.update(&buffer, (1, 3));
+        assert_eq!(search.current_match(), Some(SearchMatch { row: 2, col: 0, len: 5 }));
+        assert_eq!(search.next_match().map(|m| m.row), Some(0));
+        assert_eq!(search.prev_match().map(|m| m.row), Some(2));
+    }
+}